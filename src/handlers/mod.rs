@@ -1,55 +1,195 @@
-use crate::models::auth::{DashboardData, LoginRequest, PasswordChangeRequest};
-use crate::models::config::AppConfig;
-use crate::services::auth::AuthService;
-use crate::services::monitor::MonitorService;
+use crate::models::auth::{
+    DashboardData, HostGroup, LoginRequest, NotificationPreferences, PasswordChangeRequest, USER_CHANNELS,
+};
+use crate::models::availability::{AvailabilityReport, ReportPeriod};
+use crate::models::backup::BaselineExport;
+use crate::models::config::{AppConfig, DisplayConfig};
+use crate::models::dashboard::{DashboardLayout, Panel, PanelKind, PanelSize, CHART_METRICS};
+use crate::models::incidents::{Incident, IncidentStatus};
+use crate::models::metrics::{AnomalySeverity, HistoryRange, SystemMetrics, ThreatIntel};
+use crate::models::push::PushSubscription;
+use crate::services::auth::{self, AuthService};
+use crate::services::baseline_history;
+use crate::services::backup::{self, create_backup, export_baselines, import_baselines, restore_backup, RESTORE_BODY_LIMIT};
+use crate::services::config::ConfigService;
+use crate::services::diagnostics;
+use crate::services::incidents::IncidentLog;
+use crate::services::dns;
+use crate::services::ingest;
+use crate::services::log_viewer::{self, LogFilter};
+use crate::services::log_watch::LogTail;
+use crate::services::kernel_log;
+use crate::services::monitor::{anomaly_severity, anomaly_subject, format_duration, MonitorService, DEFAULT_PUSH_CONTACT};
+use crate::services::ports;
+use crate::services::public_ip;
+use crate::services::suid::{self, SuidStatus};
+use crate::services::status_snapshot::{PublishedStatus, LAN_DEVICES_GROUP};
+use crate::services::storage;
+use crate::services::summary_report::SummaryReportService;
+use crate::services::supervisor::Supervisor;
+use crate::services::support_bundle;
 use crate::services::threat_intel::ThreatIntelService;
+use crate::utils::assets;
+use crate::utils::graphql;
+use crate::utils::i18n::Language;
+use crate::utils::websocket;
+use crate::utils::zip;
 use anyhow::Result;
 use askama::Template;
 use axum::{
-    body::Body,
-    extract::{Path, Query, State},
-    http::{header, StatusCode},
+    body::{Body, Bytes},
+    extract::{DefaultBodyLimit, Path, Query, RawForm, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    response::sse::{Event as SseEvent, KeepAlive, Sse},
     response::{Html, IntoResponse, Redirect, Response},
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Form, Json, Router,
 };
 use serde::Deserialize;
 use serde_json::json;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::fs;
-use tokio::sync::RwLock;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::{broadcast, RwLock};
+use tokio_util::io::ReaderStream;
 use tower_cookies::{Cookie, CookieManagerLayer, Cookies};
-use tower_http::{compression::CompressionLayer, services::ServeDir, trace::TraceLayer};
+use tower_http::{compression::CompressionLayer, trace::TraceLayer};
+
+// Everything under here can be browsed and downloaded from the downloads page
+const DOWNLOADS_DIR: &str = "logs";
+const DOWNLOAD_EXTENSIONS: &[&str] = &["log", "txt", "json", "jsonl", "html", "zip"];
+// The subset of those shown line by line in the log viewer
+const LOG_EXTENSIONS: &[&str] = &["log", "txt", "jsonl"];
+const LOG_FOLLOW_INTERVAL_SECS: u64 = 1;
+// Most recent incidents shown with their timelines on the reports page
+const REPORT_INCIDENTS: usize = 20;
+// How long the runtime diagnostics wait for the monitor before reporting it locked
+const DEBUG_LOCK_TIMEOUT_SECS: u64 = 2;
+// Archived samples per page of a from/to query, by default and at most
+const ARCHIVE_PAGE: usize = 1000;
+const MAX_ARCHIVE_PAGE: usize = 10_000;
+// Text the dashboard's script adds to the page, translated for it
+const DASHBOARD_STRINGS: &[&str] = &[
+    "Anomalies Detected!",
+    "All Normal",
+    "Learning...",
+    "CPU %",
+    "RAM %",
+    "Disk %",
+    "Temp °C",
+    "Ping ms",
+    "Connections",
+    "Failed Logins",
+    "GPU %",
+    "VRAM %",
+];
 
 // Templates
 #[derive(Template)]
 #[template(path = "login.html")]
-struct LoginTemplate;
+struct LoginTemplate {
+    lang: Language,
+}
 
 #[derive(Template)]
 #[template(path = "dashboard.html")]
 struct DashboardTemplate {
+    lang: Language,
     status: Vec<String>,
     anomalies: Vec<String>,
     has_anomaly: bool,
     graphs: String,
+    refresh_rate: u64,
+    panels: Vec<PanelView>,
+    /// The panels as JSON, for updating them in place.
+    layout: String,
+    /// Translations of the text the page's script shows, as JSON.
+    strings: String,
+}
+
+struct PanelView {
+    panel: Panel,
+    /// Host groups shown on a hosts panel.
+    host_groups: Vec<HostGroup>,
 }
 
 #[derive(Template)]
 #[template(path = "downloads.html")]
 struct DownloadsTemplate {
+    lang: Language,
+    /// The directory shown, relative to the downloads directory.
+    dir: String,
+    parent: Option<String>,
     files: Vec<DownloadFileInfo>,
 }
 
+#[derive(Template)]
+#[template(path = "logs.html")]
+struct LogsTemplate {
+    lang: Language,
+    files: Vec<String>,
+}
+
 #[derive(Template)]
 #[template(path = "settings.html")]
 struct SettingsTemplate {
+    lang: Language,
+    // (code, name, chosen by the user)
+    languages: Vec<(&'static str, &'static str, bool)>,
+    /// The server's language, used when the user has not chosen one.
+    default_language: Language,
     toggles: HashMap<String, bool>,
+    refresh_rate: u64,
+    // (channel, chosen) for the user's own notifications
+    notify_channels: Vec<(&'static str, bool)>,
+    // (severity, the least severe chosen)
+    notify_severities: Vec<(String, bool)>,
+    // (host group, chosen)
+    notify_groups: Vec<(String, bool)>,
+}
+
+#[derive(Template)]
+#[template(path = "reports.html")]
+struct ReportsTemplate {
+    lang: Language,
+    // (query value, label, selected)
+    periods: Vec<(&'static str, &'static str, bool)>,
+    rows: Vec<ReportRow>,
+    suid_scanned_at: String,
+    // (path, mode, status, unexpected)
+    suid: Vec<(String, String, String, bool)>,
+    incidents: Vec<IncidentRow>,
+}
+
+struct IncidentRow {
+    id: u64,
+    status: String,
+    severity: String,
+    title: String,
+    hint: String,
+    opened: String,
+    duration: String,
+    assignee: String,
+    open: bool,
+    // (time, kind, message, author)
+    timeline: Vec<(String, String, String, String)>,
+}
+
+struct ReportRow {
+    check: String,
+    uptime: String,
+    downtime: String,
+    mttr: String,
+    // (down, recovered, duration)
+    outages: Vec<(String, String, String)>,
 }
 
 struct DownloadFileInfo {
     name: String,
+    path: String,
+    is_dir: bool,
     size: u64,
     modified: String,
 }
@@ -58,49 +198,141 @@ struct DownloadFileInfo {
 #[derive(Clone)]
 pub struct AppState {
     pub monitor: Arc<RwLock<MonitorService>>,
+    /// The monitor's state as of its latest cycle, for pages and endpoints polled often.
+    pub status: Arc<PublishedStatus>,
     pub threat_intel: Arc<RwLock<ThreatIntelService>>,
     pub auth: Arc<AuthService>,
-    pub config: AppConfig,
+    pub config: Arc<ConfigService>,
+    pub reports: Arc<SummaryReportService>,
+    pub supervisor: Arc<Supervisor>,
+    /// Each sample as the monitoring loop collects it, for `/ws/metrics`.
+    pub live_metrics: broadcast::Sender<Arc<SystemMetrics>>,
 }
 
-pub fn create_app(
-    monitor: Arc<RwLock<MonitorService>>,
-    threat_intel: Arc<RwLock<ThreatIntelService>>,
-    config: AppConfig,
-) -> Router {
-    let auth = Arc::new(AuthService::new());
-    
-    let state = AppState {
-        monitor,
-        threat_intel,
-        auth,
-        config,
-    };
-    
+pub fn create_app(state: AppState) -> Router {
     Router::new()
         .route("/", get(root))
         .route("/login", get(login_page).post(login_handler))
         .route("/logout", get(logout_handler))
         .route("/dashboard", get(dashboard_page))
+        .route("/reports", get(reports_page))
         .route("/downloads", get(downloads_page))
-        .route("/download/:filename", get(download_file))
+        .route("/downloads/zip", post(download_zip))
+        .route("/download/*path", get(download_file))
+        .route("/logs", get(logs_page))
         .route("/settings", get(settings_page).post(settings_handler))
+        .route("/settings/display", post(settings_display_handler))
+        .route("/settings/language", post(settings_language_handler))
+        .route("/settings/notifications", post(settings_notifications_handler))
+        .route("/healthz", get(healthz))
+        .route("/api/debug/runtime", get(api_debug_runtime))
+        // Served from the root so the service worker controls every page
+        .route("/sw.js", get(|| static_asset("sw.js")))
+        .route("/manifest.webmanifest", get(|| static_asset("manifest.webmanifest")))
+        .route("/icon.svg", get(|| static_asset("icon.svg")))
         .route("/api/status", get(api_status))
+        .route(
+            "/api/dashboard/layout",
+            get(api_dashboard_layout).put(api_set_dashboard_layout).delete(api_reset_dashboard_layout),
+        )
         .route("/api/metrics", get(api_metrics))
-        .nest_service("/static", ServeDir::new("static"))
+        .route("/ws/metrics", get(ws_metrics))
+        .route("/graphql", get(graphql_get).post(graphql_post))
+        .route("/api/availability", get(api_availability))
+        .route("/api/reports/summary", post(api_summary_report))
+        .route("/api/support-bundle", post(api_support_bundle))
+        .route("/api/support-bundle/:file", get(api_support_bundle_download))
+        .route("/api/push/key", get(api_push_key))
+        .route("/api/push/subscribe", post(api_push_subscribe))
+        .route("/api/push/unsubscribe", post(api_push_unsubscribe))
+        .route("/api/push/test", post(api_push_test))
+        .route("/api/logs", get(api_logs))
+        .route("/api/logs/:name", get(api_log))
+        .route("/api/logs/:name/follow", get(api_log_follow))
+        .route("/api/hosts/:host/traceroute", post(api_traceroute))
+        .route("/api/public-ip", get(api_public_ip))
+        .route("/api/dns", get(api_dns))
+        .route("/api/oom-kills", get(api_oom_kills))
+        .route("/api/kubernetes", get(api_kubernetes))
+        .route("/api/proxmox", get(api_proxmox))
+        .route("/api/lan/usage", get(api_lan_usage))
+        .route("/api/pihole", get(api_pihole))
+        .route("/api/backups", get(api_backups))
+        .route("/api/collectors", get(api_collectors))
+        .route("/api/heartbeats", get(api_heartbeats))
+        .route("/api/heartbeat/:token", get(api_heartbeat).post(api_heartbeat))
+        .route("/api/heartbeat/:token/fail", get(api_heartbeat_fail).post(api_heartbeat_fail))
+        .route("/api/ingest/webhook", post(api_ingest_webhook))
+        .route("/api/incidents", get(api_incidents))
+        .route("/api/incidents/:id", get(api_incident))
+        .route("/api/incidents/:id/ack", post(api_incident_ack))
+        .route("/api/incidents/:id/resolve", post(api_incident_resolve))
+        .route("/api/incidents/:id/assignee", put(api_incident_assign))
+        .route("/api/incidents/:id/notes", post(api_incident_note))
+        .route("/api/ssh-keys", get(api_ssh_keys))
+        .route("/api/security/ports", get(api_ports))
+        .route("/api/security/suid", get(api_suid))
+        .route("/api/security/suid/accept", post(api_suid_accept))
+        .route("/api/config", get(api_get_config).put(api_update_config))
+        .route("/api/backup", get(api_backup))
+        .route("/api/restore", post(api_restore).layer(DefaultBodyLimit::max(RESTORE_BODY_LIMIT)))
+        .route("/api/baselines", get(api_baselines))
+        .route("/api/feedback", get(api_feedback).post(api_mark_normal))
+        .route("/api/feedback/:id", delete(api_feedback_delete))
+        .route("/api/baselines/history", get(api_baseline_history))
+        .route("/api/baselines/diff", get(api_baseline_diff))
+        .route("/api/baselines/export", get(api_baselines_export))
+        .route("/api/baselines/import", post(api_baselines_import))
+        .route("/api/baselines/:metric/reset", post(api_baseline_reset))
+        .route("/api/baselines/:metric/freeze", post(api_baseline_freeze))
+        .route("/api/baselines/:metric/unfreeze", post(api_baseline_unfreeze))
+        .route("/static/*path", get(static_file))
         .layer(TraceLayer::new_for_http())
         .layer(CompressionLayer::new())
         .layer(CookieManagerLayer::new())
         .with_state(state)
 }
 
+/// Session token from the `session` cookie or an `Authorization: Bearer` header.
+fn session_token(cookies: &Cookies, headers: &HeaderMap) -> Option<String> {
+    cookies.get("session").map(|c| c.value().to_string()).or_else(|| {
+        headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|token| token.to_string())
+    })
+}
+
+/// Resolves the signed-in user for API routes, or the error response to send instead.
+async fn require_session(state: &AppState, cookies: &Cookies, headers: &HeaderMap) -> Result<String, Response> {
+    let username = match session_token(cookies, headers) {
+        Some(token) => state.auth.verify_token(&token).await.ok(),
+        None => None,
+    };
+    username.ok_or_else(|| (StatusCode::UNAUTHORIZED, Json(json!({ "error": "Authentication required" }))).into_response())
+}
+
+/// Resolves the calling administrator for API routes, or the error response to send instead.
+async fn require_admin(state: &AppState, cookies: &Cookies, headers: &HeaderMap) -> Result<String, Response> {
+    let username = require_session(state, cookies, headers).await?;
+    
+    if state.auth.is_admin(&username).await {
+        Ok(username)
+    } else {
+        Err((StatusCode::FORBIDDEN, Json(json!({ "error": "Administrator privileges required" }))).into_response())
+    }
+}
+
 // Routes
 async fn root() -> impl IntoResponse {
     Redirect::to("/login")
 }
 
-async fn login_page() -> impl IntoResponse {
-    let template = LoginTemplate;
+async fn login_page(State(state): State<AppState>) -> impl IntoResponse {
+    let template = LoginTemplate {
+        lang: state.config.current().await.display.language,
+    };
     Html(template.render().unwrap_or_else(|_| "Template error".to_string()))
 }
 
@@ -146,89 +378,257 @@ async fn dashboard_page(
     cookies: Cookies,
 ) -> impl IntoResponse {
     // Verify session
-    if let Some(token) = cookies.get("session") {
-        if state.auth.verify_token(token.value()).await.is_err() {
-            return Redirect::to("/login").into_response();
+    let username = if let Some(token) = cookies.get("session") {
+        match state.auth.verify_token(token.value()).await {
+            Ok(user) => user,
+            Err(_) => return Redirect::to("/login").into_response(),
         }
     } else {
         return Redirect::to("/login").into_response();
-    }
+    };
     
-    let monitor = state.monitor.read().await;
-    let (anomalies, has_anomaly) = monitor.detect_anomalies();
-    let status = monitor.status_report();
-    let history = monitor.get_metrics_history();
-    
-    // Build graph data
-    let graphs = json!({
-        "cpu": history.iter().map(|m| m.cpu_percent).collect::<Vec<_>>(),
-        "ram": history.iter().map(|m| m.ram_percent).collect::<Vec<_>>(),
-        "disk": history.iter().map(|m| m.disk_percent).collect::<Vec<_>>(),
-        "temp": history.iter().map(|m| m.temperature).collect::<Vec<_>>(),
-        "ping": history.iter().map(|m| m.ping_ms).collect::<Vec<_>>(),
-        "net": history.iter().map(|m| m.net_connections).collect::<Vec<_>>(),
-        "fail": history.iter().map(|m| m.failed_logins).collect::<Vec<_>>(),
-    });
+    let display = state.config.current().await.display;
+    let layout = dashboard_layout(&state, &username, &display).await;
     
-    drop(monitor);
+    let snapshot = state.status.load();
     
+    let panels = layout
+        .panels
+        .iter()
+        .map(|panel| PanelView {
+            host_groups: snapshot.hosts.iter().filter(|group| panel.shows_group(&group.name)).cloned().collect(),
+            panel: panel.clone(),
+        })
+        .collect();
+    
+    let lang = page_language(&state, &username).await;
     let template = DashboardTemplate {
-        status,
-        anomalies,
-        has_anomaly,
-        graphs: graphs.to_string(),
+        lang,
+        status: snapshot.status.clone(),
+        anomalies: snapshot.anomalies.clone(),
+        has_anomaly: snapshot.has_anomaly,
+        graphs: snapshot.graphs.to_string(),
+        refresh_rate: display.refresh_rate,
+        panels,
+        layout: serde_json::to_string(&layout.panels).unwrap_or_else(|_| "[]".to_string()),
+        strings: lang.strings(DASHBOARD_STRINGS),
+    };
+    
+    Html(template.render().unwrap_or_else(|_| "Template error".to_string())).into_response()
+}
+
+/// The layout `username` arranged, or the default built from the display settings.
+async fn dashboard_layout(state: &AppState, username: &str, display: &DisplayConfig) -> DashboardLayout {
+    match state.auth.dashboard_layout(username).await {
+        Some(layout) => layout,
+        None => DashboardLayout::default_for(display),
+    }
+}
+
+#[derive(Deserialize)]
+struct AvailabilityQuery {
+    #[serde(default)]
+    period: ReportPeriod,
+    check: Option<String>,
+}
+
+async fn availability_reports(state: &AppState, params: &AvailabilityQuery) -> Vec<AvailabilityReport> {
+    let monitor = state.monitor.read().await;
+    let availability = monitor.availability();
+    let checks: Vec<String> = monitor
+        .check_names()
+        .into_iter()
+        .filter(|name| params.check.as_ref().map_or(true, |check| check == name))
+        .collect();
+    drop(monitor);
+    
+    availability.report(&checks, params.period).await
+}
+
+async fn reports_page(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    Query(params): Query<AvailabilityQuery>,
+) -> impl IntoResponse {
+    // Verify session
+    let username = if let Some(token) = cookies.get("session") {
+        match state.auth.verify_token(token.value()).await {
+            Ok(user) => user,
+            Err(_) => return Redirect::to("/login").into_response(),
+        }
+    } else {
+        return Redirect::to("/login").into_response();
     };
     
+    let time = |t: chrono::DateTime<chrono::Utc>| t.format("%Y-%m-%d %H:%M").to_string();
+    let rows = availability_reports(&state, &params)
+        .await
+        .into_iter()
+        .map(|report| ReportRow {
+            check: report.check,
+            uptime: report.uptime_percent.map(|p| format!("{:.3}%", p)).unwrap_or_else(|| "no data".to_string()),
+            downtime: format_duration(report.downtime_secs),
+            mttr: report.mttr_secs.map(format_duration).unwrap_or_else(|| "-".to_string()),
+            outages: report
+                .outages
+                .iter()
+                .map(|o| {
+                    let end = o.end.map(time).unwrap_or_else(|| "ongoing".to_string());
+                    (time(o.start), end, format_duration(o.duration_secs))
+                })
+                .collect(),
+        })
+        .collect();
+    
+    let periods = [
+        ("day", "Day", ReportPeriod::Day),
+        ("week", "Week", ReportPeriod::Week),
+        ("month", "Month", ReportPeriod::Month),
+    ]
+    .into_iter()
+    .map(|(value, label, period)| (value, label, period == params.period))
+    .collect();
+    
+    let suid_report = suid::report().await;
+    let suid_scanned_at = suid_report.scanned_at.map(time).unwrap_or_else(|| "never".to_string());
+    let suid = suid_report
+        .binaries
+        .into_iter()
+        .map(|binary| {
+            let status = match binary.status {
+                SuidStatus::New => "new",
+                SuidStatus::Modified => "modified",
+                SuidStatus::Removed => "removed",
+                SuidStatus::Baseline => "baseline",
+            };
+            let unexpected = matches!(binary.status, SuidStatus::New | SuidStatus::Modified);
+            (binary.path, binary.mode, status.to_string(), unexpected)
+        })
+        .collect();
+    
+    let incidents = state
+        .monitor
+        .read()
+        .await
+        .incidents()
+        .incidents()
+        .iter()
+        .rev()
+        .take(REPORT_INCIDENTS)
+        .map(|incident| {
+            let end = incident.resolved_at.unwrap_or_else(chrono::Utc::now);
+            IncidentRow {
+                id: incident.id,
+                status: incident.status().to_string(),
+                severity: incident.severity.to_string(),
+                title: incident.title.clone(),
+                hint: incident.hint.clone().unwrap_or_default(),
+                opened: time(incident.opened_at),
+                duration: format_duration((end - incident.opened_at).num_seconds()),
+                assignee: incident.assignee.clone().unwrap_or_else(|| "-".to_string()),
+                open: incident.is_open(),
+                timeline: incident
+                    .timeline
+                    .iter()
+                    .map(|entry| {
+                        (
+                            entry.at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                            entry.kind.to_string(),
+                            entry.message.clone(),
+                            entry.author.clone().unwrap_or_default(),
+                        )
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+    
+    let template = ReportsTemplate {
+        lang: page_language(&state, &username).await,
+        periods,
+        rows,
+        suid_scanned_at,
+        suid,
+        incidents,
+    };
     Html(template.render().unwrap_or_else(|_| "Template error".to_string())).into_response()
 }
 
+#[derive(Deserialize)]
+struct DownloadsQuery {
+    /// Subdirectory to list, relative to the downloads directory.
+    #[serde(default)]
+    dir: String,
+}
+
 async fn downloads_page(
     State(state): State<AppState>,
     cookies: Cookies,
+    Query(params): Query<DownloadsQuery>,
 ) -> impl IntoResponse {
     // Verify session
-    if let Some(token) = cookies.get("session") {
-        if state.auth.verify_token(token.value()).await.is_err() {
-            return Redirect::to("/login").into_response();
+    let username = if let Some(token) = cookies.get("session") {
+        match state.auth.verify_token(token.value()).await {
+            Ok(user) => user,
+            Err(_) => return Redirect::to("/login").into_response(),
         }
     } else {
         return Redirect::to("/login").into_response();
-    }
+    };
+    
+    let Some((root, dir)) = download_path(&params.dir).await.filter(|(_, dir)| dir.is_dir()) else {
+        return (StatusCode::NOT_FOUND, "Directory not found").into_response();
+    };
     
     let mut files = Vec::new();
     
-    if let Ok(mut entries) = fs::read_dir("logs").await {
+    if let Ok(mut entries) = fs::read_dir(&dir).await {
         while let Ok(Some(entry)) = entries.next_entry().await {
+            // Links are not followed, so they cannot lead out of the downloads directory
+            let Ok(file_type) = entry.file_type().await else {
+                continue;
+            };
+            let name = entry.file_name().to_string_lossy().to_string();
+            if file_type.is_symlink() || !(file_type.is_dir() || downloadable(&name)) {
+                continue;
+            }
             if let Ok(metadata) = entry.metadata().await {
-                if metadata.is_file() {
-                    let name = entry.file_name().to_string_lossy().to_string();
-                    if name.ends_with(".log") || name.ends_with(".txt") || name.ends_with(".json") {
-                        let modified = metadata.modified()
-                            .ok()
-                            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                            .map(|d| d.as_secs() as i64)
-                            .unwrap_or(0);
-                        
-                        files.push(DownloadFileInfo {
-                            name,
-                            size: metadata.len(),
-                            modified: chrono::DateTime::from_timestamp(modified, 0)
-                                .map(|d| d.format("%Y-%m-%d %H:%M").to_string())
-                                .unwrap_or_default(),
-                        });
-                    }
-                }
+                let modified = metadata.modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs() as i64)
+                    .unwrap_or(0);
+                
+                files.push(DownloadFileInfo {
+                    path: relative_path(&root, &entry.path()),
+                    name,
+                    is_dir: file_type.is_dir(),
+                    size: metadata.len(),
+                    modified: chrono::DateTime::from_timestamp(modified, 0)
+                        .map(|d| d.format("%Y-%m-%d %H:%M").to_string())
+                        .unwrap_or_default(),
+                });
             }
         }
     }
+    // Directories first, newest dated directory at the top
+    files.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| if a.is_dir { b.name.cmp(&a.name) } else { a.name.cmp(&b.name) }));
     
-    let template = DownloadsTemplate { files };
+    let dir = relative_path(&root, &dir);
+    let parent = (!dir.is_empty()).then(|| dir.rsplit_once('/').map(|(parent, _)| parent.to_string()).unwrap_or_default());
+    let template = DownloadsTemplate {
+        lang: page_language(&state, &username).await,
+        dir,
+        parent,
+        files,
+    };
     Html(template.render().unwrap_or_else(|_| "Template error".to_string())).into_response()
 }
 
 async fn download_file(
     State(state): State<AppState>,
     cookies: Cookies,
+    headers: HeaderMap,
     Path(filename): Path<String>,
 ) -> impl IntoResponse {
     // Verify session
@@ -240,37 +640,117 @@ async fn download_file(
         return Redirect::to("/login").into_response();
     }
     
-    // Security: Only allow safe filenames
-    let safe_filename = std::path::Path::new(&filename)
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("")
-        .to_string();
-    
-    if !safe_filename.ends_with(".log") 
-        && !safe_filename.ends_with(".txt") 
-        && !safe_filename.ends_with(".json") 
-    {
+    // Security: Only allow safe file types
+    if !downloadable(&filename) {
         return (StatusCode::FORBIDDEN, "Invalid file type").into_response();
     }
     
-    let file_path = format!("logs/{}", safe_filename);
+    let Some((_, file_path)) = download_path(&filename).await else {
+        return (StatusCode::NOT_FOUND, "File not found").into_response();
+    };
+    send_file(&file_path, &headers).await
+}
+
+/// Sends a file as an attachment, honouring a `Range` header.
+async fn send_file(file_path: &std::path::Path, headers: &HeaderMap) -> Response {
+    let safe_filename = file_path.file_name().unwrap_or_default().to_string_lossy().to_string();
     
-    if let Ok(content) = fs::read(&file_path).await {
-        Response::builder()
-            .header(header::CONTENT_TYPE, "application/octet-stream")
-            .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", safe_filename))
-            .body(Body::from(content))
-            .unwrap()
-            .into_response()
-    } else {
-        (StatusCode::NOT_FOUND, "File not found").into_response()
+    // Streamed rather than read whole, since logs can run to gigabytes
+    let Ok(mut file) = fs::File::open(&file_path).await else {
+        return (StatusCode::NOT_FOUND, "File not found").into_response();
+    };
+    let Ok(len) = file.metadata().await.map(|metadata| metadata.len()) else {
+        return (StatusCode::NOT_FOUND, "File not found").into_response();
+    };
+    
+    let range = headers.get(header::RANGE).and_then(|range| range.to_str().ok()).map(|range| byte_range(range, len));
+    let (status, start, end) = match range {
+        Some(Err(())) => {
+            return Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+                .body(Body::empty())
+                .unwrap()
+                .into_response();
+        }
+        Some(Ok(Some((start, end)))) => (StatusCode::PARTIAL_CONTENT, start, end),
+        _ => (StatusCode::OK, 0, len.saturating_sub(1)),
+    };
+    let length = if len == 0 { 0 } else { end - start + 1 };
+    if start > 0 && file.seek(std::io::SeekFrom::Start(start)).await.is_err() {
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to read file").into_response();
+    }
+    
+    let mut response = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, content_type(&safe_filename))
+        .header(header::CONTENT_LENGTH, length)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", safe_filename));
+    if status == StatusCode::PARTIAL_CONTENT {
+        response = response.header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, len));
     }
+    response
+        .body(Body::from_stream(ReaderStream::new(file.take(length))))
+        .unwrap()
 }
 
-async fn settings_page(
+/// Parses a `Range` header for a file of `len` bytes into the first and last byte
+/// to send. Headers this does not handle, such as several ranges, give `Ok(None)` so
+/// the whole file is sent; `Err` means the range lies outside the file.
+fn byte_range(range: &str, len: u64) -> Result<Option<(u64, u64)>, ()> {
+    let Some((first, last)) = range.trim().strip_prefix("bytes=").filter(|r| !r.contains(',')).and_then(|r| r.split_once('-')) else {
+        return Ok(None);
+    };
+    let (first, last) = (first.trim(), last.trim());
+    
+    if first.is_empty() {
+        // A suffix such as `bytes=-500` asks for the last 500 bytes
+        return match last.parse::<u64>() {
+            Ok(0) => Err(()),
+            Ok(_) if len == 0 => Err(()),
+            Ok(suffix) => Ok(Some((len.saturating_sub(suffix), len - 1))),
+            Err(_) => Ok(None),
+        };
+    }
+    let Ok(first) = first.parse::<u64>() else {
+        return Ok(None);
+    };
+    let last = match last {
+        "" => len.saturating_sub(1),
+        last => match last.parse::<u64>() {
+            Ok(last) => last.min(len.saturating_sub(1)),
+            Err(_) => return Ok(None),
+        },
+    };
+    if first >= len || last < first {
+        return Err(());
+    }
+    Ok(Some((first, last)))
+}
+
+fn content_type(name: &str) -> &'static str {
+    match std::path::Path::new(name).extension().and_then(|e| e.to_str()) {
+        Some("log" | "txt") => "text/plain; charset=utf-8",
+        Some("json") => "application/json",
+        Some("jsonl") => "application/x-ndjson",
+        Some("html") => "text/html; charset=utf-8",
+        Some("zip") => "application/zip",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "application/javascript",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("webmanifest") => "application/manifest+json",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Streams the files and directories ticked on the downloads page as one zip
+/// archive; directories are included with everything downloadable beneath them.
+async fn download_zip(
     State(state): State<AppState>,
     cookies: Cookies,
+    RawForm(form): RawForm,
 ) -> impl IntoResponse {
     // Verify session
     if let Some(token) = cookies.get("session") {
@@ -281,9 +761,163 @@ async fn settings_page(
         return Redirect::to("/login").into_response();
     }
     
-    let toggles = state.config.display.stat_visibility.clone();
+    let mut selected = BTreeMap::new();
+    for (key, value) in form_urlencoded::parse(&form) {
+        if key != "file" {
+            continue;
+        }
+        let Some((root, path)) = download_path(&value).await else {
+            return (StatusCode::NOT_FOUND, format!("{} not found", value)).into_response();
+        };
+        for file in downloadable_files(path).await {
+            selected.insert(relative_path(&root, &file), file);
+        }
+    }
+    if selected.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Select at least one file").into_response();
+    }
+    
+    let filename = format!("downloads-{}.zip", chrono::Utc::now().format("%Y%m%d-%H%M%S"));
+    let archive = zip::stream(selected.into_iter().collect());
+    Response::builder()
+        .header(header::CONTENT_TYPE, "application/zip")
+        .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename))
+        .body(Body::from_stream(ReaderStream::new(archive)))
+        .unwrap()
+        .into_response()
+}
+
+fn downloadable(name: &str) -> bool {
+    let extension = std::path::Path::new(name).extension().and_then(|e| e.to_str()).unwrap_or("");
+    DOWNLOAD_EXTENSIONS.contains(&extension)
+}
+
+/// Resolves a path relative to the downloads directory, along with that directory,
+/// refusing anything that would lead outside it, including through links.
+async fn download_path(relative: &str) -> Option<(PathBuf, PathBuf)> {
+    let relative = std::path::Path::new(relative);
+    if !relative.components().all(|c| matches!(c, std::path::Component::Normal(_))) {
+        return None;
+    }
+    let root = fs::canonicalize(DOWNLOADS_DIR).await.ok()?;
+    let path = fs::canonicalize(root.join(relative)).await.ok()?;
+    path.starts_with(&root).then_some((root, path))
+}
+
+fn relative_path(root: &std::path::Path, path: &std::path::Path) -> String {
+    path.strip_prefix(root).unwrap_or(path).to_string_lossy().to_string()
+}
+
+/// `path` itself when it is a downloadable file, or every downloadable file beneath
+/// it when it is a directory. Links are skipped.
+async fn downloadable_files(path: PathBuf) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![path];
+    
+    while let Some(path) = pending.pop() {
+        let Ok(metadata) = fs::symlink_metadata(&path).await else {
+            continue;
+        };
+        if metadata.is_file() && downloadable(&path.to_string_lossy()) {
+            files.push(path);
+        } else if metadata.is_dir() {
+            if let Ok(mut entries) = fs::read_dir(&path).await {
+                while let Ok(Some(entry)) = entries.next_entry().await {
+                    pending.push(entry.path());
+                }
+            }
+        }
+    }
+    files
+}
+
+async fn logs_page(
+    State(state): State<AppState>,
+    cookies: Cookies,
+) -> impl IntoResponse {
+    // Verify session
+    let username = if let Some(token) = cookies.get("session") {
+        match state.auth.verify_token(token.value()).await {
+            Ok(user) => user,
+            Err(_) => return Redirect::to("/login").into_response(),
+        }
+    } else {
+        return Redirect::to("/login").into_response();
+    };
+    
+    let template = LogsTemplate {
+        lang: page_language(&state, &username).await,
+        files: log_files().await,
+    };
+    Html(template.render().unwrap_or_else(|_| "Template error".to_string())).into_response()
+}
+
+/// Log files under the downloads directory, by path relative to it.
+async fn log_files() -> Vec<String> {
+    let Some((root, _)) = download_path("").await else {
+        return Vec::new();
+    };
+    let mut files: Vec<String> = downloadable_files(root.clone())
+        .await
+        .iter()
+        .filter(|path| viewable_log(&path.to_string_lossy()))
+        .map(|path| relative_path(&root, path))
+        .collect();
+    files.sort();
+    files
+}
+
+fn viewable_log(name: &str) -> bool {
+    let extension = std::path::Path::new(name).extension().and_then(|e| e.to_str()).unwrap_or("");
+    LOG_EXTENSIONS.contains(&extension)
+}
+
+async fn settings_page(
+    State(state): State<AppState>,
+    cookies: Cookies,
+) -> impl IntoResponse {
+    // Verify session
+    let username = if let Some(token) = cookies.get("session") {
+        match state.auth.verify_token(token.value()).await {
+            Ok(user) => user,
+            Err(_) => return Redirect::to("/login").into_response(),
+        }
+    } else {
+        return Redirect::to("/login").into_response();
+    };
+    
+    let config = state.config.current().await;
+    let display = config.display;
+    let chosen = state.auth.language(&username).await;
+    let notifications = state.auth.notification_preferences(&username).await;
+    let mut groups: BTreeSet<String> = config.monitoring.all_checks().into_iter().flat_map(|check| check.tags).collect();
+    groups.extend(notifications.groups.iter().cloned());
     
-    let template = SettingsTemplate { toggles };
+    let template = SettingsTemplate {
+        lang: chosen.unwrap_or(display.language),
+        languages: Language::ALL
+            .iter()
+            .map(|language| (language.code(), language.name(), chosen == Some(*language)))
+            .collect(),
+        default_language: display.language,
+        toggles: display.stat_visibility,
+        refresh_rate: display.refresh_rate,
+        notify_channels: USER_CHANNELS
+            .iter()
+            .map(|channel| (*channel, notifications.channels.iter().any(|chosen| chosen == channel)))
+            .collect(),
+        notify_severities: [AnomalySeverity::Critical, AnomalySeverity::Warning, AnomalySeverity::Info]
+            .iter()
+            .map(|severity| (severity.to_string(), *severity == notifications.min_severity))
+            .collect(),
+        notify_groups: groups
+            .into_iter()
+            .map(|group| {
+                let chosen = notifications.groups.contains(&group);
+                (group, chosen)
+            })
+            .collect(),
+    };
     Html(template.render().unwrap_or_else(|_| "Template error".to_string())).into_response()
 }
 
@@ -308,38 +942,1617 @@ async fn settings_handler(
     }
 }
 
-async fn api_status(
+#[derive(Deserialize)]
+struct LanguageForm {
+    /// A language code, or empty to follow the server's language.
+    language: String,
+}
+
+async fn settings_language_handler(
     State(state): State<AppState>,
+    cookies: Cookies,
+    Form(form): Form<LanguageForm>,
 ) -> impl IntoResponse {
-    let monitor = state.monitor.read().await;
-    let status = monitor.status_report();
-    let (anomalies, has_anomaly) = monitor.detect_anomalies();
+    // Verify session
+    let username = if let Some(token) = cookies.get("session") {
+        match state.auth.verify_token(token.value()).await {
+            Ok(user) => user,
+            Err(_) => return Redirect::to("/login").into_response(),
+        }
+    } else {
+        return Redirect::to("/login").into_response();
+    };
     
-    Json(DashboardData {
-        status,
-        anomalies,
-        has_anomaly,
-        graphs: json!({}),
-    })
+    let language = match form.language.as_str() {
+        "" => None,
+        code => match Language::from_code(code) {
+            Some(language) => Some(language),
+            None => return (StatusCode::BAD_REQUEST, format!("Unknown language '{}'", code)).into_response(),
+        },
+    };
+    match state.auth.set_language(&username, language).await {
+        Ok(()) => Redirect::to("/settings").into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
 }
 
-#[derive(Deserialize)]
-struct MetricsQuery {
-    limit: Option<usize>,
+/// Saves the alerts the signed-in user is sent on their own channels. Checkboxes
+/// are `channel_<name>` and `group_<tag>`; no groups means every alert.
+async fn settings_notifications_handler(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    Form(form): Form<HashMap<String, String>>,
+) -> impl IntoResponse {
+    // Verify session
+    let username = if let Some(token) = cookies.get("session") {
+        match state.auth.verify_token(token.value()).await {
+            Ok(user) => user,
+            Err(_) => return Redirect::to("/login").into_response(),
+        }
+    } else {
+        return Redirect::to("/login").into_response();
+    };
+    
+    let min_severity = match form.get("min_severity").map(String::as_str) {
+        Some("Info") => AnomalySeverity::Info,
+        Some("Warning") => AnomalySeverity::Warning,
+        Some("Critical") | None => AnomalySeverity::Critical,
+        Some(other) => return (StatusCode::BAD_REQUEST, format!("Unknown severity '{}'", other)).into_response(),
+    };
+    let preferences = NotificationPreferences {
+        channels: USER_CHANNELS
+            .iter()
+            .filter(|channel| form.contains_key(&format!("channel_{}", channel)))
+            .map(|channel| channel.to_string())
+            .collect(),
+        min_severity,
+        groups: form.keys().filter_map(|key| key.strip_prefix("group_")).map(str::to_string).collect(),
+    };
+    match state.auth.set_notification_preferences(&username, Some(preferences)).await {
+        Ok(()) => Redirect::to("/settings").into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
 }
 
-async fn api_metrics(
+async fn settings_display_handler(
     State(state): State<AppState>,
-    Query(params): Query<MetricsQuery>,
+    cookies: Cookies,
+    Form(form): Form<HashMap<String, String>>,
 ) -> impl IntoResponse {
-    let monitor = state.monitor.read().await;
-    let history = monitor.get_metrics_history();
+    // Verify session
+    let username = if let Some(token) = cookies.get("session") {
+        match state.auth.verify_token(token.value()).await {
+            Ok(user) => user,
+            Err(_) => return Redirect::to("/login").into_response(),
+        }
+    } else {
+        return Redirect::to("/login").into_response();
+    };
+    
+    if !state.auth.is_admin(&username).await {
+        return (StatusCode::FORBIDDEN, "Administrator privileges required").into_response();
+    }
+    
+    // Unchecked checkboxes are simply absent from the submitted form
+    let mut config = state.config.current().await;
+    for (stat, visible) in config.display.stat_visibility.iter_mut() {
+        *visible = form.contains_key(&format!("show_{}", stat));
+    }
+    if let Some(rate) = form.get("refresh_rate").and_then(|r| r.trim().parse().ok()) {
+        config.display.refresh_rate = rate;
+    }
+    
+    match state.config.update(config, &format!("settings page ({})", username)).await {
+        Ok(_) => Redirect::to("/settings").into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, format!("Invalid settings: {}", e)).into_response(),
+    }
+}
+
+/// The language `username` sees the web pages in: their own choice, otherwise the
+/// server's.
+async fn page_language(state: &AppState, username: &str) -> Language {
+    match state.auth.language(username).await {
+        Some(language) => language,
+        None => state.config.current().await.display.language,
+    }
+}
+
+/// Liveness of the background tasks; 503 while any of them is being restarted.
+async fn healthz(State(state): State<AppState>) -> impl IntoResponse {
+    let healthy = state.supervisor.is_healthy();
+    let status = if healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    
+    (
+        status,
+        Json(json!({
+            "status": if healthy { "ok" } else { "degraded" },
+            "tasks": state.supervisor.health(),
+        })),
+    )
+}
+
+/// Internals for diagnosing the monitor in the field: background tasks, recent
+/// cycle timings, collector failures, queue depths and the async runtime.
+async fn api_debug_runtime(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(response) = require_admin(&state, &cookies, &headers).await {
+        return response;
+    }
     
-    let limit = params.limit.unwrap_or(60);
-    let metrics: Vec<_> = history.iter().rev().take(limit).collect();
+    // A monitor that stays locked is the finding, so it is not waited on for long
+    let monitor = match tokio::time::timeout(std::time::Duration::from_secs(DEBUG_LOCK_TIMEOUT_SECS), state.monitor.read()).await {
+        Ok(monitor) => json!({
+            "locked": false,
+            "collectors": monitor.collectors(),
+            "queues": monitor.queue_depths(),
+        }),
+        Err(_) => json!({ "locked": true }),
+    };
+    let runtime = tokio::runtime::Handle::current().metrics();
     
     Json(json!({
-        "metrics": metrics,
-        "count": metrics.len(),
+        "tasks": state.supervisor.health(),
+        "cycles": state.supervisor.cycles(),
+        "monitor": monitor,
+        "runtime": {
+            "workers": runtime.num_workers(),
+            "alive_tasks": runtime.num_alive_tasks(),
+            "global_queue_depth": runtime.global_queue_depth(),
+        },
     }))
+    .into_response()
+}
+
+async fn api_status(
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let snapshot = state.status.load();
+    
+    Json(DashboardData {
+        status: snapshot.status.clone(),
+        anomalies: snapshot.anomalies.clone(),
+        has_anomaly: snapshot.has_anomaly,
+        graphs: snapshot.graphs.clone(),
+        uptime: snapshot.uptime.clone(),
+        // Device names and addresses are not for anonymous visitors
+        hosts: snapshot.hosts.iter().filter(|group| group.name != LAN_DEVICES_GROUP).cloned().collect(),
+    })
+}
+
+#[derive(Deserialize)]
+struct MetricsQuery {
+    limit: Option<usize>,
+    range: Option<HistoryRange>,
+    /// Start and end of a time range of archived samples, RFC 3339.
+    from: Option<String>,
+    to: Option<String>,
+    /// Seconds of a downsampling tier, to return its averages over the time range.
+    bucket: Option<u64>,
+    /// `next_cursor` of the previous page.
+    cursor: Option<String>,
+    /// Comma-separated fields to return with each sample's timestamp.
+    fields: Option<String>,
+    /// Comma-separated checks to keep in `host_status`.
+    hosts: Option<String>,
+}
+
+async fn api_dashboard_layout(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+) -> Response {
+    let username = match require_session(&state, &cookies, &headers).await {
+        Ok(username) => username,
+        Err(response) => return response,
+    };
+    let display = state.config.current().await.display;
+    let custom = state.auth.dashboard_layout(&username).await.is_some();
+    let layout = dashboard_layout(&state, &username, &display).await;
+    
+    Json(json!({ "panels": layout.panels, "custom": custom, "metrics": CHART_METRICS })).into_response()
+}
+
+async fn api_set_dashboard_layout(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Json(layout): Json<DashboardLayout>,
+) -> Response {
+    let username = match require_session(&state, &cookies, &headers).await {
+        Ok(username) => username,
+        Err(response) => return response,
+    };
+    if let Err(e) = layout.validate() {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": e }))).into_response();
+    }
+    
+    match state.auth.set_dashboard_layout(&username, Some(layout)).await {
+        Ok(()) => Json(json!({ "status": "saved" })).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))).into_response(),
+    }
+}
+
+async fn api_reset_dashboard_layout(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+) -> Response {
+    let username = match require_session(&state, &cookies, &headers).await {
+        Ok(username) => username,
+        Err(response) => return response,
+    };
+    
+    match state.auth.set_dashboard_layout(&username, None).await {
+        Ok(()) => Json(json!({ "status": "reset" })).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))).into_response(),
+    }
+}
+
+/// Metric samples, newest first, or aggregated history for a `range`, oldest first,
+/// or archived samples or a tier's averages (`bucket`) between `from` and `to`,
+/// oldest first. Pages of `limit` samples continue from `cursor`, the timestamp of
+/// the previous page's last sample; `fields` and `hosts` trim each sample to what
+/// was asked for.
+async fn api_metrics(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Query(params): Query<MetricsQuery>,
+) -> Response {
+    if let Err(response) = require_session(&state, &cookies, &headers).await {
+        return response;
+    }
+    let parse = |value: Option<&str>, name: &'static str| {
+        value
+            .map(chrono::DateTime::parse_from_rfc3339)
+            .transpose()
+            .map(|time| time.map(|time| time.with_timezone(&chrono::Utc)))
+            .map_err(|_| name)
+    };
+    let (cursor, from, to) = match (
+        parse(params.cursor.as_deref(), "cursor"),
+        parse(params.from.as_deref(), "from"),
+        parse(params.to.as_deref(), "to"),
+    ) {
+        (Ok(cursor), Ok(from), Ok(to)) => (cursor, from, to),
+        (Err(name), _, _) | (_, Err(name), _) | (_, _, Err(name)) => {
+            return (StatusCode::BAD_REQUEST, Json(json!({ "error": format!("Invalid {}", name) }))).into_response();
+        }
+    };
+    if params.range.is_some() && (from.is_some() || to.is_some()) {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "Use either range or from and to" }))).into_response();
+    }
+    let fields = comma_list(params.fields.as_deref());
+    let hosts = comma_list(params.hosts.as_deref());
+    let snapshot = state.status.load();
+    
+    // A range selects aggregated history, from and to archived samples; otherwise
+    // the latest raw samples are returned
+    let (samples, limit): (Vec<(chrono::DateTime<chrono::Utc>, serde_json::Value)>, usize) = match params.range {
+        _ if from.is_some() || to.is_some() || params.bucket.is_some() => {
+            let limit = params.limit.unwrap_or(ARCHIVE_PAGE).min(MAX_ARCHIVE_PAGE);
+            let after = cursor.or(from.map(|from| from - chrono::Duration::nanoseconds(1)));
+            let after = after.unwrap_or(chrono::DateTime::<chrono::Utc>::MIN_UTC);
+            let to = to.unwrap_or_else(chrono::Utc::now);
+            // One more than a page, to tell whether another follows
+            let archived = match params.bucket {
+                Some(bucket) => storage::query_averages(bucket, after, to, limit.saturating_add(1))
+                    .await
+                    .map(|points| points.into_iter().map(|point| (point.timestamp, json!(point))).collect()),
+                None => storage::query(after, to, limit.saturating_add(1))
+                    .await
+                    .map(|samples| samples.into_iter().map(|sample| (sample.timestamp, json!(sample))).collect()),
+            };
+            match archived {
+                Ok(archived) => (archived, limit),
+                Err(e) => {
+                    return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))).into_response();
+                }
+            }
+        }
+        // Only the page and the one after it are converted to JSON
+        Some(range) => {
+            let limit = params.limit.unwrap_or(usize::MAX);
+            let samples = snapshot
+                .history(range)
+                .iter()
+                .filter(|point| cursor.map_or(true, |cursor| point.timestamp > cursor))
+                .take(limit.saturating_add(1))
+                .map(|point| (point.timestamp, json!(point)))
+                .collect();
+            (samples, limit)
+        }
+        None => {
+            let limit = params.limit.unwrap_or(60);
+            let samples = snapshot
+                .metrics
+                .iter()
+                .rev()
+                .filter(|sample| cursor.map_or(true, |cursor| sample.timestamp < cursor))
+                .take(limit.saturating_add(1))
+                .map(|sample| (sample.timestamp, json!(sample)))
+                .collect();
+            (samples, limit)
+        }
+    };
+    
+    if let (Some(fields), Some((_, serde_json::Value::Object(first)))) = (&fields, samples.first()) {
+        let unknown: Vec<&String> = fields.iter().filter(|field| !first.contains_key(*field)).collect();
+        if !unknown.is_empty() {
+            return (StatusCode::BAD_REQUEST, Json(json!({ "error": "Unknown fields", "fields": unknown })))
+                .into_response();
+        }
+    }
+    
+    let next_cursor = (limit > 0 && samples.len() > limit).then(|| samples[limit - 1].0.to_rfc3339_opts(chrono::SecondsFormat::Nanos, true));
+    let metrics: Vec<serde_json::Value> = samples
+        .into_iter()
+        .take(limit)
+        .map(|(_, sample)| select_fields(sample, fields.as_deref(), hosts.as_deref()))
+        .collect();
+    
+    Json(json!({
+        "range": params.range,
+        "from": from,
+        "to": to,
+        "bucket": params.bucket,
+        "metrics": metrics,
+        "count": metrics.len(),
+        "next_cursor": next_cursor,
+    }))
+    .into_response()
+}
+
+/// Streams each metric sample to a WebSocket client as a JSON text message as soon
+/// as it is collected, so live charts need not poll. A client that falls behind
+/// skips the samples it missed.
+async fn ws_metrics(State(state): State<AppState>, cookies: Cookies, request: Request) -> Response {
+    if let Err(response) = require_session(&state, &cookies, request.headers()).await {
+        return response;
+    }
+    if websocket::is_cross_origin(request.headers()) {
+        return (StatusCode::FORBIDDEN, Json(json!({ "error": "Cross-origin connections are not allowed" }))).into_response();
+    }
+    
+    let mut samples = state.live_metrics.subscribe();
+    let response = websocket::upgrade(request, move |mut socket| async move {
+        loop {
+            tokio::select! {
+                sample = samples.recv() => {
+                    let sample = match sample {
+                        Ok(sample) => sample,
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
+                    let Ok(text) = serde_json::to_string(&*sample) else {
+                        continue;
+                    };
+                    if socket.send_text(&text).await.is_err() {
+                        break;
+                    }
+                }
+                // Nothing is expected from the client; reading notices it leaving
+                message = socket.recv() => {
+                    if !matches!(message, Ok(Some(_))) {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+    response.unwrap_or_else(|| (StatusCode::BAD_REQUEST, Json(json!({ "error": "Expected a WebSocket upgrade" }))).into_response())
+}
+
+/// The non-empty items of a comma-separated query parameter.
+fn comma_list(list: Option<&str>) -> Option<Vec<String>> {
+    list.map(|list| list.split(',').map(str::trim).filter(|item| !item.is_empty()).map(str::to_string).collect())
+}
+
+/// Keeps `fields` of a serialized sample, always with its timestamp, and only the
+/// checks in `hosts` of its `host_status`.
+fn select_fields(mut sample: serde_json::Value, fields: Option<&[String]>, hosts: Option<&[String]>) -> serde_json::Value {
+    let serde_json::Value::Object(map) = &mut sample else {
+        return sample;
+    };
+    if let Some(fields) = fields {
+        map.retain(|key, _| key == "timestamp" || fields.contains(key));
+    }
+    if let (Some(hosts), Some(serde_json::Value::Object(status))) = (hosts, map.get_mut("host_status")) {
+        status.retain(|check, _| hosts.contains(check));
+    }
+    sample
+}
+
+#[derive(Deserialize)]
+struct GraphqlRequest {
+    query: String,
+    #[serde(default)]
+    variables: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct GraphqlQuery {
+    query: String,
+    /// Variables as a JSON object.
+    variables: Option<String>,
+}
+
+async fn graphql_get(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Query(params): Query<GraphqlQuery>,
+) -> Response {
+    let variables = match params.variables.as_deref().map(serde_json::from_str) {
+        Some(Ok(variables)) => Some(variables),
+        Some(Err(_)) => return graphql_error(StatusCode::BAD_REQUEST, "variables must be a JSON object"),
+        None => None,
+    };
+    graphql(&state, &cookies, &headers, GraphqlRequest { query: params.query, variables }).await
+}
+
+async fn graphql_post(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Json(request): Json<GraphqlRequest>,
+) -> Response {
+    graphql(&state, &cookies, &headers, request).await
+}
+
+/// Answers a GraphQL query over `metrics(limit, range, hosts)`, `anomalies`, `hosts`
+/// and `threats(source, limit)`, when `api.graphql` is enabled.
+async fn graphql(state: &AppState, cookies: &Cookies, headers: &HeaderMap, request: GraphqlRequest) -> Response {
+    if !state.config.current().await.api.graphql {
+        return graphql_error(StatusCode::NOT_FOUND, "GraphQL is not enabled");
+    }
+    if let Err(response) = require_session(state, cookies, headers).await {
+        return response;
+    }
+    let variables = match request.variables {
+        Some(serde_json::Value::Object(variables)) => variables,
+        Some(serde_json::Value::Null) | None => serde_json::Map::new(),
+        Some(_) => return graphql_error(StatusCode::BAD_REQUEST, "variables must be a JSON object"),
+    };
+    let selection = match graphql::parse(&request.query, &variables) {
+        Ok(selection) => selection,
+        Err(e) => return graphql_error(StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+    
+    let mut data = serde_json::Map::new();
+    for field in &selection {
+        let value = match graphql_root(state, field).await {
+            Ok(value) => value,
+            Err(e) => return graphql_error(StatusCode::BAD_REQUEST, &format!("{}: {}", field.key(), e)),
+        };
+        match graphql::project(&value, &field.selection) {
+            Ok(value) => data.insert(field.key().to_string(), value),
+            Err(e) => return graphql_error(StatusCode::BAD_REQUEST, &format!("{}: {}", field.key(), e)),
+        };
+    }
+    Json(json!({ "data": data })).into_response()
+}
+
+/// The value of a root field before its selection is applied.
+async fn graphql_root(state: &AppState, field: &graphql::Field) -> anyhow::Result<serde_json::Value> {
+    let argument = |name: &str| field.arguments.get(name).filter(|value| !value.is_null());
+    let limit = match argument("limit") {
+        Some(limit) => Some(limit.as_u64().ok_or_else(|| anyhow::anyhow!("limit must be a positive integer"))? as usize),
+        None => None,
+    };
+    
+    match field.name.as_str() {
+        "metrics" => {
+            let hosts: Option<Vec<String>> = argument("hosts").map(|hosts| serde_json::from_value(hosts.clone())).transpose()?;
+            let snapshot = state.status.load();
+            let samples: Vec<serde_json::Value> = match argument("range") {
+                Some(range) => {
+                    let range: HistoryRange = serde_json::from_value(range.clone())
+                        .map_err(|_| anyhow::anyhow!("range must be 1h, 6h, 24h or 7d"))?;
+                    let history = snapshot.history(range);
+                    let skip = history.len().saturating_sub(limit.unwrap_or(usize::MAX));
+                    history.iter().skip(skip).map(|point| json!(point)).collect()
+                }
+                None => snapshot
+                    .metrics
+                    .iter()
+                    .rev()
+                    .take(limit.unwrap_or(60))
+                    .map(|sample| select_fields(json!(sample), None, hosts.as_deref()))
+                    .collect(),
+            };
+            Ok(json!(samples))
+        }
+        "anomalies" => {
+            let anomalies: Vec<serde_json::Value> = state
+                .status
+                .load()
+                .anomalies
+                .iter()
+                .map(|anomaly| {
+                    json!({
+                        "message": anomaly,
+                        "subject": anomaly_subject(anomaly),
+                        "severity": anomaly_severity(anomaly),
+                    })
+                })
+                .collect();
+            Ok(json!(anomalies))
+        }
+        "hosts" => Ok(json!(state.status.load().hosts)),
+        "threats" => {
+            let source = argument("source").and_then(|source| source.as_str());
+            let threat_intel = state.threat_intel.read().await;
+            let mut threats: Vec<&ThreatIntel> = threat_intel
+                .get_data()
+                .iter()
+                .filter(|(name, _)| source.map_or(true, |source| source == name.as_str()))
+                .flat_map(|(_, items)| items)
+                .collect();
+            threats.sort_by_key(|threat| std::cmp::Reverse(threat.published));
+            threats.truncate(limit.unwrap_or(usize::MAX));
+            Ok(json!(threats))
+        }
+        name => Err(anyhow::anyhow!("Unknown field '{}'", name)),
+    }
+}
+
+fn graphql_error(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "errors": [{ "message": message }] }))).into_response()
+}
+
+async fn api_availability(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Query(params): Query<AvailabilityQuery>,
+) -> Response {
+    if let Err(response) = require_session(&state, &cookies, &headers).await {
+        return response;
+    }
+    let reports = availability_reports(&state, &params).await;
+    
+    Json(json!({
+        "period": params.period,
+        "reports": reports,
+    }))
+    .into_response()
+}
+
+#[derive(Deserialize)]
+struct SummaryReportQuery {
+    #[serde(default)]
+    period: ReportPeriod,
+}
+
+/// Generates the summary report now, written to the downloads area and mailed to the
+/// configured recipients like a scheduled one.
+async fn api_summary_report(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Query(params): Query<SummaryReportQuery>,
+) -> Response {
+    if let Err(response) = require_admin(&state, &cookies, &headers).await {
+        return response;
+    }
+    if params.period == ReportPeriod::Month {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "Summary reports cover a day or a week" }))).into_response();
+    }
+    
+    let config = state.config.current().await.reports;
+    match state.reports.generate(params.period, &config).await {
+        Ok(file) => Json(json!({ "file": file, "download": format!("/download/{}", file) })).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))).into_response(),
+    }
+}
+
+async fn api_support_bundle(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(response) = require_admin(&state, &cookies, &headers).await {
+        return response;
+    }
+    
+    match support_bundle::create(&state.config, &state.monitor).await {
+        Ok(file) => Json(json!({ "file": file, "download": format!("/api/support-bundle/{}", file) })).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))).into_response(),
+    }
+}
+
+/// A support bundle; admins only, as it holds logs and anomaly history.
+async fn api_support_bundle_download(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(file): Path<String>,
+) -> Response {
+    if let Err(response) = require_admin(&state, &cookies, &headers).await {
+        return response;
+    }
+    
+    match support_bundle::bundle_path(&file) {
+        Some(path) => send_file(&path, &headers).await,
+        None => (StatusCode::NOT_FOUND, "File not found").into_response(),
+    }
+}
+
+async fn static_file(Path(path): Path<String>) -> Response {
+    static_asset(&path).await
+}
+
+/// A built-in static file, or its replacement in `static/`.
+async fn static_asset(path: &str) -> Response {
+    match assets::load(path).await {
+        // Revalidated every time so replacing a file takes effect at once
+        Some(content) => (
+            [(header::CONTENT_TYPE, content_type(path)), (header::CACHE_CONTROL, "no-cache")],
+            Body::from(content),
+        )
+            .into_response(),
+        None => (StatusCode::NOT_FOUND, "Not found").into_response(),
+    }
+}
+
+async fn api_push_key(State(state): State<AppState>) -> impl IntoResponse {
+    let key = state.monitor.read().await.web_push().public_key();
+    Json(json!({ "key": key }))
+}
+
+async fn api_push_subscribe(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Json(mut subscription): Json<PushSubscription>,
+) -> Response {
+    let username = match require_session(&state, &cookies, &headers).await {
+        Ok(username) => username,
+        Err(response) => return response,
+    };
+    subscription.username = username;
+    subscription.created_at = chrono::Utc::now();
+    
+    let web_push = state.monitor.read().await.web_push();
+    match web_push.subscribe(subscription).await {
+        Ok(()) => (StatusCode::CREATED, Json(json!({ "subscribed": true }))).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct PushUnsubscribe {
+    endpoint: String,
+}
+
+async fn api_push_unsubscribe(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Json(request): Json<PushUnsubscribe>,
+) -> Response {
+    let username = match require_session(&state, &cookies, &headers).await {
+        Ok(username) => username,
+        Err(response) => return response,
+    };
+    // Only an admin may remove another account's browser
+    let is_admin = state.auth.is_admin(&username).await;
+    
+    let web_push = state.monitor.read().await.web_push();
+    match web_push.unsubscribe(&request.endpoint, (!is_admin).then_some(username.as_str())).await {
+        Ok(removed) => Json(json!({ "removed": removed })).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))).into_response(),
+    }
+}
+
+async fn api_push_test(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(response) = require_admin(&state, &cookies, &headers).await {
+        return response;
+    }
+    
+    let web_push = state.monitor.read().await.web_push();
+    let alerts = state.config.current().await.alerts;
+    let message = json!({
+        "title": alerts.language.t("Test notification"),
+        "body": alerts.language.t("Critical alerts will arrive like this."),
+        "url": "/settings",
+    });
+    let sent = web_push.notify(&message, alerts.push_contact.as_deref().unwrap_or(DEFAULT_PUSH_CONTACT)).await;
+    Json(json!({ "sent": sent, "subscribed": web_push.subscriptions().len() })).into_response()
+}
+
+async fn api_logs(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(response) = require_session(&state, &cookies, &headers).await {
+        return response;
+    }
+    Json(log_files().await).into_response()
+}
+
+#[derive(Deserialize)]
+struct LogQuery {
+    #[serde(default = "default_log_page")]
+    page: usize,
+    #[serde(default = "default_log_per_page")]
+    per_page: usize,
+    /// Text to search for.
+    q: Option<String>,
+    /// Least severe level to show.
+    severity: Option<AnomalySeverity>,
+}
+
+fn default_log_page() -> usize {
+    1
+}
+
+fn default_log_per_page() -> usize {
+    200
+}
+
+/// A log file by path relative to the downloads directory, when it may be viewed.
+async fn log_path(name: &str) -> Option<PathBuf> {
+    let (_, path) = download_path(name).await.filter(|(_, path)| path.is_file())?;
+    viewable_log(&path.to_string_lossy()).then_some(path)
+}
+
+async fn api_log(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    Query(params): Query<LogQuery>,
+) -> Response {
+    if let Err(response) = require_session(&state, &cookies, &headers).await {
+        return response;
+    }
+    let Some(path) = log_path(&name).await else {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": format!("No log named {}", name) }))).into_response();
+    };
+    
+    let filter = LogFilter::new(params.q, params.severity);
+    match log_viewer::read_page(&name, path, filter, params.page, params.per_page).await {
+        Ok(page) => Json(page).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))).into_response(),
+    }
+}
+
+/// Sends lines as they are appended to a log, as server-sent events each holding a
+/// JSON array of the new lines that pass the search and severity filters.
+async fn api_log_follow(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    Query(params): Query<LogQuery>,
+) -> Response {
+    if let Err(response) = require_session(&state, &cookies, &headers).await {
+        return response;
+    }
+    let Some(path) = log_path(&name).await else {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": format!("No log named {}", name) }))).into_response();
+    };
+    
+    let filter = LogFilter::new(params.q, params.severity);
+    let mut tail = LogTail::new();
+    // The first read only notes where the file ends
+    tail.read_new(&path).await;
+    
+    let lines = futures::stream::unfold((tail, path, filter), |(mut tail, path, filter)| async move {
+        loop {
+            tokio::time::sleep(std::time::Duration::from_secs(LOG_FOLLOW_INTERVAL_SECS)).await;
+            let lines: Vec<_> = tail.read_new(&path).await.iter().filter_map(|line| filter.apply(line)).collect();
+            if !lines.is_empty() {
+                return Some((SseEvent::default().json_data(&lines), (tail, path, filter)));
+            }
+        }
+    });
+    Sse::new(lines).keep_alive(KeepAlive::default()).into_response()
+}
+
+async fn api_traceroute(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(host): Path<String>,
+) -> impl IntoResponse {
+    if let Err(response) = require_admin(&state, &cookies, &headers).await {
+        return response;
+    }
+    
+    let Some(target) = state.monitor.read().await.trace_target(&host) else {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "Check has no traceable host" }))).into_response();
+    };
+    
+    match diagnostics::traceroute(&target).await {
+        Ok(trace) => Json(trace).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))).into_response(),
+    }
+}
+
+async fn api_public_ip(State(state): State<AppState>, cookies: Cookies, headers: HeaderMap) -> Response {
+    if let Err(response) = require_admin(&state, &cookies, &headers).await {
+        return response;
+    }
+    let history = public_ip::history().await;
+    let blocklisted = state.monitor.read().await.external_anomalies(public_ip::DNSBL_SOURCE).to_vec();
+    
+    Json(json!({
+        "current": history.last().map(|change| change.address.clone()),
+        "blocklisted": blocklisted,
+        "history": history,
+    }))
+    .into_response()
+}
+
+async fn api_dns(State(state): State<AppState>, cookies: Cookies, headers: HeaderMap) -> Response {
+    if let Err(response) = require_session(&state, &cookies, &headers).await {
+        return response;
+    }
+    let records: Vec<_> = state
+        .monitor
+        .read()
+        .await
+        .dns()
+        .answers()
+        .into_iter()
+        .map(|(name, record_type, answers)| json!({ "name": name, "type": record_type, "answers": answers }))
+        .collect();
+    
+    Json(json!({
+        "records": records,
+        "history": dns::history().await,
+    }))
+    .into_response()
+}
+
+async fn api_oom_kills(State(state): State<AppState>, cookies: Cookies, headers: HeaderMap) -> Response {
+    if let Err(response) = require_session(&state, &cookies, &headers).await {
+        return response;
+    }
+    Json(json!({ "oom_kills": kernel_log::oom_kills().await })).into_response()
+}
+
+async fn api_kubernetes(State(state): State<AppState>, cookies: Cookies, headers: HeaderMap) -> Response {
+    if let Err(response) = require_session(&state, &cookies, &headers).await {
+        return response;
+    }
+    let monitor = state.monitor.read().await;
+    let config = state.config.current().await.monitoring.kubernetes;
+    
+    Json(json!({
+        "cluster": monitor.kubernetes().status(),
+        // The cluster is as of an earlier poll when the latest one failed
+        "stale": monitor.kubernetes().error().is_some(),
+        "error": monitor.kubernetes().error(),
+        "anomalies": monitor.kubernetes().anomalies(&config),
+    }))
+    .into_response()
+}
+
+async fn api_proxmox(State(state): State<AppState>, cookies: Cookies, headers: HeaderMap) -> Response {
+    if let Err(response) = require_session(&state, &cookies, &headers).await {
+        return response;
+    }
+    Json(json!({ "clusters": state.monitor.read().await.proxmox().resources() })).into_response()
+}
+
+#[derive(Deserialize)]
+struct LanUsageQuery {
+    /// "2026-10"; the current month by default.
+    month: Option<String>,
+}
+
+/// Each LAN device's traffic in a month, most first, with its current rates and the
+/// hourly tallies of the last week.
+async fn api_lan_usage(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Query(query): Query<LanUsageQuery>,
+) -> Response {
+    if let Err(response) = require_session(&state, &cookies, &headers).await {
+        return response;
+    }
+    let month = query.month.unwrap_or_else(|| chrono::Utc::now().format("%Y-%m").to_string());
+    let monitor = state.monitor.read().await;
+    let lan_usage = monitor.lan_usage();
+    let mut devices = lan_usage.month_devices(&month);
+    devices.sort_by_key(|device| std::cmp::Reverse(device.month.upload + device.month.download));
+    
+    Json(json!({
+        "month": month,
+        "months": lan_usage.months(),
+        "devices": devices,
+        "hours": lan_usage.hours(),
+    }))
+    .into_response()
+}
+
+async fn api_pihole(State(state): State<AppState>, cookies: Cookies, headers: HeaderMap) -> Response {
+    if let Err(response) = require_session(&state, &cookies, &headers).await {
+        return response;
+    }
+    Json(json!({ "instances": state.monitor.read().await.pihole().status() })).into_response()
+}
+
+async fn api_backups(State(state): State<AppState>, cookies: Cookies, headers: HeaderMap) -> Response {
+    if let Err(response) = require_session(&state, &cookies, &headers).await {
+        return response;
+    }
+    Json(json!({ "backups": state.monitor.read().await.backups().status() })).into_response()
+}
+
+async fn api_collectors(State(state): State<AppState>, cookies: Cookies, headers: HeaderMap) -> Response {
+    if let Err(response) = require_session(&state, &cookies, &headers).await {
+        return response;
+    }
+    Json(json!({ "collectors": state.monitor.read().await.collectors() })).into_response()
+}
+
+async fn api_heartbeats(State(state): State<AppState>, cookies: Cookies, headers: HeaderMap) -> Response {
+    if let Err(response) = require_session(&state, &cookies, &headers).await {
+        return response;
+    }
+    let jobs = state.monitor.read().await.heartbeats().jobs();
+    let heartbeats: Vec<_> = state
+        .config
+        .current()
+        .await
+        .monitoring
+        .heartbeats
+        .into_iter()
+        .map(|heartbeat| {
+            json!({
+                "name": heartbeat.name,
+                "period": heartbeat.period,
+                "grace": heartbeat.grace,
+                "state": jobs.get(&heartbeat.name),
+            })
+        })
+        .collect();
+    
+    Json(json!({ "heartbeats": heartbeats })).into_response()
+}
+
+/// Check-in URL for cron jobs; the token identifies and authenticates the job.
+async fn api_heartbeat(State(state): State<AppState>, Path(token): Path<String>) -> Response {
+    record_heartbeat(&state, &token, false).await
+}
+
+async fn api_heartbeat_fail(State(state): State<AppState>, Path(token): Path<String>) -> Response {
+    record_heartbeat(&state, &token, true).await
+}
+
+async fn record_heartbeat(state: &AppState, token: &str, failed: bool) -> Response {
+    let heartbeats = state.config.current().await.monitoring.heartbeats;
+    let Some(heartbeat) = heartbeats.iter().find(|heartbeat| auth::tokens_match(token, &heartbeat.token)) else {
+        return (StatusCode::NOT_FOUND, "Unknown heartbeat\n").into_response();
+    };
+    
+    let log = state.monitor.read().await.heartbeats();
+    log.record(&heartbeat.name, failed).await;
+    "OK\n".into_response()
+}
+
+/// Alerts pushed by Alertmanager, Grafana or Uptime Kuma.
+async fn api_ingest_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<HashMap<String, String>>,
+    Json(payload): Json<serde_json::Value>,
+) -> Response {
+    let Some(expected) = state.config.current().await.alerts.ingest_token.filter(|token| !token.is_empty()) else {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": "Webhook ingestion is not enabled" }))).into_response();
+    };
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let given = bearer.or(query.get("token").map(String::as_str));
+    if !given.is_some_and(|given| auth::tokens_match(given, &expected)) {
+        return (StatusCode::UNAUTHORIZED, Json(json!({ "error": "Invalid token" }))).into_response();
+    }
+    
+    match ingest::parse_payload(&payload) {
+        Ok(alerts) => {
+            let accepted = alerts.len();
+            state.monitor.write().await.ingest(alerts);
+            Json(json!({ "accepted": accepted })).into_response()
+        }
+        Err(e) => (StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct IncidentsQuery {
+    status: Option<IncidentStatus>,
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct AssignRequest {
+    assignee: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NoteRequest {
+    text: String,
+}
+
+/// An incident with its derived status.
+fn incident_json(incident: &Incident) -> serde_json::Value {
+    let mut value = serde_json::to_value(incident).unwrap_or_default();
+    value["status"] = json!(incident.status());
+    value
+}
+
+/// An incident as shown to `username`. What diagnostics captured, such as process
+/// command lines and sockets, can hold secrets, so only administrators see it.
+async fn incident_json_for(state: &AppState, username: &str, incident: &Incident) -> serde_json::Value {
+    if state.auth.is_admin(username).await {
+        return incident_json(incident);
+    }
+    let mut incident = incident.clone();
+    for entry in &mut incident.timeline {
+        entry.details = None;
+    }
+    incident_json(&incident)
+}
+
+/// Incidents newest first, optionally only those in one status.
+async fn api_incidents(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Query(params): Query<IncidentsQuery>,
+) -> Response {
+    let username = match require_session(&state, &cookies, &headers).await {
+        Ok(username) => username,
+        Err(response) => return response,
+    };
+    let incidents: Vec<Incident> = state
+        .monitor
+        .read()
+        .await
+        .incidents()
+        .incidents()
+        .iter()
+        .rev()
+        .filter(|incident| params.status.map_or(true, |status| incident.status() == status))
+        .take(params.limit.unwrap_or(50))
+        .cloned()
+        .collect();
+    
+    let mut listed = Vec::new();
+    for incident in &incidents {
+        listed.push(incident_json_for(&state, &username, incident).await);
+    }
+    Json(json!({ "incidents": listed })).into_response()
+}
+
+async fn api_incident(State(state): State<AppState>, cookies: Cookies, headers: HeaderMap, Path(id): Path<u64>) -> Response {
+    let username = match require_session(&state, &cookies, &headers).await {
+        Ok(username) => username,
+        Err(response) => return response,
+    };
+    let incident = state.monitor.read().await.incidents().get(id);
+    match incident {
+        Some(incident) => Json(incident_json_for(&state, &username, &incident).await).into_response(),
+        None => (StatusCode::NOT_FOUND, Json(json!({ "error": "Unknown incident" }))).into_response(),
+    }
+}
+
+/// The calling administrator and the incident log, once the incident is known to exist.
+async fn incident_editor(
+    state: &AppState,
+    cookies: &Cookies,
+    headers: &HeaderMap,
+    id: u64,
+) -> Result<(String, Arc<IncidentLog>), Response> {
+    let username = require_admin(state, cookies, headers).await?;
+    let incidents = state.monitor.read().await.incidents();
+    if incidents.get(id).is_none() {
+        return Err((StatusCode::NOT_FOUND, Json(json!({ "error": "Unknown incident" }))).into_response());
+    }
+    Ok((username, incidents))
+}
+
+fn incident_change_response(result: Result<Incident>) -> Response {
+    match result {
+        Ok(incident) => Json(incident_json(&incident)).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))).into_response(),
+    }
+}
+
+async fn api_incident_ack(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+) -> Response {
+    match incident_editor(&state, &cookies, &headers, id).await {
+        Ok((username, incidents)) => incident_change_response(incidents.acknowledge(id, &username).await),
+        Err(response) => response,
+    }
+}
+
+async fn api_incident_resolve(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+) -> Response {
+    match incident_editor(&state, &cookies, &headers, id).await {
+        Ok((username, incidents)) => incident_change_response(incidents.resolve(id, &username).await),
+        Err(response) => response,
+    }
+}
+
+/// Sets or, with a null or empty assignee, clears who handles the incident.
+async fn api_incident_assign(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+    Json(request): Json<AssignRequest>,
+) -> Response {
+    let assignee = request
+        .assignee
+        .map(|assignee| assignee.trim().to_string())
+        .filter(|assignee| !assignee.is_empty());
+    match incident_editor(&state, &cookies, &headers, id).await {
+        Ok((username, incidents)) => incident_change_response(incidents.assign(id, assignee, &username).await),
+        Err(response) => response,
+    }
+}
+
+async fn api_incident_note(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+    Json(request): Json<NoteRequest>,
+) -> Response {
+    let text = request.text.trim();
+    if text.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "Note text is required" }))).into_response();
+    }
+    match incident_editor(&state, &cookies, &headers, id).await {
+        Ok((username, incidents)) => incident_change_response(incidents.add_note(id, text, &username).await),
+        Err(response) => response,
+    }
+}
+
+async fn api_ssh_keys(State(state): State<AppState>, cookies: Cookies, headers: HeaderMap) -> Response {
+    if let Err(response) = require_admin(&state, &cookies, &headers).await {
+        return response;
+    }
+    
+    Json(json!({ "files": state.monitor.read().await.ssh_keys().keys() })).into_response()
+}
+
+async fn api_ports(State(state): State<AppState>, cookies: Cookies, headers: HeaderMap) -> Response {
+    if let Err(response) = require_admin(&state, &cookies, &headers).await {
+        return response;
+    }
+    
+    let sockets = tokio::task::spawn_blocking(|| ports::listening_sockets(true)).await.unwrap_or_default();
+    Json(json!({ "listening": sockets })).into_response()
+}
+
+async fn api_suid(State(state): State<AppState>, cookies: Cookies, headers: HeaderMap) -> Response {
+    if let Err(response) = require_admin(&state, &cookies, &headers).await {
+        return response;
+    }
+    
+    Json(suid::report().await).into_response()
+}
+
+/// Accepts the binaries of the last SUID scan as legitimate.
+async fn api_suid_accept(State(state): State<AppState>, cookies: Cookies, headers: HeaderMap) -> Response {
+    if let Err(response) = require_admin(&state, &cookies, &headers).await {
+        return response;
+    }
+    
+    if let Err(e) = suid::accept_current().await {
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))).into_response();
+    }
+    state.monitor.write().await.set_external_anomalies(suid::SUID_SOURCE, Vec::new());
+    
+    Json(json!({ "accepted": true })).into_response()
+}
+
+async fn api_get_config(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(response) = require_admin(&state, &cookies, &headers).await {
+        return response;
+    }
+    
+    Json(state.config.redacted().await).into_response()
+}
+
+async fn api_update_config(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Json(config): Json<AppConfig>,
+) -> impl IntoResponse {
+    let username = match require_admin(&state, &cookies, &headers).await {
+        Ok(username) => username,
+        Err(response) => return response,
+    };
+    
+    match state.config.update(config, &format!("API ({})", username)).await {
+        Ok(changes) => Json(json!({
+            "success": true,
+            "changes": changes,
+        }))
+        .into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "success": false,
+                "error": e.to_string(),
+            })),
+        )
+            .into_response(),
+    }
+}
+
+async fn api_backup(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(response) = require_admin(&state, &cookies, &headers).await {
+        return response;
+    }
+    
+    let backup = create_backup(&state.config, &state.auth, &state.monitor).await;
+    let filename = format!("super-monitor-backup-{}.zip", backup.created_at.format("%Y%m%d-%H%M%S"));
+    let json = match serde_json::to_vec_pretty(&backup) {
+        Ok(json) => json,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, format!("Backup failed: {}", e)).into_response(),
+    };
+    
+    let files = match tokio::task::spawn_blocking(backup::archive_files).await {
+        Ok(files) => files,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, format!("Backup failed: {}", e)).into_response(),
+    };
+    let archive = zip::stream_with(vec![(backup::BACKUP_ENTRY.to_string(), json)], files);
+    Response::builder()
+        .header(header::CONTENT_TYPE, "application/zip")
+        .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename))
+        .body(Body::from_stream(ReaderStream::new(archive)))
+        .unwrap()
+        .into_response()
+}
+
+async fn api_restore(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    if let Err(response) = require_admin(&state, &cookies, &headers).await {
+        return response;
+    }
+    
+    let backup = match backup::parse_backup(&body) {
+        Ok(backup) => backup,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({ "success": false, "error": e.to_string() }))).into_response(),
+    };
+    match restore_backup(backup, &state.config, &state.auth, &state.monitor).await {
+        Ok(()) => Json(json!({ "success": true })).into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "success": false,
+                "error": e.to_string(),
+            })),
+        )
+            .into_response(),
+    }
+}
+
+async fn api_baselines(State(state): State<AppState>, cookies: Cookies, headers: HeaderMap) -> Response {
+    if let Err(response) = require_session(&state, &cookies, &headers).await {
+        return response;
+    }
+    let monitor = state.monitor.read().await;
+    Json(json!({
+        "baselines": monitor.baselines(),
+        "relearning": monitor.relearning(),
+    }))
+    .into_response()
+}
+
+/// Feedback rules with their current confidence; inactive ones no longer suppress.
+async fn api_feedback(State(state): State<AppState>, cookies: Cookies, headers: HeaderMap) -> Response {
+    if let Err(response) = require_session(&state, &cookies, &headers).await {
+        return response;
+    }
+    let now = chrono::Utc::now();
+    let monitor = state.monitor.read().await;
+    let rules: Vec<serde_json::Value> = monitor
+        .feedback()
+        .iter()
+        .map(|rule| {
+            let mut value = json!(rule);
+            value["confidence"] = json!(rule.confidence(now));
+            value["active"] = json!(rule.is_active(now));
+            value
+        })
+        .collect();
+    
+    Json(json!({ "feedback": rules })).into_response()
+}
+
+#[derive(Deserialize)]
+struct MarkNormalRequest {
+    metric: String,
+    value: f64,
+}
+
+/// Marks a metric's value as a false positive, so nearby values stop raising anomalies.
+async fn api_mark_normal(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Json(request): Json<MarkNormalRequest>,
+) -> Response {
+    let username = match require_admin(&state, &cookies, &headers).await {
+        Ok(username) => username,
+        Err(response) => return response,
+    };
+    if !request.value.is_finite() {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "Value must be a number" }))).into_response();
+    }
+    
+    let mut monitor = state.monitor.write().await;
+    let rule = monitor.mark_normal(&request.metric, request.value, &username);
+    if let Err(e) = monitor.save_baseline().await {
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))).into_response();
+    }
+    
+    Json(rule).into_response()
+}
+
+async fn api_feedback_delete(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+) -> Response {
+    if let Err(response) = require_admin(&state, &cookies, &headers).await {
+        return response;
+    }
+    
+    let mut monitor = state.monitor.write().await;
+    if !monitor.remove_feedback(id) {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": "Unknown feedback rule" }))).into_response();
+    }
+    if let Err(e) = monitor.save_baseline().await {
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))).into_response();
+    }
+    
+    Json(json!({ "success": true })).into_response()
+}
+
+#[derive(Deserialize)]
+struct BaselineHistoryQuery {
+    /// Only this metric's baseline from each snapshot.
+    metric: Option<String>,
+    limit: Option<usize>,
+}
+
+/// Baseline snapshots newest first, whole or for one metric.
+async fn api_baseline_history(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Query(params): Query<BaselineHistoryQuery>,
+) -> Response {
+    if let Err(response) = require_admin(&state, &cookies, &headers).await {
+        return response;
+    }
+    let snapshots = baseline_history::snapshots().await;
+    let limit = params.limit.unwrap_or(100);
+    
+    let history: Vec<serde_json::Value> = snapshots
+        .into_iter()
+        .rev()
+        .filter_map(|snapshot| match &params.metric {
+            Some(metric) => snapshot.baselines.get(metric).map(|stats| {
+                json!({
+                    "version": snapshot.version,
+                    "learned_at": snapshot.learned_at,
+                    "window_size": snapshot.window_size,
+                    "sample_count": snapshot.sample_count,
+                    "baseline": stats,
+                })
+            }),
+            None => serde_json::to_value(&snapshot).ok(),
+        })
+        .take(limit)
+        .collect();
+    
+    Json(json!({ "history": history })).into_response()
+}
+
+#[derive(Deserialize)]
+struct BaselineDiffQuery {
+    /// Snapshot versions to compare; the oldest and the newest by default.
+    from: Option<u64>,
+    to: Option<u64>,
+}
+
+/// How each metric's baseline moved between two snapshots.
+async fn api_baseline_diff(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Query(params): Query<BaselineDiffQuery>,
+) -> Response {
+    if let Err(response) = require_admin(&state, &cookies, &headers).await {
+        return response;
+    }
+    let snapshots = baseline_history::snapshots().await;
+    let find = |version: u64| snapshots.iter().find(|snapshot| snapshot.version == version);
+    let from = params.from.map_or(snapshots.first(), find);
+    let to = params.to.map_or(snapshots.last(), find);
+    let (Some(from), Some(to)) = (from, to) else {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": "Unknown baseline snapshot" }))).into_response();
+    };
+    
+    let metrics: BTreeSet<&String> = from.baselines.keys().chain(to.baselines.keys()).collect();
+    let changes: serde_json::Map<String, serde_json::Value> = metrics
+        .into_iter()
+        .map(|metric| {
+            let (before, after) = (from.baselines.get(metric), to.baselines.get(metric));
+            let change = match (before, after) {
+                (Some(before), Some(after)) => json!({
+                    "from": before,
+                    "to": after,
+                    "mean_change": after.mean - before.mean,
+                    "std_change": after.std - before.std,
+                }),
+                _ => json!({ "from": before, "to": after }),
+            };
+            (metric.clone(), change)
+        })
+        .collect();
+    
+    Json(json!({
+        "from": { "version": from.version, "learned_at": from.learned_at },
+        "to": { "version": to.version, "learned_at": to.learned_at },
+        "metrics": changes,
+    }))
+    .into_response()
+}
+
+/// Applies a change to one metric's baseline and persists it; 404 when the change
+/// reports the metric unknown.
+async fn change_baseline(
+    state: &AppState,
+    cookies: &Cookies,
+    headers: &HeaderMap,
+    metric: &str,
+    change: impl FnOnce(&mut MonitorService) -> bool,
+) -> Response {
+    if let Err(response) = require_admin(state, cookies, headers).await {
+        return response;
+    }
+    
+    let mut monitor = state.monitor.write().await;
+    if !change(&mut monitor) {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": format!("No baseline for {}", metric) }))).into_response();
+    }
+    if let Err(e) = monitor.save_baseline().await {
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))).into_response();
+    }
+    
+    Json(json!({
+        "metric": metric,
+        "baseline": monitor.baselines().get(metric),
+        "relearning_since": monitor.relearning().get(metric),
+    }))
+    .into_response()
+}
+
+/// Drops one metric's baseline so it relearns from new samples, leaving the rest.
+async fn api_baseline_reset(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(metric): Path<String>,
+) -> Response {
+    change_baseline(&state, &cookies, &headers, &metric, |monitor| monitor.reset_baseline(&metric)).await
+}
+
+/// Pins one metric's current baseline so it is no longer relearned.
+async fn api_baseline_freeze(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(metric): Path<String>,
+) -> Response {
+    change_baseline(&state, &cookies, &headers, &metric, |monitor| monitor.freeze_baseline(&metric, true)).await
+}
+
+async fn api_baseline_unfreeze(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(metric): Path<String>,
+) -> Response {
+    change_baseline(&state, &cookies, &headers, &metric, |monitor| monitor.freeze_baseline(&metric, false)).await
+}
+
+async fn api_baselines_export(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(response) = require_admin(&state, &cookies, &headers).await {
+        return response;
+    }
+    
+    let export = export_baselines(&state.monitor).await;
+    let filename = format!("super-monitor-baselines-{}.json", export.created_at.format("%Y%m%d-%H%M%S"));
+    
+    match serde_json::to_vec_pretty(&export) {
+        Ok(body) => Response::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename))
+            .body(Body::from(body))
+            .unwrap()
+            .into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Export failed: {}", e)).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct BaselineImportQuery {
+    /// Drop every current baseline first instead of merging.
+    #[serde(default)]
+    replace: bool,
+}
+
+async fn api_baselines_import(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Query(params): Query<BaselineImportQuery>,
+    Json(export): Json<BaselineExport>,
+) -> impl IntoResponse {
+    if let Err(response) = require_admin(&state, &cookies, &headers).await {
+        return response;
+    }
+    
+    match import_baselines(export, params.replace, &state.monitor).await {
+        Ok(imported) => Json(json!({ "success": true, "imported": imported })).into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "success": false,
+                "error": e.to_string(),
+            })),
+        )
+            .into_response(),
+    }
 }
@@ -1,7 +1,10 @@
 use anyhow::Result;
+use chrono::Utc;
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
+use tokio::time::{Duration, Instant};
 use tracing::{info, warn};
 
 mod handlers;
@@ -9,61 +12,284 @@ mod models;
 mod services;
 mod utils;
 
-use handlers::create_app;
+use handlers::{create_app, AppState};
 use models::config::AppConfig;
+use models::metrics::SystemMetrics;
+use services::auth::AuthService;
+use services::backup::restore_backup_file;
+use services::config::ConfigService;
+use services::events::EventLog;
 use services::monitor::MonitorService;
+use services::persistence::PersistenceWatcher;
+use services::crowdsec::CrowdSecService;
+use services::file_watch::{ChangeWatcher, FileWatchService};
+use services::public_ip::PublicIpService;
+use services::status_snapshot::{PublishedStatus, StatusSnapshot};
+use services::storage;
+use services::suid::SuidScanService;
+use services::summary_report::SummaryReportService;
+use services::supervisor::{CycleTiming, Supervisor};
 use services::threat_intel::ThreatIntelService;
 use utils::logging::init_logging;
+use utils::systemd;
+
+const CONFIG_POLL_INTERVAL_SECS: u64 = 5;
+// How often the file watcher picks up changes to the watched paths
+const FILE_WATCH_REFRESH_SECS: u64 = 30;
+// The collection loop counts as wedged after this many missed cycles (minimum 2 minutes)
+const WATCHDOG_STALL_CYCLES: u64 = 3;
+const WATCHDOG_MIN_STALL_SECS: i64 = 120;
+// How often cron, systemd and profile locations are scanned for persistence
+const PERSISTENCE_SCAN_SECS: u64 = 60;
+// How often archived metrics are averaged and pruned to the retention policy
+const STORAGE_COMPACTION_SECS: u64 = 3600;
+// Samples a live metrics client may fall behind by before it skips ahead
+const LIVE_METRICS_BUFFER: usize = 16;
+
+/// Progress of the collection loop, so watchdog pings reflect real work being done.
+struct LoopProgress {
+    last_cycle: AtomicI64,
+    interval_secs: AtomicU64,
+}
+
+impl LoopProgress {
+    fn new(interval_secs: u64) -> Self {
+        Self {
+            last_cycle: AtomicI64::new(Utc::now().timestamp()),
+            interval_secs: AtomicU64::new(interval_secs),
+        }
+    }
+    
+    fn record_cycle(&self, interval_secs: u64) {
+        self.last_cycle.store(Utc::now().timestamp(), Ordering::Relaxed);
+        self.interval_secs.store(interval_secs, Ordering::Relaxed);
+    }
+    
+    fn is_stalled(&self) -> bool {
+        let allowed = (self.interval_secs.load(Ordering::Relaxed) * WATCHDOG_STALL_CYCLES) as i64;
+        let elapsed = Utc::now().timestamp() - self.last_cycle.load(Ordering::Relaxed);
+        elapsed > allowed.max(WATCHDOG_MIN_STALL_SECS)
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging
-    init_logging()?;
+    // `--dir <path>` or SUPER_MONITOR_HOME holds the config, data/ and logs/, so the
+    // binary can be started from anywhere; otherwise they are in the working directory
+    let args: Vec<String> = std::env::args().collect();
+    let launch_dir = std::env::current_dir()?;
+    let home = match args.iter().position(|a| a == "--dir") {
+        Some(pos) => Some(args.get(pos + 1).cloned().ok_or_else(|| anyhow::anyhow!("--dir requires a directory"))?),
+        None => std::env::var("SUPER_MONITOR_HOME").ok().filter(|home| !home.is_empty()),
+    };
+    if let Some(home) = home {
+        std::fs::create_dir_all(&home)?;
+        std::env::set_current_dir(&home).map_err(|e| anyhow::anyhow!("Cannot use {} as the data directory: {}", home, e))?;
+    }
     
-    info!("Starting ShaydZ Super Monitor v2.0 (Rust)");
+    // `--restore <file>` restores a backup from /api/backup and exits
+    if let Some(pos) = args.iter().position(|a| a == "--restore") {
+        let path = args.get(pos + 1).ok_or_else(|| anyhow::anyhow!("--restore requires a backup file"))?;
+        // Relative to where the command was run, not the data directory
+        restore_backup_file(&launch_dir.join(path).to_string_lossy())?;
+        println!("Restored backup from {}", path);
+        return Ok(());
+    }
     
-    // Load configuration
+    // Load configuration first so logging can honour its target
     let config = AppConfig::load().unwrap_or_default();
+    
+    // Initialize logging
+    init_logging(&config.logging)?;
+    
+    info!("Starting ShaydZ Super Monitor v2.0 (Rust)");
     info!("Configuration loaded successfully");
     
     // Initialize shared state
-    let monitor = Arc::new(RwLock::new(MonitorService::new(config.monitoring.clone())));
-    let threat_intel = Arc::new(RwLock::new(ThreatIntelService::new()));
+    let event_log = Arc::new(EventLog::new(&config.logging.events));
+    let auth = Arc::new(AuthService::new());
+    let monitor = Arc::new(RwLock::new(MonitorService::new(
+        config.monitoring.clone(),
+        config.alerts.clone(),
+        config.display.clone(),
+        Arc::clone(&event_log),
+        Arc::clone(&auth),
+    )));
+    let status = Arc::new(PublishedStatus::new(StatusSnapshot::capture(&*monitor.read().await)));
+    let (live_metrics, _) = broadcast::channel(LIVE_METRICS_BUFFER);
+    let threat_intel = Arc::new(RwLock::new(ThreatIntelService::new(&config.threat_intel, Arc::clone(&monitor))));
+    let public_ip = Arc::new(RwLock::new(PublicIpService::new(
+        &config.public_ip,
+        Arc::clone(&monitor),
+        Arc::clone(&event_log),
+    )));
+    let file_watch = Arc::new(RwLock::new(FileWatchService::new(&config.file_watch, Arc::clone(&monitor))));
+    let suid = Arc::new(RwLock::new(SuidScanService::new(&config.monitoring.detection, Arc::clone(&monitor))));
+    let crowdsec = Arc::new(RwLock::new(CrowdSecService::new(Arc::clone(&monitor), Arc::clone(&event_log))));
+    let reports = Arc::new(SummaryReportService::new(Arc::clone(&monitor), Arc::clone(&threat_intel)));
+    let config_service = Arc::new(ConfigService::new(
+        config,
+        Arc::clone(&monitor),
+        Arc::clone(&threat_intel),
+        Arc::clone(&public_ip),
+        Arc::clone(&file_watch),
+        Arc::clone(&suid),
+        Arc::clone(&event_log),
+    ));
+    
+    // Background loops run under a supervisor that restarts them if they die
+    let supervisor = Arc::new(Supervisor::new());
     
     // Start background monitoring task
+    let progress = Arc::new(LoopProgress::new(config_service.current().await.monitoring.update_interval));
     let monitor_clone = Arc::clone(&monitor);
-    tokio::spawn(async move {
-        background_monitor_loop(monitor_clone, config.monitoring.update_interval).await;
+    let status_clone = Arc::clone(&status);
+    let progress_clone = Arc::clone(&progress);
+    let supervisor_clone = Arc::clone(&supervisor);
+    let live_metrics_clone = live_metrics.clone();
+    supervisor.spawn("monitor", move || {
+        background_monitor_loop(
+            Arc::clone(&monitor_clone),
+            Arc::clone(&status_clone),
+            Arc::clone(&progress_clone),
+            Arc::clone(&supervisor_clone),
+            live_metrics_clone.clone(),
+        )
     });
     
     // Start threat intelligence refresh task
     let threat_intel_clone = Arc::clone(&threat_intel);
-    tokio::spawn(async move {
-        threat_intel_refresh_loop(threat_intel_clone, 1800).await;
+    supervisor.spawn("threat_intel", move || threat_intel_refresh_loop(Arc::clone(&threat_intel_clone)));
+    
+    // Start public IP tracking task
+    supervisor.spawn("public_ip", move || public_ip_loop(Arc::clone(&public_ip)));
+    
+    // Start file change watching task
+    supervisor.spawn("file_watch", move || file_watch_loop(Arc::clone(&file_watch)));
+    
+    // Start persistence scanning task
+    let persistence = Arc::new(parking_lot::Mutex::new(PersistenceWatcher::new()));
+    let monitor_clone = Arc::clone(&monitor);
+    let config_clone = Arc::clone(&config_service);
+    supervisor.spawn("persistence", move || {
+        persistence_loop(Arc::clone(&persistence), Arc::clone(&monitor_clone), Arc::clone(&config_clone))
     });
     
+    // Start SUID/SGID scanning task
+    supervisor.spawn("suid_scan", move || suid_scan_loop(Arc::clone(&suid)));
+    
+    // Start scheduled summary reports task
+    let reports_clone = Arc::clone(&reports);
+    let config_clone = Arc::clone(&config_service);
+    supervisor.spawn("summary_reports", move || {
+        summary_report_loop(Arc::clone(&reports_clone), Arc::clone(&config_clone))
+    });
+    
+    // Start CrowdSec bouncer and reporting task
+    let config_clone = Arc::clone(&config_service);
+    supervisor.spawn("crowdsec", move || crowdsec_loop(Arc::clone(&crowdsec), Arc::clone(&config_clone)));
+    
+    // Start archived metrics compaction task
+    let config_clone = Arc::clone(&config_service);
+    supervisor.spawn("storage_compaction", move || storage_compaction_loop(Arc::clone(&config_clone)));
+    
+    // Watch the configuration file for changes
+    let config_clone = Arc::clone(&config_service);
+    supervisor.spawn("config_reload", move || config_reload_loop(Arc::clone(&config_clone)));
+    
     // Create and run the web server
-    let app = create_app(monitor, threat_intel, config);
+    let app = create_app(AppState {
+        monitor: Arc::clone(&monitor),
+        status: Arc::clone(&status),
+        threat_intel,
+        auth,
+        config: config_service,
+        reports,
+        supervisor: Arc::clone(&supervisor),
+        live_metrics,
+    });
     
     let addr = SocketAddr::from(([0, 0, 0, 0], 5001));
     info!("Web server listening on http://{}", addr);
     
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    
+    // Tell systemd we're up and keep its watchdog fed while collection progresses
+    match systemd::notify("READY=1") {
+        Ok(true) => info!("Notified systemd of readiness"),
+        Ok(false) => {}
+        Err(e) => warn!("systemd notification failed: {}", e),
+    }
+    if let Some(timeout) = systemd::watchdog_timeout() {
+        info!("systemd watchdog enabled ({}s)", timeout.as_secs());
+        supervisor.spawn("watchdog", move || watchdog_loop(timeout, Arc::clone(&progress)));
+    }
+    
+    tokio::select! {
+        result = axum::serve(listener, app) => result?,
+        _ = shutdown_signal() => info!("Shutting down"),
+    }
+    
+    // Keep the metrics window for the next run
+    let mut monitor_guard = monitor.write().await;
+    if let Err(e) = monitor_guard.save_history().await {
+        warn!("Metrics history save error: {}", e);
+    }
+    if let Err(e) = monitor_guard.save_baseline().await {
+        warn!("Baseline save error: {}", e);
+    }
     
     Ok(())
 }
 
-async fn background_monitor_loop(monitor: Arc<RwLock<MonitorService>>, interval_secs: u64) {
-    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(interval_secs));
+/// Resolves on Ctrl-C or, on Unix, SIGTERM from systemd or `kill`.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+async fn background_monitor_loop(
+    monitor: Arc<RwLock<MonitorService>>,
+    status: Arc<PublishedStatus>,
+    progress: Arc<LoopProgress>,
+    supervisor: Arc<Supervisor>,
+    live_metrics: broadcast::Sender<Arc<SystemMetrics>>,
+) {
+    let mut interval_secs = monitor.read().await.update_interval();
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
     
     loop {
         interval.tick().await;
         
+        // Collect without holding the monitor, so slow collectors and checks
+        // do not hold up the dashboard and API
+        let started = Instant::now();
+        let collection = monitor.write().await.collection();
+        let collected = collection.run().await;
+        let collected_at = Instant::now();
+        
         let mut monitor_guard = monitor.write().await;
+        let locked_at = Instant::now();
         
         // Update metrics
-        if let Err(e) = monitor_guard.update().await {
+        if let Err(e) = monitor_guard.update(collected).await {
             warn!("Monitor update error: {}", e);
         }
         
@@ -72,26 +298,57 @@ async fn background_monitor_loop(monitor: Arc<RwLock<MonitorService>>, interval_
         
         // Check for anomalies
         let (anomalies, has_anomaly) = monitor_guard.detect_anomalies();
+        let current: &[String] = if has_anomaly { &anomalies } else { &[] };
+        let raised = monitor_guard.record_anomalies(current).await;
         
         if has_anomaly {
             info!("Anomalies detected: {:?}", anomalies);
-            // Trigger actions
-            if let Err(e) = monitor_guard.trigger_actions(&anomalies).await {
-                warn!("Action trigger error: {}", e);
-            }
+        }
+        // Trigger actions for anomalies raised this cycle only
+        if let Err(e) = monitor_guard.trigger_actions(&raised).await {
+            warn!("Action trigger error: {}", e);
         }
         
         // Save baseline periodically
         if let Err(e) = monitor_guard.save_baseline().await {
             warn!("Baseline save error: {}", e);
         }
+        monitor_guard.snapshot_baselines().await;
+        if monitor_guard.history_save_due() {
+            if let Err(e) = monitor_guard.save_history().await {
+                warn!("Metrics history save error: {}", e);
+            }
+        }
         
+        // Pages and status endpoints read this rather than waiting on the monitor
+        status.publish(StatusSnapshot::capture(&monitor_guard));
+        if let Some(sample) = monitor_guard.get_metrics_history().back() {
+            // Sending only fails when no client is connected
+            let _ = live_metrics.send(Arc::new(sample.clone()));
+        }
+        
+        let configured_secs = monitor_guard.update_interval();
         drop(monitor_guard);
+        
+        progress.record_cycle(configured_secs);
+        supervisor.record_cycle(CycleTiming {
+            finished_at: Utc::now(),
+            collect_ms: (collected_at - started).as_millis() as u64,
+            lock_wait_ms: (locked_at - collected_at).as_millis() as u64,
+            locked_ms: locked_at.elapsed().as_millis() as u64,
+        });
+        
+        // Pick up a changed update interval from a configuration reload
+        if configured_secs != interval_secs {
+            interval_secs = configured_secs;
+            interval = rescheduled_interval(interval_secs);
+        }
     }
 }
 
-async fn threat_intel_refresh_loop(threat_intel: Arc<RwLock<ThreatIntelService>>, interval_secs: u64) {
-    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(interval_secs));
+async fn threat_intel_refresh_loop(threat_intel: Arc<RwLock<ThreatIntelService>>) {
+    let mut interval_secs = threat_intel.read().await.refresh_interval();
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
     
     loop {
         interval.tick().await;
@@ -104,6 +361,231 @@ async fn threat_intel_refresh_loop(threat_intel: Arc<RwLock<ThreatIntelService>>
             info!("Threat intelligence updated");
         }
         
+        let configured_secs = intel_guard.refresh_interval();
         drop(intel_guard);
+        
+        if configured_secs != interval_secs {
+            interval_secs = configured_secs;
+            interval = rescheduled_interval(interval_secs);
+        }
+    }
+}
+
+async fn public_ip_loop(public_ip: Arc<RwLock<PublicIpService>>) {
+    let mut interval_secs = public_ip.read().await.interval();
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+    
+    loop {
+        interval.tick().await;
+        
+        let mut ip_guard = public_ip.write().await;
+        
+        if let Err(e) = ip_guard.refresh().await {
+            warn!("Public IP check error: {}", e);
+        }
+        
+        let configured_secs = ip_guard.interval();
+        drop(ip_guard);
+        
+        if configured_secs != interval_secs {
+            interval_secs = configured_secs;
+            interval = rescheduled_interval(interval_secs);
+        }
     }
 }
+
+async fn suid_scan_loop(suid: Arc<RwLock<SuidScanService>>) {
+    let mut interval_secs = suid.read().await.interval();
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+    
+    loop {
+        interval.tick().await;
+        
+        // The scan can take minutes, and configuration changes wait on this lock
+        let scan = suid.read().await.next_scan();
+        let result = scan.run().await;
+        
+        let mut suid_guard = suid.write().await;
+        match result {
+            Ok(()) => suid_guard.mark_published(),
+            Err(e) => warn!("SUID scan error: {}", e),
+        }
+        let configured_secs = suid_guard.interval();
+        drop(suid_guard);
+        
+        if configured_secs != interval_secs {
+            interval_secs = configured_secs;
+            interval = rescheduled_interval(interval_secs);
+        }
+    }
+}
+
+async fn persistence_loop(
+    persistence: Arc<parking_lot::Mutex<PersistenceWatcher>>,
+    monitor: Arc<RwLock<MonitorService>>,
+    config: Arc<ConfigService>,
+) {
+    let mut interval = tokio::time::interval(Duration::from_secs(PERSISTENCE_SCAN_SECS));
+    
+    loop {
+        interval.tick().await;
+        
+        let current = config.current().await;
+        if !current.monitoring.detection.persistence {
+            continue;
+        }
+        let delegated = if current.file_watch.enabled { current.file_watch.paths } else { Vec::new() };
+        
+        // Walking the directories blocks, and the monitor lock is only taken to raise alerts
+        let scanner = Arc::clone(&persistence);
+        let alerts = match tokio::task::spawn_blocking(move || scanner.lock().scan(&delegated)).await {
+            Ok(alerts) => alerts,
+            Err(e) => {
+                warn!("Persistence scan failed: {}", e);
+                continue;
+            }
+        };
+        if alerts.is_empty() {
+            continue;
+        }
+        let mut monitor = monitor.write().await;
+        for alert in alerts {
+            warn!("{}", alert);
+            monitor.raise_transient(alert);
+        }
+    }
+}
+
+async fn summary_report_loop(reports: Arc<SummaryReportService>, config: Arc<ConfigService>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(reports.interval()));
+    
+    loop {
+        interval.tick().await;
+        
+        if let Err(e) = reports.run_if_due(&config.current().await.reports).await {
+            warn!("Summary report error: {}", e);
+        }
+    }
+}
+
+async fn crowdsec_loop(crowdsec: Arc<RwLock<CrowdSecService>>, config: Arc<ConfigService>) {
+    let mut interval_secs = config.current().await.crowdsec.interval.max(10);
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+    
+    loop {
+        interval.tick().await;
+        
+        let settings = config.current().await.crowdsec;
+        crowdsec.write().await.run(&settings).await;
+        
+        // Pick up a changed poll interval from a configuration reload
+        let configured_secs = settings.interval.max(10);
+        if configured_secs != interval_secs {
+            interval_secs = configured_secs;
+            interval = rescheduled_interval(interval_secs);
+        }
+    }
+}
+
+async fn file_watch_loop(file_watch: Arc<RwLock<FileWatchService>>) {
+    let mut interval_secs = file_watch.read().await.sweep_interval();
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+    let mut watched: Vec<String> = Vec::new();
+    let mut watcher: Option<ChangeWatcher> = None;
+    
+    loop {
+        // Follow configuration changes to the watched paths
+        let roots = file_watch.read().await.roots();
+        if roots != watched {
+            watcher = match ChangeWatcher::new(&roots) {
+                Ok(watcher) => Some(watcher),
+                Err(e) => {
+                    if !roots.is_empty() {
+                        warn!("Real-time file watching unavailable, relying on sweeps: {}", e);
+                    }
+                    None
+                }
+            };
+            watched = roots;
+        }
+        
+        tokio::select! {
+            _ = interval.tick() => {
+                let mut watch_guard = file_watch.write().await;
+                watch_guard.sweep().await;
+                
+                let configured_secs = watch_guard.sweep_interval();
+                drop(watch_guard);
+                
+                if configured_secs != interval_secs {
+                    interval_secs = configured_secs;
+                    interval = rescheduled_interval(interval_secs);
+                }
+            }
+            changes = next_file_changes(watcher.as_ref()) => match changes {
+                Ok(files) => file_watch.write().await.rescan(&files).await,
+                Err(e) => {
+                    warn!("File watcher failed, relying on sweeps: {}", e);
+                    watcher = None;
+                }
+            },
+            _ = tokio::time::sleep(Duration::from_secs(FILE_WATCH_REFRESH_SECS)) => {}
+        }
+    }
+}
+
+async fn next_file_changes(watcher: Option<&ChangeWatcher>) -> anyhow::Result<Vec<std::path::PathBuf>> {
+    match watcher {
+        Some(watcher) => watcher.changes().await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn storage_compaction_loop(config: Arc<ConfigService>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(STORAGE_COMPACTION_SECS));
+    
+    loop {
+        interval.tick().await;
+        storage::compact(&config.current().await.monitoring.retention).await;
+    }
+}
+
+async fn config_reload_loop(config: Arc<ConfigService>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(CONFIG_POLL_INTERVAL_SECS));
+    
+    loop {
+        interval.tick().await;
+        config.reload_if_changed().await;
+    }
+}
+
+/// Pings the systemd watchdog at half its timeout, but only while the collection loop
+/// keeps completing cycles, so a wedged monitor gets restarted rather than reported healthy.
+async fn watchdog_loop(timeout: Duration, progress: Arc<LoopProgress>) {
+    let mut interval = tokio::time::interval(timeout / 2);
+    let mut stalled = false;
+    
+    loop {
+        interval.tick().await;
+        
+        if progress.is_stalled() {
+            if !stalled {
+                warn!("Monitoring loop has stalled; withholding systemd watchdog pings");
+                stalled = true;
+            }
+            continue;
+        }
+        stalled = false;
+        
+        if let Err(e) = systemd::notify("WATCHDOG=1") {
+            warn!("systemd watchdog ping failed: {}", e);
+        }
+    }
+}
+
+/// An interval whose first tick is one full period away, for swapping in after a
+/// configuration change without triggering an immediate extra cycle.
+fn rescheduled_interval(secs: u64) -> tokio::time::Interval {
+    let period = Duration::from_secs(secs);
+    tokio::time::interval_at(Instant::now() + period, period)
+}
@@ -1,3 +1,6 @@
+use crate::models::dashboard::DashboardLayout;
+use crate::models::metrics::{AnomalySeverity, UptimeInfo};
+use crate::utils::i18n::Language;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -7,6 +10,61 @@ pub struct User {
     pub password_hash: String,
     pub created_at: DateTime<Utc>,
     pub last_login: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub is_admin: bool,
+    /// The user's own dashboard arrangement; the default layout when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dashboard: Option<DashboardLayout>,
+    /// Language of the web pages for this user; `display.language` when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<Language>,
+    /// Which alerts this user is sent; the defaults when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notifications: Option<NotificationPreferences>,
+}
+
+/// Channels a user can choose to be notified on. Webhooks belong to the whole
+/// server and go out whatever users choose.
+pub const USER_CHANNELS: [&str; 1] = ["push"];
+
+/// The alerts a user is sent on their own channels.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotificationPreferences {
+    #[serde(default = "default_channels")]
+    pub channels: Vec<String>,
+    /// The least severe alert sent.
+    #[serde(default = "default_min_severity")]
+    pub min_severity: AnomalySeverity,
+    /// Host groups (check tags) to hear about; every alert when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            channels: default_channels(),
+            min_severity: default_min_severity(),
+            groups: Vec::new(),
+        }
+    }
+}
+
+impl NotificationPreferences {
+    /// Whether an alert of `severity` about a host in `groups` goes to `channel`.
+    pub fn wants(&self, channel: &str, severity: AnomalySeverity, groups: &[String]) -> bool {
+        self.channels.iter().any(|chosen| chosen == channel)
+            && severity >= self.min_severity
+            && (self.groups.is_empty() || self.groups.iter().any(|group| groups.contains(group)))
+    }
+}
+
+fn default_channels() -> Vec<String> {
+    vec!["push".to_string()]
+}
+
+fn default_min_severity() -> AnomalySeverity {
+    AnomalySeverity::Critical
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,6 +101,22 @@ pub struct DashboardData {
     pub anomalies: Vec<String>,
     pub has_anomaly: bool,
     pub graphs: serde_json::Value,
+    pub uptime: UptimeInfo,
+    pub hosts: Vec<HostGroup>,
+}
+
+/// Hosts shown together on the dashboard: a check group or a Proxmox cluster.
+#[derive(Debug, Clone, Serialize)]
+pub struct HostGroup {
+    pub name: String,
+    pub hosts: Vec<HostStatus>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HostStatus {
+    pub name: String,
+    pub status: String,
+    pub down: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -0,0 +1,49 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A check changing between up and down, one line of `data/availability.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvailabilityTransition {
+    pub timestamp: DateTime<Utc>,
+    pub check: String,
+    pub up: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportPeriod {
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl ReportPeriod {
+    pub fn duration(&self) -> Duration {
+        match self {
+            ReportPeriod::Day => Duration::days(1),
+            ReportPeriod::Week => Duration::days(7),
+            ReportPeriod::Month => Duration::days(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Outage {
+    pub start: DateTime<Utc>,
+    /// `None` while the check is still down.
+    pub end: Option<DateTime<Utc>>,
+    pub duration_secs: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvailabilityReport {
+    pub check: String,
+    pub period: ReportPeriod,
+    /// Share of the observed time the check was up; `None` without any observations.
+    pub uptime_percent: Option<f64>,
+    pub downtime_secs: i64,
+    /// Mean time to recovery over the outages that ended.
+    pub mttr_secs: Option<i64>,
+    pub outages: Vec<Outage>,
+}
@@ -0,0 +1,36 @@
+use crate::models::auth::User;
+use crate::models::config::AppConfig;
+use crate::models::feedback::{deserialize_feedback, FeedbackRule};
+use crate::models::metrics::BaselineStats;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// 2: feedback became a list of rules; the keyed map of version 1 is still read
+pub const BACKUP_FORMAT_VERSION: u32 = 2;
+
+/// Everything needed to move a monitor to new hardware without relearning.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateBackup {
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    pub config: AppConfig,
+    pub users: Vec<User>,
+    pub baselines: HashMap<String, BaselineStats>,
+    #[serde(default, deserialize_with = "deserialize_feedback")]
+    pub feedback: Vec<FeedbackRule>,
+}
+
+/// Learned baselines and feedback alone, for seeding another monitor so it need not
+/// start from an empty learning period.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BaselineExport {
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    /// The host the baselines were learned on.
+    #[serde(default)]
+    pub hostname: String,
+    pub baselines: HashMap<String, BaselineStats>,
+    #[serde(default, deserialize_with = "deserialize_feedback")]
+    pub feedback: Vec<FeedbackRule>,
+}
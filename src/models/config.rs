@@ -1,5 +1,20 @@
+use crate::utils::cidr::IpNet;
+use crate::utils::glob;
+use crate::utils::i18n::Language;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+/// Configuration files in the order they are looked up.
+pub const CONFIG_FILES: [&str; 4] = ["config.toml", "config.yaml", "config.yml", "config.json"];
+/// How long a built-in collector may run without a schedule saying otherwise.
+pub const DEFAULT_COLLECTOR_TIMEOUT_SECS: u64 = 10;
+/// Shortest secret accepted for URLs and endpoints that take a token instead of a session.
+pub const MIN_TOKEN_LEN: usize = 16;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
@@ -8,61 +23,1896 @@ pub struct AppConfig {
     pub display: DisplayConfig,
     pub alerts: AlertConfig,
     pub logging: LoggingConfig,
+    #[serde(default)]
+    pub threat_intel: ThreatIntelConfig,
+    #[serde(default)]
+    pub public_ip: PublicIpConfig,
+    #[serde(default)]
+    pub file_watch: FileWatchConfig,
+    #[serde(default)]
+    pub reports: SummaryReportConfig,
+    #[serde(default)]
+    pub crowdsec: CrowdSecConfig,
+    #[serde(default)]
+    pub api: ApiConfig,
+    /// Extra files merged into this one, e.g. `["conf.d/*.toml"]`. Lists such as
+    /// host checks or feeds are appended; other values override.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            monitoring: MonitoringConfig::default(),
+            security: SecurityConfig::default(),
+            display: DisplayConfig::default(),
+            alerts: AlertConfig::default(),
+            logging: LoggingConfig::default(),
+            threat_intel: ThreatIntelConfig::default(),
+            public_ip: PublicIpConfig::default(),
+            file_watch: FileWatchConfig::default(),
+            reports: SummaryReportConfig::default(),
+            crowdsec: CrowdSecConfig::default(),
+            api: ApiConfig::default(),
+            include: Vec::new(),
+        }
+    }
+}
+
+impl AppConfig {
+    pub fn load() -> Option<Self> {
+        // Try to load from config.toml, config.yaml or config.json
+        CONFIG_FILES.iter().find_map(|path| Self::load_from(path).ok())
+    }
+    
+    pub fn load_from(path: &str) -> Result<Self> {
+        let mut merged = read_config_value(Path::new(path))?;
+        
+        for file in included_files(path, &merged) {
+            let included = read_config_value(&file).map_err(|e| anyhow!("{}: {}", file.display(), e))?;
+            merge_values(&mut merged, included);
+        }
+        
+        Ok(serde_json::from_value(merged)?)
+    }
+    
+    /// The main configuration file plus every file it currently includes.
+    pub fn source_files(path: &str) -> Vec<PathBuf> {
+        let mut files = vec![PathBuf::from(path)];
+        if let Ok(main) = read_config_value(Path::new(path)) {
+            files.extend(included_files(path, &main));
+        }
+        files
+    }
+    
+    /// Writes the configuration atomically, in the format implied by the extension,
+    /// so a YAML or JSON file edited through the API keeps its format.
+    /// List entries supplied by included files are left out so they stay owned by those files.
+    pub fn save_to(&self, path: &str) -> Result<()> {
+        let mut value = serde_json::to_value(self)?;
+        for file in glob_includes(path, &self.include) {
+            if let Ok(included) = read_config_value(&file) {
+                subtract_values(&mut value, &included);
+            }
+        }
+        
+        let content = if path.ends_with(".toml") {
+            strip_nulls(&mut value);
+            toml::to_string_pretty(&value)?
+        } else if path.ends_with(".yaml") || path.ends_with(".yml") {
+            strip_nulls(&mut value);
+            let mut content = String::new();
+            yaml_rust2::YamlEmitter::new(&mut content).dump(&json_to_yaml(&value))?;
+            content.push('\n');
+            content
+        } else {
+            serde_json::to_string_pretty(&value)?
+        };
+        
+        let temp_file = format!("{}.tmp", path);
+        std::fs::write(&temp_file, content)?;
+        std::fs::rename(&temp_file, path)?;
+        
+        Ok(())
+    }
+    
+    /// Checks values that would break monitoring if applied. Returns every problem found.
+    pub fn validate(&self) -> std::result::Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        let host_pattern = regex::Regex::new(r"^[A-Za-z0-9.:\-]{1,253}$").unwrap();
+        
+        if self.monitoring.window_size < 20 || self.monitoring.window_size > 100_000 {
+            errors.push("monitoring.window_size must be between 20 and 100000".to_string());
+        }
+        if self.monitoring.update_interval == 0 {
+            errors.push("monitoring.update_interval must be at least 1 second".to_string());
+        }
+        if self.monitoring.anomaly_threshold.is_nan() || self.monitoring.anomaly_threshold <= 0.0 {
+            errors.push("monitoring.anomaly_threshold must be positive".to_string());
+        }
+        let mut check_names = std::collections::HashSet::new();
+        for check in &self.monitoring.checks {
+            if check.name.is_empty() || !check_names.insert(check.name.as_str()) {
+                errors.push(format!("monitoring.checks: duplicate or empty name '{}'", check.name));
+            }
+            let valid_target = match check.check_type {
+                CheckType::Http => check.target.starts_with("http://") || check.target.starts_with("https://"),
+                _ => host_pattern.is_match(&check.target),
+            };
+            if !valid_target {
+                errors.push(format!("monitoring.checks: invalid target '{}' for '{}'", check.target, check.name));
+            }
+            if check.check_type == CheckType::Tcp && check.port.is_none() {
+                errors.push(format!("monitoring.checks: tcp check '{}' needs a port", check.name));
+            }
+            if check.username.is_some() != check.password.is_some() {
+                errors.push(format!("monitoring.checks: '{}' needs both username and password", check.name));
+            }
+            // Sent inside SMTP and IMAP command lines, where a line break would start another command
+            let line_safe = |value: &Option<String>| value.as_deref().map_or(true, |v| !v.contains(['\r', '\n', '\0']));
+            if !line_safe(&check.username) || !line_safe(&check.password) {
+                errors.push(format!("monitoring.checks: '{}' username and password cannot contain line breaks", check.name));
+            }
+            if check.count.is_some_and(|count| count == 0 || count > 100) {
+                errors.push(format!("monitoring.checks: '{}' count must be between 1 and 100", check.name));
+            }
+            if check.timeout == 0 || check.interval == Some(0) {
+                errors.push(format!("monitoring.checks: '{}' needs a positive interval and timeout", check.name));
+            }
+            let positive = |value: Option<f64>| value.map_or(true, |v| v > 0.0);
+            if !positive(check.threshold) || !positive(check.expected_latency) {
+                errors.push(format!("monitoring.checks: '{}' threshold and expected_latency must be positive", check.name));
+            }
+        }
+        for (name, module) in &self.monitoring.probe_modules {
+            if module.timeout == 0 {
+                errors.push(format!("monitoring.probe_modules: '{}' timeout must be positive", name));
+            }
+            if let Some(Err(e)) = module.body_regex.as_deref().map(regex::Regex::new) {
+                errors.push(format!("monitoring.probe_modules: '{}' body_regex is invalid: {}", name, e));
+            }
+        }
+        for probe in &self.monitoring.probes {
+            let Some(module) = self.monitoring.probe_modules.get(&probe.module) else {
+                errors.push(format!("monitoring.probes: unknown module '{}'", probe.module));
+                continue;
+            };
+            if probe.targets.is_empty() || probe.interval == Some(0) {
+                errors.push(format!("monitoring.probes: '{}' needs targets and a positive interval", probe.module));
+            }
+            for target in &probe.targets {
+                let valid = match module.prober {
+                    ProberKind::TcpConnect | ProberKind::Tls => {
+                        split_host_port(target).is_some_and(|(host, _)| host_pattern.is_match(&host))
+                    }
+                    ProberKind::Http => target.starts_with("http://") || target.starts_with("https://"),
+                    ProberKind::Dns => host_pattern.is_match(target),
+                };
+                if !valid {
+                    errors.push(format!("monitoring.probes: invalid target '{}' for module '{}'", target, probe.module));
+                }
+            }
+        }
+        let mut probe_names = HashSet::new();
+        for check in self.monitoring.all_checks().iter().skip(self.monitoring.checks.len()) {
+            if check_names.contains(check.name.as_str()) || !probe_names.insert(check.name.clone()) {
+                errors.push(format!("monitoring.probes: '{}' is checked twice", check.name));
+            }
+        }
+        let all_checks = self.monitoring.all_checks();
+        let parent_of = |name: &str| {
+            all_checks.iter().find(|check| check.name == name).and_then(|check| check.parent.as_deref())
+        };
+        for check in &all_checks {
+            let Some(parent) = check.parent.as_deref() else {
+                continue;
+            };
+            if !all_checks.iter().any(|c| c.name == parent) {
+                errors.push(format!("monitoring.checks: '{}' has unknown parent '{}'", check.name, parent));
+                continue;
+            }
+            // Following parents from a check must end, not come back round to it
+            let mut ancestor = Some(parent);
+            for _ in 0..all_checks.len() {
+                match ancestor {
+                    Some(name) if name == check.name => {
+                        errors.push(format!("monitoring.checks: '{}' is its own ancestor", check.name));
+                        break;
+                    }
+                    Some(name) => ancestor = parent_of(name),
+                    None => break,
+                }
+            }
+        }
+        for record in &self.monitoring.dns_records {
+            if !host_pattern.is_match(&record.name) || record.name.starts_with('-') {
+                errors.push(format!("monitoring.dns_records: invalid name '{}'", record.name));
+            }
+            if record.interval.is_some_and(|interval| interval < 10) {
+                errors.push(format!("monitoring.dns_records: '{}' interval must be at least 10 seconds", record.name));
+            }
+        }
+        if self.monitoring.detection.suid_scan_interval < 3600 {
+            errors.push("monitoring.detection.suid_scan_interval must be at least 3600 seconds".to_string());
+        }
+        if self.monitoring.detection.suid_scan_paths.is_empty() {
+            // find would fall back to scanning the working directory
+            errors.push("monitoring.detection.suid_scan_paths must list at least one path".to_string());
+        }
+        if !self.monitoring.detection.suid_scan_paths.iter().all(|path| path.starts_with('/')) {
+            errors.push("monitoring.detection.suid_scan_paths must be absolute paths".to_string());
+        }
+        for entry in &self.monitoring.detection.allowlist {
+            if entry.parse::<IpNet>().is_err() {
+                errors.push(format!("monitoring.detection.allowlist: '{}' is not an address or CIDR range", entry));
+            }
+        }
+        if !self.monitoring.detection.snapshot_disk_paths.iter().all(|path| path.starts_with('/')) {
+            errors.push("monitoring.detection.snapshot_disk_paths must be absolute paths".to_string());
+        }
+        for cluster in &self.monitoring.proxmox {
+            if !cluster.url.starts_with("http://") && !cluster.url.starts_with("https://") {
+                errors.push(format!("monitoring.proxmox: '{}' url must be an http(s) URL", cluster.name));
+            }
+            if cluster.interval < 10 {
+                errors.push(format!("monitoring.proxmox: '{}' interval must be at least 10 seconds", cluster.name));
+            }
+        }
+        for pihole in &self.monitoring.pihole {
+            if !pihole.url.starts_with("http://") && !pihole.url.starts_with("https://") {
+                errors.push(format!("monitoring.pihole: '{}' url must be an http(s) URL", pihole.name));
+            }
+            if pihole.interval < 10 {
+                errors.push(format!("monitoring.pihole: '{}' interval must be at least 10 seconds", pihole.name));
+            }
+        }
+        for backup in &self.monitoring.backups {
+            if backup.path.is_empty() || backup.max_age == 0 {
+                errors.push(format!("monitoring.backups: '{}' needs a path and a positive max_age", backup.name));
+            }
+        }
+        let mut tokens = HashSet::new();
+        for heartbeat in &self.monitoring.heartbeats {
+            if heartbeat.token.len() < 16 || !heartbeat.token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                errors.push(format!("monitoring.heartbeats: '{}' token must be at least 16 letters, digits, '-' or '_'", heartbeat.name));
+            }
+            if !tokens.insert(&heartbeat.token) {
+                errors.push(format!("monitoring.heartbeats: '{}' reuses another heartbeat's token", heartbeat.name));
+            }
+            if heartbeat.period == 0 {
+                errors.push(format!("monitoring.heartbeats: '{}' period must be positive", heartbeat.name));
+            }
+        }
+        let mut rule_names = HashSet::new();
+        for rule in &self.monitoring.log_watch {
+            if !rule_names.insert(&rule.name) {
+                errors.push(format!("monitoring.log_watch: '{}' is defined twice", rule.name));
+            }
+            if rule.files.is_empty() || !rule.files.iter().all(|file| file.starts_with('/')) {
+                errors.push(format!("monitoring.log_watch: '{}' needs absolute file paths", rule.name));
+            }
+            if let Err(e) = regex::Regex::new(&rule.pattern) {
+                errors.push(format!("monitoring.log_watch: '{}' pattern is invalid: {}", rule.name, e));
+            }
+            if rule.threshold == 0 || rule.window == 0 {
+                errors.push(format!("monitoring.log_watch: '{}' threshold and window must be positive", rule.name));
+            }
+        }
+        for access_log in &self.monitoring.access_logs {
+            if access_log.files.is_empty() || !access_log.files.iter().all(|file| file.starts_with('/')) {
+                errors.push(format!("monitoring.access_logs: '{}' needs absolute file paths", access_log.name));
+            }
+            if access_log.window == 0 {
+                errors.push(format!("monitoring.access_logs: '{}' window must be positive", access_log.name));
+            }
+        }
+        let suricata = &self.monitoring.suricata;
+        if suricata.enabled {
+            if !suricata.eve_log.starts_with('/') {
+                errors.push("monitoring.suricata.eve_log must be an absolute path".to_string());
+            }
+            if suricata.window == 0 {
+                errors.push("monitoring.suricata.window must be positive".to_string());
+            }
+            if !(1..=4).contains(&suricata.min_severity) || suricata.ban_severity > 4 {
+                errors.push("monitoring.suricata: severities run from 1 (highest) to 4, and ban_severity 0 disables banning".to_string());
+            }
+        }
+        let zeek = &self.monitoring.zeek;
+        if zeek.enabled {
+            if !zeek.log_dir.starts_with('/') {
+                errors.push("monitoring.zeek.log_dir must be an absolute path".to_string());
+            }
+            if zeek.window == 0 || zeek.notice_window == 0 {
+                errors.push("monitoring.zeek: window and notice_window must be positive".to_string());
+            }
+        }
+        let capture = &self.monitoring.capture;
+        if capture.enabled {
+            if capture.interface.is_empty() || capture.interface == "any" {
+                errors.push("monitoring.capture.interface must name an interface".to_string());
+            }
+            if capture.window == 0 || capture.top == 0 {
+                errors.push("monitoring.capture: window and top must be positive".to_string());
+            }
+            if capture.prefix_v4 > 32 || capture.prefix_v6 > 128 {
+                errors.push("monitoring.capture: prefix_v4 is at most 32 and prefix_v6 at most 128".to_string());
+            }
+            for range in &capture.lan {
+                if range.parse::<IpNet>().is_err() {
+                    errors.push(format!("monitoring.capture.lan: '{}' is not an address or CIDR range", range));
+                }
+            }
+            if capture.top_talker_kb_per_s < 0.0 {
+                errors.push("monitoring.capture.top_talker_kb_per_s must not be negative".to_string());
+            }
+        }
+        let mut server_names = HashSet::new();
+        for server in &self.monitoring.web_status {
+            if !server_names.insert(&server.name) {
+                errors.push(format!("monitoring.web_status: '{}' is defined twice", server.name));
+            }
+            if !server.url.starts_with("http://") && !server.url.starts_with("https://") {
+                errors.push(format!("monitoring.web_status: '{}' url must be an http(s) URL", server.name));
+            }
+        }
+        let mut database_names = HashSet::new();
+        for database in &self.monitoring.databases {
+            if !database_names.insert(&database.name) {
+                errors.push(format!("monitoring.databases: '{}' is defined twice", database.name));
+            }
+            if database.user.is_empty() {
+                errors.push(format!("monitoring.databases: '{}' needs a user", database.name));
+            }
+            if database.interval < 10 {
+                errors.push(format!("monitoring.databases: '{}' interval must be at least 10 seconds", database.name));
+            }
+            // Each is passed to the client program, which would take them for options
+            let fields = [Some(&database.host), Some(&database.user), database.database.as_ref()];
+            if fields.into_iter().flatten().any(|field| field.starts_with('-')) {
+                errors.push(format!(
+                    "monitoring.databases: '{}' host, user and database must not start with '-'",
+                    database.name
+                ));
+            }
+        }
+        let mut cache_names = HashSet::new();
+        for cache in &self.monitoring.caches {
+            if !cache_names.insert(&cache.name) {
+                errors.push(format!("monitoring.caches: '{}' is defined twice", cache.name));
+            }
+            if cache.address.rsplit_once(':').map_or(true, |(_, port)| port.parse::<u16>().is_err()) {
+                errors.push(format!("monitoring.caches: '{}' address must be host:port", cache.name));
+            }
+        }
+        for directory in &self.monitoring.directories {
+            if !directory.path.starts_with('/') {
+                errors.push(format!("monitoring.directories: '{}' path must be absolute", directory.name));
+            }
+            if directory.interval < 60 {
+                errors.push(format!("monitoring.directories: '{}' interval must be at least 60 seconds", directory.name));
+            }
+        }
+        let mut exec_names = HashSet::new();
+        for exec in &self.monitoring.exec {
+            if exec.name.is_empty() || !exec.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                errors.push(format!("monitoring.exec: '{}' name must be letters, digits, '-' or '_'", exec.name));
+            }
+            if !exec_names.insert(&exec.name) {
+                errors.push(format!("monitoring.exec: '{}' is defined twice", exec.name));
+            }
+            if exec.command.is_empty() {
+                errors.push(format!("monitoring.exec: '{}' needs a command", exec.name));
+            }
+            if exec.interval == 0 || exec.timeout == 0 {
+                errors.push(format!("monitoring.exec: '{}' interval and timeout must be positive", exec.name));
+            }
+        }
+        let mut process_names = HashSet::new();
+        for process in &self.monitoring.processes {
+            if process.name.trim().is_empty() || !process_names.insert(&process.name) {
+                errors.push(format!("monitoring.processes: '{}' needs a unique name", process.name));
+            }
+            if process.process.is_some() == process.pidfile.is_some() {
+                errors.push(format!("monitoring.processes: '{}' needs either process or pidfile", process.name));
+            }
+            if process.action == WatchAction::Restart && process.command.is_empty() {
+                errors.push(format!("monitoring.processes: '{}' needs a command to restart it", process.name));
+            }
+            if process.restart_interval == 0 {
+                errors.push(format!("monitoring.processes: '{}' restart_interval must be positive", process.name));
+            }
+        }
+        for (name, schedule) in &self.monitoring.collector_schedules {
+            if schedule.interval == Some(0) || schedule.timeout == 0 {
+                errors.push(format!("monitoring.collector_schedules: '{}' interval and timeout must be positive", name));
+            }
+            // Collectors run within the update cycle, so they cannot run more often than it
+            if schedule.interval.is_some_and(|interval| interval < self.monitoring.update_interval) {
+                errors.push(format!(
+                    "monitoring.collector_schedules: '{}' interval must be at least monitoring.update_interval",
+                    name
+                ));
+            }
+        }
+        for (mount, percent) in &self.monitoring.disk_thresholds {
+            if !(mount.starts_with('/') && *percent > 0.0 && *percent <= 100.0) {
+                errors.push(format!("monitoring.disk_thresholds: '{}' needs an absolute path and a percentage up to 100", mount));
+            }
+        }
+        let retention = &self.monitoring.retention;
+        if retention.raw_hours > 87_600 {
+            errors.push("monitoring.retention.raw_hours must be at most 87600".to_string());
+        }
+        for (i, tier) in retention.downsample.iter().enumerate() {
+            // Averages are stored a day per file, so buckets must not straddle midnight
+            if tier.bucket_secs < 60 || 86_400 % tier.bucket_secs != 0 {
+                errors.push(format!(
+                    "monitoring.retention.downsample: bucket_secs {} must be at least 60 and divide a day",
+                    tier.bucket_secs
+                ));
+            }
+            if tier.days == 0 || tier.days > 3650 {
+                errors.push(format!("monitoring.retention.downsample: days {} must be between 1 and 3650", tier.days));
+            }
+            if retention.downsample[..i].iter().any(|other| other.bucket_secs == tier.bucket_secs) {
+                errors.push(format!("monitoring.retention.downsample: bucket_secs {} is listed twice", tier.bucket_secs));
+            }
+        }
+        if self.monitoring.kubernetes.interval < 10 {
+            errors.push("monitoring.kubernetes.interval must be at least 10 seconds".to_string());
+        }
+        for window in &self.monitoring.maintenance_windows {
+            if window.end <= window.start {
+                errors.push(format!("monitoring.maintenance_windows: '{}' ends before it starts", window.name));
+            }
+        }
+        if self.security.session_timeout == 0 {
+            errors.push("security.session_timeout must be positive".to_string());
+        }
+        if self.display.refresh_rate == 0 {
+            errors.push("display.refresh_rate must be at least 1 second".to_string());
+        }
+        if let Some(url) = &self.alerts.webhook_url {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                errors.push("alerts.webhook_url must be an http(s) URL".to_string());
+            }
+        }
+        for route in &self.alerts.routes {
+            if route.tag.trim().is_empty() {
+                errors.push("alerts.routes: every route needs a tag".to_string());
+            }
+            if let Some(url) = &route.webhook_url {
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    errors.push(format!("alerts.routes: webhook for '{}' must be an http(s) URL", route.tag));
+                }
+            }
+        }
+        for webhook in &self.alerts.anomaly_webhooks {
+            if !webhook.url.starts_with("http://") && !webhook.url.starts_with("https://") {
+                errors.push(format!("alerts.anomaly_webhooks: '{}' must be an http(s) URL", webhook.url));
+            }
+        }
+        if let Some(token) = self.alerts.ingest_token.as_deref().filter(|token| !token.is_empty()) {
+            if token.chars().count() < MIN_TOKEN_LEN {
+                errors.push(format!("alerts.ingest_token must be at least {} characters", MIN_TOKEN_LEN));
+            }
+        }
+        if let Some(contact) = &self.alerts.push_contact {
+            if !contact.starts_with("mailto:") && !contact.starts_with("https://") {
+                errors.push("alerts.push_contact must be a mailto: or https:// address".to_string());
+            }
+        }
+        for quiet in &self.alerts.quiet_hours {
+            if !QUIET_HOUR_CHANNELS.contains(&quiet.channel.as_str()) {
+                errors.push(format!("alerts.quiet_hours: channel '{}' must be webhook or push", quiet.channel));
+            }
+            if parse_clock(&quiet.start).is_none() || parse_clock(&quiet.end).is_none() {
+                errors.push(format!("alerts.quiet_hours: times for '{}' must look like 23:00", quiet.channel));
+            }
+        }
+        let flapping = &self.alerts.flapping;
+        if flapping.transitions < 2 {
+            errors.push("alerts.flapping.transitions must be at least 2".to_string());
+        }
+        if flapping.window == 0 || flapping.stable == 0 {
+            errors.push("alerts.flapping.window and stable must be positive".to_string());
+        }
+        if parse_byte_size(&self.logging.max_file_size).is_none() {
+            errors.push("logging.max_file_size must look like 10MB".to_string());
+        }
+        if parse_byte_size(&self.logging.events.max_file_size).is_none() {
+            errors.push("logging.events.max_file_size must look like 5MB".to_string());
+        }
+        if self.threat_intel.refresh_interval < 60 {
+            errors.push("threat_intel.refresh_interval must be at least 60 seconds".to_string());
+        }
+        if self.public_ip.interval < 60 {
+            errors.push("public_ip.interval must be at least 60 seconds".to_string());
+        }
+        let http_url = |url: &String| url.starts_with("http://") || url.starts_with("https://");
+        if !self.public_ip.services.iter().all(http_url) || !self.public_ip.ddns_update_url.iter().all(http_url) {
+            errors.push("public_ip: services and ddns_update_url must be http(s) URLs".to_string());
+        }
+        if self.public_ip.dnsbl_interval < 300 {
+            errors.push("public_ip.dnsbl_interval must be at least 300 seconds".to_string());
+        }
+        for zone in &self.public_ip.dnsbl {
+            if !host_pattern.is_match(zone) || zone.starts_with('-') {
+                errors.push(format!("public_ip.dnsbl: invalid zone '{}'", zone));
+            }
+        }
+        if self.reports.hour > 23 {
+            errors.push("reports.hour must be between 0 and 23".to_string());
+        }
+        // Addresses become mail headers, where a line break would add headers of its own
+        let header_safe = |value: &str| !value.contains(['\r', '\n']);
+        for address in &self.reports.email {
+            if !address.contains('@') || !header_safe(address) {
+                errors.push(format!("reports.email: '{}' is not an email address", address.escape_debug()));
+            }
+        }
+        if self.reports.from.as_deref().is_some_and(|from| !from.contains('@') || !header_safe(from)) {
+            errors.push("reports.from is not an email address".to_string());
+        }
+        if self.file_watch.sweep_interval < 60 {
+            errors.push("file_watch.sweep_interval must be at least 60 seconds".to_string());
+        }
+        for path in &self.file_watch.paths {
+            if !path.starts_with('/') {
+                errors.push(format!("file_watch.paths: '{}' must be an absolute path", path));
+            }
+        }
+        for feed in &self.threat_intel.feeds {
+            if feed.name.trim().is_empty() || (!feed.url.starts_with("https://") && !feed.url.starts_with("http://")) {
+                errors.push(format!("threat_intel.feeds: invalid feed '{}'", feed.name));
+            }
+        }
+        if self.crowdsec.enabled {
+            let crowdsec = &self.crowdsec;
+            if !crowdsec.url.starts_with("http://") && !crowdsec.url.starts_with("https://") {
+                errors.push("crowdsec.url must be an http(s) URL".to_string());
+            }
+            if crowdsec.interval < 10 {
+                errors.push("crowdsec.interval must be at least 10 seconds".to_string());
+            }
+            if crowdsec.machine_id.is_some() != crowdsec.password.is_some() {
+                errors.push("crowdsec: machine_id and password must be set together".to_string());
+            }
+            if crowdsec.api_key.is_none() && crowdsec.machine_id.is_none() {
+                errors.push("crowdsec: set api_key to block bans, or machine_id and password to report detections".to_string());
+            }
+            if !regex::Regex::new(r"^(\d+[hms])+$").unwrap().is_match(&crowdsec.ban_duration) {
+                errors.push("crowdsec.ban_duration must look like 4h or 1h30m".to_string());
+            }
+        }
+        for list in &self.threat_intel.blocklists {
+            if list.name.trim().is_empty() || (!list.url.starts_with("https://") && !list.url.starts_with("http://")) {
+                errors.push(format!("threat_intel.blocklists: invalid list '{}'", list.name));
+            }
+        }
+        
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+    
+    /// The configuration file `load` reads from, if one exists.
+    pub fn source_path() -> Option<&'static str> {
+        CONFIG_FILES.iter().copied().find(|path| Path::new(path).exists())
+    }
+    
+    /// Lists every setting that differs from `other` as "section.key: old -> new".
+    /// Values of secret-looking keys are not included.
+    pub fn diff(&self, other: &AppConfig) -> Vec<String> {
+        let mut changes = Vec::new();
+        
+        match (serde_json::to_value(self), serde_json::to_value(other)) {
+            (Ok(old), Ok(new)) => collect_changes("", &old, &new, &mut changes),
+            (Err(e), _) | (_, Err(e)) => changes.push(format!("configuration not comparable: {}", e)),
+        }
+        
+        changes
+    }
+}
+
+fn read_config_value(path: &Path) -> Result<Value> {
+    let content = std::fs::read_to_string(path)?;
+    
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => Ok(serde_json::from_str(&content)?),
+        Some("yaml") | Some("yml") => {
+            let documents = yaml_rust2::YamlLoader::load_from_str(&content)?;
+            Ok(documents.first().map(yaml_to_json).unwrap_or(Value::Null))
+        }
+        _ => Ok(toml::from_str(&content)?),
+    }
+}
+
+// Parsed by hand rather than through the `config` crate, which lowercases every key
+fn yaml_to_json(yaml: &yaml_rust2::Yaml) -> Value {
+    use yaml_rust2::Yaml;
+    
+    match yaml {
+        Yaml::Real(_) => yaml.as_f64().map(Value::from).unwrap_or(Value::Null),
+        Yaml::Integer(i) => Value::from(*i),
+        Yaml::String(s) => Value::from(s.clone()),
+        Yaml::Boolean(b) => Value::from(*b),
+        Yaml::Array(items) => Value::Array(items.iter().map(yaml_to_json).collect()),
+        Yaml::Hash(map) => Value::Object(
+            map.iter()
+                .filter_map(|(key, value)| {
+                    let key = match key {
+                        Yaml::String(s) => s.clone(),
+                        Yaml::Integer(i) => i.to_string(),
+                        Yaml::Boolean(b) => b.to_string(),
+                        _ => return None,
+                    };
+                    Some((key, yaml_to_json(value)))
+                })
+                .collect(),
+        ),
+        Yaml::Alias(_) | Yaml::Null | Yaml::BadValue => Value::Null,
+    }
+}
+
+fn json_to_yaml(value: &Value) -> yaml_rust2::Yaml {
+    use yaml_rust2::Yaml;
+    
+    match value {
+        Value::Null => Yaml::Null,
+        Value::Bool(b) => Yaml::Boolean(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Yaml::Integer(i),
+            None => Yaml::Real(n.to_string()),
+        },
+        Value::String(s) => Yaml::String(s.clone()),
+        Value::Array(items) => Yaml::Array(items.iter().map(json_to_yaml).collect()),
+        Value::Object(map) => Yaml::Hash(
+            map.iter()
+                .map(|(key, value)| (Yaml::String(key.clone()), json_to_yaml(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// TOML has no null, so unset optional values are dropped before writing it, and
+/// YAML gets the same so both read alike.
+fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, v| !v.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+fn included_files(path: &str, main: &Value) -> Vec<PathBuf> {
+    let patterns: Vec<String> = main
+        .get("include")
+        .and_then(|include| serde_json::from_value(include.clone()).ok())
+        .unwrap_or_default();
+    glob_includes(path, &patterns)
+}
+
+fn glob_includes(path: &str, patterns: &[String]) -> Vec<PathBuf> {
+    let base = Path::new(path).parent().unwrap_or(Path::new(""));
+    patterns.iter().flat_map(|pattern| glob::expand(base, pattern)).collect()
+}
+
+/// Deep-merges `overlay` into `base`: tables merge recursively, lists gain the
+/// entries they don't already contain, anything else is replaced.
+fn merge_values(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base_map), Value::Object(overlay_map)) => {
+            for (key, value) in overlay_map {
+                match base_map.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        base_map.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(base_list), Value::Array(overlay_list)) => {
+            for item in overlay_list {
+                if !base_list.contains(&item) {
+                    base_list.push(item);
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Removes list entries contributed by `included` from `value`, undoing `merge_values` for lists.
+fn subtract_values(value: &mut Value, included: &Value) {
+    match (value, included) {
+        (Value::Object(map), Value::Object(included_map)) => {
+            for (key, included_value) in included_map {
+                if let Some(existing) = map.get_mut(key) {
+                    subtract_values(existing, included_value);
+                }
+            }
+        }
+        (Value::Array(list), Value::Array(included_list)) => {
+            list.retain(|item| !included_list.contains(item));
+        }
+        _ => {}
+    }
+}
+
+fn collect_changes(path: &str, old: &Value, new: &Value, changes: &mut Vec<String>) {
+    match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            let keys: BTreeSet<&String> = old_map.keys().chain(new_map.keys()).collect();
+            for key in keys {
+                let child = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                collect_changes(
+                    &child,
+                    old_map.get(key).unwrap_or(&Value::Null),
+                    new_map.get(key).unwrap_or(&Value::Null),
+                    changes,
+                );
+            }
+        }
+        _ if old == new => {}
+        _ if ["password", "secret", "token", "api_key", "ddns"].iter().any(|s| path.contains(s)) => {
+            changes.push(format!("{}: changed", path));
+        }
+        _ => changes.push(format!("{}: {} -> {}", path, old, new)),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitoringConfig {
+    pub window_size: usize,
+    pub update_interval: u64,
+    /// How long samples archived in `data/metrics/` are kept, for querying past
+    /// time ranges through `/api/metrics?from=&to=`.
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    pub anomaly_threshold: f64,
+    /// How the system metrics are compared with their learned normal.
+    #[serde(default)]
+    pub detector: Detector,
+    /// Availability checks. Plain host strings (the old `monitored_hosts` list) are ICMP checks.
+    #[serde(alias = "monitored_hosts")]
+    pub checks: Vec<CheckConfig>,
+    /// Named prober settings, shared by the targets in `probes`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub probe_modules: BTreeMap<String, ProbeModule>,
+    /// Targets checked with a probe module; each is reported like a check named
+    /// `<module>:<target>`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub probes: Vec<ProbeConfig>,
+    /// Planned downtime; reboots and failing checks inside a window are not reported.
+    #[serde(default)]
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+    /// DNS records watched for unexpected changes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dns_records: Vec<DnsRecordConfig>,
+    /// Host intrusion detectors run alongside the metrics.
+    #[serde(default)]
+    pub detection: DetectionConfig,
+    /// Attribute network traffic to processes so bandwidth anomalies name the busiest one.
+    /// Samples `ss` every cycle; traffic of other users' processes needs root.
+    #[serde(default)]
+    pub process_network: bool,
+    /// Collectors switched on or off by name, e.g. `temperature = false`. Unlisted
+    /// collectors are on.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub collectors: BTreeMap<String, bool>,
+    /// How often built-in collectors run and how long they may take, by name, e.g.
+    /// `temperature = { interval = 60 }`. Unlisted collectors run every cycle.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub collector_schedules: BTreeMap<String, CollectorSchedule>,
+    /// Percent of space in use above which a mount point is reported, by mount
+    /// point, e.g. `"/boot" = 80.0`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub disk_thresholds: BTreeMap<String, f64>,
+    /// Cluster health, collected when kubeconfig or in-cluster credentials are found.
+    #[serde(default)]
+    pub kubernetes: KubernetesConfig,
+    /// Containers of the local Docker engine, collected when its socket exists.
+    #[serde(default)]
+    pub docker: DockerConfig,
+    /// Proxmox VE clusters whose nodes and guests join the dashboard's hosts.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub proxmox: Vec<ProxmoxConfig>,
+    /// Pi-hole instances whose query and blocking statistics are collected.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pihole: Vec<PiholeConfig>,
+    /// Backups that must keep being written.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub backups: Vec<BackupConfig>,
+    /// Jobs that report in through `/api/heartbeat/<token>`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub heartbeats: Vec<HeartbeatConfig>,
+    /// Patterns watched for in log files.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub log_watch: Vec<LogWatchRule>,
+    /// Web server access logs analysed for traffic spikes and scanners.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub access_logs: Vec<AccessLogConfig>,
+    /// Suricata alerts read from its EVE JSON log.
+    #[serde(default)]
+    pub suricata: SuricataConfig,
+    /// Connection statistics and notices from a Zeek sensor's logs.
+    #[serde(default)]
+    pub zeek: ZeekConfig,
+    /// Traffic summarized from packet headers on one interface.
+    #[serde(default)]
+    pub capture: CaptureConfig,
+    /// nginx, Apache and PHP-FPM status pages.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub web_status: Vec<WebStatusConfig>,
+    /// MySQL and PostgreSQL servers checked for reachability, connections and replication lag.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub databases: Vec<DatabaseConfig>,
+    /// Redis and Memcached instances checked for latency, memory, evictions and hit ratio.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub caches: Vec<CacheConfig>,
+    /// Directories whose size and growth are tracked.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub directories: Vec<DirectoryConfig>,
+    /// Commands whose output is collected as custom metrics. Only read from the
+    /// configuration file; changes submitted through the API are ignored.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exec: Vec<ExecMetricConfig>,
+    /// Daemons that must keep running, and what to do when one is not. Only read
+    /// from the configuration file, as they can run commands.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub processes: Vec<WatchedProcessConfig>,
+}
+
+impl Default for MonitoringConfig {
+    fn default() -> Self {
+        Self {
+            window_size: 60,
+            update_interval: 5,
+            retention: RetentionConfig::default(),
+            anomaly_threshold: 3.0,
+            detector: Detector::default(),
+            checks: vec![
+                CheckConfig::icmp("8.8.8.8"),
+                CheckConfig::icmp("1.1.1.1"),
+            ],
+            probe_modules: BTreeMap::new(),
+            probes: Vec::new(),
+            maintenance_windows: Vec::new(),
+            dns_records: Vec::new(),
+            detection: DetectionConfig::default(),
+            process_network: false,
+            collectors: BTreeMap::new(),
+            collector_schedules: BTreeMap::new(),
+            disk_thresholds: BTreeMap::new(),
+            kubernetes: KubernetesConfig::default(),
+            docker: DockerConfig::default(),
+            proxmox: Vec::new(),
+            pihole: Vec::new(),
+            backups: Vec::new(),
+            heartbeats: Vec::new(),
+            log_watch: Vec::new(),
+            access_logs: Vec::new(),
+            suricata: SuricataConfig::default(),
+            zeek: ZeekConfig::default(),
+            capture: CaptureConfig::default(),
+            web_status: Vec::new(),
+            databases: Vec::new(),
+            caches: Vec::new(),
+            directories: Vec::new(),
+            exec: Vec::new(),
+            processes: Vec::new(),
+        }
+    }
+}
+
+impl MonitoringConfig {
+    /// The configured checks followed by one check per probe target.
+    pub fn all_checks(&self) -> Vec<CheckConfig> {
+        let mut checks = self.checks.clone();
+        for probe in &self.probes {
+            // Unknown modules and malformed targets were reported by validation
+            let Some(module) = self.probe_modules.get(&probe.module) else {
+                continue;
+            };
+            checks.extend(probe.targets.iter().filter_map(|target| probe.check(module, target)));
+        }
+        checks
+    }
+}
+
+/// The tool blocked addresses are dropped with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Firewall {
+    /// `iptables` for IPv4 and `ip6tables` for IPv6 addresses.
+    #[default]
+    Iptables,
+    /// Rules in an `inet` table of their own, covering both families.
+    Nftables,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DetectionConfig {
+    /// Watch cron, systemd units, init scripts and shell profiles for new entries.
+    pub persistence: bool,
+    /// Watch `/etc/passwd`, `/etc/group` and `/etc/shadow` for account changes.
+    pub accounts: bool,
+    /// Groups whose membership grants root or equivalent.
+    pub sensitive_groups: Vec<String>,
+    /// Watch every account's SSH `authorized_keys`.
+    pub authorized_keys: bool,
+    /// Look for SUID/SGID binaries outside the known-good baseline.
+    pub suid_scan: bool,
+    pub suid_scan_interval: u64,
+    /// Where to search; each path is searched without crossing into other filesystems.
+    pub suid_scan_paths: Vec<String>,
+    /// Alert when a port starts listening that was not listening before.
+    pub listening_ports: bool,
+    /// Ports that may come and go without an alert.
+    pub ignore_ports: Vec<u16>,
+    /// Watch the kernel log for OOM kills, I/O errors, read-only remounts and USB resets.
+    pub kernel_log: bool,
+    /// Block clients that access logs show probing for pages.
+    pub ban_web_scanners: bool,
+    /// Failed SSH logins from one address within five minutes that make it a brute-force
+    /// attempt; 0 disables the check.
+    pub ssh_brute_force: u32,
+    /// How blocked addresses are dropped.
+    pub firewall: Firewall,
+    /// Addresses and CIDR ranges that are never blocked or reported as threats.
+    pub allowlist: Vec<String>,
+    /// Capture processes, connections, the kernel log and disk usage when a critical
+    /// anomaly opens an incident.
+    pub snapshot_on_critical: bool,
+    /// Directories whose immediate subdirectories are sized in those snapshots.
+    pub snapshot_disk_paths: Vec<String>,
+}
+
+impl Default for DetectionConfig {
+    fn default() -> Self {
+        Self {
+            persistence: true,
+            accounts: true,
+            sensitive_groups: ["root", "sudo", "wheel", "admin", "adm", "docker", "lxd", "libvirt", "disk", "shadow"]
+                .iter()
+                .map(|group| group.to_string())
+                .collect(),
+            authorized_keys: true,
+            suid_scan: true,
+            suid_scan_interval: 86400,
+            suid_scan_paths: vec!["/".to_string()],
+            listening_ports: true,
+            ignore_ports: Vec::new(),
+            kernel_log: true,
+            ban_web_scanners: false,
+            ssh_brute_force: 10,
+            firewall: Firewall::default(),
+            allowlist: Vec::new(),
+            snapshot_on_critical: true,
+            snapshot_disk_paths: ["/var", "/home", "/tmp", "/opt", "/srv"].iter().map(|path| path.to_string()).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KubernetesConfig {
+    pub enabled: bool,
+    pub interval: u64,
+    /// Kubeconfig to use instead of `$KUBECONFIG`, `~/.kube/config` or the pod's service account.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kubeconfig: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    /// How long a pod may stay pending before it is reported.
+    pub pending_after: u64,
+}
+
+impl Default for KubernetesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: 60,
+            kubeconfig: None,
+            context: None,
+            pending_after: 300,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DockerConfig {
+    /// The Engine API socket; the monitor's user needs to be in the `docker` group.
+    pub socket: String,
+}
+
+impl Default for DockerConfig {
+    fn default() -> Self {
+        Self {
+            socket: "/var/run/docker.sock".to_string(),
+        }
+    }
+}
+
+/// A Proxmox VE API endpoint, read with an API token (Datacenter > Permissions > API Tokens;
+/// the PVEAuditor role is enough).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProxmoxConfig {
+    pub name: String,
+    /// e.g. `https://pve.lan:8006`
+    pub url: String,
+    /// `user@realm!tokenid`
+    pub token_id: String,
+    pub token_secret: String,
+    #[serde(default = "default_proxmox_interval")]
+    pub interval: u64,
+    /// Proxmox installs with a self-signed certificate; set to false to accept it.
+    #[serde(default = "default_verify_tls")]
+    pub verify_tls: bool,
+}
+
+fn default_proxmox_interval() -> u64 {
+    60
+}
+
+fn default_verify_tls() -> bool {
+    true
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PiholeConfig {
+    pub name: String,
+    /// e.g. `http://pi.hole`
+    pub url: String,
+    /// Pi-hole v6 app password, or the v5 API token; empty when none is set.
+    #[serde(default)]
+    pub password: String,
+    #[serde(default = "default_pihole_interval")]
+    pub interval: u64,
+}
+
+fn default_pihole_interval() -> u64 {
+    60
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Detector {
+    /// Each metric on its own, by how many standard deviations it is from its mean.
+    #[default]
+    Zscore,
+    /// CPU, RAM, disk, temperature, ping, connections, bandwidth and failed logins
+    /// together, by their Mahalanobis distance from normal. Catches combinations
+    /// that are individually normal but jointly unusual.
+    Mahalanobis,
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckType {
+    #[default]
+    Icmp,
+    Tcp,
+    Http,
+    Dns,
+    /// SMTP handshake: banner, EHLO, STARTTLS and optionally AUTH.
+    Smtp,
+    /// IMAP handshake: greeting, STARTTLS and optionally LOGIN.
+    Imap,
+}
+
+/// One availability check against a host. A host may have several checks, each with
+/// its own schedule; results are keyed by `name`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "CheckEntry")]
+pub struct CheckConfig {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub check_type: CheckType,
+    pub target: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// Seconds between runs; defaults to `update_interval`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u64>,
+    /// Seconds before the check counts as failed.
+    pub timeout: u64,
+    /// ICMP echo requests per run, used to measure loss and jitter (default 5).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+    /// HTTP: status code or body text; DNS: an address the name must resolve to;
+    /// TCP, SMTP and IMAP: text the service banner must contain.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expect: Option<String>,
+    /// SMTP and IMAP: log in with these credentials to test authentication. Only
+    /// sent over TLS.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// Accept self-signed and otherwise unverifiable certificates when false.
+    #[serde(skip_serializing_if = "is_true")]
+    pub verify_tls: bool,
+    /// Groups such as "network", "servers" or "iot", used for dashboard grouping,
+    /// alert routing and maintenance windows.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Overrides `monitoring.anomaly_threshold` for this check's latency.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f64>,
+    /// Latency in milliseconds above which the check is anomalous, whatever its baseline.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_latency: Option<f64>,
+    /// The target may go offline without raising "Device Down" (e.g. a sleeping laptop).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_down: bool,
+    /// The check this one depends on, such as the switch in front of a host. When both
+    /// are down the failures are grouped into one incident under the parent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// Set on checks expanded from `monitoring.probes`, which run this prober instead.
+    #[serde(skip)]
+    pub probe: Option<ProbeModule>,
+}
+
+const DEFAULT_CHECK_TIMEOUT: u64 = 2;
+
+impl CheckConfig {
+    pub fn icmp(host: &str) -> Self {
+        Self {
+            name: host.to_string(),
+            check_type: CheckType::Icmp,
+            target: host.to_string(),
+            port: None,
+            interval: None,
+            timeout: DEFAULT_CHECK_TIMEOUT,
+            count: None,
+            expect: None,
+            username: None,
+            password: None,
+            verify_tls: true,
+            tags: Vec::new(),
+            threshold: None,
+            expected_latency: None,
+            allow_down: false,
+            parent: None,
+            probe: None,
+        }
+    }
+    
+    /// The host the check talks to, e.g. for running a traceroute towards it.
+    pub fn host(&self) -> Option<String> {
+        match self.check_type {
+            CheckType::Http => reqwest::Url::parse(&self.target)
+                .ok()
+                .and_then(|url| url.host_str().map(|h| h.trim_matches(|c| c == '[' || c == ']').to_string())),
+            _ => Some(self.target.clone()),
+        }
+    }
+    
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CheckEntry {
+    Host(String),
+    Check(Box<CheckFields>),
+}
+
+#[derive(Deserialize)]
+struct CheckFields {
+    name: Option<String>,
+    #[serde(rename = "type", default)]
+    check_type: CheckType,
+    target: String,
+    port: Option<u16>,
+    interval: Option<u64>,
+    timeout: Option<u64>,
+    count: Option<u32>,
+    expect: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+    #[serde(default = "default_verify_tls")]
+    verify_tls: bool,
+    #[serde(default)]
+    tags: Vec<String>,
+    threshold: Option<f64>,
+    expected_latency: Option<f64>,
+    #[serde(default)]
+    allow_down: bool,
+    parent: Option<String>,
+}
+
+impl From<CheckEntry> for CheckConfig {
+    fn from(entry: CheckEntry) -> Self {
+        match entry {
+            CheckEntry::Host(host) => CheckConfig::icmp(&host),
+            CheckEntry::Check(fields) => {
+                let CheckFields {
+                    name,
+                    check_type,
+                    target,
+                    port,
+                    interval,
+                    timeout,
+                    count,
+                    expect,
+                    username,
+                    password,
+                    verify_tls,
+                    tags,
+                    threshold,
+                    expected_latency,
+                    allow_down,
+                    parent,
+                } = *fields;
+                
+                // ICMP checks keep the bare host as their name so existing baselines still apply
+                let name = name.unwrap_or_else(|| match (check_type, port) {
+                    (CheckType::Icmp, _) | (CheckType::Http, _) => target.clone(),
+                    (CheckType::Tcp, Some(port)) => format!("{}:{}", target, port),
+                    (CheckType::Tcp, None) => format!("tcp:{}", target),
+                    (CheckType::Dns, _) => format!("dns:{}", target),
+                    (CheckType::Smtp, _) => format!("smtp:{}", target),
+                    (CheckType::Imap, _) => format!("imap:{}", target),
+                });
+                
+                CheckConfig {
+                    name,
+                    check_type,
+                    target,
+                    port,
+                    interval,
+                    timeout: timeout.unwrap_or(DEFAULT_CHECK_TIMEOUT),
+                    count,
+                    expect,
+                    username,
+                    password,
+                    verify_tls,
+                    tags,
+                    threshold,
+                    expected_latency,
+                    allow_down,
+                    parent,
+                    probe: None,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProberKind {
+    /// Connects to `host:port`.
+    TcpConnect,
+    /// Connects to `host:port` and completes a TLS handshake.
+    Tls,
+    /// Fetches a URL.
+    Http,
+    /// Resolves a name.
+    Dns,
+}
+
+/// How a probe target is checked, in the manner of a blackbox exporter module.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProbeModule {
+    pub prober: ProberKind,
+    #[serde(default = "default_probe_timeout")]
+    pub timeout: u64,
+    /// TLS and HTTP: accept self-signed and otherwise unverifiable certificates when false.
+    #[serde(default = "default_verify_tls")]
+    pub verify_tls: bool,
+    /// HTTP: status codes that count as up; any 2xx when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub valid_status: Vec<u16>,
+    /// HTTP: a regular expression the response body must match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_regex: Option<String>,
+    /// DNS: addresses the name must resolve to, all of them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expect_answers: Vec<IpAddr>,
+}
+
+fn default_probe_timeout() -> u64 {
+    5
+}
+
+/// Targets checked with one probe module.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProbeConfig {
+    pub module: String,
+    /// `host:port` for TCP and TLS, a URL for HTTP and a name for DNS.
+    pub targets: Vec<String>,
+    /// Seconds between runs; defaults to `update_interval`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_down: bool,
+    /// The check every target depends on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
 }
 
-impl Default for AppConfig {
+impl ProbeConfig {
+    /// The check standing for one target, or `None` when the target does not suit
+    /// the module's prober.
+    fn check(&self, module: &ProbeModule, target: &str) -> Option<CheckConfig> {
+        let (check_type, host, port) = match module.prober {
+            ProberKind::TcpConnect | ProberKind::Tls => {
+                let (host, port) = split_host_port(target)?;
+                (CheckType::Tcp, host, Some(port))
+            }
+            ProberKind::Http => (CheckType::Http, target.to_string(), None),
+            ProberKind::Dns => (CheckType::Dns, target.to_string(), None),
+        };
+        
+        Some(CheckConfig {
+            name: format!("{}:{}", self.module, target),
+            check_type,
+            target: host,
+            port,
+            interval: self.interval,
+            timeout: module.timeout,
+            count: None,
+            expect: None,
+            username: None,
+            password: None,
+            verify_tls: module.verify_tls,
+            tags: self.tags.clone(),
+            threshold: None,
+            expected_latency: None,
+            allow_down: self.allow_down,
+            parent: self.parent.clone(),
+            probe: Some(module.clone()),
+        })
+    }
+}
+
+/// Splits `host:port` or `[v6 address]:port`.
+fn split_host_port(target: &str) -> Option<(String, u16)> {
+    let (host, port) = target.rsplit_once(':')?;
+    let host = host.strip_prefix('[').and_then(|host| host.strip_suffix(']')).unwrap_or(host);
+    Some((host.to_string(), port.parse().ok()?))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum DnsRecordType {
+    #[default]
+    A,
+    Aaaa,
+    Cname,
+    Mx,
+    Txt,
+    Ns,
+}
+
+impl std::fmt::Display for DnsRecordType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            DnsRecordType::A => "A",
+            DnsRecordType::Aaaa => "AAAA",
+            DnsRecordType::Cname => "CNAME",
+            DnsRecordType::Mx => "MX",
+            DnsRecordType::Txt => "TXT",
+            DnsRecordType::Ns => "NS",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A DNS record to watch. With `expected` values any other answer is an alert;
+/// without them the first answer seen is learned and later changes are reported.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DnsRecordConfig {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub record_type: DnsRecordType,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expected: Vec<String>,
+    /// Seconds between lookups (default 300).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupKind {
+    /// Files matching `path`, or the entries of a directory.
+    #[default]
+    Path,
+    Restic,
+    Borg,
+}
+
+/// A backup whose newest artifact must be younger than `max_age`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupConfig {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub kind: BackupKind,
+    /// A file pattern such as `/mnt/backup/db-*.sql.gz`, a directory, or the repository.
+    pub path: String,
+    /// Seconds since the newest backup before it is reported as stale.
+    pub max_age: u64,
+    /// Bytes the newest backup must at least contain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_size: Option<u64>,
+    /// Repository passphrase file for restic and borg.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_file: Option<String>,
+    /// Seconds between checks; paths default to every cycle, repositories to hourly.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u64>,
+}
+
+/// A cron job or script expected to request its heartbeat URL every `period` seconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeartbeatConfig {
+    pub name: String,
+    /// The secret part of the URL.
+    pub token: String,
+    pub period: u64,
+    /// Extra seconds allowed before a late heartbeat is reported.
+    #[serde(default = "default_heartbeat_grace")]
+    pub grace: u64,
+}
+
+fn default_heartbeat_grace() -> u64 {
+    300
+}
+
+/// How long archived samples are kept, first whole and then as averages.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    /// Hours every sample is kept; 0 archives nothing. Samples are compacted a
+    /// day at a time, once the whole day is older than this.
+    #[serde(default = "default_raw_hours")]
+    pub raw_hours: u64,
+    /// Averages the samples are compacted into, each kept for its own number of days.
+    #[serde(default = "default_downsample")]
+    pub downsample: Vec<DownsampleTier>,
+}
+
+impl Default for RetentionConfig {
     fn default() -> Self {
         Self {
-            monitoring: MonitoringConfig::default(),
-            security: SecurityConfig::default(),
-            display: DisplayConfig::default(),
-            alerts: AlertConfig::default(),
-            logging: LoggingConfig::default(),
+            raw_hours: default_raw_hours(),
+            downsample: default_downsample(),
         }
     }
 }
 
-impl AppConfig {
-    pub fn load() -> Option<Self> {
-        // Try to load from config.toml or config.json
-        if let Ok(content) = std::fs::read_to_string("config.toml") {
-            if let Ok(config) = toml::from_str(&content) {
-                return Some(config);
-            }
+fn default_raw_hours() -> u64 {
+    24
+}
+
+fn default_downsample() -> Vec<DownsampleTier> {
+    vec![DownsampleTier { bucket_secs: 300, days: 30 }]
+}
+
+/// Averages over `bucket_secs`, kept for `days`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DownsampleTier {
+    pub bucket_secs: u64,
+    pub days: u64,
+}
+
+/// When a built-in collector runs. Its latest readings are reported in between.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CollectorSchedule {
+    /// Seconds between runs, no shorter than `update_interval`; every update cycle
+    /// when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u64>,
+    /// Seconds before a run is abandoned and the collector reported as failing.
+    #[serde(default = "default_collector_timeout")]
+    pub timeout: u64,
+}
+
+/// A command whose output is collected as a metric named after it. Output that is a
+/// number (or starts with one) is the value; a JSON object contributes each numeric
+/// field as `<name>.<field>`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecMetricConfig {
+    pub name: String,
+    /// Program and arguments, run directly rather than through a shell.
+    pub command: Vec<String>,
+    /// Seconds between runs; the latest values are reported in between.
+    #[serde(default = "default_exec_interval")]
+    pub interval: u64,
+    /// Seconds before the command is killed.
+    #[serde(default = "default_exec_timeout")]
+    pub timeout: u64,
+    /// Dotted path to the value in JSON output, e.g. `queues.mail.depth`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// Limits beyond which a value is always anomalous, whatever the baseline.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+}
+
+/// What is done about a watched process that is not running.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchAction {
+    #[default]
+    Alert,
+    /// Alert and run the process's `command`.
+    Restart,
+}
+
+/// A daemon that must keep running, found by process name or by the pid in a pidfile.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchedProcessConfig {
+    pub name: String,
+    /// Process name as in `/proc/<pid>/comm`, e.g. `mosquitto`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pidfile: Option<String>,
+    #[serde(default)]
+    pub action: WatchAction,
+    /// Program and arguments that start the process again, run directly rather
+    /// than through a shell, e.g. `["sudo", "-n", "systemctl", "restart", "mosquitto"]`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub command: Vec<String>,
+    /// Seconds between restart attempts while the process stays down.
+    #[serde(default = "default_restart_interval")]
+    pub restart_interval: u64,
+    /// Attempts after which restarting is given up until the process runs again;
+    /// 0 keeps trying.
+    #[serde(default = "default_max_restarts")]
+    pub max_restarts: u32,
+}
+
+fn default_restart_interval() -> u64 {
+    60
+}
+
+fn default_max_restarts() -> u32 {
+    5
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogSeverity {
+    Critical,
+    #[default]
+    Warning,
+    Info,
+}
+
+/// A pattern searched for in lines appended to log files. Reaching `threshold`
+/// matches within `window` seconds is an anomaly.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogWatchRule {
+    pub name: String,
+    /// Log files; wildcards are allowed in the file name, e.g. `/var/log/nginx/*.log`.
+    pub files: Vec<String>,
+    /// Regular expression matched against each new line.
+    pub pattern: String,
+    #[serde(default)]
+    pub severity: LogSeverity,
+    #[serde(default = "default_log_threshold")]
+    pub threshold: u32,
+    #[serde(default = "default_log_window")]
+    pub window: u64,
+}
+
+fn default_log_threshold() -> u32 {
+    1
+}
+
+fn default_log_window() -> u64 {
+    300
+}
+
+/// Web server access logs in the common or combined format, as written by nginx
+/// and Apache.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessLogConfig {
+    pub name: String,
+    /// Log files; wildcards are allowed in the file name.
+    pub files: Vec<String>,
+    /// Seconds over which rates, error ratios and per-client counts are taken.
+    #[serde(default = "default_access_log_window")]
+    pub window: u64,
+    /// 404 responses to one client within the window that mark it as a scanner;
+    /// 0 turns scanner detection off.
+    #[serde(default = "default_scanner_404s")]
+    pub scanner_404s: u32,
+}
+
+fn default_access_log_window() -> u64 {
+    60
+}
+
+fn default_scanner_404s() -> u32 {
+    20
+}
+
+/// Suricata's EVE JSON log. Each signature an address triggers is raised as an anomaly
+/// until `window` seconds pass without another hit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SuricataConfig {
+    pub enabled: bool,
+    pub eve_log: String,
+    pub window: u64,
+    /// The least severe alerts reported; Suricata severities run from 1 (highest) to 4.
+    pub min_severity: u8,
+    /// Sources of alerts this severe or more are blocked, as are sources on a threat
+    /// intelligence blocklist; 0 blocks only the latter.
+    pub ban_severity: u8,
+}
+
+impl Default for SuricataConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            eve_log: "/var/log/suricata/eve.json".to_string(),
+            window: 300,
+            min_severity: 3,
+            ban_severity: 0,
         }
-        
-        if let Ok(content) = std::fs::read_to_string("config.json") {
-            if let Ok(config) = serde_json::from_str(&content) {
-                return Some(config);
-            }
+    }
+}
+
+/// The current logs of a Zeek sensor, in TSV or JSON. `conn.log` and `dns.log` feed
+/// the `zeek.*` metrics, and each notice type raised for a source in `notice.log` is an
+/// anomaly until `notice_window` seconds pass without another.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ZeekConfig {
+    pub enabled: bool,
+    pub log_dir: String,
+    /// Seconds over which connection and DNS rates are taken.
+    pub window: u64,
+    pub notice_window: u64,
+}
+
+impl Default for ZeekConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            log_dir: "/opt/zeek/logs/current".to_string(),
+            window: 60,
+            notice_window: 900,
         }
-        
-        None
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MonitoringConfig {
-    pub window_size: usize,
-    pub update_interval: u64,
-    pub anomaly_threshold: f64,
-    pub monitored_hosts: Vec<String>,
+/// Packet capture on `interface`, optionally narrowed by a tcpdump `filter`. Only
+/// headers are read, and remote addresses are kept as networks of `prefix_v4` and
+/// `prefix_v6` bits; 32 and 128 keep single hosts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureConfig {
+    pub enabled: bool,
+    pub interface: String,
+    pub filter: String,
+    /// Seconds of traffic summarized.
+    pub window: u64,
+    pub prefix_v4: u8,
+    pub prefix_v6: u8,
+    /// Protocols and destinations listed in the summary.
+    pub top: usize,
+    /// Ranges of the LAN whose devices' traffic is accounted; the private ranges when
+    /// empty.
+    pub lan: Vec<String>,
+    /// Names shown for LAN devices, by MAC address or IP address.
+    pub device_names: BTreeMap<String, String>,
+    /// Rate a device must move to be reported as a new top talker; 0 turns it off.
+    pub top_talker_kb_per_s: f64,
 }
 
-impl Default for MonitoringConfig {
+impl Default for CaptureConfig {
     fn default() -> Self {
         Self {
-            window_size: 60,
-            update_interval: 5,
-            anomaly_threshold: 3.0,
-            monitored_hosts: vec![
-                "8.8.8.8".to_string(),
-                "1.1.1.1".to_string(),
-            ],
+            enabled: false,
+            interface: String::new(),
+            filter: String::new(),
+            window: 300,
+            prefix_v4: 24,
+            prefix_v6: 48,
+            top: 10,
+            lan: Vec::new(),
+            device_names: BTreeMap::new(),
+            top_talker_kb_per_s: 256.0,
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WebServerKind {
+    /// `stub_status`
+    Nginx,
+    /// `mod_status`; `?auto` is added to the URL.
+    Apache,
+    /// The pool's `pm.status_path`; `?json` is added to the URL.
+    PhpFpm,
+}
+
+/// A web server or PHP-FPM pool whose status page is collected as metrics named
+/// `<name>.<measure>`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebStatusConfig {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: WebServerKind,
+    pub url: String,
+    /// Seconds between requests (default every cycle).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseKind {
+    /// MySQL or MariaDB, through the `mysql` client.
+    Mysql,
+    /// PostgreSQL, through `psql`.
+    Postgres,
+}
+
+/// A database server checked with its command-line client. Metrics are named
+/// `<name>.latency_ms`, `<name>.connections`, `<name>.connections_pct` and, on
+/// replicas, `<name>.replication_lag_s`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatabaseConfig {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: DatabaseKind,
+    #[serde(default = "default_database_host")]
+    pub host: String,
+    /// The client's default port when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    pub user: String,
+    #[serde(default)]
+    pub password: String,
+    /// Database to connect to; PostgreSQL needs one the user may open.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+    #[serde(default = "default_database_interval")]
+    pub interval: u64,
+    /// Share of `max_connections` in use above which the pool counts as saturated.
+    #[serde(default = "default_max_connections_pct")]
+    pub max_connections_pct: f64,
+    /// Seconds a replica may fall behind its primary.
+    #[serde(default = "default_max_replication_lag")]
+    pub max_replication_lag: u64,
+}
+
+fn default_database_host() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_database_interval() -> u64 {
+    60
+}
+
+fn default_max_connections_pct() -> f64 {
+    90.0
+}
+
+fn default_max_replication_lag() -> u64 {
+    300
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheKind {
+    Redis,
+    Memcached,
+}
+
+/// A Redis or Memcached instance. Metrics are named `<name>.ping_ms`,
+/// `<name>.hit_ratio_pct`, `<name>.evictions_per_s` and so on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheConfig {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: CacheKind,
+    /// `host:port`, e.g. `127.0.0.1:6379`
+    pub address: String,
+    /// Redis 6 ACL user; without one the password is for the default user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// Redis `requirepass` or ACL password; empty when none is set.
+    #[serde(default)]
+    pub password: String,
+    /// Seconds between checks (default every cycle).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u64>,
+}
+
+/// A directory whose total size is measured, to catch runaway growth early.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirectoryConfig {
+    pub name: String,
+    pub path: String,
+    /// Seconds between measurements; large trees take a while to walk.
+    #[serde(default = "default_directory_interval")]
+    pub interval: u64,
+    /// Bytes the directory may hold.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u64>,
+    /// Bytes per hour the directory may grow by.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_growth: Option<u64>,
+    /// Hours of free space left at the current growth rate below which the
+    /// directory is reported as filling the disk; 0 turns this off.
+    #[serde(default = "default_fill_warning")]
+    pub fill_warning: u64,
+}
+
+fn default_directory_interval() -> u64 {
+    300
+}
+
+fn default_fill_warning() -> u64 {
+    24
+}
+
+fn default_collector_timeout() -> u64 {
+    DEFAULT_COLLECTOR_TIMEOUT_SECS
+}
+
+fn default_exec_interval() -> u64 {
+    60
+}
+
+fn default_exec_timeout() -> u64 {
+    10
+}
+
+/// Planned downtime. A window without tags covers this machine and every check;
+/// a tagged window only silences checks carrying one of its tags.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    pub name: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl MaintenanceWindow {
+    pub fn contains(&self, time: DateTime<Utc>) -> bool {
+        self.start <= time && time <= self.end
+    }
+    
+    pub fn covers(&self, check: &CheckConfig, time: DateTime<Utc>) -> bool {
+        self.contains(time) && (self.tags.is_empty() || self.tags.iter().any(|tag| check.has_tag(tag)))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecurityConfig {
     pub password_hash: String,
@@ -85,6 +1935,9 @@ impl Default for SecurityConfig {
 pub struct DisplayConfig {
     pub stat_visibility: HashMap<String, bool>,
     pub refresh_rate: u64,
+    /// Language of the web pages for users who have not picked their own.
+    #[serde(default)]
+    pub language: Language,
 }
 
 impl Default for DisplayConfig {
@@ -97,20 +1950,164 @@ impl Default for DisplayConfig {
         visibility.insert("ping".to_string(), true);
         visibility.insert("net".to_string(), true);
         visibility.insert("fail".to_string(), true);
+        visibility.insert("gpu".to_string(), true);
+        visibility.insert("vram".to_string(), true);
         
         Self {
             stat_visibility: visibility,
             refresh_rate: 5,
+            language: Language::default(),
         }
     }
 }
 
+impl DisplayConfig {
+    /// Stats without an explicit toggle are shown.
+    pub fn is_visible(&self, stat: &str) -> bool {
+        self.stat_visibility.get(stat).copied().unwrap_or(true)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AlertConfig {
     pub enabled: bool,
     pub high_temp_threshold: f64,
     pub email_notifications: bool,
     pub webhook_url: Option<String>,
+    /// Per-group delivery for check alerts; groups without a route use `webhook_url`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub routes: Vec<AlertRoute>,
+    /// Enables `POST /api/ingest/webhook` for alerts from Alertmanager, Grafana or
+    /// Uptime Kuma, sent as a bearer token or `?token=`. Empty leaves it disabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ingest_token: Option<String>,
+    /// Receivers of a JSON document for every anomaly raised or cleared, for automation
+    /// rather than people; see `services::anomaly_hooks`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub anomaly_webhooks: Vec<AnomalyWebhook>,
+    /// `mailto:` or `https:` contact given to browser push services with each
+    /// Web Push notification, as they ask of senders.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub push_contact: Option<String>,
+    /// Language of webhook, push and wall messages. Event logs and the API stay in
+    /// English.
+    #[serde(default)]
+    pub language: Language,
+    /// Follow each webhook and push alert with a "Recovered" message, with the outage
+    /// duration, once its incident resolves by itself.
+    #[serde(default = "default_notify_recovery")]
+    pub notify_recovery: bool,
+    #[serde(default)]
+    pub flapping: FlappingConfig,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quiet_hours: Vec<QuietHours>,
+}
+
+/// Channels that quiet hours can hold alerts for.
+pub const QUIET_HOUR_CHANNELS: [&str; 2] = ["webhook", "push"];
+
+/// A daily period, in the server's local time, when alerts on one channel are held
+/// and sent together as a digest once it ends.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuietHours {
+    /// `webhook` or `push`.
+    pub channel: String,
+    /// `HH:MM`; a period that ends before it starts runs past midnight.
+    pub start: String,
+    pub end: String,
+    /// Critical alerts are still sent straight away.
+    #[serde(default = "default_allow_critical")]
+    pub allow_critical: bool,
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        let (Some(start), Some(end)) = (parse_clock(&self.start), parse_clock(&self.end)) else {
+            return false;
+        };
+        if start <= end {
+            start <= time && time < end
+        } else {
+            time >= start || time < end
+        }
+    }
+}
+
+fn parse_clock(text: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(text.trim(), "%H:%M").ok()
+}
+
+fn default_allow_critical() -> bool {
+    true
+}
+
+/// Collapses anomalies that keep raising and clearing into one `Flapping` alert.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlappingConfig {
+    #[serde(default = "default_flapping")]
+    pub enabled: bool,
+    /// State changes (raised or cleared) within `window` that make an anomaly flap.
+    #[serde(default = "default_flap_transitions")]
+    pub transitions: usize,
+    /// Seconds over which state changes are counted.
+    #[serde(default = "default_flap_window")]
+    pub window: u64,
+    /// Seconds without a state change before a flapping anomaly is reported on its
+    /// own again.
+    #[serde(default = "default_flap_stable")]
+    pub stable: u64,
+}
+
+impl Default for FlappingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            transitions: default_flap_transitions(),
+            window: default_flap_window(),
+            stable: default_flap_stable(),
+        }
+    }
+}
+
+fn default_flapping() -> bool {
+    true
+}
+
+fn default_flap_transitions() -> usize {
+    6
+}
+
+fn default_flap_window() -> u64 {
+    1800
+}
+
+fn default_flap_stable() -> u64 {
+    600
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnomalyWebhook {
+    pub url: String,
+    /// Key for the HMAC-SHA256 of each body, sent as `X-Signature-256: sha256=<hex>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertRoute {
+    pub tag: String,
+    pub webhook_url: Option<String>,
+    /// Broadcast a wall message when a check in this group goes down.
+    #[serde(default = "default_wall")]
+    pub wall: bool,
+}
+
+fn default_wall() -> bool {
+    true
+}
+
+fn default_notify_recovery() -> bool {
+    true
 }
 
 impl Default for AlertConfig {
@@ -120,6 +2117,14 @@ impl Default for AlertConfig {
             high_temp_threshold: 80.0,
             email_notifications: false,
             webhook_url: None,
+            routes: Vec::new(),
+            ingest_token: None,
+            anomaly_webhooks: Vec::new(),
+            push_contact: None,
+            language: Language::default(),
+            notify_recovery: true,
+            flapping: FlappingConfig::default(),
+            quiet_hours: Vec::new(),
         }
     }
 }
@@ -129,6 +2134,10 @@ pub struct LoggingConfig {
     pub level: String,
     pub max_file_size: String,
     pub backup_count: u32,
+    #[serde(default)]
+    pub target: LogTarget,
+    #[serde(default)]
+    pub events: EventLogConfig,
 }
 
 impl Default for LoggingConfig {
@@ -137,6 +2146,255 @@ impl Default for LoggingConfig {
             level: "INFO".to_string(),
             max_file_size: "10MB".to_string(),
             backup_count: 5,
+            target: LogTarget::default(),
+            events: EventLogConfig::default(),
+        }
+    }
+}
+
+/// Where console-style application logs are sent. `logs/app.log` is always written
+/// regardless of the target so the downloads page keeps working.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogTarget {
+    #[default]
+    Stdout,
+    Syslog,
+    Journald,
+}
+
+/// Rotation policy for the structured event log, kept separate from `app.log`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventLogConfig {
+    pub max_file_size: String,
+    pub backup_count: u32,
+}
+
+impl Default for EventLogConfig {
+    fn default() -> Self {
+        Self {
+            max_file_size: "5MB".to_string(),
+            backup_count: 10,
+        }
+    }
+}
+
+impl EventLogConfig {
+    pub fn max_bytes(&self) -> u64 {
+        parse_byte_size(&self.max_file_size).unwrap_or(5 * 1024 * 1024)
+    }
+}
+
+/// Parses sizes such as "512KB", "10MB" or "1GB" (binary multiples) into bytes.
+pub fn parse_byte_size(value: &str) -> Option<u64> {
+    let value = value.trim().to_ascii_uppercase();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    
+    let multiplier = match unit.trim() {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    
+    Some(number * multiplier)
+}
+
+/// WAN address tracking. Each service is any URL whose response contains the address,
+/// e.g. a "what is my IP" service or a router status page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicIpConfig {
+    pub enabled: bool,
+    pub interval: u64,
+    pub services: Vec<String>,
+    /// Requested when the address changes; `{ip}` is replaced with the new address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ddns_update_url: Option<String>,
+    /// DNS blocklist zones the address is looked up in; empty disables the check.
+    #[serde(default = "default_dnsbl_zones")]
+    pub dnsbl: Vec<String>,
+    #[serde(default = "default_dnsbl_interval")]
+    pub dnsbl_interval: u64,
+}
+
+// SORBS shut down in 2024; its zone no longer answers meaningfully
+fn default_dnsbl_zones() -> Vec<String> {
+    ["zen.spamhaus.org", "bl.spamcop.net", "b.barracudacentral.org"]
+        .iter()
+        .map(|zone| zone.to_string())
+        .collect()
+}
+
+fn default_dnsbl_interval() -> u64 {
+    3600
+}
+
+impl Default for PublicIpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: 300,
+            services: vec![
+                "https://api.ipify.org".to_string(),
+                "https://icanhazip.com".to_string(),
+                "https://ifconfig.me/ip".to_string(),
+            ],
+            ddns_update_url: None,
+            dnsbl: default_dnsbl_zones(),
+            dnsbl_interval: default_dnsbl_interval(),
+        }
+    }
+}
+
+/// High-value files whose changes are alerted on. They are watched with inotify where
+/// available and re-hashed every `sweep_interval` seconds to catch anything missed.
+/// A directory covers the files directly inside it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileWatchConfig {
+    pub enabled: bool,
+    pub paths: Vec<String>,
+    pub sweep_interval: u64,
+}
+
+impl Default for FileWatchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            paths: vec![
+                "/etc/ssh/sshd_config".to_string(),
+                "/etc/sudoers".to_string(),
+                "/etc/sudoers.d".to_string(),
+                "/etc/crontab".to_string(),
+                "/etc/cron.d".to_string(),
+                "/var/spool/cron/crontabs".to_string(),
+            ],
+            sweep_interval: 3600,
+        }
+    }
+}
+
+/// The CrowdSec local API. With a bouncer key the addresses CrowdSec bans are blocked
+/// through the configured firewall; with machine credentials the monitor's own SSH
+/// brute-force and web scanner detections are reported to it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrowdSecConfig {
+    pub enabled: bool,
+    pub url: String,
+    /// Seconds between polls of the decision stream.
+    pub interval: u64,
+    /// Bouncer key from `cscli bouncers add`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// Machine from `cscli machines add`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub machine_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// How long CrowdSec bans the addresses reported to it, e.g. `4h`.
+    pub ban_duration: String,
+}
+
+impl Default for CrowdSecConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: "http://127.0.0.1:8080".to_string(),
+            interval: 30,
+            api_key: None,
+            machine_id: None,
+            password: None,
+            ban_duration: "4h".to_string(),
+        }
+    }
+}
+
+/// Optional APIs beyond the REST endpoints.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiConfig {
+    /// Serve GraphQL queries over metrics, anomalies, hosts and threat intel at `/graphql`.
+    pub graphql: bool,
+}
+
+/// Scheduled HTML summary of availability, anomalies, resource use and threat intel,
+/// written to the downloads area and mailed to `email` through the local `sendmail`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SummaryReportConfig {
+    pub schedule: ReportSchedule,
+    /// Hour of the day (UTC) the report is generated; weekly reports go out on Mondays.
+    pub hour: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub email: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+}
+
+impl Default for SummaryReportConfig {
+    fn default() -> Self {
+        Self {
+            schedule: ReportSchedule::Off,
+            hour: 7,
+            email: Vec::new(),
+            from: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportSchedule {
+    #[default]
+    Off,
+    Daily,
+    Weekly,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreatIntelConfig {
+    pub refresh_interval: u64,
+    pub feeds: Vec<FeedConfig>,
+    /// Plain-text lists of malicious addresses and CIDR ranges, one per line with `#`
+    /// or `;` comments. Connections with a listed address are reported and the range
+    /// is blocked.
+    #[serde(default)]
+    pub blocklists: Vec<BlocklistConfig>,
+}
+
+impl Default for ThreatIntelConfig {
+    fn default() -> Self {
+        let feed = |name: &str, url: &str| FeedConfig {
+            name: name.to_string(),
+            url: url.to_string(),
+        };
+        
+        Self {
+            refresh_interval: 1800,
+            feeds: vec![
+                feed("CISA", "https://www.cisa.gov/news-events/cybersecurity-advisories.xml"),
+                feed("BleepingComputer", "https://www.bleepingcomputer.com/feed/"),
+                feed("KrebsOnSecurity", "https://krebsonsecurity.com/feed/"),
+                feed("TheHackerNews", "https://thehackernews.com/feeds/posts/default"),
+            ],
+            blocklists: Vec::new(),
         }
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeedConfig {
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlocklistConfig {
+    pub name: String,
+    pub url: String,
+    /// Sent in a `Key` header, as AbuseIPDB's blacklist endpoint expects.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+}
@@ -0,0 +1,113 @@
+use crate::models::config::DisplayConfig;
+use serde::{Deserialize, Serialize};
+
+/// Stats the dashboard can chart, as keyed in its graph data. `gpu` and `vram` are
+/// left off charts on hosts without a graphics card.
+pub const CHART_METRICS: &[&str] = &["cpu", "ram", "disk", "temp", "ping", "net", "fail", "gpu", "vram"];
+const MAX_PANELS: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PanelKind {
+    Anomalies,
+    Status,
+    Hosts,
+    Chart,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PanelSize {
+    Half,
+    #[default]
+    Full,
+}
+
+/// One panel of a dashboard layout.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Panel {
+    pub kind: PanelKind,
+    #[serde(default)]
+    pub size: PanelSize,
+    /// Heading shown instead of the panel's usual one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Stats a chart plots, from `CHART_METRICS`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub metrics: Vec<String>,
+    /// Host groups a hosts panel shows; every group when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+}
+
+impl Panel {
+    fn new(kind: PanelKind) -> Self {
+        Self {
+            kind,
+            size: PanelSize::Full,
+            title: None,
+            metrics: Vec::new(),
+            groups: Vec::new(),
+        }
+    }
+    
+    pub fn heading(&self) -> String {
+        self.title.clone().unwrap_or_else(|| {
+            match self.kind {
+                PanelKind::Anomalies => "Anomalies",
+                PanelKind::Status => "System Status",
+                PanelKind::Hosts => "Hosts",
+                PanelKind::Chart => "Metrics History",
+            }
+            .to_string()
+        })
+    }
+    
+    pub fn shows_group(&self, group: &str) -> bool {
+        self.groups.is_empty() || self.groups.iter().any(|shown| shown == group)
+    }
+}
+
+/// The panels of one user's dashboard, in display order. Saved with the account.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DashboardLayout {
+    pub panels: Vec<Panel>,
+}
+
+impl DashboardLayout {
+    /// The layout of users who have not arranged their own: every panel at full
+    /// width, charting the stats enabled in the display settings.
+    pub fn default_for(display: &DisplayConfig) -> Self {
+        let chart = Panel {
+            metrics: CHART_METRICS
+                .iter()
+                .filter(|metric| display.is_visible(metric))
+                .map(|metric| metric.to_string())
+                .collect(),
+            ..Panel::new(PanelKind::Chart)
+        };
+        Self {
+            panels: vec![
+                Panel::new(PanelKind::Anomalies),
+                Panel::new(PanelKind::Status),
+                Panel::new(PanelKind::Hosts),
+                chart,
+            ],
+        }
+    }
+    
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.panels.is_empty() || self.panels.len() > MAX_PANELS {
+            return Err(format!("A dashboard has between 1 and {} panels", MAX_PANELS));
+        }
+        for panel in &self.panels {
+            if panel.kind == PanelKind::Chart && panel.metrics.is_empty() {
+                return Err("Chart panels need at least one metric".to_string());
+            }
+            if let Some(metric) = panel.metrics.iter().find(|metric| !CHART_METRICS.contains(&metric.as_str())) {
+                return Err(format!("Unknown metric '{}'; expected one of {}", metric, CHART_METRICS.join(", ")));
+            }
+        }
+        Ok(())
+    }
+}
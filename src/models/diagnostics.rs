@@ -0,0 +1,38 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TracerouteHop {
+    pub hop: u32,
+    /// First responding address; `None` when every probe timed out.
+    pub address: Option<String>,
+    pub latencies_ms: Vec<f64>,
+    pub avg_ms: Option<f64>,
+    pub loss_percent: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Traceroute {
+    pub target: String,
+    pub started_at: DateTime<Utc>,
+    pub hops: Vec<TracerouteHop>,
+}
+
+/// What one command showed about the host.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotSection {
+    pub name: String,
+    pub command: String,
+    pub output: String,
+    /// Why the command produced nothing, e.g. it is not installed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The state of the host moments after a critical anomaly fired.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub anomaly: String,
+    pub captured_at: DateTime<Utc>,
+    pub sections: Vec<SnapshotSection>,
+}
@@ -0,0 +1,41 @@
+use crate::models::metrics::AnomalySeverity;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    Anomaly,
+    Action,
+    Notification,
+    Config,
+    Diagnostic,
+}
+
+/// One line of the structured event log (`logs/events.jsonl`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub timestamp: DateTime<Utc>,
+    pub kind: EventKind,
+    pub severity: AnomalySeverity,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub details: serde_json::Value,
+}
+
+impl Event {
+    pub fn new(kind: EventKind, severity: AnomalySeverity, message: impl Into<String>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            kind,
+            severity,
+            message: message.into(),
+            details: serde_json::Value::Null,
+        }
+    }
+    
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = details;
+        self
+    }
+}
@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+
+// Days until a rule marked once is trusted half as much; each further mark lengthens it
+pub const FEEDBACK_HALF_LIFE_DAYS: f64 = 14.0;
+// Confidence below which a rule stops suppressing anomalies
+pub const MIN_FEEDBACK_CONFIDENCE: f64 = 0.25;
+
+/// A range of values marked as normal for a metric although they stray from its
+/// baseline. Trust in it decays unless it is marked again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedbackRule {
+    pub id: u64,
+    /// Baseline key of the metric, as listed by `/api/baselines`.
+    pub metric: String,
+    pub min: f64,
+    pub max: f64,
+    /// When a value in the range was last marked; confidence decays from here.
+    pub marked_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub marked_by: Option<String>,
+    pub times_marked: u32,
+}
+
+impl FeedbackRule {
+    /// 1 when just marked, halving every half-life times the number of marks.
+    pub fn confidence(&self, now: DateTime<Utc>) -> f64 {
+        let age_days = (now - self.marked_at).num_seconds().max(0) as f64 / 86_400.0;
+        0.5_f64.powf(age_days / (FEEDBACK_HALF_LIFE_DAYS * self.times_marked.max(1) as f64))
+    }
+    
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.confidence(now) >= MIN_FEEDBACK_CONFIDENCE
+    }
+    
+    pub fn covers(&self, metric: &str, value: f64) -> bool {
+        self.metric == metric && value >= self.min && value <= self.max
+    }
+}
+
+/// Reads feedback rules, or the `{"cpu-91": true}` map earlier versions stored, where
+/// each key matched a single rounded value.
+pub fn deserialize_feedback<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<FeedbackRule>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Rules(Vec<FeedbackRule>),
+        Keys(HashMap<String, bool>),
+    }
+    
+    Ok(match Stored::deserialize(deserializer)? {
+        Stored::Rules(rules) => rules,
+        Stored::Keys(keys) => {
+            let now = Utc::now();
+            keys.into_iter()
+                .filter(|(_, normal)| *normal)
+                .filter_map(|(key, _)| {
+                    let (metric, value) = key.rsplit_once('-')?;
+                    Some((metric.to_string(), value.parse::<f64>().ok()?))
+                })
+                .zip(1..)
+                .map(|((metric, value), id)| FeedbackRule {
+                    id,
+                    metric,
+                    min: value - 0.5,
+                    max: value + 0.5,
+                    marked_at: now,
+                    marked_by: None,
+                    times_marked: 1,
+                })
+                .collect()
+        }
+    })
+}
@@ -0,0 +1,134 @@
+use crate::models::events::EventKind;
+use crate::models::metrics::AnomalySeverity;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// One anomaly filed under an incident.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncidentAnomaly {
+    pub message: String,
+    pub raised_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cleared_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IncidentStatus {
+    Open,
+    Acknowledged,
+    Resolved,
+}
+
+impl std::fmt::Display for IncidentStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IncidentStatus::Open => write!(f, "open"),
+            IncidentStatus::Acknowledged => write!(f, "acknowledged"),
+            IncidentStatus::Resolved => write!(f, "resolved"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimelineKind {
+    /// An anomaly raised or cleared.
+    Anomaly,
+    /// Something done about it, such as blocking an address.
+    Action,
+    /// An alert sent.
+    Notification,
+    /// A diagnostic captured, such as a traceroute.
+    Diagnostic,
+    /// Opened, acknowledged, assigned or resolved.
+    Status,
+    Note,
+}
+
+impl std::fmt::Display for TimelineKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimelineKind::Anomaly => write!(f, "anomaly"),
+            TimelineKind::Action => write!(f, "action"),
+            TimelineKind::Notification => write!(f, "notification"),
+            TimelineKind::Diagnostic => write!(f, "diagnostic"),
+            TimelineKind::Status => write!(f, "status"),
+            TimelineKind::Note => write!(f, "note"),
+        }
+    }
+}
+
+impl From<EventKind> for TimelineKind {
+    fn from(kind: EventKind) -> Self {
+        match kind {
+            EventKind::Anomaly => TimelineKind::Anomaly,
+            EventKind::Action => TimelineKind::Action,
+            EventKind::Notification => TimelineKind::Notification,
+            EventKind::Diagnostic => TimelineKind::Diagnostic,
+            EventKind::Config => TimelineKind::Status,
+        }
+    }
+}
+
+/// One entry of an incident's record.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineEntry {
+    pub at: DateTime<Utc>,
+    pub kind: TimelineKind,
+    pub message: String,
+    /// The user behind a status change or note; none for the monitor's own entries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// What a diagnostic captured, such as a traceroute or state snapshot.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
+
+/// Anomalies raised together that probably share one cause, such as every check
+/// behind a switch that went down. Persisted in `data/incidents.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Incident {
+    pub id: u64,
+    /// What ties the anomalies together: `host`, `check:<topmost check down>`,
+    /// `instance:<database, cache etc.>`, or else the subject of the anomaly that opened it.
+    pub key: String,
+    /// The anomaly that opened the incident.
+    pub title: String,
+    /// The highest severity among its anomalies.
+    pub severity: AnomalySeverity,
+    /// Probable root cause, when the anomalies fit a known pattern.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+    pub opened_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acknowledged_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assignee: Option<String>,
+    /// Set once none of its anomalies is active any more, or when resolved by hand.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_at: Option<DateTime<Utc>>,
+    /// The user who resolved it by hand; none when it resolved itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_by: Option<String>,
+    pub anomalies: Vec<IncidentAnomaly>,
+    /// Anomalies, actions, notifications, status changes and notes, oldest first.
+    #[serde(default)]
+    pub timeline: Vec<TimelineEntry>,
+}
+
+impl Incident {
+    pub fn is_open(&self) -> bool {
+        self.resolved_at.is_none()
+    }
+    
+    pub fn status(&self) -> IncidentStatus {
+        match (self.resolved_at, self.acknowledged_at) {
+            (Some(_), _) => IncidentStatus::Resolved,
+            (None, Some(_)) => IncidentStatus::Acknowledged,
+            (None, None) => IncidentStatus::Open,
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use crate::models::metrics::AnomalySeverity;
+use serde::Serialize;
+
+/// One line of a log file shown in the log viewer.
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLine {
+    /// Line number in the file, counting from 1; none for lines streamed while following.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number: Option<u64>,
+    pub text: String,
+    /// The level the line was logged at, when it states one.
+    pub severity: Option<AnomalySeverity>,
+}
+
+/// A page of matching lines. Page 1 holds the most recent matches.
+#[derive(Debug, Clone, Serialize)]
+pub struct LogPage {
+    pub name: String,
+    pub page: usize,
+    pub per_page: usize,
+    /// Lines that match the search and severity, across the whole file.
+    pub total: u64,
+    pub pages: u64,
+    pub lines: Vec<LogLine>,
+}
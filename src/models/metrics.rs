@@ -1,18 +1,92 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemMetrics {
     pub timestamp: DateTime<Utc>,
     pub cpu_percent: f64,
     pub ram_percent: f64,
+    /// Percent of swap space in use; 0 without swap.
+    #[serde(default)]
+    pub swap_percent: f64,
     pub disk_percent: f64,
     pub temperature: f64,
     pub ping_ms: f64,
-    pub net_connections: usize,
+    pub net_connections: usize, // established TCP connections
+    #[serde(default)]
+    pub net_rx_bytes: f64, // per second, all interfaces but loopback
+    #[serde(default)]
+    pub net_tx_bytes: f64,
     pub failed_logins: u32,
-    pub host_status: HashMap<String, f64>, // host -> ping time in ms
+    /// Load averages over 1, 5 and 15 minutes, which count tasks waiting on I/O
+    /// as well as those wanting the CPU.
+    #[serde(default)]
+    pub load_1: f64,
+    #[serde(default)]
+    pub load_5: f64,
+    #[serde(default)]
+    pub load_15: f64,
+    /// Tasks running or ready to run, and tasks blocked on I/O, when sampled.
+    #[serde(default)]
+    pub procs_running: u32,
+    #[serde(default)]
+    pub procs_blocked: u32,
+    /// When the host last booted, as the kernel reports it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boot_time: Option<DateTime<Utc>>,
+    pub host_status: HashMap<String, CheckResult>, // check name -> latest result
+    #[serde(default)]
+    pub disk_io: HashMap<String, DiskIo>, // block device -> activity since the previous sample
+    /// Percent of space in use on each mounted filesystem, by mount point.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub mounts: BTreeMap<String, f64>,
+    /// TCP sockets in each state, e.g. `TIME_WAIT`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tcp_states: BTreeMap<String, u32>,
+    /// Traffic of each network interface but loopback since the previous sample.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub interfaces: BTreeMap<String, InterfaceRate>,
+    /// Graphics cards, e.g. `nvidia0` or `amd0`; empty on hosts without one.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub gpus: BTreeMap<String, GpuReading>,
+    /// Readings of collectors without a dedicated field, by metric name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom: BTreeMap<String, f64>,
+}
+
+/// Outcome of one run of a host check.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CheckResult {
+    /// Average round trip in milliseconds, -1 when the check failed.
+    pub latency_ms: f64,
+    #[serde(default)]
+    pub loss_percent: f64,
+    /// Mean difference between consecutive round trips in milliseconds.
+    #[serde(default)]
+    pub jitter_ms: f64,
+}
+
+impl CheckResult {
+    pub fn up(latency_ms: f64) -> Self {
+        Self {
+            latency_ms,
+            loss_percent: 0.0,
+            jitter_ms: 0.0,
+        }
+    }
+    
+    pub fn down() -> Self {
+        Self {
+            latency_ms: -1.0,
+            loss_percent: 100.0,
+            jitter_ms: 0.0,
+        }
+    }
+    
+    pub fn is_up(&self) -> bool {
+        self.latency_ms >= 0.0
+    }
 }
 
 impl SystemMetrics {
@@ -21,16 +95,119 @@ impl SystemMetrics {
             timestamp: Utc::now(),
             cpu_percent: 0.0,
             ram_percent: 0.0,
+            swap_percent: 0.0,
             disk_percent: 0.0,
             temperature: 0.0,
             ping_ms: -1.0,
             net_connections: 0,
+            net_rx_bytes: 0.0,
+            net_tx_bytes: 0.0,
             failed_logins: 0,
+            load_1: 0.0,
+            load_5: 0.0,
+            load_15: 0.0,
+            procs_running: 0,
+            procs_blocked: 0,
+            boot_time: None,
             host_status: HashMap::new(),
+            disk_io: HashMap::new(),
+            mounts: BTreeMap::new(),
+            tcp_states: BTreeMap::new(),
+            interfaces: BTreeMap::new(),
+            gpus: BTreeMap::new(),
+            custom: BTreeMap::new(),
+        }
+    }
+    
+    /// Adds the readings one collector took into a record of its own, i.e. the
+    /// fields it changed from their defaults.
+    pub fn merge(&mut self, readings: &SystemMetrics) {
+        let defaults = SystemMetrics::new();
+        for (field, reading, default) in [
+            (&mut self.cpu_percent, readings.cpu_percent, defaults.cpu_percent),
+            (&mut self.ram_percent, readings.ram_percent, defaults.ram_percent),
+            (&mut self.swap_percent, readings.swap_percent, defaults.swap_percent),
+            (&mut self.disk_percent, readings.disk_percent, defaults.disk_percent),
+            (&mut self.temperature, readings.temperature, defaults.temperature),
+            (&mut self.ping_ms, readings.ping_ms, defaults.ping_ms),
+            (&mut self.net_rx_bytes, readings.net_rx_bytes, defaults.net_rx_bytes),
+            (&mut self.net_tx_bytes, readings.net_tx_bytes, defaults.net_tx_bytes),
+            (&mut self.load_1, readings.load_1, defaults.load_1),
+            (&mut self.load_5, readings.load_5, defaults.load_5),
+            (&mut self.load_15, readings.load_15, defaults.load_15),
+        ] {
+            if reading != default {
+                *field = reading;
+            }
+        }
+        if readings.net_connections != defaults.net_connections {
+            self.net_connections = readings.net_connections;
         }
+        for (field, reading, default) in [
+            (&mut self.failed_logins, readings.failed_logins, defaults.failed_logins),
+            (&mut self.procs_running, readings.procs_running, defaults.procs_running),
+            (&mut self.procs_blocked, readings.procs_blocked, defaults.procs_blocked),
+        ] {
+            if reading != default {
+                *field = reading;
+            }
+        }
+        if readings.boot_time.is_some() {
+            self.boot_time = readings.boot_time;
+        }
+        self.host_status.extend(readings.host_status.iter().map(|(name, result)| (name.clone(), *result)));
+        self.disk_io.extend(readings.disk_io.iter().map(|(device, io)| (device.clone(), *io)));
+        self.mounts.extend(readings.mounts.iter().map(|(mount, percent)| (mount.clone(), *percent)));
+        self.tcp_states.extend(readings.tcp_states.iter().map(|(state, count)| (state.clone(), *count)));
+        self.interfaces.extend(readings.interfaces.iter().map(|(name, rate)| (name.clone(), *rate)));
+        self.gpus.extend(readings.gpus.iter().map(|(name, gpu)| (name.clone(), *gpu)));
+        self.custom.extend(readings.custom.iter().map(|(name, value)| (name.clone(), *value)));
+    }
+    
+    /// The mean utilization of the graphics cards, when there are any.
+    pub fn gpu_percent(&self) -> Option<f64> {
+        (!self.gpus.is_empty())
+            .then(|| self.gpus.values().map(|gpu| gpu.utilization_percent).sum::<f64>() / self.gpus.len() as f64)
+    }
+    
+    /// The share of all graphics memory in use, when there are cards reporting it.
+    pub fn gpu_memory_percent(&self) -> Option<f64> {
+        let total: f64 = self.gpus.values().map(|gpu| gpu.memory_total_mb).sum();
+        (total > 0.0).then(|| self.gpus.values().map(|gpu| gpu.memory_used_mb).sum::<f64>() / total * 100.0)
     }
 }
 
+/// Activity of one block device between two samples of `/proc/diskstats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct DiskIo {
+    pub read_iops: f64,
+    pub write_iops: f64,
+    pub read_bytes_per_sec: f64,
+    pub write_bytes_per_sec: f64,
+    /// Mean time a request took, queueing included, in milliseconds.
+    pub await_ms: f64,
+    /// Average number of requests in flight.
+    pub queue_depth: f64,
+    /// Share of the time the device was busy.
+    pub util_percent: f64,
+}
+
+/// Throughput of one network interface between two samples of `/proc/net/dev`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct InterfaceRate {
+    pub rx_bytes_per_sec: f64,
+    pub tx_bytes_per_sec: f64,
+}
+
+/// One graphics card as reported by `nvidia-smi` or `rocm-smi`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct GpuReading {
+    pub utilization_percent: f64,
+    pub memory_used_mb: f64,
+    pub memory_total_mb: f64,
+    pub temperature: f64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Anomaly {
     pub metric: String,
@@ -41,7 +218,7 @@ pub struct Anomaly {
     pub timestamp: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum AnomalySeverity {
     Info,
     Warning,
@@ -63,6 +240,21 @@ pub struct BaselineStats {
     pub mean: f64,
     pub std: f64,
     pub sample_count: usize,
+    /// Pinned by hand: kept as is instead of relearned from recent samples.
+    #[serde(default)]
+    pub frozen: bool,
+}
+
+/// The baselines as learned at one point in time, one line of `data/baseline_history.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BaselineSnapshot {
+    /// Counts up from 1 with each snapshot.
+    pub version: u64,
+    pub learned_at: DateTime<Utc>,
+    /// The configured learning window and how many samples it held.
+    pub window_size: usize,
+    pub sample_count: usize,
+    pub baselines: BTreeMap<String, BaselineStats>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -72,3 +264,91 @@ pub struct ThreatIntel {
     pub url: String,
     pub published: Option<DateTime<Utc>>,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UptimeInfo {
+    pub boot_time: DateTime<Utc>,
+    pub uptime_secs: i64,
+    pub monitor_started: DateTime<Utc>,
+    pub monitor_uptime_secs: i64,
+}
+
+/// Time ranges selectable on the dashboard, served from the rolled-up history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HistoryRange {
+    #[serde(rename = "1h")]
+    Hour,
+    #[serde(rename = "6h")]
+    SixHours,
+    #[serde(rename = "24h")]
+    Day,
+    #[serde(rename = "7d")]
+    Week,
+}
+
+impl HistoryRange {
+    pub const ALL: [HistoryRange; 4] = [HistoryRange::Hour, HistoryRange::SixHours, HistoryRange::Day, HistoryRange::Week];
+    
+    pub fn duration(&self) -> chrono::Duration {
+        match self {
+            HistoryRange::Hour => chrono::Duration::hours(1),
+            HistoryRange::SixHours => chrono::Duration::hours(6),
+            HistoryRange::Day => chrono::Duration::hours(24),
+            HistoryRange::Week => chrono::Duration::days(7),
+        }
+    }
+}
+
+/// Averaged metrics over one aggregation bucket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricPoint {
+    pub timestamp: DateTime<Utc>,
+    pub cpu_percent: f64,
+    pub ram_percent: f64,
+    pub disk_percent: f64,
+    pub temperature: f64,
+    pub ping_ms: f64,
+    pub net_connections: f64,
+    pub failed_logins: f64,
+    /// Mean utilization and VRAM use of the graphics cards, 0 without any.
+    #[serde(default)]
+    pub gpu_percent: f64,
+    #[serde(default)]
+    pub gpu_memory_percent: f64,
+    pub samples: u32,
+}
+
+impl MetricPoint {
+    pub fn from_metrics(timestamp: DateTime<Utc>, metrics: &SystemMetrics) -> Self {
+        Self {
+            timestamp,
+            cpu_percent: metrics.cpu_percent,
+            ram_percent: metrics.ram_percent,
+            disk_percent: metrics.disk_percent,
+            temperature: metrics.temperature,
+            ping_ms: metrics.ping_ms,
+            net_connections: metrics.net_connections as f64,
+            failed_logins: metrics.failed_logins as f64,
+            gpu_percent: metrics.gpu_percent().unwrap_or(0.0),
+            gpu_memory_percent: metrics.gpu_memory_percent().unwrap_or(0.0),
+            samples: 1,
+        }
+    }
+    
+    /// Folds `other` into this point, weighting both by their sample counts.
+    pub fn merge(&mut self, other: &MetricPoint) {
+        let total = (self.samples + other.samples) as f64;
+        let (a, b) = (self.samples as f64 / total, other.samples as f64 / total);
+        
+        self.cpu_percent = self.cpu_percent * a + other.cpu_percent * b;
+        self.ram_percent = self.ram_percent * a + other.ram_percent * b;
+        self.disk_percent = self.disk_percent * a + other.disk_percent * b;
+        self.temperature = self.temperature * a + other.temperature * b;
+        self.ping_ms = self.ping_ms * a + other.ping_ms * b;
+        self.net_connections = self.net_connections * a + other.net_connections * b;
+        self.failed_logins = self.failed_logins * a + other.failed_logins * b;
+        self.gpu_percent = self.gpu_percent * a + other.gpu_percent * b;
+        self.gpu_memory_percent = self.gpu_memory_percent * a + other.gpu_memory_percent * b;
+        self.samples += other.samples;
+    }
+}
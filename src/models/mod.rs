@@ -1,3 +1,12 @@
 pub mod auth;
+pub mod availability;
+pub mod backup;
 pub mod config;
+pub mod dashboard;
+pub mod diagnostics;
+pub mod events;
+pub mod feedback;
+pub mod incidents;
+pub mod logs;
 pub mod metrics;
+pub mod push;
@@ -0,0 +1,23 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The keys a browser issues with a push subscription, base64url encoded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PushKeys {
+    /// The browser's P-256 public key the payload is encrypted to.
+    pub p256dh: String,
+    /// Shared authentication secret mixed into the encryption keys.
+    pub auth: String,
+}
+
+/// A browser that asked to be sent Web Push notifications, as given by
+/// `PushSubscription.toJSON()`. Saved in `data/push_subscriptions.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PushSubscription {
+    pub endpoint: String,
+    pub keys: PushKeys,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+}
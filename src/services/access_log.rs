@@ -0,0 +1,179 @@
+use crate::models::config::{AccessLogConfig, MonitoringConfig};
+use crate::models::metrics::SystemMetrics;
+use crate::services::collectors::Collector;
+use crate::services::log_watch::LogTail;
+use crate::utils::glob;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+const TOP_CLIENTS: usize = 10;
+
+struct Request {
+    at: DateTime<Utc>,
+    client: IpAddr,
+    status: u16,
+}
+
+struct AccessLogState {
+    config: AccessLogConfig,
+    /// Requests within the window, oldest first.
+    requests: VecDeque<Request>,
+    started: DateTime<Utc>,
+}
+
+impl AccessLogState {
+    /// Clients with at least `scanner_404s` not-found responses in the window.
+    fn scanners(&self) -> Vec<IpAddr> {
+        if self.config.scanner_404s == 0 {
+            return Vec::new();
+        }
+        let mut not_found: BTreeMap<IpAddr, usize> = BTreeMap::new();
+        for request in self.requests.iter().filter(|request| request.status == 404) {
+            *not_found.entry(request.client).or_default() += 1;
+        }
+        not_found
+            .into_iter()
+            .filter(|(_, count)| *count >= self.config.scanner_404s as usize)
+            .map(|(client, _)| client)
+            .collect()
+    }
+}
+
+/// Request rates, error ratios and busy clients from nginx and Apache access logs.
+pub struct AccessLogCollector {
+    logs: Vec<AccessLogState>,
+    tail: LogTail,
+    /// Client address and status code of a common or combined log line.
+    line: Regex,
+}
+
+impl AccessLogCollector {
+    pub fn new() -> Self {
+        Self {
+            logs: Vec::new(),
+            tail: LogTail::new(),
+            line: Regex::new(r#"^(\S+) \S+ \S+ \[[^\]]*\] "[^"]*" (\d{3}) "#).unwrap(),
+        }
+    }
+}
+
+#[async_trait]
+impl Collector for AccessLogCollector {
+    fn name(&self) -> &str {
+        "access_log"
+    }
+    
+    fn configure(&mut self, config: &MonitoringConfig) {
+        let mut previous = std::mem::take(&mut self.logs);
+        for access_log in &config.access_logs {
+            match previous.iter().position(|state| &state.config == access_log) {
+                Some(index) => self.logs.push(previous.swap_remove(index)),
+                None => self.logs.push(AccessLogState {
+                    config: access_log.clone(),
+                    requests: VecDeque::new(),
+                    started: Utc::now(),
+                }),
+            }
+        }
+    }
+    
+    async fn collect(&mut self, metrics: &mut SystemMetrics) -> Result<()> {
+        let now = Utc::now();
+        
+        let files: Vec<Vec<PathBuf>> = self
+            .logs
+            .iter()
+            .map(|state| state.config.files.iter().flat_map(|file| glob::expand(Path::new("/"), file)).collect())
+            .collect();
+        let paths: BTreeSet<PathBuf> = files.iter().flatten().cloned().collect();
+        self.tail.retain(&paths);
+        let mut lines: HashMap<&PathBuf, Vec<String>> = HashMap::new();
+        for path in &paths {
+            lines.insert(path, self.tail.read_new(path).await);
+        }
+        
+        for (state, files) in self.logs.iter_mut().zip(&files) {
+            for line in files.iter().flat_map(|file| &lines[file]) {
+                let Some(captures) = self.line.captures(line) else {
+                    continue;
+                };
+                // Logs with unresolved addresses only are not supported
+                let (Ok(client), Ok(status)) = (captures[1].parse::<IpAddr>(), captures[2].parse::<u16>()) else {
+                    continue;
+                };
+                state.requests.push_back(Request { at: now, client, status });
+            }
+            while state.requests.front().is_some_and(|request| (now - request.at).num_seconds() >= state.config.window as i64) {
+                state.requests.pop_front();
+            }
+            
+            // Requests per second over this log's window, shorter while it is still filling
+            let seconds = ((now - state.started).num_seconds() as f64).clamp(1.0, state.config.window as f64);
+            let total = state.requests.len();
+            let share = |range: std::ops::Range<u16>| {
+                let count = state.requests.iter().filter(|request| range.contains(&request.status)).count();
+                if total == 0 {
+                    0.0
+                } else {
+                    count as f64 * 100.0 / total as f64
+                }
+            };
+            let name = &state.config.name;
+            metrics.custom.insert(format!("{}.requests_per_s", name), total as f64 / seconds);
+            metrics.custom.insert(format!("{}.client_errors_pct", name), share(400..500));
+            metrics.custom.insert(format!("{}.server_errors_pct", name), share(500..600));
+        }
+        
+        Ok(())
+    }
+    
+    fn anomalies(&self) -> Vec<String> {
+        self.logs
+            .iter()
+            .flat_map(|state| {
+                state.scanners().into_iter().map(move |client| {
+                    format!(
+                        "Web Scanner: {} on {} made {}+ 404s in {}s",
+                        client, state.config.name, state.config.scanner_404s, state.config.window
+                    )
+                })
+            })
+            .collect()
+    }
+    
+    fn details(&self) -> Option<Value> {
+        if self.logs.is_empty() {
+            return None;
+        }
+        let logs: BTreeMap<&str, Value> = self
+            .logs
+            .iter()
+            .map(|state| {
+                let mut clients: HashMap<IpAddr, (usize, usize)> = HashMap::new();
+                for request in &state.requests {
+                    let entry = clients.entry(request.client).or_default();
+                    entry.0 += 1;
+                    if request.status == 404 {
+                        entry.1 += 1;
+                    }
+                }
+                let mut top: Vec<(IpAddr, (usize, usize))> = clients.into_iter().collect();
+                top.sort_by_key(|(client, (requests, _))| (std::cmp::Reverse(*requests), *client));
+                top.truncate(TOP_CLIENTS);
+                
+                let top: Vec<Value> = top
+                    .into_iter()
+                    .map(|(client, (requests, not_found))| json!({ "client": client, "requests": requests, "not_found": not_found }))
+                    .collect();
+                (state.config.name.as_str(), json!({ "requests": state.requests.len(), "top_clients": top }))
+            })
+            .collect();
+        Some(json!(logs))
+    }
+}
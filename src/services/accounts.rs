@@ -0,0 +1,189 @@
+use crate::utils::data_dir;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::SystemTime;
+use tracing::warn;
+
+pub const ACCOUNTS_FILE: &str = "data/accounts.json";
+
+const PASSWD: &str = "/etc/passwd";
+const GROUP: &str = "/etc/group";
+const SHADOW: &str = "/etc/shadow";
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct AccountState {
+    /// User name to UID.
+    users: BTreeMap<String, u32>,
+    /// Group name to members, including users with it as their primary group.
+    groups: BTreeMap<String, BTreeSet<String>>,
+    /// Modification time of `/etc/shadow`, which is usually not readable.
+    shadow_modified: Option<u64>,
+}
+
+/// Reports new accounts, UID 0 accounts, password database changes and membership
+/// changes of privileged groups.
+pub struct AccountWatcher {
+    state: Option<AccountState>,
+    last_modified: Vec<Option<SystemTime>>,
+}
+
+impl AccountWatcher {
+    /// Starts from the state saved by earlier runs, so changes made while stopped are reported.
+    pub fn new() -> Self {
+        let state = std::fs::read_to_string(ACCOUNTS_FILE)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok());
+        
+        Self {
+            state,
+            last_modified: Vec::new(),
+        }
+    }
+    
+    /// The scan of the next cycle, to be read without holding the monitor.
+    pub fn next_poll(&self) -> AccountPoll {
+        AccountPoll {
+            last_modified: self.last_modified.clone(),
+        }
+    }
+    
+    /// Keeps the account databases an [`AccountPoll`] read and describes the differences.
+    pub fn apply(&mut self, scan: AccountScan, sensitive_groups: &[String]) -> Vec<String> {
+        self.last_modified = scan.modified;
+        let current = scan.state;
+        
+        let alerts = match &self.state {
+            Some(previous) => describe_changes(previous, &current, sensitive_groups),
+            None => Vec::new(),
+        };
+        
+        if self.state.as_ref() != Some(&current) {
+            if let Err(e) = save(&current) {
+                warn!("Failed to save account state: {}", e);
+            }
+            self.state = Some(current);
+        }
+        
+        alerts
+    }
+}
+
+/// Modification times of the account databases at the previous scan.
+pub struct AccountPoll {
+    last_modified: Vec<Option<SystemTime>>,
+}
+
+/// What an [`AccountPoll`] read.
+pub struct AccountScan {
+    modified: Vec<Option<SystemTime>>,
+    state: AccountState,
+}
+
+impl AccountPoll {
+    /// Re-reads the account databases, or nothing when they are unchanged.
+    pub async fn run(self) -> Option<AccountScan> {
+        let scan = tokio::task::spawn_blocking(move || {
+            let modified: Vec<Option<SystemTime>> = [PASSWD, GROUP, SHADOW]
+                .iter()
+                .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+                .collect();
+            (modified != self.last_modified).then(|| AccountScan {
+                modified,
+                state: read_state(),
+            })
+        });
+        scan.await.ok().flatten()
+    }
+}
+
+fn describe_changes(previous: &AccountState, current: &AccountState, sensitive_groups: &[String]) -> Vec<String> {
+    let mut alerts = Vec::new();
+    
+    for (user, uid) in &current.users {
+        match previous.users.get(user) {
+            None if *uid == 0 => alerts.push(format!("Root Account Added: {} uid=0", user)),
+            None => alerts.push(format!("New Account: {} uid={}", user, uid)),
+            Some(old_uid) if *uid == 0 && *old_uid != 0 => {
+                alerts.push(format!("Root Account Added: {} uid={}->0", user, old_uid))
+            }
+            Some(old_uid) if old_uid != uid => alerts.push(format!("Account Changed: {} uid={}->{}", user, old_uid, uid)),
+            _ => {}
+        }
+    }
+    for user in previous.users.keys().filter(|user| !current.users.contains_key(*user)) {
+        alerts.push(format!("Account Changed: {} removed", user));
+    }
+    
+    let empty = BTreeSet::new();
+    for group in sensitive_groups {
+        let before = previous.groups.get(group).unwrap_or(&empty);
+        let after = current.groups.get(group).unwrap_or(&empty);
+        
+        let added: Vec<&str> = after.difference(before).map(String::as_str).collect();
+        let removed: Vec<&str> = before.difference(after).map(String::as_str).collect();
+        if !added.is_empty() {
+            alerts.push(format!("Privileged Group Changed: {} +{}", group, added.join(",+")));
+        }
+        if !removed.is_empty() {
+            alerts.push(format!("Account Changed: {} -{}", group, removed.join(",-")));
+        }
+    }
+    
+    if previous.shadow_modified.is_some() && current.shadow_modified != previous.shadow_modified {
+        alerts.push(format!("Account Changed: {} modified", SHADOW));
+    }
+    
+    alerts
+}
+
+fn read_state() -> AccountState {
+    let passwd = std::fs::read_to_string(PASSWD).unwrap_or_default();
+    let group = std::fs::read_to_string(GROUP).unwrap_or_default();
+    
+    let mut users = BTreeMap::new();
+    let mut primary_groups: Vec<(u32, String)> = Vec::new();
+    for fields in passwd.lines().map(|line| line.split(':').collect::<Vec<_>>()) {
+        let (Some(name), Some(uid), Some(gid)) = (fields.first(), fields.get(2), fields.get(3)) else {
+            continue;
+        };
+        let (Ok(uid), Ok(gid)) = (uid.parse::<u32>(), gid.parse::<u32>()) else {
+            continue;
+        };
+        users.insert(name.to_string(), uid);
+        primary_groups.push((gid, name.to_string()));
+    }
+    
+    let mut groups = BTreeMap::new();
+    for fields in group.lines().map(|line| line.split(':').collect::<Vec<_>>()) {
+        let (Some(name), Some(gid), Some(members)) = (fields.first(), fields.get(2), fields.get(3)) else {
+            continue;
+        };
+        let mut all: BTreeSet<String> = members
+            .split(',')
+            .filter(|member| !member.is_empty())
+            .map(str::to_string)
+            .collect();
+        if let Ok(gid) = gid.parse::<u32>() {
+            all.extend(primary_groups.iter().filter(|(primary, _)| *primary == gid).map(|(_, user)| user.clone()));
+        }
+        groups.insert(name.to_string(), all);
+    }
+    
+    let shadow_modified = std::fs::metadata(SHADOW)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|age| age.as_secs());
+    
+    AccountState {
+        users,
+        groups,
+        shadow_modified,
+    }
+}
+
+fn save(state: &AccountState) -> anyhow::Result<()> {
+    data_dir::ensure()?;
+    std::fs::write(ACCOUNTS_FILE, serde_json::to_string(state)?)?;
+    Ok(())
+}
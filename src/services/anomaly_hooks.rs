@@ -0,0 +1,93 @@
+use crate::models::config::AnomalyWebhook;
+use crate::services::monitor::{anomaly_severity, anomaly_subject};
+use chrono::Utc;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+const DELIVERY_TIMEOUT_SECS: u64 = 10;
+pub const SIGNATURE_HEADER: &str = "X-Signature-256";
+
+/// Machine-readable account of an anomaly being raised or cleared. `correlation_id`
+/// is shared by the two documents of one occurrence.
+pub fn anomaly_document(event: &str, anomaly: &str, correlation_id: &str, incident: Option<u64>) -> Value {
+    let subject = anomaly_subject(anomaly);
+    let (value, baseline) = match anomaly.split_once(" (Normal: ") {
+        Some((head, normal)) => {
+            let value = head.rsplit_once(' ').and_then(|(_, value)| value.parse::<f64>().ok());
+            (value, parse_normal(normal.split_once(')').map_or(normal, |(normal, _)| normal)))
+        }
+        None => (None, Value::Null),
+    };
+    
+    json!({
+        "event": event,
+        "correlation_id": correlation_id,
+        "metric": subject.strip_prefix("Anomaly: ").unwrap_or(&subject),
+        "value": value,
+        "baseline": baseline,
+        "severity": anomaly_severity(anomaly),
+        "message": anomaly,
+        "incident": incident,
+        "host": sysinfo::System::host_name().unwrap_or_default(),
+        "timestamp": Utc::now(),
+    })
+}
+
+/// `10.0±2.0` from a learned baseline, or `<90` from a configured limit.
+fn parse_normal(normal: &str) -> Value {
+    if let Some((mean, std)) = normal.split_once('±') {
+        if let (Ok(mean), Ok(std)) = (mean.parse::<f64>(), std.parse::<f64>()) {
+            return json!({ "mean": mean, "std": std });
+        }
+    }
+    match normal.strip_prefix('<').map(str::parse::<f64>) {
+        Some(Ok(limit)) => json!({ "limit": limit }),
+        _ => Value::Null,
+    }
+}
+
+/// Posts the documents to every anomaly webhook in the background, signing each body
+/// with the webhook's secret so receivers can verify where it came from.
+pub fn deliver(webhooks: &[AnomalyWebhook], documents: Vec<Value>) {
+    if webhooks.is_empty() || documents.is_empty() {
+        return;
+    }
+    let webhooks = webhooks.to_vec();
+    
+    tokio::spawn(async move {
+        let Ok(client) = reqwest::Client::builder()
+            .timeout(Duration::from_secs(DELIVERY_TIMEOUT_SECS))
+            .build()
+        else {
+            return;
+        };
+        
+        for document in &documents {
+            let body = document.to_string();
+            for webhook in &webhooks {
+                let mut request = client
+                    .post(&webhook.url)
+                    .header(reqwest::header::CONTENT_TYPE, "application/json");
+                if let Some(secret) = webhook.secret.as_deref().filter(|secret| !secret.is_empty()) {
+                    request = request.header(SIGNATURE_HEADER, format!("sha256={}", sign(secret, &body)));
+                }
+                
+                match request.body(body.clone()).send().await {
+                    Ok(response) if response.status().is_success() => {
+                        debug!("Delivered anomaly {} to {}", document["event"], webhook.url)
+                    }
+                    Ok(response) => warn!("Anomaly webhook {} returned {}", webhook.url, response.status()),
+                    Err(e) => warn!("Anomaly webhook {} failed: {}", webhook.url, e),
+                }
+            }
+        }
+    });
+}
+
+/// Hex HMAC-SHA256 of the body.
+fn sign(secret: &str, body: &str) -> String {
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = ring::hmac::sign(&key, body.as_bytes());
+    tag.as_ref().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
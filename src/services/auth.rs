@@ -1,4 +1,8 @@
-use crate::models::auth::{LoginRequest, LoginResponse, PasswordChangeRequest, User};
+use crate::models::auth::{LoginRequest, LoginResponse, NotificationPreferences, PasswordChangeRequest, User};
+use crate::models::dashboard::DashboardLayout;
+use crate::models::metrics::AnomalySeverity;
+use crate::utils::data_dir;
+use crate::utils::i18n::Language;
 use anyhow::{anyhow, Result};
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
@@ -7,11 +11,12 @@ use argon2::{
 use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 const JWT_SECRET: &[u8] = b"shaydz-secret-key-change-in-production";
+const USERS_FILE: &str = "data/users.json";
 const SESSION_DURATION_HOURS: i64 = 1;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -28,16 +33,8 @@ pub struct AuthService {
 
 impl AuthService {
     pub fn new() -> Self {
-        let mut users = HashMap::new();
-        
-        // Create default admin user
-        let default_user = User {
-            username: "admin".to_string(),
-            password_hash: "$argon2i$v=19$m=4096,t=3,p=1$SHhhZFpNdWx0aU1vbml0b3I$V2VsY29tZVRvU2hheWRa".to_string(),
-            created_at: Utc::now(),
-            last_login: None,
-        };
-        users.insert("admin".to_string(), default_user);
+        // Accounts saved by a previous run (or restored from a backup) take precedence
+        let users = load_users().unwrap_or_else(default_users);
         
         Self {
             users: Arc::new(RwLock::new(users)),
@@ -45,6 +42,24 @@ impl AuthService {
         }
     }
     
+    pub async fn users(&self) -> Vec<User> {
+        self.users.read().await.values().cloned().collect()
+    }
+    
+    /// Replaces every account (e.g. from a backup) and ends all sessions.
+    pub async fn replace_users(&self, users: Vec<User>) -> Result<()> {
+        if users.is_empty() {
+            return Err(anyhow!("Refusing to restore an empty user list"));
+        }
+        
+        let users: HashMap<String, User> = users.into_iter().map(|u| (u.username.clone(), u)).collect();
+        save_users(&users)?;
+        *self.users.write().await = users;
+        self.sessions.write().await.clear();
+        
+        Ok(())
+    }
+    
     pub async fn login(&self, req: LoginRequest) -> Result<LoginResponse> {
         // Validate input length
         if req.username.len() > 64 || req.password.len() > 128 {
@@ -108,6 +123,70 @@ impl AuthService {
         }
     }
     
+    pub async fn is_admin(&self, username: &str) -> bool {
+        let users = self.users.read().await;
+        users.get(username).map(|user| user.is_admin).unwrap_or(false)
+    }
+    
+    /// The dashboard layout `username` saved, if any.
+    pub async fn dashboard_layout(&self, username: &str) -> Option<DashboardLayout> {
+        self.users.read().await.get(username).and_then(|user| user.dashboard.clone())
+    }
+    
+    /// Saves `username`'s dashboard layout, or goes back to the default with `None`.
+    pub async fn set_dashboard_layout(&self, username: &str, layout: Option<DashboardLayout>) -> Result<()> {
+        let mut users = self.users.write().await;
+        let user = users.get_mut(username).ok_or_else(|| anyhow!("User not found"))?;
+        user.dashboard = layout;
+        save_users(&users)
+    }
+    
+    /// The language `username` chose for the web pages, if any.
+    pub async fn language(&self, username: &str) -> Option<Language> {
+        self.users.read().await.get(username).and_then(|user| user.language)
+    }
+    
+    /// Saves `username`'s language, or follows the server's with `None`.
+    pub async fn set_language(&self, username: &str, language: Option<Language>) -> Result<()> {
+        let mut users = self.users.write().await;
+        let user = users.get_mut(username).ok_or_else(|| anyhow!("User not found"))?;
+        user.language = language;
+        save_users(&users)
+    }
+    
+    /// The alerts `username` chose to be sent, or the defaults.
+    pub async fn notification_preferences(&self, username: &str) -> NotificationPreferences {
+        self.users
+            .read()
+            .await
+            .get(username)
+            .and_then(|user| user.notifications.clone())
+            .unwrap_or_default()
+    }
+    
+    /// Saves `username`'s notification preferences, or goes back to the defaults with `None`.
+    pub async fn set_notification_preferences(
+        &self,
+        username: &str,
+        preferences: Option<NotificationPreferences>,
+    ) -> Result<()> {
+        let mut users = self.users.write().await;
+        let user = users.get_mut(username).ok_or_else(|| anyhow!("User not found"))?;
+        user.notifications = preferences;
+        save_users(&users)
+    }
+    
+    /// Users who want an alert of `severity` about a host in `groups` on `channel`.
+    pub async fn recipients(&self, channel: &str, severity: AnomalySeverity, groups: &[String]) -> HashSet<String> {
+        self.users
+            .read()
+            .await
+            .values()
+            .filter(|user| user.notifications.clone().unwrap_or_default().wants(channel, severity, groups))
+            .map(|user| user.username.clone())
+            .collect()
+    }
+    
     pub async fn logout(&self, token: &str) -> Result<()> {
         let mut sessions = self.sessions.write().await;
         sessions.remove(token);
@@ -141,6 +220,7 @@ impl AuthService {
                         .map_err(|e| anyhow!("Password hashing failed: {:?}", e))?;
                     
                     user.password_hash = new_hash.to_string();
+                    save_users(&users)?;
                     return Ok(());
                 }
             }
@@ -150,3 +230,52 @@ impl AuthService {
         Err(anyhow!("User not found"))
     }
 }
+
+fn default_users() -> HashMap<String, User> {
+    let mut users = HashMap::new();
+    
+    // Create default admin user
+    let default_user = User {
+        username: "admin".to_string(),
+        password_hash: "$argon2i$v=19$m=4096,t=3,p=1$SHhhZFpNdWx0aU1vbml0b3I$V2VsY29tZVRvU2hheWRa".to_string(),
+        created_at: Utc::now(),
+        last_login: None,
+        is_admin: true,
+        dashboard: None,
+        language: None,
+        notifications: None,
+    };
+    users.insert("admin".to_string(), default_user);
+    
+    users
+}
+
+/// Whether a token given by a client is the expected one, taking the same time
+/// wherever they differ. Both are hashed first so neither length shows either.
+pub fn tokens_match(given: &str, expected: &str) -> bool {
+    let given = ring::digest::digest(&ring::digest::SHA256, given.as_bytes());
+    let expected = ring::digest::digest(&ring::digest::SHA256, expected.as_bytes());
+    given.as_ref().iter().zip(expected.as_ref()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+fn load_users() -> Option<HashMap<String, User>> {
+    let content = std::fs::read_to_string(USERS_FILE).ok()?;
+    let users: Vec<User> = serde_json::from_str(&content).ok()?;
+    
+    if users.is_empty() {
+        return None;
+    }
+    Some(users.into_iter().map(|u| (u.username.clone(), u)).collect())
+}
+
+/// Persists accounts with an atomic write so a crash can't leave a truncated file.
+pub fn save_users(users: &HashMap<String, User>) -> Result<()> {
+    data_dir::ensure()?;
+    
+    let list: Vec<&User> = users.values().collect();
+    let temp_file = format!("{}.tmp", USERS_FILE);
+    std::fs::write(&temp_file, serde_json::to_string_pretty(&list)?)?;
+    std::fs::rename(&temp_file, USERS_FILE)?;
+    
+    Ok(())
+}
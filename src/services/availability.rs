@@ -0,0 +1,154 @@
+use crate::models::availability::{AvailabilityReport, AvailabilityTransition, Outage, ReportPeriod};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+use tokio::fs::{self, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+pub const AVAILABILITY_FILE: &str = "data/availability.jsonl";
+
+/// Persistent record of check up/down transitions, the source of availability reports.
+pub struct AvailabilityLog {
+    path: PathBuf,
+    write_lock: Mutex<()>,
+}
+
+impl AvailabilityLog {
+    pub fn new() -> Self {
+        Self {
+            path: PathBuf::from(AVAILABILITY_FILE),
+            write_lock: Mutex::new(()),
+        }
+    }
+    
+    /// Records a transition. Failures are logged rather than returned so they can
+    /// never interrupt monitoring.
+    pub async fn record(&self, check: &str, up: bool) {
+        let transition = AvailabilityTransition {
+            timestamp: Utc::now(),
+            check: check.to_string(),
+            up,
+        };
+        
+        if let Err(e) = self.append(&transition).await {
+            warn!("Failed to write availability log: {}", e);
+        }
+    }
+    
+    async fn append(&self, transition: &AvailabilityTransition) -> Result<()> {
+        let _guard = self.write_lock.lock().await;
+        
+        let mut line = serde_json::to_string(transition)?;
+        line.push('\n');
+        
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        
+        Ok(())
+    }
+    
+    async fn transitions(&self) -> Vec<AvailabilityTransition> {
+        let content = fs::read_to_string(&self.path).await.unwrap_or_default();
+        content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+    
+    /// Availability of each check over the last `period`, for the given checks.
+    pub async fn report(&self, checks: &[String], period: ReportPeriod) -> Vec<AvailabilityReport> {
+        let transitions = self.transitions().await;
+        let end = Utc::now();
+        let start = end - period.duration();
+        
+        checks
+            .iter()
+            .map(|check| {
+                let history: Vec<&AvailabilityTransition> =
+                    transitions.iter().filter(|t| &t.check == check).collect();
+                check_report(check, &history, period, start, end)
+            })
+            .collect()
+    }
+}
+
+fn check_report(
+    check: &str,
+    history: &[&AvailabilityTransition],
+    period: ReportPeriod,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> AvailabilityReport {
+    // State carried into the window from the last transition before it
+    let mut state = history.iter().rev().find(|t| t.timestamp < start).map(|t| t.up);
+    let mut since = start;
+    let mut observed_secs = 0;
+    let mut up_secs = 0;
+    let mut outages = Vec::new();
+    let mut outage_start = (state == Some(false)).then_some(start);
+    
+    let within = history.iter().filter(|t| t.timestamp >= start && t.timestamp <= end);
+    for transition in within {
+        if let Some(up) = state {
+            let secs = (transition.timestamp - since).num_seconds();
+            observed_secs += secs;
+            if up {
+                up_secs += secs;
+            }
+        }
+        
+        match (outage_start, transition.up) {
+            (None, false) => outage_start = Some(transition.timestamp),
+            (Some(down_since), true) => {
+                outages.push(Outage {
+                    start: down_since,
+                    end: Some(transition.timestamp),
+                    duration_secs: (transition.timestamp - down_since).num_seconds(),
+                });
+                outage_start = None;
+            }
+            _ => {}
+        }
+        
+        state = Some(transition.up);
+        since = transition.timestamp;
+    }
+    
+    if let Some(up) = state {
+        let secs = (end - since).num_seconds();
+        observed_secs += secs;
+        if up {
+            up_secs += secs;
+        }
+    }
+    
+    let recovered: Vec<i64> = outages.iter().map(|o| o.duration_secs).collect();
+    let mttr_secs = (!recovered.is_empty()).then(|| recovered.iter().sum::<i64>() / recovered.len() as i64);
+    
+    if let Some(down_since) = outage_start {
+        outages.push(Outage {
+            start: down_since,
+            end: None,
+            duration_secs: (end - down_since).num_seconds(),
+        });
+    }
+    
+    AvailabilityReport {
+        check: check.to_string(),
+        period,
+        uptime_percent: (observed_secs > 0).then(|| up_secs as f64 / observed_secs as f64 * 100.0),
+        downtime_secs: outages.iter().map(|o| o.duration_secs).sum(),
+        mttr_secs,
+        outages,
+    }
+}
@@ -0,0 +1,207 @@
+use crate::models::backup::{BaselineExport, StateBackup, BACKUP_FORMAT_VERSION};
+use crate::models::config::{AppConfig, CONFIG_FILES};
+use crate::services::auth::{save_users, AuthService};
+use crate::services::config::ConfigService;
+use crate::services::monitor::{MonitorService, BASELINE_FILE};
+use crate::services::support_bundle::BUNDLE_DIR;
+use crate::utils::data_dir::{self, DATA_DIR};
+use crate::utils::zip;
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use tokio::sync::RwLock;
+
+/// Captures configuration, accounts and learned baselines from the running services.
+pub async fn create_backup(
+    config: &ConfigService,
+    auth: &AuthService,
+    monitor: &RwLock<MonitorService>,
+) -> StateBackup {
+    let monitor = monitor.read().await;
+    
+    StateBackup {
+        version: BACKUP_FORMAT_VERSION,
+        created_at: Utc::now(),
+        config: config.current().await,
+        users: auth.users().await,
+        baselines: monitor.baselines().clone(),
+        feedback: monitor.feedback().to_vec(),
+    }
+}
+
+/// Name of the backup itself inside a backup archive.
+pub const BACKUP_ENTRY: &str = "backup.json";
+
+/// Largest backup upload accepted by /api/restore.
+pub const RESTORE_BODY_LIMIT: usize = 256 * 1024 * 1024;
+
+// Most a backup archive may inflate to, however its entries compress
+const MAX_RESTORE_SIZE: u64 = 4 * RESTORE_BODY_LIMIT as u64;
+
+/// The files written into a backup archive after `backup.json`, as archive name and
+/// path: the configuration files under `config/` and everything under `data/` apart
+/// from support bundles and half-written `.tmp` files.
+pub fn archive_files() -> Vec<(String, PathBuf)> {
+    let mut files = Vec::new();
+    if let Some(source) = AppConfig::source_path() {
+        for path in AppConfig::source_files(source) {
+            if let Some(name) = path.file_name() {
+                files.push((format!("config/{}", name.to_string_lossy()), path));
+            }
+        }
+    }
+    collect_data(Path::new(DATA_DIR), &mut files);
+    files
+}
+
+fn collect_data(dir: &Path, files: &mut Vec<(String, PathBuf)>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+    for path in paths {
+        if path == Path::new(BUNDLE_DIR) || path.extension().is_some_and(|e| e == "tmp") {
+            continue;
+        }
+        // Links are not followed, so the archive holds only what the monitor wrote
+        let Ok(metadata) = std::fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            collect_data(&path, files);
+        } else if metadata.is_file() {
+            files.push((path.to_string_lossy().into_owned(), path));
+        }
+    }
+}
+
+/// Reads a backup from the bytes of either a backup archive or a plain JSON backup
+/// from an earlier version.
+pub fn parse_backup(bytes: &[u8]) -> Result<StateBackup> {
+    if !bytes.starts_with(b"PK") {
+        return Ok(serde_json::from_slice(bytes)?);
+    }
+    let entries = zip::read_entries(bytes, MAX_RESTORE_SIZE)?;
+    let (_, json) = entries
+        .iter()
+        .find(|(name, _)| name == BACKUP_ENTRY)
+        .ok_or_else(|| anyhow!("Archive has no {}", BACKUP_ENTRY))?;
+    Ok(serde_json::from_slice(json)?)
+}
+
+/// Restores a backup into the running services and persists it. Only `backup.json`
+/// of an archive applies here: the other files under `data/` are held in memory by
+/// the running services, so they are restored with `--restore` while stopped.
+pub async fn restore_backup(
+    backup: StateBackup,
+    config: &ConfigService,
+    auth: &AuthService,
+    monitor: &RwLock<MonitorService>,
+) -> Result<()> {
+    check_version(backup.version)?;
+    
+    config.update(backup.config, "backup restore").await?;
+    auth.replace_users(backup.users).await?;
+    
+    let mut monitor = monitor.write().await;
+    monitor.restore_baselines(backup.baselines, backup.feedback);
+    monitor.save_baseline().await?;
+    
+    Ok(())
+}
+
+/// Restores a backup file before the services start (`--restore <file>`). An archive
+/// also puts back every file it holds under `data/`, such as the uptime record,
+/// incidents and firewall blocks; `backup.json` is applied last.
+pub fn restore_backup_file(path: &str) -> Result<()> {
+    let bytes = std::fs::read(path)?;
+    let backup = parse_backup(&bytes)?;
+    check_version(backup.version)?;
+    backup.config.validate().map_err(|errors| anyhow!(errors.join("; ")))?;
+    let users: HashMap<_, _> = backup.users.into_iter().map(|u| (u.username.clone(), u)).collect();
+    if users.is_empty() {
+        return Err(anyhow!("Backup contains no user accounts"));
+    }
+    
+    if bytes.starts_with(b"PK") {
+        let entries: Vec<_> = zip::read_entries(&bytes, MAX_RESTORE_SIZE)?
+            .into_iter()
+            .filter(|(name, _)| Path::new(name).starts_with(DATA_DIR))
+            .collect();
+        // Every name is checked before anything is written, so a bad archive changes nothing
+        if let Some((name, _)) = entries
+            .iter()
+            .find(|(name, _)| !Path::new(name).components().all(|c| matches!(c, Component::Normal(_))))
+        {
+            return Err(anyhow!("Refusing to restore {}", name));
+        }
+        for (name, data) in entries {
+            let path = Path::new(&name);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, data)?;
+        }
+    }
+    
+    backup.config.save_to(AppConfig::source_path().unwrap_or(CONFIG_FILES[0]))?;
+    save_users(&users)?;
+    
+    let data = serde_json::json!({
+        "baseline": backup.baselines,
+        "feedback": backup.feedback,
+    });
+    data_dir::ensure()?;
+    let temp_file = format!("{}.tmp", BASELINE_FILE);
+    std::fs::write(&temp_file, serde_json::to_string_pretty(&data)?)?;
+    std::fs::rename(&temp_file, BASELINE_FILE)?;
+    
+    Ok(())
+}
+
+/// Captures the learned baselines and feedback of the running monitor.
+pub async fn export_baselines(monitor: &RwLock<MonitorService>) -> BaselineExport {
+    let monitor = monitor.read().await;
+    
+    BaselineExport {
+        version: BACKUP_FORMAT_VERSION,
+        created_at: Utc::now(),
+        hostname: sysinfo::System::host_name().unwrap_or_default(),
+        baselines: monitor.baselines().clone(),
+        feedback: monitor.feedback().to_vec(),
+    }
+}
+
+/// Loads exported baselines into the running monitor and persists them. Baselines of
+/// the same metric are overwritten and others kept, unless `replace` drops them all
+/// first. Returns how many baselines were imported.
+pub async fn import_baselines(export: BaselineExport, replace: bool, monitor: &RwLock<MonitorService>) -> Result<usize> {
+    check_version(export.version)?;
+    if let Some((metric, _)) = export
+        .baselines
+        .iter()
+        .find(|(_, stats)| !stats.mean.is_finite() || !stats.std.is_finite() || stats.std < 0.0)
+    {
+        return Err(anyhow!("Invalid baseline for {}", metric));
+    }
+    
+    let count = export.baselines.len();
+    let mut monitor = monitor.write().await;
+    monitor.import_baselines(export.baselines, export.feedback, replace);
+    monitor.save_baseline().await?;
+    
+    Ok(count)
+}
+
+fn check_version(version: u32) -> Result<()> {
+    if version > BACKUP_FORMAT_VERSION {
+        return Err(anyhow!(
+            "Backup format {} is newer than supported version {}",
+            version,
+            BACKUP_FORMAT_VERSION
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,264 @@
+use crate::models::config::{BackupConfig, BackupKind};
+use crate::utils::{glob, process};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::warn;
+
+const DEFAULT_REPOSITORY_INTERVAL: u64 = 3600;
+// How long restic or borg may take to list a repository; both can hang on an
+// unreachable ssh repository, a hung mount or a lock
+const REPOSITORY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The newest backup found by the latest check.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupStatus {
+    pub checked_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// The file or snapshot the other fields describe.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+struct Artifact {
+    name: String,
+    modified: DateTime<Utc>,
+    size: Option<u64>,
+}
+
+/// Checks declared backups for recency and size.
+pub struct BackupWatcher {
+    status: BTreeMap<String, BackupStatus>,
+}
+
+impl BackupWatcher {
+    pub fn new() -> Self {
+        Self {
+            status: BTreeMap::new(),
+        }
+    }
+    
+    /// Picks the backups that are due a check, to be run without holding the monitor.
+    pub fn next_poll(&self, backups: &[BackupConfig]) -> BackupPoll {
+        let now = Utc::now();
+        let mut due = Vec::new();
+        for backup in backups {
+            let interval = match (backup.interval, backup.kind) {
+                (Some(interval), _) => interval,
+                (None, BackupKind::Path) => 0,
+                (None, _) => DEFAULT_REPOSITORY_INTERVAL,
+            };
+            let status = self.status.get(&backup.name);
+            if status.map_or(true, |status| (now - status.checked_at).num_seconds() >= interval as i64) {
+                let failing = status.is_some_and(|status| status.error.is_some());
+                due.push((backup.clone(), failing));
+            }
+        }
+        
+        BackupPoll { backups: due, at: now }
+    }
+    
+    /// Keeps the statuses a [`BackupPoll`] found.
+    pub fn apply(&mut self, polled: BackupStatuses, backups: &[BackupConfig]) {
+        self.status.retain(|name, _| backups.iter().any(|backup| &backup.name == name));
+        for (name, status) in polled.statuses {
+            // A reload may have removed the backup while it was checked
+            if backups.iter().any(|backup| backup.name == name) {
+                self.status.insert(name, status);
+            }
+        }
+    }
+    
+    /// Backups that are missing, older than allowed or smaller than expected.
+    pub fn anomalies(&self, backups: &[BackupConfig]) -> Vec<String> {
+        let now = Utc::now();
+        let mut anomalies = Vec::new();
+        
+        for backup in backups {
+            let Some(status) = self.status.get(&backup.name) else {
+                continue;
+            };
+            let Some(latest) = status.latest else {
+                let problem = if backup.kind == BackupKind::Path { "not found" } else { "unreadable" };
+                anomalies.push(format!("Backup Missing: {} {}", backup.name, problem));
+                continue;
+            };
+            
+            if (now - latest).num_seconds() > backup.max_age as i64 {
+                anomalies.push(format!(
+                    "Backup Stale: {} since {}",
+                    backup.name,
+                    latest.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+                ));
+            }
+            if let (Some(min_size), Some(size)) = (backup.min_size, status.size) {
+                if size < min_size {
+                    anomalies.push(format!("Backup Too Small: {} {}B", backup.name, size));
+                }
+            }
+        }
+        
+        anomalies
+    }
+    
+    pub fn status(&self) -> &BTreeMap<String, BackupStatus> {
+        &self.status
+    }
+}
+
+/// Backups due a check, each with whether its previous check failed.
+pub struct BackupPoll {
+    backups: Vec<(BackupConfig, bool)>,
+    at: DateTime<Utc>,
+}
+
+/// What a [`BackupPoll`] found, by backup.
+pub struct BackupStatuses {
+    statuses: Vec<(String, BackupStatus)>,
+}
+
+impl BackupPoll {
+    pub async fn run(self) -> BackupStatuses {
+        let mut statuses = Vec::new();
+        for (backup, failing) in self.backups {
+            let result = match backup.kind {
+                BackupKind::Path => {
+                    let path = backup.path.clone();
+                    tokio::task::spawn_blocking(move || newest_file(&path))
+                        .await
+                        .unwrap_or_else(|e| Err(e.into()))
+                }
+                BackupKind::Restic => latest_restic_snapshot(&backup).await,
+                BackupKind::Borg => latest_borg_archive(&backup).await,
+            };
+            let status = match result {
+                Ok(artifact) => BackupStatus {
+                    checked_at: self.at,
+                    latest: Some(artifact.modified),
+                    size: artifact.size,
+                    artifact: Some(artifact.name),
+                    error: None,
+                },
+                Err(e) => {
+                    // Only log when the problem first appears, paths are checked every cycle
+                    if !failing {
+                        warn!("Backup {} check failed: {}", backup.name, e);
+                    }
+                    BackupStatus {
+                        checked_at: self.at,
+                        latest: None,
+                        size: None,
+                        artifact: None,
+                        error: Some(e.to_string()),
+                    }
+                }
+            };
+            statuses.push((backup.name, status));
+        }
+        
+        BackupStatuses { statuses }
+    }
+}
+
+/// The most recently modified file matching a pattern, or entry of a directory.
+fn newest_file(path: &str) -> Result<Artifact> {
+    let candidates = if Path::new(path).is_dir() {
+        std::fs::read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .collect()
+    } else {
+        glob::expand(Path::new("/"), path)
+    };
+    
+    candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let metadata = std::fs::metadata(&candidate).ok()?;
+            Some(Artifact {
+                name: candidate.to_string_lossy().to_string(),
+                modified: DateTime::<Utc>::from(metadata.modified().ok()?),
+                size: metadata.is_file().then_some(metadata.len()),
+            })
+        })
+        .max_by_key(|artifact| artifact.modified)
+        .ok_or_else(|| anyhow!("nothing matches {}", path))
+}
+
+async fn latest_restic_snapshot(backup: &BackupConfig) -> Result<Artifact> {
+    let mut command = Command::new("restic");
+    command.args(["--repo", &backup.path, "snapshots", "--json", "--no-lock", "--latest", "1"]);
+    if let Some(password_file) = &backup.password_file {
+        command.args(["--password-file", password_file]);
+    }
+    let snapshots = run_json(command).await?;
+    
+    // One snapshot per host and path; the newest of them counts
+    let newest = snapshots
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|snapshot| {
+            let time = DateTime::parse_from_rfc3339(snapshot["time"].as_str()?).ok()?;
+            Some((time.with_timezone(&Utc), snapshot))
+        })
+        .max_by_key(|(time, _)| *time)
+        .ok_or_else(|| anyhow!("repository has no snapshots"))?;
+    
+    Ok(Artifact {
+        name: newest.1["short_id"].as_str().unwrap_or_default().to_string(),
+        modified: newest.0,
+        // Recorded by restic 0.17 and later
+        size: newest.1["summary"]["total_bytes_processed"].as_u64(),
+    })
+}
+
+async fn latest_borg_archive(backup: &BackupConfig) -> Result<Artifact> {
+    let mut command = Command::new("borg");
+    command.args(["info", "--json", "--bypass-lock", "--last", "1", &backup.path]);
+    if let Some(password_file) = &backup.password_file {
+        let passphrase = tokio::fs::read_to_string(password_file).await?;
+        command.env("BORG_PASSPHRASE", passphrase.trim_end_matches('\n'));
+    }
+    let info = run_json(command).await?;
+    
+    let archive = info["archives"]
+        .as_array()
+        .and_then(|archives| archives.first())
+        .ok_or_else(|| anyhow!("repository has no archives"))?;
+    // Borg reports local time without an offset
+    let start = archive["start"]
+        .as_str()
+        .and_then(|start| NaiveDateTime::parse_from_str(start, "%Y-%m-%dT%H:%M:%S%.f").ok())
+        .and_then(|start| Local.from_local_datetime(&start).earliest())
+        .ok_or_else(|| anyhow!("archive without a start time"))?;
+    
+    Ok(Artifact {
+        name: archive["name"].as_str().unwrap_or_default().to_string(),
+        modified: start.with_timezone(&Utc),
+        size: archive["stats"]["original_size"].as_u64(),
+    })
+}
+
+async fn run_json(mut command: Command) -> Result<Value> {
+    command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    let output = process::output_within(&mut command, REPOSITORY_TIMEOUT).await?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("{}", stderr.lines().last().unwrap_or("failed").trim()));
+    }
+    
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
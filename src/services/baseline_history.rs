@@ -0,0 +1,77 @@
+use crate::models::metrics::{BaselineSnapshot, BaselineStats};
+use crate::utils::data_dir;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use tokio::fs::{self, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tracing::warn;
+
+pub const BASELINE_HISTORY_FILE: &str = "data/baseline_history.jsonl";
+// Baselines drift over weeks, so a few snapshots a day are enough to chart it
+const SNAPSHOT_INTERVAL_HOURS: i64 = 6;
+
+/// Versioned snapshots of the learned baselines, for seeing how normal drifts over time.
+pub struct BaselineHistory {
+    /// Version and time of the newest snapshot.
+    last: Option<(u64, DateTime<Utc>)>,
+}
+
+impl BaselineHistory {
+    pub fn new() -> Self {
+        // Carry on numbering from the newest snapshot of a previous run
+        let last = std::fs::read_to_string(BASELINE_HISTORY_FILE)
+            .ok()
+            .and_then(|content| content.lines().rev().find_map(|line| serde_json::from_str::<BaselineSnapshot>(line).ok()))
+            .map(|snapshot| (snapshot.version, snapshot.learned_at));
+        
+        Self { last }
+    }
+    
+    /// Appends a snapshot once the interval since the last one has passed. A snapshot
+    /// that cannot be written is tried again next cycle.
+    pub async fn record_if_due(&mut self, baselines: &HashMap<String, BaselineStats>, window_size: usize, sample_count: usize) {
+        let now = Utc::now();
+        if baselines.is_empty()
+            || self.last.is_some_and(|(_, at)| now - at < Duration::hours(SNAPSHOT_INTERVAL_HOURS))
+        {
+            return;
+        }
+        
+        let snapshot = BaselineSnapshot {
+            version: self.last.map_or(1, |(version, _)| version + 1),
+            learned_at: now,
+            window_size,
+            sample_count,
+            baselines: baselines.iter().map(|(key, stats)| (key.clone(), stats.clone())).collect(),
+        };
+        match append(&snapshot).await {
+            Ok(()) => self.last = Some((snapshot.version, now)),
+            Err(e) => warn!("Failed to write baseline history: {}", e),
+        }
+    }
+}
+
+async fn append(snapshot: &BaselineSnapshot) -> Result<()> {
+    let mut line = serde_json::to_string(snapshot)?;
+    line.push('\n');
+    
+    data_dir::ensure_async().await?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(BASELINE_HISTORY_FILE)
+        .await?;
+    file.write_all(line.as_bytes()).await?;
+    
+    Ok(())
+}
+
+/// Every recorded snapshot, oldest first.
+pub async fn snapshots() -> Vec<BaselineSnapshot> {
+    let content = fs::read_to_string(BASELINE_HISTORY_FILE).await.unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
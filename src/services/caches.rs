@@ -0,0 +1,276 @@
+use crate::models::config::{CacheConfig, CacheKind, MonitoringConfig};
+use crate::models::metrics::SystemMetrics;
+use crate::services::collectors::Collector;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration, Instant};
+
+const MB: f64 = 1024.0 * 1024.0;
+const TIMEOUT_SECS: u64 = 5;
+// Redis answers INFO with a few kilobytes; anything far larger is not Redis
+const MAX_INFO_BYTES: usize = 1024 * 1024;
+
+/// Readings of one check. Counters only grow and are compared with the previous check.
+struct CacheStats {
+    ping_ms: f64,
+    gauges: Vec<(&'static str, f64)>,
+    evictions: f64,
+    hits: f64,
+    misses: f64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    last_run: Option<DateTime<Utc>>,
+    /// Time, evictions, hits and misses of the previous successful check.
+    counters: Option<(DateTime<Utc>, f64, f64, f64)>,
+    /// Readings of the latest check, repeated until the next one.
+    values: Vec<(String, f64)>,
+    error: Option<String>,
+}
+
+/// Latency, memory use, evictions and hit ratio of Redis and Memcached instances.
+pub struct CacheCollector {
+    caches: Vec<CacheConfig>,
+    state: BTreeMap<String, CacheState>,
+}
+
+impl CacheCollector {
+    pub fn new() -> Self {
+        Self {
+            caches: Vec::new(),
+            state: BTreeMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Collector for CacheCollector {
+    fn name(&self) -> &str {
+        "caches"
+    }
+    
+    fn configure(&mut self, config: &MonitoringConfig) {
+        // Another address is another server, whose counters cannot be compared
+        self.state.retain(|name, _| {
+            let address = address_of(&config.caches, name);
+            address.is_some() && address == address_of(&self.caches, name)
+        });
+        self.caches = config.caches.clone();
+    }
+    
+    async fn collect(&mut self, metrics: &mut SystemMetrics) -> Result<()> {
+        let now = Utc::now();
+        
+        for cache in &self.caches {
+            let state = self.state.entry(cache.name.clone()).or_default();
+            let due = state
+                .last_run
+                .map_or(true, |last| (now - last).num_seconds() >= cache.interval.unwrap_or(0) as i64);
+            if due {
+                state.last_run = Some(now);
+                let stats = timeout(Duration::from_secs(TIMEOUT_SECS), async {
+                    match cache.kind {
+                        CacheKind::Redis => redis_stats(cache).await,
+                        CacheKind::Memcached => memcached_stats(cache).await,
+                    }
+                })
+                .await
+                .unwrap_or_else(|_| Err(anyhow!("no answer within {}s", TIMEOUT_SECS)));
+                
+                match stats {
+                    Ok(stats) => {
+                        state.values = vec![(format!("{}.ping_ms", cache.name), stats.ping_ms)];
+                        state.values.extend(
+                            stats
+                                .gauges
+                                .iter()
+                                .map(|(measure, value)| (format!("{}.{}", cache.name, measure), *value)),
+                        );
+                        // Lower counts than last time mean Redis or Memcached restarted and began again from 0
+                        let previous = state.counters.replace((now, stats.evictions, stats.hits, stats.misses));
+                        if let Some((then, evictions, hits, misses)) = previous
+                            .filter(|&(then, evictions, hits, misses)| {
+                                then < now && stats.evictions >= evictions && stats.hits >= hits && stats.misses >= misses
+                            })
+                        {
+                            let seconds = (now - then).num_milliseconds() as f64 / 1000.0;
+                            state.values.push((
+                                format!("{}.evictions_per_s", cache.name),
+                                (stats.evictions - evictions) / seconds,
+                            ));
+                            let lookups = (stats.hits - hits) + (stats.misses - misses);
+                            if lookups > 0.0 {
+                                state.values.push((
+                                    format!("{}.hit_ratio_pct", cache.name),
+                                    (stats.hits - hits) * 100.0 / lookups,
+                                ));
+                            }
+                        }
+                        state.error = None;
+                    }
+                    Err(e) => {
+                        state.values.clear();
+                        state.counters = None;
+                        state.error = Some(e.to_string());
+                    }
+                }
+            }
+            
+            metrics.custom.extend(state.values.iter().cloned());
+        }
+        
+        // Errors stay with each cache for `anomalies` to raise as Cache Down
+        Ok(())
+    }
+    
+    fn anomalies(&self) -> Vec<String> {
+        self.state
+            .iter()
+            .filter_map(|(name, state)| Some(format!("Cache Down: {} {}", name, state.error.as_ref()?)))
+            .collect()
+    }
+}
+
+fn address_of<'a>(caches: &'a [CacheConfig], name: &str) -> Option<&'a str> {
+    caches.iter().find(|cache| cache.name == name).map(|cache| cache.address.as_str())
+}
+
+async fn redis_stats(cache: &CacheConfig) -> Result<CacheStats> {
+    let mut stream = BufReader::new(TcpStream::connect(&cache.address).await?);
+    
+    if !cache.password.is_empty() {
+        // The one-argument form also works before Redis 6 and its ACL users
+        let auth = match &cache.username {
+            Some(user) => redis_command(&["AUTH", user, &cache.password]),
+            None => redis_command(&["AUTH", &cache.password]),
+        };
+        stream.write_all(&auth).await?;
+        redis_reply(&mut stream).await?;
+    }
+    
+    let started = Instant::now();
+    stream.write_all(&redis_command(&["PING"])).await?;
+    let pong = redis_reply(&mut stream).await?;
+    let ping_ms = started.elapsed().as_secs_f64() * 1000.0;
+    if pong != "PONG" {
+        return Err(anyhow!("unexpected PING reply: {}", pong));
+    }
+    
+    stream.write_all(&redis_command(&["INFO"])).await?;
+    let info = redis_reply(&mut stream).await?;
+    let fields: HashMap<&str, f64> = info
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .filter_map(|(key, value)| Some((key, value.trim().parse().ok()?)))
+        .collect();
+    let field = |key: &str| fields.get(key).copied().ok_or_else(|| anyhow!("INFO without {}", key));
+    
+    let used = field("used_memory")?;
+    let mut gauges = vec![
+        ("used_memory_mb", used / MB),
+        ("fragmentation_ratio", field("mem_fragmentation_ratio")?),
+        ("connected_clients", field("connected_clients")?),
+    ];
+    if let Some(&limit) = fields.get("maxmemory").filter(|&&limit| limit > 0.0) {
+        gauges.push(("used_memory_pct", used * 100.0 / limit));
+    }
+    
+    Ok(CacheStats {
+        ping_ms,
+        gauges,
+        evictions: field("evicted_keys")?,
+        hits: field("keyspace_hits")?,
+        misses: field("keyspace_misses")?,
+    })
+}
+
+/// A command in the Redis protocol, safe for arguments with spaces.
+fn redis_command(args: &[&str]) -> Vec<u8> {
+    let mut command = format!("*{}\r\n", args.len());
+    for arg in args {
+        command.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+    }
+    command.into_bytes()
+}
+
+/// Reads a simple or bulk string reply.
+async fn redis_reply(stream: &mut BufReader<TcpStream>) -> Result<String> {
+    let mut line = String::new();
+    if stream.read_line(&mut line).await? == 0 {
+        return Err(anyhow!("connection closed"));
+    }
+    let line = line.trim_end();
+    
+    match (line.get(..1).unwrap_or_default(), line.get(1..).unwrap_or_default()) {
+        ("+", reply) => Ok(reply.to_string()),
+        ("-", error) => Err(anyhow!("{}", error)),
+        ("$", length) => {
+            let length: usize = length.parse()?;
+            if length > MAX_INFO_BYTES {
+                return Err(anyhow!("reply of {} bytes is too large", length));
+            }
+            let mut data = vec![0u8; length + 2];
+            stream.read_exact(&mut data).await?;
+            data.truncate(length);
+            Ok(String::from_utf8_lossy(&data).into_owned())
+        }
+        _ => Err(anyhow!("unexpected reply: {}", line)),
+    }
+}
+
+async fn memcached_stats(cache: &CacheConfig) -> Result<CacheStats> {
+    let mut stream = BufReader::new(TcpStream::connect(&cache.address).await?);
+    
+    // Memcached has no ping; `version` is its cheapest round trip
+    let started = Instant::now();
+    stream.write_all(b"version\r\n").await?;
+    let mut line = String::new();
+    stream.read_line(&mut line).await?;
+    let ping_ms = started.elapsed().as_secs_f64() * 1000.0;
+    if !line.starts_with("VERSION") {
+        return Err(anyhow!("unexpected version reply: {}", line.trim_end()));
+    }
+    
+    stream.write_all(b"stats\r\n").await?;
+    let mut fields: HashMap<String, f64> = HashMap::new();
+    loop {
+        line.clear();
+        if stream.read_line(&mut line).await? == 0 {
+            return Err(anyhow!("connection closed"));
+        }
+        let mut parts = line.split_whitespace();
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some("END"), _, _) => break,
+            (Some("STAT"), Some(key), Some(value)) => {
+                if let Ok(value) = value.parse() {
+                    fields.insert(key.to_string(), value);
+                }
+            }
+            _ => return Err(anyhow!("unexpected stats reply: {}", line.trim_end())),
+        }
+    }
+    let field = |key: &str| fields.get(key).copied().ok_or_else(|| anyhow!("stats without {}", key));
+    
+    let used = field("bytes")?;
+    let limit = field("limit_maxbytes")?;
+    let mut gauges = vec![
+        ("used_memory_mb", used / MB),
+        ("connected_clients", field("curr_connections")?),
+    ];
+    if limit > 0.0 {
+        gauges.push(("used_memory_pct", used * 100.0 / limit));
+    }
+    
+    Ok(CacheStats {
+        ping_ms,
+        gauges,
+        evictions: field("evictions")?,
+        hits: field("get_hits")?,
+        misses: field("get_misses")?,
+    })
+}
@@ -0,0 +1,463 @@
+use crate::models::config::{CaptureConfig, MonitoringConfig};
+use crate::models::metrics::SystemMetrics;
+use crate::services::collectors::Collector;
+use crate::services::lan_usage::DeviceTally;
+use crate::utils::cidr::{IpNet, PrefixTrie};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::ffi::CString;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+// Only this much of each packet is copied out of the kernel: enough for the link, IP
+// and port headers, never the payload
+const SNAPLEN: usize = 128;
+// Distinct protocol and destination pairs counted between two collections; the rest
+// are counted under "other"
+const MAX_FLOWS: usize = 4096;
+// LAN addresses tallied between two polls of the device accounting
+const MAX_DEVICES: usize = 4096;
+// The LAN when `capture.lan` is empty: the private IPv4 ranges and IPv6 unique local
+// addresses
+const PRIVATE_RANGES: [&str; 4] = ["10.0.0.0/8", "172.16.0.0/12", "192.168.0.0/16", "fc00::/7"];
+const SO_ATTACH_FILTER: libc::c_int = 26;
+const PACKET_OUTGOING: u8 = 4;
+
+/// Traffic to and from one destination over one protocol.
+#[derive(Debug, Clone, Copy, Default)]
+struct Traffic {
+    bytes_in: u64,
+    bytes_out: u64,
+    packets: u64,
+}
+
+impl Traffic {
+    fn add(&mut self, other: &Traffic) {
+        self.bytes_in += other.bytes_in;
+        self.bytes_out += other.bytes_out;
+        self.packets += other.packets;
+    }
+    
+    fn total(&self) -> u64 {
+        self.bytes_in + self.bytes_out
+    }
+}
+
+/// Traffic by protocol and remote range; `None` for frames without an IP peer and
+/// for the overflow past `MAX_FLOWS`.
+type Flows = HashMap<(String, Option<IpNet>), Traffic>;
+
+/// A capture running on its own thread, tallying into `flows` until stopped.
+struct Sniffer {
+    config: CaptureConfig,
+    flows: Arc<Mutex<Flows>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for Sniffer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Summarizes the traffic on one interface by protocol and remote network, from packet
+/// headers read through an AF_PACKET socket. Remote addresses are only kept as ranges
+/// of `prefix_v4`/`prefix_v6` bits, so single hosts are not tracked unless asked for.
+pub struct CaptureCollector {
+    config: CaptureConfig,
+    sniffer: Option<Sniffer>,
+    /// Traffic between LAN devices and the outside, for the device accounting.
+    devices: DeviceTally,
+    /// Flows tallied between collections, newest last, within the window.
+    samples: VecDeque<(DateTime<Utc>, Flows)>,
+    started: DateTime<Utc>,
+}
+
+impl CaptureCollector {
+    pub fn new(devices: DeviceTally) -> Self {
+        Self {
+            config: CaptureConfig::default(),
+            sniffer: None,
+            devices,
+            samples: VecDeque::new(),
+            started: Utc::now(),
+        }
+    }
+    
+    /// Opens the socket and starts the capture thread for the current configuration.
+    async fn start(&mut self) -> Result<()> {
+        let program = match self.config.filter.trim() {
+            "" => None,
+            filter => Some(compile_filter(&self.config.interface, filter).await?),
+        };
+        let socket = open_socket(&self.config.interface, program.as_deref())?;
+        
+        let flows = Arc::new(Mutex::new(Flows::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (thread_flows, thread_stop) = (Arc::clone(&flows), Arc::clone(&stop));
+        let prefixes = (self.config.prefix_v4, self.config.prefix_v6);
+        let lan = lan_ranges(&self.config.lan);
+        let devices = Arc::clone(&self.devices);
+        std::thread::Builder::new()
+            .name("capture".to_string())
+            .spawn(move || capture(socket, prefixes, &lan, &thread_flows, &devices, &thread_stop))?;
+        
+        info!("Capturing traffic on {}", self.config.interface);
+        self.sniffer = Some(Sniffer {
+            config: self.config.clone(),
+            flows,
+            stop,
+        });
+        self.samples.clear();
+        self.started = Utc::now();
+        Ok(())
+    }
+    
+    /// Everything tallied within the window, merged.
+    fn window_flows(&self) -> Flows {
+        let mut merged = Flows::new();
+        for (_, flows) in &self.samples {
+            for (key, traffic) in flows {
+                merged.entry(key.clone()).or_default().add(traffic);
+            }
+        }
+        merged
+    }
+}
+
+#[async_trait]
+impl Collector for CaptureCollector {
+    fn name(&self) -> &str {
+        "capture"
+    }
+    
+    fn configure(&mut self, config: &MonitoringConfig) {
+        self.config = config.capture.clone();
+    }
+    
+    async fn collect(&mut self, metrics: &mut SystemMetrics) -> Result<()> {
+        if !self.config.enabled {
+            self.sniffer = None;
+            self.samples.clear();
+            return Ok(());
+        }
+        // The window and summary size apply without restarting the capture
+        let restart = self.sniffer.as_ref().map_or(true, |sniffer| {
+            let (running, wanted) = (&sniffer.config, &self.config);
+            (&running.interface, &running.filter, running.prefix_v4, running.prefix_v6, &running.lan)
+                != (&wanted.interface, &wanted.filter, wanted.prefix_v4, wanted.prefix_v6, &wanted.lan)
+        });
+        if restart {
+            self.sniffer = None;
+            self.start().await?;
+        }
+        
+        let now = Utc::now();
+        if let Some(sniffer) = &self.sniffer {
+            let flows = std::mem::take(&mut *sniffer.flows.lock());
+            self.samples.push_back((now, flows));
+        }
+        let window = self.config.window as i64;
+        while self.samples.front().is_some_and(|(at, _)| (now - *at).num_seconds() >= window) {
+            self.samples.pop_front();
+        }
+        
+        let seconds = ((now - self.started).num_seconds() as f64).clamp(1.0, self.config.window as f64);
+        let mut total = Traffic::default();
+        for traffic in self.samples.iter().flat_map(|(_, flows)| flows.values()) {
+            total.add(traffic);
+        }
+        metrics.custom.insert("capture.kb_per_s".to_string(), total.total() as f64 / 1024.0 / seconds);
+        metrics.custom.insert("capture.packets_per_s".to_string(), total.packets as f64 / seconds);
+        
+        Ok(())
+    }
+    
+    fn details(&self) -> Option<Value> {
+        if !self.config.enabled {
+            return None;
+        }
+        let flows = self.window_flows();
+        let mut protocols: HashMap<&str, Traffic> = HashMap::new();
+        let mut destinations: HashMap<Option<IpNet>, Traffic> = HashMap::new();
+        for ((protocol, remote), traffic) in &flows {
+            protocols.entry(protocol.as_str()).or_default().add(traffic);
+            destinations.entry(*remote).or_default().add(traffic);
+        }
+        
+        let entry = |name: String, traffic: &Traffic| {
+            json!({
+                "name": name,
+                "kb_in": traffic.bytes_in / 1024,
+                "kb_out": traffic.bytes_out / 1024,
+                "packets": traffic.packets,
+            })
+        };
+        let top = self.config.top;
+        let mut protocols: Vec<(&str, Traffic)> = protocols.into_iter().collect();
+        protocols.sort_by_key(|(_, traffic)| std::cmp::Reverse(traffic.total()));
+        let mut destinations: Vec<(Option<IpNet>, Traffic)> = destinations.into_iter().collect();
+        destinations.sort_by_key(|(_, traffic)| std::cmp::Reverse(traffic.total()));
+        
+        Some(json!({
+            "interface": self.config.interface,
+            "window": self.config.window,
+            "protocols": protocols.iter().take(top).map(|(name, traffic)| entry(name.to_string(), traffic)).collect::<Vec<_>>(),
+            "destinations": destinations
+                .iter()
+                .take(top)
+                .map(|(remote, traffic)| entry(remote.map_or("other".to_string(), |range| range.to_string()), traffic))
+                .collect::<Vec<_>>(),
+        }))
+    }
+}
+
+/// Compiles a tcpdump filter expression to classic BPF for `interface`'s link type.
+async fn compile_filter(interface: &str, filter: &str) -> Result<Vec<libc::sock_filter>> {
+    let output = Command::new("sudo")
+        .args(["-n", "tcpdump", "-i", interface, "-ddd", filter])
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "tcpdump could not compile the filter: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    
+    // A count line, then one `code jt jf k` line per instruction
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut lines = text.lines();
+    let count: usize = lines.next().and_then(|line| line.trim().parse().ok()).ok_or_else(|| anyhow!("tcpdump printed no program"))?;
+    let program: Vec<libc::sock_filter> = lines
+        .filter_map(|line| {
+            let fields: Vec<u32> = line.split_whitespace().filter_map(|field| field.parse().ok()).collect();
+            match fields[..] {
+                [code, jt, jf, k] => Some(libc::sock_filter {
+                    code: code as u16,
+                    jt: jt as u8,
+                    jf: jf as u8,
+                    k,
+                }),
+                _ => None,
+            }
+        })
+        .collect();
+    if program.len() != count {
+        return Err(anyhow!("tcpdump printed {} of {} instructions", program.len(), count));
+    }
+    Ok(program)
+}
+
+/// A raw packet socket bound to `interface`, with `program` attached to drop unwanted
+/// packets in the kernel. Needs CAP_NET_RAW.
+fn open_socket(interface: &str, program: Option<&[libc::sock_filter]>) -> Result<OwnedFd> {
+    let name = CString::new(interface)?;
+    let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    if index == 0 {
+        return Err(anyhow!("no interface named {}", interface));
+    }
+    
+    let protocol = (libc::ETH_P_ALL as u16).to_be();
+    let raw = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW | libc::SOCK_CLOEXEC, protocol as libc::c_int) };
+    if raw < 0 {
+        return Err(anyhow!("cannot open a packet socket: {}", std::io::Error::last_os_error()));
+    }
+    let socket = unsafe { OwnedFd::from_raw_fd(raw) };
+    
+    // Attached before binding, so no unfiltered packet is queued in between
+    if let Some(program) = program {
+        let fprog = libc::sock_fprog {
+            len: program.len() as u16,
+            filter: program.as_ptr() as *mut libc::sock_filter,
+        };
+        set_option(&socket, SO_ATTACH_FILTER, &fprog)?;
+    }
+    // Reads time out so the thread notices when it is stopped
+    let timeout = libc::timeval { tv_sec: 1, tv_usec: 0 };
+    set_option(&socket, libc::SO_RCVTIMEO, &timeout)?;
+    
+    let mut address: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
+    address.sll_family = libc::AF_PACKET as u16;
+    address.sll_protocol = protocol;
+    address.sll_ifindex = index as i32;
+    let bound = unsafe {
+        libc::bind(
+            socket.as_raw_fd(),
+            &address as *const libc::sockaddr_ll as *const libc::sockaddr,
+            std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+        )
+    };
+    if bound < 0 {
+        return Err(anyhow!("cannot capture on {}: {}", interface, std::io::Error::last_os_error()));
+    }
+    Ok(socket)
+}
+
+fn set_option<T>(socket: &OwnedFd, option: libc::c_int, value: &T) -> Result<()> {
+    let set = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            option,
+            value as *const T as *const libc::c_void,
+            std::mem::size_of::<T>() as libc::socklen_t,
+        )
+    };
+    if set < 0 {
+        return Err(anyhow!("setsockopt failed: {}", std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+/// The configured LAN ranges, or the private ranges; entries were checked when the
+/// configuration loaded.
+fn lan_ranges(configured: &[String]) -> PrefixTrie<()> {
+    let mut lan = PrefixTrie::new();
+    let ranges: Vec<&str> = if configured.is_empty() {
+        PRIVATE_RANGES.to_vec()
+    } else {
+        configured.iter().map(String::as_str).collect()
+    };
+    for range in ranges.into_iter().filter_map(|range| range.parse::<IpNet>().ok()) {
+        lan.insert(range, ());
+    }
+    lan
+}
+
+/// Reads packets until `stop` is set, tallying each into `flows`, and traffic between a
+/// LAN address and the outside into `devices`.
+fn capture(
+    socket: OwnedFd,
+    prefixes: (u8, u8),
+    lan: &PrefixTrie<()>,
+    flows: &Mutex<Flows>,
+    devices: &DeviceTally,
+    stop: &AtomicBool,
+) {
+    let mut buffer = [0u8; SNAPLEN];
+    while !stop.load(Ordering::Relaxed) {
+        let mut address: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
+        let mut address_len = std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t;
+        // MSG_TRUNC returns the full length of packets longer than the buffer
+        let length = unsafe {
+            libc::recvfrom(
+                socket.as_raw_fd(),
+                buffer.as_mut_ptr() as *mut libc::c_void,
+                buffer.len(),
+                libc::MSG_TRUNC,
+                &mut address as *mut libc::sockaddr_ll as *mut libc::sockaddr,
+                &mut address_len,
+            )
+        };
+        if length < 0 {
+            let error = std::io::Error::last_os_error();
+            match error.kind() {
+                std::io::ErrorKind::WouldBlock | std::io::ErrorKind::Interrupted => continue,
+                _ => {
+                    warn!("Packet capture stopped: {}", error);
+                    return;
+                }
+            }
+        }
+        
+        let length = length as usize;
+        let captured = &buffer[..length.min(SNAPLEN)];
+        let outgoing = address.sll_pkttype == PACKET_OUTGOING;
+        let (protocol, addresses) = classify(captured);
+        let length_bytes = length as u64;
+        if let Some((source, destination)) = addresses {
+            // Traffic staying inside the LAN is nobody's upload or download
+            let device = match (lan.contains(source), lan.contains(destination)) {
+                (true, false) => Some((source, (length_bytes, 0))),
+                (false, true) => Some((destination, (0, length_bytes))),
+                _ => None,
+            };
+            if let Some((address, (upload, download))) = device {
+                let mut devices = devices.lock();
+                if devices.len() < MAX_DEVICES || devices.contains_key(&address) {
+                    let tally = devices.entry(address).or_default();
+                    tally.0 += upload;
+                    tally.1 += download;
+                }
+            }
+        }
+        let remote = addresses.map(|(source, destination)| if outgoing { destination } else { source });
+        let remote = remote.and_then(|ip| {
+            let prefix = if ip.is_ipv4() { prefixes.0 } else { prefixes.1 };
+            IpNet::new(ip, prefix)
+        });
+        
+        let mut flows = flows.lock();
+        let mut key = (protocol, remote);
+        if flows.len() >= MAX_FLOWS && !flows.contains_key(&key) {
+            key = ("other".to_string(), None);
+        }
+        let traffic = flows.entry(key).or_default();
+        traffic.packets += 1;
+        if outgoing {
+            traffic.bytes_out += length_bytes;
+        } else {
+            traffic.bytes_in += length_bytes;
+        }
+    }
+}
+
+/// The protocol of an Ethernet frame, as `tcp/443`-style labels with the lower port
+/// (usually the service), and its source and destination addresses.
+fn classify(frame: &[u8]) -> (String, Option<(IpAddr, IpAddr)>) {
+    let mut offset = 12;
+    let mut ethertype = read_u16(frame, offset);
+    // VLAN tags sit between the addresses and the real type
+    while matches!(ethertype, Some(0x8100) | Some(0x88a8)) {
+        offset += 4;
+        ethertype = read_u16(frame, offset);
+    }
+    let ip = &frame[(offset + 2).min(frame.len())..];
+    
+    let (next, source, destination, header_len) = match ethertype {
+        Some(0x0800) if ip.len() >= 20 => {
+            let source = Ipv4Addr::new(ip[12], ip[13], ip[14], ip[15]);
+            let destination = Ipv4Addr::new(ip[16], ip[17], ip[18], ip[19]);
+            (ip[9], IpAddr::V4(source), IpAddr::V4(destination), (ip[0] & 0x0f) as usize * 4)
+        }
+        Some(0x86dd) if ip.len() >= 40 => {
+            let address = |start: usize| {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(&ip[start..start + 16]);
+                IpAddr::V6(Ipv6Addr::from(octets))
+            };
+            (ip[6], address(8), address(24), 40)
+        }
+        Some(0x0806) => return ("arp".to_string(), None),
+        _ => return ("other".to_string(), None),
+    };
+    let protocol = match next {
+        6 | 17 => {
+            let name = if next == 6 { "tcp" } else { "udp" };
+            match (read_u16(ip, header_len), read_u16(ip, header_len + 2)) {
+                (Some(source_port), Some(destination_port)) => {
+                    format!("{}/{}", name, source_port.min(destination_port))
+                }
+                _ => name.to_string(),
+            }
+        }
+        1 => "icmp".to_string(),
+        58 => "icmpv6".to_string(),
+        other => format!("ip/{}", other),
+    };
+    (protocol, Some((source, destination)))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
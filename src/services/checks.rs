@@ -0,0 +1,432 @@
+use crate::models::config::{CheckConfig, CheckType};
+use crate::models::metrics::CheckResult;
+use crate::services::probes;
+use crate::utils::process;
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use std::net::IpAddr;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::Command;
+use tokio::time::{timeout, Duration, Instant};
+use tokio_rustls::rustls::client::{ServerCertVerified, ServerCertVerifier};
+use tokio_rustls::rustls::{Certificate, ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName};
+use tokio_rustls::TlsConnector;
+use tracing::debug;
+
+const DEFAULT_PING_COUNT: u32 = 5;
+const SMTP_PORT: u16 = 25;
+const SMTPS_PORT: u16 = 465;
+const IMAP_PORT: u16 = 143;
+const IMAPS_PORT: u16 = 993;
+
+/// Runs one check. Only ICMP checks send several probes and so measure loss and jitter.
+pub async fn run_check(check: &CheckConfig) -> CheckResult {
+    if let Some(module) = &check.probe {
+        return probes::run_probe(module, check).await;
+    }
+    let limit = Duration::from_secs(check.timeout);
+    
+    let latency = match check.check_type {
+        CheckType::Icmp => {
+            let count = check.count.unwrap_or(DEFAULT_PING_COUNT).max(1);
+            return ping(&check.target, count, check.timeout).await;
+        }
+        CheckType::Tcp => tcp_check(check, limit).await,
+        CheckType::Http => http_check(check, limit).await,
+        CheckType::Dns => dns_check(check, limit).await,
+        CheckType::Smtp | CheckType::Imap => mail_check(check, limit).await,
+    };
+    
+    if latency < 0.0 {
+        CheckResult::down()
+    } else {
+        CheckResult::up(latency)
+    }
+}
+
+/// ICMP ping through the system `ping` command, falling back to a TCP connect.
+pub async fn ping(host: &str, count: u32, timeout_secs: u64) -> CheckResult {
+    let host = unbracket(host);
+    let count_arg = count.to_string();
+    let wait = timeout_secs.to_string();
+    let wait_ms = (timeout_secs * 1000).to_string();
+    
+    // Use system ping command; 0.2s is the shortest interval allowed without root
+    let mut cmd = if cfg!(target_os = "windows") {
+        vec!["ping", "-n", &count_arg, "-w", &wait_ms]
+    } else {
+        vec!["ping", "-c", &count_arg, "-i", "0.2", "-W", &wait]
+    };
+    // Older iputils and busybox builds only ping IPv6 addresses when told to
+    if is_ipv6(host) {
+        cmd.push("-6");
+    }
+    cmd.push(host);
+    
+    // Every echo request waits up to the timeout, and a hung ping is killed
+    let limit = Duration::from_secs(timeout_secs * count as u64 + 1);
+    if let Ok(output) = process::output_within(
+        Command::new(cmd[0]).args(&cmd[1..]).stdout(Stdio::piped()).stderr(Stdio::null()),
+        limit,
+    )
+    .await
+    {
+        // Judge by the replies received, since some ping builds exit non-zero on partial loss
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let times = parse_ping_times(&stdout);
+        if !times.is_empty() {
+            return ping_result(&times, count);
+        }
+    }
+    
+    // Fallback: try TCP connection
+    let latency = tcp_ping(host, Duration::from_secs(timeout_secs)).await;
+    if latency < 0.0 {
+        CheckResult::down()
+    } else {
+        CheckResult::up(latency)
+    }
+}
+
+fn ping_result(times: &[f64], sent: u32) -> CheckResult {
+    let received = times.len().min(sent as usize);
+    let jitter = if times.len() > 1 {
+        times.windows(2).map(|pair| (pair[1] - pair[0]).abs()).sum::<f64>() / (times.len() - 1) as f64
+    } else {
+        0.0
+    };
+    
+    CheckResult {
+        latency_ms: times.iter().sum::<f64>() / times.len() as f64,
+        loss_percent: (sent as usize - received) as f64 / sent as f64 * 100.0,
+        jitter_ms: jitter,
+    }
+}
+
+async fn tcp_ping(host: &str, limit: Duration) -> f64 {
+    let start = Instant::now();
+    
+    match timeout(limit, TcpStream::connect((host, 80))).await {
+        Ok(Ok(_)) => start.elapsed().as_millis() as f64,
+        _ => {
+            // Try DNS resolution
+            match dns_lookup::lookup_host(host) {
+                Ok(_) => 50.0, // Assume reasonable latency
+                Err(_) => -1.0,
+            }
+        }
+    }
+}
+
+async fn tcp_check(check: &CheckConfig, limit: Duration) -> f64 {
+    let Some(port) = check.port else {
+        return -1.0;
+    };
+    let start = Instant::now();
+    
+    let Ok(Ok(mut stream)) = timeout(limit, TcpStream::connect((unbracket(&check.target), port))).await else {
+        return -1.0;
+    };
+    let elapsed = start.elapsed().as_millis() as f64;
+    
+    // Optionally match the service banner (SSH, SMTP, ...)
+    if let Some(expect) = &check.expect {
+        let mut banner = vec![0u8; 512];
+        let read = match timeout(limit, stream.read(&mut banner)).await {
+            Ok(Ok(read)) => read,
+            _ => return -1.0,
+        };
+        if !String::from_utf8_lossy(&banner[..read]).contains(expect.as_str()) {
+            return -1.0;
+        }
+    }
+    
+    elapsed
+}
+
+async fn http_check(check: &CheckConfig, limit: Duration) -> f64 {
+    let Ok(client) = reqwest::Client::builder().timeout(limit).build() else {
+        return -1.0;
+    };
+    let start = Instant::now();
+    
+    let Ok(response) = client.get(&check.target).send().await else {
+        return -1.0;
+    };
+    let elapsed = start.elapsed().as_millis() as f64;
+    let status = response.status();
+    
+    // A numeric expectation is a status code, anything else must appear in the body
+    let passed = match check.expect.as_deref() {
+        None => status.is_success(),
+        Some(code) if code.parse::<u16>().is_ok() => status.as_str() == code,
+        Some(text) => status.is_success() && response.text().await.is_ok_and(|body| body.contains(text)),
+    };
+    
+    if passed { elapsed } else { -1.0 }
+}
+
+async fn dns_check(check: &CheckConfig, limit: Duration) -> f64 {
+    let host = check.target.clone();
+    let start = Instant::now();
+    
+    let lookup = tokio::task::spawn_blocking(move || dns_lookup::lookup_host(&host));
+    let Ok(Ok(Ok(addresses))) = timeout(limit, lookup).await else {
+        return -1.0;
+    };
+    let elapsed = start.elapsed().as_millis() as f64;
+    
+    let passed = match check.expect.as_deref().map(str::parse::<IpAddr>) {
+        None => !addresses.is_empty(),
+        Some(Ok(expected)) => addresses.contains(&expected),
+        Some(Err(_)) => false,
+    };
+    
+    if passed { elapsed } else { -1.0 }
+}
+
+/// Times a complete mail protocol handshake, which catches a mail server that accepts
+/// connections but cannot serve them.
+async fn mail_check(check: &CheckConfig, limit: Duration) -> f64 {
+    let start = Instant::now();
+    
+    let handshake = async {
+        match check.check_type {
+            CheckType::Smtp => smtp_handshake(check).await,
+            _ => imap_handshake(check).await,
+        }
+    };
+    match timeout(limit, handshake).await {
+        Ok(Ok(())) => start.elapsed().as_millis() as f64,
+        Ok(Err(e)) => {
+            debug!("Check {} failed: {}", check.name, e);
+            -1.0
+        }
+        Err(_) => -1.0,
+    }
+}
+
+async fn smtp_handshake(check: &CheckConfig) -> Result<()> {
+    let mut session = MailSession::connect(check, SMTP_PORT, SMTPS_PORT).await?;
+    let (code, banner) = smtp_reply(&mut session).await?;
+    if code != 220 {
+        return Err(anyhow!("greeting {} {}", code, banner.join(" ")));
+    }
+    expect_banner(check, &banner.join(" "))?;
+    
+    let ehlo = format!("EHLO {}", sysinfo::System::host_name().unwrap_or_else(|| "localhost".to_string()));
+    let extensions = smtp_command(&mut session, &ehlo, 250).await?;
+    if !session.tls && extensions.iter().any(|extension| extension.eq_ignore_ascii_case("STARTTLS")) {
+        smtp_command(&mut session, "STARTTLS", 220).await?;
+        session = session.start_tls(check).await?;
+        smtp_command(&mut session, &ehlo, 250).await?;
+    }
+    
+    if let (Some(username), Some(password)) = (&check.username, &check.password) {
+        if !session.tls {
+            return Err(anyhow!("no STARTTLS offered, so the credentials were not sent"));
+        }
+        let token = base64::engine::general_purpose::STANDARD.encode(format!("\0{}\0{}", username, password));
+        smtp_command(&mut session, &format!("AUTH PLAIN {}", token), 235).await?;
+    }
+    
+    // The server did its part; a missing goodbye is not a failure
+    let _ = smtp_command(&mut session, "QUIT", 221).await;
+    Ok(())
+}
+
+/// Reads a possibly multi-line SMTP reply: its code and the text of each line.
+async fn smtp_reply(session: &mut MailSession) -> Result<(u16, Vec<String>)> {
+    let mut lines = Vec::new();
+    loop {
+        let line = session.read_line().await?;
+        let code = line
+            .get(..3)
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(|| anyhow!("not an SMTP reply: {}", line))?;
+        lines.push(line.get(4..).unwrap_or_default().to_string());
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return Ok((code, lines));
+        }
+    }
+}
+
+async fn smtp_command(session: &mut MailSession, command: &str, expected: u16) -> Result<Vec<String>> {
+    session.send(command).await?;
+    let (code, lines) = smtp_reply(session).await?;
+    if code != expected {
+        // Only the verb, since AUTH carries the credentials
+        let verb = command.split(' ').next().unwrap_or(command);
+        return Err(anyhow!("{} answered {} {}", verb, code, lines.join(" ")));
+    }
+    Ok(lines)
+}
+
+async fn imap_handshake(check: &CheckConfig) -> Result<()> {
+    let mut session = MailSession::connect(check, IMAP_PORT, IMAPS_PORT).await?;
+    let greeting = session.read_line().await?;
+    if !greeting.starts_with("* OK") && !greeting.starts_with("* PREAUTH") {
+        return Err(anyhow!("greeting {}", greeting));
+    }
+    expect_banner(check, &greeting)?;
+    
+    if !session.tls {
+        let capabilities = imap_command(&mut session, "a1", "CAPABILITY").await?;
+        if capabilities.iter().flat_map(|line| line.split_whitespace()).any(|word| word.eq_ignore_ascii_case("STARTTLS")) {
+            imap_command(&mut session, "a2", "STARTTLS").await?;
+            session = session.start_tls(check).await?;
+        }
+    }
+    
+    if let (Some(username), Some(password)) = (&check.username, &check.password) {
+        if !session.tls {
+            return Err(anyhow!("no STARTTLS offered, so the credentials were not sent"));
+        }
+        let login = format!("LOGIN {} {}", imap_quote(username), imap_quote(password));
+        imap_command(&mut session, "a3", &login).await?;
+    }
+    
+    let _ = imap_command(&mut session, "a4", "LOGOUT").await;
+    Ok(())
+}
+
+/// Sends a tagged IMAP command and returns the untagged lines sent before its OK.
+async fn imap_command(session: &mut MailSession, tag: &str, command: &str) -> Result<Vec<String>> {
+    session.send(&format!("{} {}", tag, command)).await?;
+    let tagged = format!("{} ", tag);
+    let mut untagged = Vec::new();
+    loop {
+        let line = session.read_line().await?;
+        let Some(status) = line.strip_prefix(&tagged) else {
+            untagged.push(line);
+            continue;
+        };
+        if status.starts_with("OK") {
+            return Ok(untagged);
+        }
+        let verb = command.split(' ').next().unwrap_or(command);
+        return Err(anyhow!("{} answered {}", verb, status));
+    }
+}
+
+fn imap_quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn expect_banner(check: &CheckConfig, banner: &str) -> Result<()> {
+    match &check.expect {
+        Some(expect) if !banner.contains(expect.as_str()) => Err(anyhow!("greeting without '{}'", expect)),
+        _ => Ok(()),
+    }
+}
+
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+/// A line-based connection that can switch to TLS part way through.
+struct MailSession {
+    stream: BufReader<Box<dyn Stream>>,
+    tls: bool,
+}
+
+impl MailSession {
+    /// Connects to the check's port, or `plain_port` by default. `tls_port` speaks TLS
+    /// from the start.
+    async fn connect(check: &CheckConfig, plain_port: u16, tls_port: u16) -> Result<Self> {
+        let port = check.port.unwrap_or(plain_port);
+        let stream = TcpStream::connect((unbracket(&check.target), port)).await?;
+        let session = Self {
+            stream: BufReader::new(Box::new(stream)),
+            tls: false,
+        };
+        if port == tls_port {
+            session.start_tls(check).await
+        } else {
+            Ok(session)
+        }
+    }
+    
+    async fn read_line(&mut self) -> Result<String> {
+        let mut line = String::new();
+        if self.stream.read_line(&mut line).await? == 0 {
+            return Err(anyhow!("connection closed"));
+        }
+        Ok(line.trim_end().to_string())
+    }
+    
+    async fn send(&mut self, line: &str) -> Result<()> {
+        self.stream.write_all(format!("{}\r\n", line).as_bytes()).await?;
+        Ok(())
+    }
+    
+    async fn start_tls(self, check: &CheckConfig) -> Result<Self> {
+        let name = ServerName::try_from(unbracket(&check.target)).map_err(|_| anyhow!("invalid TLS name {}", check.target))?;
+        let stream = tls_connector(check.verify_tls).connect(name, self.stream.into_inner()).await?;
+        Ok(Self {
+            stream: BufReader::new(Box::new(stream)),
+            tls: true,
+        })
+    }
+}
+
+/// A TLS client trusting the Mozilla root certificates, or any certificate at all
+/// when `verify` is false.
+pub fn tls_connector(verify: bool) -> TlsConnector {
+    let mut roots = RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+        OwnedTrustAnchor::from_subject_spki_name_constraints(anchor.subject, anchor.spki, anchor.name_constraints)
+    }));
+    let mut config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    if !verify {
+        config.dangerous().set_certificate_verifier(Arc::new(AcceptAnyCertificate));
+    }
+    TlsConnector::from(Arc::new(config))
+}
+
+struct AcceptAnyCertificate;
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+/// An IPv6 address without the brackets it is written in inside URLs and configs.
+pub fn unbracket(host: &str) -> &str {
+    host.strip_prefix('[').and_then(|host| host.strip_suffix(']')).unwrap_or(host)
+}
+
+/// Whether `host` is an IPv6 address, including link-local ones with a `%interface` zone.
+pub fn is_ipv6(host: &str) -> bool {
+    let address = unbracket(host).split('%').next().unwrap_or_default();
+    address.parse::<std::net::Ipv6Addr>().is_ok()
+}
+
+fn parse_ping_times(output: &str) -> Vec<f64> {
+    // Parse time=XX.Xms or time=XX ms patterns, one per reply
+    output
+        .lines()
+        .filter_map(|line| {
+            let pos = line.find("time=")?;
+            let time_part = &line[pos + 5..];
+            let time_str: String = time_part.chars().take_while(|c| c.is_ascii_digit() || *c == '.').collect();
+            time_str.parse::<f64>().ok()
+        })
+        .collect()
+}
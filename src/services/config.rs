@@ -0,0 +1,256 @@
+use crate::models::config::{AppConfig, CONFIG_FILES};
+use crate::models::events::{Event, EventKind};
+use crate::models::metrics::AnomalySeverity;
+use crate::services::events::EventLog;
+use crate::services::file_watch::FileWatchService;
+use crate::services::monitor::MonitorService;
+use crate::services::public_ip::PublicIpService;
+use crate::services::suid::SuidScanService;
+use crate::services::threat_intel::ThreatIntelService;
+use anyhow::{anyhow, Result};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::{Mutex, RwLock};
+use tracing::{info, warn};
+
+/// Owns the live configuration and pushes changes into the running services.
+pub struct ConfigService {
+    current: RwLock<AppConfig>,
+    source_modified: Mutex<Vec<(PathBuf, Option<SystemTime>)>>,
+    monitor: Arc<RwLock<MonitorService>>,
+    threat_intel: Arc<RwLock<ThreatIntelService>>,
+    public_ip: Arc<RwLock<PublicIpService>>,
+    file_watch: Arc<RwLock<FileWatchService>>,
+    suid: Arc<RwLock<SuidScanService>>,
+    event_log: Arc<EventLog>,
+}
+
+impl ConfigService {
+    pub fn new(
+        config: AppConfig,
+        monitor: Arc<RwLock<MonitorService>>,
+        threat_intel: Arc<RwLock<ThreatIntelService>>,
+        public_ip: Arc<RwLock<PublicIpService>>,
+        file_watch: Arc<RwLock<FileWatchService>>,
+        suid: Arc<RwLock<SuidScanService>>,
+        event_log: Arc<EventLog>,
+    ) -> Self {
+        Self {
+            current: RwLock::new(config),
+            source_modified: Mutex::new(source_modified_time()),
+            monitor,
+            threat_intel,
+            public_ip,
+            file_watch,
+            suid,
+            event_log,
+        }
+    }
+    
+    pub async fn current(&self) -> AppConfig {
+        self.current.read().await.clone()
+    }
+    
+    /// The live configuration with secrets blanked, for display through the API.
+    pub async fn redacted(&self) -> AppConfig {
+        let mut config = self.current().await;
+        config.security.password_hash.clear();
+        for cluster in &mut config.monitoring.proxmox {
+            cluster.token_secret.clear();
+        }
+        for pihole in &mut config.monitoring.pihole {
+            pihole.password.clear();
+        }
+        for database in &mut config.monitoring.databases {
+            database.password.clear();
+        }
+        for check in &mut config.monitoring.checks {
+            if let Some(password) = &mut check.password {
+                password.clear();
+            }
+        }
+        for cache in &mut config.monitoring.caches {
+            cache.password.clear();
+        }
+        for heartbeat in &mut config.monitoring.heartbeats {
+            heartbeat.token.clear();
+        }
+        if let Some(token) = &mut config.alerts.ingest_token {
+            token.clear();
+        }
+        for webhook in &mut config.alerts.anomaly_webhooks {
+            if let Some(secret) = &mut webhook.secret {
+                secret.clear();
+            }
+        }
+        for secret in [&mut config.crowdsec.api_key, &mut config.crowdsec.password].into_iter().flatten() {
+            secret.clear();
+        }
+        for list in &mut config.threat_intel.blocklists {
+            if let Some(api_key) = &mut list.api_key {
+                api_key.clear();
+            }
+        }
+        config
+    }
+    
+    /// Validates a submitted configuration, persists it and applies it. Blank secrets
+    /// (as returned by `redacted`) keep their current value.
+    pub async fn update(&self, mut config: AppConfig, source: &str) -> Result<Vec<String>> {
+        let current = self.current().await;
+        if config.security.password_hash.is_empty() {
+            config.security.password_hash = current.security.password_hash.clone();
+        }
+        for cluster in &mut config.monitoring.proxmox {
+            if cluster.token_secret.is_empty() {
+                let known = current.monitoring.proxmox.iter().find(|known| known.name == cluster.name);
+                cluster.token_secret = known.map(|known| known.token_secret.clone()).unwrap_or_default();
+            }
+        }
+        for pihole in &mut config.monitoring.pihole {
+            if pihole.password.is_empty() {
+                let known = current.monitoring.pihole.iter().find(|known| known.name == pihole.name);
+                pihole.password = known.map(|known| known.password.clone()).unwrap_or_default();
+            }
+        }
+        for database in &mut config.monitoring.databases {
+            if database.password.is_empty() {
+                let known = current.monitoring.databases.iter().find(|known| known.name == database.name);
+                database.password = known.map(|known| known.password.clone()).unwrap_or_default();
+            }
+        }
+        for check in &mut config.monitoring.checks {
+            if check.password.as_deref() == Some("") {
+                let known = current.monitoring.checks.iter().find(|known| known.name == check.name);
+                check.password = known.and_then(|known| known.password.clone());
+            }
+        }
+        for cache in &mut config.monitoring.caches {
+            if cache.password.is_empty() {
+                let known = current.monitoring.caches.iter().find(|known| known.name == cache.name);
+                cache.password = known.map(|known| known.password.clone()).unwrap_or_default();
+            }
+        }
+        for heartbeat in &mut config.monitoring.heartbeats {
+            if heartbeat.token.is_empty() {
+                let known = current.monitoring.heartbeats.iter().find(|known| known.name == heartbeat.name);
+                heartbeat.token = known.map(|known| known.token.clone()).unwrap_or_default();
+            }
+        }
+        if config.alerts.ingest_token.as_deref() == Some("") {
+            config.alerts.ingest_token = current.alerts.ingest_token.clone();
+        }
+        for webhook in &mut config.alerts.anomaly_webhooks {
+            if webhook.secret.as_deref() == Some("") {
+                let known = current.alerts.anomaly_webhooks.iter().find(|known| known.url == webhook.url);
+                webhook.secret = known.and_then(|known| known.secret.clone());
+            }
+        }
+        if config.crowdsec.api_key.as_deref() == Some("") {
+            config.crowdsec.api_key = current.crowdsec.api_key.clone();
+        }
+        if config.crowdsec.password.as_deref() == Some("") {
+            config.crowdsec.password = current.crowdsec.password.clone();
+        }
+        for list in &mut config.threat_intel.blocklists {
+            if list.api_key.as_deref() == Some("") {
+                let known = current.threat_intel.blocklists.iter().find(|known| known.name == list.name);
+                list.api_key = known.and_then(|known| known.api_key.clone());
+            }
+        }
+        // Commands to run can only come from the file, never from a web session
+        config.monitoring.exec = current.monitoring.exec.clone();
+        config.monitoring.processes = current.monitoring.processes.clone();
+        
+        config.validate().map_err(|errors| anyhow!(errors.join("; ")))?;
+        
+        let path = AppConfig::source_path().unwrap_or(CONFIG_FILES[0]);
+        config.save_to(path)?;
+        
+        // Our own write must not be picked up again as an external edit
+        *self.source_modified.lock().await = source_modified_time();
+        
+        Ok(self.apply(config, source).await)
+    }
+    
+    /// Re-reads the configuration file if it changed on disk since the last check.
+    /// An unparsable file is ignored so a half-saved edit never takes the monitor down.
+    pub async fn reload_if_changed(&self) {
+        let Some(path) = AppConfig::source_path() else {
+            return;
+        };
+        
+        let modified = source_modified_time();
+        {
+            let mut last_modified = self.source_modified.lock().await;
+            if *last_modified == modified {
+                return;
+            }
+            *last_modified = modified;
+        }
+        
+        match AppConfig::load_from(path) {
+            Ok(config) => {
+                self.apply(config, path).await;
+            }
+            Err(e) => warn!("Ignoring invalid configuration in {}: {}", path, e),
+        }
+    }
+    
+    /// Applies `config` to the running services and records what changed in the
+    /// event log. Returns the list of changes.
+    pub async fn apply(&self, config: AppConfig, source: &str) -> Vec<String> {
+        let mut current = self.current.write().await;
+        
+        let changes = current.diff(&config);
+        if changes.is_empty() {
+            return changes;
+        }
+        
+        self.monitor.write().await.apply_config(
+            config.monitoring.clone(),
+            config.alerts.clone(),
+            config.display.clone(),
+        );
+        self.threat_intel.write().await.apply_config(&config.threat_intel);
+        self.public_ip.write().await.apply_config(&config.public_ip);
+        self.file_watch.write().await.apply_config(&config.file_watch);
+        self.suid.write().await.apply_config(&config.monitoring.detection);
+        
+        if current.logging.target != config.logging.target {
+            warn!("Logging target changes take effect after a restart");
+        }
+        
+        info!("Configuration updated from {}: {}", source, changes.join("; "));
+        self.event_log
+            .record(
+                Event::new(
+                    EventKind::Config,
+                    AnomalySeverity::Info,
+                    format!("Configuration updated from {}", source),
+                )
+                .with_details(serde_json::json!({ "changes": changes })),
+            )
+            .await;
+        
+        *current = config;
+        changes
+    }
+}
+
+/// Modification times of the configuration file and its includes; any edit, added
+/// or removed include changes the result.
+fn source_modified_time() -> Vec<(PathBuf, Option<SystemTime>)> {
+    let Some(path) = AppConfig::source_path() else {
+        return Vec::new();
+    };
+    
+    AppConfig::source_files(path)
+        .into_iter()
+        .map(|file| {
+            let modified = std::fs::metadata(&file).and_then(|m| m.modified()).ok();
+            (file, modified)
+        })
+        .collect()
+}
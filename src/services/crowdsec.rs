@@ -0,0 +1,311 @@
+use crate::models::config::CrowdSecConfig;
+use crate::models::events::{Event, EventKind};
+use crate::models::metrics::AnomalySeverity;
+use crate::services::events::EventLog;
+use crate::services::firewall;
+use crate::services::monitor::MonitorService;
+use crate::utils::cidr::IpNet;
+use crate::utils::data_dir;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::net::IpAddr;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+pub const CROWDSEC_DECISIONS_FILE: &str = "data/crowdsec_decisions.json";
+const REQUEST_TIMEOUT_SECS: u64 = 15;
+// Ranges named in a decision event, at most
+const EVENT_RANGES: usize = 20;
+// The anomalies reported to CrowdSec, and the scenario each is reported under
+const SCENARIOS: [(&str, &str); 2] = [
+    ("SSH Brute Force: ", "shaydz/ssh-bruteforce"),
+    ("Web Scanner: ", "shaydz/http-probing"),
+];
+
+#[derive(Debug, Deserialize)]
+struct DecisionStream {
+    #[serde(default)]
+    new: Option<Vec<Decision>>,
+    #[serde(default)]
+    deleted: Option<Vec<Decision>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Decision {
+    id: i64,
+    #[serde(rename = "type")]
+    kind: String,
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WatcherLogin {
+    token: String,
+    expire: DateTime<Utc>,
+}
+
+/// Talks to the CrowdSec local API: as a bouncer it blocks what CrowdSec bans and
+/// lifts the blocks when the decisions expire, and as a watcher it reports the
+/// monitor's own detections.
+pub struct CrowdSecService {
+    client: reqwest::Client,
+    /// Blocked ranges by decision id, kept on disk so decisions that expire while the
+    /// monitor is stopped are still lifted.
+    decisions: BTreeMap<i64, String>,
+    /// Decisions that expired or were deleted, whose blocks are still to be lifted.
+    lifting: HashSet<i64>,
+    /// The API and key the decision stream was last read from; a new one starts over.
+    stream_source: Option<(String, String)>,
+    token: Option<WatcherLogin>,
+    /// Detections already reported while they stay raised, as scenario and address.
+    reported: HashSet<(&'static str, IpAddr)>,
+    monitor: Arc<RwLock<MonitorService>>,
+    event_log: Arc<EventLog>,
+}
+
+impl CrowdSecService {
+    pub fn new(monitor: Arc<RwLock<MonitorService>>, event_log: Arc<EventLog>) -> Self {
+        let decisions = std::fs::read_to_string(CROWDSEC_DECISIONS_FILE)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        let client = reqwest::Client::builder()
+            .user_agent("ShaydZ-SuperMonitor/2.0")
+            .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .build()
+            .unwrap_or_default();
+        
+        Self {
+            client,
+            decisions,
+            lifting: HashSet::new(),
+            stream_source: None,
+            token: None,
+            reported: HashSet::new(),
+            monitor,
+            event_log,
+        }
+    }
+    
+    /// Applies new and expired decisions when a bouncer key is set, then reports new
+    /// detections when machine credentials are. Does nothing when disabled.
+    pub async fn run(&mut self, config: &CrowdSecConfig) {
+        if !config.enabled {
+            return;
+        }
+        if let Some(key) = &config.api_key {
+            if let Err(e) = self.pull_decisions(config, key).await {
+                warn!("CrowdSec decision stream failed: {}", e);
+            }
+        }
+        if let (Some(machine_id), Some(password)) = (&config.machine_id, &config.password) {
+            if let Err(e) = self.push_detections(config, machine_id, password).await {
+                warn!("Reporting to CrowdSec failed: {}", e);
+            }
+        }
+    }
+    
+    async fn pull_decisions(&mut self, config: &CrowdSecConfig, key: &str) -> Result<()> {
+        let source = (config.url.clone(), key.to_string());
+        // The first read returns every active decision rather than the changes
+        let startup = self.stream_source.as_ref() != Some(&source);
+        let stream: DecisionStream = self
+            .client
+            .get(format!("{}/v1/decisions/stream", config.url.trim_end_matches('/')))
+            .query(&[("startup", if startup { "true" } else { "false" }), ("scopes", "ip,range")])
+            .header("X-Api-Key", key)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let new = stream.new.unwrap_or_default();
+        
+        // Lifted decisions stay on record until their blocks are gone, so a failed
+        // unblock is tried again on the next poll, and after a restart
+        if startup {
+            let active: HashSet<i64> = new.iter().map(|decision| decision.id).collect();
+            self.lifting.extend(self.decisions.keys().filter(|id| !active.contains(id)));
+        }
+        for decision in stream.deleted.unwrap_or_default() {
+            if self.decisions.contains_key(&decision.id) {
+                self.lifting.insert(decision.id);
+            }
+        }
+        
+        // Only bans are enforced; captcha and other remedies are for other bouncers
+        let mut banned = Vec::new();
+        for decision in new.into_iter().filter(|decision| decision.kind.eq_ignore_ascii_case("ban")) {
+            if let Ok(range) = decision.value.parse::<IpNet>() {
+                self.decisions.insert(decision.id, range.to_string());
+                self.lifting.remove(&decision.id);
+                banned.push(range);
+            }
+        }
+        
+        // A range another decision still bans stays blocked
+        let still_banned: HashSet<&String> = self
+            .decisions
+            .iter()
+            .filter(|(id, _)| !self.lifting.contains(id))
+            .map(|(_, range)| range)
+            .collect();
+        let lifted: BTreeSet<&String> = self
+            .lifting
+            .iter()
+            .filter_map(|id| self.decisions.get(id))
+            .filter(|range| !still_banned.contains(range))
+            .collect();
+        let mut lifted: Vec<IpNet> = lifted.iter().filter_map(|range| range.parse().ok()).collect();
+        
+        let (banned, own_blocks, backend) = {
+            let monitor = self.monitor.read().await;
+            (monitor.blockable_ranges(&banned), monitor.own_blocks(), monitor.firewall())
+        };
+        // The monitor's own blocks are not CrowdSec's to lift
+        let own = own_blocks.list().await;
+        lifted.retain(|range| !own.contains(range));
+        
+        let blocked = if banned.is_empty() { Ok("") } else { firewall::block(&banned, backend).await };
+        let unblocked = if lifted.is_empty() { Ok(()) } else { firewall::unblock(&lifted, backend).await };
+        if unblocked.is_ok() {
+            // nftables merges overlapping ranges, so own blocks inside a lifted range go with it
+            let inside: Vec<IpNet> = own
+                .iter()
+                .copied()
+                .filter(|range| lifted.iter().any(|lifted| lifted.overlaps(*range)))
+                .collect();
+            if !inside.is_empty() {
+                if let Err(e) = firewall::block(&inside, backend).await {
+                    warn!("Failed to restore blocks inside lifted CrowdSec ranges: {}", e);
+                }
+            }
+            for id in std::mem::take(&mut self.lifting) {
+                self.decisions.remove(&id);
+            }
+        }
+        self.save();
+        blocked?;
+        unblocked?;
+        // Until the bans are in place, every poll reads the full list again
+        self.stream_source = Some(source);
+        
+        if !banned.is_empty() || !lifted.is_empty() {
+            info!("CrowdSec decisions: blocked {}, lifted {}", banned.len(), lifted.len());
+            let named = |ranges: &[IpNet]| -> Vec<String> {
+                ranges.iter().take(EVENT_RANGES).map(IpNet::to_string).collect()
+            };
+            self.event_log
+                .record(
+                    Event::new(
+                        EventKind::Action,
+                        AnomalySeverity::Info,
+                        format!("Applied CrowdSec decisions: {} blocked, {} lifted", banned.len(), lifted.len()),
+                    )
+                    .with_details(json!({ "blocked": named(&banned), "lifted": named(&lifted) })),
+                )
+                .await;
+        }
+        
+        Ok(())
+    }
+    
+    /// Sends an alert with a ban for each detection raised since the last report.
+    async fn push_detections(&mut self, config: &CrowdSecConfig, machine_id: &str, password: &str) -> Result<()> {
+        let (anomalies, _) = self.monitor.read().await.detect_anomalies();
+        let mut detections: Vec<(&'static str, IpAddr, String)> = Vec::new();
+        for anomaly in anomalies {
+            let found = SCENARIOS.iter().find_map(|(prefix, scenario)| {
+                let source = anomaly.strip_prefix(prefix)?.split(' ').next()?.parse::<IpAddr>().ok()?;
+                Some((*scenario, source))
+            });
+            if let Some((scenario, source)) = found {
+                detections.push((scenario, source, anomaly));
+            }
+        }
+        // Reported again if it clears and comes back
+        let raised: HashSet<(&'static str, IpAddr)> = detections.iter().map(|(scenario, source, _)| (*scenario, *source)).collect();
+        self.reported.retain(|detection| raised.contains(detection));
+        detections.retain(|(scenario, source, _)| !self.reported.contains(&(*scenario, *source)));
+        if detections.is_empty() {
+            return Ok(());
+        }
+        
+        let url = config.url.trim_end_matches('/');
+        let token = self.watcher_token(url, machine_id, password).await?;
+        let now = Utc::now().to_rfc3339();
+        let alerts: Vec<_> = detections
+            .iter()
+            .map(|(scenario, source, anomaly)| {
+                json!({
+                    "scenario": scenario,
+                    "scenario_hash": "",
+                    "scenario_version": "",
+                    "message": anomaly,
+                    "events_count": 1,
+                    "capacity": 0,
+                    "leakspeed": "0s",
+                    "simulated": false,
+                    "start_at": now,
+                    "stop_at": now,
+                    "events": [{ "timestamp": now, "meta": [{ "key": "source_ip", "value": source.to_string() }] }],
+                    "source": { "scope": "Ip", "value": source.to_string(), "ip": source.to_string() },
+                    "decisions": [{
+                        "duration": config.ban_duration,
+                        "origin": "crowdsec",
+                        "scenario": scenario,
+                        "scope": "Ip",
+                        "type": "ban",
+                        "value": source.to_string(),
+                    }],
+                })
+            })
+            .collect();
+        
+        let response = self.client.post(format!("{}/v1/alerts", url)).bearer_auth(token).json(&alerts).send().await?;
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            self.token = None;
+        }
+        response.error_for_status()?;
+        
+        for (scenario, source, _) in detections {
+            info!("Reported {} {} to CrowdSec", scenario, source);
+            self.reported.insert((scenario, source));
+        }
+        Ok(())
+    }
+    
+    /// The machine's login token, logging in again shortly before it expires.
+    async fn watcher_token(&mut self, url: &str, machine_id: &str, password: &str) -> Result<String> {
+        if let Some(login) = self.token.as_ref().filter(|login| (login.expire - Utc::now()).num_seconds() > 60) {
+            return Ok(login.token.clone());
+        }
+        let scenarios: Vec<&str> = SCENARIOS.iter().map(|(_, scenario)| *scenario).collect();
+        let response = self
+            .client
+            .post(format!("{}/v1/watchers/login", url))
+            .json(&json!({ "machine_id": machine_id, "password": password, "scenarios": scenarios }))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("login as {} refused: {}", machine_id, response.status()));
+        }
+        let login: WatcherLogin = response.json().await?;
+        let token = login.token.clone();
+        self.token = Some(login);
+        Ok(token)
+    }
+    
+    fn save(&self) {
+        let result = data_dir::ensure().and_then(|_| {
+            std::fs::write(CROWDSEC_DECISIONS_FILE, serde_json::to_string_pretty(&self.decisions)?)
+        });
+        if let Err(e) = result {
+            warn!("Failed to save CrowdSec decisions: {}", e);
+        }
+    }
+}
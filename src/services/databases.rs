@@ -0,0 +1,264 @@
+use crate::models::config::{DatabaseConfig, DatabaseKind, MonitoringConfig};
+use crate::models::metrics::SystemMetrics;
+use crate::services::collectors::Collector;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+const CONNECT_TIMEOUT_SECS: u64 = 5;
+// Covers the connection plus the queries, which only read server statistics
+const QUERY_TIMEOUT_SECS: u64 = 10;
+
+const POSTGRES_QUERY: &str = "SELECT (SELECT count(*) FROM pg_stat_activity), current_setting('max_connections'), \
+    pg_is_in_recovery(), EXTRACT(EPOCH FROM now() - pg_last_xact_replay_timestamp())";
+const MYSQL_QUERY: &str = "SHOW GLOBAL STATUS LIKE 'Threads_connected'; SHOW GLOBAL VARIABLES LIKE 'max_connections'";
+
+#[derive(Debug, Clone, PartialEq)]
+enum Replication {
+    /// Not a replica.
+    None,
+    Lag(f64),
+    /// A replica that is not applying changes from its primary.
+    Stopped,
+}
+
+#[derive(Debug, Clone)]
+struct DatabaseStats {
+    /// Round trip of the statistics query, including client startup and login.
+    latency_ms: f64,
+    connections: f64,
+    max_connections: f64,
+    replication: Replication,
+}
+
+#[derive(Default)]
+struct DatabaseState {
+    last_run: Option<DateTime<Utc>>,
+    latest: Option<Result<DatabaseStats, String>>,
+}
+
+/// Checks MySQL and PostgreSQL servers through their command-line clients.
+pub struct DatabaseCollector {
+    databases: Vec<DatabaseConfig>,
+    state: BTreeMap<String, DatabaseState>,
+}
+
+impl DatabaseCollector {
+    pub fn new() -> Self {
+        Self {
+            databases: Vec::new(),
+            state: BTreeMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Collector for DatabaseCollector {
+    fn name(&self) -> &str {
+        "databases"
+    }
+    
+    fn configure(&mut self, config: &MonitoringConfig) {
+        self.state
+            .retain(|name, _| config.databases.iter().any(|database| &database.name == name));
+        self.databases = config.databases.clone();
+    }
+    
+    async fn collect(&mut self, metrics: &mut SystemMetrics) -> Result<()> {
+        let now = Utc::now();
+        
+        for database in &self.databases {
+            let state = self.state.entry(database.name.clone()).or_default();
+            let due = state
+                .last_run
+                .map_or(true, |last| (now - last).num_seconds() >= database.interval as i64);
+            if due {
+                state.last_run = Some(now);
+                let stats = match database.kind {
+                    DatabaseKind::Mysql => mysql_stats(database).await,
+                    DatabaseKind::Postgres => postgres_stats(database).await,
+                };
+                state.latest = Some(stats.map_err(|e| e.to_string()));
+            }
+            
+            let Some(Ok(stats)) = &state.latest else {
+                continue;
+            };
+            let name = &database.name;
+            metrics.custom.insert(format!("{}.latency_ms", name), stats.latency_ms);
+            metrics.custom.insert(format!("{}.connections", name), stats.connections);
+            if stats.max_connections > 0.0 {
+                metrics.custom.insert(
+                    format!("{}.connections_pct", name),
+                    stats.connections * 100.0 / stats.max_connections,
+                );
+            }
+            if let Replication::Lag(lag) = stats.replication {
+                metrics.custom.insert(format!("{}.replication_lag_s", name), lag);
+            }
+        }
+        
+        // Failing here would also drop the readings of the databases that did answer
+        Ok(())
+    }
+    
+    fn anomalies(&self) -> Vec<String> {
+        let mut anomalies = Vec::new();
+        
+        for database in &self.databases {
+            let Some(Some(latest)) = self.state.get(&database.name).map(|state| &state.latest) else {
+                continue;
+            };
+            let stats = match latest {
+                Ok(stats) => stats,
+                Err(e) => {
+                    anomalies.push(format!("Database Down: {} {}", database.name, e));
+                    continue;
+                }
+            };
+            
+            if stats.max_connections > 0.0 {
+                let used = stats.connections * 100.0 / stats.max_connections;
+                if used > database.max_connections_pct {
+                    anomalies.push(format!(
+                        "Anomaly: {}.connections_pct {:.1} (Normal: <{:.1})",
+                        database.name, used, database.max_connections_pct
+                    ));
+                }
+            }
+            match stats.replication {
+                Replication::Lag(lag) if lag > database.max_replication_lag as f64 => anomalies.push(format!(
+                    "Anomaly: {}.replication_lag_s {:.1} (Normal: <{})",
+                    database.name, lag, database.max_replication_lag
+                )),
+                Replication::Stopped => {
+                    anomalies.push(format!("Database Replication Stopped: {} is not replicating", database.name))
+                }
+                _ => {}
+            }
+        }
+        
+        anomalies
+    }
+}
+
+async fn postgres_stats(database: &DatabaseConfig) -> Result<DatabaseStats> {
+    let mut command = Command::new("psql");
+    command
+        .args(["-X", "-w", "-A", "-t", "-F", "\t", "-h", &database.host, "-U", &database.user])
+        .env("PGPASSWORD", &database.password)
+        .env("PGCONNECT_TIMEOUT", CONNECT_TIMEOUT_SECS.to_string());
+    if let Some(port) = database.port {
+        command.args(["-p", &port.to_string()]);
+    }
+    if let Some(name) = &database.database {
+        command.args(["-d", name]);
+    }
+    command.args(["-c", POSTGRES_QUERY]);
+    
+    let started = Instant::now();
+    let output = run(command).await?;
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+    
+    // The lag column is empty on a primary, so only the line end is trimmed
+    let fields: Vec<&str> = output.trim_end_matches(['\n', '\r']).split('\t').collect();
+    let [connections, max_connections, in_recovery, lag] = fields[..] else {
+        return Err(anyhow!("unexpected psql output: {}", output.trim()));
+    };
+    let replication = match (in_recovery, lag.parse::<f64>()) {
+        ("t", Ok(lag)) => Replication::Lag(lag.max(0.0)),
+        // A replica that has not replayed anything yet
+        ("t", Err(_)) => Replication::Stopped,
+        _ => Replication::None,
+    };
+    
+    Ok(DatabaseStats {
+        latency_ms,
+        connections: connections.parse()?,
+        max_connections: max_connections.parse()?,
+        replication,
+    })
+}
+
+async fn mysql_stats(database: &DatabaseConfig) -> Result<DatabaseStats> {
+    let started = Instant::now();
+    let output = run(mysql_command(database, MYSQL_QUERY)).await?;
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+    
+    let values: BTreeMap<&str, f64> = output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter_map(|(key, value)| Some((key, value.trim().parse().ok()?)))
+        .collect();
+    let (Some(&connections), Some(&max_connections)) =
+        (values.get("Threads_connected"), values.get("max_connections"))
+    else {
+        return Err(anyhow!("unexpected mysql output: {}", output.trim()));
+    };
+    
+    // SHOW REPLICA STATUS is MySQL 8.0.22 and MariaDB 10.5.1 on; older servers know the old name
+    let status = match run(mysql_command(database, "SHOW REPLICA STATUS\\G")).await {
+        Ok(status) => status,
+        Err(_) => run(mysql_command(database, "SHOW SLAVE STATUS\\G")).await?,
+    };
+    let lag = status.lines().find_map(|line| {
+        let (key, value) = line.trim().split_once(':')?;
+        matches!(key, "Seconds_Behind_Source" | "Seconds_Behind_Master").then(|| value.trim().to_string())
+    });
+    let replication = match lag {
+        None => Replication::None,
+        Some(lag) => lag.parse().map_or(Replication::Stopped, Replication::Lag),
+    };
+    
+    Ok(DatabaseStats {
+        latency_ms,
+        connections,
+        max_connections,
+        replication,
+    })
+}
+
+fn mysql_command(database: &DatabaseConfig, sql: &str) -> Command {
+    let mut command = Command::new("mysql");
+    command
+        .args(["-N", "-B", "-h", &database.host, "-u", &database.user])
+        .arg(format!("--connect-timeout={}", CONNECT_TIMEOUT_SECS))
+        // Keeps the password out of the process list
+        .env("MYSQL_PWD", &database.password);
+    if let Some(port) = database.port {
+        command.args(["-P", &port.to_string()]);
+    }
+    command.args(["-e", sql]);
+    if let Some(name) = &database.database {
+        // As a bare argument a name starting with "--" would be read as an option
+        command.arg(format!("--database={}", name));
+    }
+    command
+}
+
+/// Runs a database client and returns its standard output, or the first line of its
+/// error output when it fails.
+async fn run(mut command: Command) -> Result<String> {
+    let program = command.as_std().get_program().to_string_lossy().into_owned();
+    let output = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(Duration::from_secs(QUERY_TIMEOUT_SECS), output)
+        .await
+        .map_err(|_| anyhow!("no answer within {}s", QUERY_TIMEOUT_SECS))?
+        .map_err(|e| anyhow!("{}: {}", program, e))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default();
+        return Err(anyhow!("{}", message));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,178 @@
+use crate::models::diagnostics::{SnapshotSection, StateSnapshot, Traceroute, TracerouteHop};
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use std::process::Stdio;
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+
+const TRACEROUTE_MAX_HOPS: &str = "20";
+const TRACEROUTE_PROBES: u32 = 3;
+const TRACEROUTE_TIMEOUT_SECS: u64 = 60;
+const SNAPSHOT_COMMAND_TIMEOUT_SECS: u64 = 20;
+const SNAPSHOT_PROCESSES: usize = 40;
+const SNAPSHOT_CONNECTIONS: usize = 200;
+const SNAPSHOT_KERNEL_LINES: usize = 50;
+const SNAPSHOT_DIRECTORIES: usize = 25;
+
+/// Runs `traceroute` against `host` and parses per-hop latency and loss.
+pub async fn traceroute(host: &str) -> Result<Traceroute> {
+    // Hosts are passed as arguments, so one starting with '-' would be read as an option
+    let valid = host.len() <= 253 && host.chars().all(|c| c.is_ascii_alphanumeric() || ".:-".contains(c));
+    if host.is_empty() || host.starts_with('-') || !valid {
+        return Err(anyhow!("Invalid host '{}'", host));
+    }
+    
+    let started_at = Utc::now();
+    let probes = TRACEROUTE_PROBES.to_string();
+    let output = timeout(
+        Duration::from_secs(TRACEROUTE_TIMEOUT_SECS),
+        Command::new("traceroute")
+            .args(["-n", "-q", &probes, "-w", "1", "-m", TRACEROUTE_MAX_HOPS, host])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output(),
+    )
+    .await
+    .map_err(|_| anyhow!("traceroute timed out"))?
+    .map_err(|e| anyhow!("traceroute unavailable: {}", e))?;
+    
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let hops: Vec<TracerouteHop> = stdout.lines().filter_map(parse_hop).collect();
+    if hops.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("traceroute failed: {}", stderr.trim()));
+    }
+    
+    Ok(Traceroute {
+        target: host.to_string(),
+        started_at,
+        hops,
+    })
+}
+
+/// Captures the busiest processes, open connections, the end of the kernel log and
+/// the largest directories under `disk_paths`, running the commands side by side.
+pub async fn state_snapshot(anomaly: &str, disk_paths: &[String]) -> StateSnapshot {
+    let captured_at = Utc::now();
+    let disk_paths: Vec<&str> = disk_paths
+        .iter()
+        .map(String::as_str)
+        .filter(|path| std::path::Path::new(path).is_dir())
+        .collect();
+    let mut du_args = vec!["-xk", "--max-depth=1"];
+    du_args.extend(&disk_paths);
+    
+    let (processes, connections, kernel, disk) = tokio::join!(
+        snapshot_section("processes", "ps", &["-eo", "pid,user,%cpu,%mem,rss,etime,stat,args", "--sort=-%cpu"]),
+        snapshot_section("connections", "ss", &["-tunap"]),
+        snapshot_section("kernel log", "dmesg", &["-T"]),
+        snapshot_section("disk usage", "du", &du_args),
+    );
+    
+    let mut sections = vec![processes, connections, kernel, disk];
+    truncate_lines(&mut sections[0].output, SNAPSHOT_PROCESSES + 1, false);
+    truncate_lines(&mut sections[1].output, SNAPSHOT_CONNECTIONS + 1, false);
+    truncate_lines(&mut sections[2].output, SNAPSHOT_KERNEL_LINES, true);
+    sections[3].output = largest_directories(&sections[3].output);
+    
+    StateSnapshot {
+        anomaly: anomaly.to_string(),
+        captured_at,
+        sections,
+    }
+}
+
+async fn snapshot_section(name: &str, program: &str, args: &[&str]) -> SnapshotSection {
+    let output = timeout(
+        Duration::from_secs(SNAPSHOT_COMMAND_TIMEOUT_SECS),
+        Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await;
+    
+    // du reports unreadable directories but still sizes the rest, so stdout counts even on failure
+    let (output, error) = match output {
+        Err(_) => (String::new(), Some(format!("{} timed out", program))),
+        Ok(Err(e)) => (String::new(), Some(format!("{} unavailable: {}", program, e))),
+        Ok(Ok(output)) => {
+            let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+            let error = (stdout.trim().is_empty() && !output.status.success())
+                .then(|| String::from_utf8_lossy(&output.stderr).trim().to_string());
+            (stdout, error)
+        }
+    };
+    
+    SnapshotSection {
+        name: name.to_string(),
+        command: format!("{} {}", program, args.join(" ")),
+        output,
+        error,
+    }
+}
+
+/// Keeps the first (or, with `tail`, the last) `max` lines of `text`.
+fn truncate_lines(text: &mut String, max: usize, tail: bool) {
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.len() <= max {
+        return;
+    }
+    let kept = if tail { &lines[lines.len() - max..] } else { &lines[..max] };
+    *text = kept.join("\n");
+}
+
+/// Sorts `du -k` output by size and renders the largest directories in MiB.
+fn largest_directories(du: &str) -> String {
+    let mut sizes: Vec<(u64, &str)> = du
+        .lines()
+        .filter_map(|line| {
+            let (size, path) = line.split_once('\t')?;
+            Some((size.trim().parse().ok()?, path))
+        })
+        .collect();
+    sizes.sort_by_key(|(size, _)| std::cmp::Reverse(*size));
+    sizes
+        .iter()
+        .take(SNAPSHOT_DIRECTORIES)
+        .map(|(size, path)| format!("{:>10.1} MiB  {}", *size as f64 / 1024.0, path))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parses a hop line such as ` 3  10.0.0.1  5.1 ms *  5.3 ms`.
+fn parse_hop(line: &str) -> Option<TracerouteHop> {
+    let mut tokens = line.split_whitespace().peekable();
+    let hop = tokens.next()?.parse::<u32>().ok()?;
+    
+    let mut address = None;
+    let mut latencies = Vec::new();
+    let mut lost = 0u32;
+    
+    while let Some(token) = tokens.next() {
+        if token == "*" {
+            lost += 1;
+        } else if let Ok(ms) = token.parse::<f64>() {
+            if tokens.peek() == Some(&"ms") {
+                tokens.next();
+            }
+            latencies.push(ms);
+        } else if address.is_none() && !token.starts_with('!') {
+            address = Some(token.to_string());
+        }
+    }
+    
+    let sent = (latencies.len() as u32 + lost).max(1);
+    let avg_ms = (!latencies.is_empty()).then(|| latencies.iter().sum::<f64>() / latencies.len() as f64);
+    
+    Some(TracerouteHop {
+        hop,
+        address,
+        avg_ms,
+        loss_percent: lost as f64 / sent as f64 * 100.0,
+        latencies_ms: latencies,
+    })
+}
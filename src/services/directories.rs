@@ -0,0 +1,191 @@
+use crate::models::config::{DirectoryConfig, MonitoringConfig};
+use crate::models::metrics::SystemMetrics;
+use crate::services::collectors::Collector;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, VecDeque};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+const MB: f64 = 1024.0 * 1024.0;
+// Growth is measured over up to this long, so a single burst does not dominate the rate
+const GROWTH_WINDOW_SECS: i64 = 3600;
+
+#[derive(Default)]
+struct DirectoryState {
+    last_run: Option<DateTime<Utc>>,
+    /// Measured sizes within the growth window, oldest first.
+    sizes: VecDeque<(DateTime<Utc>, u64)>,
+    /// Bytes per hour, once there are two measurements.
+    growth: Option<f64>,
+    /// Bytes available to unprivileged users on the directory's filesystem.
+    free: Option<u64>,
+    error: Option<String>,
+}
+
+/// Measures the total size of configured directories and how fast they grow.
+pub struct DirectoryCollector {
+    directories: Vec<DirectoryConfig>,
+    state: BTreeMap<String, DirectoryState>,
+}
+
+impl DirectoryCollector {
+    pub fn new() -> Self {
+        Self {
+            directories: Vec::new(),
+            state: BTreeMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Collector for DirectoryCollector {
+    fn name(&self) -> &str {
+        "directories"
+    }
+    
+    fn configure(&mut self, config: &MonitoringConfig) {
+        // A changed path starts over rather than reporting a jump as growth
+        self.state.retain(|name, _| {
+            let path = path_of(&config.directories, name);
+            path.is_some() && path == path_of(&self.directories, name)
+        });
+        self.directories = config.directories.clone();
+    }
+    
+    async fn collect(&mut self, metrics: &mut SystemMetrics) -> Result<()> {
+        let now = Utc::now();
+        
+        for directory in &self.directories {
+            let state = self.state.entry(directory.name.clone()).or_default();
+            let due = state.last_run.map_or(true, |last| (now - last).num_seconds() >= directory.interval as i64);
+            if due {
+                state.last_run = Some(now);
+                let path = PathBuf::from(&directory.path);
+                match tokio::task::spawn_blocking(move || directory_size(&path)).await? {
+                    Ok(size) => {
+                        state.sizes.retain(|(measured, _)| (now - *measured).num_seconds() <= GROWTH_WINDOW_SECS);
+                        state.sizes.push_back((now, size));
+                        state.growth = state.sizes.front().filter(|(first, _)| *first < now).map(|(first, first_size)| {
+                            let hours = (now - *first).num_seconds() as f64 / 3600.0;
+                            (size as f64 - *first_size as f64) / hours
+                        });
+                        state.free = free_space(&directory.path);
+                        state.error = None;
+                    }
+                    Err(e) => {
+                        state.sizes.clear();
+                        state.growth = None;
+                        state.error = Some(e.to_string());
+                    }
+                }
+            }
+            
+            if let Some((_, size)) = state.sizes.back() {
+                metrics.custom.insert(format!("{}.size_mb", directory.name), *size as f64 / MB);
+            }
+            if let Some(growth) = state.growth {
+                metrics.custom.insert(format!("{}.growth_mb_h", directory.name), growth / MB);
+            }
+        }
+        
+        let errors: Vec<String> = self
+            .state
+            .iter()
+            .filter_map(|(name, state)| Some(format!("{}: {}", name, state.error.as_ref()?)))
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(errors.join("; ")))
+        }
+    }
+    
+    fn anomalies(&self) -> Vec<String> {
+        let mut anomalies = Vec::new();
+        
+        for directory in &self.directories {
+            let Some(state) = self.state.get(&directory.name) else {
+                continue;
+            };
+            let Some((_, size)) = state.sizes.back() else {
+                continue;
+            };
+            
+            if let Some(limit) = directory.max_size.filter(|limit| size > limit) {
+                anomalies.push(format!(
+                    "Anomaly: {}.size_mb {:.1} (Normal: <{:.1})",
+                    directory.name,
+                    *size as f64 / MB,
+                    limit as f64 / MB
+                ));
+            }
+            let Some(growth) = state.growth else {
+                continue;
+            };
+            if let Some(limit) = directory.max_growth.filter(|&limit| growth > limit as f64) {
+                anomalies.push(format!(
+                    "Anomaly: {}.growth_mb_h {:.1} (Normal: <{:.1})",
+                    directory.name,
+                    growth / MB,
+                    limit as f64 / MB
+                ));
+            }
+            if let Some(free) = state.free.filter(|_| directory.fill_warning > 0 && growth > 0.0) {
+                if (free as f64 / growth) < directory.fill_warning as f64 {
+                    anomalies.push(format!(
+                        "Directory Filling Disk: {} fills {} within {}h",
+                        directory.name, directory.path, directory.fill_warning
+                    ));
+                }
+            }
+        }
+        
+        anomalies
+    }
+}
+
+/// Total size of the files below `root`, like `du -sx --apparent-size`. Symlinks are
+/// not followed and other filesystems mounted inside are not entered.
+fn directory_size(root: &Path) -> Result<u64> {
+    let device = std::fs::metadata(root)
+        .map_err(|e| anyhow!("{}: {}", root.display(), e))?
+        .dev();
+    let mut total = 0u64;
+    let mut pending = vec![root.to_path_buf()];
+    
+    while let Some(dir) = pending.pop() {
+        // Subdirectories that cannot be read are skipped rather than failing the whole tree
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                if metadata.dev() == device {
+                    pending.push(entry.path());
+                }
+            } else if metadata.is_file() {
+                total += metadata.len();
+            }
+        }
+    }
+    
+    Ok(total)
+}
+
+fn path_of<'a>(directories: &'a [DirectoryConfig], name: &str) -> Option<&'a str> {
+    directories.iter().find(|directory| directory.name == name).map(|directory| directory.path.as_str())
+}
+
+fn free_space(path: &str) -> Option<u64> {
+    let path = std::ffi::CString::new(path).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
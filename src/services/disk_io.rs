@@ -0,0 +1,106 @@
+use crate::models::metrics::DiskIo;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Instant;
+
+const SECTOR_BYTES: f64 = 512.0;
+
+/// Cumulative counters of one device, as read from `/proc/diskstats`.
+#[derive(Debug, Clone, Copy)]
+struct DiskCounters {
+    reads: u64,
+    sectors_read: u64,
+    read_ms: u64,
+    writes: u64,
+    sectors_written: u64,
+    write_ms: u64,
+    busy_ms: u64,
+    weighted_ms: u64,
+}
+
+/// Turns successive `/proc/diskstats` readings into rates.
+pub struct DiskIoSampler {
+    previous: Option<(Instant, HashMap<String, DiskCounters>)>,
+}
+
+impl DiskIoSampler {
+    pub fn new() -> Self {
+        Self { previous: None }
+    }
+    
+    /// Activity of every whole disk since the previous call; empty on the first call.
+    pub fn sample(&mut self) -> HashMap<String, DiskIo> {
+        let now = Instant::now();
+        let current = read_diskstats();
+        let Some((then, previous)) = self.previous.replace((now, current.clone())) else {
+            return HashMap::new();
+        };
+        
+        let elapsed_ms = now.duration_since(then).as_secs_f64() * 1000.0;
+        if elapsed_ms <= 0.0 {
+            return HashMap::new();
+        }
+        let per_sec = 1000.0 / elapsed_ms;
+        
+        current
+            .iter()
+            .filter_map(|(device, now)| {
+                let before = previous.get(device)?;
+                // Counters are reset when a device is re-attached
+                let delta = |now: u64, before: u64| now.saturating_sub(before) as f64;
+                
+                let reads = delta(now.reads, before.reads);
+                let writes = delta(now.writes, before.writes);
+                let io_ms = delta(now.read_ms, before.read_ms) + delta(now.write_ms, before.write_ms);
+                let requests = reads + writes;
+                
+                Some((
+                    device.clone(),
+                    DiskIo {
+                        read_iops: reads * per_sec,
+                        write_iops: writes * per_sec,
+                        read_bytes_per_sec: delta(now.sectors_read, before.sectors_read) * SECTOR_BYTES * per_sec,
+                        write_bytes_per_sec: delta(now.sectors_written, before.sectors_written) * SECTOR_BYTES * per_sec,
+                        await_ms: if requests > 0.0 { io_ms / requests } else { 0.0 },
+                        queue_depth: delta(now.weighted_ms, before.weighted_ms) / elapsed_ms,
+                        util_percent: (delta(now.busy_ms, before.busy_ms) / elapsed_ms * 100.0).min(100.0),
+                    },
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Counters of whole disks; partitions would count the same requests twice, and
+/// loop and RAM devices say nothing about the hardware.
+fn read_diskstats() -> HashMap<String, DiskCounters> {
+    let content = std::fs::read_to_string("/proc/diskstats").unwrap_or_default();
+    
+    content
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let device = *fields.get(2)?;
+            if ["loop", "ram", "zram", "fd", "sr"].iter().any(|prefix| device.starts_with(prefix))
+                || !Path::new("/sys/block").join(device).exists()
+            {
+                return None;
+            }
+            
+            let field = |index: usize| fields.get(index).and_then(|value| value.parse::<u64>().ok());
+            Some((
+                device.to_string(),
+                DiskCounters {
+                    reads: field(3)?,
+                    sectors_read: field(5)?,
+                    read_ms: field(6)?,
+                    writes: field(7)?,
+                    sectors_written: field(9)?,
+                    write_ms: field(10)?,
+                    busy_ms: field(12)?,
+                    weighted_ms: field(13)?,
+                },
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,264 @@
+use crate::models::config::{DnsRecordConfig, DnsRecordType};
+use crate::utils::data_dir;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use dns_lookup::{AddrInfoHints, LookupErrorKind};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::process::Stdio;
+use tokio::fs::{self, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::warn;
+
+pub const DNS_HISTORY_FILE: &str = "data/dns_history.jsonl";
+const DEFAULT_DNS_INTERVAL: u64 = 300;
+
+/// A record's answers changing (or first being seen), one line of `data/dns_history.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsChange {
+    pub timestamp: DateTime<Utc>,
+    pub name: String,
+    pub record_type: DnsRecordType,
+    pub previous: Vec<String>,
+    pub current: Vec<String>,
+}
+
+type RecordKey = (String, DnsRecordType);
+
+/// Resolves watched DNS records on their schedules and keeps the last answers.
+pub struct DnsWatcher {
+    answers: HashMap<RecordKey, Vec<String>>,
+    last_lookup: HashMap<RecordKey, DateTime<Utc>>,
+}
+
+impl DnsWatcher {
+    /// Starts from the answers recorded by earlier runs, so learned records stay learned.
+    pub fn new() -> Self {
+        let content = std::fs::read_to_string(DNS_HISTORY_FILE).unwrap_or_default();
+        let answers = content
+            .lines()
+            .filter_map(|line| serde_json::from_str::<DnsChange>(line).ok())
+            .map(|change| ((change.name, change.record_type), change.current))
+            .collect();
+        
+        Self {
+            answers,
+            last_lookup: HashMap::new(),
+        }
+    }
+    
+    /// Forgets records that are no longer watched.
+    pub fn retain(&mut self, records: &[DnsRecordConfig]) {
+        let watched = |key: &RecordKey| records.iter().any(|r| r.name == key.0 && r.record_type == key.1);
+        self.answers.retain(|key, _| watched(key));
+        self.last_lookup.retain(|key, _| watched(key));
+    }
+    
+    /// Marks the records that are due a lookup, to be resolved without holding the monitor.
+    pub fn next_poll(&mut self, records: &[DnsRecordConfig]) -> DnsPoll {
+        let now = Utc::now();
+        let mut due = Vec::new();
+        
+        for record in records {
+            let key = (record.name.clone(), record.record_type);
+            let interval = record.interval.unwrap_or(DEFAULT_DNS_INTERVAL) as i64;
+            if self.last_lookup.get(&key).is_some_and(|last| (now - *last).num_seconds() < interval) {
+                continue;
+            }
+            self.last_lookup.insert(key, now);
+            due.push(record.clone());
+        }
+        
+        DnsPoll { records: due, at: now }
+    }
+    
+    /// Keeps the answers of a [`DnsPoll`] and returns a message for each learned
+    /// record whose answers changed.
+    pub async fn apply(&mut self, polled: DnsAnswers, records: &[DnsRecordConfig]) -> Vec<String> {
+        let mut changes = Vec::new();
+        
+        for (record, current) in polled.answers {
+            // A reload may have removed or changed the record while it was looked up
+            let Some(record) = records.iter().find(|r| r.name == record.name && r.record_type == record.record_type) else {
+                continue;
+            };
+            let key = (record.name.clone(), record.record_type);
+            let previous = self.answers.get(&key).cloned();
+            if previous.as_ref() == Some(&current) {
+                continue;
+            }
+            
+            let change = DnsChange {
+                timestamp: polled.at,
+                name: record.name.clone(),
+                record_type: record.record_type,
+                previous: previous.clone().unwrap_or_default(),
+                current: current.clone(),
+            };
+            if let Err(e) = append_change(&change).await {
+                warn!("Failed to record DNS change: {}", e);
+            }
+            
+            // Records with expected answers are reported as mismatches instead
+            if let Some(previous) = previous.filter(|_| record.expected.is_empty()) {
+                changes.push(format!(
+                    "DNS Changed: {} {} {} -> {}",
+                    record.name,
+                    record.record_type,
+                    display_answers(&previous),
+                    display_answers(&current)
+                ));
+            }
+            self.answers.insert(key, current);
+        }
+        
+        changes
+    }
+    
+    /// Ongoing anomalies for records whose answers differ from the expected ones.
+    pub fn mismatches(&self, records: &[DnsRecordConfig]) -> Vec<String> {
+        records
+            .iter()
+            .filter(|record| !record.expected.is_empty())
+            .filter_map(|record| {
+                let current = self.answers.get(&(record.name.clone(), record.record_type))?;
+                let expected = normalize(record.expected.iter().map(String::as_str));
+                (current != &expected).then(|| {
+                    format!("DNS Mismatch: {} {} = {}", record.name, record.record_type, display_answers(current))
+                })
+            })
+            .collect()
+    }
+    
+    /// Latest answers of every watched record.
+    pub fn answers(&self) -> Vec<(String, DnsRecordType, Vec<String>)> {
+        self.answers
+            .iter()
+            .map(|((name, record_type), answers)| (name.clone(), *record_type, answers.clone()))
+            .collect()
+    }
+}
+
+/// Records due a lookup.
+pub struct DnsPoll {
+    records: Vec<DnsRecordConfig>,
+    at: DateTime<Utc>,
+}
+
+/// What a [`DnsPoll`] resolved; failed lookups are logged and left out.
+pub struct DnsAnswers {
+    answers: Vec<(DnsRecordConfig, Vec<String>)>,
+    at: DateTime<Utc>,
+}
+
+impl DnsPoll {
+    pub async fn run(self) -> DnsAnswers {
+        let mut answers = Vec::new();
+        for record in self.records {
+            match resolve(&record.name, record.record_type).await {
+                Ok(current) => answers.push((record, current)),
+                Err(e) => warn!("DNS lookup of {} {} failed: {}", record.name, record.record_type, e),
+            }
+        }
+        DnsAnswers { answers, at: self.at }
+    }
+}
+
+/// Resolves a record with `dig`. Without `dig`, A and AAAA records fall back to the
+/// system resolver. A name that does not exist resolves to no answers.
+pub async fn resolve(name: &str, record_type: DnsRecordType) -> Result<Vec<String>> {
+    if name.starts_with('-') {
+        return Err(anyhow!("Invalid name"));
+    }
+    
+    match Command::new("dig")
+        .args(["+short", "+time=3", "+tries=2", name, &record_type.to_string()])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .await
+    {
+        Ok(output) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            // Comment lines are dig reporting errors such as timeouts
+            if stdout.lines().any(|line| line.starts_with(';')) {
+                return Err(anyhow!("{}", stdout.trim()));
+            }
+            Ok(normalize(stdout.lines()))
+        }
+        Ok(output) => Err(anyhow!("dig exited with {}", output.status)),
+        Err(_) => system_resolve(name, record_type).await,
+    }
+}
+
+async fn system_resolve(name: &str, record_type: DnsRecordType) -> Result<Vec<String>> {
+    let want_v4 = match record_type {
+        DnsRecordType::A => true,
+        DnsRecordType::Aaaa => false,
+        other => return Err(anyhow!("{} lookups need dig installed", other)),
+    };
+    let host = name.to_string();
+    
+    let addresses = tokio::task::spawn_blocking(move || {
+        match dns_lookup::getaddrinfo(Some(&host), None, Some(AddrInfoHints::default())) {
+            Ok(entries) => Ok(entries.filter_map(|entry| entry.ok()).map(|entry| entry.sockaddr.ip()).collect()),
+            Err(e) if matches!(e.kind(), LookupErrorKind::NoName | LookupErrorKind::NoData) => Ok(Vec::new()),
+            Err(e) => Err(anyhow!("{}", std::io::Error::from(e))),
+        }
+    })
+    .await??;
+    
+    let addresses: Vec<IpAddr> = addresses;
+    let answers: Vec<String> = addresses
+        .into_iter()
+        .filter(|ip| ip.is_ipv4() == want_v4)
+        .map(|ip| ip.to_string())
+        .collect();
+    Ok(normalize(answers.iter().map(String::as_str)))
+}
+
+/// Lowercased, without trailing dots, sorted and deduplicated, so answers compare as sets.
+fn normalize<'a>(answers: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut answers: Vec<String> = answers
+        .map(|answer| answer.trim().trim_end_matches('.').to_lowercase())
+        .filter(|answer| !answer.is_empty())
+        .collect();
+    answers.sort();
+    answers.dedup();
+    answers
+}
+
+fn display_answers(answers: &[String]) -> String {
+    if answers.is_empty() {
+        "(none)".to_string()
+    } else {
+        answers.join(",")
+    }
+}
+
+/// Recorded DNS changes, oldest first.
+pub async fn history() -> Vec<DnsChange> {
+    let content = fs::read_to_string(DNS_HISTORY_FILE).await.unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+async fn append_change(change: &DnsChange) -> Result<()> {
+    data_dir::ensure_async().await?;
+    
+    let mut line = serde_json::to_string(change)?;
+    line.push('\n');
+    
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(DNS_HISTORY_FILE)
+        .await?;
+    file.write_all(line.as_bytes()).await?;
+    
+    Ok(())
+}
@@ -0,0 +1,222 @@
+use crate::models::config::MonitoringConfig;
+use crate::models::metrics::SystemMetrics;
+use crate::services::collectors::Collector;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tokio::time::{timeout, Duration};
+
+const MB: f64 = 1024.0 * 1024.0;
+const TIMEOUT_SECS: u64 = 5;
+// Listing a few hundred containers stays well below this
+const MAX_RESPONSE_BYTES: u64 = 16 * 1024 * 1024;
+
+/// One container as listed by `/api/collectors`.
+#[derive(Debug, Clone, Serialize)]
+struct ContainerStatus {
+    name: String,
+    /// `running`, `exited`, `restarting` and so on.
+    state: String,
+    /// Docker's own summary, e.g. `Up 3 hours`.
+    status: String,
+    restarts: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cpu_percent: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    memory_mb: Option<f64>,
+}
+
+/// Containers of the local Docker engine, read from its API socket: their state and
+/// restarts, and the CPU and memory of the running ones. A container that stops
+/// with an error is reported until it runs again or is removed.
+pub struct DockerCollector {
+    socket: String,
+    containers: Vec<ContainerStatus>,
+    /// CPU time of each running container and of the host at the previous run, in
+    /// nanoseconds, by container id.
+    cpu_times: HashMap<String, (u64, u64)>,
+    /// Restart count and whether it was running at the previous run, by name.
+    previous: HashMap<String, (u64, bool)>,
+    /// Why each container seen running has since stopped with an error, by name.
+    exited: BTreeMap<String, String>,
+    /// Containers that restarted since the previous run.
+    restarted: Vec<String>,
+}
+
+impl DockerCollector {
+    pub fn new() -> Self {
+        Self {
+            socket: String::new(),
+            containers: Vec::new(),
+            cpu_times: HashMap::new(),
+            previous: HashMap::new(),
+            exited: BTreeMap::new(),
+            restarted: Vec::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Collector for DockerCollector {
+    fn name(&self) -> &str {
+        "docker"
+    }
+    
+    fn configure(&mut self, config: &MonitoringConfig) {
+        self.socket = config.docker.socket.clone();
+    }
+    
+    async fn collect(&mut self, metrics: &mut SystemMetrics) -> Result<()> {
+        self.restarted.clear();
+        // Hosts without Docker have nothing to report
+        if !Path::new(&self.socket).exists() {
+            self.containers.clear();
+            self.exited.clear();
+            return Ok(());
+        }
+        
+        let list = get(&self.socket, "/containers/json?all=1").await?;
+        let socket = &self.socket;
+        let inspected = futures::future::join_all(list.as_array().into_iter().flatten().map(|container| async move {
+            let id = container["Id"].as_str().unwrap_or_default();
+            let running = container["State"].as_str() == Some("running");
+            let details = get(socket, &format!("/containers/{}/json", id)).await?;
+            let stats = if running {
+                Some(get(socket, &format!("/containers/{}/stats?stream=false&one-shot=true", id)).await?)
+            } else {
+                None
+            };
+            Ok::<_, anyhow::Error>((container, details, stats))
+        }))
+        .await;
+        
+        let mut containers = Vec::new();
+        let mut cpu_times = HashMap::new();
+        let mut previous = HashMap::new();
+        for result in inspected {
+            // A container removed between the listing and its inspection is skipped
+            let Ok((container, details, stats)) = result else {
+                continue;
+            };
+            let id = container["Id"].as_str().unwrap_or_default().to_string();
+            let name = container["Names"][0].as_str().unwrap_or(&id).trim_start_matches('/').to_string();
+            let mut status = ContainerStatus {
+                name: name.clone(),
+                state: container["State"].as_str().unwrap_or("unknown").to_string(),
+                status: container["Status"].as_str().unwrap_or_default().to_string(),
+                restarts: details["RestartCount"].as_u64().unwrap_or(0),
+                cpu_percent: None,
+                memory_mb: None,
+            };
+            let running = status.state == "running";
+            
+            if let Some(stats) = stats {
+                let times = (
+                    stats["cpu_stats"]["cpu_usage"]["total_usage"].as_u64().unwrap_or(0),
+                    stats["cpu_stats"]["system_cpu_usage"].as_u64().unwrap_or(0),
+                );
+                // CPU use is the share of host CPU time since the previous run, which a
+                // one-shot reading cannot give on its own
+                if let Some(&(container_then, system_then)) = self.cpu_times.get(&id) {
+                    if times.0 >= container_then && times.1 > system_then {
+                        let cpus = stats["cpu_stats"]["online_cpus"].as_f64().unwrap_or(1.0);
+                        status.cpu_percent =
+                            Some((times.0 - container_then) as f64 / (times.1 - system_then) as f64 * cpus * 100.0);
+                    }
+                }
+                cpu_times.insert(id, times);
+                
+                // Page cache the kernel can reclaim is not counted, as `docker stats` does
+                let memory = &stats["memory_stats"];
+                if let Some(usage) = memory["usage"].as_f64() {
+                    let cache = memory["stats"]["inactive_file"]
+                        .as_f64()
+                        .or_else(|| memory["stats"]["total_inactive_file"].as_f64())
+                        .unwrap_or(0.0);
+                    status.memory_mb = Some((usage - cache).max(0.0) / MB);
+                }
+            }
+            
+            if let Some(cpu) = status.cpu_percent {
+                metrics.custom.insert(format!("docker.{}.cpu_pct", name), cpu);
+            }
+            if let Some(memory) = status.memory_mb {
+                metrics.custom.insert(format!("docker.{}.mem_mb", name), memory);
+            }
+            
+            // Containers seen for the first time only set the starting state
+            if let Some(&(restarts, was_running)) = self.previous.get(&name) {
+                if status.restarts > restarts {
+                    self.restarted.push(format!("{} restarts={}", name, status.restarts));
+                }
+                let exit_code = details["State"]["ExitCode"].as_i64().unwrap_or(0);
+                let oom_killed = details["State"]["OOMKilled"].as_bool().unwrap_or(false);
+                // 143 and 137 are SIGTERM and SIGKILL, which `docker stop` sends; only
+                // the kernel's OOM killer makes those unexpected
+                let stopped = matches!(exit_code, 0 | 137 | 143);
+                if was_running && !running && (!stopped || oom_killed) {
+                    let reason = if oom_killed {
+                        "out of memory".to_string()
+                    } else {
+                        format!("exit code {}", exit_code)
+                    };
+                    self.exited.insert(name.clone(), reason);
+                }
+            }
+            if running {
+                self.exited.remove(&name);
+            }
+            previous.insert(name, (status.restarts, running));
+            containers.push(status);
+        }
+        
+        self.exited.retain(|name, _| previous.contains_key(name));
+        self.containers = containers;
+        self.cpu_times = cpu_times;
+        self.previous = previous;
+        Ok(())
+    }
+    
+    fn anomalies(&self) -> Vec<String> {
+        let mut anomalies: Vec<String> = self
+            .exited
+            .iter()
+            .map(|(name, reason)| format!("Container Exited: {} {}", name, reason))
+            .collect();
+        anomalies.extend(self.restarted.iter().map(|restart| format!("Container Restarted: {}", restart)));
+        anomalies
+    }
+    
+    fn details(&self) -> Option<Value> {
+        (!self.containers.is_empty()).then(|| serde_json::json!({ "containers": self.containers }))
+    }
+}
+
+/// The JSON answer to a GET of the Engine API. HTTP/1.0 keeps the engine from
+/// chunking the body, which is then read until the connection closes.
+async fn get(socket: &str, path: &str) -> Result<Value> {
+    let response = timeout(Duration::from_secs(TIMEOUT_SECS), async {
+        let mut stream = UnixStream::connect(socket).await?;
+        stream
+            .write_all(format!("GET {} HTTP/1.0\r\nHost: docker\r\n\r\n", path).as_bytes())
+            .await?;
+        let mut response = Vec::new();
+        stream.take(MAX_RESPONSE_BYTES).read_to_end(&mut response).await?;
+        Ok::<_, anyhow::Error>(response)
+    })
+    .await
+    .map_err(|_| anyhow!("no answer from {} within {}s", socket, TIMEOUT_SECS))??;
+    
+    let text = String::from_utf8_lossy(&response);
+    let (head, body) = text.split_once("\r\n\r\n").ok_or_else(|| anyhow!("malformed response to {}", path))?;
+    let status = head.lines().next().unwrap_or_default();
+    if status.split_whitespace().nth(1) != Some("200") {
+        return Err(anyhow!("{}: {}", path, status));
+    }
+    Ok(serde_json::from_str(body)?)
+}
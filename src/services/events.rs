@@ -0,0 +1,86 @@
+use crate::models::config::EventLogConfig;
+use crate::models::events::Event;
+use anyhow::Result;
+use std::path::PathBuf;
+use tokio::fs::{self, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+pub const EVENT_LOG_FILE: &str = "logs/events.jsonl";
+
+/// Append-only JSONL log of anomalies, actions and notifications.
+///
+/// Rotated files are named `events.1.jsonl`, `events.2.jsonl`, ... so they keep
+/// an extension the downloads page will serve.
+pub struct EventLog {
+    path: PathBuf,
+    max_bytes: u64,
+    backup_count: u32,
+    write_lock: Mutex<()>,
+}
+
+impl EventLog {
+    pub fn new(config: &EventLogConfig) -> Self {
+        Self {
+            path: PathBuf::from(EVENT_LOG_FILE),
+            max_bytes: config.max_bytes(),
+            backup_count: config.backup_count,
+            write_lock: Mutex::new(()),
+        }
+    }
+    
+    /// Records an event. Failures are logged rather than returned so that event
+    /// logging can never interrupt monitoring.
+    pub async fn record(&self, event: Event) {
+        if let Err(e) = self.append(&event).await {
+            warn!("Failed to write event log: {}", e);
+        }
+    }
+    
+    async fn append(&self, event: &Event) -> Result<()> {
+        let _guard = self.write_lock.lock().await;
+        
+        let mut line = serde_json::to_string(event)?;
+        line.push('\n');
+        
+        if let Ok(metadata) = fs::metadata(&self.path).await {
+            if metadata.len() + line.len() as u64 > self.max_bytes {
+                self.rotate().await?;
+            }
+        }
+        
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        
+        Ok(())
+    }
+    
+    async fn rotate(&self) -> Result<()> {
+        if self.backup_count == 0 {
+            fs::remove_file(&self.path).await?;
+            return Ok(());
+        }
+        
+        let _ = fs::remove_file(self.backup_path(self.backup_count)).await;
+        for index in (1..self.backup_count).rev() {
+            let _ = fs::rename(self.backup_path(index), self.backup_path(index + 1)).await;
+        }
+        fs::rename(&self.path, self.backup_path(1)).await?;
+        
+        Ok(())
+    }
+    
+    fn backup_path(&self, index: u32) -> PathBuf {
+        let stem = self.path.file_stem().and_then(|s| s.to_str()).unwrap_or("events");
+        self.path.with_file_name(format!("{}.{}.jsonl", stem, index))
+    }
+}
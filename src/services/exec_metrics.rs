@@ -0,0 +1,155 @@
+use crate::models::config::ExecMetricConfig;
+use crate::models::metrics::SystemMetrics;
+use crate::services::collectors::Collector;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Runs a configured command and reports the number(s) it prints.
+pub struct ExecCollector {
+    config: ExecMetricConfig,
+    last_run: Option<DateTime<Utc>>,
+    /// Outcome of the latest run, repeated until the next one.
+    latest: Result<Vec<(String, f64)>, String>,
+}
+
+impl ExecCollector {
+    pub fn new(config: ExecMetricConfig) -> Self {
+        Self {
+            config,
+            last_run: None,
+            latest: Ok(Vec::new()),
+        }
+    }
+    
+    pub fn config(&self) -> &ExecMetricConfig {
+        &self.config
+    }
+    
+    async fn run(&self) -> Result<Vec<(String, f64)>> {
+        let (program, args) = self
+            .config
+            .command
+            .split_first()
+            .ok_or_else(|| anyhow!("no command"))?;
+        let output = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .output();
+        let output = tokio::time::timeout(Duration::from_secs(self.config.timeout), output)
+            .await
+            .map_err(|_| anyhow!("timed out after {}s", self.config.timeout))??;
+        
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!(
+                "{}: {}",
+                output.status,
+                stderr.lines().last().unwrap_or_default().trim()
+            ));
+        }
+        
+        parse_output(&self.config, &String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+#[async_trait]
+impl Collector for ExecCollector {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+    
+    async fn collect(&mut self, metrics: &mut SystemMetrics) -> Result<()> {
+        let now = Utc::now();
+        let due = self
+            .last_run
+            .map_or(true, |last| (now - last).num_seconds() >= self.config.interval as i64);
+        if due {
+            self.last_run = Some(now);
+            self.latest = self.run().await.map_err(|e| e.to_string());
+        }
+        
+        match &self.latest {
+            Ok(values) => {
+                metrics.custom.extend(values.iter().cloned());
+                Ok(())
+            }
+            Err(e) => Err(anyhow!("{}", e)),
+        }
+    }
+    
+    fn anomalies(&self) -> Vec<String> {
+        let Ok(values) = &self.latest else {
+            return Vec::new();
+        };
+        
+        values
+            .iter()
+            .filter_map(|(name, value)| {
+                if let Some(max) = self.config.max.filter(|max| value > max) {
+                    Some(format!("Anomaly: {} {:.1} (Normal: <{:.1})", name, value, max))
+                } else {
+                    self.config
+                        .min
+                        .filter(|min| value < min)
+                        .map(|min| format!("Anomaly: {} {:.1} (Normal: >{:.1})", name, value, min))
+                }
+            })
+            .collect()
+    }
+}
+
+/// Reads the value(s) from a command's standard output.
+fn parse_output(config: &ExecMetricConfig, stdout: &str) -> Result<Vec<(String, f64)>> {
+    let stdout = stdout.trim();
+    
+    if let Some(field) = &config.field {
+        let json: Value = serde_json::from_str(stdout).map_err(|e| anyhow!("output is not JSON: {}", e))?;
+        let value = field
+            .split('.')
+            .try_fold(&json, |value, key| match value {
+                Value::Array(items) => key.parse::<usize>().ok().and_then(|index| items.get(index)),
+                _ => value.get(key),
+            })
+            .and_then(number)
+            .ok_or_else(|| anyhow!("no number at {}", field))?;
+        return Ok(vec![(config.name.clone(), value)]);
+    }
+    
+    if let Ok(Value::Object(fields)) = serde_json::from_str::<Value>(stdout) {
+        let values: Vec<(String, f64)> = fields
+            .iter()
+            .filter_map(|(key, value)| Some((format!("{}.{}", config.name, key), number(value)?)))
+            .collect();
+        if values.is_empty() {
+            return Err(anyhow!("JSON output has no numeric fields"));
+        }
+        return Ok(values);
+    }
+    
+    // `wc -l file` and the like print the number first
+    stdout
+        .split_whitespace()
+        .next()
+        .and_then(|token| token.parse::<f64>().ok())
+        .filter(|value| value.is_finite())
+        .map(|value| vec![(config.name.clone(), value)])
+        .ok_or_else(|| anyhow!("output does not start with a number"))
+}
+
+/// A JSON number, or a string holding one.
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    }
+    .filter(|value: &f64| value.is_finite())
+}
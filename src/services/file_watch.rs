@@ -0,0 +1,392 @@
+use crate::models::config::FileWatchConfig;
+use crate::services::monitor::MonitorService;
+use crate::utils::data_dir;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{debug, warn};
+
+pub const FILE_HASHES_FILE: &str = "data/file_hashes.json";
+
+/// What a watched file looked like when last checked.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FileFingerprint {
+    /// SHA-256 of the contents; `None` when the file is not readable by the monitor.
+    sha256: Option<String>,
+    mode: u32,
+    uid: u32,
+    gid: u32,
+}
+
+/// Baseline persisted across restarts, so changes made while stopped are still reported.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Baseline {
+    /// Configured paths the baseline covers; a newly added path is learned silently.
+    roots: Vec<String>,
+    files: BTreeMap<String, FileFingerprint>,
+}
+
+/// Alerts on changes to high-value files such as sshd_config, sudoers and crontabs.
+pub struct FileWatchService {
+    config: FileWatchConfig,
+    baseline: Baseline,
+    monitor: Arc<RwLock<MonitorService>>,
+}
+
+impl FileWatchService {
+    pub fn new(config: &FileWatchConfig, monitor: Arc<RwLock<MonitorService>>) -> Self {
+        let baseline = std::fs::read_to_string(FILE_HASHES_FILE)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        
+        Self {
+            config: config.clone(),
+            baseline,
+            monitor,
+        }
+    }
+    
+    pub fn apply_config(&mut self, config: &FileWatchConfig) {
+        self.config = config.clone();
+    }
+    
+    pub fn sweep_interval(&self) -> u64 {
+        self.config.sweep_interval.max(60)
+    }
+    
+    /// Paths to watch in real time; none when disabled.
+    pub fn roots(&self) -> Vec<String> {
+        if self.config.enabled {
+            self.config.paths.clone()
+        } else {
+            Vec::new()
+        }
+    }
+    
+    /// Re-hashes every watched file, alerting on anything that changed since the last look.
+    pub async fn sweep(&mut self) {
+        if !self.config.enabled {
+            return;
+        }
+        
+        let mut alerts = Vec::new();
+        let roots = self.config.paths.clone();
+        for root in &roots {
+            let known = self.baseline.roots.contains(root);
+            let mut files: BTreeSet<PathBuf> = list_files(Path::new(root)).into_iter().collect();
+            files.extend(
+                self.baseline
+                    .files
+                    .keys()
+                    .map(PathBuf::from)
+                    .filter(|file| covers(root, file)),
+            );
+            
+            for file in files {
+                if let Some(alert) = self.check_file(&file) {
+                    if known {
+                        alerts.push(alert);
+                    }
+                }
+            }
+        }
+        
+        // Forget files no longer covered by any configured path
+        self.baseline
+            .files
+            .retain(|file, _| roots.iter().any(|root| covers(root, Path::new(file))));
+        self.baseline.roots = roots;
+        
+        self.finish(alerts).await;
+    }
+    
+    /// Checks files reported by the real-time watcher.
+    pub async fn rescan(&mut self, files: &[PathBuf]) {
+        let roots = self.baseline.roots.clone();
+        let alerts = files
+            .iter()
+            .filter(|file| roots.iter().any(|root| covers(root, file)))
+            .filter_map(|file| self.check_file(file))
+            .collect();
+        
+        self.finish(alerts).await;
+    }
+    
+    async fn finish(&mut self, alerts: Vec<String>) {
+        if let Err(e) = self.save() {
+            warn!("Failed to save file hashes: {}", e);
+        }
+        
+        if alerts.is_empty() {
+            return;
+        }
+        let mut monitor = self.monitor.write().await;
+        for alert in alerts {
+            warn!("{}", alert);
+            monitor.raise_transient(alert);
+        }
+    }
+    
+    /// Updates the baseline for one file and describes the change, if any.
+    fn check_file(&mut self, file: &Path) -> Option<String> {
+        let key = file.to_string_lossy().to_string();
+        let current = match fingerprint(file) {
+            Ok(current) => current,
+            Err(e) => {
+                debug!("Cannot check {}: {}", key, e);
+                return None;
+            }
+        };
+        
+        let change = match (self.baseline.files.get(&key), &current) {
+            (None, None) => return None,
+            (None, Some(_)) => "created",
+            (Some(_), None) => "deleted",
+            (Some(previous), Some(current)) if previous.sha256 != current.sha256 => "modified",
+            (Some(previous), Some(current)) if previous != current => "permissions",
+            _ => return None,
+        };
+        
+        match current {
+            Some(current) => self.baseline.files.insert(key.clone(), current),
+            None => self.baseline.files.remove(&key),
+        };
+        Some(format!("File Changed: {} {}", key, change))
+    }
+    
+    fn save(&self) -> Result<()> {
+        data_dir::ensure()?;
+        std::fs::write(FILE_HASHES_FILE, serde_json::to_string_pretty(&self.baseline)?)?;
+        Ok(())
+    }
+}
+
+/// Whether `file` is the configured path itself or directly inside it.
+pub fn covers(root: &str, file: &Path) -> bool {
+    let root = Path::new(root);
+    file == root || file.parent() == Some(root)
+}
+
+/// The file itself, or the regular files directly inside a directory.
+fn list_files(root: &Path) -> Vec<PathBuf> {
+    if !root.is_dir() {
+        return if root.exists() { vec![root.to_path_buf()] } else { Vec::new() };
+    }
+    
+    match std::fs::read_dir(root) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect(),
+        Err(e) => {
+            debug!("Cannot list {}: {}", root.display(), e);
+            Vec::new()
+        }
+    }
+}
+
+/// `Ok(None)` for a missing file; metadata errors such as an unreadable parent are errors.
+fn fingerprint(file: &Path) -> std::io::Result<Option<FileFingerprint>> {
+    let metadata = match std::fs::symlink_metadata(file) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    
+    let sha256 = std::fs::read(file).ok().map(|content| {
+        ring::digest::digest(&ring::digest::SHA256, &content)
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    });
+    
+    Ok(Some(FileFingerprint {
+        sha256,
+        mode: metadata.mode(),
+        uid: metadata.uid(),
+        gid: metadata.gid(),
+    }))
+}
+
+#[cfg(target_os = "linux")]
+pub use inotify::Inotify as ChangeWatcher;
+
+/// Without inotify only the periodic sweep runs.
+#[cfg(not(target_os = "linux"))]
+pub struct ChangeWatcher;
+
+#[cfg(not(target_os = "linux"))]
+impl ChangeWatcher {
+    pub fn new(_roots: &[String]) -> Result<Self> {
+        Err(anyhow::anyhow!("real-time file watching needs inotify (Linux)"))
+    }
+    
+    pub async fn changes(&self) -> Result<Vec<PathBuf>> {
+        std::future::pending().await
+    }
+}
+
+/// Real-time change notification through inotify. Each configured path is watched
+/// through its directory, so editors that save by renaming a new file into place are seen.
+#[cfg(target_os = "linux")]
+mod inotify {
+    use anyhow::{anyhow, Result};
+    use parking_lot::Mutex;
+    use std::collections::{BTreeSet, HashMap, HashSet};
+    use std::ffi::{CString, OsStr, OsString};
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+    use tokio::io::unix::AsyncFd;
+    use tokio::time::Duration;
+    use tracing::debug;
+    
+    const WATCH_MASK: u32 = libc::IN_CLOSE_WRITE
+        | libc::IN_MOVED_TO
+        | libc::IN_MOVED_FROM
+        | libc::IN_CREATE
+        | libc::IN_DELETE
+        | libc::IN_ATTRIB;
+    
+    // Editors save in several steps; let them finish before hashing
+    const SETTLE_MS: u64 = 300;
+    
+    struct WatchedDir {
+        path: PathBuf,
+        /// Names of interest in the directory; `None` means every entry.
+        names: Option<HashSet<OsString>>,
+    }
+    
+    pub struct Inotify {
+        fd: AsyncFd<OwnedFd>,
+        dirs: HashMap<i32, WatchedDir>,
+        /// Changes read but not yet returned; kept here so a call dropped while
+        /// settling (the caller selects over it) hands them to the next call.
+        pending: Mutex<BTreeSet<PathBuf>>,
+    }
+    
+    impl Inotify {
+        pub fn new(roots: &[String]) -> Result<Self> {
+            let raw = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+            if raw < 0 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+            let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+            
+            let mut dirs: HashMap<i32, WatchedDir> = HashMap::new();
+            for root in roots {
+                let path = Path::new(root);
+                let (dir, name) = if path.is_dir() {
+                    (path, None)
+                } else {
+                    match (path.parent(), path.file_name()) {
+                        (Some(dir), Some(name)) => (dir, Some(name.to_os_string())),
+                        _ => continue,
+                    }
+                };
+                
+                let c_dir = CString::new(dir.as_os_str().as_bytes())?;
+                let wd = unsafe { libc::inotify_add_watch(fd.as_raw_fd(), c_dir.as_ptr(), WATCH_MASK) };
+                if wd < 0 {
+                    debug!("Cannot watch {}: {}", dir.display(), std::io::Error::last_os_error());
+                    continue;
+                }
+                
+                // Several files in one directory share a watch descriptor
+                let watched = dirs.entry(wd).or_insert_with(|| WatchedDir {
+                    path: dir.to_path_buf(),
+                    names: Some(HashSet::new()),
+                });
+                match (name, &mut watched.names) {
+                    (Some(name), Some(names)) => {
+                        names.insert(name);
+                    }
+                    (None, names) => *names = None,
+                    _ => {}
+                }
+            }
+            
+            if dirs.is_empty() {
+                return Err(anyhow!("none of the paths can be watched"));
+            }
+            
+            Ok(Self {
+                fd: AsyncFd::new(fd)?,
+                dirs,
+                pending: Mutex::new(BTreeSet::new()),
+            })
+        }
+        
+        /// Waits for changes and returns the affected files. Cancel-safe: events
+        /// already read stay pending until a call runs to completion.
+        pub async fn changes(&self) -> Result<Vec<PathBuf>> {
+            let mut buffer = vec![0u8; 16 * 1024];
+            
+            while self.pending.lock().is_empty() {
+                let mut guard = self.fd.readable().await?;
+                if let Ok(read) = guard.try_io(|fd| read_events(fd.get_ref().as_raw_fd(), &mut buffer)) {
+                    self.parse(&buffer[..read?], &mut self.pending.lock());
+                }
+            }
+            
+            tokio::time::sleep(Duration::from_millis(SETTLE_MS)).await;
+            loop {
+                match read_events(self.fd.get_ref().as_raw_fd(), &mut buffer) {
+                    Ok(read) => self.parse(&buffer[..read], &mut self.pending.lock()),
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            
+            Ok(std::mem::take(&mut *self.pending.lock()).into_iter().collect())
+        }
+        
+        fn parse(&self, data: &[u8], changed: &mut BTreeSet<PathBuf>) {
+            let header = std::mem::size_of::<libc::inotify_event>();
+            let mut offset = 0;
+            
+            while offset + header <= data.len() {
+                let event = unsafe { std::ptr::read_unaligned(data[offset..].as_ptr() as *const libc::inotify_event) };
+                let name_end = (offset + header + event.len as usize).min(data.len());
+                let name = data[offset + header..name_end].split(|byte| *byte == 0).next().unwrap_or_default();
+                offset = name_end;
+                
+                // Events were dropped, so every watched file may have changed
+                if event.mask & libc::IN_Q_OVERFLOW != 0 {
+                    for dir in self.dirs.values() {
+                        match &dir.names {
+                            Some(names) => changed.extend(names.iter().map(|name| dir.path.join(name))),
+                            None => changed.extend(super::list_files(&dir.path)),
+                        }
+                    }
+                    continue;
+                }
+                
+                let Some(dir) = self.dirs.get(&event.wd) else {
+                    continue;
+                };
+                let name = OsStr::from_bytes(name);
+                if name.is_empty() || dir.names.as_ref().is_some_and(|names| !names.contains(name)) {
+                    continue;
+                }
+                changed.insert(dir.path.join(name));
+            }
+        }
+    }
+    
+    fn read_events(fd: RawFd, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let read = unsafe { libc::read(fd, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len()) };
+        if read < 0 {
+            Err(std::io::Error::last_os_error())
+        } else {
+            Ok(read as usize)
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use crate::models::config::Firewall;
+use crate::utils::cidr::IpNet;
+use crate::utils::data_dir;
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+pub const OWN_BLOCKS_FILE: &str = "data/firewall_blocks.json";
+
+// Blocked ranges are kept in sets matched by a single rule per family, so thousands
+// of them cost one lookup per packet rather than a rule each
+const IPSET_V4: &str = "shaydz_monitor";
+const IPSET_V6: &str = "shaydz_monitor6";
+// One inet table covers IPv4 and IPv6, and keeps the rules apart from the host's own
+const NFT_TABLE: &str = "shaydz_monitor";
+const NFT_CHAIN: &str = "input";
+const NFT_SET_V4: &str = "blocked4";
+const NFT_SET_V6: &str = "blocked6";
+
+/// The ranges the monitor blocked on its own account rather than for another source
+/// such as CrowdSec, kept in `data/firewall_blocks.json` so lifting another source's
+/// block never takes one of them down.
+pub struct OwnBlocks {
+    ranges: Mutex<HashSet<IpNet>>,
+}
+
+impl OwnBlocks {
+    pub fn new() -> Self {
+        let ranges: Vec<String> = std::fs::read_to_string(OWN_BLOCKS_FILE)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            ranges: Mutex::new(ranges.iter().filter_map(|range| range.parse().ok()).collect()),
+        }
+    }
+    
+    pub async fn list(&self) -> Vec<IpNet> {
+        self.ranges.lock().await.iter().copied().collect()
+    }
+    
+    pub async fn add(&self, range: IpNet) {
+        let mut ranges = self.ranges.lock().await;
+        if !ranges.insert(range) {
+            return;
+        }
+        let mut saved: Vec<String> = ranges.iter().map(IpNet::to_string).collect();
+        saved.sort();
+        let result = async {
+            data_dir::ensure_async().await?;
+            tokio::fs::write(OWN_BLOCKS_FILE, serde_json::to_string_pretty(&saved)?).await
+        };
+        if let Err(e) = result.await {
+            warn!("Failed to save the monitor's firewall blocks: {}", e);
+        }
+    }
+}
+
+/// Drops all traffic from the addresses in `ranges` with `firewall`, adding the sets
+/// and the rules matching them the first time. Returns the tool that was used.
+pub async fn block(ranges: &[IpNet], firewall: Firewall) -> Result<&'static str> {
+    for v4 in [true, false] {
+        let family: Vec<String> = ranges
+            .iter()
+            .filter(|range| range.addr().is_ipv4() == v4)
+            .map(IpNet::to_string)
+            .collect();
+        if family.is_empty() {
+            continue;
+        }
+        match firewall {
+            Firewall::Iptables => {
+                let (tool, set, family_name) = ipset(v4);
+                sudo(&["ipset", "create", set, "hash:net", "family", family_name, "-exist"]).await?;
+                let additions: String = family.iter().map(|range| format!("add {} {}\n", set, range)).collect();
+                sudo_with_input(&["ipset", "restore", "-exist"], &additions).await?;
+                
+                // -C fails when the rule is missing, so it is only inserted once
+                let rule = ["INPUT", "-m", "set", "--match-set", set, "src", "-j", "DROP"];
+                if sudo(&[&[tool, "-C"], &rule[..]].concat()).await.is_err() {
+                    sudo(&[&[tool, "-I"], &rule[..]].concat()).await?;
+                }
+            }
+            Firewall::Nftables => {
+                let (match_family, set, kind) = nft_set(v4);
+                // auto-merge lets a range be added over addresses or ranges already inside it
+                let script = format!(
+                    "add table inet {table}\n\
+                     add chain inet {table} {chain} {{ type filter hook input priority -10 ; policy accept ; }}\n\
+                     add set inet {table} {set} {{ type {kind} ; flags interval ; auto-merge ; }}\n\
+                     add element inet {table} {set} {{ {elements} }}\n",
+                    table = NFT_TABLE,
+                    chain = NFT_CHAIN,
+                    set = set,
+                    kind = kind,
+                    elements = family.join(", "),
+                );
+                sudo_with_input(&["nft", "-f", "-"], &script).await?;
+                
+                let rule = format!("{} saddr @{} drop", match_family, set);
+                let existing = sudo(&["nft", "list", "chain", "inet", NFT_TABLE, NFT_CHAIN]).await?;
+                if !existing.lines().any(|line| line.trim() == rule) {
+                    let set_ref = format!("@{}", set);
+                    sudo(&["nft", "add", "rule", "inet", NFT_TABLE, NFT_CHAIN, match_family, "saddr", &set_ref, "drop"]).await?;
+                }
+            }
+        }
+    }
+    Ok(match firewall {
+        Firewall::Iptables => "ipset",
+        Firewall::Nftables => "nft",
+    })
+}
+
+/// Takes `ranges` out of the blocked sets again. Ranges that were not blocked are
+/// skipped.
+pub async fn unblock(ranges: &[IpNet], firewall: Firewall) -> Result<()> {
+    match firewall {
+        Firewall::Iptables => {
+            // The sets are gone after a reboot; creating them lets the removals succeed
+            let mut removals: String = [true, false]
+                .iter()
+                .map(|v4| {
+                    let (_, set, family) = ipset(*v4);
+                    format!("create {} hash:net family {}\n", set, family)
+                })
+                .collect();
+            for range in ranges {
+                removals.push_str(&format!("del {} {}\n", ipset(range.addr().is_ipv4()).1, range));
+            }
+            sudo_with_input(&["ipset", "restore", "-exist"], &removals).await?;
+        }
+        Firewall::Nftables => {
+            let script: String = ranges
+                .iter()
+                .map(|range| format!("delete element inet {} {} {{ {} }}\n", NFT_TABLE, nft_set(range.addr().is_ipv4()).1, range))
+                .collect();
+            if sudo_with_input(&["nft", "-f", "-"], &script).await.is_ok() {
+                return Ok(());
+            }
+            
+            // nft rejects a whole batch over a single missing element, so only then
+            // are they removed one at a time; ranges already gone count as removed
+            let mut failed = 0;
+            for range in ranges {
+                let set = nft_set(range.addr().is_ipv4()).1;
+                let element = range.to_string();
+                if let Err(e) = sudo(&["nft", "delete", "element", "inet", NFT_TABLE, set, "{", &element, "}"]).await {
+                    if !e.to_string().contains("No such file or directory") {
+                        failed += 1;
+                    }
+                }
+            }
+            if failed > 0 {
+                return Err(anyhow!("nft could not remove {} of {} ranges", failed, ranges.len()));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The iptables tool, set name and ipset family for IPv4 or IPv6.
+fn ipset(v4: bool) -> (&'static str, &'static str, &'static str) {
+    if v4 {
+        ("iptables", IPSET_V4, "inet")
+    } else {
+        ("ip6tables", IPSET_V6, "inet6")
+    }
+}
+
+/// The match family, set name and element type for IPv4 or IPv6.
+fn nft_set(v4: bool) -> (&'static str, &'static str, &'static str) {
+    if v4 {
+        ("ip", NFT_SET_V4, "ipv4_addr")
+    } else {
+        ("ip6", NFT_SET_V6, "ipv6_addr")
+    }
+}
+
+async fn sudo(args: &[&str]) -> Result<String> {
+    let output = Command::new("sudo").arg("-n").args(args).output().await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Runs a batch read from standard input, which keeps thousands of ranges out of the
+/// argument list.
+async fn sudo_with_input(args: &[&str], input: &str) -> Result<()> {
+    let mut child = Command::new("sudo")
+        .arg("-n")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes()).await?;
+    }
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
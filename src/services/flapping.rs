@@ -0,0 +1,106 @@
+use crate::models::config::FlappingConfig;
+use crate::services::monitor::anomaly_subject;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+const FLAPPING_PREFIX: &str = "Flapping: ";
+
+/// An anomaly that started or stopped flapping in a cycle.
+pub enum FlapChange {
+    /// The subject, and the state changes counted within the window.
+    Started(String, usize),
+    Stopped(String),
+}
+
+/// Watches anomalies for ones that keep raising and clearing, and reports each of
+/// them as a single `Flapping` anomaly until it settles, so that incidents and
+/// notifications are not opened and closed on every change.
+pub struct FlapDetector {
+    config: FlappingConfig,
+    /// Subjects raised in the last cycle, before flapping ones were collapsed.
+    last: HashSet<String>,
+    /// When each subject was raised or cleared within the window.
+    changes: HashMap<String, VecDeque<DateTime<Utc>>>,
+    /// Subjects collapsed into a flapping alert.
+    flapping: BTreeSet<String>,
+}
+
+impl FlapDetector {
+    pub fn new(config: &FlappingConfig) -> Self {
+        Self {
+            config: config.clone(),
+            last: HashSet::new(),
+            changes: HashMap::new(),
+            flapping: BTreeSet::new(),
+        }
+    }
+    
+    pub fn update_config(&mut self, config: &FlappingConfig) {
+        self.config = config.clone();
+        if !self.config.enabled {
+            self.changes.clear();
+            self.flapping.clear();
+        }
+    }
+    
+    /// Records this cycle's anomalies and returns them with each flapping subject
+    /// replaced by one `Flapping: <subject>` anomaly, which stays raised whatever the
+    /// subject's state until it has gone `stable` seconds without changing.
+    pub fn collapse(&mut self, anomalies: &[String]) -> (Vec<String>, Vec<FlapChange>) {
+        if !self.config.enabled {
+            return (anomalies.to_vec(), Vec::new());
+        }
+        
+        let now = Utc::now();
+        let current: HashSet<String> = anomalies.iter().map(|anomaly| anomaly_subject(anomaly)).collect();
+        for subject in current.symmetric_difference(&self.last) {
+            self.changes.entry(subject.clone()).or_default().push_back(now);
+        }
+        self.last = current;
+        
+        let window = self.config.window as i64;
+        self.changes.retain(|_, changes| {
+            while changes.front().is_some_and(|at| (now - *at).num_seconds() >= window) {
+                changes.pop_front();
+            }
+            !changes.is_empty()
+        });
+        
+        let mut flap_changes = Vec::new();
+        let stable = self.config.stable as i64;
+        let settled: Vec<String> = self
+            .flapping
+            .iter()
+            .filter(|subject| {
+                let last_change = self.changes.get(*subject).and_then(|changes| changes.back());
+                last_change.map_or(true, |at| (now - *at).num_seconds() >= stable)
+            })
+            .cloned()
+            .collect();
+        // Changes from before it settled do not count towards flapping again
+        for subject in settled {
+            self.flapping.remove(&subject);
+            self.changes.remove(&subject);
+            flap_changes.push(FlapChange::Stopped(subject));
+        }
+        for (subject, changes) in &self.changes {
+            if changes.len() >= self.config.transitions && !self.flapping.contains(subject) {
+                self.flapping.insert(subject.clone());
+                flap_changes.push(FlapChange::Started(subject.clone(), changes.len()));
+            }
+        }
+        
+        let mut collapsed: Vec<String> = anomalies
+            .iter()
+            .filter(|anomaly| !self.flapping.contains(&anomaly_subject(anomaly)))
+            .cloned()
+            .collect();
+        collapsed.extend(self.flapping.iter().map(|subject| format!("{}{}", FLAPPING_PREFIX, subject)));
+        (collapsed, flap_changes)
+    }
+    
+    /// Whether `anomaly`'s subject is collapsed into a flapping alert.
+    pub fn is_flapping(&self, anomaly: &str) -> bool {
+        self.flapping.contains(&anomaly_subject(anomaly))
+    }
+}
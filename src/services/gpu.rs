@@ -0,0 +1,152 @@
+use crate::models::metrics::{GpuReading, SystemMetrics};
+use crate::services::collectors::Collector;
+use crate::utils::process;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::process::Command;
+
+const MB: f64 = 1024.0 * 1024.0;
+// Both tools wake the driver, which can take a moment on an idle card
+const SMI_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GpuTool {
+    Nvidia,
+    Rocm,
+}
+
+impl GpuTool {
+    fn program(&self) -> &'static str {
+        match self {
+            GpuTool::Nvidia => "nvidia-smi",
+            GpuTool::Rocm => "rocm-smi",
+        }
+    }
+}
+
+/// Utilization, VRAM and temperature of NVIDIA and AMD graphics cards, read with
+/// `nvidia-smi` and `rocm-smi`. Hosts with neither tool installed report nothing.
+pub struct GpuCollector {
+    /// The tools found on the `PATH`, looked for on the first run.
+    tools: Option<Vec<GpuTool>>,
+}
+
+impl GpuCollector {
+    pub fn new() -> Self {
+        Self { tools: None }
+    }
+}
+
+#[async_trait]
+impl Collector for GpuCollector {
+    fn name(&self) -> &str {
+        "gpu"
+    }
+    
+    async fn collect(&mut self, metrics: &mut SystemMetrics) -> Result<()> {
+        let tools = self.tools.get_or_insert_with(|| {
+            [GpuTool::Nvidia, GpuTool::Rocm]
+                .into_iter()
+                .filter(|tool| on_path(tool.program()))
+                .collect()
+        });
+        
+        for tool in tools.iter() {
+            let readings = match tool {
+                GpuTool::Nvidia => nvidia_readings().await?,
+                GpuTool::Rocm => rocm_readings().await?,
+            };
+            metrics.gpus.extend(readings);
+        }
+        Ok(())
+    }
+}
+
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+async fn nvidia_readings() -> Result<BTreeMap<String, GpuReading>> {
+    let output = process::output_within(
+        Command::new("nvidia-smi").args([
+            "--query-gpu=index,utilization.gpu,memory.used,memory.total,temperature.gpu",
+            "--format=csv,noheader,nounits",
+        ]),
+        SMI_TIMEOUT,
+    )
+    .await?;
+    if !output.status.success() {
+        return Err(anyhow!("nvidia-smi: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(parse_nvidia(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// The cards in `nvidia-smi` CSV output, as `nvidia0`, `nvidia1` and so on. Readings
+/// a card does not support (`[N/A]`) are left at 0.
+fn parse_nvidia(output: &str) -> BTreeMap<String, GpuReading> {
+    let mut gpus = BTreeMap::new();
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let [index, utilization, used, total, temperature] = fields[..] else {
+            continue;
+        };
+        let value = |field: &str| field.parse::<f64>().unwrap_or(0.0);
+        gpus.insert(
+            format!("nvidia{}", index),
+            GpuReading {
+                utilization_percent: value(utilization),
+                memory_used_mb: value(used),
+                memory_total_mb: value(total),
+                temperature: value(temperature),
+            },
+        );
+    }
+    gpus
+}
+
+async fn rocm_readings() -> Result<BTreeMap<String, GpuReading>> {
+    let output = process::output_within(
+        Command::new("rocm-smi").args(["--showuse", "--showtemp", "--showmeminfo", "vram", "--json"]),
+        SMI_TIMEOUT,
+    )
+    .await?;
+    if !output.status.success() {
+        return Err(anyhow!("rocm-smi: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    parse_rocm(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// The cards in `rocm-smi --json` output, `card0` becoming `amd0`. Its keys differ
+/// between ROCm releases, so readings are found by the start of their names.
+fn parse_rocm(output: &str) -> Result<BTreeMap<String, GpuReading>> {
+    let cards: BTreeMap<String, Value> = serde_json::from_str(output)?;
+    let mut gpus = BTreeMap::new();
+    
+    for (card, readings) in cards {
+        let (Some(index), Some(readings)) = (card.strip_prefix("card"), readings.as_object()) else {
+            continue;
+        };
+        // Values are given as strings, e.g. "45.0"
+        let value = |prefix: &str| {
+            readings
+                .iter()
+                .find(|(key, _)| key.starts_with(prefix))
+                .and_then(|(_, value)| value.as_str()?.trim().parse::<f64>().ok())
+                .unwrap_or(0.0)
+        };
+        let edge = value("Temperature (Sensor edge)");
+        gpus.insert(
+            format!("amd{}", index),
+            GpuReading {
+                utilization_percent: value("GPU use (%)"),
+                memory_used_mb: value("VRAM Total Used Memory (B)") / MB,
+                memory_total_mb: value("VRAM Total Memory (B)") / MB,
+                temperature: if edge > 0.0 { edge } else { value("Temperature") },
+            },
+        );
+    }
+    Ok(gpus)
+}
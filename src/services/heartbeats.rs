@@ -0,0 +1,129 @@
+use crate::models::config::HeartbeatConfig;
+use crate::utils::data_dir;
+use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::fs;
+use tracing::warn;
+
+pub const HEARTBEATS_FILE: &str = "data/heartbeats.json";
+
+/// What the monitor knows about one job, persisted across restarts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeartbeatState {
+    /// When the job was first configured; a job that never reports is late from here.
+    pub registered_at: DateTime<Utc>,
+    pub last_ping: Option<DateTime<Utc>>,
+    /// Set when the job reported failure and cleared by its next successful ping.
+    pub failed_at: Option<DateTime<Utc>>,
+    pub pings: u64,
+}
+
+impl HeartbeatState {
+    fn new() -> Self {
+        Self {
+            registered_at: Utc::now(),
+            last_ping: None,
+            failed_at: None,
+            pings: 0,
+        }
+    }
+}
+
+/// Check-ins from cron jobs and scripts, shared between the monitor and the web server.
+pub struct HeartbeatLog {
+    jobs: Mutex<HashMap<String, HeartbeatState>>,
+    write_lock: tokio::sync::Mutex<()>,
+}
+
+impl HeartbeatLog {
+    pub fn new() -> Self {
+        let content = std::fs::read_to_string(HEARTBEATS_FILE).unwrap_or_default();
+        
+        Self {
+            jobs: Mutex::new(serde_json::from_str(&content).unwrap_or_default()),
+            write_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+    
+    /// Records a check-in of `job`; `failed` when the job reported that it failed.
+    pub async fn record(&self, job: &str, failed: bool) {
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            let state = jobs.entry(job.to_string()).or_insert_with(HeartbeatState::new);
+            let now = Utc::now();
+            state.last_ping = Some(now);
+            state.failed_at = failed.then_some(now);
+            state.pings += 1;
+        }
+        
+        if let Err(e) = self.save().await {
+            warn!("Failed to save heartbeats: {}", e);
+        }
+    }
+    
+    /// Starts the clock for newly configured jobs and forgets removed ones.
+    pub async fn register(&self, heartbeats: &[HeartbeatConfig]) {
+        let changed = {
+            let mut jobs = self.jobs.lock().unwrap();
+            let before = jobs.len();
+            jobs.retain(|name, _| heartbeats.iter().any(|heartbeat| &heartbeat.name == name));
+            let mut changed = jobs.len() != before;
+            for heartbeat in heartbeats {
+                if !jobs.contains_key(&heartbeat.name) {
+                    jobs.insert(heartbeat.name.clone(), HeartbeatState::new());
+                    changed = true;
+                }
+            }
+            changed
+        };
+        
+        if changed {
+            if let Err(e) = self.save().await {
+                warn!("Failed to save heartbeats: {}", e);
+            }
+        }
+    }
+    
+    /// Jobs that reported failure or are overdue.
+    pub fn anomalies(&self, heartbeats: &[HeartbeatConfig]) -> Vec<String> {
+        let jobs = self.jobs.lock().unwrap();
+        let now = Utc::now();
+        let mut anomalies = Vec::new();
+        
+        for heartbeat in heartbeats {
+            let Some(state) = jobs.get(&heartbeat.name) else {
+                continue;
+            };
+            if state.failed_at.is_some() {
+                anomalies.push(format!("Heartbeat Failed: {} reported failure", heartbeat.name));
+                continue;
+            }
+            
+            let since = state.last_ping.unwrap_or(state.registered_at);
+            if (now - since).num_seconds() > (heartbeat.period + heartbeat.grace) as i64 {
+                let last = match state.last_ping {
+                    Some(last) => last.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string(),
+                    None => "never".to_string(),
+                };
+                anomalies.push(format!("Heartbeat Missed: {} last {}", heartbeat.name, last));
+            }
+        }
+        
+        anomalies
+    }
+    
+    pub fn jobs(&self) -> HashMap<String, HeartbeatState> {
+        self.jobs.lock().unwrap().clone()
+    }
+    
+    async fn save(&self) -> Result<()> {
+        let _guard = self.write_lock.lock().await;
+        let content = serde_json::to_string_pretty(&self.jobs())?;
+        data_dir::ensure_async().await?;
+        fs::write(HEARTBEATS_FILE, content).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,381 @@
+use crate::models::events::{Event, EventKind};
+use crate::models::incidents::{Incident, IncidentAnomaly, TimelineEntry, TimelineKind};
+use crate::services::monitor::{anomaly_severity, anomaly_subject};
+use crate::utils::data_dir;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeSet, HashSet};
+use std::sync::Mutex;
+use tokio::fs;
+use tracing::warn;
+
+pub const INCIDENTS_FILE: &str = "data/incidents.json";
+// Resolved incidents kept for the record; open ones are always kept
+const MAX_RESOLVED: usize = 200;
+// An open incident takes in anomalies raised this long after it opened; later ones
+// start an incident of their own, so one lingering warning cannot absorb a day of alerts
+const GROUPING_WINDOW_SECS: i64 = 1800;
+
+/// Anomalies about the host's own resources, which are grouped into one incident.
+const HOST_ANOMALIES: &[&str] = &[
+    "Anomaly: CPU",
+    "Anomaly: RAM",
+    "Anomaly: Temp",
+    "Anomaly: Disk",
+    "Anomaly: disk ",
+    "Anomaly: Connections",
+    "Anomaly: Net RX",
+    "Anomaly: Net TX",
+    "Anomaly: Metric Combination",
+    "OOM Kill",
+];
+
+/// Combinations of host anomalies with a likely common cause, most specific first.
+const HOST_HINTS: &[(&[&str], &str)] = &[
+    (&["OOM Kill", "Anomaly: RAM"], "Memory ran out and the kernel killed a process"),
+    (&["Anomaly: CPU", "Anomaly: Temp"], "Sustained CPU load is heating the host"),
+    (&["Anomaly: RAM", "Anomaly: disk "], "Memory pressure is pushing the host into swap"),
+    (&["Anomaly: Net RX", "Anomaly: Connections"], "A surge of inbound traffic, possibly a flood"),
+    (&["Anomaly: CPU", "Anomaly: Connections"], "A surge of connections is driving CPU load"),
+];
+
+/// Anomalies about a whole monitored instance, named right after the prefix.
+const INSTANCE_ANOMALIES: &[&str] = &["Database Down: ", "Database Replication Stopped: ", "Cache Down: "];
+
+pub fn is_host_anomaly(anomaly: &str) -> bool {
+    HOST_ANOMALIES.iter().any(|prefix| anomaly.starts_with(prefix))
+}
+
+/// The database, cache or other instance an anomaly is about, for anomalies of the
+/// form "Database Down: db1 ..." or "Anomaly: db1.latency_ms ...".
+pub fn instance_of(anomaly: &str) -> Option<&str> {
+    let rest = INSTANCE_ANOMALIES.iter().find_map(|prefix| anomaly.strip_prefix(prefix));
+    match rest {
+        Some(rest) => rest.split(' ').next(),
+        None => anomaly.strip_prefix("Anomaly: ")?.split(' ').next()?.split_once('.').map(|(instance, _)| instance),
+    }
+}
+
+/// Where one raised anomaly was filed.
+pub struct Filed {
+    pub incident: u64,
+    /// Whether the anomaly opened the incident.
+    pub opened: bool,
+    /// Whether the anomaly raised the severity of an incident already open.
+    pub escalated: bool,
+}
+
+/// What an update did, for the event log and notifications.
+pub struct IncidentUpdate {
+    /// Where each raised anomaly was filed, in the order given.
+    pub filed: Vec<Filed>,
+    /// Incidents resolved by the update.
+    pub resolved: Vec<Incident>,
+}
+
+/// Groups anomalies raised together for one cause into incidents, so an outage
+/// alerts once instead of once per symptom.
+pub struct IncidentLog {
+    incidents: Mutex<Vec<Incident>>,
+    write_lock: tokio::sync::Mutex<()>,
+}
+
+impl IncidentLog {
+    pub fn new() -> Self {
+        let content = std::fs::read_to_string(INCIDENTS_FILE).unwrap_or_default();
+        
+        Self {
+            incidents: Mutex::new(serde_json::from_str(&content).unwrap_or_default()),
+            write_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+    
+    /// Files newly raised anomalies, each given with its correlation key, under the
+    /// open incident with that key opened within the grouping window, opening one
+    /// where there is none. Anomalies whose
+    /// subject is not in `active` are cleared, and incidents left without active
+    /// anomalies are resolved.
+    pub async fn update(&self, raised: &[(String, String)], active: &HashSet<String>) -> IncidentUpdate {
+        let now = Utc::now();
+        let mut filed = Vec::new();
+        let mut resolved = Vec::new();
+        
+        let changed = {
+            let mut incidents = self.incidents.lock().unwrap();
+            let mut changed = false;
+            
+            // Incidents resolved by hand still record when their anomalies cleared
+            for incident in incidents.iter_mut() {
+                for anomaly in &mut incident.anomalies {
+                    let subject = anomaly_subject(&anomaly.message);
+                    if anomaly.cleared_at.is_none() && !active.contains(&subject) {
+                        anomaly.cleared_at = Some(now);
+                        incident.timeline.push(entry(now, TimelineKind::Anomaly, format!("Cleared: {}", subject), None));
+                        changed = true;
+                    }
+                }
+                if incident.is_open() && incident.anomalies.iter().all(|anomaly| anomaly.cleared_at.is_some()) {
+                    incident.resolved_at = Some(now);
+                    incident
+                        .timeline
+                        .push(entry(now, TimelineKind::Status, "Resolved: all anomalies cleared".to_string(), None));
+                    resolved.push(incident.clone());
+                }
+            }
+            
+            for (message, key) in raised {
+                let anomaly = IncidentAnomaly {
+                    message: message.clone(),
+                    raised_at: now,
+                    cleared_at: None,
+                };
+                let severity = anomaly_severity(message);
+                let raised_entry = entry(now, TimelineKind::Anomaly, message.clone(), None);
+                
+                let grouping = incidents.iter_mut().rev().find(|incident| {
+                    incident.is_open()
+                        && &incident.key == key
+                        && (now - incident.opened_at).num_seconds() < GROUPING_WINDOW_SECS
+                });
+                match grouping {
+                    Some(incident) => {
+                        incident.anomalies.push(anomaly);
+                        incident.timeline.push(raised_entry);
+                        let escalated = severity > incident.severity;
+                        if escalated {
+                            incident.severity = severity;
+                            incident.timeline.push(entry(
+                                now,
+                                TimelineKind::Status,
+                                format!("Escalated to {:?}", severity),
+                                None,
+                            ));
+                        }
+                        incident.hint = root_cause_hint(incident);
+                        filed.push(Filed {
+                            incident: incident.id,
+                            opened: false,
+                            escalated,
+                        });
+                    }
+                    None => {
+                        let id = incidents.iter().map(|incident| incident.id).max().unwrap_or(0) + 1;
+                        let mut incident = Incident {
+                            id,
+                            key: key.clone(),
+                            title: message.clone(),
+                            severity,
+                            hint: None,
+                            opened_at: now,
+                            acknowledged_at: None,
+                            acknowledged_by: None,
+                            assignee: None,
+                            resolved_at: None,
+                            resolved_by: None,
+                            anomalies: vec![anomaly],
+                            timeline: vec![raised_entry],
+                        };
+                        incident.hint = root_cause_hint(&incident);
+                        incidents.push(incident);
+                        filed.push(Filed {
+                            incident: id,
+                            opened: true,
+                            escalated: false,
+                        });
+                    }
+                }
+                changed = true;
+            }
+            
+            let closed = incidents.iter().filter(|incident| !incident.is_open()).count();
+            if closed > MAX_RESOLVED {
+                let mut excess = closed - MAX_RESOLVED;
+                incidents.retain(|incident| {
+                    let drop = excess > 0 && !incident.is_open();
+                    if drop {
+                        excess -= 1;
+                    }
+                    !drop
+                });
+            }
+            
+            changed
+        };
+        
+        if changed {
+            if let Err(e) = self.save().await {
+                warn!("Failed to save incidents: {}", e);
+            }
+        }
+        
+        IncidentUpdate { filed, resolved }
+    }
+    
+    /// Adds an action, notification or diagnostic taken for `anomaly` to the timeline
+    /// of the incident it was filed under, while that anomaly is still active. Actions
+    /// repeated every cycle, such as wall broadcasts, are listed once. Diagnostics keep
+    /// what they captured on the entry.
+    pub async fn log_event(&self, anomaly: &str, event: &Event) {
+        let subject = anomaly_subject(anomaly);
+        let kind = TimelineKind::from(event.kind);
+        let added = {
+            let mut incidents = self.incidents.lock().unwrap();
+            let incident = incidents.iter_mut().rev().find(|incident| {
+                incident.anomalies.iter().any(|anomaly| {
+                    anomaly.cleared_at.is_none() && anomaly_subject(&anomaly.message) == subject
+                })
+            });
+            match incident {
+                Some(incident) if !incident.timeline.iter().any(|e| e.kind == kind && e.message == event.message) => {
+                    let mut entry = entry(event.timestamp, kind, event.message.clone(), None);
+                    if event.kind == EventKind::Diagnostic && !event.details.is_null() {
+                        entry.details = Some(event.details.clone());
+                    }
+                    incident.timeline.push(entry);
+                    true
+                }
+                _ => false,
+            }
+        };
+        
+        if added {
+            if let Err(e) = self.save().await {
+                warn!("Failed to save incidents: {}", e);
+            }
+        }
+    }
+    
+    /// Adds an action or notification to the timeline of incident `id`, open or not.
+    pub async fn log_event_for(&self, id: u64, event: &Event) {
+        let kind = TimelineKind::from(event.kind);
+        if let Err(e) = self.change(id, |_, _| Ok(entry(event.timestamp, kind, event.message.clone(), None))).await {
+            warn!("Failed to add to incident #{}: {}", id, e);
+        }
+    }
+    
+    /// Marks an open incident as being handled by `user`.
+    pub async fn acknowledge(&self, id: u64, user: &str) -> Result<Incident> {
+        self.change(id, |incident, now| {
+            if !incident.is_open() {
+                return Err(anyhow!("Incident #{} is already resolved", id));
+            }
+            if incident.acknowledged_at.is_none() {
+                incident.acknowledged_at = Some(now);
+                incident.acknowledged_by = Some(user.to_string());
+            }
+            Ok(entry(now, TimelineKind::Status, "Acknowledged".to_string(), Some(user)))
+        })
+        .await
+    }
+    
+    /// Hands the incident to `assignee`, or takes it back from whoever had it.
+    pub async fn assign(&self, id: u64, assignee: Option<String>, user: &str) -> Result<Incident> {
+        self.change(id, |incident, now| {
+            let message = match &assignee {
+                Some(assignee) => format!("Assigned to {}", assignee),
+                None => "Unassigned".to_string(),
+            };
+            incident.assignee = assignee;
+            Ok(entry(now, TimelineKind::Status, message, Some(user)))
+        })
+        .await
+    }
+    
+    pub async fn add_note(&self, id: u64, text: &str, user: &str) -> Result<Incident> {
+        self.change(id, |_, now| Ok(entry(now, TimelineKind::Note, text.to_string(), Some(user))))
+            .await
+    }
+    
+    /// Closes the incident by hand, even with anomalies still active. Those anomalies
+    /// open a new incident only once they clear and are raised again.
+    pub async fn resolve(&self, id: u64, user: &str) -> Result<Incident> {
+        self.change(id, |incident, now| {
+            if !incident.is_open() {
+                return Err(anyhow!("Incident #{} is already resolved", id));
+            }
+            incident.resolved_at = Some(now);
+            incident.resolved_by = Some(user.to_string());
+            Ok(entry(now, TimelineKind::Status, "Resolved".to_string(), Some(user)))
+        })
+        .await
+    }
+    
+    /// Applies `change` to one incident, adds the entry it returns to the timeline
+    /// and saves.
+    async fn change(
+        &self,
+        id: u64,
+        change: impl FnOnce(&mut Incident, DateTime<Utc>) -> Result<TimelineEntry>,
+    ) -> Result<Incident> {
+        let incident = {
+            let mut incidents = self.incidents.lock().unwrap();
+            let incident = incidents
+                .iter_mut()
+                .find(|incident| incident.id == id)
+                .ok_or_else(|| anyhow!("Incident #{} not found", id))?;
+            let entry = change(incident, Utc::now())?;
+            incident.timeline.push(entry);
+            incident.clone()
+        };
+        
+        self.save().await?;
+        Ok(incident)
+    }
+    
+    pub fn get(&self, id: u64) -> Option<Incident> {
+        self.incidents.lock().unwrap().iter().find(|incident| incident.id == id).cloned()
+    }
+    
+    /// All kept incidents, oldest first.
+    pub fn incidents(&self) -> Vec<Incident> {
+        self.incidents.lock().unwrap().clone()
+    }
+    
+    async fn save(&self) -> Result<()> {
+        let _guard = self.write_lock.lock().await;
+        let content = serde_json::to_string_pretty(&self.incidents())?;
+        data_dir::ensure_async().await?;
+        fs::write(INCIDENTS_FILE, content).await?;
+        Ok(())
+    }
+}
+
+fn entry(at: DateTime<Utc>, kind: TimelineKind, message: String, author: Option<&str>) -> TimelineEntry {
+    TimelineEntry {
+        at,
+        kind,
+        message,
+        author: author.map(str::to_string),
+        details: None,
+    }
+}
+
+/// A probable root cause for the incident's anomalies, if they fit a known pattern.
+fn root_cause_hint(incident: &Incident) -> Option<String> {
+    let subjects: BTreeSet<String> = incident
+        .anomalies
+        .iter()
+        .map(|anomaly| anomaly_subject(&anomaly.message))
+        .collect();
+    let has = |prefix: &str| subjects.iter().any(|subject| subject.starts_with(prefix));
+    
+    if let Some(root) = incident.key.strip_prefix("check:") {
+        let root_down = format!("Device Down: {}", root);
+        let behind = subjects.iter().filter(|subject| subject.starts_with("Device Down") && **subject != root_down).count();
+        return (subjects.contains(&root_down) && behind > 0)
+            .then(|| format!("{} is down; the other {} failing check(s) depend on it", root, behind));
+    }
+    if incident.key == "host" {
+        let known = HOST_HINTS
+            .iter()
+            .find(|(prefixes, _)| prefixes.iter().all(|prefix| has(prefix)))
+            .map(|(_, hint)| hint.to_string());
+        return known.or_else(|| {
+            (subjects.len() > 1).then(|| "Several host resources are strained at once; look for one busy process".to_string())
+        });
+    }
+    if let Some(name) = incident.key.strip_prefix("instance:") {
+        return (subjects.len() > 1).then(|| format!("Several {} metrics are off at once, which points at {} itself", name, name));
+    }
+    None
+}
@@ -0,0 +1,141 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Firing alerts that are neither resolved nor re-sent for this long are dropped,
+/// so a sender that goes away cannot leave an alert behind forever.
+const FIRING_EXPIRY_SECS: i64 = 86400;
+
+/// One alert from another monitoring system, already phrased as an anomaly.
+#[derive(Debug, Clone)]
+pub struct IngestedAlert {
+    /// Identifies the alert across notifications, e.g. Alertmanager's fingerprint.
+    pub key: String,
+    pub message: String,
+    pub firing: bool,
+}
+
+/// Alerts currently firing in other systems.
+pub struct IngestedAlerts {
+    firing: BTreeMap<String, (DateTime<Utc>, String)>,
+}
+
+impl IngestedAlerts {
+    pub fn new() -> Self {
+        Self {
+            firing: BTreeMap::new(),
+        }
+    }
+    
+    /// Adds firing alerts and removes resolved ones. A re-sent alert keeps its first
+    /// message so changing values in a summary do not notify again.
+    pub fn apply(&mut self, alerts: Vec<IngestedAlert>) {
+        let now = Utc::now();
+        self.firing.retain(|_, (seen, _)| (now - *seen).num_seconds() < FIRING_EXPIRY_SECS);
+        
+        for alert in alerts {
+            if !alert.firing {
+                self.firing.remove(&alert.key);
+                continue;
+            }
+            let entry = self.firing.entry(alert.key).or_insert((now, alert.message));
+            entry.0 = now;
+        }
+    }
+    
+    pub fn anomalies(&self) -> Vec<String> {
+        let now = Utc::now();
+        self.firing
+            .values()
+            .filter(|(seen, _)| (now - *seen).num_seconds() < FIRING_EXPIRY_SECS)
+            .map(|(_, message)| message.clone())
+            .collect()
+    }
+}
+
+/// Reads an Alertmanager, Grafana or Uptime Kuma webhook notification.
+pub fn parse_payload(payload: &Value) -> Result<Vec<IngestedAlert>> {
+    if let Some(alerts) = payload["alerts"].as_array() {
+        // Grafana's unified alerting sends Alertmanager's format with a few extra fields
+        let source = if payload.get("orgId").is_some() { "grafana" } else { "alertmanager" };
+        return Ok(alerts.iter().map(|alert| alertmanager_alert(source, alert)).collect());
+    }
+    if payload.get("heartbeat").is_some() || payload.get("monitor").is_some() {
+        return Ok(uptime_kuma_alert(payload).into_iter().collect());
+    }
+    if payload.get("ruleName").is_some() {
+        return Ok(vec![grafana_legacy_alert(payload)]);
+    }
+    
+    Err(anyhow!("unrecognised payload; expected Alertmanager, Grafana or Uptime Kuma"))
+}
+
+fn alertmanager_alert(source: &str, alert: &Value) -> IngestedAlert {
+    let labels = &alert["labels"];
+    let name = labels["alertname"].as_str().unwrap_or("alert");
+    let instance = ["instance", "host", "job"].iter().find_map(|label| labels[*label].as_str());
+    let summary = ["summary", "description"].iter().find_map(|key| alert["annotations"][*key].as_str());
+    
+    let mut message = format!("{}: {} {}", prefix(labels["severity"].as_str()), source, name);
+    if let Some(instance) = instance {
+        message.push_str(&format!(" on {}", instance));
+    }
+    if let Some(summary) = summary {
+        message.push_str(&format!(" - {}", summary));
+    }
+    
+    let key = match alert["fingerprint"].as_str() {
+        Some(fingerprint) => format!("{} {}", source, fingerprint),
+        None => format!("{} {}", source, labels),
+    };
+    IngestedAlert {
+        key,
+        message,
+        firing: alert["status"] != "resolved",
+    }
+}
+
+/// Uptime Kuma status codes: 0 down, 1 up, 2 pending, 3 maintenance. Test
+/// notifications come without a heartbeat and are ignored.
+fn uptime_kuma_alert(payload: &Value) -> Option<IngestedAlert> {
+    let status = payload["heartbeat"]["status"].as_u64()?;
+    let name = payload["monitor"]["name"].as_str().unwrap_or("monitor");
+    let detail = payload["heartbeat"]["msg"].as_str().filter(|msg| !msg.is_empty());
+    
+    let mut message = format!("{}: uptime-kuma {} down", prefix(Some("critical")), name);
+    if let Some(detail) = detail {
+        message.push_str(&format!(" - {}", detail));
+    }
+    
+    Some(IngestedAlert {
+        key: format!("uptime-kuma {}", name),
+        message,
+        firing: status == 0,
+    })
+}
+
+fn grafana_legacy_alert(payload: &Value) -> IngestedAlert {
+    let name = payload["ruleName"].as_str().unwrap_or("alert");
+    let severity = payload["tags"]["severity"].as_str().or(Some("warning"));
+    
+    let mut message = format!("{}: grafana {}", prefix(severity), name);
+    if let Some(detail) = payload["message"].as_str().filter(|detail| !detail.is_empty()) {
+        message.push_str(&format!(" - {}", detail));
+    }
+    
+    IngestedAlert {
+        key: format!("grafana rule {}", payload["ruleId"].as_u64().map_or(name.to_string(), |id| id.to_string())),
+        message,
+        firing: matches!(payload["state"].as_str(), Some("alerting") | Some("no_data")),
+    }
+}
+
+/// The anomaly prefix carrying the sender's severity.
+fn prefix(severity: Option<&str>) -> &'static str {
+    match severity.map(str::to_ascii_lowercase).as_deref() {
+        Some("critical" | "error" | "page" | "high") => "External Critical",
+        Some("warning" | "warn" | "medium") => "External Warning",
+        _ => "External Alert",
+    }
+}
@@ -0,0 +1,259 @@
+use crate::utils::{data_dir, process};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::os::unix::fs::OpenOptionsExt;
+use std::time::Duration;
+use tokio::fs::{self as async_fs, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::{debug, warn};
+
+pub const OOM_KILLS_FILE: &str = "data/oom_kills.jsonl";
+
+/// Kernel messages worth an anomaly: (category, patterns). The category becomes part
+/// of the anomaly, e.g. "Kernel I/O Error: ...". OOM kills are parsed separately.
+// How long `dmesg` may take before the poll gives up on it
+const DMESG_TIMEOUT: Duration = Duration::from_secs(5);
+const PATTERNS: [(&str, &[&str]); 4] = [
+    (
+        "I/O Error",
+        &["I/O error", "critical medium error", "Medium Error", "exception Emask", "failed command:"],
+    ),
+    (
+        "Read-only Remount",
+        &["Remounting filesystem read-only", "remounted read-only", "forced readonly", "Filesystem has been shut down"],
+    ),
+    (
+        "USB Reset",
+        &["reset high-speed USB", "reset full-speed USB", "reset low-speed USB", "reset SuperSpeed"],
+    ),
+    ("Under-voltage", &["Under-voltage detected", "Undervoltage detected"]),
+];
+
+/// A process the OOM killer ended, from its "Killed process" line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OomKill {
+    pub pid: u32,
+    pub process: String,
+    pub uid: Option<u32>,
+    pub total_vm_kb: Option<u64>,
+    pub anon_rss_kb: Option<u64>,
+    pub line: String,
+}
+
+/// System memory when an OOM kill was noticed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryState {
+    pub total_bytes: u64,
+    pub used_bytes: u64,
+    pub available_bytes: u64,
+    pub swap_total_bytes: u64,
+    pub swap_used_bytes: u64,
+    /// Largest processes left running: (pid, name, resident bytes).
+    pub top_processes: Vec<(u32, String, u64)>,
+}
+
+/// One line of `data/oom_kills.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OomRecord {
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kill: OomKill,
+    pub memory: MemoryState,
+}
+
+/// What a poll of the kernel log found.
+#[derive(Debug, Default)]
+pub struct KernelLogUpdate {
+    pub alerts: Vec<String>,
+    pub oom_kills: Vec<OomKill>,
+}
+
+enum Source {
+    /// `/dev/kmsg`, read without blocking from where it was opened.
+    Kmsg(File),
+    /// `dmesg -r` output, newer than the last timestamp seen.
+    Dmesg { last: Option<f64> },
+}
+
+/// Turns new kernel log messages matching known failure patterns into anomalies.
+pub struct KernelLogWatcher {
+    source: Option<Source>,
+}
+
+impl KernelLogWatcher {
+    pub fn new() -> Self {
+        let kmsg = std::fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open("/dev/kmsg")
+            .and_then(|mut file| file.seek(SeekFrom::End(0)).map(|_| file));
+        
+        let source = match kmsg {
+            Ok(file) => Source::Kmsg(file),
+            Err(e) => {
+                debug!("Cannot read /dev/kmsg, using dmesg: {}", e);
+                Source::Dmesg { last: None }
+            }
+        };
+        
+        Self { source: Some(source) }
+    }
+    
+    /// Reads messages logged since the last poll. Alerts are at most one per category,
+    /// with a count of the lines each stands for.
+    pub async fn poll(&mut self) -> KernelLogUpdate {
+        let lines = match &mut self.source {
+            Some(Source::Kmsg(file)) => read_kmsg(file),
+            Some(Source::Dmesg { last }) => match read_dmesg(last).await {
+                Ok(lines) => lines,
+                Err(e) => {
+                    warn!("Kernel log unavailable, not watching it: {}", e);
+                    self.source = None;
+                    Vec::new()
+                }
+            },
+            None => Vec::new(),
+        };
+        
+        KernelLogUpdate {
+            alerts: alerts(&lines),
+            oom_kills: lines.iter().filter_map(|line| parse_oom_kill(line)).collect(),
+        }
+    }
+}
+
+/// Groups matched lines by category; each alert carries the first raw line.
+fn alerts(lines: &[String]) -> Vec<String> {
+    let mut matched: BTreeMap<&str, (&String, usize)> = BTreeMap::new();
+    for line in lines {
+        let Some((category, _)) = PATTERNS
+            .iter()
+            .find(|(_, patterns)| patterns.iter().any(|pattern| line.contains(pattern)))
+        else {
+            continue;
+        };
+        matched.entry(category).or_insert((line, 0)).1 += 1;
+    }
+    
+    matched
+        .into_iter()
+        .map(|(category, (line, count))| {
+            if count > 1 {
+                format!("Kernel {}: {} (+{} more)", category, line, count - 1)
+            } else {
+                format!("Kernel {}: {}", category, line)
+            }
+        })
+        .collect()
+}
+
+/// Parses e.g. `Out of memory: Killed process 1234 (java) total-vm:4096kB, anon-rss:2048kB, ..., UID:1000 ...`.
+fn parse_oom_kill(line: &str) -> Option<OomKill> {
+    let rest = &line[line.find("Killed process ")? + "Killed process ".len()..];
+    let (pid, rest) = rest.split_once(' ')?;
+    let process = rest.strip_prefix('(')?.split_once(')')?.0;
+    let field = |name: &str| {
+        rest.split([' ', ','])
+            .find_map(|field| field.strip_prefix(name))
+            .and_then(|value| value.trim_end_matches("kB").parse::<u64>().ok())
+    };
+    
+    Some(OomKill {
+        pid: pid.parse().ok()?,
+        process: process.to_string(),
+        uid: field("UID:").map(|uid| uid as u32),
+        total_vm_kb: field("total-vm:"),
+        anon_rss_kb: field("anon-rss:"),
+        line: line.to_string(),
+    })
+}
+
+/// Appends an OOM kill to `data/oom_kills.jsonl`.
+pub async fn record_oom_kill(record: &OomRecord) -> Result<()> {
+    data_dir::ensure_async().await?;
+    
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+    
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(OOM_KILLS_FILE)
+        .await?;
+    file.write_all(line.as_bytes()).await?;
+    
+    Ok(())
+}
+
+/// Recorded OOM kills, oldest first.
+pub async fn oom_kills() -> Vec<OomRecord> {
+    let content = async_fs::read_to_string(OOM_KILLS_FILE).await.unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Each read of `/dev/kmsg` returns one record: `priority,sequence,usec,flags;message`
+/// followed by optional continuation lines.
+fn read_kmsg(file: &mut File) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut buffer = vec![0u8; 8192];
+    
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => {
+                let record = String::from_utf8_lossy(&buffer[..read]);
+                if let Some((_, message)) = record.lines().next().and_then(|line| line.split_once(';')) {
+                    lines.push(message.to_string());
+                }
+            }
+            // Records were overwritten before we read them; reading continues after the gap
+            Err(e) if e.raw_os_error() == Some(libc::EPIPE) => continue,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+            Err(e) => {
+                debug!("Reading /dev/kmsg failed: {}", e);
+                break;
+            }
+        }
+    }
+    
+    lines
+}
+
+/// Parses `dmesg -r` lines (`<6>[   12.345678] message`). The first call only
+/// records where the log ends.
+async fn read_dmesg(last: &mut Option<f64>) -> Result<Vec<String>> {
+    let output = process::output_within(Command::new("dmesg").arg("-r"), DMESG_TIMEOUT).await?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("{}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = Vec::new();
+    let mut newest = *last;
+    
+    for line in stdout.lines() {
+        let Some((timestamp, message)) = line
+            .split_once('[')
+            .and_then(|(_, rest)| rest.split_once(']'))
+            .and_then(|(timestamp, message)| Some((timestamp.trim().parse::<f64>().ok()?, message.trim())))
+        else {
+            continue;
+        };
+        
+        if last.is_some_and(|last| timestamp > last) {
+            lines.push(message.to_string());
+        }
+        newest = Some(newest.map_or(timestamp, |newest: f64| newest.max(timestamp)));
+    }
+    
+    *last = newest.or(Some(0.0));
+    Ok(lines)
+}
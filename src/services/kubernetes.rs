@@ -0,0 +1,289 @@
+use crate::models::config::KubernetesConfig;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::process::Command;
+use tracing::{debug, warn};
+
+const SERVICE_ACCOUNT_TOKEN: &str = "/var/run/secrets/kubernetes.io/serviceaccount/token";
+
+/// Node conditions that mean trouble when true.
+const PRESSURE_CONDITIONS: [&str; 4] = ["MemoryPressure", "DiskPressure", "PIDPressure", "NetworkUnavailable"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeStatus {
+    pub name: String,
+    /// Status of the Ready condition: "True", "False" or "Unknown".
+    pub ready: String,
+    /// Pressure conditions currently true.
+    pub pressure: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingPod {
+    pub namespace: String,
+    pub name: String,
+    pub since: DateTime<Utc>,
+    pub reason: String,
+}
+
+/// The cluster as seen by the latest poll.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClusterStatus {
+    pub collected_at: DateTime<Utc>,
+    pub nodes: Vec<NodeStatus>,
+    pub pods: usize,
+    pub running: usize,
+    pub pending: Vec<PendingPod>,
+    /// Container restarts summed over all pods.
+    pub restarts: u64,
+}
+
+/// Polls the cluster through `kubectl` when credentials are available.
+pub struct KubernetesWatcher {
+    last_poll: Option<DateTime<Utc>>,
+    /// Restart count per "namespace/pod container".
+    restarts: HashMap<String, u64>,
+    status: Option<ClusterStatus>,
+    /// Why the latest poll failed; `status` is then from an earlier one.
+    error: Option<String>,
+}
+
+impl KubernetesWatcher {
+    pub fn new() -> Self {
+        Self {
+            last_poll: None,
+            restarts: HashMap::new(),
+            status: None,
+            error: None,
+        }
+    }
+    
+    /// Marks a poll of the cluster as started when one is due, to be run without holding the monitor.
+    pub fn next_poll(&mut self, config: &KubernetesConfig) -> Option<KubernetesPoll> {
+        let Some(args) = kubectl_args(config) else {
+            self.status = None;
+            self.error = None;
+            return None;
+        };
+        
+        let now = Utc::now();
+        if self.last_poll.is_some_and(|last| (now - last).num_seconds() < config.interval as i64) {
+            return None;
+        }
+        self.last_poll = Some(now);
+        Some(KubernetesPoll { args, at: now })
+    }
+    
+    /// Keeps the node and pod state of a [`KubernetesPoll`] and returns an alert for
+    /// each container that restarted since the previous poll.
+    pub fn apply(&mut self, polled: KubernetesLists) -> Vec<String> {
+        let now = polled.at;
+        let (nodes, pods) = match polled.lists {
+            Ok(lists) => lists,
+            Err(e) => {
+                warn!("Kubernetes poll failed: {}", e);
+                self.error = Some(e.to_string());
+                return Vec::new();
+            }
+        };
+        self.error = None;
+        
+        let mut restarts = HashMap::new();
+        let mut alerts = Vec::new();
+        let mut status = ClusterStatus {
+            collected_at: now,
+            nodes: items(&nodes).map(node_status).collect(),
+            pods: 0,
+            running: 0,
+            pending: Vec::new(),
+            restarts: 0,
+        };
+        
+        for pod in items(&pods) {
+            let namespace = pod["metadata"]["namespace"].as_str().unwrap_or_default();
+            let name = pod["metadata"]["name"].as_str().unwrap_or_default();
+            status.pods += 1;
+            
+            match pod["status"]["phase"].as_str() {
+                Some("Running") => status.running += 1,
+                Some("Pending") => status.pending.push(pending_pod(namespace, name, pod)),
+                _ => {}
+            }
+            
+            for container in pod["status"]["containerStatuses"].as_array().into_iter().flatten() {
+                let count = container["restartCount"].as_u64().unwrap_or(0);
+                let key = format!("{}/{} {}", namespace, name, container["name"].as_str().unwrap_or_default());
+                status.restarts += count;
+                
+                // Containers seen for the first time only set the starting count
+                if self.restarts.get(&key).is_some_and(|previous| count > *previous) {
+                    let reason = container["lastState"]["terminated"]["reason"].as_str().unwrap_or("restarted");
+                    alerts.push(format!("Pod Restarted: {} {} restarts={}", key, reason, count));
+                }
+                restarts.insert(key, count);
+            }
+        }
+        
+        debug!(
+            "Kubernetes: {} nodes, {} pods, {} pending",
+            status.nodes.len(),
+            status.pods,
+            status.pending.len()
+        );
+        self.restarts = restarts;
+        self.status = Some(status);
+        alerts
+    }
+    
+    /// Ongoing problems: nodes not ready or under pressure, and pods pending too long.
+    pub fn anomalies(&self, config: &KubernetesConfig) -> Vec<String> {
+        let Some(status) = &self.status else {
+            return Vec::new();
+        };
+        let mut anomalies = Vec::new();
+        
+        for node in &status.nodes {
+            if node.ready != "True" {
+                anomalies.push(format!("Node Not Ready: {} {}", node.name, node.ready));
+            }
+            for condition in &node.pressure {
+                anomalies.push(format!("Node Pressure: {} {}", node.name, condition));
+            }
+        }
+        for pod in &status.pending {
+            if (status.collected_at - pod.since).num_seconds() >= config.pending_after as i64 {
+                anomalies.push(format!("Pod Pending: {}/{} {}", pod.namespace, pod.name, pod.reason));
+            }
+        }
+        
+        anomalies
+    }
+    
+    pub fn status(&self) -> Option<&ClusterStatus> {
+        self.status.as_ref()
+    }
+    
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+/// A due poll of the cluster.
+pub struct KubernetesPoll {
+    args: Vec<String>,
+    at: DateTime<Utc>,
+}
+
+/// The node and pod lists a [`KubernetesPoll`] read.
+pub struct KubernetesLists {
+    lists: Result<(Value, Value)>,
+    at: DateTime<Utc>,
+}
+
+impl KubernetesPoll {
+    pub async fn run(self) -> KubernetesLists {
+        KubernetesLists {
+            lists: tokio::try_join!(kubectl(&self.args, "nodes"), kubectl(&self.args, "pods")),
+            at: self.at,
+        }
+    }
+}
+
+/// Arguments selecting the cluster, or `None` when disabled or no credentials are present.
+fn kubectl_args(config: &KubernetesConfig) -> Option<Vec<String>> {
+    if !config.enabled {
+        return None;
+    }
+    
+    let mut args = Vec::new();
+    if let Some(kubeconfig) = &config.kubeconfig {
+        args.push(format!("--kubeconfig={}", kubeconfig));
+    } else {
+        let from_env = std::env::var("KUBECONFIG")
+            .is_ok_and(|paths| std::env::split_paths(&paths).any(|path| path.is_file()));
+        let from_home = std::env::var("HOME").is_ok_and(|home| Path::new(&home).join(".kube/config").is_file());
+        let in_cluster = std::env::var_os("KUBERNETES_SERVICE_HOST").is_some() && Path::new(SERVICE_ACCOUNT_TOKEN).is_file();
+        if !(from_env || from_home || in_cluster) {
+            return None;
+        }
+    }
+    if let Some(context) = &config.context {
+        args.push(format!("--context={}", context));
+    }
+    
+    Some(args)
+}
+
+async fn kubectl(args: &[String], resource: &str) -> Result<Value> {
+    let output = Command::new("kubectl")
+        .args(args)
+        .args(["get", resource, "--all-namespaces", "--output=json", "--request-timeout=20s"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("kubectl get {}: {}", resource, stderr.trim()));
+    }
+    
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+fn items(list: &Value) -> impl Iterator<Item = &Value> {
+    list["items"].as_array().into_iter().flatten()
+}
+
+fn node_status(node: &Value) -> NodeStatus {
+    let conditions: Vec<&Value> = node["status"]["conditions"].as_array().into_iter().flatten().collect();
+    let condition = |kind: &str| {
+        conditions
+            .iter()
+            .find(|condition| condition["type"] == kind)
+            .and_then(|condition| condition["status"].as_str())
+    };
+    
+    NodeStatus {
+        name: node["metadata"]["name"].as_str().unwrap_or_default().to_string(),
+        ready: condition("Ready").unwrap_or("Unknown").to_string(),
+        pressure: PRESSURE_CONDITIONS
+            .iter()
+            .filter(|kind| condition(kind) == Some("True"))
+            .map(|kind| kind.to_string())
+            .collect(),
+    }
+}
+
+fn pending_pod(namespace: &str, name: &str, pod: &Value) -> PendingPod {
+    let since = pod["metadata"]["creationTimestamp"]
+        .as_str()
+        .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .unwrap_or_else(Utc::now);
+    
+    // Unschedulable pods say so on PodScheduled; pods stuck pulling images on the container
+    let scheduling = pod["status"]["conditions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|condition| condition["type"] == "PodScheduled" && condition["status"] == "False")
+        .and_then(|condition| condition["reason"].as_str());
+    let waiting = pod["status"]["containerStatuses"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find_map(|container| container["state"]["waiting"]["reason"].as_str());
+    
+    PendingPod {
+        namespace: namespace.to_string(),
+        name: name.to_string(),
+        since,
+        reason: scheduling.or(waiting).unwrap_or("Pending").to_string(),
+    }
+}
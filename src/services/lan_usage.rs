@@ -0,0 +1,386 @@
+use crate::models::config::CaptureConfig;
+use crate::utils::{data_dir, process};
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::Arc;
+use tokio::process::Command;
+use tracing::warn;
+
+pub const LAN_USAGE_FILE: &str = "data/lan_usage.json";
+// Hourly tallies kept, and months
+const HOURS_KEPT: usize = 7 * 24;
+const MONTHS_KEPT: usize = 13;
+// Hours of history before a device can be a new top talker, so the first day of
+// traffic is learned rather than reported
+const TOP_TALKER_HISTORY_HOURS: usize = 24;
+const SAVE_INTERVAL_SECS: i64 = 300;
+// How long `ip -6 neigh` may take before IPv6 neighbours are skipped for a cycle
+const NEIGH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Bytes a LAN device sent to and received from outside the LAN, by address, as
+/// tallied by the capture thread between polls.
+pub type DeviceTally = Arc<Mutex<HashMap<IpAddr, (u64, u64)>>>;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    pub upload: u64,
+    pub download: u64,
+}
+
+impl Usage {
+    fn add(&mut self, other: &Usage) {
+        self.upload += other.upload;
+        self.download += other.download;
+    }
+}
+
+/// A device seen on the LAN, keyed by its MAC address when the neighbour table has
+/// it and by its address otherwise.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Device {
+    pub mac: Option<String>,
+    /// Addresses it used recently.
+    pub addresses: BTreeSet<IpAddr>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Traffic per device in one hour.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HourUsage {
+    pub start: DateTime<Utc>,
+    pub devices: BTreeMap<String, Usage>,
+}
+
+/// The on-disk tallies.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct UsageFile {
+    devices: BTreeMap<String, Device>,
+    hours: VecDeque<HourUsage>,
+    /// Totals per device by month, as "2026-10".
+    months: BTreeMap<String, BTreeMap<String, Usage>>,
+}
+
+/// A device's current rates and monthly total, for the dashboard and the API.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceUsage {
+    pub device: String,
+    pub name: String,
+    pub mac: Option<String>,
+    pub addresses: BTreeSet<IpAddr>,
+    pub upload_kb_per_s: f64,
+    pub download_kb_per_s: f64,
+    pub month: Usage,
+}
+
+/// Attributes LAN bandwidth to devices from the capture's per-address tallies and the
+/// kernel's neighbour table, keeping hourly and monthly totals in `data/lan_usage.json`
+/// and spotting a device that starts moving the most traffic.
+pub struct LanUsageWatcher {
+    tally: DeviceTally,
+    usage: UsageFile,
+    /// Per-device traffic of each poll within the capture window.
+    recent: VecDeque<(DateTime<Utc>, BTreeMap<String, Usage>)>,
+    /// MAC address of each LAN address, as last seen in the neighbour table.
+    neighbours: HashMap<IpAddr, String>,
+    /// Names from `capture.device_names`, by MAC address or IP address.
+    names: BTreeMap<String, String>,
+    window: i64,
+    started: DateTime<Utc>,
+    saved: DateTime<Utc>,
+    /// Devices newly on top of upload and download, with their labels.
+    new_top_talkers: Vec<(String, &'static str)>,
+}
+
+impl LanUsageWatcher {
+    pub fn new() -> Self {
+        let usage = std::fs::read_to_string(LAN_USAGE_FILE)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            tally: DeviceTally::default(),
+            usage,
+            recent: VecDeque::new(),
+            neighbours: HashMap::new(),
+            names: BTreeMap::new(),
+            window: 300,
+            started: Utc::now(),
+            saved: Utc::now(),
+            new_top_talkers: Vec::new(),
+        }
+    }
+    
+    /// The tally the capture collector adds to.
+    pub fn tally(&self) -> DeviceTally {
+        Arc::clone(&self.tally)
+    }
+    
+    /// Takes the traffic tallied since the last poll, to be attributed to devices
+    /// once the neighbour table is read without holding the monitor.
+    pub fn next_poll(&mut self, config: &CaptureConfig) -> LanPoll {
+        let tallied = if config.enabled {
+            std::mem::take(&mut *self.tally.lock())
+        } else {
+            HashMap::new()
+        };
+        LanPoll { tallied }
+    }
+    
+    /// Moves the traffic of a [`LanPoll`] into the per-device totals.
+    pub fn apply(&mut self, polled: LanTallies, config: &CaptureConfig) {
+        self.names = config.device_names.clone();
+        self.window = config.window as i64;
+        let now = Utc::now();
+        if !config.enabled {
+            self.recent.clear();
+            self.new_top_talkers.clear();
+            return;
+        }
+        
+        self.neighbours.extend(polled.neighbours);
+        let mut sample: BTreeMap<String, Usage> = BTreeMap::new();
+        for (address, (upload, download)) in polled.tallied {
+            let mac = self.neighbours.get(&address).cloned();
+            let key = mac.clone().unwrap_or_else(|| address.to_string());
+            let device = self.usage.devices.entry(key.clone()).or_insert_with(|| Device {
+                mac,
+                addresses: BTreeSet::new(),
+                last_seen: now,
+            });
+            device.addresses.insert(address);
+            device.last_seen = now;
+            sample.entry(key).or_default().add(&Usage { upload, download });
+        }
+        
+        // Hourly and monthly totals
+        let hour = now.duration_trunc(Duration::hours(1)).unwrap_or(now);
+        let new_hour = self.usage.hours.back().map_or(true, |last| last.start != hour);
+        if new_hour {
+            self.usage.hours.push_back(HourUsage {
+                start: hour,
+                devices: BTreeMap::new(),
+            });
+            while self.usage.hours.len() > HOURS_KEPT {
+                self.usage.hours.pop_front();
+            }
+            self.forget_devices();
+        }
+        let month = now.format("%Y-%m").to_string();
+        for (key, usage) in &sample {
+            if let Some(current) = self.usage.hours.back_mut() {
+                current.devices.entry(key.clone()).or_default().add(usage);
+            }
+            self.usage.months.entry(month.clone()).or_default().entry(key.clone()).or_default().add(usage);
+        }
+        while self.usage.months.len() > MONTHS_KEPT {
+            self.usage.months.pop_first();
+        }
+        
+        self.recent.push_back((now, sample));
+        while self.recent.front().is_some_and(|(at, _)| (now - *at).num_seconds() >= self.window) {
+            self.recent.pop_front();
+        }
+        
+        self.new_top_talkers.clear();
+        if config.top_talker_kb_per_s > 0.0 {
+            for (direction, pick) in [("upload", upload as fn(&Usage) -> u64), ("download", download)] {
+                if let Some(key) = self.new_top_talker(pick, config.top_talker_kb_per_s) {
+                    self.new_top_talkers.push((self.label(&key), direction));
+                }
+            }
+        }
+        
+        if new_hour || (now - self.saved).num_seconds() >= SAVE_INTERVAL_SECS {
+            self.save();
+            self.saved = now;
+        }
+    }
+    
+    /// The device moving the most traffic one way over the window, when it moves at least
+    /// `threshold` KB/s and was not the top device of any earlier hour on record.
+    fn new_top_talker(&self, pick: fn(&Usage) -> u64, threshold: f64) -> Option<String> {
+        let earlier = self.usage.hours.len().saturating_sub(1);
+        if earlier < TOP_TALKER_HISTORY_HOURS {
+            return None;
+        }
+        let usage = self.window_usage();
+        let (key, bytes) = top(usage.iter(), pick)?;
+        if bytes as f64 / 1024.0 / self.window_seconds() < threshold {
+            return None;
+        }
+        let known = self
+            .usage
+            .hours
+            .iter()
+            .take(earlier)
+            .any(|hour| top(hour.devices.iter(), pick).is_some_and(|(top, _)| top == key));
+        (!known).then(|| key.clone())
+    }
+    
+    pub fn anomalies(&self) -> Vec<String> {
+        self.new_top_talkers
+            .iter()
+            .map(|(label, direction)| format!("New Top Talker: {} {}", label, direction))
+            .collect()
+    }
+    
+    /// Devices with traffic this month, busiest right now first.
+    pub fn devices(&self) -> Vec<DeviceUsage> {
+        let month = Utc::now().format("%Y-%m").to_string();
+        let mut devices = self.month_devices(&month);
+        devices.sort_by(|a, b| {
+            (b.upload_kb_per_s + b.download_kb_per_s)
+                .total_cmp(&(a.upload_kb_per_s + a.download_kb_per_s))
+                .then((b.month.upload + b.month.download).cmp(&(a.month.upload + a.month.download)))
+        });
+        devices
+    }
+    
+    /// Each device's totals for `month` ("2026-10"), with its current rates.
+    pub fn month_devices(&self, month: &str) -> Vec<DeviceUsage> {
+        let current = self.window_usage();
+        let seconds = self.window_seconds();
+        let Some(totals) = self.usage.months.get(month) else {
+            return Vec::new();
+        };
+        totals
+            .iter()
+            .map(|(key, usage)| {
+                let rate = current.get(key).copied().unwrap_or_default();
+                let device = self.usage.devices.get(key);
+                DeviceUsage {
+                    device: key.clone(),
+                    name: self.label(key),
+                    mac: device.and_then(|device| device.mac.clone()),
+                    addresses: device.map(|device| device.addresses.clone()).unwrap_or_default(),
+                    upload_kb_per_s: rate.upload as f64 / 1024.0 / seconds,
+                    download_kb_per_s: rate.download as f64 / 1024.0 / seconds,
+                    month: *usage,
+                }
+            })
+            .collect()
+    }
+    
+    pub fn months(&self) -> Vec<String> {
+        self.usage.months.keys().cloned().collect()
+    }
+    
+    pub fn hours(&self) -> &VecDeque<HourUsage> {
+        &self.usage.hours
+    }
+    
+    /// The configured name of a device, else its address and MAC.
+    fn label(&self, key: &str) -> String {
+        let device = self.usage.devices.get(key);
+        let named = std::iter::once(key.to_string())
+            .chain(device.into_iter().flat_map(|device| device.addresses.iter().map(IpAddr::to_string)))
+            .find_map(|id| self.names.get(&id).cloned());
+        match (named, device) {
+            (Some(name), _) => name,
+            (None, Some(Device { mac: Some(mac), addresses, .. })) => match addresses.iter().next_back() {
+                Some(address) => format!("{} ({})", address, mac),
+                None => mac.clone(),
+            },
+            _ => key.to_string(),
+        }
+    }
+    
+    fn window_usage(&self) -> BTreeMap<String, Usage> {
+        let mut totals: BTreeMap<String, Usage> = BTreeMap::new();
+        for (key, usage) in self.recent.iter().flat_map(|(_, sample)| sample) {
+            totals.entry(key.clone()).or_default().add(usage);
+        }
+        totals
+    }
+    
+    fn window_seconds(&self) -> f64 {
+        ((Utc::now() - self.started).num_seconds() as f64).clamp(1.0, self.window.max(1) as f64)
+    }
+    
+    /// Drops devices and addresses with no traffic in the hours and months on record.
+    fn forget_devices(&mut self) {
+        let Some(oldest) = self.usage.hours.front().map(|hour| hour.start) else {
+            return;
+        };
+        let counted: BTreeSet<&String> = self.usage.months.values().flat_map(|devices| devices.keys()).collect();
+        self.usage.devices.retain(|key, device| device.last_seen >= oldest || counted.contains(key));
+    }
+    
+    fn save(&self) {
+        let result = data_dir::ensure().and_then(|_| {
+            std::fs::write(LAN_USAGE_FILE, serde_json::to_string(&self.usage)?)
+        });
+        if let Err(e) = result {
+            warn!("Failed to save LAN usage: {}", e);
+        }
+    }
+}
+
+/// Traffic tallied since the previous poll, by address.
+pub struct LanPoll {
+    tallied: HashMap<IpAddr, (u64, u64)>,
+}
+
+/// A [`LanPoll`]'s traffic with the MAC addresses the neighbour table had for it.
+pub struct LanTallies {
+    tallied: HashMap<IpAddr, (u64, u64)>,
+    neighbours: Vec<(IpAddr, String)>,
+}
+
+impl LanPoll {
+    pub async fn run(self) -> LanTallies {
+        let neighbours = if self.tallied.is_empty() { Vec::new() } else { read_neighbours().await };
+        LanTallies {
+            tallied: self.tallied,
+            neighbours,
+        }
+    }
+}
+
+/// Reads the IPv4 ARP table and the IPv6 neighbours. Entries that expired are left
+/// out, so devices keep their last MAC address.
+async fn read_neighbours() -> Vec<(IpAddr, String)> {
+    let mut neighbours = Vec::new();
+    let arp = tokio::fs::read_to_string("/proc/net/arp").await.unwrap_or_default();
+    for line in arp.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        // Flags 0x0 are incomplete entries
+        if let [address, _, flags, mac, ..] = fields[..] {
+            if let (Ok(address), true) = (address.parse(), flags != "0x0") {
+                neighbours.push((address, mac.to_lowercase()));
+            }
+        }
+    }
+    
+    let output = match process::output_within(Command::new("ip").args(["-6", "neigh", "show"]), NEIGH_TIMEOUT).await {
+        Ok(output) if output.status.success() => output,
+        _ => return neighbours,
+    };
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let mac = fields.iter().position(|field| *field == "lladdr").and_then(|index| fields.get(index + 1));
+        if let (Some(Ok(address)), Some(mac)) = (fields.first().map(|field| field.parse()), mac) {
+            neighbours.push((address, mac.to_lowercase()));
+        }
+    }
+    neighbours
+}
+
+fn upload(usage: &Usage) -> u64 {
+    usage.upload
+}
+
+fn download(usage: &Usage) -> u64 {
+    usage.download
+}
+
+/// The device with the most traffic by `pick`, if any moved some.
+fn top<'a>(devices: impl Iterator<Item = (&'a String, &'a Usage)>, pick: fn(&Usage) -> u64) -> Option<(&'a String, u64)> {
+    devices
+        .map(|(key, usage)| (key, pick(usage)))
+        .filter(|(_, bytes)| *bytes > 0)
+        .max_by_key(|(_, bytes)| *bytes)
+}
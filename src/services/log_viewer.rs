@@ -0,0 +1,121 @@
+use crate::models::logs::{LogLine, LogPage};
+use crate::models::metrics::AnomalySeverity;
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+
+pub const MAX_PER_PAGE: usize = 1000;
+// Paging back further than this many lines would hold too much in memory
+const MAX_SCROLLBACK: usize = 100_000;
+
+/// Which lines the log viewer shows.
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    /// Text the line must contain, matched case-insensitively.
+    pub query: Option<String>,
+    /// Least severe level shown; lines without a level are hidden when set.
+    pub severity: Option<AnomalySeverity>,
+}
+
+impl LogFilter {
+    pub fn new(query: Option<String>, severity: Option<AnomalySeverity>) -> Self {
+        Self {
+            query: query.filter(|query| !query.is_empty()).map(|query| query.to_lowercase()),
+            severity,
+        }
+    }
+    
+    /// The line with its level when it passes the filter.
+    pub fn apply(&self, text: &str) -> Option<LogLine> {
+        if let Some(query) = &self.query {
+            if !text.to_lowercase().contains(query) {
+                return None;
+            }
+        }
+        let severity = line_severity(text);
+        if self.severity.is_some_and(|least| severity.map_or(true, |severity| severity < least)) {
+            return None;
+        }
+        Some(LogLine {
+            number: None,
+            text: text.to_string(),
+            severity,
+        })
+    }
+}
+
+/// Reads one page of the lines in `path` that pass `filter`, counting pages back
+/// from the end of the file so page 1 is the latest.
+pub async fn read_page(name: &str, path: PathBuf, filter: LogFilter, page: usize, per_page: usize) -> Result<LogPage> {
+    let per_page = per_page.clamp(1, MAX_PER_PAGE);
+    let page = page.clamp(1, MAX_SCROLLBACK / per_page);
+    
+    let (total, mut window) = tokio::task::spawn_blocking(move || -> Result<(u64, VecDeque<LogLine>)> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut window = VecDeque::with_capacity(page * per_page);
+        let mut buffer = Vec::new();
+        let (mut number, mut total) = (0u64, 0u64);
+        
+        while reader.read_until(b'\n', &mut buffer)? > 0 {
+            number += 1;
+            let text = String::from_utf8_lossy(&buffer);
+            if let Some(mut line) = filter.apply(text.trim_end_matches(['\n', '\r'])) {
+                line.number = Some(number);
+                total += 1;
+                if window.len() == page * per_page {
+                    window.pop_front();
+                }
+                window.push_back(line);
+            }
+            buffer.clear();
+        }
+        Ok((total, window))
+    })
+    .await??;
+    
+    // The window ends with page 1, so the requested page is at its front
+    let skipped = (page as u64 - 1) * per_page as u64;
+    let lines = if total > skipped {
+        let count = (total - skipped).min(per_page as u64) as usize;
+        window.drain(..count).collect()
+    } else {
+        Vec::new()
+    };
+    
+    Ok(LogPage {
+        name: name.to_string(),
+        page,
+        per_page,
+        total,
+        pages: (total + per_page as u64 - 1) / per_page as u64,
+        lines,
+    })
+}
+
+/// The level a log line states: `level` or `severity` in JSON lines such as the
+/// monitor's own logs, otherwise a level word near the start of the line.
+pub fn line_severity(line: &str) -> Option<AnomalySeverity> {
+    if line.starts_with('{') {
+        if let Ok(serde_json::Value::Object(fields)) = serde_json::from_str(line) {
+            return ["level", "severity"]
+                .iter()
+                .find_map(|key| fields.get(*key).and_then(|value| value.as_str()))
+                .and_then(severity_word);
+        }
+    }
+    line.split_whitespace()
+        .take(6)
+        .map(|word| word.trim_matches(|c: char| !c.is_ascii_alphabetic()))
+        .find_map(severity_word)
+}
+
+fn severity_word(word: &str) -> Option<AnomalySeverity> {
+    match word.to_ascii_uppercase().as_str() {
+        "CRITICAL" | "CRIT" | "FATAL" | "ERROR" | "ERR" | "EMERG" | "ALERT" => Some(AnomalySeverity::Critical),
+        "WARNING" | "WARN" => Some(AnomalySeverity::Warning),
+        "INFO" | "NOTICE" | "DEBUG" | "TRACE" => Some(AnomalySeverity::Info),
+        _ => None,
+    }
+}
@@ -0,0 +1,180 @@
+use crate::models::config::{LogSeverity, LogWatchRule, MonitoringConfig};
+use crate::models::metrics::SystemMetrics;
+use crate::services::collectors::Collector;
+use crate::utils::glob;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::io::SeekFrom;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+// A log that grew by more than this between two reads is only read from here on
+const MAX_READ_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Reads the lines appended to log files since the previous read, following
+/// rotation and truncation.
+pub struct LogTail {
+    /// Inode and read offset of each file.
+    positions: HashMap<PathBuf, (u64, u64)>,
+}
+
+impl LogTail {
+    pub fn new() -> Self {
+        Self {
+            positions: HashMap::new(),
+        }
+    }
+    
+    /// Complete lines added to `path` since the previous call. A file seen for the
+    /// first time starts at its end, so existing history is not reported.
+    pub async fn read_new(&mut self, path: &Path) -> Vec<String> {
+        let Ok(mut file) = File::open(path).await else {
+            self.positions.remove(path);
+            return Vec::new();
+        };
+        let Ok(metadata) = file.metadata().await else {
+            return Vec::new();
+        };
+        let (inode, len) = (metadata.ino(), metadata.len());
+        
+        let offset = match self.positions.get(path) {
+            None => {
+                self.positions.insert(path.to_path_buf(), (inode, len));
+                return Vec::new();
+            }
+            // Rotated away or truncated: the file starts over
+            Some(&(known, offset)) if known != inode || len < offset => 0,
+            Some(&(_, offset)) => offset.max(len.saturating_sub(MAX_READ_BYTES)),
+        };
+        
+        let mut data = Vec::new();
+        if file.seek(SeekFrom::Start(offset)).await.is_err()
+            || file.take(len - offset).read_to_end(&mut data).await.is_err()
+        {
+            return Vec::new();
+        }
+        // A partly written last line is read again once it is complete
+        let complete = data.iter().rposition(|&b| b == b'\n').map_or(0, |end| end + 1);
+        self.positions.insert(path.to_path_buf(), (inode, offset + complete as u64));
+        
+        String::from_utf8_lossy(&data[..complete]).lines().map(str::to_string).collect()
+    }
+    
+    /// Forgets files no longer read, so a file that comes back starts at its end.
+    pub fn retain(&mut self, paths: &BTreeSet<PathBuf>) {
+        self.positions.retain(|path, _| paths.contains(path));
+    }
+}
+
+struct RuleState {
+    rule: LogWatchRule,
+    regex: Regex,
+    /// Matches counted per second, oldest first, so a burst costs one entry.
+    matches: VecDeque<(DateTime<Utc>, usize)>,
+}
+
+impl RuleState {
+    fn count(&self) -> usize {
+        self.matches.iter().map(|(_, count)| count).sum()
+    }
+}
+
+/// Counts matches of the configured patterns in new log lines.
+pub struct LogWatchCollector {
+    rules: Vec<RuleState>,
+    tail: LogTail,
+}
+
+impl LogWatchCollector {
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            tail: LogTail::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Collector for LogWatchCollector {
+    fn name(&self) -> &str {
+        "log_watch"
+    }
+    
+    fn configure(&mut self, config: &MonitoringConfig) {
+        let mut previous = std::mem::take(&mut self.rules);
+        for rule in &config.log_watch {
+            // Unchanged rules keep their recent matches
+            if let Some(index) = previous.iter().position(|state| &state.rule == rule) {
+                self.rules.push(previous.swap_remove(index));
+                continue;
+            }
+            // Patterns were checked when the configuration was validated
+            if let Ok(regex) = Regex::new(&rule.pattern) {
+                self.rules.push(RuleState {
+                    rule: rule.clone(),
+                    regex,
+                    matches: VecDeque::new(),
+                });
+            }
+        }
+    }
+    
+    async fn collect(&mut self, metrics: &mut SystemMetrics) -> Result<()> {
+        let now = Utc::now();
+        
+        // Each file is read once however many rules watch it
+        let files: Vec<Vec<PathBuf>> = self
+            .rules
+            .iter()
+            .map(|state| state.rule.files.iter().flat_map(|file| glob::expand(Path::new("/"), file)).collect())
+            .collect();
+        let paths: BTreeSet<PathBuf> = files.iter().flatten().cloned().collect();
+        self.tail.retain(&paths);
+        let mut lines: HashMap<&PathBuf, Vec<String>> = HashMap::new();
+        for path in &paths {
+            lines.insert(path, self.tail.read_new(path).await);
+        }
+        
+        let second = now.timestamp();
+        for (state, files) in self.rules.iter_mut().zip(&files) {
+            let count: usize = files
+                .iter()
+                .map(|file| lines[file].iter().filter(|line| state.regex.is_match(line)).count())
+                .sum();
+            match state.matches.back_mut() {
+                _ if count == 0 => {}
+                Some((at, total)) if at.timestamp() == second => *total += count,
+                _ => state.matches.push_back((now, count)),
+            }
+            while state.matches.front().is_some_and(|(at, _)| (now - *at).num_seconds() >= state.rule.window as i64) {
+                state.matches.pop_front();
+            }
+            metrics.custom.insert(format!("{}.matches", state.rule.name), state.count() as f64);
+        }
+        
+        Ok(())
+    }
+    
+    fn anomalies(&self) -> Vec<String> {
+        self.rules
+            .iter()
+            .filter(|state| state.count() >= state.rule.threshold as usize)
+            .map(|state| {
+                let prefix = match state.rule.severity {
+                    LogSeverity::Critical => "Log Critical",
+                    LogSeverity::Warning => "Log Warning",
+                    LogSeverity::Info => "Log Match",
+                };
+                format!(
+                    "{}: {} matched {}+ times in {}s",
+                    prefix, state.rule.name, state.rule.threshold, state.rule.window
+                )
+            })
+            .collect()
+    }
+}
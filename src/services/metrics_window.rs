@@ -0,0 +1,370 @@
+use crate::models::metrics::{CheckResult, DiskIo, GpuReading, SystemMetrics};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Serializer};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+const DISK_FIELDS: u8 = 7;
+const GPU_FIELDS: u8 = 4;
+/// The `Series::Disk` fields of the `DiskIo` measures with baselines.
+pub const DISK_AWAIT: u8 = 4;
+pub const DISK_UTIL: u8 = 6;
+// Series are pruned at most once per window, and not more often than this
+const COMPACTION_MIN_SAMPLES: usize = 100;
+
+/// One of the system readings every sample has, in the order of `system_row`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemField {
+    Cpu,
+    Ram,
+    Disk,
+    Temperature,
+    Ping,
+    Connections,
+    NetRx,
+    NetTx,
+    FailedLogins,
+    Load1,
+    Load5,
+    Load15,
+    ProcsRunning,
+    ProcsBlocked,
+    Swap,
+}
+
+impl SystemField {
+    /// The reading in `metrics`, at full precision.
+    pub fn of(self, metrics: &SystemMetrics) -> f64 {
+        match self {
+            SystemField::Cpu => metrics.cpu_percent,
+            SystemField::Ram => metrics.ram_percent,
+            SystemField::Disk => metrics.disk_percent,
+            SystemField::Temperature => metrics.temperature,
+            SystemField::Ping => metrics.ping_ms,
+            SystemField::Connections => metrics.net_connections as f64,
+            SystemField::NetRx => metrics.net_rx_bytes,
+            SystemField::NetTx => metrics.net_tx_bytes,
+            SystemField::FailedLogins => metrics.failed_logins as f64,
+            SystemField::Load1 => metrics.load_1,
+            SystemField::Load5 => metrics.load_5,
+            SystemField::Load15 => metrics.load_15,
+            SystemField::ProcsRunning => metrics.procs_running as f64,
+            SystemField::ProcsBlocked => metrics.procs_blocked as f64,
+            SystemField::Swap => metrics.swap_percent,
+        }
+    }
+}
+
+/// A reading a sample holds under a name, such as a check's latency.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Series {
+    /// A check's latency, loss or jitter.
+    Host(String, u8),
+    /// One of the `DiskIo` measures of a block device.
+    Disk(String, u8),
+    /// Percent of space in use at a mount point.
+    Mount(String),
+    /// Received (0) or transmitted (1) bytes per second of a network interface.
+    Interface(String, u8),
+    /// TCP sockets in a state.
+    TcpState(String),
+    /// One of the `GpuReading` measures of a graphics card.
+    Gpu(String, u8),
+    Custom(String),
+    /// When the host booted, in seconds since the epoch.
+    BootTime,
+}
+
+/// The window of samples the baselines learn from, stored by column: the system
+/// readings as one row of floats per sample and the readings kept by name as
+/// numbered series, so a window of thousands of samples stays small. Samples are
+/// rebuilt when read; the latest is also kept whole for the detectors.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsWindow {
+    timestamps: VecDeque<DateTime<Utc>>,
+    /// The system readings of each sample, in the order of `system_row`.
+    system: VecDeque<[f32; 15]>,
+    /// The named readings of each sample, by series number.
+    series_values: VecDeque<Box<[(u32, f64)]>>,
+    series: Vec<Series>,
+    series_ids: HashMap<Series, u32>,
+    /// Samples added since series no longer in the window were last dropped.
+    added_since_compaction: usize,
+    latest: Option<SystemMetrics>,
+}
+
+impl MetricsWindow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+    
+    /// The newest sample.
+    pub fn back(&self) -> Option<&SystemMetrics> {
+        self.latest.as_ref()
+    }
+    
+    /// The sample at `index`, oldest first.
+    pub fn get(&self, index: usize) -> Option<SystemMetrics> {
+        let timestamp = *self.timestamps.get(index)?;
+        let row = &self.system[index];
+        let value = |field: SystemField| row[field as usize] as f64;
+        let mut metrics = SystemMetrics::new();
+        metrics.timestamp = timestamp;
+        metrics.cpu_percent = value(SystemField::Cpu);
+        metrics.ram_percent = value(SystemField::Ram);
+        metrics.disk_percent = value(SystemField::Disk);
+        metrics.temperature = value(SystemField::Temperature);
+        metrics.ping_ms = value(SystemField::Ping);
+        metrics.net_connections = value(SystemField::Connections) as usize;
+        metrics.net_rx_bytes = value(SystemField::NetRx);
+        metrics.net_tx_bytes = value(SystemField::NetTx);
+        metrics.failed_logins = value(SystemField::FailedLogins) as u32;
+        metrics.load_1 = value(SystemField::Load1);
+        metrics.load_5 = value(SystemField::Load5);
+        metrics.load_15 = value(SystemField::Load15);
+        metrics.procs_running = value(SystemField::ProcsRunning) as u32;
+        metrics.procs_blocked = value(SystemField::ProcsBlocked) as u32;
+        metrics.swap_percent = value(SystemField::Swap);
+        
+        for &(id, value) in self.series_values[index].iter() {
+            match &self.series[id as usize] {
+                Series::Host(name, field) => {
+                    let result = metrics.host_status.entry(name.clone()).or_insert_with(CheckResult::down);
+                    match field {
+                        0 => result.latency_ms = value,
+                        1 => result.loss_percent = value,
+                        _ => result.jitter_ms = value,
+                    }
+                }
+                Series::Disk(device, field) => {
+                    let io = metrics.disk_io.entry(device.clone()).or_default();
+                    *disk_field(io, *field) = value;
+                }
+                Series::Mount(mount) => {
+                    metrics.mounts.insert(mount.clone(), value);
+                }
+                Series::Interface(name, field) => {
+                    let rate = metrics.interfaces.entry(name.clone()).or_default();
+                    match field {
+                        0 => rate.rx_bytes_per_sec = value,
+                        _ => rate.tx_bytes_per_sec = value,
+                    }
+                }
+                Series::TcpState(state) => {
+                    metrics.tcp_states.insert(state.clone(), value as u32);
+                }
+                Series::Gpu(name, field) => {
+                    let gpu = metrics.gpus.entry(name.clone()).or_default();
+                    *gpu_field(gpu, *field) = value;
+                }
+                Series::Custom(name) => {
+                    metrics.custom.insert(name.clone(), value);
+                }
+                Series::BootTime => metrics.boot_time = DateTime::from_timestamp(value as i64, 0),
+            }
+        }
+        Some(metrics)
+    }
+    
+    /// The samples, oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = SystemMetrics> + '_ {
+        (0..self.len()).filter_map(|index| self.get(index))
+    }
+    
+    pub fn timestamp(&self, index: usize) -> Option<DateTime<Utc>> {
+        self.timestamps.get(index).copied()
+    }
+    
+    /// The index of the first sample taken after `since`; the length when none was.
+    pub fn position_after(&self, since: DateTime<Utc>) -> usize {
+        self.timestamps.partition_point(|timestamp| *timestamp <= since)
+    }
+    
+    /// One system reading of each sample from index `from` on, oldest first, read
+    /// in place rather than from rebuilt samples.
+    pub fn column(&self, field: SystemField, from: usize) -> impl Iterator<Item = f64> + Clone + '_ {
+        self.system.range(from.min(self.len())..).map(move |row| row[field as usize] as f64)
+    }
+    
+    /// Every named reading of the samples from index `from` on, each with the index
+    /// and value of every sample that has it, oldest first.
+    pub fn named_columns(&self, from: usize) -> Vec<(&Series, Vec<(usize, f64)>)> {
+        let mut columns: Vec<Vec<(usize, f64)>> = vec![Vec::new(); self.series.len()];
+        for (index, values) in self.series_values.iter().enumerate().skip(from) {
+            for &(id, value) in values.iter() {
+                columns[id as usize].push((index, value));
+            }
+        }
+        self.series
+            .iter()
+            .zip(columns)
+            .filter(|(_, column)| !column.is_empty())
+            .collect()
+    }
+    
+    /// The latest `count` samples as a window of their own.
+    pub fn tail(&self, count: usize) -> MetricsWindow {
+        let mut tail = MetricsWindow::new();
+        let skip = self.len().saturating_sub(count);
+        tail.timestamps = self.timestamps.range(skip..).copied().collect();
+        tail.system = self.system.range(skip..).copied().collect();
+        tail.series_values = self.series_values.range(skip..).cloned().collect();
+        tail.series = self.series.clone();
+        tail.series_ids = self.series_ids.clone();
+        tail.latest = self.latest.clone().filter(|_| !tail.timestamps.is_empty());
+        tail.compact();
+        tail
+    }
+    
+    pub fn push_back(&mut self, metrics: SystemMetrics) {
+        self.timestamps.push_back(metrics.timestamp);
+        self.system.push_back(system_row(&metrics));
+        
+        let mut values = Vec::new();
+        for (name, result) in &metrics.host_status {
+            for (field, value) in [result.latency_ms, result.loss_percent, result.jitter_ms].into_iter().enumerate() {
+                values.push((self.series_id(Series::Host(name.clone(), field as u8)), value));
+            }
+        }
+        for (device, io) in &metrics.disk_io {
+            let mut io = *io;
+            for field in 0..DISK_FIELDS {
+                let value = *disk_field(&mut io, field);
+                values.push((self.series_id(Series::Disk(device.clone(), field)), value));
+            }
+        }
+        for (mount, percent) in &metrics.mounts {
+            values.push((self.series_id(Series::Mount(mount.clone())), *percent));
+        }
+        for (name, rate) in &metrics.interfaces {
+            for (field, value) in [rate.rx_bytes_per_sec, rate.tx_bytes_per_sec].into_iter().enumerate() {
+                values.push((self.series_id(Series::Interface(name.clone(), field as u8)), value));
+            }
+        }
+        for (state, count) in &metrics.tcp_states {
+            values.push((self.series_id(Series::TcpState(state.clone())), *count as f64));
+        }
+        for (name, gpu) in &metrics.gpus {
+            let mut gpu = *gpu;
+            for field in 0..GPU_FIELDS {
+                let value = *gpu_field(&mut gpu, field);
+                values.push((self.series_id(Series::Gpu(name.clone(), field)), value));
+            }
+        }
+        for (name, value) in &metrics.custom {
+            values.push((self.series_id(Series::Custom(name.clone())), *value));
+        }
+        if let Some(boot_time) = metrics.boot_time {
+            values.push((self.series_id(Series::BootTime), boot_time.timestamp() as f64));
+        }
+        self.series_values.push_back(values.into_boxed_slice());
+        self.latest = Some(metrics);
+        
+        self.added_since_compaction += 1;
+        if self.added_since_compaction >= self.len().max(COMPACTION_MIN_SAMPLES) {
+            self.compact();
+        }
+    }
+    
+    pub fn pop_front(&mut self) {
+        self.timestamps.pop_front();
+        self.system.pop_front();
+        self.series_values.pop_front();
+        if self.is_empty() {
+            self.latest = None;
+        }
+    }
+    
+    fn series_id(&mut self, series: Series) -> u32 {
+        if let Some(id) = self.series_ids.get(&series) {
+            return *id;
+        }
+        let id = self.series.len() as u32;
+        self.series.push(series.clone());
+        self.series_ids.insert(series, id);
+        id
+    }
+    
+    /// Drops series no sample in the window has, so removed checks and renamed
+    /// metrics do not accumulate.
+    fn compact(&mut self) {
+        self.added_since_compaction = 0;
+        let used: HashSet<u32> = self.series_values.iter().flat_map(|values| values.iter().map(|(id, _)| *id)).collect();
+        if used.len() == self.series.len() {
+            return;
+        }
+        
+        let mut renumbered = HashMap::new();
+        let mut series = Vec::with_capacity(used.len());
+        for (id, entry) in std::mem::take(&mut self.series).into_iter().enumerate() {
+            if used.contains(&(id as u32)) {
+                renumbered.insert(id as u32, series.len() as u32);
+                series.push(entry);
+            }
+        }
+        for values in self.series_values.iter_mut() {
+            for (id, _) in values.iter_mut() {
+                *id = renumbered[id];
+            }
+        }
+        self.series_ids = series.iter().enumerate().map(|(id, entry)| (entry.clone(), id as u32)).collect();
+        self.series = series;
+    }
+}
+
+/// Serialized as the list of samples, rebuilt one at a time as they are written.
+impl Serialize for MetricsWindow {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+/// The system readings of a sample. Single precision is plenty for percentages,
+/// temperatures, latencies and rates.
+fn system_row(metrics: &SystemMetrics) -> [f32; 15] {
+    [
+        metrics.cpu_percent as f32,
+        metrics.ram_percent as f32,
+        metrics.disk_percent as f32,
+        metrics.temperature as f32,
+        metrics.ping_ms as f32,
+        metrics.net_connections as f32,
+        metrics.net_rx_bytes as f32,
+        metrics.net_tx_bytes as f32,
+        metrics.failed_logins as f32,
+        metrics.load_1 as f32,
+        metrics.load_5 as f32,
+        metrics.load_15 as f32,
+        metrics.procs_running as f32,
+        metrics.procs_blocked as f32,
+        metrics.swap_percent as f32,
+    ]
+}
+
+fn disk_field(io: &mut DiskIo, field: u8) -> &mut f64 {
+    match field {
+        0 => &mut io.read_iops,
+        1 => &mut io.write_iops,
+        2 => &mut io.read_bytes_per_sec,
+        3 => &mut io.write_bytes_per_sec,
+        DISK_AWAIT => &mut io.await_ms,
+        5 => &mut io.queue_depth,
+        _ => &mut io.util_percent,
+    }
+}
+
+fn gpu_field(gpu: &mut GpuReading, field: u8) -> &mut f64 {
+    match field {
+        0 => &mut gpu.utilization_percent,
+        1 => &mut gpu.memory_used_mb,
+        2 => &mut gpu.memory_total_mb,
+        _ => &mut gpu.temperature,
+    }
+}
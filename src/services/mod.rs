@@ -1,3 +1,59 @@
+pub mod access_log;
+pub mod accounts;
+pub mod anomaly_hooks;
 pub mod auth;
+pub mod availability;
+pub mod backups;
+pub mod baseline_history;
+pub mod backup;
+pub mod caches;
+pub mod capture;
+pub mod checks;
+pub mod collectors;
+pub mod config;
+pub mod crowdsec;
+pub mod databases;
+pub mod diagnostics;
+pub mod docker;
+pub mod directories;
+pub mod disk_io;
+pub mod dns;
+pub mod events;
+pub mod exec_metrics;
+pub mod file_watch;
+pub mod firewall;
+pub mod flapping;
+pub mod gpu;
+pub mod heartbeats;
+pub mod incidents;
+pub mod ingest;
+pub mod kernel_log;
+pub mod kubernetes;
+pub mod lan_usage;
+pub mod log_viewer;
+pub mod log_watch;
+pub mod metrics_window;
 pub mod monitor;
+pub mod net_usage;
+pub mod outliers;
+pub mod persistence;
+pub mod plugins;
+pub mod pihole;
+pub mod ports;
+pub mod probes;
+pub mod process_watch;
+pub mod proxmox;
+pub mod public_ip;
+pub mod quiet_hours;
+pub mod ssh_keys;
+pub mod status_snapshot;
+pub mod storage;
+pub mod suid;
+pub mod summary_report;
+pub mod support_bundle;
+pub mod supervisor;
+pub mod suricata;
 pub mod threat_intel;
+pub mod web_push;
+pub mod web_status;
+pub mod zeek;
@@ -1,309 +1,805 @@
-use crate::models::metrics::{Anomaly, AnomalySeverity, BaselineStats, SystemMetrics};
-use crate::models::config::MonitoringConfig;
+use crate::models::events::{Event, EventKind};
+use crate::models::incidents::{Incident, TimelineKind};
+use crate::models::feedback::{deserialize_feedback, FeedbackRule};
+use crate::models::metrics::{
+    Anomaly, AnomalySeverity, BaselineStats, CheckResult, DiskIo, HistoryRange, MetricPoint, SystemMetrics,
+    UptimeInfo,
+};
+use crate::models::config::{
+    AlertConfig, AlertRoute, CheckConfig, Detector, DisplayConfig, Firewall, MonitoringConfig, QUIET_HOUR_CHANNELS,
+};
+use crate::services::accounts::{AccountPoll, AccountScan, AccountWatcher};
+use crate::services::anomaly_hooks;
+use crate::services::auth::AuthService;
+use crate::services::availability::AvailabilityLog;
+use crate::services::backups::{BackupPoll, BackupStatuses, BackupWatcher};
+use crate::services::baseline_history::BaselineHistory;
+use crate::services::checks;
+use crate::services::collectors::{CollectorRegistry, CollectorStatus};
+use crate::services::diagnostics;
+use crate::services::dns::{DnsAnswers, DnsPoll, DnsWatcher};
+use crate::services::firewall::{self, OwnBlocks};
+use crate::services::flapping::{FlapChange, FlapDetector};
+use crate::services::kernel_log::{self, KernelLogUpdate, KernelLogWatcher, MemoryState, OomRecord};
+use crate::services::kubernetes::{KubernetesLists, KubernetesPoll, KubernetesWatcher};
+use crate::services::lan_usage::{LanPoll, LanTallies, LanUsageWatcher};
+use crate::services::metrics_window::{MetricsWindow, Series, SystemField, DISK_AWAIT, DISK_UTIL};
+use crate::services::net_usage::{ProcessNetSampler, ProcessNetUsage};
+use crate::services::outliers::{self, MultivariateModel};
+use crate::services::pihole::{PiholePoll, PiholeStatuses, PiholeWatcher};
+use crate::services::ports::{self, PortPoll, PortScan, PortWatcher};
+use crate::services::proxmox::{ProxmoxPoll, ProxmoxResources, ProxmoxWatcher};
+use crate::services::quiet_hours::{self, HeldAlert, NotificationDigest};
+use crate::services::ssh_keys::{AuthorizedKeysPoll, AuthorizedKeysScan, AuthorizedKeysWatcher};
+use crate::services::storage::MetricsStore;
+use crate::services::suricata;
+use crate::services::web_push::WebPush;
+use crate::utils::cidr::{IpNet, PrefixTrie};
+use crate::services::events::EventLog;
+use crate::services::heartbeats::HeartbeatLog;
+use crate::services::incidents::{self, IncidentLog};
+use crate::services::ingest::{IngestedAlert, IngestedAlerts};
+use crate::utils::data_dir;
 use anyhow::{anyhow, Result};
-use chrono::Utc;
+use chrono::{DateTime, Local, Utc};
 use regex::Regex;
-use std::collections::{HashMap, HashSet, VecDeque};
-use std::process::Stdio;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::sync::Arc;
-use sysinfo::{Disks, Networks, System};
+use sysinfo::System;
 use tokio::fs;
 use tokio::process::Command;
 use tokio::sync::Mutex;
 use tracing::{info, warn};
 
-const BASELINE_FILE: &str = "data/baseline.json";
+pub const BASELINE_FILE: &str = "data/baseline.json";
+const UPTIME_FILE: &str = "data/uptime.json";
+const HISTORY_FILE: &str = "data/metrics_history.json";
+const ROLLUP_FILE: &str = "data/metrics_rollup.json";
+// The window is also saved on shutdown; this bounds what a crash loses
+const HISTORY_SAVE_SECS: i64 = 300;
+// A window saved longer ago than this no longer describes the host
+const HISTORY_MAX_AGE_HOURS: i64 = 24;
+// How long one-off events such as an unexpected reboot stay listed as anomalies
+const TRANSIENT_ALERT_SECS: i64 = 3600;
+// Threat IPs are blocked with at most this much of the listed range around them
+const MAX_BLOCK_PREFIX_V4: u8 = 24;
+const MAX_BLOCK_PREFIX_V6: u8 = 64;
+// sysinfo derives boot time from the current uptime, so it can wobble slightly
+const BOOT_TIME_TOLERANCE_SECS: i64 = 60;
+// Long-range history is kept as one-minute averages for the longest dashboard range
+const ROLLUP_BUCKET_SECS: i64 = 60;
+const ROLLUP_RETENTION_DAYS: i64 = 7;
+// Points returned per range request, regardless of range length
+const HISTORY_POINTS: i64 = 120;
+const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+// A flapping critical anomaly should not run ps, ss and du every cycle
+const SNAPSHOT_COOLDOWN_SECS: i64 = 600;
+// Push services ask for a way to reach whoever runs a sender
+pub const DEFAULT_PUSH_CONTACT: &str = "mailto:root@localhost";
+// Smallest deviation treated as significant; loss sits at a flat 0% on healthy links,
+// which would otherwise leave a zero deviation and no way to flag it
+const LOSS_STD_FLOOR: f64 = 5.0;
+const JITTER_STD_FLOOR: f64 = 1.0;
+const DISK_AWAIT_STD_FLOOR: f64 = 2.0;
+const DISK_UTIL_STD_FLOOR: f64 = 5.0;
+// Socket counts that rarely change, such as listeners, still allow for a few
+const TCP_STATE_STD_FLOOR: f64 = 2.0;
+const TCP_STATE_PREFIX: &str = "tcp ";
+// Baseline keys whose readings changed meaning and are dropped wherever saved baselines
+// come in: `net` counted network interfaces before established connections (`conns`)
+const RETIRED_BASELINES: [&str; 1] = ["net"];
+// KB/s; an interface that is usually idle should not alert on a small transfer
+const INTERFACE_STD_FLOOR: f64 = 50.0;
+// Dashboard group for checks without tags
+const UNGROUPED: &str = "other";
 
 pub struct MonitorService {
     config: MonitoringConfig,
-    system: System,
-    metrics_history: VecDeque<SystemMetrics>,
+    /// The configured checks plus those expanded from probes.
+    checks: Vec<CheckConfig>,
+    alerts: AlertConfig,
+    /// Which stats the status report shows, as toggled on the settings page.
+    display: DisplayConfig,
+    /// Run by each cycle's [`Collection`], outside the monitor's lock.
+    collectors: Arc<Mutex<CollectorRegistry>>,
+    /// A reloaded configuration the collectors pick up on their next run.
+    collectors_config: Option<MonitoringConfig>,
+    /// The collectors as of their latest run, and the limits they found breached.
+    collector_statuses: Vec<CollectorStatus>,
+    collector_limits: Vec<String>,
+    metrics_history: MetricsWindow,
+    /// When `metrics_history` was last written to disk.
+    history_saved: Option<DateTime<Utc>>,
+    /// Every sample, kept beyond the window as `retention` allows.
+    archive: Arc<MetricsStore>,
+    own_blocks: Arc<OwnBlocks>,
+    rollup_history: VecDeque<MetricPoint>,
     baselines: HashMap<String, BaselineStats>,
-    feedback: HashMap<String, bool>,
-    current_iocs: HashSet<String>,
+    /// Joint model of the system metrics, learned when the detector uses it.
+    multivariate: Option<MultivariateModel>,
+    /// Value ranges marked as normal despite the baselines.
+    feedback: Vec<FeedbackRule>,
+    /// Metrics whose baseline was reset, and when; they relearn from later samples only.
+    relearning: HashMap<String, DateTime<Utc>>,
+    baseline_history: BaselineHistory,
+    /// Malicious ranges from the threat intelligence blocklists, with the list naming each.
+    indicators: Arc<PrefixTrie<String>>,
+    /// Ranges from `detection.allowlist`, never blocked or reported.
+    allowlist: PrefixTrie<()>,
+    /// Peers of current connections found in `indicators`, with the range and list.
+    current_iocs: Vec<(IpAddr, IpNet, String)>,
+    active_anomalies: HashSet<String>,
+    /// Correlation id, latest message and incident of each active anomaly, by subject,
+    /// for the documents sent to anomaly webhooks.
+    correlation_ids: HashMap<String, (String, String, u64)>,
+    flapping: FlapDetector,
+    /// Alerts held by quiet hours.
+    digest: Arc<NotificationDigest>,
+    last_snapshot: Option<DateTime<Utc>>,
+    // Latest result of each host check and when it ran
+    check_results: HashMap<String, (DateTime<Utc>, CheckResult)>,
+    // Up/down state of each check as last written to the availability log
+    check_states: HashMap<String, bool>,
+    availability: Arc<AvailabilityLog>,
+    heartbeats: Arc<HeartbeatLog>,
+    incidents: Arc<IncidentLog>,
+    web_push: Arc<WebPush>,
+    dns: DnsWatcher,
+    /// Ongoing anomalies maintained by other services, by source.
+    external_anomalies: BTreeMap<&'static str, Vec<String>>,
+    accounts: AccountWatcher,
+    ssh_keys: AuthorizedKeysWatcher,
+    ports: PortWatcher,
+    kernel_log: Arc<Mutex<KernelLogWatcher>>,
+    kubernetes: KubernetesWatcher,
+    proxmox: ProxmoxWatcher,
+    lan_usage: LanUsageWatcher,
+    pihole: PiholeWatcher,
+    backups: BackupWatcher,
+    ingested: IngestedAlerts,
+    process_net: Arc<Mutex<ProcessNetSampler>>,
+    /// Processes that moved the most network traffic in the latest cycle.
+    top_talkers: Vec<ProcessNetUsage>,
+    event_log: Arc<EventLog>,
+    /// Users, for who is sent push notifications.
+    auth: Arc<AuthService>,
     file_lock: Arc<Mutex<()>>,
+    boot_time: DateTime<Utc>,
+    /// Seconds the host had been up at the latest cycle, which unlike the boot
+    /// time does not move with the clock.
+    host_uptime: u64,
+    started_at: DateTime<Utc>,
+    // One-off anomalies (reboots, address changes) and when they were raised
+    transient_anomalies: Vec<(DateTime<Utc>, String)>,
+}
+
+/// The slow part of a cycle: the collectors, which ping the gateway and run
+/// commands, the per-process traffic, the host checks that are due, the kernel log
+/// and the scans of connections, accounts, SSH keys and listening ports. It runs
+/// with the monitor unlocked, so the dashboard and API wait only while its
+/// results are applied by [`MonitorService::update`].
+pub struct Collection {
+    collectors: Arc<Mutex<CollectorRegistry>>,
+    reconfigure: Option<MonitoringConfig>,
+    enabled: BTreeMap<String, bool>,
+    process_net: Option<Arc<Mutex<ProcessNetSampler>>>,
+    checks: Vec<CheckConfig>,
+    dns: DnsPoll,
+    kubernetes: Option<KubernetesPoll>,
+    proxmox: ProxmoxPoll,
+    lan_usage: LanPoll,
+    pihole: PiholePoll,
+    backups: BackupPoll,
+    /// Whether connections are matched against blocklists, which needs their peers.
+    peers: bool,
+    accounts: Option<AccountPoll>,
+    ssh_keys: Option<AuthorizedKeysPoll>,
+    ports: Option<PortPoll>,
+    kernel_log: Arc<Mutex<KernelLogWatcher>>,
+}
+
+/// What a [`Collection`] gathered.
+pub struct Collected {
+    metrics: SystemMetrics,
+    collectors: Vec<CollectorStatus>,
+    limits: Vec<String>,
+    top_talkers: Vec<ProcessNetUsage>,
+    checked_at: DateTime<Utc>,
+    checks: Vec<(CheckConfig, CheckResult)>,
+    dns: DnsAnswers,
+    kubernetes: Option<KubernetesLists>,
+    proxmox: ProxmoxResources,
+    lan_usage: LanTallies,
+    pihole: PiholeStatuses,
+    backups: BackupStatuses,
+    peers: BTreeSet<IpAddr>,
+    accounts: Option<AccountScan>,
+    ssh_keys: Option<AuthorizedKeysScan>,
+    ports: Option<PortScan>,
+    kernel_log: KernelLogUpdate,
+}
+
+impl Collection {
+    pub async fn run(self) -> Collected {
+        let mut metrics = SystemMetrics::new();
+        let collect = async {
+            let (collectors, limits) = {
+                let mut registry = self.collectors.lock().await;
+                if let Some(config) = &self.reconfigure {
+                    registry.configure(config);
+                }
+                registry.collect(&self.enabled, &mut metrics).await;
+                (registry.list(&self.enabled), registry.anomalies(&self.enabled))
+            };
+            
+            // Per-process traffic, so a bandwidth anomaly can name its likely cause
+            let top_talkers = match &self.process_net {
+                Some(sampler) => sampler.lock().await.sample().await,
+                None => Vec::new(),
+            };
+            (collectors, limits, top_talkers)
+        };
+        
+        // Host checks run alongside the collectors rather than after the slowest of them
+        let checked_at = Utc::now();
+        let ((collectors, limits, top_talkers), results) =
+            tokio::join!(collect, futures::future::join_all(self.checks.iter().map(checks::run_check)));
+        
+        // Lookups, cluster APIs and backup repositories can each take seconds, so they
+        // are read here too and only their results are applied under the monitor
+        let kubernetes = async {
+            match self.kubernetes {
+                Some(poll) => Some(poll.run().await),
+                None => None,
+            }
+        };
+        let (dns, kubernetes, proxmox, lan_usage, pihole, backups) = tokio::join!(
+            self.dns.run(),
+            kubernetes,
+            self.proxmox.run(),
+            self.lan_usage.run(),
+            self.pihole.run(),
+            self.backups.run(),
+        );
+        
+        // So are the scans of /proc, the account databases and every home directory
+        let peers = async {
+            if !self.peers {
+                return BTreeSet::new();
+            }
+            tokio::task::spawn_blocking(ports::connected_peers).await.unwrap_or_default()
+        };
+        let (peers, accounts, ssh_keys, ports, kernel_log) = tokio::join!(
+            peers,
+            async {
+                match self.accounts {
+                    Some(poll) => poll.run().await,
+                    None => None,
+                }
+            },
+            async {
+                match self.ssh_keys {
+                    Some(poll) => poll.run().await,
+                    None => None,
+                }
+            },
+            async {
+                match self.ports {
+                    Some(poll) => poll.run().await,
+                    None => None,
+                }
+            },
+            // Read every cycle so nothing queues up while disabled
+            async { self.kernel_log.lock().await.poll().await },
+        );
+        
+        Collected {
+            metrics,
+            collectors,
+            limits,
+            top_talkers,
+            checked_at,
+            checks: self.checks.into_iter().zip(results).collect(),
+            dns,
+            kubernetes,
+            proxmox,
+            lan_usage,
+            pihole,
+            backups,
+            peers,
+            accounts,
+            ssh_keys,
+            ports,
+            kernel_log,
+        }
+    }
 }
 
 impl MonitorService {
-    pub fn new(config: MonitoringConfig) -> Self {
+    pub fn new(
+        config: MonitoringConfig,
+        alerts: AlertConfig,
+        display: DisplayConfig,
+        event_log: Arc<EventLog>,
+        auth: Arc<AuthService>,
+    ) -> Self {
+        let lan_usage = LanUsageWatcher::new();
+        let flapping = FlapDetector::new(&alerts.flapping);
+        let mut collectors = CollectorRegistry::new(lan_usage.tally());
+        collectors.configure(&config);
         let mut service = Self {
+            checks: config.all_checks(),
             config,
-            system: System::new_all(),
-            metrics_history: VecDeque::with_capacity(100),
+            alerts,
+            display,
+            collectors: Arc::new(Mutex::new(collectors)),
+            collectors_config: None,
+            collector_statuses: Vec::new(),
+            collector_limits: Vec::new(),
+            metrics_history: MetricsWindow::new(),
+            history_saved: None,
+            archive: Arc::new(MetricsStore::new()),
+            own_blocks: Arc::new(OwnBlocks::new()),
+            rollup_history: VecDeque::new(),
             baselines: HashMap::new(),
-            feedback: HashMap::new(),
-            current_iocs: HashSet::new(),
+            multivariate: None,
+            feedback: Vec::new(),
+            relearning: HashMap::new(),
+            baseline_history: BaselineHistory::new(),
+            indicators: Arc::new(PrefixTrie::new()),
+            allowlist: PrefixTrie::new(),
+            current_iocs: Vec::new(),
+            active_anomalies: HashSet::new(),
+            correlation_ids: HashMap::new(),
+            flapping,
+            digest: Arc::new(NotificationDigest::new()),
+            last_snapshot: None,
+            check_results: HashMap::new(),
+            check_states: HashMap::new(),
+            availability: Arc::new(AvailabilityLog::new()),
+            heartbeats: Arc::new(HeartbeatLog::new()),
+            incidents: Arc::new(IncidentLog::new()),
+            web_push: Arc::new(WebPush::new()),
+            dns: DnsWatcher::new(),
+            external_anomalies: BTreeMap::new(),
+            accounts: AccountWatcher::new(),
+            ssh_keys: AuthorizedKeysWatcher::new(),
+            ports: PortWatcher::new(),
+            kernel_log: Arc::new(Mutex::new(KernelLogWatcher::new())),
+            kubernetes: KubernetesWatcher::new(),
+            proxmox: ProxmoxWatcher::new(),
+            lan_usage,
+            pihole: PiholeWatcher::new(),
+            backups: BackupWatcher::new(),
+            ingested: IngestedAlerts::new(),
+            process_net: Arc::new(Mutex::new(ProcessNetSampler::new())),
+            top_talkers: Vec::new(),
+            event_log,
+            auth,
             file_lock: Arc::new(Mutex::new(())),
+            boot_time: DateTime::from_timestamp(System::boot_time() as i64, 0).unwrap_or_else(Utc::now),
+            host_uptime: System::uptime(),
+            started_at: Utc::now(),
+            transient_anomalies: Vec::new(),
         };
         
+        service.allowlist = allowlist(&service.config);
+        
         // Load existing baseline if available
         let _ = service.load_baseline();
+        if let Err(e) = service.load_history() {
+            warn!("Failed to load the metrics history: {}", e);
+        }
+        if let Err(e) = service.load_rollup() {
+            warn!("Failed to load the long-range history: {}", e);
+        }
+        
+        service.check_reboot();
         
         service
     }
     
-    pub async fn update(&mut self) -> Result<()> {
-        self.system.refresh_all();
-        
-        let mut metrics = SystemMetrics::new();
-        
-        // CPU usage
-        metrics.cpu_percent = self.system.global_cpu_info().cpu_usage() as f64;
-        
-        // RAM usage
-        let total_memory = self.system.total_memory() as f64;
-        let used_memory = self.system.used_memory() as f64;
-        if total_memory > 0.0 {
-            metrics.ram_percent = (used_memory / total_memory) * 100.0;
+    /// Applies a reloaded configuration. History beyond a reduced window is discarded
+    /// and baselines of checks no longer configured are dropped.
+    pub fn apply_config(&mut self, config: MonitoringConfig, alerts: AlertConfig, display: DisplayConfig) {
+        while self.metrics_history.len() > config.window_size.max(1) {
+            self.metrics_history.pop_front();
         }
         
-        // Disk usage
-        let disks = Disks::new_with_refreshed_list();
-        let mut total_space = 0u64;
-        let mut used_space = 0u64;
-        for disk in &disks {
-            total_space += disk.total_space();
-            used_space += disk.total_space() - disk.available_space();
-        }
-        if total_space > 0 {
-            metrics.disk_percent = (used_space as f64 / total_space as f64) * 100.0;
+        let checks = config.all_checks();
+        for check in &self.checks {
+            if !checks.iter().any(|c| c.name == check.name) {
+                self.baselines.remove(&check.name);
+                self.check_results.remove(&check.name);
+                self.check_states.remove(&check.name);
+            }
         }
+        self.checks = checks;
         
-        // Temperature
-        metrics.temperature = self.get_temperature().await;
-        
-        // Network connections
-        let networks = Networks::new_with_refreshed_list();
-        metrics.net_connections = networks.len();
-        
-        // Ping gateway
-        metrics.ping_ms = self.ping_gateway().await;
-        
-        // Failed logins
-        metrics.failed_logins = self.failed_logins().await;
+        self.dns.retain(&config.dns_records);
+        self.collectors_config = Some(config.clone());
+        self.allowlist = allowlist(&config);
+        self.config = config;
+        self.flapping.update_config(&alerts.flapping);
+        self.alerts = alerts;
+        self.display = display;
+    }
+    
+    /// Compares the host boot time with the one seen by the previous run.
+    fn check_reboot(&mut self) {
+        let previous_boot = std::fs::read_to_string(UPTIME_FILE)
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|data| data.get("boot_time").and_then(|b| b.as_str()).map(str::to_string))
+            .and_then(|boot| DateTime::parse_from_rfc3339(&boot).ok())
+            .map(|boot| boot.with_timezone(&Utc));
         
-        // Host status
-        for host in &self.config.monitored_hosts {
-            let ping_time = self.ping_host(host).await;
-            metrics.host_status.insert(host.clone(), ping_time);
+        if let Some(previous_boot) = previous_boot {
+            let rebooted = (self.boot_time - previous_boot).num_seconds().abs() > BOOT_TIME_TOLERANCE_SECS;
+            self.report_reboot(rebooted);
         }
-        
-        // Add to history
-        if self.metrics_history.len() >= self.config.window_size {
-            self.metrics_history.pop_front();
+        self.save_boot_time();
+    }
+    
+    /// Follows the boot time sampled each cycle. The kernel derives it from the wall
+    /// clock, so it also moves when the clock is set, e.g. by NTP on a Pi without a
+    /// real-time clock; it is a reboot only when the host uptime went back as well.
+    fn track_boot_time(&mut self, sampled: Option<DateTime<Utc>>) {
+        let uptime = System::uptime();
+        let uptime_reset = uptime < self.host_uptime;
+        self.host_uptime = uptime;
+        let Some(boot_time) = sampled else {
+            return;
+        };
+        if (boot_time - self.boot_time).num_seconds().abs() <= BOOT_TIME_TOLERANCE_SECS {
+            return;
         }
-        self.metrics_history.push_back(metrics);
         
-        Ok(())
+        if !uptime_reset {
+            info!("Boot time moved from {} to {} with the system clock", self.boot_time, boot_time);
+        }
+        self.boot_time = boot_time;
+        self.report_reboot(uptime_reset);
+        self.save_boot_time();
     }
     
-    async fn get_temperature(&self) -> f64 {
-        // Try Raspberry Pi vcgencmd first
-        if let Ok(output) = Command::new("vcgencmd")
-            .args(["measure_temp"])
-            .output()
-            .await
-        {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            if let Some(temp_str) = stdout.split('=').nth(1) {
-                let temp_clean = temp_str.replace("'C", "").trim().to_string();
-                if let Ok(temp) = temp_clean.parse::<f64>() {
-                    return temp;
-                }
-            }
-        }
+    /// Flags a reboot that did not happen inside a planned maintenance window.
+    fn report_reboot(&mut self, rebooted: bool) {
+        let planned = self
+            .config
+            .maintenance_windows
+            .iter()
+            .find(|window| window.tags.is_empty() && window.contains(self.boot_time));
         
-        // Try thermal zone files
-        for i in 0..5 {
-            let path = format!("/sys/class/thermal/thermal_zone{}/temp", i);
-            if let Ok(content) = fs::read_to_string(&path).await {
-                if let Ok(temp_milli) = content.trim().parse::<f64>() {
-                    return temp_milli / 1000.0;
-                }
+        match (rebooted, planned) {
+            (true, Some(window)) => info!("Host rebooted during maintenance window '{}'", window.name),
+            (true, None) => {
+                warn!("Unexpected reboot detected (booted {})", self.boot_time);
+                self.raise_transient(format!(
+                    "Unexpected Reboot: host booted at {}",
+                    self.boot_time.format("%Y-%m-%d %H:%M:%S UTC")
+                ));
             }
+            _ => {}
         }
-        
-        // Simulate temperature based on CPU usage
-        let cpu = self.system.global_cpu_info().cpu_usage() as f64;
-        let base_temp = 35.0 + (cpu * 0.3);
-        let variation = (chrono::Utc::now().timestamp() as f64 / 100.0).sin() * 5.0;
-        (base_temp + variation).round()
     }
     
-    async fn ping_gateway(&self) -> f64 {
-        // Determine gateway
-        let gateway = self.get_default_gateway().await;
-        self.ping_host(&gateway).await
+    fn save_boot_time(&self) {
+        let _ = data_dir::ensure();
+        let data = serde_json::json!({ "boot_time": self.boot_time.to_rfc3339() });
+        if let Err(e) = std::fs::write(UPTIME_FILE, data.to_string()) {
+            warn!("Failed to record boot time: {}", e);
+        }
     }
     
-    async fn get_default_gateway(&self) -> String {
-        // Try to get default gateway from routing table
-        #[cfg(target_os = "linux")]
-        {
-            if let Ok(output) = Command::new("ip")
-                .args(["route", "show", "default"])
-                .output()
-                .await
-            {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                for line in stdout.lines() {
-                    if let Some(gw) = line.split_whitespace().nth(2) {
-                        return gw.to_string();
-                    }
-                }
-            }
+    /// Replaces the blocklist ranges that connections are matched against.
+    pub fn set_indicators(&mut self, indicators: Arc<PrefixTrie<String>>) {
+        self.indicators = indicators;
+    }
+    
+    /// Lists a one-off event as an anomaly for the next hour, so it goes through the
+    /// usual alerting even though there is no ongoing condition to detect.
+    pub fn raise_transient(&mut self, anomaly: String) {
+        let now = Utc::now();
+        // Raising the same event again restarts its hour rather than listing it twice
+        self.transient_anomalies.retain(|(raised_at, raised)| {
+            (now - *raised_at).num_seconds() < TRANSIENT_ALERT_SECS && *raised != anomaly
+        });
+        self.transient_anomalies.push((now, anomaly));
+    }
+    
+    /// Replaces the ongoing anomalies reported by a service that runs outside the
+    /// collection cycle, e.g. the blocklist listings of the public IP service.
+    pub fn set_external_anomalies(&mut self, source: &'static str, anomalies: Vec<String>) {
+        self.external_anomalies.insert(source, anomalies);
+    }
+    
+    pub fn external_anomalies(&self, source: &str) -> &[String] {
+        self.external_anomalies.get(source).map(Vec::as_slice).unwrap_or_default()
+    }
+    
+    /// Takes alerts forwarded by other monitoring systems.
+    pub fn ingest(&mut self, alerts: Vec<IngestedAlert>) {
+        self.ingested.apply(alerts);
+    }
+    
+    pub fn uptime(&self) -> UptimeInfo {
+        let now = Utc::now();
+        UptimeInfo {
+            boot_time: self.boot_time,
+            uptime_secs: (now - self.boot_time).num_seconds(),
+            monitor_started: self.started_at,
+            monitor_uptime_secs: (now - self.started_at).num_seconds(),
+        }
+    }
+    
+    pub fn update_interval(&self) -> u64 {
+        self.config.update_interval.max(1)
+    }
+    
+    /// The slow part of the next cycle, to be run without holding the monitor.
+    pub fn collection(&mut self) -> Collection {
+        let now = Utc::now();
+        Collection {
+            collectors: Arc::clone(&self.collectors),
+            reconfigure: self.collectors_config.take(),
+            enabled: self.config.collectors.clone(),
+            process_net: self.config.process_network.then(|| Arc::clone(&self.process_net)),
+            // Host checks run on their own schedules; between runs the last result carries over
+            checks: self
+                .checks
+                .iter()
+                .filter(|check| self.check_due(check, now))
+                .cloned()
+                .collect(),
+            dns: self.dns.next_poll(&self.config.dns_records),
+            kubernetes: self.kubernetes.next_poll(&self.config.kubernetes),
+            proxmox: self.proxmox.next_poll(&self.config.proxmox),
+            lan_usage: self.lan_usage.next_poll(&self.config.capture),
+            pihole: self.pihole.next_poll(&self.config.pihole),
+            backups: self.backups.next_poll(&self.config.backups),
+            peers: !self.indicators.is_empty(),
+            accounts: self.config.detection.accounts.then(|| self.accounts.next_poll()),
+            ssh_keys: self.config.detection.authorized_keys.then(|| self.ssh_keys.next_poll()),
+            ports: self
+                .config
+                .detection
+                .listening_ports
+                .then(|| self.ports.next_poll(&self.config.detection.ignore_ports)),
+            kernel_log: Arc::clone(&self.kernel_log),
         }
-        
-        // Fallback to common gateway addresses
-        "192.168.1.1".to_string()
     }
     
-    async fn ping_host(&self, host: &str) -> f64 {
-        // Use system ping command
-        let cmd = if cfg!(target_os = "windows") {
-            vec!["ping", "-n", "1", "-w", "1000", host]
+    /// Completes a cycle with what its collection gathered.
+    pub async fn update(&mut self, collected: Collected) -> Result<()> {
+        let Collected {
+            mut metrics,
+            collectors,
+            limits,
+            top_talkers,
+            checked_at,
+            checks: results,
+            dns,
+            kubernetes,
+            proxmox,
+            lan_usage,
+            pihole,
+            backups,
+            peers,
+            accounts,
+            ssh_keys,
+            ports,
+            kernel_log: kernel_update,
+        } = collected;
+        self.collector_statuses = collectors;
+        self.collector_limits = limits;
+        self.top_talkers = top_talkers;
+        
+        // Connections with addresses on a blocklist
+        self.current_iocs = if self.indicators.is_empty() {
+            Vec::new()
         } else {
-            vec!["ping", "-c", "1", "-W", "1", host]
+            peers
+                .into_iter()
+                .filter(|ip| !self.allowlist.contains(*ip) && !IpNet::host(*ip).is_private())
+                .filter_map(|ip| self.indicators.get(ip).map(|(range, list)| (ip, range, list.clone())))
+                .collect()
         };
         
-        match Command::new(cmd[0])
-            .args(&cmd[1..])
-            .stdout(Stdio::piped())
-            .stderr(Stdio::null())
-            .output()
-            .await
-        {
-            Ok(output) if output.status.success() => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                // Parse ping time from output
-                if let Some(time_ms) = parse_ping_time(&stdout) {
-                    return time_ms;
-                }
+        for (check, result) in results {
+            // A reload may have removed the check while it ran
+            if !self.checks.iter().any(|c| c.name == check.name) {
+                continue;
+            }
+            self.check_results.insert(check.name.clone(), (checked_at, result));
+            
+            let up = result.is_up();
+            if self.check_states.insert(check.name.clone(), up) != Some(up) {
+                self.availability.record(&check.name, up).await;
+            }
+        }
+        for check in &self.checks {
+            if let Some((_, result)) = self.check_results.get(&check.name) {
+                metrics.host_status.insert(check.name.clone(), *result);
             }
-            _ => {}
         }
         
-        // Fallback: try TCP connection
-        self.tcp_ping(host).await
-    }
-    
-    async fn tcp_ping(&self, host: &str) -> f64 {
-        use tokio::net::TcpStream;
-        use tokio::time::{timeout, Duration};
+        self.track_boot_time(metrics.boot_time);
         
-        let start = tokio::time::Instant::now();
+        // DNS records; a learned record changing is a one-off event
+        for change in self.dns.apply(dns, &self.config.dns_records).await {
+            warn!("{}", change);
+            self.raise_transient(change);
+        }
         
-        match timeout(Duration::from_secs(2), TcpStream::connect((host, 80))).await {
-            Ok(Ok(_)) => {
-                let elapsed = start.elapsed().as_millis() as f64;
-                return elapsed;
+        // New users and privileged group members
+        if let Some(scan) = accounts.filter(|_| self.config.detection.accounts) {
+            for alert in self.accounts.apply(scan, &self.config.detection.sensitive_groups) {
+                warn!("{}", alert);
+                self.raise_transient(alert);
             }
-            _ => {
-                // Try DNS resolution
-                match dns_lookup::lookup_host(host) {
-                    Ok(_) => 50.0, // Assume reasonable latency
-                    Err(_) => -1.0,
-                }
+        }
+        
+        // Keys added to any account
+        if let Some(scan) = ssh_keys.filter(|_| self.config.detection.authorized_keys) {
+            for alert in self.ssh_keys.apply(scan) {
+                warn!("{}", alert);
+                self.raise_transient(alert);
             }
         }
-    }
-    
-    async fn failed_logins(&self) -> u32 {
-        let log_files = vec![
-            "/var/log/auth.log",
-            "/var/log/secure",
-            "/var/log/messages",
-        ];
         
-        let mut count = 0u32;
+        // Services or backdoors that started listening
+        if let Some(scan) = ports.filter(|_| self.config.detection.listening_ports) {
+            for alert in self.ports.apply(scan) {
+                warn!("{}", alert);
+                self.raise_transient(alert);
+            }
+        }
         
-        for log_file in &log_files {
-            if let Ok(content) = fs::read_to_string(log_file).await {
-                let lines: Vec<&str> = content.lines().collect();
-                let recent_lines = lines.iter().rev().take(500);
+        // Hardware and kernel trouble
+        if self.config.detection.kernel_log {
+            for alert in kernel_update.alerts {
+                warn!("{}", alert);
+                self.raise_transient(alert);
+            }
+            
+            // The memory pressure is usually gone by the next cycle, so keep the evidence
+            for kill in kernel_update.oom_kills {
+                let rss = kill.anon_rss_kb.map(|kb| format!(" rss={}MB", kb / 1024)).unwrap_or_default();
+                let alert = format!("OOM Kill: {} pid={}{}", kill.process, kill.pid, rss);
+                warn!("{}", alert);
                 
-                for line in recent_lines {
-                    if line.contains("Failed password") && !line.contains("invalid user") {
-                        count += 1;
-                    }
+                let record = OomRecord {
+                    timestamp: Utc::now(),
+                    kill,
+                    memory: Self::memory_state(),
+                };
+                if let Err(e) = kernel_log::record_oom_kill(&record).await {
+                    warn!("Failed to record OOM kill: {}", e);
                 }
+                self.raise_transient(alert);
             }
         }
         
-        // Simulate occasional failed logins if no logs available
-        if count == 0 {
-            use rand::Rng;
-            let mut rng = rand::thread_rng();
-            if rng.gen_bool(0.1) {
-                count = rng.gen_range(0..3);
-            }
+        // Cluster health; restarted containers are one-off events
+        for alert in kubernetes.map(|lists| self.kubernetes.apply(lists)).unwrap_or_default() {
+            warn!("{}", alert);
+            self.raise_transient(alert);
         }
         
-        count
-    }
-    
-    pub fn learn_baseline(&mut self) {
-        if self.metrics_history.len() < 20 {
-            return;
+        // Proxmox nodes and guests; a guest stopping is a one-off event
+        for alert in self.proxmox.apply(proxmox, &self.config.proxmox) {
+            warn!("{}", alert);
+            self.raise_transient(alert);
         }
         
-        // Learn CPU baseline
-        let cpu_values: Vec<f64> = self.metrics_history.iter().map(|m| m.cpu_percent).collect();
-        if let Some(stats) = calculate_stats(&cpu_values) {
-            self.baselines.insert("cpu".to_string(), stats);
-        }
+        // Captured traffic of LAN devices
+        self.lan_usage.apply(lan_usage, &self.config.capture);
         
-        // Learn RAM baseline
-        let ram_values: Vec<f64> = self.metrics_history.iter().map(|m| m.ram_percent).collect();
-        if let Some(stats) = calculate_stats(&ram_values) {
-            self.baselines.insert("ram".to_string(), stats);
-        }
+        // DNS filtering health
+        self.pihole.apply(pihole, &self.config.pihole);
         
-        // Learn Disk baseline
-        let disk_values: Vec<f64> = self.metrics_history.iter().map(|m| m.disk_percent).collect();
-        if let Some(stats) = calculate_stats(&disk_values) {
-            self.baselines.insert("disk".to_string(), stats);
-        }
+        // Backups that stopped happening
+        self.backups.apply(backups, &self.config.backups);
+        self.heartbeats.register(&self.config.heartbeats).await;
+        
+        self.record_rollup(&metrics);
         
-        // Learn Temperature baseline
-        let temp_values: Vec<f64> = self.metrics_history.iter().map(|m| m.temperature).collect();
-        if let Some(stats) = calculate_stats(&temp_values) {
-            self.baselines.insert("temp".to_string(), stats);
+        // Written in the background so a slow disk never holds up the monitor
+        let archive = Arc::clone(&self.archive);
+        let (sample, retention) = (metrics.clone(), self.config.retention.clone());
+        tokio::spawn(async move { archive.append(&sample, &retention).await });
+        
+        // Add to history
+        if self.metrics_history.len() >= self.config.window_size {
+            self.metrics_history.pop_front();
         }
+        self.metrics_history.push_back(metrics);
         
-        // Learn Ping baseline
-        let ping_values: Vec<f64> = self.metrics_history.iter().map(|m| m.ping_ms).collect();
-        if let Some(stats) = calculate_stats(&ping_values) {
-            self.baselines.insert("ping".to_string(), stats);
+        Ok(())
+    }
+    
+    fn check_due(&self, check: &CheckConfig, now: DateTime<Utc>) -> bool {
+        let interval = check.interval.unwrap_or(self.config.update_interval) as i64;
+        match self.check_results.get(&check.name) {
+            // Half a second of slack so a check on the update interval isn't skipped by timer jitter
+            Some((last_run, _)) => (now - *last_run).num_milliseconds() + 500 >= interval * 1000,
+            None => true,
         }
+    }
+    
+    fn memory_state() -> MemoryState {
+        // Only needed after an OOM kill, so the process table is read on demand
+        let system = System::new_all();
+        let mut processes: Vec<(u32, String, u64)> = system
+            .processes()
+            .iter()
+            .filter(|(_, process)| process.thread_kind().is_none())
+            .map(|(pid, process)| (pid.as_u32(), process.name().to_string(), process.memory()))
+            .collect();
+        processes.sort_by_key(|process| std::cmp::Reverse(process.2));
+        processes.truncate(5);
         
-        // Learn Net baseline
-        let net_values: Vec<f64> = self.metrics_history.iter().map(|m| m.net_connections as f64).collect();
-        if let Some(stats) = calculate_stats(&net_values) {
-            self.baselines.insert("net".to_string(), stats);
+        MemoryState {
+            total_bytes: system.total_memory(),
+            used_bytes: system.used_memory(),
+            available_bytes: system.available_memory(),
+            swap_total_bytes: system.total_swap(),
+            swap_used_bytes: system.used_swap(),
+            top_processes: processes,
+        }
+    }
+    
+    pub fn learn_baseline(&mut self) {
+        if self.metrics_history.len() < 20 {
+            return;
         }
         
-        // Learn Failed Logins baseline
-        let fail_values: Vec<f64> = self.metrics_history.iter().map(|m| m.failed_logins as f64).collect();
-        if let Some(stats) = calculate_stats(&fail_values) {
-            self.baselines.insert("fail".to_string(), stats);
+        let window = &self.metrics_history;
+        // A metric reset by hand learns only from samples taken since, until none older remain
+        if let Some(oldest) = window.timestamp(0) {
+            self.relearning.retain(|_, since| oldest <= *since);
         }
         
-        // Learn baselines for monitored hosts
-        for host in &self.config.monitored_hosts {
-            let values: Vec<f64> = self.metrics_history
-                .iter()
-                .filter_map(|m| m.host_status.get(host).copied())
-                .collect();
-            
-            if let Some(stats) = calculate_stats(&values) {
-                self.baselines.insert(host.clone(), stats);
+        let mut learned = learn_stats(window, 0, &self.checks);
+        for (key, since) in &self.relearning {
+            learned.remove(key);
+            let from = window.position_after(*since);
+            if window.len() - from >= 20 {
+                if let Some(stats) = learn_stats(window, from, &self.checks).remove(key) {
+                    learned.insert(key.clone(), stats);
+                }
+            }
+        }
+        for (key, stats) in learned {
+            if !self.baselines.get(&key).is_some_and(|current| current.frozen) {
+                self.baselines.insert(key, stats);
             }
         }
+        
+        self.multivariate = match self.config.detector {
+            Detector::Zscore => None,
+            Detector::Mahalanobis | Detector::Both => {
+                // The latest sample is judged against the ones before it, not against itself
+                let past = window.len() - 1;
+                let from = past.saturating_sub(outliers::MAX_SAMPLES);
+                let columns: Vec<Vec<f64>> = SYSTEM_READINGS
+                    .iter()
+                    .map(|(_, field, scale, _)| window.column(*field, from).take(past - from).map(|v| v * scale).collect())
+                    .collect();
+                let samples: Vec<Vec<f64>> = (0..past - from)
+                    .map(|index| columns.iter().map(|column| column[index]).collect())
+                    .collect();
+                let labels = SYSTEM_READINGS.map(|(_, _, _, label)| label);
+                MultivariateModel::fit(&labels, &samples)
+            }
+        };
     }
     
     pub fn detect_anomalies(&self) -> (Vec<String>, bool) {
-        if self.metrics_history.len() < 20 {
+        // Baselines saved by a previous run or imported need no learning period
+        if self.metrics_history.is_empty() || (self.metrics_history.len() < 20 && self.baselines.is_empty()) {
             return (vec!["Learning...".to_string()], false);
         }
         
@@ -311,49 +807,214 @@ impl MonitorService {
         let latest = self.metrics_history.back().unwrap();
         let threshold = self.config.anomaly_threshold;
         
-        // Check system metrics
-        let checks = vec![
-            ("cpu", latest.cpu_percent, "CPU"),
-            ("ram", latest.ram_percent, "RAM"),
-            ("disk", latest.disk_percent, "Disk"),
-            ("temp", latest.temperature, "Temp"),
-            ("ping", latest.ping_ms, "Ping"),
-            ("net", latest.net_connections as f64, "Connections"),
-            ("fail", latest.failed_logins as f64, "Failed Login"),
-        ];
-        
-        for (metric, value, label) in checks {
+        // Check system metrics, one by one and/or together
+        let readings = system_readings(latest);
+        let per_metric = if self.config.detector == Detector::Mahalanobis { &readings[..0] } else { &readings[..] };
+        
+        for &(metric, value, label) in per_metric {
             if let Some(baseline) = self.baselines.get(metric) {
-                let feedback_key = format!("{}-{:.0}", metric, value);
                 if baseline.std > 0.0 
                     && (value - baseline.mean).abs() > threshold * baseline.std
-                    && !self.feedback.get(&feedback_key).copied().unwrap_or(false) 
+                    && !self.marked_normal(metric, value)
                 {
-                    anomalies.push(format!(
+                    let mut anomaly = format!(
                         "Anomaly: {} {:.1} (Normal: {:.1}±{:.1})",
                         label, value, baseline.mean, baseline.std
+                    );
+                    // Name the process behind a bandwidth spike when it stands out
+                    if let ("rx" | "tx", Some(top)) = (metric, self.top_talkers.first()) {
+                        let total: u64 = self.top_talkers.iter().map(|usage| usage.bytes).sum();
+                        if top.bytes * 2 > total {
+                            anomaly.push_str(&format!(" mostly {} (pid {})", top.process, top.pid));
+                        }
+                    }
+                    anomalies.push(anomaly);
+                }
+            }
+        }
+        
+        if let Some(model) = &self.multivariate {
+            if let Some(outlier) = model.outlier(&readings.map(|(_, value, _)| value), threshold) {
+                anomalies.push(format!(
+                    "Anomaly: Metric Combination {:.1} (Normal: {:.1}±{:.1}) driven by {}",
+                    outlier.distance,
+                    outlier.normal_mean,
+                    outlier.normal_std,
+                    outlier.drivers.join(", ")
+                ));
+            }
+        }
+        
+        // Configured limits take precedence over the baseline
+        let limits: Vec<String> = self
+            .collector_limits
+            .iter()
+            .map(|anomaly| self.mark_listed(anomaly.clone()))
+            .collect();
+        for (name, &value) in &latest.custom {
+            if limits.iter().any(|anomaly| anomaly_subject(anomaly) == format!("Anomaly: {}", name)) {
+                continue;
+            }
+            let key = custom_key(name);
+            if let Some(baseline) = self.baselines.get(&key) {
+                if baseline.std > 0.0
+                    && (value - baseline.mean).abs() > threshold * baseline.std
+                    && !self.marked_normal(&key, value)
+                {
+                    anomalies.push(format!(
+                        "Anomaly: {} {:.1} (Normal: {:.1}±{:.1})",
+                        name, value, baseline.mean, baseline.std
                     ));
                 }
             }
         }
         
-        // Check host status
-        for (host, &ping_time) in &latest.host_status {
+        // Check host status, skipping checks under maintenance
+        let now = Utc::now();
+        for (host, result) in &latest.host_status {
+            let check = self.check(host);
+            let in_maintenance = check.is_some_and(|check| {
+                self.config.maintenance_windows.iter().any(|window| window.covers(check, now))
+            });
+            if in_maintenance {
+                continue;
+            }
+            
+            // Per-check overrides of the global threshold and expectations
+            let host_threshold = check.and_then(|c| c.threshold).unwrap_or(threshold);
+            let expected_latency = check.and_then(|c| c.expected_latency);
+            let allow_down = check.is_some_and(|c| c.allow_down);
+            let ping_time = result.latency_ms;
+            
             if ping_time < 0.0 {
-                anomalies.push(format!("Device Down: {}", host));
+                if !allow_down {
+                    anomalies.push(format!("Device Down: {}", host));
+                }
+                continue;
+            }
+            
+            if let Some(limit) = expected_latency.filter(|&limit| ping_time > limit) {
+                anomalies.push(format!("Anomaly: {} {:.1}ms (Normal: <{:.1})", host, ping_time, limit));
             } else if let Some(baseline) = self.baselines.get(host) {
-                if baseline.std > 0.0 && (ping_time - baseline.mean).abs() > threshold * baseline.std {
+                if baseline.std > 0.0 && (ping_time - baseline.mean).abs() > host_threshold * baseline.std {
                     anomalies.push(format!(
                         "Anomaly: {} {:.1}ms (Normal: {:.1}±{:.1})",
                         host, ping_time, baseline.mean, baseline.std
                     ));
                 }
             }
+            
+            // Only a rise in loss or jitter is a problem
+            let quality = [
+                (loss_key(host), result.loss_percent, LOSS_STD_FLOOR, "%"),
+                (jitter_key(host), result.jitter_ms, JITTER_STD_FLOOR, "ms"),
+            ];
+            for (key, value, floor, unit) in quality {
+                if let Some(baseline) = self.baselines.get(&key) {
+                    if value - baseline.mean > host_threshold * baseline.std.max(floor) {
+                        anomalies.push(format!(
+                            "Anomaly: {} {:.1}{} (Normal: {:.1}±{:.1})",
+                            key, value, unit, baseline.mean, baseline.std
+                        ));
+                    }
+                }
+            }
+        }
+        
+        // Only a rise in disk latency or utilisation is a problem
+        let mut devices: Vec<(&String, &DiskIo)> = latest.disk_io.iter().collect();
+        devices.sort_by_key(|(device, _)| *device);
+        for (device, io) in devices {
+            let measures = [
+                (disk_key(device, "await"), io.await_ms, DISK_AWAIT_STD_FLOOR, "ms"),
+                (disk_key(device, "util"), io.util_percent, DISK_UTIL_STD_FLOOR, "%"),
+            ];
+            for (key, value, floor, unit) in measures {
+                if let Some(baseline) = self.baselines.get(&key) {
+                    if value - baseline.mean > threshold * baseline.std.max(floor) {
+                        anomalies.push(format!(
+                            "Anomaly: {} {:.1}{} (Normal: {:.1}±{:.1})",
+                            key, value, unit, baseline.mean, baseline.std
+                        ));
+                    }
+                }
+            }
+        }
+        
+        // Connections in each TCP state, established ones being the `conns` baseline:
+        // a pile of SYN_RECV suggests a flood, fewer LISTEN a service gone
+        // (a state without sockets is missing from the sample, unless none were read)
+        if !latest.tcp_states.is_empty() {
+            let mut states: Vec<(&String, &BaselineStats)> =
+                self.baselines.iter().filter(|(key, _)| key.starts_with(TCP_STATE_PREFIX)).collect();
+            states.sort_by_key(|(key, _)| *key);
+            for (key, baseline) in states {
+                let state = &key[TCP_STATE_PREFIX.len()..];
+                let count = latest.tcp_states.get(state).copied().unwrap_or(0);
+                let value = count as f64;
+                if (value - baseline.mean).abs() > threshold * baseline.std.max(TCP_STATE_STD_FLOOR)
+                    && !self.marked_normal(key, value)
+                {
+                    anomalies.push(format!(
+                        "Anomaly: {} {} (Normal: {:.1}±{:.1})",
+                        key, count, baseline.mean, baseline.std
+                    ));
+                }
+            }
+        }
+        
+        // Only a rise in traffic is a problem; a burst of outbound traffic on one
+        // interface can be data leaving the network
+        for (name, rate) in &latest.interfaces {
+            let directions = [
+                (interface_key(name, "rx"), rate.rx_bytes_per_sec / 1024.0),
+                (interface_key(name, "tx"), rate.tx_bytes_per_sec / 1024.0),
+            ];
+            for (key, value) in directions {
+                if let Some(baseline) = self.baselines.get(&key) {
+                    if value - baseline.mean > threshold * baseline.std.max(INTERFACE_STD_FLOOR)
+                        && !self.marked_normal(&key, value)
+                    {
+                        let mut anomaly = format!(
+                            "Anomaly: {} {:.1}KB/s (Normal: {:.1}±{:.1})",
+                            key, value, baseline.mean, baseline.std
+                        );
+                        if let Some(top) = self.top_talkers.first() {
+                            let total: u64 = self.top_talkers.iter().map(|usage| usage.bytes).sum();
+                            if top.bytes * 2 > total {
+                                anomaly.push_str(&format!(" mostly {} (pid {})", top.process, top.pid));
+                            }
+                        }
+                        anomalies.push(anomaly);
+                    }
+                }
+            }
         }
         
         // Check for threat IPs
-        for ip in &self.current_iocs {
-            anomalies.push(format!("Threat IP: {}", ip));
+        for (ip, range, list) in &self.current_iocs {
+            if range.is_host() {
+                anomalies.push(format!("Threat IP: {} (listed on {})", ip, list));
+            } else {
+                anomalies.push(format!("Threat IP: {} (in {} listed on {})", ip, range, list));
+            }
+        }
+        
+        anomalies.extend(limits);
+        anomalies.extend(self.dns.mismatches(&self.config.dns_records));
+        anomalies.extend(self.kubernetes.anomalies(&self.config.kubernetes));
+        anomalies.extend(self.proxmox.anomalies());
+        anomalies.extend(self.lan_usage.anomalies());
+        anomalies.extend(self.pihole.anomalies());
+        anomalies.extend(self.backups.anomalies(&self.config.backups));
+        anomalies.extend(self.heartbeats.anomalies(&self.config.heartbeats));
+        anomalies.extend(self.ingested.anomalies());
+        anomalies.extend(self.external_anomalies.values().flatten().cloned());
+        
+        for (raised_at, anomaly) in &self.transient_anomalies {
+            if (Utc::now() - *raised_at).num_seconds() < TRANSIENT_ALERT_SECS {
+                anomalies.push(anomaly.clone());
+            }
         }
         
         let has_anomaly = !anomalies.is_empty();
@@ -364,6 +1025,17 @@ impl MonitorService {
         (anomalies, has_anomaly)
     }
     
+    /// Notes the blocklist of a Suricata alert's source, when it is on one.
+    fn mark_listed(&self, anomaly: String) -> String {
+        let listed = suricata::alert_source(&anomaly).and_then(|(source, _)| self.indicators.get(source));
+        match listed {
+            Some((_, list)) => format!("{}, listed on {})", anomaly.strip_suffix(')').unwrap_or(&anomaly), list),
+            None => anomaly,
+        }
+    }
+    
+    /// The lines of the status panel. Stats toggled off on the settings page are left
+    /// out, and a line is dropped once all of its stats are.
     pub fn status_report(&self) -> Vec<String> {
         use chrono::Local;
         
@@ -371,87 +1043,941 @@ impl MonitorService {
         let time_str = now.format("%H:%M:%S").to_string();
         
         if let Some(latest) = self.metrics_history.back() {
-            vec![
-                time_str,
-                format!("CPU:{:.1}% RAM:{:.1}%", latest.cpu_percent, latest.ram_percent),
-                format!("Disk:{:.1}% Tmp:{:.1}C", latest.disk_percent, latest.temperature),
-                format!("Ping:{:.1}ms Net:{}", latest.ping_ms, latest.net_connections),
-                format!("Fails:{}", latest.failed_logins),
-            ]
+            let shown = |parts: &[(&str, String)]| {
+                let visible: Vec<&str> = parts
+                    .iter()
+                    .filter(|(stat, _)| self.display.is_visible(stat))
+                    .map(|(_, part)| part.as_str())
+                    .collect();
+                (!visible.is_empty()).then(|| visible.join(" "))
+            };
+            
+            let mut lines = vec![time_str];
+            lines.extend(shown(&[
+                ("cpu", format!("CPU:{:.1}%", latest.cpu_percent)),
+                ("ram", format!("RAM:{:.1}%", latest.ram_percent)),
+            ]));
+            lines.extend(shown(&[
+                ("disk", format!("Disk:{:.1}%", latest.disk_percent)),
+                ("temp", format!("Tmp:{:.1}C", latest.temperature)),
+            ]));
+            lines.extend(shown(&[
+                ("ping", format!("Ping:{:.1}ms", latest.ping_ms)),
+                ("net", format!("Conns:{}", latest.net_connections)),
+            ]));
+            lines.extend(shown(&[
+                ("net", format!("RX:{:.1}KB/s", latest.net_rx_bytes / 1024.0)),
+                ("net", format!("TX:{:.1}KB/s", latest.net_tx_bytes / 1024.0)),
+            ]));
+            lines.extend(shown(&[
+                ("fail", format!("Fails:{}", latest.failed_logins)),
+                ("ram", format!("Swap:{:.1}%", latest.swap_percent)),
+            ]));
+            lines.push(format!(
+                "Load:{:.2} {:.2} {:.2} Run:{} Blocked:{}",
+                latest.load_1, latest.load_5, latest.load_15, latest.procs_running, latest.procs_blocked
+            ));
+            // The fullest mount point, which the total can hide
+            if let Some((mount, percent)) = latest.mounts.iter().max_by(|a, b| a.1.total_cmp(b.1)) {
+                lines.extend(shown(&[("disk", format!("Mount:{} {:.1}%", mount, percent))]));
+            }
+            // The busiest block device
+            if let Some((device, io)) = latest
+                .disk_io
+                .iter()
+                .max_by(|a, b| a.1.util_percent.total_cmp(&b.1.util_percent))
+            {
+                lines.extend(shown(&[(
+                    "disk",
+                    format!(
+                        "IO:{} {:.0}% {:.1}ms {:.0}iops",
+                        device,
+                        io.util_percent,
+                        io.await_ms,
+                        io.read_iops + io.write_iops
+                    ),
+                )]));
+            }
+            for (name, gpu) in &latest.gpus {
+                let stats = shown(&[
+                    ("gpu", format!("{:.0}%", gpu.utilization_percent)),
+                    ("vram", format!("{:.1}/{:.1}GB", gpu.memory_used_mb / 1024.0, gpu.memory_total_mb / 1024.0)),
+                    ("gpu", format!("{:.0}C", gpu.temperature)),
+                ]);
+                lines.extend(stats.map(|stats| format!("GPU:{} {}", name, stats)));
+            }
+            if !latest.custom.is_empty() {
+                let values: Vec<String> = latest.custom.iter().map(|(name, value)| format!("{}:{:.1}", name, value)).collect();
+                lines.push(values.join(" "));
+            }
+            if let Some(cluster) = self.kubernetes.status() {
+                let ready = cluster.nodes.iter().filter(|node| node.ready == "True").count();
+                let stale = if self.kubernetes.error().is_some() { " (stale)" } else { "" };
+                lines.push(format!(
+                    "K8s:{}/{} nodes {}/{} pods{}",
+                    ready,
+                    cluster.nodes.len(),
+                    cluster.running,
+                    cluster.pods,
+                    stale
+                ));
+            }
+            for (name, status) in self.pihole.status() {
+                lines.push(match &status.stats {
+                    Some(stats) => format!("Pi-hole {}: {}q {:.1}% blocked", name, stats.queries, stats.percent_blocked),
+                    None => format!("Pi-hole {}: DOWN", name),
+                });
+            }
+            lines.push(self.uptime_line());
+            lines
         } else {
-            vec![time_str, "No data available".to_string()]
+            vec![time_str, "No data available".to_string(), self.uptime_line()]
         }
     }
     
-    pub async fn trigger_actions(&self, anomalies: &[String]) -> Result<()> {
-        for anomaly in anomalies {
-            if anomaly.contains("Device Down") {
-                info!("Device down detected: {}", anomaly);
-                #[cfg(target_os = "linux")]
-                {
-                    let _ = Command::new("wall")
-                        .arg("Device Down Detected!")
-                        .spawn();
+    /// Appends newly raised anomalies to the event log and files them into incidents.
+    /// Anomalies that were already active in the previous cycle are not logged again.
+    /// Webhooks and push hear of an anomaly that opens or escalates an incident, and
+    /// of every critical one, so a lingering warning cannot hide what follows it.
+    /// Their targets, per-group routes included, are worked out here, but the posts
+    /// run in spawned tasks, so the caller's write guard never waits on a webhook.
+    /// Returns the newly raised anomalies.
+    pub async fn record_anomalies(&mut self, anomalies: &[String]) -> Vec<String> {
+        let (anomalies, flap_changes) = self.flapping.collapse(anomalies);
+        let anomalies = anomalies.as_slice();
+        for change in flap_changes {
+            let event = match change {
+                FlapChange::Started(subject, changes) => Event::new(
+                    EventKind::Anomaly,
+                    AnomalySeverity::Warning,
+                    format!(
+                        "{} is flapping: {} state changes in {}",
+                        subject,
+                        changes,
+                        format_duration(self.alerts.flapping.window as i64)
+                    ),
+                ),
+                FlapChange::Stopped(subject) => {
+                    Event::new(EventKind::Anomaly, AnomalySeverity::Info, format!("{} stopped flapping", subject))
                 }
+            };
+            self.event_log.record(event).await;
+        }
+        
+        let active: HashSet<String> = anomalies.iter().map(|anomaly| anomaly_subject(anomaly)).collect();
+        let down: HashSet<String> = anomalies
+            .iter()
+            .filter(|anomaly| anomaly.starts_with("Device Down"))
+            .filter_map(|anomaly| self.check_for_anomaly(anomaly))
+            .map(|check| check.name.clone())
+            .collect();
+        
+        let mut raised: Vec<(String, String)> = anomalies
+            .iter()
+            .filter(|anomaly| !self.active_anomalies.contains(&anomaly_subject(anomaly)))
+            .map(|anomaly| (anomaly.clone(), self.correlation_key(anomaly, &down)))
+            .collect();
+        // The root check's own failure goes first so it titles the incident
+        raised.sort_by_key(|(anomaly, key)| {
+            let own = self.check_for_anomaly(anomaly).map(|check| format!("check:{}", check.name));
+            own.as_ref() != Some(key) || !anomaly.starts_with("Device Down")
+        });
+        let update = self.incidents.update(&raised, &active).await;
+        
+        for incident in &update.resolved {
+            let duration = incident.resolved_at.unwrap_or_else(Utc::now) - incident.opened_at;
+            self.event_log
+                .record(
+                    Event::new(
+                        EventKind::Anomaly,
+                        AnomalySeverity::Info,
+                        format!(
+                            "Incident #{} resolved after {}: {}",
+                            incident.id,
+                            format_duration(duration.num_seconds()),
+                            incident.title
+                        ),
+                    )
+                    .with_details(serde_json::json!({ "incident": incident.id })),
+                )
+                .await;
+            // An anomaly that just started flapping has not recovered
+            if !incident.anomalies.iter().any(|anomaly| self.flapping.is_flapping(&anomaly.message)) {
+                self.send_recovery(incident).await;
+            }
+        }
+        
+        // Anomaly webhooks hear of every anomaly raised and cleared, unlike the alerts above
+        let mut documents = Vec::new();
+        for subject in self.active_anomalies.difference(&active) {
+            if let Some((id, message, incident)) = self.correlation_ids.remove(subject) {
+                documents.push(anomaly_hooks::anomaly_document("cleared", &message, &id, Some(incident)));
             }
+        }
+        
+        for ((anomaly, _), filed) in raised.iter().zip(update.filed) {
+            let incident = filed.incident;
+            let critical = anomaly_severity(anomaly) == AnomalySeverity::Critical;
+            let id = uuid::Uuid::new_v4().to_string();
+            documents.push(anomaly_hooks::anomaly_document("raised", anomaly, &id, Some(incident)));
+            self.correlation_ids.insert(anomaly_subject(anomaly), (id, anomaly.clone(), incident));
             
-            if anomaly.contains("Temp") {
-                // Extract temperature value
-                let re = Regex::new(r"Tmp:(\d+(?:\.\d+)?)").unwrap();
-                if let Some(caps) = re.captures(anomaly) {
-                    if let Ok(temp) = caps[1].parse::<f64>() {
-                        if temp > 80.0 {
-                            warn!("High temperature detected: {}°C - initiating shutdown", temp);
-                            #[cfg(target_os = "linux")]
-                            {
-                                let _ = Command::new("sudo")
-                                    .args(["shutdown", "now"])
-                                    .spawn();
-                            }
-                        }
-                    }
+            self.event_log
+                .record(
+                    Event::new(EventKind::Anomaly, anomaly_severity(anomaly), anomaly.clone())
+                        .with_details(serde_json::json!({ "incident": incident })),
+                )
+                .await;
+            if filed.opened || filed.escalated || critical {
+                self.send_webhooks(anomaly, incident).await;
+                self.send_push(anomaly, incident).await;
+                if critical {
+                    self.capture_snapshot(anomaly);
                 }
             }
             
-            if anomaly.contains("Threat IP:") {
-                // Extract and validate IP
-                let re = Regex::new(r"Threat IP:\s*([\d.]+)").unwrap();
-                if let Some(caps) = re.captures(anomaly) {
-                    let ip = &caps[1];
-                    if self.is_valid_ip(ip) {
-                        info!("Blocking threat IP: {}", ip);
-                        #[cfg(target_os = "linux")]
-                        {
-                            let _ = Command::new("sudo")
-                                .args(["iptables", "-A", "INPUT", "-s", ip, "-j", "DROP"])
-                                .spawn();
-                        }
-                    }
+            if anomaly.starts_with("Device Down") {
+                self.capture_traceroute(anomaly);
+            }
+            if let Some((source, severity)) = suricata::alert_source(anomaly) {
+                if severity <= self.config.suricata.ban_severity || self.indicators.contains(source) {
+                    self.block_ip(IpNet::host(source), "Suricata alert source", anomaly);
+                }
+            }
+            if self.config.detection.ban_web_scanners {
+                let scanner = anomaly.strip_prefix("Web Scanner: ").and_then(|rest| rest.split(' ').next());
+                if let Some(Ok(ip)) = scanner.map(str::parse::<IpAddr>) {
+                    self.block_ip(IpNet::host(ip), "web scanner", anomaly);
                 }
             }
         }
         
-        Ok(())
+        if self.alerts.enabled {
+            anomaly_hooks::deliver(&self.alerts.anomaly_webhooks, documents);
+        }
+        self.active_anomalies = active;
+        self.send_digests().await;
+        raised.into_iter().map(|(anomaly, _)| anomaly).collect()
+    }
+    
+    /// What ties `anomaly` to others raised for the same cause: the topmost failing
+    /// ancestor of its check, the host for resource anomalies, or the instance for
+    /// database and cache anomalies.
+    fn correlation_key(&self, anomaly: &str, down: &HashSet<String>) -> String {
+        if let Some(mut root) = self.check_for_anomaly(anomaly) {
+            // Bounded by the number of checks in case validation was bypassed
+            for _ in 0..self.checks.len() {
+                match root.parent.as_deref().filter(|parent| down.contains(*parent)).and_then(|parent| self.check(parent)) {
+                    Some(parent) => root = parent,
+                    None => break,
+                }
+            }
+            return format!("check:{}", root.name);
+        }
+        if incidents::is_host_anomaly(anomaly) {
+            return "host".to_string();
+        }
+        match incidents::instance_of(anomaly) {
+            Some(instance) => format!("instance:{}", instance),
+            None => anomaly_subject(anomaly),
+        }
     }
     
-    fn is_valid_ip(&self, ip: &str) -> bool {
-        let re = Regex::new(r"^(?:(?:25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)\.){3}(?:25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)$").unwrap();
-        re.is_match(ip)
+    /// [`Responses::record`] for a response made while the monitor is held.
+    async fn record_response(&self, anomaly: &str, event: Event) {
+        self.responses().record(anomaly, event).await;
     }
     
-    pub async fn save_baseline(&self) -> Result<()> {
-        let _guard = self.file_lock.lock().await;
-        
-        // Ensure data directory exists
-        if let Err(e) = fs::create_dir_all("data").await {
-            warn!("Failed to create data directory: {}", e);
+    /// Where notifications and actions sent in the background record themselves.
+    fn responses(&self) -> Responses {
+        Responses {
+            incidents: Arc::clone(&self.incidents),
+            event_log: Arc::clone(&self.event_log),
         }
-        
-        let data = serde_json::json!({
-            "baseline": self.baselines,
-            "feedback": self.feedback,
-        });
-        
+    }
+    
+    /// Resolves a check name to the host it targets; anything else is taken as a host.
+    pub fn trace_target(&self, host: &str) -> Option<String> {
+        match self.check(host) {
+            Some(check) => check.host(),
+            None => Some(host.to_string()),
+        }
+    }
+    
+    /// Traces the path to a check that just went down, in the background, and keeps
+    /// the result in the event log so the broken hop can be looked up later.
+    fn capture_traceroute(&self, anomaly: &str) {
+        let Some(target) = self.check_for_anomaly(anomaly).and_then(|check| check.host()) else {
+            return;
+        };
+        let event_log = Arc::clone(&self.event_log);
+        let incidents = Arc::clone(&self.incidents);
+        let anomaly = anomaly.to_string();
+        
+        tokio::spawn(async move {
+            match diagnostics::traceroute(&target).await {
+                Ok(trace) => {
+                    let event = Event::new(
+                        EventKind::Diagnostic,
+                        AnomalySeverity::Info,
+                        format!("Traceroute to {} after it went down", target),
+                    )
+                    .with_details(serde_json::to_value(&trace).unwrap_or_default());
+                    incidents.log_event(&anomaly, &event).await;
+                    event_log.record(event).await;
+                }
+                Err(e) => warn!("Traceroute to {} failed: {}", target, e),
+            }
+        });
+    }
+    
+    /// Records what the host is doing right after a critical anomaly opens an incident,
+    /// in the background, since that evidence is usually gone by the time anyone looks.
+    fn capture_snapshot(&mut self, anomaly: &str) {
+        let now = Utc::now();
+        let cooling = self.last_snapshot.is_some_and(|last| (now - last).num_seconds() < SNAPSHOT_COOLDOWN_SECS);
+        if !self.config.detection.snapshot_on_critical || cooling {
+            return;
+        }
+        self.last_snapshot = Some(now);
+        
+        let event_log = Arc::clone(&self.event_log);
+        let incidents = Arc::clone(&self.incidents);
+        let disk_paths = self.config.detection.snapshot_disk_paths.clone();
+        let anomaly = anomaly.to_string();
+        
+        tokio::spawn(async move {
+            let snapshot = diagnostics::state_snapshot(&anomaly, &disk_paths).await;
+            let event = Event::new(
+                EventKind::Diagnostic,
+                AnomalySeverity::Info,
+                format!("State snapshot after {}", anomaly_subject(&anomaly)),
+            )
+            .with_details(serde_json::to_value(&snapshot).unwrap_or_default());
+            incidents.log_event(&anomaly, &event).await;
+            event_log.record(event).await;
+        });
+    }
+    
+    pub fn dns(&self) -> &DnsWatcher {
+        &self.dns
+    }
+    
+    pub fn kubernetes(&self) -> &KubernetesWatcher {
+        &self.kubernetes
+    }
+    
+    pub fn proxmox(&self) -> &ProxmoxWatcher {
+        &self.proxmox
+    }
+    
+    pub fn lan_usage(&self) -> &LanUsageWatcher {
+        &self.lan_usage
+    }
+    
+    pub fn pihole(&self) -> &PiholeWatcher {
+        &self.pihole
+    }
+    
+    pub fn backups(&self) -> &BackupWatcher {
+        &self.backups
+    }
+    
+    /// The number of items waiting in each of the monitor's queues.
+    pub fn queue_depths(&self) -> BTreeMap<&'static str, usize> {
+        BTreeMap::from([
+            ("held_alerts", self.digest.len()),
+            ("transient_anomalies", self.transient_anomalies.len()),
+            ("ingested_alerts", self.ingested.anomalies().len()),
+            ("external_anomalies", self.external_anomalies.values().map(Vec::len).sum()),
+        ])
+    }
+    
+    pub fn collectors(&self) -> Vec<CollectorStatus> {
+        self.collector_statuses.clone()
+    }
+    
+    pub fn ssh_keys(&self) -> &AuthorizedKeysWatcher {
+        &self.ssh_keys
+    }
+    
+    pub fn availability(&self) -> Arc<AvailabilityLog> {
+        Arc::clone(&self.availability)
+    }
+    
+    pub fn heartbeats(&self) -> Arc<HeartbeatLog> {
+        Arc::clone(&self.heartbeats)
+    }
+    
+    pub fn incidents(&self) -> Arc<IncidentLog> {
+        Arc::clone(&self.incidents)
+    }
+    
+    pub fn web_push(&self) -> Arc<WebPush> {
+        Arc::clone(&self.web_push)
+    }
+    
+    pub fn check_names(&self) -> Vec<String> {
+        self.checks.iter().map(|check| check.name.clone()).collect()
+    }
+    
+    fn check(&self, name: &str) -> Option<&CheckConfig> {
+        self.checks.iter().find(|check| check.name == name)
+    }
+    
+    /// The check an anomaly message refers to, if it came from a host check.
+    fn check_for_anomaly(&self, anomaly: &str) -> Option<&CheckConfig> {
+        let subject = anomaly_subject(anomaly);
+        let name = subject
+            .strip_prefix("Device Down: ")
+            .or_else(|| subject.strip_prefix("Anomaly: "))?;
+        let name = name
+            .strip_suffix(" loss")
+            .or_else(|| name.strip_suffix(" jitter"))
+            .unwrap_or(name);
+        self.check(name)
+    }
+    
+    /// Alert routes for the groups of the check behind `anomaly`.
+    fn alert_routes(&self, anomaly: &str) -> Vec<&AlertRoute> {
+        match self.check_for_anomaly(anomaly) {
+            Some(check) => self.alerts.routes.iter().filter(|route| check.has_tag(&route.tag)).collect(),
+            None => Vec::new(),
+        }
+    }
+    
+    /// Pages the browsers subscribed to Web Push about an anomaly that opened an
+    /// incident, whether or not any other notification channel is configured. Each
+    /// user is only sent the severities and host groups they chose, Critical alerts
+    /// by default.
+    async fn send_push(&self, anomaly: &str, incident: u64) {
+        if !self.alerts.enabled {
+            return;
+        }
+        let severity = anomaly_severity(anomaly);
+        let recipients = self.auth.recipients("push", severity, &self.alert_groups(anomaly)).await;
+        if recipients.is_empty() {
+            return;
+        }
+        let language = self.alerts.language;
+        if quiet_hours::holds(&self.alerts.quiet_hours, "push", severity) {
+            self.hold_alert(HeldAlert {
+                channel: "push".to_string(),
+                anomaly: anomaly.to_string(),
+                text: language.anomaly(anomaly),
+                state: "firing".to_string(),
+                severity,
+                incident,
+                held_at: Utc::now(),
+            })
+            .await;
+            return;
+        }
+        let label = severity.to_string();
+        let message = serde_json::json!({
+            "title": format!("{}: {}", language.t(&label), System::host_name().unwrap_or_default()),
+            "body": language.anomaly(anomaly),
+            "incident": incident,
+            "url": "/",
+        });
+        let contact = self.alerts.push_contact.as_deref().unwrap_or(DEFAULT_PUSH_CONTACT).to_string();
+        let web_push = Arc::clone(&self.web_push);
+        let responses = self.responses();
+        let anomaly = anomaly.to_string();
+        
+        // Delivered in the background so slow push services never hold up the monitor
+        tokio::spawn(async move {
+            let sent = web_push.notify_users(&message, &contact, &recipients).await;
+            if sent > 0 {
+                let event = Event::new(
+                    EventKind::Notification,
+                    severity,
+                    format!("Sent push notification for {} to {} device(s)", anomaly, sent),
+                )
+                .with_details(serde_json::json!({ "incident": incident, "devices": sent }));
+                responses.record(&anomaly, event).await;
+            }
+        });
+    }
+    
+    /// Posts an anomaly that opened an incident to the webhooks of its groups, or to
+    /// the default webhook when none of its groups has one.
+    async fn send_webhooks(&self, anomaly: &str, incident: u64) {
+        if !self.alerts.enabled {
+            return;
+        }
+        if quiet_hours::holds(&self.alerts.quiet_hours, "webhook", anomaly_severity(anomaly)) {
+            self.hold_alert(HeldAlert {
+                channel: "webhook".to_string(),
+                anomaly: anomaly.to_string(),
+                text: self.alerts.language.anomaly(anomaly),
+                state: "firing".to_string(),
+                severity: anomaly_severity(anomaly),
+                incident,
+                held_at: Utc::now(),
+            })
+            .await;
+            return;
+        }
+        
+        let hint = self.incidents.get(incident).and_then(|incident| incident.hint);
+        let posts = self.webhook_posts(anomaly, |group| {
+            serde_json::json!({
+                "text": self.alerts.language.anomaly(anomaly),
+                "state": "firing",
+                "severity": anomaly_severity(anomaly),
+                "group": group,
+                "incident": incident,
+                "hint": hint,
+                "timestamp": Utc::now(),
+            })
+        });
+        let responses = self.responses();
+        let anomaly = anomaly.to_string();
+        
+        tokio::spawn(async move {
+            for group in post_webhooks(&anomaly, posts).await {
+                let event = Event::new(
+                    EventKind::Notification,
+                    anomaly_severity(&anomaly),
+                    format!("Sent webhook alert for {}", anomaly),
+                )
+                .with_details(serde_json::json!({ "group": group, "incident": incident }));
+                responses.record(&anomaly, event).await;
+            }
+        });
+    }
+    
+    /// Tells the webhooks and devices alerted when `incident` opened that it has
+    /// recovered, and how long the outage lasted. Incidents resolved by hand are not
+    /// recoveries and are not reported.
+    async fn send_recovery(&self, incident: &Incident) {
+        if !self.alerts.enabled || !self.alerts.notify_recovery {
+            return;
+        }
+        let alerted = |sent: &str| {
+            incident
+                .timeline
+                .iter()
+                .any(|entry| entry.kind == TimelineKind::Notification && entry.message.starts_with(sent))
+        };
+        let (webhook, push) = (alerted("Sent webhook alert"), alerted("Sent push notification"));
+        if !webhook && !push {
+            return;
+        }
+        
+        let outage = (incident.resolved_at.unwrap_or_else(Utc::now) - incident.opened_at).num_seconds();
+        let language = self.alerts.language;
+        let text = format!(
+            "{}: {} ({})",
+            language.t("Recovered"),
+            language.anomaly(&anomaly_subject(&incident.title)),
+            format_duration(outage)
+        );
+        let held = |channel: &str| HeldAlert {
+            channel: channel.to_string(),
+            anomaly: incident.title.clone(),
+            text: text.clone(),
+            state: "resolved".to_string(),
+            severity: incident.severity,
+            incident: incident.id,
+            held_at: Utc::now(),
+        };
+        let quiet = &self.alerts.quiet_hours;
+        let mut posts = Vec::new();
+        if webhook && quiet_hours::holds(quiet, "webhook", incident.severity) {
+            self.hold_alert(held("webhook")).await;
+        } else if webhook {
+            posts = self.webhook_posts(&incident.title, |group| {
+                serde_json::json!({
+                    "text": text,
+                    "state": "resolved",
+                    "severity": incident.severity,
+                    "group": group,
+                    "incident": incident.id,
+                    "duration_secs": outage,
+                    "timestamp": Utc::now(),
+                })
+            });
+        }
+        let mut page = None;
+        if push && quiet_hours::holds(quiet, "push", incident.severity) {
+            self.hold_alert(held("push")).await;
+        } else if push {
+            let message = serde_json::json!({
+                "title": format!("{}: {}", language.t("Recovered"), System::host_name().unwrap_or_default()),
+                "body": text,
+                "incident": incident.id,
+                "url": "/",
+            });
+            let recipients = self.auth.recipients("push", incident.severity, &self.alert_groups(&incident.title)).await;
+            page = Some((message, recipients));
+        }
+        if posts.is_empty() && page.is_none() {
+            return;
+        }
+        
+        let contact = self.alerts.push_contact.as_deref().unwrap_or(DEFAULT_PUSH_CONTACT).to_string();
+        let web_push = Arc::clone(&self.web_push);
+        let responses = self.responses();
+        let (id, title) = (incident.id, incident.title.clone());
+        
+        tokio::spawn(async move {
+            let mut sent = Vec::new();
+            if !post_webhooks(&title, posts).await.is_empty() {
+                sent.push("webhook".to_string());
+            }
+            if let Some((message, recipients)) = page {
+                let devices = web_push.notify_users(&message, &contact, &recipients).await;
+                if devices > 0 {
+                    sent.push(format!("push to {} device(s)", devices));
+                }
+            }
+            if sent.is_empty() {
+                return;
+            }
+            
+            let event = Event::new(
+                EventKind::Notification,
+                AnomalySeverity::Info,
+                format!("Sent recovery notification for incident #{} by {}", id, sent.join(" and ")),
+            )
+            .with_details(serde_json::json!({ "incident": id, "duration_secs": outage }));
+            responses.incidents.log_event_for(id, &event).await;
+            responses.event_log.record(event).await;
+        });
+    }
+    
+    /// Keeps an alert for the digest sent when its channel's quiet hours end, and notes
+    /// on its incident that it was held.
+    async fn hold_alert(&self, alert: HeldAlert) {
+        let what = if alert.state == "resolved" { "recovery" } else { "alert" };
+        let event = Event::new(
+            EventKind::Notification,
+            AnomalySeverity::Info,
+            format!("Held {} {} for {} until quiet hours end", alert.channel, what, alert.anomaly),
+        )
+        .with_details(serde_json::json!({ "incident": alert.incident }));
+        let incident = alert.incident;
+        self.digest.hold(alert).await;
+        self.incidents.log_event_for(incident, &event).await;
+        self.event_log.record(event).await;
+    }
+    
+    /// Sends each channel whose quiet hours are over one message listing the alerts
+    /// it held, per webhook for webhooks. Alerts that reached none of their recipients
+    /// are held again for the next attempt.
+    async fn send_digests(&self) {
+        if !self.alerts.enabled {
+            return;
+        }
+        let language = self.alerts.language;
+        let digest_text = |held: &[&HeldAlert]| {
+            let lines: Vec<String> = held
+                .iter()
+                .map(|alert| format!("{} {}", alert.held_at.with_timezone(&Local).format("%H:%M"), alert.text))
+                .collect();
+            format!("{} ({}):\n{}", language.t("Held during quiet hours"), held.len(), lines.join("\n"))
+        };
+        
+        for channel in QUIET_HOUR_CHANNELS {
+            if quiet_hours::is_quiet(&self.alerts.quiet_hours, channel) {
+                continue;
+            }
+            let held = self.digest.take(channel).await;
+            if held.is_empty() {
+                continue;
+            }
+            
+            let event_log = Arc::clone(&self.event_log);
+            let digest = Arc::clone(&self.digest);
+            if channel == "push" {
+                // Each user gets the held alerts they chose to be sent
+                let mut users: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+                for (index, alert) in held.iter().enumerate() {
+                    for user in self.auth.recipients("push", alert.severity, &self.alert_groups(&alert.anomaly)).await {
+                        users.entry(user).or_default().push(index);
+                    }
+                }
+                let pages: Vec<(Vec<usize>, HashSet<String>, serde_json::Value)> = users
+                    .into_iter()
+                    .map(|(user, indices)| {
+                        let alerts: Vec<&HeldAlert> = indices.iter().map(|index| &held[*index]).collect();
+                        let message = serde_json::json!({
+                            "title": format!("{}: {}", language.t("Held during quiet hours"), System::host_name().unwrap_or_default()),
+                            "body": digest_text(&alerts),
+                            "url": "/",
+                        });
+                        (indices, HashSet::from([user]), message)
+                    })
+                    .collect();
+                let contact = self.alerts.push_contact.as_deref().unwrap_or(DEFAULT_PUSH_CONTACT).to_string();
+                let web_push = Arc::clone(&self.web_push);
+                
+                tokio::spawn(async move {
+                    let mut outcomes = Vec::new();
+                    for (indices, users, message) in pages {
+                        outcomes.push((indices, web_push.notify_users(&message, &contact, &users).await > 0));
+                    }
+                    finish_digest(held, outcomes, &digest, &event_log, channel).await;
+                });
+            } else {
+                let mut targets: BTreeMap<(Option<&str>, &str), Vec<usize>> = BTreeMap::new();
+                for (index, alert) in held.iter().enumerate() {
+                    for target in self.webhook_targets(&alert.anomaly) {
+                        targets.entry(target).or_default().push(index);
+                    }
+                }
+                let posts: Vec<(Vec<usize>, WebhookPost)> = targets
+                    .into_iter()
+                    .map(|((group, url), indices)| {
+                        let alerts: Vec<&HeldAlert> = indices.iter().map(|index| &held[*index]).collect();
+                        let post = WebhookPost {
+                            group: group.map(str::to_string),
+                            url: url.to_string(),
+                            payload: serde_json::json!({
+                                "text": digest_text(&alerts),
+                                "state": "digest",
+                                "group": group,
+                                "alerts": alerts.iter().map(|alert| serde_json::json!({
+                                    "text": alert.text,
+                                    "state": alert.state,
+                                    "severity": alert.severity,
+                                    "incident": alert.incident,
+                                    "timestamp": alert.held_at,
+                                })).collect::<Vec<_>>(),
+                                "timestamp": Utc::now(),
+                            }),
+                        };
+                        (indices, post)
+                    })
+                    .collect();
+                
+                tokio::spawn(async move {
+                    let mut outcomes = Vec::new();
+                    for (indices, post) in posts {
+                        outcomes.push((indices, !post_webhooks("the quiet hours digest", vec![post]).await.is_empty()));
+                    }
+                    finish_digest(held, outcomes, &digest, &event_log, channel).await;
+                });
+            }
+        }
+    }
+    
+    /// The host groups (check tags) `anomaly` is about, for users' notification choices.
+    fn alert_groups(&self, anomaly: &str) -> Vec<String> {
+        self.check_for_anomaly(anomaly).map(|check| check.tags.clone()).unwrap_or_default()
+    }
+    
+    /// The webhooks of `anomaly`'s groups, or the default webhook when none of its
+    /// groups has one, with the group each is for.
+    fn webhook_targets(&self, anomaly: &str) -> Vec<(Option<&str>, &str)> {
+        let routes = self.alert_routes(anomaly);
+        let mut targets: Vec<(Option<&str>, &str)> = routes
+            .iter()
+            .filter_map(|route| route.webhook_url.as_deref().map(|url| (Some(route.tag.as_str()), url)))
+            .collect();
+        if targets.is_empty() {
+            if let Some(url) = self.alerts.webhook_url.as_deref() {
+                targets.push((None, url));
+            }
+        }
+        targets
+    }
+    
+    /// The document `payload` builds for each target group, addressed to the webhooks
+    /// of `anomaly`'s groups, or to the default webhook when none of its groups has one.
+    fn webhook_posts(&self, anomaly: &str, payload: impl Fn(Option<&str>) -> serde_json::Value) -> Vec<WebhookPost> {
+        self.webhook_targets(anomaly)
+            .into_iter()
+            .map(|(group, url)| WebhookPost {
+                group: group.map(str::to_string),
+                url: url.to_string(),
+                payload: payload(group),
+            })
+            .collect()
+    }
+    
+    /// Latest check results grouped by tag for the dashboard. Checks with several tags
+    /// appear in each group; untagged checks are listed under "other".
+    pub fn check_groups(&self) -> BTreeMap<String, Vec<(String, CheckResult)>> {
+        let mut groups: BTreeMap<String, Vec<(String, CheckResult)>> = BTreeMap::new();
+        
+        for check in &self.checks {
+            let Some((_, result)) = self.check_results.get(&check.name) else {
+                continue;
+            };
+            
+            if check.tags.is_empty() {
+                groups.entry(UNGROUPED.to_string()).or_default().push((check.name.clone(), *result));
+            }
+            for tag in &check.tags {
+                groups.entry(tag.clone()).or_default().push((check.name.clone(), *result));
+            }
+        }
+        
+        groups
+    }
+    
+    fn uptime_line(&self) -> String {
+        let uptime = self.uptime();
+        format!(
+            "Up:{} Mon:{}",
+            format_duration(uptime.uptime_secs),
+            format_duration(uptime.monitor_uptime_secs)
+        )
+    }
+    
+    /// Answers newly raised anomalies: wall broadcasts, shutdowns and blocks. Given
+    /// only what [`Self::record_anomalies`] returns, so an anomaly that lasts is
+    /// answered once rather than every cycle.
+    pub async fn trigger_actions(&self, anomalies: &[String]) -> Result<()> {
+        for anomaly in anomalies {
+            if anomaly.contains("Device Down") {
+                info!("Device down detected: {}", anomaly);
+                
+                #[cfg(target_os = "linux")]
+                {
+                    // Groups can opt out of wall broadcasts through their alert route
+                    let routes = self.alert_routes(anomaly);
+                    if routes.is_empty() || routes.iter().any(|route| route.wall) {
+                        let _ = Command::new("wall")
+                            .arg(self.alerts.language.t("Device Down Detected!"))
+                            .spawn();
+                        self.record_response(
+                            anomaly,
+                            Event::new(
+                                EventKind::Notification,
+                                AnomalySeverity::Critical,
+                                format!("Broadcast wall message for {}", anomaly),
+                            ),
+                        )
+                        .await;
+                    }
+                }
+            }
+            
+            if anomaly.contains("Temp") {
+                // Extract temperature value
+                let re = Regex::new(r"Tmp:(\d+(?:\.\d+)?)").unwrap();
+                if let Some(caps) = re.captures(anomaly) {
+                    if let Ok(temp) = caps[1].parse::<f64>() {
+                        if temp > 80.0 {
+                            warn!("High temperature detected: {}°C - initiating shutdown", temp);
+                            #[cfg(target_os = "linux")]
+                            {
+                                let _ = Command::new("sudo")
+                                    .args(["shutdown", "now"])
+                                    .spawn();
+                            }
+                            self.record_response(
+                                anomaly,
+                                Event::new(
+                                    EventKind::Action,
+                                    AnomalySeverity::Critical,
+                                    "Shutdown initiated due to high temperature",
+                                )
+                                .with_details(serde_json::json!({ "temperature": temp })),
+                            )
+                            .await;
+                        }
+                    }
+                }
+            }
+            
+            if anomaly.contains("Threat IP:") {
+                // Extract and validate IP
+                let re = Regex::new(r"Threat IP:\s*([0-9A-Fa-f.:]+)").unwrap();
+                if let Some(Ok(ip)) = re.captures(anomaly).map(|caps| caps[1].parse::<IpAddr>()) {
+                    // The listed range, narrowed so a broad entry cannot take a whole
+                    // provider with it, unless that would take allowlisted addresses too
+                    let range = self
+                        .indicators
+                        .get(ip)
+                        .and_then(|(range, _)| {
+                            let widest = if ip.is_ipv4() { MAX_BLOCK_PREFIX_V4 } else { MAX_BLOCK_PREFIX_V6 };
+                            IpNet::new(ip, range.prefix().max(widest))
+                        })
+                        .filter(|range| !self.allowlist.overlaps(*range))
+                        .unwrap_or_else(|| IpNet::host(ip));
+                    self.block_ip(range, "threat IP", anomaly);
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Drops all traffic from the addresses in `range` and records why in the event log.
+    /// Ranges holding loopback, private or allowlisted addresses are left alone.
+    fn block_ip(&self, range: IpNet, reason: &str, anomaly: &str) {
+        if !self.blockable(range) {
+            info!("Not blocking {} {}: loopback, private or allowlisted", reason, range);
+            return;
+        }
+        info!("Blocking {}: {}", reason, range);
+        let backend = self.config.detection.firewall;
+        let own_blocks = Arc::clone(&self.own_blocks);
+        let responses = self.responses();
+        let (reason, anomaly) = (reason.to_string(), anomaly.to_string());
+        
+        // The firewall commands run in the background so they never hold up the monitor
+        tokio::spawn(async move {
+            let firewall = match firewall::block(&[range], backend).await {
+                Ok(firewall) => firewall,
+                Err(e) => {
+                    warn!("Failed to block {}: {}", range, e);
+                    return;
+                }
+            };
+            own_blocks.add(range).await;
+            let event = Event::new(
+                EventKind::Action,
+                AnomalySeverity::Critical,
+                format!("Blocked {} {}", reason, range),
+            )
+            .with_details(serde_json::json!({ "ip": range.to_string(), "firewall": firewall }));
+            responses.record(&anomaly, event).await;
+        });
+    }
+    
+    /// The ranges among those banned by another source, such as CrowdSec, that may be
+    /// blocked: those holding loopback, private or allowlisted addresses are left out.
+    pub fn blockable_ranges(&self, ranges: &[IpNet]) -> Vec<IpNet> {
+        ranges.iter().copied().filter(|range| self.blockable(*range)).collect()
+    }
+    
+    pub fn own_blocks(&self) -> Arc<OwnBlocks> {
+        Arc::clone(&self.own_blocks)
+    }
+    
+    pub fn firewall(&self) -> Firewall {
+        self.config.detection.firewall
+    }
+    
+    fn blockable(&self, range: IpNet) -> bool {
+        let loopback = [IpAddr::V4(Ipv4Addr::LOCALHOST), IpAddr::V6(Ipv6Addr::LOCALHOST)];
+        // Blocking the LAN would lock out the admin along with every device on it
+        !range.addr().is_loopback()
+            && !loopback.iter().any(|ip| range.contains(*ip))
+            && !range.is_private()
+            && !self.allowlist.overlaps(range)
+    }
+    
+    pub async fn save_baseline(&self) -> Result<()> {
+        let _guard = self.file_lock.lock().await;
+        
+        // Ensure data directory exists
+        if let Err(e) = data_dir::ensure_async().await {
+            warn!("Failed to create data directory: {}", e);
+        }
+        
+        let data = serde_json::json!({
+            "baseline": self.baselines,
+            "feedback": self.feedback.iter().filter(|rule| rule.is_active(Utc::now())).collect::<Vec<_>>(),
+            "relearning": self.relearning,
+        });
+        
         // Atomic write: write to temp file then rename
         let temp_file = format!("{}.tmp", BASELINE_FILE);
         fs::write(&temp_file, serde_json::to_string_pretty(&data)?).await?;
@@ -460,6 +1986,69 @@ impl MonitorService {
         Ok(())
     }
     
+    /// Whether the metrics window is due to be saved.
+    pub fn history_save_due(&self) -> bool {
+        self.history_saved
+            .map_or(true, |saved| (Utc::now() - saved).num_seconds() >= HISTORY_SAVE_SECS)
+    }
+    
+    /// Writes the metrics window to disk, so a restart resumes detection instead of
+    /// learning again, along with the one-minute rollup so the longer dashboard
+    /// ranges survive it too.
+    pub async fn save_history(&mut self) -> Result<()> {
+        let _guard = self.file_lock.lock().await;
+        data_dir::ensure_async().await?;
+        let temp_file = format!("{}.tmp", HISTORY_FILE);
+        fs::write(&temp_file, serde_json::to_string(&self.metrics_history)?).await?;
+        fs::rename(&temp_file, HISTORY_FILE).await?;
+        let temp_file = format!("{}.tmp", ROLLUP_FILE);
+        fs::write(&temp_file, serde_json::to_string(&self.rollup_history)?).await?;
+        fs::rename(&temp_file, ROLLUP_FILE).await?;
+        self.history_saved = Some(Utc::now());
+        Ok(())
+    }
+    
+    /// Restores the metrics window saved by the previous run, without samples too old
+    /// to describe the host or beyond the configured window.
+    fn load_history(&mut self) -> Result<()> {
+        let Ok(content) = std::fs::read_to_string(HISTORY_FILE) else {
+            return Ok(());
+        };
+        let cutoff = Utc::now() - chrono::Duration::hours(HISTORY_MAX_AGE_HOURS);
+        let mut samples: Vec<SystemMetrics> = serde_json::from_str(&content)?;
+        samples.retain(|metrics| metrics.timestamp >= cutoff);
+        let skip = samples.len().saturating_sub(self.config.window_size.max(1));
+        let mut history = MetricsWindow::new();
+        for metrics in samples.into_iter().skip(skip) {
+            history.push_back(metrics);
+        }
+        if !history.is_empty() {
+            info!("Restored {} samples of metrics history", history.len());
+        }
+        self.metrics_history = history;
+        Ok(())
+    }
+    
+    /// Restores the one-minute rollup saved by the previous run, without points past
+    /// its retention. The gap while the monitor was down simply has no points.
+    fn load_rollup(&mut self) -> Result<()> {
+        let Ok(content) = std::fs::read_to_string(ROLLUP_FILE) else {
+            return Ok(());
+        };
+        let cutoff = Utc::now() - chrono::Duration::days(ROLLUP_RETENTION_DAYS);
+        let mut points: VecDeque<MetricPoint> = serde_json::from_str(&content)?;
+        points.retain(|point| point.timestamp >= cutoff);
+        self.rollup_history = points;
+        Ok(())
+    }
+    
+    /// Adds the current baselines to their versioned history when a snapshot is due.
+    pub async fn snapshot_baselines(&mut self) {
+        self.baseline_history
+            .record_if_due(&self.baselines, self.config.window_size, self.metrics_history.len())
+            .await;
+    }
+    
     fn load_baseline(&mut self) -> Result<()> {
         if let Ok(content) = std::fs::read_to_string(BASELINE_FILE) {
             let data: serde_json::Value = serde_json::from_str(&content)?;
@@ -469,46 +2058,530 @@ impl MonitorService {
             }
             
             if let Some(feedback) = data.get("feedback") {
-                self.feedback = serde_json::from_value(feedback.clone())?;
+                self.feedback = deserialize_feedback(feedback.clone())?;
             }
+            
+            if let Some(relearning) = data.get("relearning") {
+                self.relearning = serde_json::from_value(relearning.clone())?;
+            }
+            drop_retired(&mut self.baselines, &mut self.feedback);
+            self.relearning.retain(|key, _| !RETIRED_BASELINES.contains(&key.as_str()));
         }
         
         Ok(())
     }
     
-    pub fn get_metrics_history(&self) -> &VecDeque<SystemMetrics> {
+    pub fn baselines(&self) -> &HashMap<String, BaselineStats> {
+        &self.baselines
+    }
+    
+    pub fn feedback(&self) -> &[FeedbackRule] {
+        &self.feedback
+    }
+    
+    fn marked_normal(&self, metric: &str, value: f64) -> bool {
+        let now = Utc::now();
+        self.feedback.iter().any(|rule| rule.covers(metric, value) && rule.is_active(now))
+    }
+    
+    /// Marks a value of a metric as normal, suppressing anomalies for values around it.
+    /// A value at or near a range already marked widens that range and renews its
+    /// confidence instead of adding another.
+    pub fn mark_normal(&mut self, metric: &str, value: f64, user: &str) -> FeedbackRule {
+        let now = Utc::now();
+        self.feedback.retain(|rule| rule.is_active(now));
+        
+        // Values within about one standard deviation count as the same observation
+        let spread = self.baselines.get(metric).map_or(0.0, |stats| stats.std);
+        let tolerance = spread.max(value.abs() * 0.01).max(0.5);
+        let (min, max) = (value - tolerance, value + tolerance);
+        
+        let existing = self
+            .feedback
+            .iter_mut()
+            .find(|rule| rule.metric == metric && min <= rule.max && max >= rule.min);
+        match existing {
+            Some(rule) => {
+                rule.min = rule.min.min(min);
+                rule.max = rule.max.max(max);
+                rule.marked_at = now;
+                rule.marked_by = Some(user.to_string());
+                rule.times_marked += 1;
+                rule.clone()
+            }
+            None => {
+                let rule = FeedbackRule {
+                    id: self.feedback.iter().map(|rule| rule.id).max().unwrap_or(0) + 1,
+                    metric: metric.to_string(),
+                    min,
+                    max,
+                    marked_at: now,
+                    marked_by: Some(user.to_string()),
+                    times_marked: 1,
+                };
+                self.feedback.push(rule.clone());
+                rule
+            }
+        }
+    }
+    
+    /// Drops a feedback rule; false when there is none with that id.
+    pub fn remove_feedback(&mut self, id: u64) -> bool {
+        let count = self.feedback.len();
+        self.feedback.retain(|rule| rule.id != id);
+        self.feedback.len() < count
+    }
+    
+    /// Replaces learned baselines and feedback, e.g. when restoring a backup.
+    pub fn restore_baselines(&mut self, mut baselines: HashMap<String, BaselineStats>, mut feedback: Vec<FeedbackRule>) {
+        drop_retired(&mut baselines, &mut feedback);
+        self.baselines = baselines;
+        self.feedback = feedback;
+        self.relearning.clear();
+    }
+    
+    /// Adds baselines and feedback learned elsewhere. They are used from the next cycle,
+    /// without waiting for the learning period, until enough local samples replace them.
+    pub fn import_baselines(
+        &mut self,
+        mut baselines: HashMap<String, BaselineStats>,
+        mut feedback: Vec<FeedbackRule>,
+        replace: bool,
+    ) {
+        drop_retired(&mut baselines, &mut feedback);
+        if replace {
+            self.restore_baselines(baselines, feedback);
+        } else {
+            self.baselines.extend(baselines);
+            // Renumber the imported rules after ours so ids stay unique
+            let next = self.feedback.iter().map(|rule| rule.id).max().unwrap_or(0);
+            self.feedback.extend(feedback.into_iter().zip(next + 1..).map(|(rule, id)| FeedbackRule { id, ..rule }));
+        }
+    }
+    
+    /// Metrics relearning since a reset, and since when.
+    pub fn relearning(&self) -> &HashMap<String, DateTime<Utc>> {
+        &self.relearning
+    }
+    
+    /// Forgets a metric's baseline, frozen or not, and relearns it from samples taken
+    /// from now on, e.g. after a hardware change. False when nothing is known about it.
+    pub fn reset_baseline(&mut self, metric: &str) -> bool {
+        let known = self.baselines.remove(metric).is_some() || self.relearning.contains_key(metric);
+        if known {
+            self.relearning.insert(metric.to_string(), Utc::now());
+        }
+        known
+    }
+    
+    /// Pins a metric's baseline so it is no longer relearned, or lets it learn again.
+    /// False when the metric has no baseline.
+    pub fn freeze_baseline(&mut self, metric: &str, frozen: bool) -> bool {
+        match self.baselines.get_mut(metric) {
+            Some(stats) => {
+                stats.frozen = frozen;
+                true
+            }
+            None => false,
+        }
+    }
+    
+    pub fn get_metrics_history(&self) -> &MetricsWindow {
         &self.metrics_history
     }
+    
+    /// Folds a sample into the one-minute rollup that backs the longer dashboard ranges.
+    fn record_rollup(&mut self, metrics: &SystemMetrics) {
+        let bucket = bucket_start(metrics.timestamp, ROLLUP_BUCKET_SECS);
+        let point = MetricPoint::from_metrics(bucket, metrics);
+        
+        match self.rollup_history.back_mut() {
+            Some(last) if last.timestamp == bucket => last.merge(&point),
+            _ => self.rollup_history.push_back(point),
+        }
+        
+        let cutoff = Utc::now() - chrono::Duration::days(ROLLUP_RETENTION_DAYS);
+        while self.rollup_history.front().is_some_and(|p| p.timestamp < cutoff) {
+            self.rollup_history.pop_front();
+        }
+    }
+    
+    /// History over `range`, averaged into at most `HISTORY_POINTS` evenly sized buckets.
+    pub fn history(&self, range: HistoryRange) -> Vec<MetricPoint> {
+        let duration = range.duration();
+        let cutoff = Utc::now() - duration;
+        let step = (duration.num_seconds() / HISTORY_POINTS).max(ROLLUP_BUCKET_SECS);
+        
+        let mut points: Vec<MetricPoint> = Vec::new();
+        for point in self.rollup_history.iter().filter(|p| p.timestamp >= cutoff) {
+            let bucket = bucket_start(point.timestamp, step);
+            match points.last_mut() {
+                Some(last) if last.timestamp == bucket => last.merge(point),
+                _ => {
+                    let mut point = point.clone();
+                    point.timestamp = bucket;
+                    points.push(point);
+                }
+            }
+        }
+        
+        points
+    }
 }
 
-fn calculate_stats(values: &[f64]) -> Option<BaselineStats> {
-    if values.is_empty() {
-        return None;
+/// Classifies an anomaly message produced by `detect_anomalies`.
+pub fn anomaly_severity(anomaly: &str) -> AnomalySeverity {
+    // Suricata's own severity decides, and a source on a blocklist makes any alert critical
+    if let Some((_, severity)) = suricata::alert_source(anomaly) {
+        return match severity {
+            _ if anomaly.contains(", listed on ") => AnomalySeverity::Critical,
+            1 => AnomalySeverity::Critical,
+            2 => AnomalySeverity::Warning,
+            _ => AnomalySeverity::Info,
+        };
+    }
+    if anomaly.starts_with("Threat IP")
+        || anomaly.starts_with("Device Down")
+        || anomaly.starts_with("Unexpected Reboot")
+        || anomaly.starts_with("DNS Mismatch")
+        || anomaly.starts_with("Blocklisted")
+        || anomaly.starts_with("File Changed")
+        || anomaly.starts_with("New Persistence")
+        || anomaly.starts_with("New Account")
+        || anomaly.starts_with("Root Account Added")
+        || anomaly.starts_with("Privileged Group Changed")
+        || anomaly.starts_with("SSH Key Added")
+        || anomaly.starts_with("SSH Key Changed")
+        || anomaly.starts_with("New SUID Binary")
+        || anomaly.starts_with("New Listening Port")
+        || anomaly.starts_with("OOM Kill")
+        || anomaly.starts_with("Kernel I/O Error")
+        || anomaly.starts_with("Kernel Read-only Remount")
+        || anomaly.starts_with("Node Not Ready")
+        || anomaly.starts_with("Proxmox Node Offline")
+        || anomaly.starts_with("Pi-hole Down")
+        || anomaly.starts_with("Backup Missing")
+        || anomaly.starts_with("Backup Stale")
+        || anomaly.starts_with("Heartbeat Missed")
+        || anomaly.starts_with("Heartbeat Failed")
+        || anomaly.starts_with("External Critical")
+        || anomaly.starts_with("Log Critical")
+        || anomaly.starts_with("Database Down")
+        || anomaly.starts_with("Cache Down")
+        || anomaly.starts_with("Container Exited")
+        || anomaly.starts_with("Process Down")
+    {
+        AnomalySeverity::Critical
+    } else if anomaly.starts_with("Anomaly")
+        || anomaly.starts_with("Public IP Changed")
+        || anomaly.starts_with("DNS Changed")
+        || anomaly.starts_with("Persistence Changed")
+        || anomaly.starts_with("Account Changed")
+        || anomaly.starts_with("SSH Key Removed")
+        || anomaly.starts_with("SUID Binary Changed")
+        || anomaly.starts_with("Kernel USB Reset")
+        || anomaly.starts_with("Kernel Under-voltage")
+        || anomaly.starts_with("Node Pressure")
+        || anomaly.starts_with("Pod Pending")
+        || anomaly.starts_with("Pod Restarted")
+        || anomaly.starts_with("Container Restarted")
+        || anomaly.starts_with("Process Restarted")
+        || anomaly.starts_with("Proxmox Guest Stopped")
+        || anomaly.starts_with("Pi-hole Blocking Disabled")
+        || anomaly.starts_with("Backup Too Small")
+        || anomaly.starts_with("External Warning")
+        || anomaly.starts_with("Log Warning")
+        || anomaly.starts_with("Web Scanner")
+        || anomaly.starts_with("SSH Brute Force")
+        || anomaly.starts_with("Zeek Notice")
+        || anomaly.starts_with("Flapping")
+        || anomaly.starts_with("Database Replication Stopped")
+    {
+        AnomalySeverity::Warning
+    } else {
+        AnomalySeverity::Info
+    }
+}
+
+/// Strips the changing measurement from an anomaly message so the same condition
+/// can be recognised across cycles, e.g. "Anomaly: CPU 91.3 (Normal: ...)" -> "Anomaly: CPU".
+pub fn anomaly_subject(anomaly: &str) -> String {
+    match anomaly.split_once(" (Normal") {
+        Some((head, _)) => head.rsplit_once(' ').map(|(subject, _)| subject).unwrap_or(head).to_string(),
+        None => anomaly.to_string(),
+    }
+}
+
+/// The logs a notification or action taken in the background records itself in.
+#[derive(Clone)]
+struct Responses {
+    incidents: Arc<IncidentLog>,
+    event_log: Arc<EventLog>,
+}
+
+impl Responses {
+    /// Records an action or notification taken for `anomaly` in the event log and on
+    /// the timeline of its incident.
+    async fn record(&self, anomaly: &str, event: Event) {
+        self.incidents.log_event(anomaly, &event).await;
+        self.event_log.record(event).await;
+    }
+}
+
+/// A webhook document and the group and URL it is for.
+struct WebhookPost {
+    group: Option<String>,
+    url: String,
+    payload: serde_json::Value,
+}
+
+/// Posts each document to its webhook and returns the groups it was delivered to.
+async fn post_webhooks(what: &str, posts: Vec<WebhookPost>) -> Vec<Option<String>> {
+    let mut delivered = Vec::new();
+    if posts.is_empty() {
+        return delivered;
     }
     
-    let sum: f64 = values.iter().sum();
-    let mean = sum / values.len() as f64;
+    let Ok(client) = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(WEBHOOK_TIMEOUT_SECS))
+        .build()
+    else {
+        return delivered;
+    };
     
-    let variance_sum: f64 = values.iter().map(|v| (v - mean).powi(2)).sum();
-    let std = (variance_sum / values.len() as f64).sqrt();
+    for post in posts {
+        match client.post(&post.url).json(&post.payload).send().await {
+            Ok(response) if response.status().is_success() => delivered.push(post.group),
+            Ok(response) => warn!("Webhook for {} returned {}", what, response.status()),
+            Err(e) => warn!("Webhook for {} failed: {}", what, e),
+        }
+    }
+    delivered
+}
+
+/// Records a digest that reached someone and holds again the alerts that reached
+/// none of their recipients. `outcomes` pairs the alerts each message carried with
+/// whether it was delivered.
+async fn finish_digest(
+    held: Vec<HeldAlert>,
+    outcomes: Vec<(Vec<usize>, bool)>,
+    digest: &NotificationDigest,
+    event_log: &EventLog,
+    channel: &str,
+) {
+    let mut carried = HashSet::new();
+    let mut delivered = HashSet::new();
+    for (indices, sent) in outcomes {
+        if sent {
+            delivered.extend(indices.iter().copied());
+        }
+        carried.extend(indices);
+    }
+    if !delivered.is_empty() {
+        event_log.record(digest_event(delivered.len(), channel)).await;
+    }
     
-    Some(BaselineStats {
-        mean,
-        std,
-        sample_count: values.len(),
-    })
+    let undelivered: Vec<HeldAlert> = held
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| carried.contains(index) && !delivered.contains(index))
+        .map(|(_, alert)| alert)
+        .collect();
+    if !undelivered.is_empty() {
+        warn!("Holding {} alert(s) for the {} digest again after it failed", undelivered.len(), channel);
+        digest.requeue(undelivered).await;
+    }
+}
+
+fn digest_event(count: usize, channel: &str) -> Event {
+    Event::new(
+        EventKind::Notification,
+        AnomalySeverity::Info,
+        format!("Sent quiet hours digest of {} alert(s) by {}", count, channel),
+    )
+}
+
+/// The system metrics with baselines: baseline key, where the window keeps it, the
+/// scale it is judged in, and label.
+const SYSTEM_READINGS: [(&str, SystemField, f64, &str); 13] = [
+    ("cpu", SystemField::Cpu, 1.0, "CPU"),
+    ("ram", SystemField::Ram, 1.0, "RAM"),
+    ("swap", SystemField::Swap, 1.0, "Swap"),
+    ("disk", SystemField::Disk, 1.0, "Disk"),
+    ("temp", SystemField::Temperature, 1.0, "Temp"),
+    ("ping", SystemField::Ping, 1.0, "Ping"),
+    ("conns", SystemField::Connections, 1.0, "Connections"),
+    ("rx", SystemField::NetRx, 1.0 / 1024.0, "Net RX KB/s"),
+    ("tx", SystemField::NetTx, 1.0 / 1024.0, "Net TX KB/s"),
+    ("fail", SystemField::FailedLogins, 1.0, "Failed Login"),
+    ("load1", SystemField::Load1, 1.0, "Load 1m"),
+    ("load5", SystemField::Load5, 1.0, "Load 5m"),
+    ("load15", SystemField::Load15, 1.0, "Load 15m"),
+];
+
+/// The system metrics with baselines: (baseline key, value, label).
+fn system_readings(metrics: &SystemMetrics) -> [(&'static str, f64, &'static str); 13] {
+    SYSTEM_READINGS.map(|(key, field, scale, label)| (key, field.of(metrics) * scale, label))
+}
+
+/// The parsed `detection.allowlist`; entries were checked when the configuration loaded.
+fn allowlist(config: &MonitoringConfig) -> PrefixTrie<()> {
+    let mut allowlist = PrefixTrie::new();
+    for range in config.detection.allowlist.iter().filter_map(|entry| entry.parse::<IpNet>().ok()) {
+        allowlist.insert(range, ());
+    }
+    allowlist
+}
+
+fn disk_key(device: &str, measure: &str) -> String {
+    format!("disk {} {}", device, measure)
+}
+
+/// Removes the baselines and feedback of `RETIRED_BASELINES`.
+fn drop_retired(baselines: &mut HashMap<String, BaselineStats>, feedback: &mut Vec<FeedbackRule>) {
+    baselines.retain(|key, _| !RETIRED_BASELINES.contains(&key.as_str()));
+    feedback.retain(|rule| !RETIRED_BASELINES.contains(&rule.metric.as_str()));
 }
 
-fn parse_ping_time(output: &str) -> Option<f64> {
-    // Parse time=XX.Xms or time=XX ms patterns
-    for line in output.lines() {
-        if let Some(pos) = line.find("time=") {
-            let time_part = &line[pos + 5..];
-            let time_str: String = time_part.chars().take_while(|c| c.is_digit(10) || *c == '.').collect();
-            if let Ok(time_ms) = time_str.parse::<f64>() {
-                return Some(time_ms);
+fn tcp_state_key(state: &str) -> String {
+    format!("{}{}", TCP_STATE_PREFIX, state)
+}
+
+fn interface_key(interface: &str, direction: &str) -> String {
+    format!("iface {} {}", interface, direction)
+}
+
+fn custom_key(metric: &str) -> String {
+    format!("metric {}", metric)
+}
+
+fn loss_key(check: &str) -> String {
+    format!("{} loss", check)
+}
+
+fn jitter_key(check: &str) -> String {
+    format!("{} jitter", check)
+}
+
+pub fn bucket_start(timestamp: DateTime<Utc>, bucket_secs: i64) -> DateTime<Utc> {
+    let secs = timestamp.timestamp();
+    DateTime::from_timestamp(secs - secs.rem_euclid(bucket_secs), 0).unwrap_or(timestamp)
+}
+
+/// Compact duration for the status panel, e.g. "3d 4h", "2h 5m" or "42m".
+pub fn format_duration(secs: i64) -> String {
+    let secs = secs.max(0);
+    let (days, hours, minutes) = (secs / 86_400, secs % 86_400 / 3600, secs % 3600 / 60);
+    
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+/// Baselines learned from the samples of the window from index `from` on, read
+/// column by column so no sample is rebuilt.
+fn learn_stats(window: &MetricsWindow, from: usize, checks: &[CheckConfig]) -> HashMap<String, BaselineStats> {
+    let mut learned = HashMap::new();
+    
+    // Learn the system readings, from CPU and RAM to the load averages
+    for (key, field, scale, _) in SYSTEM_READINGS {
+        if let Some(stats) = calculate_stats(window.column(field, from).map(|value| value * scale)) {
+            learned.insert(key.to_string(), stats);
+        }
+    }
+    
+    let columns = window.named_columns(from);
+    
+    // Learn a baseline per TCP state; a state missing from a sample had no sockets,
+    // while samples without any were taken before the states were read
+    let sampled: HashSet<usize> = columns
+        .iter()
+        .filter(|(series, _)| matches!(series, Series::TcpState(_)))
+        .flat_map(|(_, column)| column.iter().map(|(index, _)| *index))
+        .collect();
+    for (series, column) in &columns {
+        let key = match series {
+            Series::TcpState(state) if state == "ESTABLISHED" => continue,
+            Series::TcpState(state) => tcp_state_key(state),
+            // Learn traffic baselines per network interface
+            Series::Interface(interface, field) => interface_key(interface, if *field == 0 { "rx" } else { "tx" }),
+            // Learn baselines for metrics named by their collectors
+            Series::Custom(name) => custom_key(name),
+            // Learn latency and utilisation baselines per block device
+            Series::Disk(device, DISK_AWAIT) => disk_key(device, "await"),
+            Series::Disk(device, DISK_UTIL) => disk_key(device, "util"),
+            _ => continue,
+        };
+        let stats = match series {
+            Series::TcpState(_) => {
+                let zeros = sampled.len().saturating_sub(column.len());
+                calculate_stats(scaled(column, 1.0).chain(std::iter::repeat(0.0).take(zeros)))
             }
+            Series::Interface(..) => calculate_stats(scaled(column, 1.0 / 1024.0)),
+            _ => calculate_stats(scaled(column, 1.0)),
+        };
+        if let Some(stats) = stats {
+            learned.insert(key, stats);
         }
     }
-    None
+    
+    // Learn baselines for host checks; loss and jitter only from runs that got through.
+    // A result's three readings are stored together, so their columns line up
+    let host_column = |name: &str, field: u8| {
+        columns
+            .iter()
+            .find(|(series, _)| matches!(series, Series::Host(check, f) if check == name && *f == field))
+            .map_or(&[][..], |(_, column)| column.as_slice())
+    };
+    for check in checks {
+        let latency = host_column(&check.name, 0);
+        let answered = |field: u8| {
+            latency
+                .iter()
+                .zip(host_column(&check.name, field))
+                .filter(|((index, latency), (other, _))| index == other && *latency >= 0.0)
+                .map(|(_, (_, value))| *value)
+        };
+        
+        for (key, stats) in [
+            (check.name.clone(), calculate_stats(scaled(latency, 1.0))),
+            (loss_key(&check.name), calculate_stats(answered(1))),
+            (jitter_key(&check.name), calculate_stats(answered(2))),
+        ] {
+            if let Some(stats) = stats {
+                learned.insert(key, stats);
+            }
+        }
+    }
+    
+    learned
+}
+
+/// The values of a named column, multiplied by `scale`.
+fn scaled(column: &[(usize, f64)], scale: f64) -> impl Iterator<Item = f64> + Clone + '_ {
+    column.iter().map(move |(_, value)| value * scale)
+}
+
+/// Mean and standard deviation of `values`, which are gone through twice.
+fn calculate_stats(values: impl Iterator<Item = f64> + Clone) -> Option<BaselineStats> {
+    let (count, sum) = values.clone().fold((0usize, 0.0), |(count, sum), value| (count + 1, sum + value));
+    if count == 0 {
+        return None;
+    }
+    
+    let mean = sum / count as f64;
+    
+    let variance_sum: f64 = values.map(|v| (v - mean).powi(2)).sum();
+    let std = (variance_sum / count as f64).sqrt();
+    
+    Some(BaselineStats {
+        mean,
+        std,
+        sample_count: count,
+        frozen: false,
+    })
 }
@@ -0,0 +1,173 @@
+use crate::models::metrics::InterfaceRate;
+use crate::utils::process;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+use tracing::debug;
+
+// ss answers at once unless the system is in trouble
+const SS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Received and transmitted byte counters by interface.
+type InterfaceTotals = HashMap<String, (u64, u64)>;
+
+/// Turns the interface counters of `/proc/net/dev` into throughput, loopback excluded.
+pub struct ThroughputSampler {
+    previous: Option<(Instant, InterfaceTotals)>,
+}
+
+impl ThroughputSampler {
+    pub fn new() -> Self {
+        Self { previous: None }
+    }
+    
+    /// Received and transmitted bytes per second of each interface since the
+    /// previous call. Interfaces that have just appeared are left out until they
+    /// have two readings.
+    pub fn sample(&mut self) -> BTreeMap<String, InterfaceRate> {
+        let now = Instant::now();
+        let totals = read_interface_totals();
+        
+        let mut rates = BTreeMap::new();
+        if let Some((then, previous)) = &self.previous {
+            let elapsed = now.duration_since(*then).as_secs_f64();
+            if elapsed > 0.0 {
+                for (name, (rx, tx)) in &totals {
+                    if let Some((prev_rx, prev_tx)) = previous.get(name) {
+                        // Counters restart when a driver is reloaded
+                        let rate = InterfaceRate {
+                            rx_bytes_per_sec: rx.saturating_sub(*prev_rx) as f64 / elapsed,
+                            tx_bytes_per_sec: tx.saturating_sub(*prev_tx) as f64 / elapsed,
+                        };
+                        rates.insert(name.clone(), rate);
+                    }
+                }
+            }
+        }
+        
+        self.previous = Some((now, totals));
+        rates
+    }
+}
+
+fn read_interface_totals() -> InterfaceTotals {
+    let content = std::fs::read_to_string("/proc/net/dev").unwrap_or_default();
+    
+    content
+        .lines()
+        .skip(2)
+        .filter_map(|line| {
+            let (name, counters) = line.split_once(':')?;
+            let name = name.trim();
+            if name == "lo" {
+                return None;
+            }
+            let fields: Vec<u64> = counters.split_whitespace().filter_map(|field| field.parse().ok()).collect();
+            Some((name.to_string(), (*fields.first()?, *fields.get(8)?)))
+        })
+        .collect()
+}
+
+/// Network bytes a process moved over TCP since the previous sample.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessNetUsage {
+    pub pid: u32,
+    pub process: String,
+    pub bytes: u64,
+}
+
+/// Attributes TCP traffic to processes from the per-socket byte counters of `ss -tinp`.
+/// Sockets of other users' processes are only visible when running as root.
+pub struct ProcessNetSampler {
+    /// Socket (local and peer address) to owner and bytes moved so far.
+    previous: HashMap<String, (u32, String, u64)>,
+}
+
+impl ProcessNetSampler {
+    pub fn new() -> Self {
+        Self {
+            previous: HashMap::new(),
+        }
+    }
+    
+    /// Per-process traffic since the previous call, largest first.
+    pub async fn sample(&mut self) -> Vec<ProcessNetUsage> {
+        let output = match process::output_within(Command::new("ss").arg("-tinpH"), SS_TIMEOUT).await {
+            Ok(output) if output.status.success() => output,
+            Ok(output) => {
+                debug!("ss exited with {}", output.status);
+                return Vec::new();
+            }
+            Err(e) => {
+                debug!("Cannot sample sockets: {}", e);
+                return Vec::new();
+            }
+        };
+        let current = parse_ss(&String::from_utf8_lossy(&output.stdout));
+        
+        let mut per_process: HashMap<(u32, String), u64> = HashMap::new();
+        for (socket, (pid, process, bytes)) in &current {
+            // A socket's first reading only sets where it starts: its lifetime total,
+            // which may span hours before the monitor started, is not a rate
+            let Some((_, _, before)) = self.previous.get(socket) else {
+                continue;
+            };
+            *per_process.entry((*pid, process.clone())).or_default() += bytes.saturating_sub(*before);
+        }
+        self.previous = current;
+        
+        let mut usage: Vec<ProcessNetUsage> = per_process
+            .into_iter()
+            .filter(|(_, bytes)| *bytes > 0)
+            .map(|((pid, process), bytes)| ProcessNetUsage { pid, process, bytes })
+            .collect();
+        usage.sort_by_key(|usage| std::cmp::Reverse(usage.bytes));
+        usage
+    }
+}
+
+/// Parses the socket lines of `ss -tinpH`, each followed by an indented line of
+/// TCP details with the byte counters. Loopback traffic is skipped.
+fn parse_ss(output: &str) -> HashMap<String, (u32, String, u64)> {
+    let mut sockets = HashMap::new();
+    let mut owner: Option<(String, u32, String)> = None;
+    
+    for line in output.lines() {
+        if !line.starts_with(char::is_whitespace) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            owner = match (fields.get(3), fields.get(4), line.find("users:((\"")) {
+                (Some(local), Some(peer), Some(start)) if !(is_loopback(local) && is_loopback(peer)) => {
+                    let users = &line[start + "users:((\"".len()..];
+                    let process = users.split('"').next().unwrap_or_default().to_string();
+                    let pid = users
+                        .split("pid=")
+                        .nth(1)
+                        .and_then(|rest| rest.split([',', ')']).next())
+                        .and_then(|pid| pid.parse().ok());
+                    pid.map(|pid| (format!("{} {}", local, peer), pid, process))
+                }
+                _ => None,
+            };
+            continue;
+        }
+        
+        let Some((socket, pid, process)) = owner.take() else {
+            continue;
+        };
+        let counter = |name: &str| {
+            line.split_whitespace()
+                .find_map(|field| field.strip_prefix(name))
+                .and_then(|value| value.parse::<u64>().ok())
+                .unwrap_or(0)
+        };
+        let bytes = counter("bytes_acked:") + counter("bytes_received:");
+        sockets.insert(socket, (pid, process, bytes));
+    }
+    
+    sockets
+}
+
+fn is_loopback(address: &str) -> bool {
+    address.starts_with("127.") || address.starts_with("[::1]") || address.starts_with("[::ffff:127.")
+}
@@ -0,0 +1,181 @@
+// Weight of the identity in the shrunk correlation matrix. Metrics that move together
+// (e.g. RX and TX) would otherwise leave it nearly singular and the distance unstable
+const SHRINKAGE: f64 = 0.1;
+// Most recent samples the model is fitted to, which bounds the cost of large windows
+pub const MAX_SAMPLES: usize = 500;
+// Samples whose leave-one-out distance calibrates what a normal distance is
+const CALIBRATION_SAMPLES: usize = 50;
+// How many metrics to name as driving an outlier
+const TOP_CONTRIBUTORS: usize = 2;
+
+/// Learned joint distribution of a set of metrics, for judging them together by their
+/// Mahalanobis distance from normal.
+pub struct MultivariateModel {
+    names: Vec<&'static str>,
+    /// Positions of `names` in the samples; metrics that never changed are left out.
+    columns: Vec<usize>,
+    fit: Fit,
+    /// Mean and standard deviation of the distance of a normal sample, measured on
+    /// samples left out of the fit.
+    distance_mean: f64,
+    distance_std: f64,
+}
+
+/// A sample far from the learned normal.
+pub struct Outlier {
+    pub distance: f64,
+    pub normal_mean: f64,
+    pub normal_std: f64,
+    /// The metrics that contribute most to the distance, largest first.
+    pub drivers: Vec<&'static str>,
+}
+
+struct Fit {
+    mean: Vec<f64>,
+    std: Vec<f64>,
+    /// Inverse of the shrunk correlation matrix.
+    inverse: Vec<Vec<f64>>,
+}
+
+impl MultivariateModel {
+    /// Fits the model to past samples of the named metrics, each sample in the order
+    /// of `names`. `None` when fewer than two metrics ever changed or there are too
+    /// few samples to estimate their correlations.
+    pub fn fit(names: &[&'static str], samples: &[Vec<f64>]) -> Option<Self> {
+        let samples = &samples[samples.len().saturating_sub(MAX_SAMPLES)..];
+        let columns: Vec<usize> = (0..names.len())
+            .filter(|&i| {
+                let first = samples.first().map(|sample| sample[i]);
+                samples.iter().any(|sample| Some(sample[i]) != first)
+            })
+            .collect();
+        // Every leave-one-out fit needs more samples than metrics
+        if columns.len() < 2 || samples.len() < columns.len() + 2 {
+            return None;
+        }
+        
+        let all: Vec<&Vec<f64>> = samples.iter().collect();
+        let fit = Fit::new(&columns, &all)?;
+        
+        let distances: Vec<f64> = (samples.len().saturating_sub(CALIBRATION_SAMPLES)..samples.len())
+            .filter_map(|left_out| {
+                let rest: Vec<&Vec<f64>> = all.iter().enumerate().filter(|(i, _)| *i != left_out).map(|(_, s)| *s).collect();
+                Some(Fit::new(&columns, &rest)?.distance(&columns, &samples[left_out]).0)
+            })
+            .collect();
+        let count = distances.len() as f64;
+        let distance_mean = distances.iter().sum::<f64>() / count;
+        let distance_std = (distances.iter().map(|d| (d - distance_mean).powi(2)).sum::<f64>() / count).sqrt();
+        
+        Some(Self {
+            names: columns.iter().map(|&i| names[i]).collect(),
+            columns,
+            fit,
+            distance_mean,
+            distance_std,
+        })
+    }
+    
+    /// Judges a new sample, in the order of the names the model was fitted with. It is
+    /// an outlier when its distance is more than `threshold` standard deviations above
+    /// the distance of a normal sample.
+    pub fn outlier(&self, sample: &[f64], threshold: f64) -> Option<Outlier> {
+        let (distance, shares) = self.fit.distance(&self.columns, sample);
+        if !(self.distance_std > 0.0 && distance - self.distance_mean > threshold * self.distance_std) {
+            return None;
+        }
+        
+        let mut drivers: Vec<(f64, &'static str)> = shares.into_iter().zip(self.names.iter().copied()).collect();
+        drivers.sort_by(|a, b| b.0.total_cmp(&a.0));
+        
+        Some(Outlier {
+            distance,
+            normal_mean: self.distance_mean,
+            normal_std: self.distance_std,
+            drivers: drivers.into_iter().take(TOP_CONTRIBUTORS).map(|(_, name)| name).collect(),
+        })
+    }
+}
+
+impl Fit {
+    fn new(columns: &[usize], samples: &[&Vec<f64>]) -> Option<Self> {
+        let count = samples.len() as f64;
+        let mean: Vec<f64> = columns.iter().map(|&i| samples.iter().map(|s| s[i]).sum::<f64>() / count).collect();
+        let std: Vec<f64> = columns
+            .iter()
+            .zip(&mean)
+            .map(|(&i, m)| (samples.iter().map(|s| (s[i] - m).powi(2)).sum::<f64>() / count).sqrt())
+            .collect();
+        if std.iter().any(|&s| s <= 0.0) {
+            return None;
+        }
+        let standardized: Vec<Vec<f64>> = samples
+            .iter()
+            .map(|sample| columns.iter().enumerate().map(|(j, &i)| (sample[i] - mean[j]) / std[j]).collect())
+            .collect();
+        
+        let dims = columns.len();
+        let mut correlation = vec![vec![0.0; dims]; dims];
+        for (a, row) in correlation.iter_mut().enumerate() {
+            for (b, cell) in row.iter_mut().enumerate() {
+                let r = standardized.iter().map(|z| z[a] * z[b]).sum::<f64>() / count;
+                *cell = (1.0 - SHRINKAGE) * r + if a == b { SHRINKAGE } else { 0.0 };
+            }
+        }
+        
+        Some(Self {
+            mean,
+            std,
+            inverse: invert(correlation)?,
+        })
+    }
+    
+    /// The sample's distance, and each metric's share of its square: z_i * (R^-1 z)_i.
+    fn distance(&self, columns: &[usize], sample: &[f64]) -> (f64, Vec<f64>) {
+        let z: Vec<f64> = columns
+            .iter()
+            .enumerate()
+            .map(|(j, &i)| (sample[i] - self.mean[j]) / self.std[j])
+            .collect();
+        let shares: Vec<f64> = self
+            .inverse
+            .iter()
+            .zip(&z)
+            .map(|(row, zi)| zi * row.iter().zip(&z).map(|(r, zj)| r * zj).sum::<f64>())
+            .collect();
+        (shares.iter().sum::<f64>().max(0.0).sqrt(), shares)
+    }
+}
+
+/// Gauss-Jordan elimination with partial pivoting; `None` for a singular matrix.
+fn invert(mut matrix: Vec<Vec<f64>>) -> Option<Vec<Vec<f64>>> {
+    let n = matrix.len();
+    let mut inverse: Vec<Vec<f64>> = (0..n).map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect();
+    
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&a, &b| matrix[a][col].abs().total_cmp(&matrix[b][col].abs()))?;
+        if matrix[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        matrix.swap(col, pivot);
+        inverse.swap(col, pivot);
+        
+        let scale = matrix[col][col];
+        for j in 0..n {
+            matrix[col][j] /= scale;
+            inverse[col][j] /= scale;
+        }
+        for row in 0..n {
+            if row != col {
+                let factor = matrix[row][col];
+                if factor != 0.0 {
+                    for j in 0..n {
+                        matrix[row][j] -= factor * matrix[col][j];
+                        inverse[row][j] -= factor * inverse[col][j];
+                    }
+                }
+            }
+        }
+    }
+    Some(inverse)
+}
@@ -0,0 +1,223 @@
+use crate::services::file_watch;
+use crate::utils::data_dir;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tracing::warn;
+
+pub const PERSISTENCE_FILE: &str = "data/persistence.json";
+
+/// Directories whose entries start something automatically (searched two levels deep,
+/// which covers systemd `*.wants` links).
+const SYSTEM_DIRS: [&str; 12] = [
+    "/etc/cron.d",
+    "/etc/cron.hourly",
+    "/etc/cron.daily",
+    "/etc/cron.weekly",
+    "/etc/cron.monthly",
+    "/var/spool/cron",
+    "/etc/systemd/system",
+    "/etc/systemd/user",
+    "/etc/init.d",
+    "/etc/rc.d",
+    "/etc/xdg/autostart",
+    "/etc/profile.d",
+];
+
+const SYSTEM_FILES: [&str; 7] = [
+    "/etc/crontab",
+    "/etc/rc.local",
+    "/etc/profile",
+    "/etc/bash.bashrc",
+    "/etc/zsh/zshrc",
+    "/etc/environment",
+    "/etc/ld.so.preload",
+];
+
+/// Per-user locations, relative to the home directory.
+const USER_PATHS: [&str; 8] = [
+    ".bashrc",
+    ".bash_profile",
+    ".bash_login",
+    ".profile",
+    ".zshrc",
+    ".config/systemd/user",
+    ".config/autostart",
+    ".config/fish/config.fish",
+];
+
+const MAX_DEPTH: usize = 2;
+
+/// Cheap signature of an entry, so the scan can run often without hashing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct EntryState {
+    len: u64,
+    modified: u64,
+    /// Where a symlink points, e.g. a unit enabled through `*.wants`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    link: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Baseline {
+    /// Locations already scanned; a new home directory is learned without alerts.
+    roots: Vec<String>,
+    /// File watch paths left out of the scan; entries handed back are learned without alerts.
+    #[serde(default)]
+    delegated: Vec<String>,
+    entries: BTreeMap<String, EntryState>,
+}
+
+/// Watches the places malware uses to survive a reboot or a logout.
+pub struct PersistenceWatcher {
+    baseline: Baseline,
+}
+
+impl PersistenceWatcher {
+    /// Starts from the baseline saved by earlier runs, so entries added while stopped are reported.
+    pub fn new() -> Self {
+        let baseline = std::fs::read_to_string(PERSISTENCE_FILE)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        
+        Self { baseline }
+    }
+    
+    /// Scans every location and returns alerts for added, changed and removed entries.
+    /// The very first scan only learns. Files covered by a `delegated` file watch path,
+    /// such as `/etc/crontab` and `/etc/cron.d`, are skipped: file watch already reports
+    /// their changes as they happen. Blocking; run it off the async workers.
+    pub fn scan(&mut self, delegated: &[String]) -> Vec<String> {
+        let first_scan = self.baseline.roots.is_empty();
+        let roots = persistence_roots();
+        let is_delegated =
+            |list: &[String], path: &str| list.iter().any(|root| file_watch::covers(root, Path::new(path)));
+        
+        let mut current = BTreeMap::new();
+        for root in &roots {
+            collect(Path::new(root), 0, &mut current);
+        }
+        current.retain(|path, _| !is_delegated(delegated, path));
+        
+        let known_root = |path: &str| {
+            self.baseline
+                .roots
+                .iter()
+                .any(|root| path == root || path.starts_with(&format!("{}/", root)))
+        };
+        
+        let mut alerts = Vec::new();
+        for (path, state) in &current {
+            if !known_root(path) || is_delegated(&self.baseline.delegated, path) {
+                continue;
+            }
+            match self.baseline.entries.get(path) {
+                None => alerts.push(format!("New Persistence: {} created", path)),
+                Some(previous) if previous != state => alerts.push(format!("Persistence Changed: {} modified", path)),
+                _ => {}
+            }
+        }
+        for path in self.baseline.entries.keys() {
+            if !current.contains_key(path)
+                && !is_delegated(delegated, path)
+                && roots.iter().any(|root| path == root || path.starts_with(&format!("{}/", root)))
+            {
+                alerts.push(format!("Persistence Changed: {} removed", path));
+            }
+        }
+        
+        let changed =
+            self.baseline.entries != current || self.baseline.roots != roots || self.baseline.delegated != delegated;
+        self.baseline.entries = current;
+        self.baseline.roots = roots;
+        self.baseline.delegated = delegated.to_vec();
+        if changed {
+            if let Err(e) = self.save() {
+                warn!("Failed to save persistence baseline: {}", e);
+            }
+        }
+        
+        if first_scan {
+            Vec::new()
+        } else {
+            alerts
+        }
+    }
+    
+    fn save(&self) -> anyhow::Result<()> {
+        data_dir::ensure()?;
+        std::fs::write(PERSISTENCE_FILE, serde_json::to_string(&self.baseline)?)?;
+        Ok(())
+    }
+}
+
+/// System locations plus the per-user ones of root and regular accounts.
+fn persistence_roots() -> Vec<String> {
+    let mut roots: Vec<String> = SYSTEM_DIRS.iter().chain(SYSTEM_FILES.iter()).map(|path| path.to_string()).collect();
+    
+    for home in user_homes() {
+        roots.extend(USER_PATHS.iter().map(|path| home.join(path).to_string_lossy().to_string()));
+    }
+    
+    roots
+}
+
+fn user_homes() -> Vec<PathBuf> {
+    let passwd = std::fs::read_to_string("/etc/passwd").unwrap_or_default();
+    let mut homes: Vec<PathBuf> = passwd
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            let uid: u32 = fields.get(2)?.parse().ok()?;
+            let home = fields.get(5)?;
+            // Service accounts (1..1000) have no login shell profiles worth watching
+            (uid == 0 || (1000..65534).contains(&uid)).then(|| PathBuf::from(home))
+        })
+        .filter(|home| home.as_os_str() != "/" && home.is_dir())
+        .collect();
+    homes.sort();
+    homes.dedup();
+    homes
+}
+
+fn collect(path: &Path, depth: usize, entries: &mut BTreeMap<String, EntryState>) {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return;
+    };
+    
+    if metadata.is_dir() {
+        if depth >= MAX_DEPTH {
+            return;
+        }
+        if let Ok(children) = std::fs::read_dir(path) {
+            for child in children.filter_map(|entry| entry.ok()) {
+                collect(&child.path(), depth + 1, entries);
+            }
+        }
+        return;
+    }
+    
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|age| age.as_secs())
+        .unwrap_or(0);
+    let link = metadata
+        .file_type()
+        .is_symlink()
+        .then(|| std::fs::read_link(path).ok())
+        .flatten()
+        .map(|target| target.to_string_lossy().to_string());
+    
+    entries.insert(
+        path.to_string_lossy().to_string(),
+        EntryState {
+            len: metadata.len(),
+            modified,
+            link,
+        },
+    );
+}
@@ -0,0 +1,259 @@
+use crate::models::config::PiholeConfig;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PiholeStats {
+    /// Queries and blocked queries over the last 24 hours.
+    pub queries: u64,
+    pub blocked: u64,
+    pub percent_blocked: f64,
+    pub domains_blocked: u64,
+    pub blocking: bool,
+}
+
+/// Outcome of the latest poll of one Pi-hole.
+#[derive(Debug, Clone, Serialize)]
+pub struct PiholeStatus {
+    pub checked_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<PiholeStats>,
+    /// Why no statistics could be read, e.g. FTL not running.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// "unreachable", "FTL offline" or "API error" when there are no statistics.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub down: Option<&'static str>,
+}
+
+/// Polls Pi-hole's API, v6 first and the v5 `api.php` when that is missing.
+pub struct PiholeWatcher {
+    client: Arc<Mutex<PiholeClient>>,
+    last_poll: HashMap<String, DateTime<Utc>>,
+    status: BTreeMap<String, PiholeStatus>,
+}
+
+struct PiholeClient {
+    client: reqwest::Client,
+    /// v6 session ids, reused until they expire.
+    sessions: HashMap<String, String>,
+}
+
+impl PiholeWatcher {
+    pub fn new() -> Self {
+        Self {
+            client: Arc::new(Mutex::new(PiholeClient {
+                client: reqwest::Client::builder()
+                    .timeout(Duration::from_secs(10))
+                    .build()
+                    .unwrap_or_default(),
+                sessions: HashMap::new(),
+            })),
+            last_poll: HashMap::new(),
+            status: BTreeMap::new(),
+        }
+    }
+    
+    /// Marks the instances that are due a poll, to be read without holding the monitor.
+    pub fn next_poll(&mut self, instances: &[PiholeConfig]) -> PiholePoll {
+        self.last_poll.retain(|name, _| instances.iter().any(|pihole| &pihole.name == name));
+        
+        let now = Utc::now();
+        let mut due = Vec::new();
+        for pihole in instances {
+            if self.last_poll.get(&pihole.name).is_some_and(|last| (now - *last).num_seconds() < pihole.interval as i64) {
+                continue;
+            }
+            self.last_poll.insert(pihole.name.clone(), now);
+            due.push(pihole.clone());
+        }
+        
+        PiholePoll {
+            client: Arc::clone(&self.client),
+            configured: instances.iter().map(|pihole| pihole.name.clone()).collect(),
+            instances: due,
+            at: now,
+        }
+    }
+    
+    /// Keeps the statuses a [`PiholePoll`] read.
+    pub fn apply(&mut self, polled: PiholeStatuses, instances: &[PiholeConfig]) {
+        self.status.retain(|name, _| instances.iter().any(|pihole| &pihole.name == name));
+        for (name, status) in polled.statuses {
+            // A reload may have removed the instance while it was polled
+            if instances.iter().any(|pihole| pihole.name == name) {
+                self.status.insert(name, status);
+            }
+        }
+    }
+    
+    /// Pi-holes that cannot be read and those with blocking turned off.
+    pub fn anomalies(&self) -> Vec<String> {
+        self.status
+            .iter()
+            .filter_map(|(name, status)| match (&status.stats, status.down) {
+                (_, Some(down)) => Some(format!("Pi-hole Down: {} {}", name, down)),
+                (Some(stats), None) if !stats.blocking => Some(format!("Pi-hole Blocking Disabled: {} disabled", name)),
+                _ => None,
+            })
+            .collect()
+    }
+    
+    pub fn status(&self) -> &BTreeMap<String, PiholeStatus> {
+        &self.status
+    }
+}
+
+/// Instances due a poll.
+pub struct PiholePoll {
+    client: Arc<Mutex<PiholeClient>>,
+    configured: Vec<String>,
+    instances: Vec<PiholeConfig>,
+    at: DateTime<Utc>,
+}
+
+/// What a [`PiholePoll`] read, by instance.
+pub struct PiholeStatuses {
+    statuses: Vec<(String, PiholeStatus)>,
+}
+
+impl PiholePoll {
+    pub async fn run(self) -> PiholeStatuses {
+        let mut client = self.client.lock().await;
+        client.sessions.retain(|name, _| self.configured.contains(name));
+        
+        let mut statuses = Vec::new();
+        for pihole in self.instances {
+            let status = match client.fetch(&pihole).await {
+                Ok(stats) => PiholeStatus {
+                    checked_at: self.at,
+                    stats: Some(stats),
+                    error: None,
+                    down: None,
+                },
+                Err(e) => {
+                    warn!("Pi-hole {} check failed: {}", pihole.name, e);
+                    let unreachable = e
+                        .downcast_ref::<reqwest::Error>()
+                        .is_some_and(|e| e.is_connect() || e.is_timeout());
+                    let down = if unreachable {
+                        "unreachable"
+                    } else if e.to_string().contains("FTL") {
+                        "FTL offline"
+                    } else {
+                        "API error"
+                    };
+                    PiholeStatus {
+                        checked_at: self.at,
+                        stats: None,
+                        error: Some(e.to_string()),
+                        down: Some(down),
+                    }
+                }
+            };
+            statuses.push((pihole.name, status));
+        }
+        
+        PiholeStatuses { statuses }
+    }
+}
+
+impl PiholeClient {
+    async fn fetch(&mut self, pihole: &PiholeConfig) -> Result<PiholeStats> {
+        let base = pihole.url.trim_end_matches('/');
+        
+        let Some(summary) = self.get_v6(pihole, &format!("{}/api/stats/summary", base)).await? else {
+            return self.fetch_v5(pihole).await;
+        };
+        let blocking = self
+            .get_v6(pihole, &format!("{}/api/dns/blocking", base))
+            .await?
+            .ok_or_else(|| anyhow!("blocking status missing"))?;
+        
+        Ok(PiholeStats {
+            queries: summary["queries"]["total"].as_u64().unwrap_or(0),
+            blocked: summary["queries"]["blocked"].as_u64().unwrap_or(0),
+            percent_blocked: summary["queries"]["percent_blocked"].as_f64().unwrap_or(0.0),
+            domains_blocked: summary["gravity"]["domains_being_blocked"].as_u64().unwrap_or(0),
+            blocking: blocking["blocking"] == "enabled",
+        })
+    }
+    
+    /// GETs a v6 endpoint, logging in when the session is missing or expired.
+    /// Returns `None` when the endpoint does not exist, i.e. on Pi-hole v5.
+    async fn get_v6(&mut self, pihole: &PiholeConfig, url: &str) -> Result<Option<Value>> {
+        for attempt in 0..2 {
+            let mut request = self.client.get(url);
+            if let Some(sid) = self.sessions.get(&pihole.name) {
+                request = request.header("X-FTL-SID", sid);
+            }
+            let response = request.send().await?;
+            
+            match response.status() {
+                StatusCode::NOT_FOUND => return Ok(None),
+                StatusCode::UNAUTHORIZED if attempt == 0 && !pihole.password.is_empty() => {
+                    self.login(pihole).await?;
+                }
+                status if status.is_success() => return Ok(Some(response.json().await?)),
+                status => return Err(anyhow!("{} returned {}", url, status)),
+            }
+        }
+        
+        Err(anyhow!("{} rejected the password", pihole.url))
+    }
+    
+    async fn login(&mut self, pihole: &PiholeConfig) -> Result<()> {
+        let url = format!("{}/api/auth", pihole.url.trim_end_matches('/'));
+        let response: Value = self
+            .client
+            .post(&url)
+            .json(&json!({ "password": pihole.password }))
+            .send()
+            .await?
+            .json()
+            .await?;
+        
+        let sid = response["session"]["sid"]
+            .as_str()
+            .ok_or_else(|| anyhow!("{} rejected the password", pihole.url))?;
+        self.sessions.insert(pihole.name.clone(), sid.to_string());
+        Ok(())
+    }
+    
+    async fn fetch_v5(&self, pihole: &PiholeConfig) -> Result<PiholeStats> {
+        let url = format!("{}/admin/api.php", pihole.url.trim_end_matches('/'));
+        let summary: Value = self
+            .client
+            .get(&url)
+            .query(&[("summaryRaw", ""), ("auth", pihole.password.as_str())])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        
+        if summary["FTLnotrunning"].as_bool() == Some(true) {
+            return Err(anyhow!("FTL is not running"));
+        }
+        // Without a valid token api.php answers with an empty list
+        if summary.get("status").is_none() {
+            return Err(anyhow!("{} returned no statistics; check the API token", url));
+        }
+        
+        Ok(PiholeStats {
+            queries: summary["dns_queries_today"].as_u64().unwrap_or(0),
+            blocked: summary["ads_blocked_today"].as_u64().unwrap_or(0),
+            percent_blocked: summary["ads_percentage_today"].as_f64().unwrap_or(0.0),
+            domains_blocked: summary["domains_being_blocked"].as_u64().unwrap_or(0),
+            blocking: summary["status"] == "enabled",
+        })
+    }
+}
@@ -0,0 +1,151 @@
+use crate::models::metrics::SystemMetrics;
+use crate::services::collectors::Collector;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::path::Path;
+use tracing::{info, warn};
+use wasmtime::{Config, Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+/// Where plugin collectors are loaded from, one `<name>.wasm` module each.
+pub const PLUGIN_DIR: &str = "plugins";
+
+// Linear memory a plugin may grow to during one call
+const MAX_MEMORY: usize = 16 * 1024 * 1024;
+// Instructions, roughly, a plugin may run per call before it is stopped
+const FUEL_PER_CALL: u64 = 100_000_000;
+// Largest JSON reading a plugin may return
+const MAX_OUTPUT: usize = 64 * 1024;
+
+/// A user-provided WebAssembly module run as a collector. The module exports its
+/// `memory` and `collect() -> i64`, whose result packs a pointer (high 32 bits) and
+/// a length (low 32 bits) addressing a UTF-8 JSON object of metric names to
+/// numbers. It is given no imports, so it can reach nothing outside its own
+/// memory, and each call starts from a fresh instance limited in memory and fuel.
+pub struct PluginCollector {
+    name: String,
+    engine: Engine,
+    module: Module,
+}
+
+impl PluginCollector {
+    /// Compiles every `*.wasm` module in [`PLUGIN_DIR`]. A module that does not
+    /// compile is logged and skipped; a missing directory means no plugins.
+    pub fn load_all() -> Vec<PluginCollector> {
+        let Ok(entries) = std::fs::read_dir(PLUGIN_DIR) else {
+            return Vec::new();
+        };
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = match Engine::new(&config) {
+            Ok(engine) => engine,
+            Err(e) => {
+                warn!("WebAssembly runtime unavailable, plugins not loaded: {}", e);
+                return Vec::new();
+            }
+        };
+        
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "wasm"))
+            .collect();
+        paths.sort();
+        
+        paths
+            .into_iter()
+            .filter_map(|path| match Self::load(&engine, &path) {
+                Ok(plugin) => {
+                    info!("Loaded plugin collector {}", plugin.name);
+                    Some(plugin)
+                }
+                Err(e) => {
+                    warn!("Plugin {} not loaded: {}", path.display(), e);
+                    None
+                }
+            })
+            .collect()
+    }
+    
+    fn load(engine: &Engine, path: &Path) -> Result<PluginCollector> {
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .filter(|stem| !stem.is_empty())
+            .ok_or_else(|| anyhow!("file name is not a valid collector name"))?
+            .to_string();
+        let module = Module::from_file(engine, path)?;
+        if module.imports().next().is_some() {
+            return Err(anyhow!("plugins may not import anything"));
+        }
+        
+        Ok(PluginCollector {
+            name,
+            engine: engine.clone(),
+            module,
+        })
+    }
+}
+
+#[async_trait]
+impl Collector for PluginCollector {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    async fn collect(&mut self, metrics: &mut SystemMetrics) -> Result<()> {
+        // Fuel bounds the call, but it runs to the end, so it is kept off the runtime's threads
+        let (engine, module) = (self.engine.clone(), self.module.clone());
+        let values = tokio::task::spawn_blocking(move || call(&engine, &module)).await??;
+        
+        metrics
+            .custom
+            .extend(values.into_iter().map(|(metric, value)| (format!("{}.{}", self.name, metric), value)));
+        Ok(())
+    }
+}
+
+/// Instantiates the module and reads what its `collect` returns.
+fn call(engine: &Engine, module: &Module) -> Result<Vec<(String, f64)>> {
+    let limits = StoreLimitsBuilder::new()
+        .memory_size(MAX_MEMORY)
+        .instances(1)
+        .memories(1)
+        .tables(1)
+        .build();
+    let mut store: Store<StoreLimits> = Store::new(engine, limits);
+    store.limiter(|limits| limits);
+    store.set_fuel(FUEL_PER_CALL)?;
+    
+    let instance = Instance::new(&mut store, module, &[])?;
+    let memory = instance
+        .get_memory(&mut store, "memory")
+        .ok_or_else(|| anyhow!("plugin exports no memory"))?;
+    let collect = instance.get_typed_func::<(), i64>(&mut store, "collect")?;
+    // The trap itself, e.g. fuel running out, rather than the wasm backtrace around it
+    let packed = collect
+        .call(&mut store, ())
+        .map_err(|e| anyhow!("{}", anyhow::Error::from(e).root_cause()))? as u64;
+    
+    let (pointer, length) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+    if length > MAX_OUTPUT {
+        return Err(anyhow!("plugin returned {} bytes, more than {}", length, MAX_OUTPUT));
+    }
+    let output = memory
+        .data(&store)
+        .get(pointer..pointer + length)
+        .ok_or_else(|| anyhow!("plugin returned a range outside its memory"))?;
+    
+    parse_output(output)
+}
+
+/// Reads a JSON object of metric names to numbers; other values are ignored.
+fn parse_output(output: &[u8]) -> Result<Vec<(String, f64)>> {
+    let json: Value = serde_json::from_slice(output).map_err(|e| anyhow!("output is not JSON: {}", e))?;
+    let object = json.as_object().ok_or_else(|| anyhow!("output is not a JSON object"))?;
+    
+    Ok(object
+        .iter()
+        .filter_map(|(metric, value)| Some((metric.clone(), value.as_f64().filter(|value| value.is_finite())?)))
+        .collect())
+}
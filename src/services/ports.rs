@@ -0,0 +1,327 @@
+use crate::utils::data_dir;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use tracing::warn;
+
+pub const LISTENING_PORTS_FILE: &str = "data/listening_ports.json";
+
+// Socket states in /proc/net/*
+const TCP_ESTABLISHED: &str = "01";
+const TCP_LISTEN: &str = "0A";
+const UDP_UNCONNECTED: &str = "07";
+// A UDP socket given a peer with connect(), as clients of a single server do
+const UDP_CONNECTED: &str = "01";
+const TCP_STATES: [(&str, &str); 12] = [
+    ("01", "ESTABLISHED"),
+    ("02", "SYN_SENT"),
+    ("03", "SYN_RECV"),
+    ("04", "FIN_WAIT1"),
+    ("05", "FIN_WAIT2"),
+    ("06", "TIME_WAIT"),
+    ("07", "CLOSE"),
+    ("08", "CLOSE_WAIT"),
+    ("09", "LAST_ACK"),
+    ("0A", "LISTEN"),
+    ("0B", "CLOSING"),
+    ("0C", "NEW_SYN_RECV"),
+];
+
+/// A socket accepting connections or datagrams.
+#[derive(Debug, Clone, Serialize)]
+pub struct ListeningSocket {
+    pub protocol: &'static str,
+    pub address: IpAddr,
+    pub port: u16,
+    pub pid: Option<u32>,
+    pub process: Option<String>,
+    #[serde(skip)]
+    inode: u64,
+}
+
+impl ListeningSocket {
+    /// Identity of the listener across scans, e.g. `tcp 0.0.0.0:22`.
+    fn key(&self) -> String {
+        match self.address {
+            IpAddr::V6(v6) => format!("{} [{}]:{}", self.protocol, v6, self.port),
+            IpAddr::V4(v4) => format!("{} {}:{}", self.protocol, v4, self.port),
+        }
+    }
+    
+    fn owner(&self) -> String {
+        match (&self.process, self.pid) {
+            (Some(process), Some(pid)) => format!("{}({})", process, pid),
+            _ => "unknown".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Baseline {
+    listeners: BTreeSet<String>,
+}
+
+/// Alerts when something starts listening that was not listening before.
+pub struct PortWatcher {
+    known: Option<BTreeSet<String>>,
+}
+
+impl PortWatcher {
+    /// Starts from the listeners saved by earlier runs.
+    pub fn new() -> Self {
+        let known = std::fs::read_to_string(LISTENING_PORTS_FILE)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Baseline>(&content).ok())
+            .map(|baseline| baseline.listeners);
+        
+        Self { known }
+    }
+    
+    /// The scan of the next cycle, to be read without holding the monitor.
+    pub fn next_poll(&self, ignore_ports: &[u16]) -> PortPoll {
+        PortPoll {
+            known: self.known.clone(),
+            ignore_ports: ignore_ports.to_vec(),
+        }
+    }
+    
+    /// Keeps the listeners a [`PortPoll`] found. New listeners are reported once with
+    /// their owning process; one that closes is forgotten, so it is reported again if
+    /// it comes back.
+    pub fn apply(&mut self, scan: PortScan) -> Vec<String> {
+        let Some(known) = &mut self.known else {
+            self.known = Some(scan.current);
+            self.save();
+            return Vec::new();
+        };
+        
+        let new_sockets: Vec<&ListeningSocket> =
+            scan.new_sockets.iter().filter(|socket| !known.contains(&socket.key())).collect();
+        let closed = known.len() + new_sockets.len() != scan.current.len();
+        if new_sockets.is_empty() && !closed {
+            return Vec::new();
+        }
+        
+        let alerts = new_sockets
+            .iter()
+            .map(|socket| format!("New Listening Port: {} {}", socket.key(), socket.owner()))
+            .collect();
+        *known = scan.current;
+        self.save();
+        
+        alerts
+    }
+    
+    fn save(&self) {
+        let baseline = Baseline {
+            listeners: self.known.clone().unwrap_or_default(),
+        };
+        let result = data_dir::ensure()
+            .and_then(|_| std::fs::write(LISTENING_PORTS_FILE, serde_json::to_string_pretty(&baseline)?));
+        if let Err(e) = result {
+            warn!("Failed to save listening ports: {}", e);
+        }
+    }
+}
+
+/// The listeners known at the previous scan.
+pub struct PortPoll {
+    known: Option<BTreeSet<String>>,
+    ignore_ports: Vec<u16>,
+}
+
+/// What a [`PortPoll`] found: every current listener, and those not known before
+/// with their owning processes.
+pub struct PortScan {
+    current: BTreeSet<String>,
+    new_sockets: Vec<ListeningSocket>,
+}
+
+impl PortPoll {
+    /// Reads the current listeners, and walks the process table for the owners of
+    /// new ones only.
+    pub async fn run(self) -> Option<PortScan> {
+        let scan = tokio::task::spawn_blocking(move || {
+            let mut sockets = listening_sockets(false);
+            let ephemeral = ephemeral_ports();
+            // Clients bind UDP sockets to random ephemeral ports all the time
+            sockets.retain(|socket| !(socket.protocol.starts_with("udp") && ephemeral.contains(&socket.port)));
+            sockets.retain(|socket| !self.ignore_ports.contains(&socket.port));
+            let current: BTreeSet<String> = sockets.iter().map(ListeningSocket::key).collect();
+            
+            let mut new_sockets: Vec<ListeningSocket> = match &self.known {
+                Some(known) => sockets.into_iter().filter(|socket| !known.contains(&socket.key())).collect(),
+                None => Vec::new(),
+            };
+            if !new_sockets.is_empty() {
+                resolve_owners(&mut new_sockets);
+            }
+            PortScan { current, new_sockets }
+        });
+        scan.await.ok()
+    }
+}
+
+/// Every listening TCP socket and bound, unconnected UDP socket, optionally with
+/// the owning processes (which needs a walk over every process's descriptors).
+pub fn listening_sockets(with_owners: bool) -> Vec<ListeningSocket> {
+    let mut sockets = Vec::new();
+    
+    for (protocol, file, state) in [
+        ("tcp", "/proc/net/tcp", TCP_LISTEN),
+        ("tcp6", "/proc/net/tcp6", TCP_LISTEN),
+        ("udp", "/proc/net/udp", UDP_UNCONNECTED),
+        ("udp6", "/proc/net/udp6", UDP_UNCONNECTED),
+    ] {
+        let content = std::fs::read_to_string(file).unwrap_or_default();
+        for line in content.lines().skip(1) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 10 || fields[3] != state {
+                continue;
+            }
+            let (Some((address, port)), Ok(inode)) = (parse_address(fields[1]), fields[9].parse::<u64>()) else {
+                continue;
+            };
+            sockets.push(ListeningSocket {
+                protocol: if protocol.starts_with("tcp") { "tcp" } else { "udp" },
+                address,
+                port,
+                pid: None,
+                process: None,
+                inode,
+            });
+        }
+    }
+    
+    sockets.sort_by_key(|socket| (socket.protocol, socket.port, socket.address));
+    sockets.dedup_by_key(|socket| socket.key());
+    if with_owners {
+        resolve_owners(&mut sockets);
+    }
+    sockets
+}
+
+/// The remote addresses of established TCP connections and of connected UDP
+/// sockets, without loopback peers. Half-open connections are left out: the source
+/// of a SYN is trivially spoofed, so it is no sign the peer really connected. IPv4 peers of IPv6 sockets
+/// are given as IPv4.
+pub fn connected_peers() -> BTreeSet<IpAddr> {
+    let mut peers = BTreeSet::new();
+    
+    for (file, state) in [
+        ("/proc/net/tcp", TCP_ESTABLISHED),
+        ("/proc/net/tcp6", TCP_ESTABLISHED),
+        ("/proc/net/udp", UDP_CONNECTED),
+        ("/proc/net/udp6", UDP_CONNECTED),
+    ] {
+        let content = std::fs::read_to_string(file).unwrap_or_default();
+        for line in content.lines().skip(1) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 4 || fields[3] != state {
+                continue;
+            }
+            let Some((address, _)) = parse_address(fields[2]) else {
+                continue;
+            };
+            let address = match address {
+                IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(address),
+                IpAddr::V4(_) => address,
+            };
+            if !address.is_loopback() && !address.is_unspecified() {
+                peers.insert(address);
+            }
+        }
+    }
+    
+    peers
+}
+
+/// The number of IPv4 and IPv6 TCP sockets in each state, by the state's name,
+/// e.g. `ESTABLISHED`. States without sockets are left out.
+pub fn tcp_state_counts() -> BTreeMap<String, u32> {
+    let mut counts = BTreeMap::new();
+    
+    for file in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        let content = std::fs::read_to_string(file).unwrap_or_default();
+        for line in content.lines().skip(1) {
+            let Some(state) = line.split_whitespace().nth(3) else {
+                continue;
+            };
+            if let Some((_, name)) = TCP_STATES.iter().find(|(code, _)| *code == state) {
+                *counts.entry(name.to_string()).or_insert(0) += 1;
+            }
+        }
+    }
+    
+    counts
+}
+
+/// Parses `0100007F:0016` (little-endian words) into an address and port.
+fn parse_address(hex: &str) -> Option<(IpAddr, u16)> {
+    let (ip_hex, port_hex) = hex.split_once(':')?;
+    let port = u16::from_str_radix(port_hex, 16).ok()?;
+    
+    let address = match ip_hex.len() {
+        8 => IpAddr::V4(Ipv4Addr::from(u32::from_str_radix(ip_hex, 16).ok()?.swap_bytes())),
+        32 => {
+            let mut bytes = [0u8; 16];
+            for (i, chunk) in bytes.chunks_mut(4).enumerate() {
+                let word = u32::from_str_radix(&ip_hex[i * 8..i * 8 + 8], 16).ok()?;
+                chunk.copy_from_slice(&word.to_le_bytes());
+            }
+            IpAddr::V6(Ipv6Addr::from(bytes))
+        }
+        _ => return None,
+    };
+    
+    Some((address, port))
+}
+
+/// Finds the processes holding the sockets. Processes of other users are only
+/// visible when running as root.
+fn resolve_owners(sockets: &mut [ListeningSocket]) {
+    let wanted: HashSet<u64> = sockets.iter().map(|socket| socket.inode).collect();
+    let mut owners: HashMap<u64, (u32, String)> = HashMap::new();
+    
+    let Ok(processes) = std::fs::read_dir("/proc") else {
+        return;
+    };
+    for process in processes.filter_map(|entry| entry.ok()) {
+        let Some(pid) = process.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+            continue;
+        };
+        let Ok(fds) = std::fs::read_dir(process.path().join("fd")) else {
+            continue;
+        };
+        for fd in fds.filter_map(|entry| entry.ok()) {
+            let Ok(target) = std::fs::read_link(fd.path()) else {
+                continue;
+            };
+            let inode = target
+                .to_str()
+                .and_then(|target| target.strip_prefix("socket:["))
+                .and_then(|target| target.strip_suffix(']'))
+                .and_then(|inode| inode.parse::<u64>().ok());
+            if let Some(inode) = inode.filter(|inode| wanted.contains(inode)) {
+                let name = std::fs::read_to_string(process.path().join("comm")).unwrap_or_default();
+                owners.entry(inode).or_insert((pid, name.trim().to_string()));
+            }
+        }
+    }
+    
+    for socket in sockets.iter_mut() {
+        if let Some((pid, name)) = owners.get(&socket.inode) {
+            socket.pid = Some(*pid);
+            socket.process = Some(name.clone());
+        }
+    }
+}
+
+fn ephemeral_ports() -> std::ops::RangeInclusive<u16> {
+    let range = std::fs::read_to_string("/proc/sys/net/ipv4/ip_local_port_range").unwrap_or_default();
+    let bounds: Vec<u16> = range.split_whitespace().filter_map(|bound| bound.parse().ok()).collect();
+    match bounds.as_slice() {
+        [low, high] => *low..=*high,
+        _ => 32768..=60999,
+    }
+}
@@ -0,0 +1,80 @@
+use crate::models::config::{CheckConfig, ProbeModule, ProberKind};
+use crate::models::metrics::CheckResult;
+use crate::services::checks::tls_connector;
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration, Instant};
+use tokio_rustls::rustls::ServerName;
+use tracing::debug;
+
+/// Runs the module's prober against a check expanded from `monitoring.probes`. The
+/// latency covers the whole probe, e.g. connect plus handshake for TLS.
+pub async fn run_probe(module: &ProbeModule, check: &CheckConfig) -> CheckResult {
+    let start = Instant::now();
+    
+    let probe = async {
+        match module.prober {
+            ProberKind::TcpConnect => tcp_connect(check).await.map(drop),
+            ProberKind::Tls => tls(module, check).await,
+            ProberKind::Http => http(module, check).await,
+            ProberKind::Dns => dns(module, check).await,
+        }
+    };
+    match timeout(Duration::from_secs(check.timeout), probe).await {
+        Ok(Ok(())) => CheckResult::up(start.elapsed().as_millis() as f64),
+        Ok(Err(e)) => {
+            debug!("Probe {} failed: {}", check.name, e);
+            CheckResult::down()
+        }
+        Err(_) => CheckResult::down(),
+    }
+}
+
+async fn tcp_connect(check: &CheckConfig) -> Result<TcpStream> {
+    let port = check.port.ok_or_else(|| anyhow!("no port"))?;
+    Ok(TcpStream::connect((check.target.as_str(), port)).await?)
+}
+
+async fn tls(module: &ProbeModule, check: &CheckConfig) -> Result<()> {
+    let stream = tcp_connect(check).await?;
+    let name = ServerName::try_from(check.target.as_str()).map_err(|_| anyhow!("invalid TLS name {}", check.target))?;
+    tls_connector(module.verify_tls).connect(name, stream).await?;
+    Ok(())
+}
+
+async fn http(module: &ProbeModule, check: &CheckConfig) -> Result<()> {
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(!module.verify_tls)
+        .build()?;
+    let response = client.get(&check.target).send().await?;
+    
+    let status = response.status();
+    let valid = if module.valid_status.is_empty() {
+        status.is_success()
+    } else {
+        module.valid_status.contains(&status.as_u16())
+    };
+    if !valid {
+        return Err(anyhow!("status {}", status));
+    }
+    
+    if let Some(pattern) = &module.body_regex {
+        let body = response.text().await?;
+        if !Regex::new(pattern)?.is_match(&body) {
+            return Err(anyhow!("body does not match {}", pattern));
+        }
+    }
+    Ok(())
+}
+
+async fn dns(module: &ProbeModule, check: &CheckConfig) -> Result<()> {
+    let name = check.target.clone();
+    let addresses = tokio::task::spawn_blocking(move || dns_lookup::lookup_host(&name)).await??;
+    
+    match module.expect_answers.iter().find(|expected| !addresses.contains(expected)) {
+        Some(missing) => Err(anyhow!("{} not among the answers", missing)),
+        None if addresses.is_empty() => Err(anyhow!("no answers")),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,189 @@
+use crate::models::config::{MonitoringConfig, WatchAction, WatchedProcessConfig};
+use crate::models::metrics::SystemMetrics;
+use crate::services::collectors::Collector;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+// Service managers return once the daemon has started, which can take a while on a Pi
+const RESTART_TIMEOUT: Duration = Duration::from_secs(60);
+// The kernel keeps this much of a process name in /proc/<pid>/comm
+const COMM_LEN: usize = 15;
+
+#[derive(Default)]
+struct ProcessState {
+    pid: Option<u32>,
+    /// When the process was first found missing, while it is.
+    down_since: Option<DateTime<Utc>>,
+    last_restart: Option<DateTime<Utc>>,
+    /// Restarts run since it went down.
+    restarts: u32,
+    /// Whether it came back after a restart at the latest run.
+    recovered: bool,
+}
+
+/// Checks that configured daemons are running and, for those with a restart
+/// action, runs their command while they are not.
+pub struct ProcessWatchCollector {
+    processes: Vec<WatchedProcessConfig>,
+    state: BTreeMap<String, ProcessState>,
+}
+
+impl ProcessWatchCollector {
+    pub fn new() -> Self {
+        Self {
+            processes: Vec::new(),
+            state: BTreeMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Collector for ProcessWatchCollector {
+    fn name(&self) -> &str {
+        "processes"
+    }
+    
+    fn configure(&mut self, config: &MonitoringConfig) {
+        self.state.retain(|name, _| config.processes.iter().any(|process| &process.name == name));
+        self.processes = config.processes.clone();
+    }
+    
+    async fn collect(&mut self, _metrics: &mut SystemMetrics) -> Result<()> {
+        let now = Utc::now();
+        
+        for process in &self.processes {
+            let state = self.state.entry(process.name.clone()).or_default();
+            state.pid = find_pid(process);
+            state.recovered = false;
+            
+            if state.pid.is_some() {
+                if state.down_since.take().is_some() && state.restarts > 0 {
+                    info!("Watched process {} is running again after {} restarts", process.name, state.restarts);
+                    state.recovered = true;
+                }
+                state.restarts = 0;
+                state.last_restart = None;
+                continue;
+            }
+            
+            state.down_since.get_or_insert(now);
+            let due = state
+                .last_restart
+                .map_or(true, |last| (now - last).num_seconds() >= process.restart_interval as i64);
+            let exhausted = process.max_restarts > 0 && state.restarts >= process.max_restarts;
+            if process.action == WatchAction::Restart && due && !exhausted {
+                state.last_restart = Some(now);
+                state.restarts += 1;
+                warn!("Watched process {} is not running; restarting it (attempt {})", process.name, state.restarts);
+                // The command runs on its own so a slow start cannot hold up the cycle
+                let (name, command) = (process.name.clone(), process.command.clone());
+                tokio::spawn(async move {
+                    if let Err(e) = restart(&command).await {
+                        warn!("Restarting {} failed: {}", name, e);
+                    }
+                });
+            }
+        }
+        Ok(())
+    }
+    
+    fn anomalies(&self) -> Vec<String> {
+        let mut anomalies = Vec::new();
+        
+        for process in &self.processes {
+            let Some(state) = self.state.get(&process.name) else {
+                continue;
+            };
+            if state.recovered {
+                anomalies.push(format!("Process Restarted: {}", process.name));
+            }
+            if state.down_since.is_none() {
+                continue;
+            }
+            // The subject stays the same while restarts are tried so the alert is not re-sent
+            // for every attempt; the count is in the details
+            anomalies.push(format!("Process Down: {}", process.name));
+        }
+        anomalies
+    }
+    
+    fn details(&self) -> Option<Value> {
+        let processes: Vec<Value> = self
+            .state
+            .iter()
+            .map(|(name, state)| {
+                let gave_up = self.processes.iter().any(|process| {
+                    process.name == *name
+                        && process.action == WatchAction::Restart
+                        && process.max_restarts > 0
+                        && state.restarts >= process.max_restarts
+                });
+                json!({
+                    "name": name,
+                    "pid": state.pid,
+                    "down_since": state.down_since,
+                    "restarts": state.restarts,
+                    "gave_up": state.down_since.is_some() && gave_up,
+                })
+            })
+            .collect();
+        (!processes.is_empty()).then(|| json!({ "processes": processes }))
+    }
+}
+
+/// The pid of a running instance: the one in the pidfile when it is alive, or
+/// the first process of that name.
+fn find_pid(process: &WatchedProcessConfig) -> Option<u32> {
+    if let Some(pidfile) = &process.pidfile {
+        let pid: u32 = std::fs::read_to_string(pidfile).ok()?.trim().parse().ok()?;
+        return is_alive(pid).then_some(pid);
+    }
+    
+    let name = process.process.as_deref()?;
+    let name = &name[..name.char_indices().nth(COMM_LEN).map_or(name.len(), |(index, _)| index)];
+    std::fs::read_dir("/proc")
+        .ok()?
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .find(|pid| {
+            std::fs::read_to_string(format!("/proc/{}/comm", pid)).is_ok_and(|comm| comm.trim_end() == name)
+                && is_alive(*pid)
+        })
+}
+
+/// Whether the process exists and has not exited; a zombie is only waiting to be reaped.
+fn is_alive(pid: u32) -> bool {
+    let Ok(stat) = std::fs::read_to_string(format!("/proc/{}/stat", pid)) else {
+        return false;
+    };
+    // The state follows the name, which is in parentheses and may contain spaces
+    let state = stat.rsplit_once(')').and_then(|(_, rest)| rest.split_whitespace().next());
+    !matches!(state, Some("Z" | "X") | None)
+}
+
+/// Runs a restart command. A command that has not finished within the timeout is
+/// taken to be the daemon itself, started in the foreground, and left running.
+async fn restart(command: &[String]) -> Result<()> {
+    let (program, args) = command.split_first().ok_or_else(|| anyhow!("no command"))?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| anyhow!("{}: {}", program, e))?;
+    if let Ok(status) = tokio::time::timeout(RESTART_TIMEOUT, child.wait()).await {
+        let status = status?;
+        if !status.success() {
+            return Err(anyhow!("{} exited with {}", program, status));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,197 @@
+use crate::models::config::ProxmoxConfig;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use tracing::warn;
+
+/// A node or guest from `/cluster/resources`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxmoxResource {
+    /// "node", "qemu" or "lxc".
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Missing on cluster-wide resources such as SDN zones.
+    #[serde(default)]
+    pub node: String,
+    #[serde(default)]
+    pub vmid: Option<u32>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub status: String,
+    /// Fraction of `maxcpu` in use.
+    #[serde(default)]
+    pub cpu: f64,
+    #[serde(default)]
+    pub maxcpu: f64,
+    #[serde(default)]
+    pub mem: u64,
+    #[serde(default)]
+    pub maxmem: u64,
+    #[serde(default)]
+    pub disk: u64,
+    #[serde(default)]
+    pub maxdisk: u64,
+    #[serde(default)]
+    pub netin: u64,
+    #[serde(default)]
+    pub netout: u64,
+    #[serde(default)]
+    pub uptime: u64,
+    #[serde(default)]
+    pub template: u8,
+}
+
+impl ProxmoxResource {
+    pub fn is_guest(&self) -> bool {
+        self.kind == "qemu" || self.kind == "lxc"
+    }
+    
+    /// "pve1" for nodes, "pve1/101 web" for guests.
+    pub fn label(&self) -> String {
+        match self.vmid {
+            Some(vmid) => format!("{}/{} {}", self.node, vmid, self.name.as_deref().unwrap_or_default()),
+            None => self.node.clone(),
+        }
+    }
+    
+    pub fn is_up(&self) -> bool {
+        matches!(self.status.as_str(), "online" | "running")
+    }
+}
+
+#[derive(Deserialize)]
+struct ResourceList {
+    data: Vec<serde_json::Value>,
+}
+
+/// Polls Proxmox VE clusters for node and guest status.
+pub struct ProxmoxWatcher {
+    last_poll: HashMap<String, DateTime<Utc>>,
+    /// Nodes and guests of each cluster, templates excluded.
+    resources: BTreeMap<String, Vec<ProxmoxResource>>,
+}
+
+impl ProxmoxWatcher {
+    pub fn new() -> Self {
+        Self {
+            last_poll: HashMap::new(),
+            resources: BTreeMap::new(),
+        }
+    }
+    
+    /// Marks the clusters that are due a poll, to be read without holding the monitor.
+    pub fn next_poll(&mut self, clusters: &[ProxmoxConfig]) -> ProxmoxPoll {
+        self.last_poll.retain(|name, _| clusters.iter().any(|cluster| &cluster.name == name));
+        
+        let now = Utc::now();
+        let mut due = Vec::new();
+        for cluster in clusters {
+            if self.last_poll.get(&cluster.name).is_some_and(|last| (now - *last).num_seconds() < cluster.interval as i64) {
+                continue;
+            }
+            self.last_poll.insert(cluster.name.clone(), now);
+            due.push(cluster.clone());
+        }
+        
+        ProxmoxPoll { clusters: due }
+    }
+    
+    /// Keeps the resources a [`ProxmoxPoll`] read and returns an alert for each guest
+    /// that was running at the previous poll and no longer is.
+    pub fn apply(&mut self, polled: ProxmoxResources, clusters: &[ProxmoxConfig]) -> Vec<String> {
+        self.resources.retain(|name, _| clusters.iter().any(|cluster| &cluster.name == name));
+        
+        let mut alerts = Vec::new();
+        for (name, current) in polled.resources {
+            // A reload may have removed the cluster while it was polled
+            if !clusters.iter().any(|cluster| cluster.name == name) {
+                continue;
+            }
+            if let Some(previous) = self.resources.get(&name) {
+                for guest in current.iter().filter(|resource| resource.is_guest() && !resource.is_up()) {
+                    let was_running = previous.iter().any(|known| known.vmid == guest.vmid && known.is_up());
+                    if was_running {
+                        alerts.push(format!("Proxmox Guest Stopped: {}/{} {}", name, guest.label(), guest.status));
+                    }
+                }
+            }
+            self.resources.insert(name, current);
+        }
+        
+        alerts
+    }
+    
+    /// Nodes the cluster reports as offline.
+    pub fn anomalies(&self) -> Vec<String> {
+        self.resources
+            .iter()
+            .flat_map(|(cluster, resources)| {
+                resources
+                    .iter()
+                    .filter(|resource| resource.kind == "node" && !resource.is_up())
+                    .map(move |node| format!("Proxmox Node Offline: {}/{} {}", cluster, node.node, node.status))
+            })
+            .collect()
+    }
+    
+    pub fn resources(&self) -> &BTreeMap<String, Vec<ProxmoxResource>> {
+        &self.resources
+    }
+}
+
+/// Clusters due a poll.
+pub struct ProxmoxPoll {
+    clusters: Vec<ProxmoxConfig>,
+}
+
+/// The resources a [`ProxmoxPoll`] read, by cluster; failed clusters are logged and left out.
+pub struct ProxmoxResources {
+    resources: Vec<(String, Vec<ProxmoxResource>)>,
+}
+
+impl ProxmoxPoll {
+    pub async fn run(self) -> ProxmoxResources {
+        let mut resources = Vec::new();
+        for cluster in self.clusters {
+            match fetch_resources(&cluster).await {
+                Ok(current) => resources.push((cluster.name, current)),
+                Err(e) => warn!("Proxmox poll of {} failed: {}", cluster.name, e),
+            }
+        }
+        ProxmoxResources { resources }
+    }
+}
+
+async fn fetch_resources(cluster: &ProxmoxConfig) -> Result<Vec<ProxmoxResource>> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .danger_accept_invalid_certs(!cluster.verify_tls)
+        .build()?;
+    
+    let url = format!("{}/api2/json/cluster/resources", cluster.url.trim_end_matches('/'));
+    let response = client
+        .get(&url)
+        .header("Authorization", format!("PVEAPIToken={}={}", cluster.token_id, cluster.token_secret))
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("{} returned {}", url, response.status()));
+    }
+    
+    // Storage, pools and SDN entries have other fields, so only nodes and guests are parsed
+    let mut resources = Vec::new();
+    for resource in response.json::<ResourceList>().await?.data {
+        if !matches!(resource["type"].as_str(), Some("node" | "qemu" | "lxc")) {
+            continue;
+        }
+        let resource: ProxmoxResource = serde_json::from_value(resource)?;
+        if resource.template == 0 {
+            resources.push(resource);
+        }
+    }
+    resources.sort_by_key(|resource| (resource.node.clone(), resource.vmid));
+    Ok(resources)
+}
@@ -0,0 +1,294 @@
+use crate::models::config::{DnsRecordType, PublicIpConfig};
+use crate::models::events::{Event, EventKind};
+use crate::models::metrics::AnomalySeverity;
+use crate::services::dns;
+use crate::services::events::EventLog;
+use crate::services::monitor::MonitorService;
+use crate::utils::data_dir;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+use tokio::fs::{self, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+pub const PUBLIC_IP_FILE: &str = "data/public_ip.jsonl";
+const REQUEST_TIMEOUT_SECS: u64 = 15;
+pub const DNSBL_SOURCE: &str = "dnsbl";
+
+/// A change of the WAN address, one line of `data/public_ip.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicIpChange {
+    pub timestamp: DateTime<Utc>,
+    pub address: String,
+    pub previous: Option<String>,
+}
+
+/// Tracks the WAN address, alerting on changes and pushing them to a DDNS provider,
+/// and watches DNS blocklists for it.
+pub struct PublicIpService {
+    config: PublicIpConfig,
+    current: Option<String>,
+    /// When the blocklists were last queried, and for which address.
+    dnsbl_checked: Option<(DateTime<Utc>, String)>,
+    listed_on: Vec<String>,
+    monitor: Arc<RwLock<MonitorService>>,
+    event_log: Arc<EventLog>,
+}
+
+impl PublicIpService {
+    pub fn new(config: &PublicIpConfig, monitor: Arc<RwLock<MonitorService>>, event_log: Arc<EventLog>) -> Self {
+        // The last recorded address lets a change made while we were stopped be noticed
+        let current = std::fs::read_to_string(PUBLIC_IP_FILE)
+            .ok()
+            .and_then(|content| content.lines().rev().find_map(|line| serde_json::from_str::<PublicIpChange>(line).ok()))
+            .map(|change| change.address);
+        
+        Self {
+            config: config.clone(),
+            current,
+            dnsbl_checked: None,
+            listed_on: Vec::new(),
+            monitor,
+            event_log,
+        }
+    }
+    
+    pub fn apply_config(&mut self, config: &PublicIpConfig) {
+        if config.dnsbl != self.config.dnsbl {
+            self.dnsbl_checked = None;
+        }
+        self.config = config.clone();
+    }
+    
+    pub fn interval(&self) -> u64 {
+        self.config.interval.max(60)
+    }
+    
+    /// Resolves the address and handles a change. Does nothing when disabled.
+    pub async fn refresh(&mut self) -> Result<()> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        
+        let address = self.resolve().await?;
+        if self.current.as_deref() != Some(address.as_str()) {
+            self.handle_change(address.clone()).await;
+        }
+        self.check_blocklists(&address).await;
+        
+        Ok(())
+    }
+    
+    async fn handle_change(&mut self, address: String) {
+        let previous = self.current.replace(address.clone());
+        let change = PublicIpChange {
+            timestamp: Utc::now(),
+            address: address.clone(),
+            previous: previous.clone(),
+        };
+        if let Err(e) = append_change(&change).await {
+            warn!("Failed to record public IP change: {}", e);
+        }
+        
+        // The first address ever seen is not a change
+        let Some(previous) = previous else {
+            info!("Public IP is {}", address);
+            return;
+        };
+        
+        warn!("Public IP changed from {} to {}", previous, address);
+        self.monitor
+            .write()
+            .await
+            .raise_transient(format!("Public IP Changed: {} -> {}", previous, address));
+        
+        if let Some(url) = &self.config.ddns_update_url {
+            self.update_ddns(url, &address).await;
+        }
+    }
+    
+    /// Looks the address up in each blocklist when due, or right away after it changed.
+    /// Listings stay raised as anomalies until the zone drops the address.
+    async fn check_blocklists(&mut self, address: &str) {
+        let Ok(ip) = address.parse::<IpAddr>() else {
+            return self.set_listed_on(address, Vec::new()).await;
+        };
+        let due = self.dnsbl_checked.as_ref().map_or(true, |(checked_at, checked)| {
+            checked != address || (Utc::now() - *checked_at).num_seconds() >= self.config.dnsbl_interval as i64
+        });
+        if !due {
+            return;
+        }
+        self.dnsbl_checked = Some((Utc::now(), address.to_string()));
+        
+        let mut listed_on = Vec::new();
+        for zone in &self.config.dnsbl {
+            match dnsbl_listed(ip, zone).await {
+                Ok(true) => listed_on.push(zone.clone()),
+                Ok(false) => {}
+                Err(e) => {
+                    // Keep the previous verdict rather than clearing a listing on a lookup failure
+                    warn!("Blocklist lookup in {} failed: {}", zone, e);
+                    if self.listed_on.contains(zone) {
+                        listed_on.push(zone.clone());
+                    }
+                }
+            }
+        }
+        
+        self.set_listed_on(address, listed_on).await;
+    }
+    
+    async fn set_listed_on(&mut self, address: &str, listed_on: Vec<String>) {
+        for zone in self.listed_on.iter().filter(|zone| !listed_on.contains(zone)) {
+            info!("{} is no longer listed on {}", address, zone);
+            self.event_log
+                .record(Event::new(
+                    EventKind::Action,
+                    AnomalySeverity::Info,
+                    format!("{} is no longer listed on {}", address, zone),
+                ))
+                .await;
+        }
+        
+        let anomalies = listed_on
+            .iter()
+            .map(|zone| format!("Blocklisted: {} lists {}", zone, address))
+            .collect();
+        self.monitor.write().await.set_external_anomalies(DNSBL_SOURCE, anomalies);
+        self.listed_on = listed_on;
+    }
+    
+    /// Asks each configured service in turn, returning the first address found.
+    async fn resolve(&self) -> Result<String> {
+        let client = reqwest::Client::builder()
+            .user_agent("ShaydZ-SuperMonitor/2.0")
+            .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .build()?;
+        
+        for url in &self.config.services {
+            match client.get(url).send().await {
+                Ok(response) => match response.text().await {
+                    Ok(body) => {
+                        if let Some(address) = find_address(&body) {
+                            return Ok(address);
+                        }
+                        warn!("No IP address in response from {}", url);
+                    }
+                    Err(e) => warn!("Public IP service {} failed: {}", url, e),
+                },
+                Err(e) => warn!("Public IP service {} failed: {}", url, e),
+            }
+        }
+        
+        Err(anyhow!("No public IP service answered"))
+    }
+    
+    async fn update_ddns(&self, url: &str, address: &str) {
+        let result = async {
+            let client = reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS))
+                .build()?;
+            let response = client.get(url.replace("{ip}", address)).send().await?;
+            if !response.status().is_success() {
+                return Err(anyhow!("provider returned {}", response.status()));
+            }
+            Ok(())
+        }
+        .await;
+        
+        // The URL usually carries a token, so only the outcome is logged
+        let (severity, message) = match result {
+            Ok(()) => (AnomalySeverity::Info, format!("Updated dynamic DNS to {}", address)),
+            Err(e) => {
+                warn!("Dynamic DNS update failed: {}", e);
+                (AnomalySeverity::Warning, format!("Dynamic DNS update to {} failed: {}", address, e))
+            }
+        };
+        self.event_log
+            .record(Event::new(EventKind::Action, severity, message))
+            .await;
+    }
+}
+
+/// Recorded address changes, oldest first.
+pub async fn history() -> Vec<PublicIpChange> {
+    let content = fs::read_to_string(PUBLIC_IP_FILE).await.unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+async fn append_change(change: &PublicIpChange) -> Result<()> {
+    data_dir::ensure_async().await?;
+    
+    let mut line = serde_json::to_string(change)?;
+    line.push('\n');
+    
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(PUBLIC_IP_FILE)
+        .await?;
+    file.write_all(line.as_bytes()).await?;
+    
+    Ok(())
+}
+
+/// Whether `zone` lists the address. Listings answer with an address in 127.0.0.0/8.
+/// Zones without IPv6 data simply never list an IPv6 address.
+async fn dnsbl_listed(address: IpAddr, zone: &str) -> Result<bool> {
+    let answers = dns::resolve(&format!("{}.{}", dnsbl_name(address), zone), DnsRecordType::A).await?;
+    let codes: Vec<Ipv4Addr> = answers.iter().filter_map(|answer| answer.parse().ok()).collect();
+    
+    // 127.255.255.x are error codes, e.g. Spamhaus refusing queries from public resolvers
+    if !codes.is_empty() && codes.iter().all(|code| code.octets()[..3] == [127, 255, 255]) {
+        return Err(anyhow!("query refused ({})", codes[0]));
+    }
+    Ok(codes.iter().any(|code| code.octets()[0] == 127))
+}
+
+/// The address reversed as blocklists are queried: by octet for IPv4 and by
+/// nibble for IPv6 (RFC 5782).
+fn dnsbl_name(address: IpAddr) -> String {
+    match address {
+        IpAddr::V4(v4) => {
+            let [a, b, c, d] = v4.octets();
+            format!("{}.{}.{}.{}", d, c, b, a)
+        }
+        IpAddr::V6(v6) => v6
+            .octets()
+            .iter()
+            .rev()
+            .flat_map(|byte| [byte & 0x0f, byte >> 4])
+            .map(|nibble| format!("{:x}", nibble))
+            .collect::<Vec<_>>()
+            .join("."),
+    }
+}
+
+/// First public address in `body`, so plain-text services and router status pages
+/// (which also list LAN addresses) both work.
+fn find_address(body: &str) -> Option<String> {
+    body.split(|c: char| !(c.is_ascii_hexdigit() || c == '.' || c == ':'))
+        .filter(|token| token.contains('.') || token.matches(':').count() >= 2)
+        .filter_map(|token| token.parse::<IpAddr>().ok())
+        .find(is_public)
+        .map(|address| address.to_string())
+}
+
+fn is_public(address: &IpAddr) -> bool {
+    match address {
+        IpAddr::V4(v4) => !(v4.is_private() || v4.is_loopback() || v4.is_link_local() || v4.is_unspecified()),
+        // fc00::/7 is unique local, fe80::/10 link local
+        IpAddr::V6(v6) => {
+            let first = v6.segments()[0];
+            !(v6.is_loopback() || v6.is_unspecified() || first & 0xfe00 == 0xfc00 || first & 0xffc0 == 0xfe80)
+        }
+    }
+}
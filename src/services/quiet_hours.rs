@@ -0,0 +1,113 @@
+use crate::models::config::QuietHours;
+use crate::models::metrics::AnomalySeverity;
+use crate::utils::data_dir;
+use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tokio::fs;
+use tracing::warn;
+
+const DIGEST_FILE: &str = "data/notification_digest.json";
+
+/// An alert held back by quiet hours, as it would have been sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeldAlert {
+    pub channel: String,
+    /// The anomaly, which picks the webhooks the digest goes to.
+    pub anomaly: String,
+    pub text: String,
+    /// `firing` or `resolved`.
+    pub state: String,
+    pub severity: AnomalySeverity,
+    pub incident: u64,
+    pub held_at: DateTime<Utc>,
+}
+
+/// Whether an alert of `severity` on `channel` is held now.
+pub fn holds(quiet_hours: &[QuietHours], channel: &str, severity: AnomalySeverity) -> bool {
+    let now = Local::now().time();
+    quiet_hours.iter().any(|quiet| {
+        quiet.channel == channel
+            && quiet.contains(now)
+            && !(quiet.allow_critical && severity == AnomalySeverity::Critical)
+    })
+}
+
+/// Whether `channel` is in quiet hours now.
+pub fn is_quiet(quiet_hours: &[QuietHours], channel: &str) -> bool {
+    let now = Local::now().time();
+    quiet_hours.iter().any(|quiet| quiet.channel == channel && quiet.contains(now))
+}
+
+/// Alerts held during quiet hours until they are sent as a digest, kept on disk so
+/// a restart overnight does not lose them.
+pub struct NotificationDigest {
+    held: Mutex<Vec<HeldAlert>>,
+    write_lock: tokio::sync::Mutex<()>,
+}
+
+impl NotificationDigest {
+    pub fn new() -> Self {
+        let held = std::fs::read_to_string(DIGEST_FILE)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            held: Mutex::new(held),
+            write_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+    
+    pub async fn hold(&self, alert: HeldAlert) {
+        self.held.lock().unwrap().push(alert);
+        if let Err(e) = self.save().await {
+            warn!("Failed to save the notification digest: {}", e);
+        }
+    }
+    
+    /// The number of alerts held.
+    pub fn len(&self) -> usize {
+        self.held.lock().unwrap().len()
+    }
+    
+    /// Removes and returns the alerts held for `channel`, oldest first.
+    pub async fn take(&self, channel: &str) -> Vec<HeldAlert> {
+        let taken: Vec<HeldAlert> = {
+            let mut held = self.held.lock().unwrap();
+            let (taken, kept) = held.drain(..).partition(|alert| alert.channel == channel);
+            *held = kept;
+            taken
+        };
+        if !taken.is_empty() {
+            if let Err(e) = self.save().await {
+                warn!("Failed to save the notification digest: {}", e);
+            }
+        }
+        taken
+    }
+    
+    /// Puts back alerts whose digest could not be delivered, ahead of any held since,
+    /// so the next digest for their channel carries them.
+    pub async fn requeue(&self, mut alerts: Vec<HeldAlert>) {
+        if alerts.is_empty() {
+            return;
+        }
+        {
+            let mut held = self.held.lock().unwrap();
+            alerts.append(&mut held);
+            *held = alerts;
+        }
+        if let Err(e) = self.save().await {
+            warn!("Failed to save the notification digest: {}", e);
+        }
+    }
+    
+    async fn save(&self) -> Result<()> {
+        let _guard = self.write_lock.lock().await;
+        let content = serde_json::to_string_pretty(&*self.held.lock().unwrap())?;
+        data_dir::ensure_async().await?;
+        fs::write(DIGEST_FILE, content).await?;
+        Ok(())
+    }
+}
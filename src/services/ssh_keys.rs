@@ -0,0 +1,260 @@
+use crate::utils::data_dir;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use tracing::warn;
+
+pub const AUTHORIZED_KEYS_FILE: &str = "data/authorized_keys.json";
+
+const KEY_FILES: [&str; 2] = [".ssh/authorized_keys", ".ssh/authorized_keys2"];
+
+/// One line of an `authorized_keys` file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthorizedKey {
+    pub user: String,
+    pub key_type: String,
+    /// `SHA256:...` as printed by `ssh-keygen -l`.
+    pub fingerprint: String,
+    pub comment: String,
+    /// Restrictions such as `from=` or `command=`, which matter as much as the key.
+    pub options: String,
+}
+
+/// Tracks the keys that can log in to each account and reports additions and changes.
+pub struct AuthorizedKeysWatcher {
+    /// Keys per `authorized_keys` file.
+    keys: Option<BTreeMap<String, Vec<AuthorizedKey>>>,
+    last_modified: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl AuthorizedKeysWatcher {
+    /// Starts from the keys saved by earlier runs, so keys added while stopped are reported.
+    pub fn new() -> Self {
+        let keys = std::fs::read_to_string(AUTHORIZED_KEYS_FILE)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok());
+        
+        Self {
+            keys,
+            last_modified: Vec::new(),
+        }
+    }
+    
+    /// Every known key, by file.
+    pub fn keys(&self) -> BTreeMap<String, Vec<AuthorizedKey>> {
+        self.keys.clone().unwrap_or_default()
+    }
+    
+    /// The scan of the next cycle, to be read without holding the monitor.
+    pub fn next_poll(&self) -> AuthorizedKeysPoll {
+        AuthorizedKeysPoll {
+            last_modified: self.last_modified.clone(),
+        }
+    }
+    
+    /// Keeps the keys an [`AuthorizedKeysPoll`] read and describes the differences.
+    pub fn apply(&mut self, scan: AuthorizedKeysScan) -> Vec<String> {
+        self.last_modified = scan.modified;
+        
+        let mut current = BTreeMap::new();
+        for (file, keys) in scan.files {
+            // An unreadable file keeps its previous keys rather than looking emptied
+            let keys = keys.or_else(|| self.keys.as_ref().and_then(|known| known.get(&file)).cloned());
+            if let Some(keys) = keys {
+                current.insert(file, keys);
+            }
+        }
+        
+        let alerts = match &self.keys {
+            Some(previous) => describe_changes(previous, &current),
+            None => Vec::new(),
+        };
+        
+        if self.keys.as_ref() != Some(&current) {
+            if let Err(e) = save(&current) {
+                warn!("Failed to save authorized keys: {}", e);
+            }
+            self.keys = Some(current);
+        }
+        
+        alerts
+    }
+}
+
+/// Modification times of the key files at the previous scan.
+pub struct AuthorizedKeysPoll {
+    last_modified: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+/// What an [`AuthorizedKeysPoll`] read, by file, with `None` for a file that could
+/// not be read.
+pub struct AuthorizedKeysScan {
+    modified: Vec<(PathBuf, Option<SystemTime>)>,
+    files: Vec<(String, Option<Vec<AuthorizedKey>>)>,
+}
+
+impl AuthorizedKeysPoll {
+    /// Re-reads the key files, or nothing when none of them changed.
+    pub async fn run(self) -> Option<AuthorizedKeysScan> {
+        let scan = tokio::task::spawn_blocking(move || {
+            let files = key_files();
+            let modified: Vec<(PathBuf, Option<SystemTime>)> = files
+                .iter()
+                .map(|(_, path)| (path.clone(), std::fs::metadata(path).and_then(|m| m.modified()).ok()))
+                .collect();
+            if modified == self.last_modified {
+                return None;
+            }
+            
+            let files = files
+                .iter()
+                .filter_map(|(user, path)| {
+                    let keys = match std::fs::read_to_string(path) {
+                        Ok(content) => Some(parse_keys(user, &content)),
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+                        Err(_) => None,
+                    };
+                    Some((path.to_string_lossy().to_string(), keys))
+                })
+                .collect();
+            Some(AuthorizedKeysScan { modified, files })
+        });
+        scan.await.ok().flatten()
+    }
+}
+
+fn describe_changes(
+    previous: &BTreeMap<String, Vec<AuthorizedKey>>,
+    current: &BTreeMap<String, Vec<AuthorizedKey>>,
+) -> Vec<String> {
+    let mut alerts = Vec::new();
+    let none = Vec::new();
+    
+    for (file, keys) in current {
+        let before = previous.get(file).unwrap_or(&none);
+        for key in keys {
+            match before.iter().find(|old| old.fingerprint == key.fingerprint) {
+                None => alerts.push(format!(
+                    "SSH Key Added: {} {} ({}) {}",
+                    key.user, key.key_type, key.comment, key.fingerprint
+                )),
+                Some(old) if old.options != key.options => alerts.push(format!(
+                    "SSH Key Changed: {} ({}) options {} -> {} {}",
+                    key.user,
+                    key.comment,
+                    display_options(&old.options),
+                    display_options(&key.options),
+                    key.fingerprint
+                )),
+                _ => {}
+            }
+        }
+    }
+    for (file, keys) in previous {
+        let after = current.get(file).unwrap_or(&none);
+        for key in keys.iter().filter(|key| !after.iter().any(|new| new.fingerprint == key.fingerprint)) {
+            alerts.push(format!(
+                "SSH Key Removed: {} {} ({}) {}",
+                key.user, key.key_type, key.comment, key.fingerprint
+            ));
+        }
+    }
+    
+    alerts
+}
+
+fn display_options(options: &str) -> &str {
+    if options.is_empty() {
+        "(none)"
+    } else {
+        options
+    }
+}
+
+/// `authorized_keys` files of accounts that have a home directory.
+fn key_files() -> Vec<(String, PathBuf)> {
+    let passwd = std::fs::read_to_string("/etc/passwd").unwrap_or_default();
+    let mut files: Vec<(String, PathBuf)> = passwd
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            Some((fields.first()?.to_string(), PathBuf::from(fields.get(5)?)))
+        })
+        .filter(|(_, home)| home.as_os_str() != "/" && home.is_dir())
+        .flat_map(|(user, home)| KEY_FILES.iter().map(move |file| (user.clone(), home.join(file))))
+        .collect();
+    files.sort();
+    files.dedup_by(|a, b| a.1 == b.1);
+    files
+}
+
+fn parse_keys(user: &str, content: &str) -> Vec<AuthorizedKey> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| parse_key(user, line))
+        .collect()
+}
+
+/// Parses `[options] type base64-key [comment]`. Options may contain quoted spaces.
+fn parse_key(user: &str, line: &str) -> Option<AuthorizedKey> {
+    let fields = split_fields(line);
+    // The key type is the first field followed by a decodable blob
+    let position = (0..fields.len().saturating_sub(1)).find(|&i| fingerprint(&fields[i + 1]).is_some())?;
+    
+    Some(AuthorizedKey {
+        user: user.to_string(),
+        key_type: fields[position].clone(),
+        fingerprint: fingerprint(&fields[position + 1])?,
+        comment: fields[position + 2..].join(" "),
+        options: fields[..position].join(" "),
+    })
+}
+
+fn split_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                field.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !field.is_empty() {
+                    fields.push(std::mem::take(&mut field));
+                }
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() {
+        fields.push(field);
+    }
+    
+    fields
+}
+
+fn fingerprint(blob: &str) -> Option<String> {
+    let decoded = base64::engine::general_purpose::STANDARD.decode(blob).ok()?;
+    // Real key blobs start with a length-prefixed type name
+    if decoded.len() < 20 {
+        return None;
+    }
+    let digest = ring::digest::digest(&ring::digest::SHA256, &decoded);
+    Some(format!(
+        "SHA256:{}",
+        base64::engine::general_purpose::STANDARD_NO_PAD.encode(digest.as_ref())
+    ))
+}
+
+fn save(keys: &BTreeMap<String, Vec<AuthorizedKey>>) -> anyhow::Result<()> {
+    data_dir::ensure()?;
+    std::fs::write(AUTHORIZED_KEYS_FILE, serde_json::to_string_pretty(keys)?)?;
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use crate::models::auth::{HostGroup, HostStatus};
+use crate::models::metrics::{HistoryRange, MetricPoint, SystemMetrics, UptimeInfo};
+use crate::services::metrics_window::MetricsWindow;
+use crate::services::monitor::MonitorService;
+use parking_lot::RwLock;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+/// The hosts panel group listing LAN devices, which is only shown to signed-in users.
+pub const LAN_DEVICES_GROUP: &str = "LAN Devices";
+// LAN devices on the dashboard's hosts panel, busiest first
+const LAN_DEVICES_SHOWN: usize = 10;
+// Latest samples charted; a long window is there for the baselines
+const GRAPH_POINTS: usize = 120;
+// Latest samples served by /api/metrics without a range; older ones are archived.
+// Publishing the whole window would copy it every cycle
+const SNAPSHOT_SAMPLES: usize = 1000;
+
+/// What the dashboard and the status endpoints show, captured at the end of each
+/// monitoring cycle so they are served without waiting for the monitor.
+pub struct StatusSnapshot {
+    pub status: Vec<String>,
+    pub anomalies: Vec<String>,
+    pub has_anomaly: bool,
+    pub uptime: UptimeInfo,
+    pub graphs: Value,
+    pub hosts: Vec<HostGroup>,
+    /// The latest samples of the window, up to `SNAPSHOT_SAMPLES`.
+    pub metrics: MetricsWindow,
+    /// The aggregated history of each range.
+    history: Vec<(HistoryRange, Vec<MetricPoint>)>,
+}
+
+impl StatusSnapshot {
+    pub fn capture(monitor: &MonitorService) -> Self {
+        let (anomalies, has_anomaly) = monitor.detect_anomalies();
+        Self {
+            status: monitor.status_report(),
+            anomalies,
+            has_anomaly,
+            uptime: monitor.uptime(),
+            graphs: graphs(monitor),
+            hosts: host_groups(monitor),
+            metrics: monitor.get_metrics_history().tail(SNAPSHOT_SAMPLES),
+            history: HistoryRange::ALL.iter().map(|range| (*range, monitor.history(*range))).collect(),
+        }
+    }
+    
+    pub fn history(&self, range: HistoryRange) -> &[MetricPoint] {
+        self.history
+            .iter()
+            .find(|(r, _)| *r == range)
+            .map_or(&[], |(_, points)| points.as_slice())
+    }
+}
+
+/// The latest snapshot. The lock is only held to swap or clone the `Arc`, so
+/// readers never wait for a cycle and a cycle never waits for readers.
+pub struct PublishedStatus(RwLock<Arc<StatusSnapshot>>);
+
+impl PublishedStatus {
+    pub fn new(snapshot: StatusSnapshot) -> Self {
+        Self(RwLock::new(Arc::new(snapshot)))
+    }
+    
+    pub fn load(&self) -> Arc<StatusSnapshot> {
+        Arc::clone(&self.0.read())
+    }
+    
+    pub fn publish(&self, snapshot: StatusSnapshot) {
+        *self.0.write() = Arc::new(snapshot);
+    }
+}
+
+/// Check results by group, then Proxmox nodes and guests one group per cluster.
+fn host_groups(monitor: &MonitorService) -> Vec<HostGroup> {
+    let mut host_groups: Vec<HostGroup> = monitor
+        .check_groups()
+        .into_iter()
+        .map(|(name, checks)| HostGroup {
+            name,
+            hosts: checks
+                .into_iter()
+                .map(|(name, result)| HostStatus {
+                    name,
+                    status: if !result.is_up() {
+                        "DOWN".to_string()
+                    } else if result.loss_percent > 0.0 {
+                        format!("{:.1}ms {:.0}% loss", result.latency_ms, result.loss_percent)
+                    } else {
+                        format!("{:.1}ms", result.latency_ms)
+                    },
+                    down: !result.is_up(),
+                })
+                .collect(),
+        })
+        .collect();
+    
+    for (cluster, resources) in monitor.proxmox().resources() {
+        host_groups.push(HostGroup {
+            name: format!("Proxmox {}", cluster),
+            hosts: resources
+                .iter()
+                .map(|resource| HostStatus {
+                    name: resource.label(),
+                    status: if resource.is_up() {
+                        format!(
+                            "CPU {:.0}% RAM {:.1}/{:.1}GB",
+                            resource.cpu * 100.0,
+                            resource.mem as f64 / GIB,
+                            resource.maxmem as f64 / GIB
+                        )
+                    } else {
+                        resource.status.to_uppercase()
+                    },
+                    down: !resource.is_up(),
+                })
+                .collect(),
+        });
+    }
+    
+    let devices = monitor.lan_usage().devices();
+    if !devices.is_empty() {
+        host_groups.push(HostGroup {
+            name: LAN_DEVICES_GROUP.to_string(),
+            hosts: devices
+                .into_iter()
+                .take(LAN_DEVICES_SHOWN)
+                .map(|device| HostStatus {
+                    name: device.name,
+                    status: format!(
+                        "↑{:.1} ↓{:.1} KB/s, month ↑{:.2} ↓{:.2} GB",
+                        device.upload_kb_per_s,
+                        device.download_kb_per_s,
+                        device.month.upload as f64 / GIB,
+                        device.month.download as f64 / GIB
+                    ),
+                    down: false,
+                })
+                .collect(),
+        });
+    }
+    
+    host_groups
+}
+
+/// Recent samples of each stat the dashboard can chart. The GPU stats are only
+/// there once a graphics card has been seen.
+fn graphs(monitor: &MonitorService) -> Value {
+    let window = monitor.get_metrics_history();
+    let history: Vec<SystemMetrics> = window.iter().skip(window.len().saturating_sub(GRAPH_POINTS)).collect();
+    let mut graphs = json!({
+        "cpu": history.iter().map(|m| m.cpu_percent).collect::<Vec<_>>(),
+        "ram": history.iter().map(|m| m.ram_percent).collect::<Vec<_>>(),
+        "disk": history.iter().map(|m| m.disk_percent).collect::<Vec<_>>(),
+        "temp": history.iter().map(|m| m.temperature).collect::<Vec<_>>(),
+        "ping": history.iter().map(|m| m.ping_ms).collect::<Vec<_>>(),
+        "net": history.iter().map(|m| m.net_connections).collect::<Vec<_>>(),
+        "fail": history.iter().map(|m| m.failed_logins).collect::<Vec<_>>(),
+    });
+    if history.iter().any(|m| !m.gpus.is_empty()) {
+        graphs["gpu"] = json!(history.iter().map(|m| m.gpu_percent().unwrap_or(0.0)).collect::<Vec<_>>());
+        graphs["vram"] = json!(history.iter().map(|m| m.gpu_memory_percent().unwrap_or(0.0)).collect::<Vec<_>>());
+    }
+    graphs
+}
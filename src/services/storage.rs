@@ -0,0 +1,212 @@
+use crate::models::config::RetentionConfig;
+use crate::models::metrics::{MetricPoint, SystemMetrics};
+use crate::services::monitor::bucket_start;
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::de::DeserializeOwned;
+use std::path::{Path, PathBuf};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tracing::{info, warn};
+
+pub const METRICS_DIR: &str = "data/metrics";
+
+/// Every sample the monitor collects, one JSON line each in a file per UTC day
+/// (`data/metrics/2026-01-31.jsonl`). [`compact`] later replaces old days with
+/// averages, so past time ranges can be queried after the window has moved on.
+pub struct MetricsStore {
+    dir: PathBuf,
+}
+
+impl MetricsStore {
+    pub fn new() -> Self {
+        Self {
+            dir: PathBuf::from(METRICS_DIR),
+        }
+    }
+    
+    /// Appends a sample unless retention keeps none. A sample that cannot be written,
+    /// on a full disk say, is missing from the archive but was still used for detection.
+    pub async fn append(&self, sample: &SystemMetrics, retention: &RetentionConfig) {
+        if retention.raw_hours == 0 {
+            return;
+        }
+        if let Err(e) = self.write(sample).await {
+            warn!("Failed to archive metrics: {}", e);
+        }
+    }
+    
+    async fn write(&self, sample: &SystemMetrics) -> Result<()> {
+        let mut line = serde_json::to_string(sample)?;
+        line.push('\n');
+        
+        fs::create_dir_all(&self.dir).await?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(day_file(&self.dir, sample.timestamp.date_naive()))
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        
+        Ok(())
+    }
+}
+
+/// The directory of the averages over `bucket_secs`, e.g. `data/metrics/300s/`.
+fn tier_dir(bucket_secs: u64) -> PathBuf {
+    Path::new(METRICS_DIR).join(format!("{}s", bucket_secs))
+}
+
+fn day_file(dir: &Path, day: NaiveDate) -> PathBuf {
+    dir.join(format!("{}.jsonl", day.format("%Y-%m-%d")))
+}
+
+/// The days with a file in `dir`, oldest first.
+async fn days(dir: &Path) -> Vec<NaiveDate> {
+    let mut days = Vec::new();
+    let Ok(mut entries) = fs::read_dir(dir).await else {
+        return days;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let name = entry.file_name();
+        let day = name
+            .to_str()
+            .and_then(|name| name.strip_suffix(".jsonl"))
+            .and_then(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok());
+        days.extend(day);
+    }
+    days.sort();
+    days
+}
+
+/// Averages each day of samples older than `raw_hours` into every downsampling
+/// tier and removes it, then removes averages older than their tier's `days`,
+/// so the archive stays bounded on small SD cards.
+pub async fn compact(retention: &RetentionConfig) {
+    let now = Utc::now();
+    let raw_dir = PathBuf::from(METRICS_DIR);
+    // A day is compacted once all of it is older than the raw retention
+    let raw_oldest = (now - Duration::hours(retention.raw_hours as i64)).date_naive();
+    
+    let mut compacted = 0;
+    for day in days(&raw_dir).await.into_iter().filter(|day| *day < raw_oldest) {
+        let source = day_file(&raw_dir, day);
+        let mut averaged = true;
+        for tier in &retention.downsample {
+            if day < (now - Duration::days(tier.days as i64)).date_naive() {
+                continue;
+            }
+            if let Err(e) = downsample(&source, &day_file(&tier_dir(tier.bucket_secs), day), tier.bucket_secs).await {
+                warn!("Failed to average archived metrics of {} into {}s buckets: {}", day, tier.bucket_secs, e);
+                averaged = false;
+            }
+        }
+        // Samples that could not be averaged are kept for the next run
+        if averaged {
+            match fs::remove_file(&source).await {
+                Ok(()) => compacted += 1,
+                Err(e) => warn!("Failed to remove archived metrics of {}: {}", day, e),
+            }
+        }
+    }
+    if compacted > 0 {
+        info!("Compacted {} days of archived metrics", compacted);
+    }
+    
+    for tier in &retention.downsample {
+        let dir = tier_dir(tier.bucket_secs);
+        let oldest = (now - Duration::days(tier.days as i64)).date_naive();
+        for day in days(&dir).await.into_iter().filter(|day| *day < oldest) {
+            if let Err(e) = fs::remove_file(day_file(&dir, day)).await {
+                warn!("Failed to remove averaged metrics of {}: {}", day, e);
+            }
+        }
+    }
+}
+
+/// Writes the averages of a day's samples over `bucket_secs` to `target`,
+/// replacing any earlier attempt.
+async fn downsample(source: &Path, target: &Path, bucket_secs: u64) -> Result<()> {
+    let mut points: Vec<MetricPoint> = Vec::new();
+    let mut lines = BufReader::new(fs::File::open(source).await?).lines();
+    while let Some(line) = lines.next_line().await? {
+        let Ok(sample) = serde_json::from_str::<SystemMetrics>(&line) else {
+            continue;
+        };
+        let bucket = bucket_start(sample.timestamp, bucket_secs as i64);
+        let point = MetricPoint::from_metrics(bucket, &sample);
+        match points.last_mut() {
+            Some(last) if last.timestamp == bucket => last.merge(&point),
+            _ => points.push(point),
+        }
+    }
+    
+    let mut content = String::new();
+    for point in &points {
+        content.push_str(&serde_json::to_string(point)?);
+        content.push('\n');
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    let temp = target.with_extension("jsonl.tmp");
+    fs::write(&temp, content).await?;
+    fs::rename(&temp, target).await?;
+    Ok(())
+}
+
+/// Archived samples taken after `after` and up to `to`, oldest first, at most
+/// `limit` of them.
+pub async fn query(after: DateTime<Utc>, to: DateTime<Utc>, limit: usize) -> Result<Vec<SystemMetrics>> {
+    read_range(Path::new(METRICS_DIR), after, to, limit, |sample: &SystemMetrics| sample.timestamp).await
+}
+
+/// The averages over `bucket_secs` of a downsampling tier, starting after `after`
+/// and up to `to`, oldest first, at most `limit` of them.
+pub async fn query_averages(
+    bucket_secs: u64,
+    after: DateTime<Utc>,
+    to: DateTime<Utc>,
+    limit: usize,
+) -> Result<Vec<MetricPoint>> {
+    read_range(&tier_dir(bucket_secs), after, to, limit, |point: &MetricPoint| point.timestamp).await
+}
+
+/// Reads the day files of `dir` a line at a time and only as far as needed, so a
+/// long range costs no more memory than the page returned.
+async fn read_range<T: DeserializeOwned>(
+    dir: &Path,
+    after: DateTime<Utc>,
+    to: DateTime<Utc>,
+    limit: usize,
+    timestamp: fn(&T) -> DateTime<Utc>,
+) -> Result<Vec<T>> {
+    let mut entries = Vec::new();
+    
+    for day in days(dir).await {
+        if day < after.date_naive() || day > to.date_naive() {
+            continue;
+        }
+        // Compaction may have removed the day since it was listed
+        let Ok(file) = fs::File::open(day_file(dir, day)).await else {
+            continue;
+        };
+        let mut lines = BufReader::new(file).lines();
+        while let Some(line) = lines.next_line().await? {
+            // A line cut short by a crash is skipped
+            let Ok(entry) = serde_json::from_str::<T>(&line) else {
+                continue;
+            };
+            if timestamp(&entry) > to {
+                break;
+            }
+            if timestamp(&entry) > after {
+                entries.push(entry);
+                if entries.len() >= limit {
+                    return Ok(entries);
+                }
+            }
+        }
+    }
+    Ok(entries)
+}
@@ -0,0 +1,252 @@
+use crate::models::config::DetectionConfig;
+use crate::services::monitor::MonitorService;
+use crate::utils::{data_dir, process};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::os::unix::fs::MetadataExt;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::RwLock;
+use tracing::info;
+
+pub const SUID_FILE: &str = "data/suid.json";
+pub const SUID_SOURCE: &str = "suid";
+// How long one `find` over the configured paths may take; they may be network mounts
+const SCAN_TIMEOUT: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SuidBinary {
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub sha256: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SuidState {
+    /// Binaries accepted as legitimate; the first scan's results.
+    baseline: BTreeMap<String, SuidBinary>,
+    current: BTreeMap<String, SuidBinary>,
+    scanned_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuidStatus {
+    New,
+    Modified,
+    Removed,
+    Baseline,
+}
+
+/// One binary of the security report.
+#[derive(Debug, Clone, Serialize)]
+pub struct SuidReportEntry {
+    pub path: String,
+    pub mode: String,
+    pub uid: u32,
+    pub status: SuidStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SuidReport {
+    pub scanned_at: Option<DateTime<Utc>>,
+    pub binaries: Vec<SuidReportEntry>,
+}
+
+/// Periodically searches for SUID/SGID binaries and reports those outside the baseline.
+pub struct SuidScanService {
+    config: DetectionConfig,
+    published: bool,
+    monitor: Arc<RwLock<MonitorService>>,
+}
+
+impl SuidScanService {
+    pub fn new(config: &DetectionConfig, monitor: Arc<RwLock<MonitorService>>) -> Self {
+        Self {
+            config: config.clone(),
+            published: false,
+            monitor,
+        }
+    }
+    
+    pub fn apply_config(&mut self, config: &DetectionConfig) {
+        self.config = config.clone();
+    }
+    
+    pub fn interval(&self) -> u64 {
+        scan_interval(&self.config)
+    }
+    
+    /// What the next refresh should do, taken under the lock so that the scan
+    /// itself, which walks whole filesystems, runs without it.
+    pub fn next_scan(&self) -> SuidScan {
+        SuidScan {
+            config: self.config.clone(),
+            published: self.published,
+            monitor: Arc::clone(&self.monitor),
+        }
+    }
+    
+    /// Records that findings have been published since startup, so later refreshes
+    /// always scan.
+    pub fn mark_published(&mut self) {
+        self.published = true;
+    }
+}
+
+/// One refresh of the SUID findings, run apart from the service.
+pub struct SuidScan {
+    config: DetectionConfig,
+    published: bool,
+    monitor: Arc<RwLock<MonitorService>>,
+}
+
+impl SuidScan {
+    /// Scans the filesystem, except right after startup when the last scan is recent
+    /// enough to just republish its findings.
+    pub async fn run(self) -> Result<()> {
+        if !self.config.suid_scan {
+            self.monitor.write().await.set_external_anomalies(SUID_SOURCE, Vec::new());
+            return Ok(());
+        }
+        
+        let mut state = load_state();
+        let fresh = state
+            .scanned_at
+            .is_some_and(|scanned_at| (Utc::now() - scanned_at).num_seconds() < scan_interval(&self.config) as i64);
+        
+        if self.published || !fresh {
+            info!("Scanning for SUID/SGID binaries");
+            state.current = scan(&self.config.suid_scan_paths).await?;
+            if state.scanned_at.is_none() {
+                state.baseline = state.current.clone();
+            }
+            state.scanned_at = Some(Utc::now());
+            save_state(&state)?;
+        }
+        
+        self.monitor
+            .write()
+            .await
+            .set_external_anomalies(SUID_SOURCE, findings(&state));
+        Ok(())
+    }
+}
+
+fn scan_interval(config: &DetectionConfig) -> u64 {
+    config.suid_scan_interval.max(3600)
+}
+
+fn findings(state: &SuidState) -> Vec<String> {
+    state
+        .current
+        .iter()
+        .filter_map(|(path, binary)| match state.baseline.get(path) {
+            None => Some(format!("New SUID Binary: {} {:o}", path, binary.mode & 0o7777)),
+            Some(known) if known != binary => Some(format!("SUID Binary Changed: {} modified", path)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Runs `find` over each path and fingerprints what it finds.
+async fn scan(paths: &[String]) -> Result<BTreeMap<String, SuidBinary>> {
+    let output = process::output_within(
+        Command::new("find")
+            .args(paths)
+            .args(["-xdev", "-type", "f", "(", "-perm", "-4000", "-o", "-perm", "-2000", ")", "-print0"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null()),
+        SCAN_TIMEOUT,
+    )
+    .await?;
+    // find exits non-zero on unreadable directories, which are common and harmless
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    
+    let binaries = tokio::task::spawn_blocking(move || {
+        stdout
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .filter_map(|path| {
+                let metadata = std::fs::metadata(path).ok()?;
+                let content = std::fs::read(path).ok()?;
+                let digest = ring::digest::digest(&ring::digest::SHA256, &content);
+                let sha256 = digest.as_ref().iter().map(|byte| format!("{:02x}", byte)).collect();
+                Some((
+                    path.to_string(),
+                    SuidBinary {
+                        mode: metadata.mode(),
+                        uid: metadata.uid(),
+                        gid: metadata.gid(),
+                        sha256,
+                    },
+                ))
+            })
+            .collect()
+    })
+    .await?;
+    
+    Ok(binaries)
+}
+
+/// The last scan compared with the baseline, unexpected binaries first.
+pub async fn report() -> SuidReport {
+    let state = load_state();
+    let entry = |path: &String, binary: &SuidBinary, status| SuidReportEntry {
+        path: path.clone(),
+        mode: format!("{:o}", binary.mode & 0o7777),
+        uid: binary.uid,
+        status,
+    };
+    
+    let mut binaries: Vec<SuidReportEntry> = state
+        .current
+        .iter()
+        .map(|(path, binary)| {
+            let status = match state.baseline.get(path) {
+                None => SuidStatus::New,
+                Some(known) if known != binary => SuidStatus::Modified,
+                _ => SuidStatus::Baseline,
+            };
+            entry(path, binary, status)
+        })
+        .chain(
+            state
+                .baseline
+                .iter()
+                .filter(|(path, _)| !state.current.contains_key(*path))
+                .map(|(path, binary)| entry(path, binary, SuidStatus::Removed)),
+        )
+        .collect();
+    binaries.sort_by_key(|binary| (binary.status == SuidStatus::Baseline, binary.path.clone()));
+    
+    SuidReport {
+        scanned_at: state.scanned_at,
+        binaries,
+    }
+}
+
+/// Accepts the last scan's binaries as the new baseline.
+pub async fn accept_current() -> Result<()> {
+    let mut state = load_state();
+    state.baseline = state.current.clone();
+    save_state(&state)
+}
+
+fn load_state() -> SuidState {
+    std::fs::read_to_string(SUID_FILE)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(state: &SuidState) -> Result<()> {
+    data_dir::ensure()?;
+    std::fs::write(SUID_FILE, serde_json::to_string(state)?)?;
+    Ok(())
+}
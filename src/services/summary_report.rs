@@ -0,0 +1,314 @@
+use crate::models::availability::ReportPeriod;
+use crate::models::config::{ReportSchedule, SummaryReportConfig};
+use crate::models::metrics::{AnomalySeverity, HistoryRange};
+use crate::services::monitor::{anomaly_severity, anomaly_subject, format_duration, MonitorService};
+use crate::services::threat_intel::ThreatIntelService;
+use anyhow::{anyhow, Result};
+use askama::Template;
+use chrono::{Datelike, NaiveDate, Timelike, Utc, Weekday};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::Arc;
+use sysinfo::System;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::RwLock;
+use tracing::info;
+
+/// Where reports are written, the directory the downloads page serves.
+pub const REPORTS_DIR: &str = "logs";
+// How often the schedule is checked
+const SCHEDULE_CHECK_SECS: u64 = 300;
+const TOP_ANOMALIES: usize = 10;
+const TOP_PROCESSES: usize = 5;
+const THREAT_HIGHLIGHTS: usize = 10;
+const CHART_WIDTH: f64 = 600.0;
+const CHART_HEIGHT: f64 = 60.0;
+
+#[derive(Template)]
+#[template(path = "summary_report.html")]
+struct SummaryReportTemplate {
+    title: String,
+    host: String,
+    since: String,
+    generated_at: String,
+    // (check, uptime, downtime, outages)
+    availability: Vec<(String, String, String, usize)>,
+    anomaly_count: usize,
+    incident_count: usize,
+    // (severity, count)
+    severities: Vec<(String, usize)>,
+    // (subject, count)
+    top_anomalies: Vec<(String, usize)>,
+    // (process by CPU, CPU %, process by memory, resident memory), side by side
+    top_processes: Vec<(String, String, String, String)>,
+    // (source, title, url, published)
+    threats: Vec<(String, String, String, String)>,
+    // (metric, min/avg/max, inline SVG)
+    charts: Vec<(String, String, String)>,
+}
+
+/// Generates the daily or weekly summary report. The configuration is passed in
+/// each time so edits apply to the next report.
+pub struct SummaryReportService {
+    monitor: Arc<RwLock<MonitorService>>,
+    threat_intel: Arc<RwLock<ThreatIntelService>>,
+}
+
+impl SummaryReportService {
+    pub fn new(monitor: Arc<RwLock<MonitorService>>, threat_intel: Arc<RwLock<ThreatIntelService>>) -> Self {
+        Self { monitor, threat_intel }
+    }
+    
+    pub fn interval(&self) -> u64 {
+        SCHEDULE_CHECK_SECS
+    }
+    
+    /// Generates the scheduled report once its hour has come, unless this period's
+    /// report was already written.
+    pub async fn run_if_due(&self, config: &SummaryReportConfig) -> Result<()> {
+        let now = Utc::now();
+        let period = match config.schedule {
+            ReportSchedule::Off => return Ok(()),
+            ReportSchedule::Daily => ReportPeriod::Day,
+            ReportSchedule::Weekly if now.weekday() == Weekday::Mon => ReportPeriod::Week,
+            ReportSchedule::Weekly => return Ok(()),
+        };
+        if now.hour() < config.hour || report_path(period, now.date_naive()).exists() {
+            return Ok(());
+        }
+        
+        self.generate(period, config).await.map(|_| ())
+    }
+    
+    /// Builds the report over the period up to now, writes it to the downloads area
+    /// and mails it to the configured recipients. Returns the file name.
+    pub async fn generate(&self, period: ReportPeriod, config: &SummaryReportConfig) -> Result<String> {
+        let report = self.build(period).await;
+        let title = report.title.clone();
+        let html = report.render()?;
+        
+        let path = report_path(period, Utc::now().date_naive());
+        tokio::fs::create_dir_all(REPORTS_DIR).await?;
+        tokio::fs::write(&path, &html).await?;
+        let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        info!("Wrote summary report {}", name);
+        
+        if !config.email.is_empty() {
+            let from = config.from.clone().unwrap_or_else(|| {
+                format!("super-monitor@{}", System::host_name().unwrap_or_else(|| "localhost".to_string()))
+            });
+            send_mail(&from, &config.email, &title, &html).await?;
+            info!("Mailed summary report to {}", config.email.join(", "));
+        }
+        
+        Ok(name)
+    }
+    
+    async fn build(&self, period: ReportPeriod) -> SummaryReportTemplate {
+        let now = Utc::now();
+        let since = now - period.duration();
+        let time = |t: chrono::DateTime<Utc>| t.format("%Y-%m-%d %H:%M").to_string();
+        
+        let monitor = self.monitor.read().await;
+        let (availability_log, checks, incidents) = (monitor.availability(), monitor.check_names(), monitor.incidents());
+        let range = if period == ReportPeriod::Day { HistoryRange::Day } else { HistoryRange::Week };
+        let history = monitor.history(range);
+        drop(monitor);
+        
+        let availability = availability_log
+            .report(&checks, period)
+            .await
+            .into_iter()
+            .map(|report| {
+                (
+                    report.check,
+                    report.uptime_percent.map(|p| format!("{:.3}%", p)).unwrap_or_else(|| "no data".to_string()),
+                    format_duration(report.downtime_secs),
+                    report.outages.len(),
+                )
+            })
+            .collect();
+        
+        // Anomalies raised in the period, from the incidents they were filed under
+        let incidents = incidents.incidents();
+        let raised: Vec<&str> = incidents
+            .iter()
+            .flat_map(|incident| &incident.anomalies)
+            .filter(|anomaly| anomaly.raised_at >= since)
+            .map(|anomaly| anomaly.message.as_str())
+            .collect();
+        let severities = [AnomalySeverity::Critical, AnomalySeverity::Warning, AnomalySeverity::Info]
+            .into_iter()
+            .map(|severity| {
+                let count = raised.iter().filter(|anomaly| anomaly_severity(anomaly) == severity).count();
+                (severity.to_string(), count)
+            })
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        let mut by_subject: HashMap<String, usize> = HashMap::new();
+        for anomaly in &raised {
+            *by_subject.entry(anomaly_subject(anomaly)).or_default() += 1;
+        }
+        let mut top_anomalies: Vec<(String, usize)> = by_subject.into_iter().collect();
+        top_anomalies.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top_anomalies.truncate(TOP_ANOMALIES);
+        
+        let top_processes = top_processes().await;
+        
+        let mut threats: Vec<_> = self
+            .threat_intel
+            .read()
+            .await
+            .get_data()
+            .values()
+            .flatten()
+            .filter(|item| item.published.is_some_and(|published| published >= since))
+            .cloned()
+            .collect();
+        threats.sort_by_key(|item| std::cmp::Reverse(item.published));
+        let threats = threats
+            .into_iter()
+            .take(THREAT_HIGHLIGHTS)
+            .map(|item| (item.source, item.title, item.url, item.published.map(time).unwrap_or_default()))
+            .collect();
+        
+        let charts = [
+            ("CPU", history.iter().map(|p| p.cpu_percent).collect::<Vec<f64>>(), "%"),
+            ("RAM", history.iter().map(|p| p.ram_percent).collect(), "%"),
+            ("Disk", history.iter().map(|p| p.disk_percent).collect(), "%"),
+            ("Temperature", history.iter().map(|p| p.temperature).collect(), "°C"),
+        ]
+        .into_iter()
+        .filter_map(|(label, values, unit)| {
+            let (svg, summary) = trend_chart(&values, unit)?;
+            Some((label.to_string(), summary, svg))
+        })
+        .collect();
+        
+        SummaryReportTemplate {
+            title: format!(
+                "{} summary for {}",
+                if period == ReportPeriod::Day { "Daily" } else { "Weekly" },
+                System::host_name().unwrap_or_default()
+            ),
+            host: System::host_name().unwrap_or_default(),
+            since: time(since),
+            generated_at: time(now),
+            availability,
+            anomaly_count: raised.len(),
+            incident_count: incidents.iter().filter(|incident| incident.opened_at >= since).count(),
+            severities,
+            top_anomalies,
+            top_processes,
+            threats,
+            charts,
+        }
+    }
+}
+
+/// `logs/summary-daily-2026-01-31.html`, or for the week starting that day.
+fn report_path(period: ReportPeriod, date: NaiveDate) -> std::path::PathBuf {
+    let kind = match period {
+        ReportPeriod::Day => "daily",
+        ReportPeriod::Week => "weekly",
+        ReportPeriod::Month => "monthly",
+    };
+    std::path::Path::new(REPORTS_DIR).join(format!("summary-{}-{}.html", kind, date.format("%Y-%m-%d")))
+}
+
+/// The processes using the most CPU and memory right now, summed by name, as rows of
+/// (by CPU, CPU %, by memory, memory).
+async fn top_processes() -> Vec<(String, String, String, String)> {
+    let usage = tokio::task::spawn_blocking(|| {
+        let mut system = System::new_all();
+        // CPU usage is measured between two refreshes
+        std::thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+        system.refresh_processes();
+        
+        let mut by_name: HashMap<String, (f64, u64)> = HashMap::new();
+        for process in system.processes().values().filter(|process| process.thread_kind().is_none()) {
+            let entry = by_name.entry(process.name().to_string()).or_default();
+            entry.0 += process.cpu_usage() as f64;
+            entry.1 += process.memory();
+        }
+        by_name.into_iter().collect::<Vec<(String, (f64, u64))>>()
+    })
+    .await
+    .unwrap_or_default();
+    
+    let mut by_cpu = usage.clone();
+    by_cpu.sort_by(|a, b| b.1 .0.total_cmp(&a.1 .0));
+    let mut by_memory = usage;
+    by_memory.sort_by_key(|(_, (_, memory))| std::cmp::Reverse(*memory));
+    
+    by_cpu
+        .into_iter()
+        .zip(by_memory)
+        .take(TOP_PROCESSES)
+        .map(|((by_cpu, (cpu, _)), (by_memory, (_, memory)))| {
+            (by_cpu, format!("{:.1}%", cpu), by_memory, format!("{:.0} MB", memory as f64 / 1_048_576.0))
+        })
+        .collect()
+}
+
+/// An inline SVG line of the values and a min/avg/max summary; `None` without data.
+fn trend_chart(values: &[f64], unit: &str) -> Option<(String, String)> {
+    let min = values.iter().copied().reduce(f64::min)?;
+    let max = values.iter().copied().reduce(f64::max)?;
+    let avg = values.iter().sum::<f64>() / values.len() as f64;
+    
+    let span = (max - min).max(1.0);
+    let step = CHART_WIDTH / (values.len().max(2) - 1) as f64;
+    let points: Vec<String> = values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let y = CHART_HEIGHT - (value - min) / span * (CHART_HEIGHT - 4.0) - 2.0;
+            format!("{:.1},{:.1}", i as f64 * step, y)
+        })
+        .collect();
+    let svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\
+         <polyline fill=\"none\" stroke=\"#e94560\" stroke-width=\"2\" points=\"{points}\"/></svg>",
+        w = CHART_WIDTH,
+        h = CHART_HEIGHT,
+        points = points.join(" ")
+    );
+    
+    Some((svg, format!("min {:.1}{u} / avg {:.1}{u} / max {:.1}{u}", min, avg, max, u = unit)))
+}
+
+/// Sends an HTML message through the local `sendmail -t`.
+async fn send_mail(from: &str, to: &[String], subject: &str, html: &str) -> Result<()> {
+    // `sendmail -t` takes its recipients from the headers, so none may be smuggled in
+    let mut headers = std::iter::once(from).chain(to.iter().map(String::as_str)).chain(std::iter::once(subject));
+    if headers.any(|value| value.contains(['\r', '\n'])) {
+        return Err(anyhow!("mail headers cannot contain line breaks"));
+    }
+    
+    let message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nMIME-Version: 1.0\r\nContent-Type: text/html; charset=utf-8\r\n\r\n{}",
+        from,
+        to.join(", "),
+        subject,
+        html
+    );
+    
+    let mut child = Command::new("sendmail")
+        .arg("-t")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("sendmail unavailable: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(message.as_bytes()).await?;
+    }
+    
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        return Err(anyhow!("sendmail failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::future::Future;
+use std::sync::Arc;
+use tokio::time::{Duration, Instant};
+use tracing::{error, info};
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+// A task that ran this long before failing starts over with the initial backoff
+const STABLE_RUNTIME: Duration = Duration::from_secs(600);
+// Monitoring cycles kept for `/api/debug/runtime`
+const CYCLE_HISTORY: usize = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskState {
+    Running,
+    Restarting,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskHealth {
+    pub name: String,
+    pub state: TaskState,
+    pub restarts: u32,
+    pub started_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub last_failure: Option<DateTime<Utc>>,
+}
+
+/// How long one monitoring cycle took, by phase, in milliseconds.
+#[derive(Debug, Clone, Serialize)]
+pub struct CycleTiming {
+    pub finished_at: DateTime<Utc>,
+    /// Running the collectors and due checks, with the monitor unlocked.
+    pub collect_ms: u64,
+    /// Waiting for the monitor's write lock.
+    pub lock_wait_ms: u64,
+    /// Applying the results, detecting anomalies and alerting, with the monitor locked.
+    pub locked_ms: u64,
+}
+
+/// Runs long-lived background loops, respawning them with exponential backoff when
+/// they panic or return, and keeps their health for `/healthz` and the timings of
+/// recent monitoring cycles.
+#[derive(Default)]
+pub struct Supervisor {
+    tasks: Mutex<BTreeMap<String, TaskHealth>>,
+    cycles: Mutex<VecDeque<CycleTiming>>,
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Spawns `factory()` under supervision. The factory is called again for every restart.
+    pub fn spawn<F, Fut>(self: &Arc<Self>, name: &str, factory: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let supervisor = Arc::clone(self);
+        let name = name.to_string();
+        
+        tokio::spawn(async move {
+            let mut backoff = INITIAL_BACKOFF;
+            
+            loop {
+                supervisor.mark_running(&name);
+                let started = Instant::now();
+                
+                // Run in its own task so a panic surfaces as a JoinError instead of unwinding here
+                let reason = match tokio::spawn(factory()).await {
+                    Ok(()) => "task exited unexpectedly".to_string(),
+                    Err(e) if e.is_panic() => format!("panicked: {}", panic_message(e.into_panic())),
+                    Err(e) => format!("cancelled: {}", e),
+                };
+                
+                if started.elapsed() >= STABLE_RUNTIME {
+                    backoff = INITIAL_BACKOFF;
+                }
+                
+                error!("Background task '{}' {}; restarting in {}s", name, reason, backoff.as_secs());
+                supervisor.mark_failed(&name, reason);
+                
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                info!("Restarting background task '{}'", name);
+            }
+        });
+    }
+    
+    pub fn health(&self) -> Vec<TaskHealth> {
+        self.tasks.lock().values().cloned().collect()
+    }
+    
+    pub fn is_healthy(&self) -> bool {
+        self.tasks.lock().values().all(|task| task.state == TaskState::Running)
+    }
+    
+    pub fn record_cycle(&self, timing: CycleTiming) {
+        let mut cycles = self.cycles.lock();
+        if cycles.len() >= CYCLE_HISTORY {
+            cycles.pop_front();
+        }
+        cycles.push_back(timing);
+    }
+    
+    /// Recent monitoring cycles, oldest first.
+    pub fn cycles(&self) -> Vec<CycleTiming> {
+        self.cycles.lock().iter().cloned().collect()
+    }
+    
+    fn mark_running(&self, name: &str) {
+        let mut tasks = self.tasks.lock();
+        let task = tasks.entry(name.to_string()).or_insert_with(|| TaskHealth {
+            name: name.to_string(),
+            state: TaskState::Running,
+            restarts: 0,
+            started_at: Utc::now(),
+            last_error: None,
+            last_failure: None,
+        });
+        
+        if task.state == TaskState::Restarting {
+            task.restarts += 1;
+        }
+        task.state = TaskState::Running;
+        task.started_at = Utc::now();
+    }
+    
+    fn mark_failed(&self, name: &str, reason: String) {
+        if let Some(task) = self.tasks.lock().get_mut(name) {
+            task.state = TaskState::Restarting;
+            task.last_error = Some(reason);
+            task.last_failure = Some(Utc::now());
+        }
+    }
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
@@ -0,0 +1,112 @@
+use crate::models::metrics::HistoryRange;
+use crate::services::config::ConfigService;
+use crate::services::monitor::MonitorService;
+use crate::utils::zip::ZipWriter;
+use anyhow::Result;
+use chrono::Utc;
+use std::path::{Path, PathBuf};
+use sysinfo::System;
+use tokio::fs;
+use tokio::sync::RwLock;
+
+// Kept out of the downloads area, which every signed-in user can read
+pub const BUNDLE_DIR: &str = "data/support_bundles";
+const LOG_DIR: &str = "logs";
+const BUNDLE_PREFIX: &str = "support-bundle-";
+// Logs untouched for longer than this are left out
+const RECENT_LOG_DAYS: u64 = 7;
+// Anomaly history and learned state, as kept under data/
+const HISTORY_FILES: &[&str] = &[
+    "data/incidents.json",
+    "data/baseline.json",
+    "data/baseline_history.jsonl",
+    "data/availability.jsonl",
+    "data/oom_kills.jsonl",
+    "data/heartbeats.json",
+];
+
+/// Collects recent logs, metrics, anomaly history and the configuration with its
+/// secrets removed into `data/support_bundles/support-bundle-<time>.zip`, for
+/// attaching to a bug report or keeping after an incident. Returns the file name.
+pub async fn create(config: &ConfigService, monitor: &RwLock<MonitorService>) -> Result<String> {
+    let created_at = Utc::now();
+    let name = format!("{}{}.zip", BUNDLE_PREFIX, created_at.format("%Y%m%d-%H%M%S"));
+    let path = Path::new(BUNDLE_DIR).join(&name);
+    
+    let (recent, rollup) = {
+        let monitor = monitor.read().await;
+        (
+            serde_json::to_vec_pretty(monitor.get_metrics_history())?,
+            serde_json::to_vec_pretty(&monitor.history(HistoryRange::Week))?,
+        )
+    };
+    let settings = toml::to_string_pretty(&config.redacted().await)?;
+    let logs = recent_logs().await;
+    
+    fs::create_dir_all(BUNDLE_DIR).await?;
+    let mut zip = ZipWriter::new(fs::File::create(&path).await?);
+    let written = async {
+        let mut contents = vec!["config.toml".to_string(), "metrics/recent.json".to_string(), "metrics/week.json".to_string()];
+        zip.add_bytes("config.toml", settings.as_bytes()).await?;
+        zip.add_bytes("metrics/recent.json", &recent).await?;
+        zip.add_bytes("metrics/week.json", &rollup).await?;
+        
+        for file in HISTORY_FILES.iter().map(Path::new).filter(|file| file.is_file()) {
+            let entry = format!("history/{}", file.file_name().unwrap_or_default().to_string_lossy());
+            zip.add_file(&entry, file).await?;
+            contents.push(entry);
+        }
+        for file in &logs {
+            let entry = format!("logs/{}", file.file_name().unwrap_or_default().to_string_lossy());
+            zip.add_file(&entry, file).await?;
+            contents.push(entry);
+        }
+        
+        let manifest = serde_json::json!({
+            "created_at": created_at,
+            "host": System::host_name().unwrap_or_default(),
+            "version": env!("CARGO_PKG_VERSION"),
+            "files": contents,
+        });
+        zip.add_bytes("manifest.json", &serde_json::to_vec_pretty(&manifest)?).await?;
+        zip.finish().await
+    }
+    .await;
+    
+    if let Err(e) = written {
+        let _ = fs::remove_file(&path).await;
+        return Err(e);
+    }
+    Ok(name)
+}
+
+/// The bundle called `name`, when it is one this module wrote.
+pub fn bundle_path(name: &str) -> Option<PathBuf> {
+    let valid = name.starts_with(BUNDLE_PREFIX)
+        && name.ends_with(".zip")
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.'));
+    valid.then(|| Path::new(BUNDLE_DIR).join(name))
+}
+
+/// Files in the log directory modified within the last week, except bundles an
+/// earlier version wrote there.
+async fn recent_logs() -> Vec<PathBuf> {
+    let cutoff = std::time::SystemTime::now() - std::time::Duration::from_secs(RECENT_LOG_DAYS * 86400);
+    let mut logs = Vec::new();
+    
+    let Ok(mut entries) = fs::read_dir(LOG_DIR).await else {
+        return logs;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Ok(metadata) = entry.metadata().await else {
+            continue;
+        };
+        let recent = metadata.modified().is_ok_and(|modified| modified >= cutoff);
+        if metadata.is_file() && recent && !name.starts_with(BUNDLE_PREFIX) {
+            logs.push(entry.path());
+        }
+    }
+    logs.sort();
+    logs
+}
@@ -0,0 +1,168 @@
+use crate::models::config::{MonitoringConfig, SuricataConfig};
+use crate::models::metrics::SystemMetrics;
+use crate::services::collectors::Collector;
+use crate::services::log_watch::LogTail;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::IpAddr;
+use std::path::Path;
+
+pub const SURICATA_PREFIX: &str = "Suricata Alert: ";
+// Alerts kept at once, and targets listed per alert, so a flood cannot grow without bound
+const MAX_ALERTS: usize = 500;
+const MAX_TARGETS: usize = 20;
+
+/// A line of `eve.json`; only alert events are read.
+#[derive(Debug, Deserialize)]
+struct EveEvent {
+    event_type: String,
+    src_ip: Option<IpAddr>,
+    dest_ip: Option<IpAddr>,
+    alert: Option<EveAlert>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EveAlert {
+    signature_id: u64,
+    signature: String,
+    #[serde(default)]
+    category: String,
+    severity: u8,
+}
+
+/// One signature triggered by one source within the window.
+struct ActiveAlert {
+    signature: String,
+    category: String,
+    severity: u8,
+    targets: BTreeSet<IpAddr>,
+    hits: u64,
+    first_seen: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+}
+
+/// Alerts from Suricata's EVE JSON log, raised per source address and signature.
+pub struct SuricataCollector {
+    config: SuricataConfig,
+    tail: LogTail,
+    /// Active alerts by source address and signature id.
+    alerts: BTreeMap<(IpAddr, u64), ActiveAlert>,
+}
+
+impl SuricataCollector {
+    pub fn new() -> Self {
+        Self {
+            config: SuricataConfig::default(),
+            tail: LogTail::new(),
+            alerts: BTreeMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Collector for SuricataCollector {
+    fn name(&self) -> &str {
+        "suricata"
+    }
+    
+    fn configure(&mut self, config: &MonitoringConfig) {
+        if config.suricata != self.config {
+            self.alerts.clear();
+        }
+        self.config = config.suricata.clone();
+    }
+    
+    async fn collect(&mut self, _metrics: &mut SystemMetrics) -> Result<()> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        
+        let now = Utc::now();
+        for line in self.tail.read_new(Path::new(&self.config.eve_log)).await {
+            let Ok(event) = serde_json::from_str::<EveEvent>(&line) else {
+                continue;
+            };
+            let (Some(alert), Some(source)) = (event.alert, event.src_ip) else {
+                continue;
+            };
+            if event.event_type != "alert" || alert.severity > self.config.min_severity {
+                continue;
+            }
+            let active = self.alerts.entry((source, alert.signature_id)).or_insert_with(|| ActiveAlert {
+                signature: alert.signature,
+                category: alert.category,
+                severity: alert.severity,
+                targets: BTreeSet::new(),
+                hits: 0,
+                first_seen: now,
+                last_seen: now,
+            });
+            active.hits += 1;
+            active.last_seen = now;
+            if let Some(target) = event.dest_ip.filter(|_| active.targets.len() < MAX_TARGETS) {
+                active.targets.insert(target);
+            }
+        }
+        
+        let window = self.config.window as i64;
+        self.alerts.retain(|_, alert| (now - alert.last_seen).num_seconds() < window);
+        if self.alerts.len() > MAX_ALERTS {
+            let mut by_age: Vec<((IpAddr, u64), DateTime<Utc>)> =
+                self.alerts.iter().map(|(key, alert)| (*key, alert.last_seen)).collect();
+            by_age.sort_by_key(|(_, last_seen)| *last_seen);
+            for (key, _) in by_age.into_iter().take(self.alerts.len() - MAX_ALERTS) {
+                self.alerts.remove(&key);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    fn anomalies(&self) -> Vec<String> {
+        self.alerts
+            .iter()
+            .map(|((source, sid), alert)| {
+                format!(
+                    "{}{} {} (sid {}, severity {})",
+                    SURICATA_PREFIX, source, alert.signature, sid, alert.severity
+                )
+            })
+            .collect()
+    }
+    
+    fn details(&self) -> Option<Value> {
+        if !self.config.enabled {
+            return None;
+        }
+        let alerts: Vec<Value> = self
+            .alerts
+            .iter()
+            .map(|((source, sid), alert)| {
+                json!({
+                    "source": source,
+                    "sid": sid,
+                    "signature": alert.signature,
+                    "category": alert.category,
+                    "severity": alert.severity,
+                    "targets": alert.targets,
+                    "hits": alert.hits,
+                    "first_seen": alert.first_seen,
+                    "last_seen": alert.last_seen,
+                })
+            })
+            .collect();
+        Some(json!({ "alerts": alerts }))
+    }
+}
+
+/// The source address and Suricata severity of an alert anomaly.
+pub fn alert_source(anomaly: &str) -> Option<(IpAddr, u8)> {
+    let rest = anomaly.strip_prefix(SURICATA_PREFIX)?;
+    let source = rest.split(' ').next()?.parse().ok()?;
+    let severity = rest.rsplit_once(", severity ")?.1.get(..1)?.parse().ok()?;
+    Some((source, severity))
+}
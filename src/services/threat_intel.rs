@@ -1,26 +1,56 @@
+use crate::models::config::{BlocklistConfig, ThreatIntelConfig};
 use crate::models::metrics::ThreatIntel;
+use crate::services::monitor::MonitorService;
+use crate::utils::cidr::{IpNet, PrefixTrie};
 use anyhow::{anyhow, Result};
 use rss::Channel;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::RwLock;
 
 pub struct ThreatIntelService {
     feeds: Vec<(String, String)>,
+    blocklists: Vec<BlocklistConfig>,
+    refresh_interval: u64,
     data: HashMap<String, Vec<ThreatIntel>>,
+    /// The ranges of each blocklist as last fetched.
+    indicators: HashMap<String, Vec<IpNet>>,
+    monitor: Arc<RwLock<MonitorService>>,
 }
 
 impl ThreatIntelService {
-    pub fn new() -> Self {
-        let feeds = vec![
-            ("CISA".to_string(), "https://www.cisa.gov/news-events/cybersecurity-advisories.xml".to_string()),
-            ("BleepingComputer".to_string(), "https://www.bleepingcomputer.com/feed/".to_string()),
-            ("KrebsOnSecurity".to_string(), "https://krebsonsecurity.com/feed/".to_string()),
-            ("TheHackerNews".to_string(), "https://thehackernews.com/feeds/posts/default".to_string()),
-        ];
-        
-        Self {
-            feeds,
+    pub fn new(config: &ThreatIntelConfig, monitor: Arc<RwLock<MonitorService>>) -> Self {
+        let mut service = Self {
+            feeds: Vec::new(),
+            blocklists: Vec::new(),
+            refresh_interval: config.refresh_interval,
             data: HashMap::new(),
-        }
+            indicators: HashMap::new(),
+            monitor,
+        };
+        service.apply_config(config);
+        service
+    }
+    
+    /// Replaces the feed and blocklist lists, dropping cached items of those that were
+    /// removed. Ranges of removed blocklists stop matching after the next refresh.
+    pub fn apply_config(&mut self, config: &ThreatIntelConfig) {
+        self.feeds = config
+            .feeds
+            .iter()
+            .map(|feed| (feed.name.clone(), feed.url.clone()))
+            .collect();
+        self.blocklists = config.blocklists.clone();
+        self.refresh_interval = config.refresh_interval.max(60);
+        
+        let names: Vec<&String> = self.feeds.iter().map(|(name, _)| name).collect();
+        self.data.retain(|name, _| names.contains(&name));
+        let names: Vec<&String> = self.blocklists.iter().map(|list| &list.name).collect();
+        self.indicators.retain(|name, _| names.contains(&name));
+    }
+    
+    pub fn refresh_interval(&self) -> u64 {
+        self.refresh_interval
     }
     
     pub async fn fetch_all(&mut self) -> Result<()> {
@@ -40,6 +70,30 @@ impl ThreatIntelService {
             }
         }
         
+        // A list that fails to download keeps its previous ranges
+        for list in &self.blocklists {
+            match fetch_blocklist(&client, list).await {
+                Ok(ranges) => {
+                    self.indicators.insert(list.name.clone(), ranges);
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to fetch blocklist {}: {}", list.name, e);
+                }
+            }
+        }
+        
+        // A range on several lists is kept once, under whichever list came last
+        let mut trie = PrefixTrie::new();
+        for (name, ranges) in &self.indicators {
+            for range in ranges {
+                trie.insert(*range, name.clone());
+            }
+        }
+        if !trie.is_empty() {
+            tracing::info!("Matching connections against {} blocklisted ranges", trie.len());
+        }
+        self.monitor.write().await.set_indicators(Arc::new(trie));
+        
         Ok(())
     }
     
@@ -71,3 +125,26 @@ impl ThreatIntelService {
         &self.data
     }
 }
+
+/// Downloads a list as plain text, which is also how AbuseIPDB's blacklist endpoint
+/// answers when asked for it.
+async fn fetch_blocklist(client: &reqwest::Client, list: &BlocklistConfig) -> Result<Vec<IpNet>> {
+    let mut request = client.get(&list.url).header(reqwest::header::ACCEPT, "text/plain");
+    if let Some(api_key) = list.api_key.as_deref().filter(|key| !key.is_empty()) {
+        request = request.header("Key", api_key);
+    }
+    let response = request.send().await?.error_for_status()?;
+    Ok(parse_blocklist(&response.text().await?))
+}
+
+/// The distinct addresses and ranges of a plain-text list: the first word of each
+/// line, after dropping `#` and `;` comments. Lines that are not addresses are skipped.
+fn parse_blocklist(content: &str) -> Vec<IpNet> {
+    let mut seen = HashSet::new();
+    content
+        .lines()
+        .filter_map(|line| line.split(['#', ';']).next()?.split_whitespace().next())
+        .filter_map(|entry| entry.parse().ok())
+        .filter(|range| seen.insert(*range))
+        .collect()
+}
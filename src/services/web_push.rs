@@ -0,0 +1,311 @@
+use crate::models::push::PushSubscription;
+use crate::utils::data_dir;
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use chrono::Utc;
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use ring::{aead, agreement, hkdf};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tokio::fs;
+use tracing::{info, warn};
+
+const VAPID_KEY_FILE: &str = "data/vapid_key";
+const SUBSCRIPTIONS_FILE: &str = "data/push_subscriptions.json";
+// How long a push service holds a notification for a phone that is offline
+const PUSH_TTL_SECS: u64 = 86400;
+const PUSH_TIMEOUT_SECS: u64 = 10;
+const VAPID_EXPIRY_SECS: i64 = 12 * 3600;
+const RECORD_SIZE: u32 = 4096;
+// Browsers kept per account; subscribing another drops the oldest
+const MAX_SUBSCRIPTIONS_PER_USER: usize = 10;
+
+/// Sends Web Push notifications to browsers that subscribed from the settings page,
+/// signed with this server's VAPID key (RFC 8292) and encrypted for each browser
+/// (RFC 8291), so no third-party notification service is needed.
+pub struct WebPush {
+    key_pair: EcdsaKeyPair,
+    subscriptions: Mutex<Vec<PushSubscription>>,
+    write_lock: tokio::sync::Mutex<()>,
+}
+
+impl WebPush {
+    /// Loads the VAPID key, generating one on first start; browsers subscribed with
+    /// the old key stop receiving notifications if it is lost.
+    pub fn new() -> Self {
+        let rng = SystemRandom::new();
+        let stored = std::fs::read_to_string(VAPID_KEY_FILE)
+            .ok()
+            .and_then(|key| STANDARD.decode(key.trim()).ok())
+            .and_then(|pkcs8| EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &pkcs8, &rng).ok());
+        
+        let key_pair = match stored {
+            Some(key_pair) => key_pair,
+            None => {
+                let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+                    .expect("P-256 key generation");
+                if let Err(e) = write_private(VAPID_KEY_FILE, &STANDARD.encode(pkcs8.as_ref())) {
+                    warn!("Failed to save the Web Push key: {}", e);
+                } else {
+                    info!("Generated a Web Push key in {}", VAPID_KEY_FILE);
+                }
+                EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+                    .expect("freshly generated key")
+            }
+        };
+        
+        let subscriptions = std::fs::read_to_string(SUBSCRIPTIONS_FILE)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        
+        Self {
+            key_pair,
+            subscriptions: Mutex::new(subscriptions),
+            write_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+    
+    /// The key browsers pass as `applicationServerKey` when subscribing.
+    pub fn public_key(&self) -> String {
+        URL_SAFE_NO_PAD.encode(self.key_pair.public_key().as_ref())
+    }
+    
+    pub fn subscriptions(&self) -> Vec<PushSubscription> {
+        self.subscriptions.lock().unwrap().clone()
+    }
+    
+    /// Adds a browser, replacing an earlier subscription with the same endpoint. A
+    /// browser belongs to the account that subscribed it last, and each account keeps
+    /// its newest `MAX_SUBSCRIPTIONS_PER_USER` browsers.
+    pub async fn subscribe(&self, subscription: PushSubscription) -> Result<()> {
+        if !subscription.endpoint.starts_with("https://") {
+            return Err(anyhow!("Push endpoints must use https"));
+        }
+        let p256dh = URL_SAFE_NO_PAD.decode(subscription.keys.p256dh.trim_end_matches('='));
+        let auth = URL_SAFE_NO_PAD.decode(subscription.keys.auth.trim_end_matches('='));
+        if !matches!((&p256dh, &auth), (Ok(p256dh), Ok(auth)) if p256dh.len() == 65 && auth.len() == 16) {
+            return Err(anyhow!("Invalid subscription keys"));
+        }
+        
+        {
+            let mut subscriptions = self.subscriptions.lock().unwrap();
+            subscriptions.retain(|existing| existing.endpoint != subscription.endpoint);
+            let owned = subscriptions.iter().filter(|existing| existing.username == subscription.username).count();
+            let mut excess = (owned + 1).saturating_sub(MAX_SUBSCRIPTIONS_PER_USER);
+            // Kept in subscription order, so the first ones are the oldest
+            subscriptions.retain(|existing| {
+                let drop = excess > 0 && existing.username == subscription.username;
+                excess -= drop as usize;
+                !drop
+            });
+            subscriptions.push(subscription);
+        }
+        self.save().await
+    }
+    
+    /// Removes the browser subscribed at `endpoint` when `owner` subscribed it, or
+    /// whoever did when `owner` is `None`; false when there was no such subscription.
+    pub async fn unsubscribe(&self, endpoint: &str, owner: Option<&str>) -> Result<bool> {
+        let removed = {
+            let mut subscriptions = self.subscriptions.lock().unwrap();
+            let before = subscriptions.len();
+            subscriptions.retain(|existing| {
+                existing.endpoint != endpoint || owner.is_some_and(|owner| existing.username != owner)
+            });
+            subscriptions.len() != before
+        };
+        if removed {
+            self.save().await?;
+        }
+        Ok(removed)
+    }
+    
+    /// Sends `message` to every subscribed browser and returns how many accepted it.
+    /// Subscriptions the push service reports as gone are dropped. `contact` is the
+    /// `mailto:` or `https:` address push services may use to reach the operator.
+    pub async fn notify(&self, message: &serde_json::Value, contact: &str) -> usize {
+        self.deliver(message, contact, |_| true).await
+    }
+    
+    /// Sends `message` to the browsers of `users` only.
+    pub async fn notify_users(&self, message: &serde_json::Value, contact: &str, users: &HashSet<String>) -> usize {
+        self.deliver(message, contact, |username| users.contains(username)).await
+    }
+    
+    async fn deliver(&self, message: &serde_json::Value, contact: &str, to: impl Fn(&str) -> bool) -> usize {
+        let subscriptions: Vec<PushSubscription> =
+            self.subscriptions().into_iter().filter(|subscription| to(&subscription.username)).collect();
+        if subscriptions.is_empty() {
+            return 0;
+        }
+        let Ok(client) = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(PUSH_TIMEOUT_SECS))
+            .build()
+        else {
+            return 0;
+        };
+        let payload = message.to_string();
+        
+        let mut delivered = 0;
+        let mut gone = Vec::new();
+        for subscription in &subscriptions {
+            match self.send(&client, subscription, payload.as_bytes(), contact).await {
+                Ok(status) if status.is_success() => delivered += 1,
+                // The browser unsubscribed or the subscription expired
+                Ok(status) if status.as_u16() == 404 || status.as_u16() == 410 => gone.push(subscription.endpoint.clone()),
+                Ok(status) => warn!("Push service for {} returned {}", subscription.username, status),
+                Err(e) => warn!("Push notification to {} failed: {}", subscription.username, e),
+            }
+        }
+        
+        if !gone.is_empty() {
+            self.subscriptions.lock().unwrap().retain(|subscription| !gone.contains(&subscription.endpoint));
+            if let Err(e) = self.save().await {
+                warn!("Failed to save push subscriptions: {}", e);
+            }
+        }
+        delivered
+    }
+    
+    async fn send(
+        &self,
+        client: &reqwest::Client,
+        subscription: &PushSubscription,
+        payload: &[u8],
+        contact: &str,
+    ) -> Result<reqwest::StatusCode> {
+        let body = encrypt(payload, &subscription.keys.p256dh, &subscription.keys.auth)?;
+        let authorization = self.vapid_authorization(&subscription.endpoint, contact)?;
+        
+        let response = client
+            .post(&subscription.endpoint)
+            .header("Authorization", authorization)
+            .header("Content-Encoding", "aes128gcm")
+            .header("Content-Type", "application/octet-stream")
+            .header("TTL", PUSH_TTL_SECS.to_string())
+            .header("Urgency", "high")
+            .body(body)
+            .send()
+            .await?;
+        Ok(response.status())
+    }
+    
+    /// A VAPID `Authorization` header: a short-lived ES256 token for the push
+    /// service's origin, with the public key it is checked against.
+    fn vapid_authorization(&self, endpoint: &str, contact: &str) -> Result<String> {
+        let url = reqwest::Url::parse(endpoint)?;
+        let audience = url.origin().ascii_serialization();
+        
+        let header = URL_SAFE_NO_PAD.encode(r#"{"typ":"JWT","alg":"ES256"}"#);
+        let claims = URL_SAFE_NO_PAD.encode(
+            serde_json::json!({
+                "aud": audience,
+                "exp": Utc::now().timestamp() + VAPID_EXPIRY_SECS,
+                "sub": contact,
+            })
+            .to_string(),
+        );
+        let signing_input = format!("{}.{}", header, claims);
+        let signature = self
+            .key_pair
+            .sign(&SystemRandom::new(), signing_input.as_bytes())
+            .map_err(|_| anyhow!("Failed to sign the VAPID token"))?;
+        
+        Ok(format!(
+            "vapid t={}.{}, k={}",
+            signing_input,
+            URL_SAFE_NO_PAD.encode(signature.as_ref()),
+            self.public_key()
+        ))
+    }
+    
+    async fn save(&self) -> Result<()> {
+        let _guard = self.write_lock.lock().await;
+        let content = serde_json::to_string_pretty(&self.subscriptions())?;
+        data_dir::ensure_async().await?;
+        fs::write(SUBSCRIPTIONS_FILE, content).await?;
+        Ok(())
+    }
+}
+
+/// Encrypts `payload` for one browser as a single `aes128gcm` record (RFC 8188),
+/// with keys agreed between a one-off key pair and the browser's (RFC 8291).
+fn encrypt(payload: &[u8], p256dh: &str, auth: &str) -> Result<Vec<u8>> {
+    let browser_key = URL_SAFE_NO_PAD.decode(p256dh.trim_end_matches('='))?;
+    let auth_secret = URL_SAFE_NO_PAD.decode(auth.trim_end_matches('='))?;
+    let rng = SystemRandom::new();
+    
+    let private_key = agreement::EphemeralPrivateKey::generate(&agreement::ECDH_P256, &rng)
+        .map_err(|_| anyhow!("Failed to generate a push key"))?;
+    let public_key = private_key
+        .compute_public_key()
+        .map_err(|_| anyhow!("Failed to generate a push key"))?;
+    let shared_secret = agreement::agree_ephemeral(
+        private_key,
+        &agreement::UnparsedPublicKey::new(&agreement::ECDH_P256, &browser_key),
+        |secret| secret.to_vec(),
+    )
+    .map_err(|_| anyhow!("Invalid browser push key"))?;
+    
+    let mut key_info = b"WebPush: info\0".to_vec();
+    key_info.extend_from_slice(&browser_key);
+    key_info.extend_from_slice(public_key.as_ref());
+    let input_key = hkdf_expand(&auth_secret, &shared_secret, &key_info, 32)?;
+    
+    let mut salt = [0u8; 16];
+    rng.fill(&mut salt).map_err(|_| anyhow!("Failed to generate a salt"))?;
+    let content_key = hkdf_expand(&salt, &input_key, b"Content-Encoding: aes128gcm\0", 16)?;
+    let nonce = hkdf_expand(&salt, &input_key, b"Content-Encoding: nonce\0", 12)?;
+    
+    // 0x02 marks the last (and only) record
+    let mut record = payload.to_vec();
+    record.push(2);
+    if record.len() + aead::AES_128_GCM.tag_len() > RECORD_SIZE as usize {
+        return Err(anyhow!("Push payload too large"));
+    }
+    let key = aead::LessSafeKey::new(
+        aead::UnboundKey::new(&aead::AES_128_GCM, &content_key).map_err(|_| anyhow!("Invalid content key"))?,
+    );
+    let nonce = aead::Nonce::try_assume_unique_for_key(&nonce).map_err(|_| anyhow!("Invalid nonce"))?;
+    key.seal_in_place_append_tag(nonce, aead::Aad::empty(), &mut record)
+        .map_err(|_| anyhow!("Failed to encrypt the push payload"))?;
+    
+    let mut body = Vec::with_capacity(16 + 4 + 1 + 65 + record.len());
+    body.extend_from_slice(&salt);
+    body.extend_from_slice(&RECORD_SIZE.to_be_bytes());
+    body.push(public_key.as_ref().len() as u8);
+    body.extend_from_slice(public_key.as_ref());
+    body.extend_from_slice(&record);
+    Ok(body)
+}
+
+struct OutputLength(usize);
+
+impl hkdf::KeyType for OutputLength {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+/// HKDF-SHA256 of `secret` with `salt`, expanded with `info` to `len` bytes.
+fn hkdf_expand(salt: &[u8], secret: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>> {
+    let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, salt).extract(secret);
+    let info = [info];
+    let okm = prk.expand(&info, OutputLength(len)).map_err(|_| anyhow!("HKDF output too long"))?;
+    let mut out = vec![0u8; len];
+    okm.fill(&mut out).map_err(|_| anyhow!("HKDF output too long"))?;
+    Ok(out)
+}
+
+/// Writes a file only the monitor's user can read.
+fn write_private(path: &str, content: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    
+    data_dir::ensure()?;
+    let mut file = std::fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(path)?;
+    file.write_all(content.as_bytes())
+}
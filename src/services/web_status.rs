@@ -0,0 +1,231 @@
+use crate::models::config::{MonitoringConfig, WebServerKind, WebStatusConfig};
+use crate::models::metrics::SystemMetrics;
+use crate::services::collectors::Collector;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+/// Readings of one status page. Counters only grow and are reported as rates.
+#[derive(Default)]
+struct StatusPage {
+    gauges: Vec<(&'static str, f64)>,
+    counters: Vec<(&'static str, f64)>,
+}
+
+#[derive(Default)]
+struct ServerState {
+    last_poll: Option<DateTime<Utc>>,
+    counters: HashMap<&'static str, (DateTime<Utc>, f64)>,
+    /// Readings of the latest poll, repeated until the next one.
+    values: Vec<(String, f64)>,
+    error: Option<String>,
+}
+
+/// Connections, workers and queues from nginx, Apache and PHP-FPM status pages.
+pub struct WebStatusCollector {
+    client: reqwest::Client,
+    servers: Vec<WebStatusConfig>,
+    state: BTreeMap<String, ServerState>,
+}
+
+impl WebStatusCollector {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(5))
+                .build()
+                .unwrap_or_default(),
+            servers: Vec::new(),
+            state: BTreeMap::new(),
+        }
+    }
+    
+    async fn fetch(&self, server: &WebStatusConfig) -> Result<StatusPage> {
+        let separator = if server.url.contains('?') { '&' } else { '?' };
+        let url = match server.kind {
+            WebServerKind::Nginx => server.url.clone(),
+            WebServerKind::Apache => format!("{}{}auto", server.url, separator),
+            WebServerKind::PhpFpm => format!("{}{}json", server.url, separator),
+        };
+        let body = self.client.get(&url).send().await?.error_for_status()?.text().await?;
+        
+        match server.kind {
+            WebServerKind::Nginx => parse_nginx(&body),
+            WebServerKind::Apache => parse_apache(&body),
+            WebServerKind::PhpFpm => parse_php_fpm(&body),
+        }
+    }
+}
+
+#[async_trait]
+impl Collector for WebStatusCollector {
+    fn name(&self) -> &str {
+        "web_status"
+    }
+    
+    fn configure(&mut self, config: &MonitoringConfig) {
+        // A server moved to another URL starts its counters over
+        self.state.retain(|name, _| {
+            let url = url_of(&config.web_status, name);
+            url.is_some() && url == url_of(&self.servers, name)
+        });
+        self.servers = config.web_status.clone();
+    }
+    
+    async fn collect(&mut self, metrics: &mut SystemMetrics) -> Result<()> {
+        let now = Utc::now();
+        
+        for server in &self.servers {
+            let due = self.state.get(&server.name).and_then(|state| state.last_poll).map_or(true, |last| {
+                (now - last).num_seconds() >= server.interval.unwrap_or(0) as i64
+            });
+            if due {
+                let result = self.fetch(server).await;
+                let state = self.state.entry(server.name.clone()).or_default();
+                state.last_poll = Some(now);
+                match result {
+                    Ok(page) => {
+                        state.values = page
+                            .gauges
+                            .iter()
+                            .map(|(measure, value)| (format!("{}.{}", server.name, measure), *value))
+                            .collect();
+                        for (counter, value) in page.counters {
+                            let previous = state.counters.insert(counter, (now, value));
+                            // A counter that dropped was reset by a reload or restart, which gives no rate
+                            if let Some((then, before)) = previous.filter(|(then, before)| *then < now && value >= *before) {
+                                let seconds = (now - then).num_milliseconds() as f64 / 1000.0;
+                                state.values.push((format!("{}.{}_per_s", server.name, counter), (value - before) / seconds));
+                            }
+                        }
+                        state.error = None;
+                    }
+                    Err(e) => {
+                        state.values.clear();
+                        state.error = Some(e.to_string());
+                    }
+                }
+            }
+            
+            if let Some(state) = self.state.get(&server.name) {
+                metrics.custom.extend(state.values.iter().cloned());
+            }
+        }
+        
+        let errors: Vec<String> = self
+            .state
+            .iter()
+            .filter_map(|(name, state)| Some(format!("{}: {}", name, state.error.as_ref()?)))
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(errors.join("; ")))
+        }
+    }
+}
+
+/// nginx `stub_status`:
+///
+/// ```text
+/// Active connections: 291
+/// server accepts handled requests
+///  16630948 16630948 31070465
+/// Reading: 6 Writing: 179 Waiting: 106
+/// ```
+fn parse_nginx(body: &str) -> Result<StatusPage> {
+    let lines: Vec<&str> = body.lines().collect();
+    let invalid = || anyhow!("not an nginx stub_status page");
+    
+    let active = lines
+        .first()
+        .and_then(|line| line.strip_prefix("Active connections:"))
+        .and_then(|value| value.trim().parse::<f64>().ok())
+        .ok_or_else(invalid)?;
+    let totals: Vec<f64> = lines
+        .get(2)
+        .ok_or_else(invalid)?
+        .split_whitespace()
+        .filter_map(|value| value.parse().ok())
+        .collect();
+    let [accepts, handled, requests] = totals[..] else {
+        return Err(invalid());
+    };
+    let states: Vec<f64> = lines
+        .get(3)
+        .ok_or_else(invalid)?
+        .split_whitespace()
+        .skip(1)
+        .step_by(2)
+        .filter_map(|value| value.parse().ok())
+        .collect();
+    let [reading, writing, waiting] = states[..] else {
+        return Err(invalid());
+    };
+    
+    Ok(StatusPage {
+        gauges: vec![
+            ("active_connections", active),
+            ("reading", reading),
+            ("writing", writing),
+            ("waiting", waiting),
+        ],
+        counters: vec![("requests", requests), ("dropped", accepts - handled)],
+    })
+}
+
+/// Apache `mod_status?auto`, one `Key: value` per line.
+fn parse_apache(body: &str) -> Result<StatusPage> {
+    let fields: HashMap<&str, f64> = body
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .filter_map(|(key, value)| Some((key.trim(), value.trim().parse().ok()?)))
+        .collect();
+    let (Some(&busy), Some(&idle)) = (fields.get("BusyWorkers"), fields.get("IdleWorkers")) else {
+        return Err(anyhow!("not an Apache mod_status page; is ExtendedStatus on?"));
+    };
+    
+    let mut page = StatusPage {
+        gauges: vec![("busy_workers", busy), ("idle_workers", idle)],
+        counters: Vec::new(),
+    };
+    if busy + idle > 0.0 {
+        page.gauges.push(("busy_pct", busy * 100.0 / (busy + idle)));
+    }
+    if let Some(&accesses) = fields.get("Total Accesses") {
+        page.counters.push(("requests", accesses));
+    }
+    Ok(page)
+}
+
+/// PHP-FPM `status?json`.
+fn parse_php_fpm(body: &str) -> Result<StatusPage> {
+    let status: Value = serde_json::from_str(body).map_err(|_| anyhow!("not a PHP-FPM status page"))?;
+    let field = |key: &str| status[key].as_f64().ok_or_else(|| anyhow!("PHP-FPM status without '{}'", key));
+    
+    let active = field("active processes")?;
+    let total = field("total processes")?;
+    let mut page = StatusPage {
+        gauges: vec![
+            ("listen_queue", field("listen queue")?),
+            ("active_processes", active),
+            ("idle_processes", field("idle processes")?),
+        ],
+        counters: vec![
+            ("requests", field("accepted conn")?),
+            ("max_children_reached", field("max children reached")?),
+            ("slow_requests", field("slow requests")?),
+        ],
+    };
+    if total > 0.0 {
+        page.gauges.push(("busy_pct", active * 100.0 / total));
+    }
+    Ok(page)
+}
+
+fn url_of<'a>(servers: &'a [WebStatusConfig], name: &str) -> Option<&'a str> {
+    servers.iter().find(|server| server.name == name).map(|server| server.url.as_str())
+}
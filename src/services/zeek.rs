@@ -0,0 +1,237 @@
+use crate::models::config::{MonitoringConfig, ZeekConfig};
+use crate::models::metrics::SystemMetrics;
+use crate::services::collectors::Collector;
+use crate::services::log_watch::LogTail;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::io::{BufRead, BufReader};
+use std::net::IpAddr;
+use std::path::Path;
+
+const LOGS: [&str; 3] = ["conn", "dns", "notice"];
+// Connection states where the responder never answered or refused
+const FAILED_STATES: [&str; 2] = ["S0", "REJ"];
+// Lines searched for the `#fields` header at the top of a TSV log
+const HEADER_LINES: usize = 16;
+
+/// A connection from `conn.log`.
+struct Connection {
+    at: DateTime<Utc>,
+    source: Option<IpAddr>,
+    bytes: u64,
+    failed: bool,
+}
+
+/// A notice type raised for one source within the window.
+struct ActiveNotice {
+    message: String,
+    hits: u64,
+    last_seen: DateTime<Utc>,
+}
+
+/// Connection and DNS statistics and notices from the logs of a Zeek sensor, in
+/// Zeek's TSV or JSON format.
+pub struct ZeekCollector {
+    config: ZeekConfig,
+    tail: LogTail,
+    /// Column names of each TSV log, from its `#fields` header.
+    fields: HashMap<&'static str, Vec<String>>,
+    connections: VecDeque<Connection>,
+    /// Arrival time of each DNS answer, and whether it was NXDOMAIN.
+    answers: VecDeque<(DateTime<Utc>, bool)>,
+    /// Active notices by type and source.
+    notices: BTreeMap<(String, Option<IpAddr>), ActiveNotice>,
+    started: DateTime<Utc>,
+}
+
+impl ZeekCollector {
+    pub fn new() -> Self {
+        Self {
+            config: ZeekConfig::default(),
+            tail: LogTail::new(),
+            fields: HashMap::new(),
+            connections: VecDeque::new(),
+            answers: VecDeque::new(),
+            notices: BTreeMap::new(),
+            started: Utc::now(),
+        }
+    }
+    
+    fn record(&mut self, log: &str, record: &HashMap<String, String>, now: DateTime<Utc>) {
+        let field = |name: &str| record.get(name).map(String::as_str);
+        let source = field("id.orig_h").and_then(|source| source.parse().ok());
+        match log {
+            "conn" => {
+                let bytes = ["orig_bytes", "resp_bytes"]
+                    .iter()
+                    .filter_map(|name| field(name)?.parse::<u64>().ok())
+                    .sum();
+                let failed = field("conn_state").is_some_and(|state| FAILED_STATES.contains(&state));
+                self.connections.push_back(Connection { at: now, source, bytes, failed });
+            }
+            "dns" => {
+                self.answers.push_back((now, field("rcode_name") == Some("NXDOMAIN")));
+            }
+            _ => {
+                let Some(note) = field("note") else {
+                    return;
+                };
+                let source = field("src").and_then(|source| source.parse().ok()).or(source);
+                let notice = self.notices.entry((note.to_string(), source)).or_insert_with(|| ActiveNotice {
+                    message: String::new(),
+                    hits: 0,
+                    last_seen: now,
+                });
+                notice.message = field("msg").unwrap_or_default().to_string();
+                notice.hits += 1;
+                notice.last_seen = now;
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Collector for ZeekCollector {
+    fn name(&self) -> &str {
+        "zeek"
+    }
+    
+    fn configure(&mut self, config: &MonitoringConfig) {
+        if config.zeek.log_dir != self.config.log_dir {
+            self.fields.clear();
+        }
+        self.config = config.zeek.clone();
+    }
+    
+    async fn collect(&mut self, metrics: &mut SystemMetrics) -> Result<()> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        
+        let now = Utc::now();
+        for log in LOGS {
+            let path = Path::new(&self.config.log_dir).join(format!("{}.log", log));
+            for line in self.tail.read_new(&path).await {
+                if let Some(fields) = line.strip_prefix("#fields\t") {
+                    self.fields.insert(log, fields.split('\t').map(str::to_string).collect());
+                    continue;
+                }
+                // Started on a TSV log part-way through: its header is at the top
+                if !line.starts_with('{') && !self.fields.contains_key(log) {
+                    if let Some(fields) = read_header(&path) {
+                        self.fields.insert(log, fields);
+                    }
+                }
+                if let Some(record) = parse_line(&line, self.fields.get(log).map(Vec::as_slice)) {
+                    self.record(log, &record, now);
+                }
+            }
+        }
+        
+        let window = self.config.window as i64;
+        while self.connections.front().is_some_and(|conn| (now - conn.at).num_seconds() >= window) {
+            self.connections.pop_front();
+        }
+        while self.answers.front().is_some_and(|(at, _)| (now - *at).num_seconds() >= window) {
+            self.answers.pop_front();
+        }
+        let notice_window = self.config.notice_window as i64;
+        self.notices.retain(|_, notice| (now - notice.last_seen).num_seconds() < notice_window);
+        
+        // Until a full window has passed since startup, rates are over the time so far
+        let seconds = ((now - self.started).num_seconds() as f64).clamp(1.0, self.config.window as f64);
+        let share = |count: usize, total: usize| {
+            if total == 0 {
+                0.0
+            } else {
+                count as f64 * 100.0 / total as f64
+            }
+        };
+        let connections = self.connections.len();
+        let failed = self.connections.iter().filter(|conn| conn.failed).count();
+        let bytes: u64 = self.connections.iter().map(|conn| conn.bytes).sum();
+        let sources: BTreeSet<IpAddr> = self.connections.iter().filter_map(|conn| conn.source).collect();
+        let nxdomain = self.answers.iter().filter(|(_, nxdomain)| *nxdomain).count();
+        metrics.custom.insert("zeek.connections_per_s".to_string(), connections as f64 / seconds);
+        metrics.custom.insert("zeek.failed_connections_pct".to_string(), share(failed, connections));
+        metrics.custom.insert("zeek.kb_per_s".to_string(), bytes as f64 / 1024.0 / seconds);
+        metrics.custom.insert("zeek.sources".to_string(), sources.len() as f64);
+        metrics.custom.insert("zeek.dns_queries_per_s".to_string(), self.answers.len() as f64 / seconds);
+        metrics.custom.insert("zeek.dns_nxdomain_pct".to_string(), share(nxdomain, self.answers.len()));
+        
+        Ok(())
+    }
+    
+    fn anomalies(&self) -> Vec<String> {
+        self.notices
+            .keys()
+            .map(|(note, source)| match source {
+                Some(source) => format!("Zeek Notice: {} from {}", note, source),
+                None => format!("Zeek Notice: {}", note),
+            })
+            .collect()
+    }
+    
+    fn details(&self) -> Option<Value> {
+        if !self.config.enabled {
+            return None;
+        }
+        let notices: Vec<Value> = self
+            .notices
+            .iter()
+            .map(|((note, source), notice)| {
+                json!({
+                    "note": note,
+                    "source": source,
+                    "message": notice.message,
+                    "hits": notice.hits,
+                    "last_seen": notice.last_seen,
+                })
+            })
+            .collect();
+        Some(json!({ "connections": self.connections.len(), "dns_queries": self.answers.len(), "notices": notices }))
+    }
+}
+
+/// The column names from the `#fields` line at the top of a TSV log.
+fn read_header(path: &Path) -> Option<Vec<String>> {
+    let file = std::fs::File::open(path).ok()?;
+    BufReader::new(file)
+        .lines()
+        .take(HEADER_LINES)
+        .map_while(Result::ok)
+        .find_map(|line| line.strip_prefix("#fields\t").map(|fields| fields.split('\t').map(str::to_string).collect()))
+}
+
+/// A log line as field names and values. JSON lines carry their names; TSV lines take
+/// them from the log's header. Unset and empty fields are left out.
+fn parse_line(line: &str, fields: Option<&[String]>) -> Option<HashMap<String, String>> {
+    if line.starts_with('{') {
+        let Ok(Value::Object(object)) = serde_json::from_str::<Value>(line) else {
+            return None;
+        };
+        let record = object
+            .into_iter()
+            .filter_map(|(name, value)| match value {
+                Value::String(text) => Some((name, text)),
+                Value::Number(number) => Some((name, number.to_string())),
+                Value::Bool(flag) => Some((name, flag.to_string())),
+                _ => None,
+            })
+            .collect();
+        return Some(record);
+    }
+    if line.starts_with('#') {
+        return None;
+    }
+    let record = fields?
+        .iter()
+        .zip(line.split('\t'))
+        .filter(|(_, value)| *value != "-" && *value != "(empty)")
+        .map(|(name, value)| (name.clone(), value.to_string()))
+        .collect();
+    Some(record)
+}
@@ -0,0 +1,32 @@
+use std::borrow::Cow;
+use std::path::{Component, Path};
+
+/// Files in `static/` beside the working directory replace the built-in ones of the
+/// same name, so the look can be changed without rebuilding.
+pub const OVERRIDE_DIR: &str = "static";
+
+// Everything under static/ in the source tree, compiled into the binary so copying
+// the executable alone is enough to run it
+const EMBEDDED: &[(&str, &[u8])] = &[
+    ("icon.svg", include_bytes!("../../static/icon.svg")),
+    ("manifest.webmanifest", include_bytes!("../../static/manifest.webmanifest")),
+    ("sw.js", include_bytes!("../../static/sw.js")),
+];
+
+/// The static file at `path`: an override from `static/` when there is one,
+/// otherwise the built-in copy.
+pub async fn load(path: &str) -> Option<Cow<'static, [u8]>> {
+    let relative = Path::new(path);
+    if path.is_empty() || !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+        return None;
+    }
+    
+    match tokio::fs::read(Path::new(OVERRIDE_DIR).join(relative)).await {
+        Ok(content) => Some(Cow::Owned(content)),
+        Err(_) => embedded(path).map(Cow::Borrowed),
+    }
+}
+
+fn embedded(path: &str) -> Option<&'static [u8]> {
+    EMBEDDED.iter().find(|(name, _)| *name == path).map(|(_, content)| *content)
+}
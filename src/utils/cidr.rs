@@ -0,0 +1,232 @@
+use anyhow::{anyhow, Error};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+/// A range of addresses in CIDR notation, such as `203.0.113.0/24`. A bare
+/// address is a range of one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IpNet {
+    /// The first address; host bits are always clear.
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNet {
+    /// The range of `prefix` bits around `addr`, or `None` when the prefix is longer
+    /// than the address. IPv4-mapped IPv6 addresses are treated as IPv4.
+    pub fn new(addr: IpAddr, prefix: u8) -> Option<Self> {
+        let addr = canonical(addr);
+        let (bits, width) = bits(addr);
+        if prefix > width {
+            return None;
+        }
+        let addr = match addr {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(((bits & mask(prefix)) >> 96) as u32)),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(bits & mask(prefix))),
+        };
+        Some(Self { addr, prefix })
+    }
+    
+    /// The range holding only `addr`.
+    pub fn host(addr: IpAddr) -> Self {
+        let addr = canonical(addr);
+        Self {
+            addr,
+            prefix: bits(addr).1,
+        }
+    }
+    
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+    
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+    
+    pub fn is_host(&self) -> bool {
+        self.prefix == bits(self.addr).1
+    }
+    
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = canonical(ip);
+        ip.is_ipv4() == self.addr.is_ipv4() && bits(ip).0 & mask(self.prefix) == bits(self.addr).0
+    }
+    
+    /// Whether the two ranges share an address, i.e. one holds the other.
+    pub fn overlaps(&self, other: IpNet) -> bool {
+        self.contains(other.addr) || other.contains(self.addr)
+    }
+    
+    /// Whether the range reaches into private, shared (CGNAT), link-local or unique
+    /// local addresses, which are never on the internet and so never a threat source.
+    pub fn is_private(&self) -> bool {
+        [
+            ("10.0.0.0", 8),
+            ("172.16.0.0", 12),
+            ("192.168.0.0", 16),
+            ("100.64.0.0", 10),
+            ("169.254.0.0", 16),
+            ("fe80::", 10),
+            ("fc00::", 7),
+        ]
+        .iter()
+        .filter_map(|(addr, prefix)| IpNet::new(addr.parse().ok()?, *prefix))
+        .any(|private| private.overlaps(*self))
+    }
+}
+
+impl fmt::Display for IpNet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_host() {
+            write!(f, "{}", self.addr)
+        } else {
+            write!(f, "{}/{}", self.addr, self.prefix)
+        }
+    }
+}
+
+impl FromStr for IpNet {
+    type Err = Error;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('/') {
+            Some((addr, prefix)) => {
+                let addr: IpAddr = addr.parse()?;
+                let prefix: u8 = prefix.parse()?;
+                Self::new(addr, prefix).ok_or_else(|| anyhow!("prefix /{} is too long for {}", prefix, addr))
+            }
+            None => Ok(Self::host(s.parse()?)),
+        }
+    }
+}
+
+/// Values keyed by address range, looked up by the most specific range holding an
+/// address. A binary trie over the address bits, so a lookup costs at most one step
+/// per bit however many ranges are stored.
+pub struct PrefixTrie<T> {
+    v4: Node<T>,
+    v6: Node<T>,
+    len: usize,
+}
+
+struct Node<T> {
+    value: Option<T>,
+    children: [Option<Box<Node<T>>>; 2],
+}
+
+impl<T> Node<T> {
+    fn new() -> Self {
+        Self {
+            value: None,
+            children: [None, None],
+        }
+    }
+}
+
+impl<T> Default for PrefixTrie<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> PrefixTrie<T> {
+    pub fn new() -> Self {
+        Self {
+            v4: Node::new(),
+            v6: Node::new(),
+            len: 0,
+        }
+    }
+    
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    
+    /// Stores `value` for `net`, returning the value it replaces.
+    pub fn insert(&mut self, net: IpNet, value: T) -> Option<T> {
+        let bits = bits(net.addr).0;
+        let mut node = if net.addr.is_ipv4() { &mut self.v4 } else { &mut self.v6 };
+        for depth in 0..net.prefix {
+            node = node.children[bit(bits, depth)].get_or_insert_with(|| Box::new(Node::new()));
+        }
+        let previous = node.value.replace(value);
+        if previous.is_none() {
+            self.len += 1;
+        }
+        previous
+    }
+    
+    /// The most specific stored range holding `ip`, and its value.
+    pub fn get(&self, ip: IpAddr) -> Option<(IpNet, &T)> {
+        let ip = canonical(ip);
+        let (bits, width) = bits(ip);
+        let mut node = if ip.is_ipv4() { &self.v4 } else { &self.v6 };
+        let mut best = node.value.as_ref().map(|value| (0, value));
+        for depth in 0..width {
+            match &node.children[bit(bits, depth)] {
+                Some(child) => node = child,
+                None => break,
+            }
+            if let Some(value) = &node.value {
+                best = Some((depth + 1, value));
+            }
+        }
+        best.and_then(|(prefix, value)| Some((IpNet::new(ip, prefix)?, value)))
+    }
+    
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.get(ip).is_some()
+    }
+    
+    /// Whether any stored range shares an address with `net`, either holding it or
+    /// lying inside it.
+    pub fn overlaps(&self, net: IpNet) -> bool {
+        let bits = bits(net.addr).0;
+        let mut node = if net.addr.is_ipv4() { &self.v4 } else { &self.v6 };
+        for depth in 0..net.prefix {
+            if node.value.is_some() {
+                return true;
+            }
+            match &node.children[bit(bits, depth)] {
+                Some(child) => node = child,
+                None => return false,
+            }
+        }
+        // Nodes only exist on the way to a stored range, so one below the range means
+        // a stored range inside it
+        node.value.is_some() || node.children.iter().any(Option::is_some)
+    }
+}
+
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        IpAddr::V4(_) => ip,
+    }
+}
+
+/// The address as bits from the top of a `u128`, and how many of them there are.
+fn bits(ip: IpAddr) -> (u128, u8) {
+    match ip {
+        IpAddr::V4(v4) => ((u32::from(v4) as u128) << 96, 32),
+        IpAddr::V6(v6) => (u128::from(v6), 128),
+    }
+}
+
+fn mask(prefix: u8) -> u128 {
+    if prefix == 0 {
+        0
+    } else {
+        u128::MAX << (128 - prefix as u32)
+    }
+}
+
+fn bit(bits: u128, depth: u8) -> usize {
+    ((bits >> (127 - depth as u32)) & 1) as usize
+}
@@ -0,0 +1,14 @@
+use std::io;
+
+/// Where the monitor keeps its state, relative to its working directory.
+pub const DATA_DIR: &str = "data";
+
+/// Creates the data directory if it is missing, before a file in it is written.
+pub fn ensure() -> io::Result<()> {
+    std::fs::create_dir_all(DATA_DIR)
+}
+
+/// [`ensure`] without blocking the runtime.
+pub async fn ensure_async() -> io::Result<()> {
+    tokio::fs::create_dir_all(DATA_DIR).await
+}
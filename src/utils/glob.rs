@@ -0,0 +1,57 @@
+use std::path::{Path, PathBuf};
+
+/// Matches `name` against a shell-style pattern supporting `*` and `?`.
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // Let the last `*` absorb one more character and retry
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Expands a pattern whose wildcards are limited to the final path component,
+/// e.g. `conf.d/*.toml`, relative to `base`. Results are sorted for a stable order.
+pub fn expand(base: &Path, pattern: &str) -> Vec<PathBuf> {
+    let full = base.join(pattern);
+    let (dir, file_pattern) = match (full.parent(), full.file_name().and_then(|f| f.to_str())) {
+        (Some(dir), Some(file_pattern)) => (dir.to_path_buf(), file_pattern.to_string()),
+        _ => return Vec::new(),
+    };
+    
+    if !file_pattern.contains(['*', '?']) {
+        return if full.is_file() { vec![full] } else { Vec::new() };
+    }
+    
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+                .filter(|entry| matches(&file_pattern, &entry.file_name().to_string_lossy()))
+                .map(|entry| entry.path())
+                .collect()
+        })
+        .unwrap_or_default();
+    
+    paths.sort();
+    paths
+}
@@ -0,0 +1,288 @@
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+
+// The parser recurses once per level of nesting, so deep queries would exhaust the stack
+const MAX_DEPTH: usize = 32;
+// Far more than any dashboard needs, and small enough to parse in well under a millisecond
+const MAX_TOKENS: usize = 10_000;
+const MAX_FIELDS: usize = 1_000;
+
+/// A field of a selection set.
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub alias: Option<String>,
+    pub name: String,
+    pub arguments: Map<String, Value>,
+    pub selection: Vec<Field>,
+}
+
+impl Field {
+    /// The key the field's value is returned under.
+    pub fn key(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Variable(String),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Punct(char),
+    Spread,
+}
+
+/// Parses `query` and returns the root selection set of its operation, with
+/// `variables` substituted into arguments. This is just enough GraphQL to query
+/// the monitor's JSON: one query with nested selections, aliases, arguments and
+/// variables, but no fragments, directives, mutations or subscriptions.
+pub fn parse(query: &str, variables: &Map<String, Value>) -> Result<Vec<Field>> {
+    let mut parser = Parser {
+        tokens: tokenize(query)?,
+        position: 0,
+        depth: 0,
+        fields: 0,
+        variables,
+    };
+    match parser.peek() {
+        Some(Token::Name(keyword)) if keyword == "query" => {
+            parser.position += 1;
+            if let Some(Token::Name(_)) = parser.peek() {
+                parser.position += 1;
+            }
+            // Variable types are not checked, so the definitions are skipped
+            if parser.peek() == Some(&Token::Punct('(')) {
+                while parser.next()? != Token::Punct(')') {}
+            }
+        }
+        Some(Token::Name(keyword)) => return Err(anyhow!("Only queries are supported, not {}", keyword)),
+        _ => {}
+    }
+    let selection = parser.selection_set()?;
+    if parser.peek().is_some() {
+        return Err(anyhow!("Only one operation is supported"));
+    }
+    Ok(selection)
+}
+
+/// Picks the fields of `selection` out of `value`, through lists. There is no
+/// schema, so fields are looked up by name, and values selected without a
+/// sub-selection are returned whole.
+pub fn project(value: &Value, selection: &[Field]) -> Result<Value> {
+    if selection.is_empty() {
+        return Ok(value.clone());
+    }
+    match value {
+        Value::Array(items) => items.iter().map(|item| project(item, selection)).collect::<Result<_>>().map(Value::Array),
+        Value::Object(object) => {
+            let mut projected = Map::new();
+            for field in selection {
+                let value = object
+                    .get(&field.name)
+                    .ok_or_else(|| anyhow!("Unknown field '{}'", field.name))?;
+                projected.insert(field.key().to_string(), project(value, &field.selection)?);
+            }
+            Ok(Value::Object(projected))
+        }
+        Value::Null => Ok(Value::Null),
+        _ => Err(anyhow!("Fields selected on a value that has none")),
+    }
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if tokens.len() > MAX_TOKENS {
+            return Err(anyhow!("Query is longer than {} tokens", MAX_TOKENS));
+        }
+        match c {
+            c if c.is_whitespace() || c == ',' => {
+                chars.next();
+            }
+            '#' => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            '.' => {
+                let dots: String = std::iter::from_fn(|| chars.next_if_eq(&'.')).collect();
+                if dots != "..." {
+                    return Err(anyhow!("Unexpected '{}'", dots));
+                }
+                tokens.push(Token::Spread);
+            }
+            '"' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => text.push('\n'),
+                            Some('t') => text.push('\t'),
+                            Some(escaped) => text.push(escaped),
+                            None => return Err(anyhow!("Unterminated string")),
+                        },
+                        Some(c) => text.push(c),
+                        None => return Err(anyhow!("Unterminated string")),
+                    }
+                }
+                tokens.push(Token::Str(text));
+            }
+            '$' => {
+                chars.next();
+                tokens.push(Token::Variable(take_name(&mut chars)));
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                let number: String =
+                    std::iter::from_fn(|| chars.next_if(|c| c.is_ascii_digit() || "-+.eE".contains(*c))).collect();
+                match number.parse::<i64>() {
+                    Ok(int) => tokens.push(Token::Int(int)),
+                    Err(_) => tokens.push(Token::Float(number.parse().map_err(|_| anyhow!("Bad number '{}'", number))?)),
+                }
+            }
+            c if c == '_' || c.is_ascii_alphabetic() => tokens.push(Token::Name(take_name(&mut chars))),
+            '{' | '}' | '(' | ')' | '[' | ']' | ':' | '!' | '=' | '@' => {
+                chars.next();
+                tokens.push(Token::Punct(c));
+            }
+            _ => return Err(anyhow!("Unexpected '{}'", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+fn take_name(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    std::iter::from_fn(|| chars.next_if(|c| *c == '_' || c.is_ascii_alphanumeric())).collect()
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    /// Selection sets and argument lists or objects currently open.
+    depth: usize,
+    fields: usize,
+    variables: &'a Map<String, Value>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+    
+    fn next(&mut self) -> Result<Token> {
+        let token = self.tokens.get(self.position).cloned().ok_or_else(|| anyhow!("Unexpected end of query"))?;
+        self.position += 1;
+        Ok(token)
+    }
+    
+    fn expect(&mut self, punct: char) -> Result<()> {
+        match self.next()? {
+            Token::Punct(c) if c == punct => Ok(()),
+            token => Err(anyhow!("Expected '{}', found {:?}", punct, token)),
+        }
+    }
+    
+    fn name(&mut self) -> Result<String> {
+        match self.next()? {
+            Token::Name(name) => Ok(name),
+            token => Err(anyhow!("Expected a name, found {:?}", token)),
+        }
+    }
+    
+    fn enter(&mut self) -> Result<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(anyhow!("Query is nested deeper than {} levels", MAX_DEPTH));
+        }
+        Ok(())
+    }
+    
+    fn selection_set(&mut self) -> Result<Vec<Field>> {
+        self.expect('{')?;
+        self.enter()?;
+        let mut fields = Vec::new();
+        while self.peek() != Some(&Token::Punct('}')) {
+            match self.peek() {
+                Some(Token::Spread) => return Err(anyhow!("Fragments are not supported")),
+                Some(Token::Punct('@')) => return Err(anyhow!("Directives are not supported")),
+                _ => fields.push(self.field()?),
+            }
+        }
+        self.expect('}')?;
+        self.depth -= 1;
+        Ok(fields)
+    }
+    
+    fn field(&mut self) -> Result<Field> {
+        self.fields += 1;
+        if self.fields > MAX_FIELDS {
+            return Err(anyhow!("Query selects more than {} fields", MAX_FIELDS));
+        }
+        let mut name = self.name()?;
+        let mut alias = None;
+        if self.peek() == Some(&Token::Punct(':')) {
+            self.position += 1;
+            alias = Some(name);
+            name = self.name()?;
+        }
+        let mut arguments = Map::new();
+        if self.peek() == Some(&Token::Punct('(')) {
+            self.position += 1;
+            while self.peek() != Some(&Token::Punct(')')) {
+                let argument = self.name()?;
+                self.expect(':')?;
+                let value = self.value()?;
+                arguments.insert(argument, value);
+            }
+            self.expect(')')?;
+        }
+        let selection = if self.peek() == Some(&Token::Punct('{')) {
+            self.selection_set()?
+        } else {
+            Vec::new()
+        };
+        Ok(Field { alias, name, arguments, selection })
+    }
+    
+    fn value(&mut self) -> Result<Value> {
+        Ok(match self.next()? {
+            Token::Variable(name) => self.variables.get(&name).cloned().unwrap_or(Value::Null),
+            Token::Int(int) => Value::from(int),
+            Token::Float(float) => Value::from(float),
+            Token::Str(text) => Value::String(text),
+            Token::Name(name) => match name.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                "null" => Value::Null,
+                // Enum values
+                _ => Value::String(name),
+            },
+            Token::Punct('[') => {
+                self.enter()?;
+                let mut items = Vec::new();
+                while self.peek() != Some(&Token::Punct(']')) {
+                    items.push(self.value()?);
+                }
+                self.expect(']')?;
+                self.depth -= 1;
+                Value::Array(items)
+            }
+            Token::Punct('{') => {
+                self.enter()?;
+                let mut object = Map::new();
+                while self.peek() != Some(&Token::Punct('}')) {
+                    let key = self.name()?;
+                    self.expect(':')?;
+                    let value = self.value()?;
+                    object.insert(key, value);
+                }
+                self.expect('}')?;
+                self.depth -= 1;
+                Value::Object(object)
+            }
+            token => return Err(anyhow!("Unexpected {:?}", token)),
+        })
+    }
+}
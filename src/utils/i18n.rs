@@ -0,0 +1,401 @@
+use serde::{Deserialize, Serialize};
+
+/// Languages the web pages and alert messages can be shown in. Text is looked up
+/// by its English wording, so anything missing from a catalog stays in English.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    De,
+    Es,
+}
+
+impl Language {
+    pub const ALL: [Language; 3] = [Language::En, Language::De, Language::Es];
+    
+    /// The ISO 639-1 code, as used in config and `<html lang>`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::De => "de",
+            Language::Es => "es",
+        }
+    }
+    
+    /// The language's name in itself, for choosing it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::En => "English",
+            Language::De => "Deutsch",
+            Language::Es => "Español",
+        }
+    }
+    
+    pub fn from_code(code: &str) -> Option<Language> {
+        Language::ALL.into_iter().find(|language| language.code() == code)
+    }
+    
+    /// `text` in this language, or unchanged when it has no translation.
+    pub fn t<'a>(&self, text: &'a str) -> &'a str {
+        let catalog = match self {
+            Language::En => return text,
+            Language::De => GERMAN,
+            Language::Es => SPANISH,
+        };
+        catalog
+            .iter()
+            .find(|(english, _)| *english == text)
+            .map_or(text, |(_, translated)| translated)
+    }
+    
+    /// Translations of `texts` as a JSON object keyed by the English, for scripts
+    /// that build parts of a page in the browser.
+    pub fn strings(&self, texts: &[&str]) -> String {
+        let strings: serde_json::Map<String, serde_json::Value> = texts
+            .iter()
+            .map(|text| (text.to_string(), serde_json::Value::String(self.t(text).to_string())))
+            .collect();
+        serde_json::Value::Object(strings).to_string()
+    }
+    
+    /// An anomaly message for people: the kind before the first `: ` is translated,
+    /// while host names, values and the rest are kept as they are.
+    pub fn anomaly(&self, message: &str) -> String {
+        let translated = self.t(message);
+        if translated != message {
+            return translated.to_string();
+        }
+        match message.split_once(": ") {
+            Some((kind, rest)) => format!("{}: {}", self.t(kind), rest),
+            None => message.to_string(),
+        }
+    }
+}
+
+// (English, German)
+const GERMAN: &[(&str, &str)] = &[
+    // Navigation and login
+    ("Dashboard", "Übersicht"),
+    ("Reports", "Berichte"),
+    ("Downloads", "Downloads"),
+    ("Logs", "Protokolle"),
+    ("Settings", "Einstellungen"),
+    ("Logout", "Abmelden"),
+    ("Login", "Anmelden"),
+    ("Username", "Benutzername"),
+    ("Password", "Passwort"),
+    // Dashboard
+    ("Anomalies Detected!", "Auffälligkeiten erkannt!"),
+    ("All Normal", "Alles in Ordnung"),
+    ("Learning...", "Lernphase..."),
+    ("Anomalies", "Auffälligkeiten"),
+    ("System Status", "Systemstatus"),
+    ("Hosts", "Hosts"),
+    ("Metrics History", "Messwertverlauf"),
+    ("Live", "Live"),
+    ("CPU %", "CPU %"),
+    ("RAM %", "RAM %"),
+    ("Disk %", "Festplatte %"),
+    ("Temp °C", "Temperatur °C"),
+    ("Ping ms", "Ping ms"),
+    ("Connections", "Verbindungen"),
+    ("Failed Logins", "Fehlgeschlagene Anmeldungen"),
+    ("GPU %", "GPU %"),
+    ("VRAM %", "Grafikspeicher %"),
+    // Downloads
+    ("Support Bundle", "Support-Paket"),
+    (
+        "Recent logs, metrics, anomaly history and the configuration with secrets removed, in one zip archive.",
+        "Aktuelle Protokolle, Messwerte, Auffälligkeiten und die Konfiguration ohne Geheimnisse in einem Zip-Archiv.",
+    ),
+    ("Collect Support Bundle", "Support-Paket erstellen"),
+    ("Download Files", "Dateien herunterladen"),
+    ("Filename", "Dateiname"),
+    ("Size", "Größe"),
+    ("Modified", "Geändert"),
+    ("Action", "Aktion"),
+    ("Open", "Öffnen"),
+    ("Download", "Herunterladen"),
+    ("Download Selected as Zip", "Auswahl als Zip herunterladen"),
+    // Logs
+    ("No log files yet.", "Noch keine Protokolldateien."),
+    ("Search", "Suchen"),
+    ("All levels", "Alle Stufen"),
+    ("Info and above", "Info und höher"),
+    ("Warnings and errors", "Warnungen und Fehler"),
+    ("Errors only", "Nur Fehler"),
+    ("Follow", "Mitverfolgen"),
+    ("Older", "Älter"),
+    ("Newer", "Neuer"),
+    // Reports
+    ("Availability", "Verfügbarkeit"),
+    ("Day", "Tag"),
+    ("Week", "Woche"),
+    ("Month", "Monat"),
+    ("Check", "Prüfung"),
+    ("Uptime", "Verfügbar"),
+    ("Downtime", "Ausfallzeit"),
+    ("Outages", "Ausfälle"),
+    ("MTTR", "MTTR"),
+    ("Down", "Ausgefallen"),
+    ("Recovered", "Wiederhergestellt"),
+    ("Duration", "Dauer"),
+    ("Incidents", "Vorfälle"),
+    ("Incident", "Vorfall"),
+    ("Status", "Status"),
+    ("Severity", "Schweregrad"),
+    ("Opened", "Eröffnet"),
+    ("Assignee", "Zuständig"),
+    ("Probable cause", "Wahrscheinliche Ursache"),
+    ("Time", "Zeit"),
+    ("Kind", "Art"),
+    ("Entry", "Eintrag"),
+    ("By", "Von"),
+    ("Security: SUID/SGID Binaries", "Sicherheit: SUID/SGID-Programme"),
+    ("Last scan", "Letzte Prüfung"),
+    ("never", "nie"),
+    ("Path", "Pfad"),
+    ("Mode", "Rechte"),
+    // Settings
+    ("Change Password", "Passwort ändern"),
+    ("Current Password", "Aktuelles Passwort"),
+    ("New Password", "Neues Passwort"),
+    ("Confirm Password", "Passwort bestätigen"),
+    ("Update Password", "Passwort aktualisieren"),
+    ("Display Settings", "Anzeige"),
+    (
+        "Stats charted for users who have not arranged their own dashboard.",
+        "Werte, die für Benutzer ohne eigene Übersicht angezeigt werden.",
+    ),
+    ("Dashboard refresh (seconds)", "Aktualisierung der Übersicht (Sekunden)"),
+    ("Save Settings", "Einstellungen speichern"),
+    ("Dashboard Layout", "Aufbau der Übersicht"),
+    ("Panel", "Bereich"),
+    ("Width", "Breite"),
+    ("Title", "Titel"),
+    ("Shows", "Zeigt"),
+    ("Chart", "Diagramm"),
+    ("Add Panel", "Bereich hinzufügen"),
+    ("Save Layout", "Aufbau speichern"),
+    ("Reset to Default", "Auf Standard zurücksetzen"),
+    ("Phone Notifications", "Benachrichtigungen aufs Telefon"),
+    (
+        "Get Critical alerts on this device as push notifications, even with no other alert channel set up. Needs HTTPS, or localhost; on iPhone, add this page to the home screen first.",
+        "Kritische Alarme als Push-Benachrichtigung auf diesem Gerät erhalten, auch ohne anderen Alarmkanal. Erfordert HTTPS oder localhost; auf dem iPhone die Seite zuerst zum Home-Bildschirm hinzufügen.",
+    ),
+    ("Enable on This Device", "Auf diesem Gerät aktivieren"),
+    ("Disable on This Device", "Auf diesem Gerät deaktivieren"),
+    ("Send Test", "Test senden"),
+    ("Send me push notifications", "Push-Benachrichtigungen an mich senden"),
+    ("For alerts at least as severe as", "Für Alarme ab dem Schweregrad"),
+    ("Only about these host groups (none for all):", "Nur zu diesen Hostgruppen (keine für alle):"),
+    ("Save Notifications", "Benachrichtigungen speichern"),
+    ("Language", "Sprache"),
+    ("Server default", "Serverstandard"),
+    ("Save Language", "Sprache speichern"),
+    // Alerts
+    ("Critical", "Kritisch"),
+    ("Warning", "Warnung"),
+    ("Device Down Detected!", "Gerät nicht erreichbar!"),
+    ("Test notification", "Testbenachrichtigung"),
+    ("Critical alerts will arrive like this.", "Kritische Alarme kommen so an."),
+    ("Held during quiet hours", "Während der Ruhezeit zurückgehalten"),
+    // Kinds of anomaly, as they start anomaly messages
+    ("Anomaly", "Auffälligkeit"),
+    ("Device Down", "Gerät nicht erreichbar"),
+    ("Threat IP", "Bedrohliche IP"),
+    ("Blocklisted", "Auf Sperrliste"),
+    ("Account Changed", "Konto geändert"),
+    ("New Account", "Neues Konto"),
+    ("Root Account Added", "Root-Konto hinzugefügt"),
+    ("Privileged Group Changed", "Privilegierte Gruppe geändert"),
+    ("Persistence Changed", "Autostart geändert"),
+    ("New Persistence", "Neuer Autostart"),
+    ("SUID Binary Changed", "SUID-Programm geändert"),
+    ("New SUID Binary", "Neues SUID-Programm"),
+    ("New Listening Port", "Neuer offener Port"),
+    ("File Changed", "Datei geändert"),
+    ("Public IP Changed", "Öffentliche IP geändert"),
+    ("DNS Mismatch", "DNS-Abweichung"),
+    ("OOM Kill", "Prozess wegen Speichermangel beendet"),
+    ("Heartbeat Missed", "Heartbeat ausgeblieben"),
+    ("Heartbeat Failed", "Heartbeat fehlgeschlagen"),
+    ("Database Down", "Datenbank nicht erreichbar"),
+    ("Database Replication Stopped", "Datenbankreplikation gestoppt"),
+    ("Cache Down", "Cache nicht erreichbar"),
+    ("Backup Missing", "Sicherung fehlt"),
+    ("Backup Too Small", "Sicherung zu klein"),
+    ("Pi-hole Down", "Pi-hole nicht erreichbar"),
+    ("Pi-hole Blocking Disabled", "Pi-hole-Filter deaktiviert"),
+    ("Proxmox Node Offline", "Proxmox-Knoten offline"),
+    ("Proxmox Guest Stopped", "Proxmox-Gast gestoppt"),
+    ("Node Not Ready", "Knoten nicht bereit"),
+    ("Node Pressure", "Knoten unter Last"),
+    ("Pod Pending", "Pod wartet"),
+    ("Pod Restarted", "Pod neu gestartet"),
+];
+
+// (English, Spanish)
+const SPANISH: &[(&str, &str)] = &[
+    // Navigation and login
+    ("Dashboard", "Panel"),
+    ("Reports", "Informes"),
+    ("Downloads", "Descargas"),
+    ("Logs", "Registros"),
+    ("Settings", "Ajustes"),
+    ("Logout", "Cerrar sesión"),
+    ("Login", "Iniciar sesión"),
+    ("Username", "Usuario"),
+    ("Password", "Contraseña"),
+    // Dashboard
+    ("Anomalies Detected!", "¡Anomalías detectadas!"),
+    ("All Normal", "Todo normal"),
+    ("Learning...", "Aprendiendo..."),
+    ("Anomalies", "Anomalías"),
+    ("System Status", "Estado del sistema"),
+    ("Hosts", "Equipos"),
+    ("Metrics History", "Historial de métricas"),
+    ("Live", "En vivo"),
+    ("CPU %", "CPU %"),
+    ("RAM %", "RAM %"),
+    ("Disk %", "Disco %"),
+    ("Temp °C", "Temp. °C"),
+    ("Ping ms", "Ping ms"),
+    ("Connections", "Conexiones"),
+    ("Failed Logins", "Inicios de sesión fallidos"),
+    ("GPU %", "GPU %"),
+    ("VRAM %", "VRAM %"),
+    // Downloads
+    ("Support Bundle", "Paquete de soporte"),
+    (
+        "Recent logs, metrics, anomaly history and the configuration with secrets removed, in one zip archive.",
+        "Registros recientes, métricas, historial de anomalías y la configuración sin secretos, en un archivo zip.",
+    ),
+    ("Collect Support Bundle", "Crear paquete de soporte"),
+    ("Download Files", "Descargar archivos"),
+    ("Filename", "Nombre"),
+    ("Size", "Tamaño"),
+    ("Modified", "Modificado"),
+    ("Action", "Acción"),
+    ("Open", "Abrir"),
+    ("Download", "Descargar"),
+    ("Download Selected as Zip", "Descargar selección como zip"),
+    // Logs
+    ("No log files yet.", "Todavía no hay archivos de registro."),
+    ("Search", "Buscar"),
+    ("All levels", "Todos los niveles"),
+    ("Info and above", "Info o superior"),
+    ("Warnings and errors", "Avisos y errores"),
+    ("Errors only", "Solo errores"),
+    ("Follow", "Seguir"),
+    ("Older", "Anteriores"),
+    ("Newer", "Posteriores"),
+    // Reports
+    ("Availability", "Disponibilidad"),
+    ("Day", "Día"),
+    ("Week", "Semana"),
+    ("Month", "Mes"),
+    ("Check", "Comprobación"),
+    ("Uptime", "Disponible"),
+    ("Downtime", "Inactividad"),
+    ("Outages", "Caídas"),
+    ("MTTR", "MTTR"),
+    ("Down", "Caído"),
+    ("Recovered", "Recuperado"),
+    ("Duration", "Duración"),
+    ("Incidents", "Incidentes"),
+    ("Incident", "Incidente"),
+    ("Status", "Estado"),
+    ("Severity", "Gravedad"),
+    ("Opened", "Abierto"),
+    ("Assignee", "Responsable"),
+    ("Probable cause", "Causa probable"),
+    ("Time", "Hora"),
+    ("Kind", "Tipo"),
+    ("Entry", "Entrada"),
+    ("By", "Por"),
+    ("Security: SUID/SGID Binaries", "Seguridad: binarios SUID/SGID"),
+    ("Last scan", "Último análisis"),
+    ("never", "nunca"),
+    ("Path", "Ruta"),
+    ("Mode", "Permisos"),
+    // Settings
+    ("Change Password", "Cambiar contraseña"),
+    ("Current Password", "Contraseña actual"),
+    ("New Password", "Nueva contraseña"),
+    ("Confirm Password", "Confirmar contraseña"),
+    ("Update Password", "Actualizar contraseña"),
+    ("Display Settings", "Visualización"),
+    (
+        "Stats charted for users who have not arranged their own dashboard.",
+        "Valores mostrados a los usuarios que no han organizado su propio panel.",
+    ),
+    ("Dashboard refresh (seconds)", "Actualización del panel (segundos)"),
+    ("Save Settings", "Guardar ajustes"),
+    ("Dashboard Layout", "Diseño del panel"),
+    ("Panel", "Sección"),
+    ("Width", "Ancho"),
+    ("Title", "Título"),
+    ("Shows", "Muestra"),
+    ("Chart", "Gráfico"),
+    ("Add Panel", "Añadir sección"),
+    ("Save Layout", "Guardar diseño"),
+    ("Reset to Default", "Restablecer"),
+    ("Phone Notifications", "Notificaciones en el móvil"),
+    (
+        "Get Critical alerts on this device as push notifications, even with no other alert channel set up. Needs HTTPS, or localhost; on iPhone, add this page to the home screen first.",
+        "Recibe las alertas críticas en este dispositivo como notificaciones push, aunque no haya otro canal de alertas. Requiere HTTPS o localhost; en iPhone, añade antes esta página a la pantalla de inicio.",
+    ),
+    ("Enable on This Device", "Activar en este dispositivo"),
+    ("Disable on This Device", "Desactivar en este dispositivo"),
+    ("Send Test", "Enviar prueba"),
+    ("Send me push notifications", "Enviarme notificaciones push"),
+    ("For alerts at least as severe as", "Para alertas con gravedad de al menos"),
+    ("Only about these host groups (none for all):", "Solo de estos grupos de hosts (ninguno para todos):"),
+    ("Save Notifications", "Guardar notificaciones"),
+    ("Language", "Idioma"),
+    ("Server default", "Predeterminado del servidor"),
+    ("Save Language", "Guardar idioma"),
+    // Alerts
+    ("Critical", "Crítico"),
+    ("Warning", "Advertencia"),
+    ("Info", "Información"),
+    ("Device Down Detected!", "¡Dispositivo caído!"),
+    ("Test notification", "Notificación de prueba"),
+    ("Critical alerts will arrive like this.", "Las alertas críticas llegarán así."),
+    ("Held during quiet hours", "Retenido durante las horas de silencio"),
+    // Kinds of anomaly, as they start anomaly messages
+    ("Anomaly", "Anomalía"),
+    ("Device Down", "Dispositivo caído"),
+    ("Threat IP", "IP peligrosa"),
+    ("Blocklisted", "En lista de bloqueo"),
+    ("Account Changed", "Cuenta modificada"),
+    ("New Account", "Cuenta nueva"),
+    ("Root Account Added", "Cuenta root añadida"),
+    ("Privileged Group Changed", "Grupo privilegiado modificado"),
+    ("Persistence Changed", "Arranque automático modificado"),
+    ("New Persistence", "Nuevo arranque automático"),
+    ("SUID Binary Changed", "Binario SUID modificado"),
+    ("New SUID Binary", "Nuevo binario SUID"),
+    ("New Listening Port", "Nuevo puerto abierto"),
+    ("File Changed", "Archivo modificado"),
+    ("Public IP Changed", "IP pública cambiada"),
+    ("DNS Mismatch", "Discrepancia DNS"),
+    ("OOM Kill", "Proceso terminado por falta de memoria"),
+    ("Heartbeat Missed", "Latido ausente"),
+    ("Heartbeat Failed", "Latido fallido"),
+    ("Database Down", "Base de datos caída"),
+    ("Database Replication Stopped", "Replicación de base de datos detenida"),
+    ("Cache Down", "Caché caída"),
+    ("Backup Missing", "Copia de seguridad ausente"),
+    ("Backup Too Small", "Copia de seguridad demasiado pequeña"),
+    ("Pi-hole Down", "Pi-hole caído"),
+    ("Pi-hole Blocking Disabled", "Bloqueo de Pi-hole desactivado"),
+    ("Proxmox Node Offline", "Nodo Proxmox desconectado"),
+    ("Proxmox Guest Stopped", "Invitado Proxmox detenido"),
+    ("Node Not Ready", "Nodo no preparado"),
+    ("Node Pressure", "Nodo bajo presión"),
+    ("Pod Pending", "Pod pendiente"),
+    ("Pod Restarted", "Pod reiniciado"),
+];
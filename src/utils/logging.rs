@@ -1,35 +1,191 @@
+use crate::models::config::{LogTarget, LoggingConfig};
 use anyhow::Result;
-use tracing::{info, Level};
-use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use std::io::{self, Write};
+use std::os::unix::net::UnixDatagram;
+use std::sync::Arc;
+use tracing::{info, warn, Level, Metadata};
+use tracing_subscriber::{fmt, fmt::MakeWriter, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
 
-pub fn init_logging() -> Result<()> {
+const SYSLOG_IDENTIFIER: &str = "shaydz-monitor";
+const SYSLOG_SOCKETS: [&str; 2] = ["/dev/log", "/var/run/syslog"];
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+// LOG_DAEMON facility, shifted as required by the PRI field
+const SYSLOG_FACILITY: u8 = 3 << 3;
+
+// console-subscriber reads task instrumentation that tokio only emits when built this way
+#[cfg(all(feature = "tokio-console", not(tokio_unstable)))]
+compile_error!("the tokio-console feature needs RUSTFLAGS=\"--cfg tokio_unstable\"");
+
+pub fn init_logging(config: &LoggingConfig) -> Result<()> {
     // Create logs directory
     std::fs::create_dir_all("logs")?;
     
+    // Connect to the requested system logger, falling back to stdout if it is unavailable
+    let (socket_writer, connect_error) = match config.target {
+        LogTarget::Stdout => (None, None),
+        target => match SocketWriter::connect(target) {
+            Ok(writer) => (Some(writer), None),
+            Err(e) => (None, Some(e)),
+        },
+    };
+    
+    let stdout_layer = socket_writer.is_none().then(|| {
+        fmt::layer()
+            .with_writer(std::io::stdout)
+            .with_ansi(true)
+    });
+    
+    // Syslog and journald add their own timestamps and carry the level as a priority
+    let socket_layer = socket_writer.map(|writer| {
+        fmt::layer()
+            .with_writer(writer)
+            .with_ansi(false)
+            .without_time()
+            .with_level(false)
+    });
+    
+    let file_layer = fmt::layer()
+        .with_writer(|| std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open("logs/app.log")
+            .unwrap_or_else(|_| std::fs::File::create("/dev/null").unwrap()))
+        .with_ansi(false)
+        .json();
+    
+    // The filter applies to the logs only, so tokio-console still sees the runtime's trace events
+    let logs = file_layer.and_then(stdout_layer).and_then(socket_layer).with_filter(
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info,tower_http=warn,hyper=warn")),
+    );
+    #[cfg(feature = "tokio-console")]
+    let console = Some(console_subscriber::spawn());
+    #[cfg(not(feature = "tokio-console"))]
+    let console: Option<tracing_subscriber::layer::Identity> = None;
+    
     // Build the subscriber
-    let subscriber = tracing_subscriber::registry()
-        .with(
-            EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| EnvFilter::new("info,tower_http=warn,hyper=warn")),
-        )
-        .with(
-            fmt::layer()
-                .with_writer(std::io::stdout)
-                .with_ansi(true),
-        )
-        .with(
-            fmt::layer()
-                .with_writer(|| std::fs::OpenOptions::new()
-                    .create(true)
-                    .append(true)
-                    .open("logs/app.log")
-                    .unwrap_or_else(|_| std::fs::File::create("/dev/null").unwrap()))
-                .with_ansi(false)
-                .json(),
-        );
+    let subscriber = tracing_subscriber::registry().with(console).with(logs);
     
     subscriber.init();
     
+    if let Some(e) = connect_error {
+        warn!("Could not connect to {:?} logger, using stdout: {}", config.target, e);
+    }
+    
     info!("Logging initialized");
     Ok(())
 }
+
+/// Sends each formatted log event as one datagram to syslog or journald.
+#[derive(Clone)]
+struct SocketWriter {
+    socket: Arc<UnixDatagram>,
+    target: LogTarget,
+}
+
+impl SocketWriter {
+    fn connect(target: LogTarget) -> io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        
+        match target {
+            LogTarget::Journald => socket.connect(JOURNALD_SOCKET)?,
+            _ => {
+                let mut last_error = io::Error::new(io::ErrorKind::NotFound, "no syslog socket found");
+                let connected = SYSLOG_SOCKETS.iter().any(|path| match socket.connect(path) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        last_error = e;
+                        false
+                    }
+                });
+                if !connected {
+                    return Err(last_error);
+                }
+            }
+        }
+        
+        Ok(Self {
+            socket: Arc::new(socket),
+            target,
+        })
+    }
+    
+    fn event_writer(&self, level: Level) -> SocketEventWriter {
+        SocketEventWriter {
+            socket: Arc::clone(&self.socket),
+            target: self.target,
+            level,
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for SocketWriter {
+    type Writer = SocketEventWriter;
+    
+    fn make_writer(&'a self) -> Self::Writer {
+        self.event_writer(Level::INFO)
+    }
+    
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        self.event_writer(*meta.level())
+    }
+}
+
+struct SocketEventWriter {
+    socket: Arc<UnixDatagram>,
+    target: LogTarget,
+    level: Level,
+}
+
+impl Write for SocketEventWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let message = String::from_utf8_lossy(buf);
+        let message = message.trim_end();
+        let priority = syslog_priority(self.level);
+        
+        let datagram = match self.target {
+            LogTarget::Journald => journald_datagram(priority, message),
+            _ => format!(
+                "<{}>{}[{}]: {}",
+                SYSLOG_FACILITY | priority,
+                SYSLOG_IDENTIFIER,
+                std::process::id(),
+                message
+            )
+            .into_bytes(),
+        };
+        
+        // Dropping a line is better than failing the caller if the logger is restarting
+        let _ = self.socket.send(&datagram);
+        Ok(buf.len())
+    }
+    
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn syslog_priority(level: Level) -> u8 {
+    match level {
+        Level::ERROR => 3,
+        Level::WARN => 4,
+        Level::INFO => 6,
+        _ => 7,
+    }
+}
+
+/// Encodes an entry in the journald native protocol. Values containing newlines
+/// must use the length-prefixed binary form.
+fn journald_datagram(priority: u8, message: &str) -> Vec<u8> {
+    let mut datagram = format!("PRIORITY={}\nSYSLOG_IDENTIFIER={}\n", priority, SYSLOG_IDENTIFIER).into_bytes();
+    
+    if message.contains('\n') {
+        datagram.extend_from_slice(b"MESSAGE\n");
+        datagram.extend_from_slice(&(message.len() as u64).to_le_bytes());
+        datagram.extend_from_slice(message.as_bytes());
+        datagram.push(b'\n');
+    } else {
+        datagram.extend_from_slice(format!("MESSAGE={}\n", message).as_bytes());
+    }
+    
+    datagram
+}
@@ -1 +1,11 @@
+pub mod assets;
+pub mod cidr;
+pub mod data_dir;
+pub mod glob;
+pub mod graphql;
+pub mod i18n;
 pub mod logging;
+pub mod process;
+pub mod systemd;
+pub mod websocket;
+pub mod zip;
@@ -0,0 +1,38 @@
+use anyhow::{anyhow, Result};
+use std::path::Path;
+use std::process::{Output, Stdio};
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Runs `command` to completion, killing it when it has not finished within
+/// `timeout`, so a hung child cannot hold up the cycle that waits for it.
+pub async fn output_within(command: &mut Command, timeout: Duration) -> Result<Output> {
+    let program = command.as_std().get_program().to_string_lossy().into_owned();
+    let output = command.stdin(Stdio::null()).kill_on_drop(true).output();
+    tokio::time::timeout(timeout, output)
+        .await
+        .map_err(|_| anyhow!("{} did not finish within {}s", program, timeout.as_secs_f64()))?
+        .map_err(|e| anyhow!("{}: {}", program, e))
+}
+
+/// Reads a file, giving up after `timeout`; files under `/sys` and on network
+/// mounts can block indefinitely. The read runs on a thread of its own, because a
+/// read stuck in the kernel cannot be cancelled: on timeout that thread is left
+/// behind until the read returns, rather than taking a slot of tokio's blocking
+/// pool or holding up shutdown.
+pub async fn read_within(path: impl AsRef<Path>, timeout: Duration) -> Result<String> {
+    let path = path.as_ref().to_path_buf();
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    let reading = path.clone();
+    std::thread::Builder::new()
+        .name("read_within".to_string())
+        .spawn(move || {
+            let _ = sender.send(std::fs::read_to_string(&reading));
+        })?;
+    
+    tokio::time::timeout(timeout, receiver)
+        .await
+        .map_err(|_| anyhow!("reading {} did not finish within {}s", path.display(), timeout.as_secs_f64()))?
+        .map_err(|_| anyhow!("reading {} was abandoned", path.display()))?
+        .map_err(|e| anyhow!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,46 @@
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::time::Duration;
+
+/// Sends a state update such as `READY=1` to systemd. Returns `Ok(false)` when the
+/// process was not started by systemd with `Type=notify`.
+pub fn notify(state: &str) -> io::Result<bool> {
+    let Some(path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return Ok(false);
+    };
+    let path = path.to_string_lossy().to_string();
+    
+    let socket = UnixDatagram::unbound()?;
+    
+    // A leading '@' denotes a socket in the Linux abstract namespace
+    if let Some(name) = path.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())?;
+            socket.send_to_addr(state.as_bytes(), &addr)?;
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = name;
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "abstract sockets require Linux"));
+        }
+    } else {
+        socket.send_to(state.as_bytes(), &path)?;
+    }
+    
+    Ok(true)
+}
+
+/// The watchdog timeout configured with `WatchdogSec=`, if it applies to this process.
+pub fn watchdog_timeout() -> Option<Duration> {
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    
+    if let Ok(pid) = std::env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok()? != std::process::id() {
+            return None;
+        }
+    }
+    
+    (usec > 0).then(|| Duration::from_micros(usec))
+}
@@ -0,0 +1,224 @@
+use anyhow::{anyhow, bail, Result};
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::Response;
+use base64::Engine;
+use hyper::upgrade::Upgraded;
+use hyper_util::rt::TokioIo;
+use std::future::Future;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::warn;
+
+// Appended to the client's key before hashing, from RFC 6455
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+// Clients only send control frames and small messages to these endpoints
+const MAX_PAYLOAD: usize = 64 * 1024;
+const READ_CHUNK: usize = 4096;
+
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+/// Answers a WebSocket handshake with `101 Switching Protocols` and runs `serve`
+/// on the connection once it is upgraded, or returns `None` when `request` is
+/// not a WebSocket handshake.
+pub fn upgrade<F, Fut>(mut request: Request, serve: F) -> Option<Response>
+where
+    F: FnOnce(WebSocket) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    let accept = accept_key(handshake_key(request.headers())?);
+    let on_upgrade = hyper::upgrade::on(&mut request);
+    
+    tokio::spawn(async move {
+        match on_upgrade.await {
+            Ok(upgraded) => serve(WebSocket::new(upgraded)).await,
+            Err(e) => warn!("WebSocket upgrade failed: {}", e),
+        }
+    });
+    
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+    let headers = response.headers_mut();
+    headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+    headers.insert(header::CONNECTION, HeaderValue::from_static("upgrade"));
+    headers.insert(header::SEC_WEBSOCKET_ACCEPT, HeaderValue::from_str(&accept).ok()?);
+    Some(response)
+}
+
+/// The client's `Sec-WebSocket-Key`, when the headers ask for a version 13 upgrade.
+fn handshake_key(headers: &HeaderMap) -> Option<&str> {
+    let has_token = |name: header::HeaderName, token: &str| {
+        headers
+            .get_all(name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|value| value.trim().eq_ignore_ascii_case(token))
+    };
+    if !has_token(header::UPGRADE, "websocket") || !has_token(header::CONNECTION, "upgrade") {
+        return None;
+    }
+    if headers.get(header::SEC_WEBSOCKET_VERSION)?.as_bytes() != b"13" {
+        return None;
+    }
+    headers.get(header::SEC_WEBSOCKET_KEY)?.to_str().ok()
+}
+
+/// Whether a browser opened the connection from another site. Browsers send
+/// cookies with cross-site WebSocket handshakes, so these must be refused.
+pub fn is_cross_origin(headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN).and_then(|value| value.to_str().ok()) else {
+        return false;
+    };
+    let origin_host = origin.split_once("://").map_or(origin, |(_, host)| host);
+    headers.get(header::HOST).and_then(|value| value.to_str().ok()) != Some(origin_host)
+}
+
+fn accept_key(key: &str) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA1_FOR_LEGACY_USE_ONLY, format!("{}{}", key, ACCEPT_GUID).as_bytes());
+    base64::engine::general_purpose::STANDARD.encode(digest.as_ref())
+}
+
+/// The server end of a WebSocket connection, written by hand because axum's `ws`
+/// feature needs tokio-tungstenite, which is not among the crates this builds with.
+/// Pings are answered and the closing handshake is completed by `recv`, so a
+/// connection only needs to be read for those to happen.
+pub struct WebSocket {
+    io: TokioIo<Upgraded>,
+    /// Bytes read but not yet parsed into a frame.
+    buffer: Vec<u8>,
+    /// Encoded frames not yet written: replies to control frames wait here until
+    /// `recv` or a send writes them, so a cancelled write never leaves half a frame.
+    outgoing: Vec<u8>,
+    closed: bool,
+}
+
+impl WebSocket {
+    fn new(upgraded: Upgraded) -> Self {
+        Self {
+            io: TokioIo::new(upgraded),
+            buffer: Vec::new(),
+            outgoing: Vec::new(),
+            closed: false,
+        }
+    }
+    
+    pub async fn send_text(&mut self, text: &str) -> Result<()> {
+        self.send_frame(OPCODE_TEXT, text.as_bytes()).await
+    }
+    
+    /// The payload of the next text or binary frame from the client, or `None`
+    /// once the connection is closed. Fragments of a long message are returned
+    /// one frame at a time. Bytes read are kept until their frame is complete and
+    /// replies are queued before they are written, so this can be raced against
+    /// other work in a `select!`.
+    pub async fn recv(&mut self) -> Result<Option<Vec<u8>>> {
+        loop {
+            self.write_outgoing().await?;
+            if self.closed {
+                return Ok(None);
+            }
+            let Some((opcode, payload)) = self.parse_frame()? else {
+                let mut chunk = [0u8; READ_CHUNK];
+                let read = self.io.read(&mut chunk).await?;
+                if read == 0 {
+                    self.closed = true;
+                    return Ok(None);
+                }
+                self.buffer.extend_from_slice(&chunk[..read]);
+                continue;
+            };
+            
+            match opcode {
+                OPCODE_PING => self.queue_frame(OPCODE_PONG, &payload),
+                OPCODE_PONG => {}
+                OPCODE_CLOSE => {
+                    // Echo the status code back, as the closing handshake expects
+                    self.queue_frame(OPCODE_CLOSE, &payload[..payload.len().min(2)]);
+                    self.closed = true;
+                    let _ = self.write_outgoing().await;
+                    return Ok(None);
+                }
+                _ => return Ok(Some(payload)),
+            }
+        }
+    }
+    
+    /// Takes one whole frame off the buffer, unmasked, or `None` when more bytes
+    /// are needed.
+    fn parse_frame(&mut self) -> Result<Option<(u8, Vec<u8>)>> {
+        let buffer = &self.buffer;
+        if buffer.len() < 2 {
+            return Ok(None);
+        }
+        let opcode = buffer[0] & 0x0F;
+        if buffer[1] & 0x80 == 0 {
+            bail!("client frame is not masked");
+        }
+        
+        let (length, mut offset) = match buffer[1] & 0x7F {
+            126 if buffer.len() >= 4 => (u16::from_be_bytes([buffer[2], buffer[3]]) as usize, 4),
+            127 if buffer.len() >= 10 => {
+                let length = u64::from_be_bytes(buffer[2..10].try_into()?);
+                (usize::try_from(length).unwrap_or(usize::MAX), 10)
+            }
+            126 | 127 => return Ok(None),
+            length => (length as usize, 2),
+        };
+        if length > MAX_PAYLOAD {
+            return Err(anyhow!("client frame of {} bytes is too large", length));
+        }
+        if buffer.len() < offset + 4 + length {
+            return Ok(None);
+        }
+        
+        let mask = [buffer[offset], buffer[offset + 1], buffer[offset + 2], buffer[offset + 3]];
+        offset += 4;
+        let payload = buffer[offset..offset + length]
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| byte ^ mask[i % 4])
+            .collect();
+        self.buffer.drain(..offset + length);
+        Ok(Some((opcode, payload)))
+    }
+    
+    async fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> Result<()> {
+        self.queue_frame(opcode, payload);
+        self.write_outgoing().await
+    }
+    
+    fn queue_frame(&mut self, opcode: u8, payload: &[u8]) {
+        let frame = &mut self.outgoing;
+        frame.push(0x80 | opcode);
+        match payload.len() {
+            length if length < 126 => frame.push(length as u8),
+            length if length <= u16::MAX as usize => {
+                frame.push(126);
+                frame.extend_from_slice(&(length as u16).to_be_bytes());
+            }
+            length => {
+                frame.push(127);
+                frame.extend_from_slice(&(length as u64).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(payload);
+    }
+    
+    /// Writes the queued frames. Each write either happens or not, and what was
+    /// written is removed at once, so dropping this part way loses nothing.
+    async fn write_outgoing(&mut self) -> Result<()> {
+        while !self.outgoing.is_empty() {
+            let written = self.io.write(&self.outgoing).await?;
+            if written == 0 {
+                bail!("connection closed while writing");
+            }
+            self.outgoing.drain(..written);
+        }
+        self.io.flush().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,286 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Local, Timelike};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream};
+use tracing::warn;
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR: u32 = 0x0807_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+const VERSION: u16 = 20;
+// Sizes follow the data in a descriptor (bit 3) and names are UTF-8 (bit 11)
+const FLAGS: u16 = 0x0808;
+const STORED: u16 = 0;
+const DEFLATE: u16 = 8;
+const CHUNK_SIZE: usize = 64 * 1024;
+
+struct CentralEntry {
+    name: String,
+    time: u16,
+    date: u16,
+    crc: u32,
+    compressed: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Writes a deflated zip archive to `out` one entry at a time, without holding
+/// whole files in memory. Archives are limited to 4 GiB and 65535 entries, since
+/// ZIP64 is not written.
+pub struct ZipWriter<W> {
+    out: W,
+    offset: u64,
+    entries: Vec<CentralEntry>,
+}
+
+impl<W: AsyncWrite + Unpin> ZipWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            offset: 0,
+            entries: Vec::new(),
+        }
+    }
+    
+    /// Adds `data` as `name`, stamped with the current time.
+    pub async fn add_bytes(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        self.add_reader(name, data, Local::now()).await
+    }
+    
+    /// Streams the file at `path` into the archive as `name`.
+    pub async fn add_file(&mut self, name: &str, path: &Path) -> Result<()> {
+        let file = tokio::fs::File::open(path).await?;
+        let modified = file.metadata().await?.modified().map(DateTime::<Local>::from).unwrap_or_else(|_| Local::now());
+        self.add_reader(name, file, modified).await
+    }
+    
+    async fn add_reader(&mut self, name: &str, mut reader: impl AsyncRead + Unpin, modified: DateTime<Local>) -> Result<()> {
+        if self.entries.len() >= u16::MAX as usize {
+            return Err(anyhow!("Too many files for one archive"));
+        }
+        let offset = fits(self.offset)?;
+        let (time, date) = dos_timestamp(modified);
+        
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend(LOCAL_HEADER.to_le_bytes());
+        for field in [VERSION, FLAGS, DEFLATE, time, date] {
+            header.extend(field.to_le_bytes());
+        }
+        // CRC and sizes are left zero here and given in the data descriptor
+        header.extend([0u8; 12]);
+        header.extend((name.len() as u16).to_le_bytes());
+        header.extend(0u16.to_le_bytes());
+        header.extend(name.as_bytes());
+        self.write(&header).await?;
+        
+        let mut crc = crc32fast::Hasher::new();
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        let mut buffer = vec![0u8; CHUNK_SIZE];
+        let mut size = 0u64;
+        let mut compressed = 0u64;
+        loop {
+            let read = reader.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            crc.update(&buffer[..read]);
+            encoder.write_all(&buffer[..read])?;
+            size += read as u64;
+            
+            let deflated = std::mem::take(encoder.get_mut());
+            compressed += deflated.len() as u64;
+            self.write(&deflated).await?;
+        }
+        let deflated = encoder.finish()?;
+        compressed += deflated.len() as u64;
+        self.write(&deflated).await?;
+        
+        let entry = CentralEntry {
+            name: name.to_string(),
+            time,
+            date,
+            crc: crc.finalize(),
+            compressed: fits(compressed)?,
+            size: fits(size)?,
+            offset,
+        };
+        let mut descriptor = Vec::with_capacity(16);
+        for field in [DATA_DESCRIPTOR, entry.crc, entry.compressed, entry.size] {
+            descriptor.extend(field.to_le_bytes());
+        }
+        self.write(&descriptor).await?;
+        self.entries.push(entry);
+        
+        Ok(())
+    }
+    
+    /// Writes the central directory and returns the underlying writer.
+    pub async fn finish(mut self) -> Result<W> {
+        let start = fits(self.offset)?;
+        let mut directory = Vec::new();
+        for entry in &self.entries {
+            directory.extend(CENTRAL_HEADER.to_le_bytes());
+            for field in [VERSION, VERSION, FLAGS, DEFLATE, entry.time, entry.date] {
+                directory.extend(field.to_le_bytes());
+            }
+            for field in [entry.crc, entry.compressed, entry.size] {
+                directory.extend(field.to_le_bytes());
+            }
+            // Name length, then no extra field, comment, disk number or attributes
+            directory.extend((entry.name.len() as u16).to_le_bytes());
+            directory.extend([0u8; 12]);
+            directory.extend(entry.offset.to_le_bytes());
+            directory.extend(entry.name.as_bytes());
+        }
+        
+        let count = self.entries.len() as u16;
+        let mut end = Vec::with_capacity(22);
+        end.extend(END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+        end.extend([0u8; 4]);
+        end.extend(count.to_le_bytes());
+        end.extend(count.to_le_bytes());
+        end.extend((directory.len() as u32).to_le_bytes());
+        end.extend(start.to_le_bytes());
+        end.extend(0u16.to_le_bytes());
+        
+        self.write(&directory).await?;
+        self.write(&end).await?;
+        self.out.flush().await?;
+        Ok(self.out)
+    }
+    
+    async fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.out.write_all(bytes).await?;
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+/// Archives `files`, given as archive name and path, in the background and returns
+/// the read end so the archive can be sent while it is still being written. A file
+/// that cannot be read ends the archive early.
+pub fn stream(files: Vec<(String, PathBuf)>) -> DuplexStream {
+    stream_with(Vec::new(), files)
+}
+
+/// Like [`stream`], with `entries` held in memory written ahead of the files.
+pub fn stream_with(entries: Vec<(String, Vec<u8>)>, files: Vec<(String, PathBuf)>) -> DuplexStream {
+    let (reader, writer) = tokio::io::duplex(CHUNK_SIZE);
+    
+    tokio::spawn(async move {
+        let mut zip = ZipWriter::new(writer);
+        for (name, data) in &entries {
+            if let Err(e) = zip.add_bytes(name, data).await {
+                warn!("Stopped zip download at {}: {}", name, e);
+                return;
+            }
+        }
+        for (name, path) in &files {
+            if let Err(e) = zip.add_file(name, path).await {
+                warn!("Stopped zip download at {}: {}", path.display(), e);
+                return;
+            }
+        }
+        if let Err(e) = zip.finish().await {
+            warn!("Failed to finish zip download: {}", e);
+        }
+    });
+    
+    reader
+}
+
+/// Reads every entry of an archive held in memory, as archive name and contents.
+/// Entries may be stored or deflated; ZIP64 and encrypted archives are not read,
+/// nor archives whose entries add up to more than `max_size` bytes.
+pub fn read_entries(archive: &[u8], max_size: u64) -> Result<Vec<(String, Vec<u8>)>> {
+    // The end record is 22 bytes followed by a comment of at most 65535
+    let search_from = archive.len().saturating_sub(22 + u16::MAX as usize);
+    let end = (search_from..archive.len().saturating_sub(21))
+        .rev()
+        .find(|&at| read_u32(archive, at).ok() == Some(END_OF_CENTRAL_DIRECTORY))
+        .ok_or_else(|| anyhow!("Not a zip archive"))?;
+    let count = read_u16(archive, end + 10)?;
+    let mut at = read_u32(archive, end + 16)? as usize;
+    
+    let mut entries = Vec::with_capacity(count as usize);
+    let mut total: u64 = 0;
+    for _ in 0..count {
+        if read_u32(archive, at)? != CENTRAL_HEADER {
+            return Err(anyhow!("Corrupt zip central directory"));
+        }
+        let flags = read_u16(archive, at + 8)?;
+        let method = read_u16(archive, at + 10)?;
+        let crc = read_u32(archive, at + 16)?;
+        let compressed = read_u32(archive, at + 20)? as usize;
+        let size = read_u32(archive, at + 24)? as usize;
+        let name_len = read_u16(archive, at + 28)? as usize;
+        let extra_len = read_u16(archive, at + 30)? as usize;
+        let comment_len = read_u16(archive, at + 32)? as usize;
+        let offset = read_u32(archive, at + 42)? as usize;
+        let name = String::from_utf8_lossy(slice(archive, at + 46, name_len)?).into_owned();
+        at += 46 + name_len + extra_len + comment_len;
+        
+        if flags & 1 != 0 {
+            return Err(anyhow!("{} is encrypted", name));
+        }
+        total += size as u64;
+        if total > max_size {
+            return Err(anyhow!("Archive inflates to more than {} MiB", max_size / (1024 * 1024)));
+        }
+        if read_u32(archive, offset)? != LOCAL_HEADER {
+            return Err(anyhow!("Corrupt zip entry {}", name));
+        }
+        let start = offset + 30 + read_u16(archive, offset + 26)? as usize + read_u16(archive, offset + 28)? as usize;
+        let raw = slice(archive, start, compressed)?;
+        
+        let data = match method {
+            STORED => raw.to_vec(),
+            DEFLATE => {
+                // Read one byte past the stated size so an entry that inflates to more is
+                // caught; the buffer grows with what inflates rather than the stated size
+                let mut data = Vec::new();
+                DeflateDecoder::new(raw).take(size as u64 + 1).read_to_end(&mut data)?;
+                data
+            }
+            other => return Err(anyhow!("{} uses unsupported compression method {}", name, other)),
+        };
+        if data.len() != size || crc32fast::hash(&data) != crc {
+            return Err(anyhow!("{} is corrupt", name));
+        }
+        entries.push((name, data));
+    }
+    
+    Ok(entries)
+}
+
+fn slice(archive: &[u8], at: usize, len: usize) -> Result<&[u8]> {
+    at.checked_add(len)
+        .and_then(|end| archive.get(at..end))
+        .ok_or_else(|| anyhow!("Truncated zip archive"))
+}
+
+fn read_u16(archive: &[u8], at: usize) -> Result<u16> {
+    Ok(u16::from_le_bytes(slice(archive, at, 2)?.try_into()?))
+}
+
+fn read_u32(archive: &[u8], at: usize) -> Result<u32> {
+    Ok(u32::from_le_bytes(slice(archive, at, 4)?.try_into()?))
+}
+
+fn fits(value: u64) -> Result<u32> {
+    u32::try_from(value).map_err(|_| anyhow!("Archive exceeds 4 GiB"))
+}
+
+/// Packs a local time into MS-DOS time and date fields, which start in 1980 and
+/// count seconds in pairs.
+fn dos_timestamp(at: DateTime<Local>) -> (u16, u16) {
+    let year = at.year().clamp(1980, 2107) as u32 - 1980;
+    let time = (at.hour() << 11) | (at.minute() << 5) | (at.second() / 2);
+    let date = (year << 9) | (at.month() << 5) | at.day();
+    (time as u16, date as u16)
+}
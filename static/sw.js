@@ -0,0 +1,34 @@
+// Service worker for ShaydZ Super Monitor: shows Web Push alerts and opens the
+// dashboard when one is tapped. Pages are never cached, so the UI is always live.
+
+self.addEventListener('install', () => self.skipWaiting());
+self.addEventListener('activate', (event) => event.waitUntil(self.clients.claim()));
+
+self.addEventListener('push', (event) => {
+    let data = {};
+    try {
+        data = event.data ? event.data.json() : {};
+    } catch (e) {
+        data = { body: event.data ? event.data.text() : '' };
+    }
+    event.waitUntil(self.registration.showNotification(data.title || 'ShaydZ Super Monitor', {
+        body: data.body || '',
+        icon: '/icon.svg',
+        badge: '/icon.svg',
+        tag: data.incident ? 'incident-' + data.incident : undefined,
+        requireInteraction: true,
+        data: { url: data.url || '/' },
+    }));
+});
+
+self.addEventListener('notificationclick', (event) => {
+    event.notification.close();
+    const url = new URL(event.notification.data.url, self.location.origin).href;
+    event.waitUntil(self.clients.matchAll({ type: 'window', includeUncontrolled: true }).then((windows) => {
+        const open = windows.find((client) => client.url === url);
+        if (open) {
+            return open.focus();
+        }
+        return self.clients.openWindow(url);
+    }));
+});